@@ -0,0 +1,1084 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use gtk::prelude::*;
+use gtk::{cairo, pango};
+use pdfium_render::prelude::PdfRect;
+
+use crate::backend::{DocumentBackend, PageChar, PageLink, RenderedPage};
+use crate::services::bookmarks::BookmarkEntry;
+
+/// Size of the pages an ebook is laid out on, in points (6 x 9 inches)
+pub const PAGE_WIDTH: f64 = 432.0;
+pub const PAGE_HEIGHT: f64 = 648.0;
+const MARGIN: f64 = 48.0;
+const CONTENT_WIDTH: f64 = PAGE_WIDTH - 2.0 * MARGIN;
+const CONTENT_HEIGHT: f64 = PAGE_HEIGHT - 2.0 * MARGIN;
+
+/// Headings down to this level make the table of contents
+const OUTLINE_MAX_LEVEL: u8 = 3;
+
+/// Metadata elements of the package document shown as the document's metadata
+const METADATA_FIELDS: [&str; 6] = [
+    "title",
+    "creator",
+    "subject",
+    "description",
+    "publisher",
+    "language",
+];
+
+/// Elements whose content is not part of the text
+const SKIPPED_ELEMENTS: [&str; 5] = ["head", "script", "style", "svg", "math"];
+
+/// Elements starting a new block of text
+const BLOCK_ELEMENTS: [&str; 24] = [
+    "p",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ul",
+    "ol",
+    "dl",
+    "dt",
+    "dd",
+    "blockquote",
+    "pre",
+    "section",
+    "article",
+    "aside",
+    "header",
+    "footer",
+    "figcaption",
+    "table",
+    "tr",
+];
+
+/// Error type for reading EPUB files
+#[derive(Debug)]
+pub enum EpubError {
+    IoError(String),
+    ArchiveError(String),
+    /// The archive lacks a part every EPUB has, or it can't be read
+    InvalidBook(String),
+}
+
+impl std::fmt::Display for EpubError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EpubError::IoError(msg) => write!(f, "IO error: {}", msg),
+            EpubError::ArchiveError(msg) => write!(f, "Archive error: {}", msg),
+            EpubError::InvalidBook(msg) => write!(f, "Invalid EPUB: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for EpubError {}
+
+impl From<std::io::Error> for EpubError {
+    fn from(err: std::io::Error) -> Self {
+        EpubError::IoError(err.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for EpubError {
+    fn from(err: zip::result::ZipError) -> Self {
+        EpubError::ArchiveError(err.to_string())
+    }
+}
+
+/// How a block of text is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    Paragraph,
+    /// `h1` to `h6`
+    Heading(u8),
+    ListItem,
+    Quote,
+    /// Text kept as written, line breaks and spaces included
+    Preformatted,
+}
+
+impl BlockKind {
+    fn font(self) -> pango::FontDescription {
+        pango::FontDescription::from_string(match self {
+            BlockKind::Heading(1) => "Serif Bold 20",
+            BlockKind::Heading(2) => "Serif Bold 16",
+            BlockKind::Heading(3) => "Serif Bold 13.5",
+            BlockKind::Heading(_) => "Serif Bold 11.5",
+            BlockKind::Preformatted => "Monospace 9",
+            BlockKind::Paragraph | BlockKind::ListItem | BlockKind::Quote => "Serif 11",
+        })
+    }
+
+    /// Space from the left margin, in points
+    fn indent(self) -> f64 {
+        match self {
+            BlockKind::ListItem | BlockKind::Quote => 18.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Space above the block when it doesn't start a page, in points
+    fn space_before(self) -> f64 {
+        match self {
+            BlockKind::Heading(_) => 18.0,
+            BlockKind::ListItem => 3.0,
+            _ => 8.0,
+        }
+    }
+}
+
+/// A paragraph, heading or other block of a chapter, with its text as displayed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub text: String,
+}
+
+/// The text of an EPUB in reading order
+#[derive(Debug, Default)]
+pub struct Book {
+    /// Values of the package metadata, as (field, value)
+    pub metadata: Vec<(String, String)>,
+    /// Blocks of each chapter of the spine
+    pub chapters: Vec<Vec<Block>>,
+}
+
+impl Book {
+    /// Read the book at `path`: the package document named by the container, then
+    /// every chapter of its spine
+    pub fn open(path: &Path) -> Result<Self, EpubError> {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+
+        let container = read_entry(&mut archive, "META-INF/container.xml")?;
+        let package_path = tokenize(&container)
+            .into_iter()
+            .find_map(|token| match token {
+                Token::Start {
+                    name, attributes, ..
+                } if name == "rootfile" => attribute(attributes, "full-path"),
+                _ => None,
+            })
+            .ok_or_else(|| EpubError::InvalidBook("No package document".to_string()))?;
+        let package_dir = package_path
+            .rsplit_once('/')
+            .map(|(dir, _)| dir)
+            .unwrap_or("");
+
+        let package = read_entry(&mut archive, &package_path)?;
+        let (metadata, spine) = parse_package(&package, package_dir);
+
+        let mut chapters = Vec::new();
+        for chapter_path in spine {
+            // A missing chapter leaves a gap rather than losing the whole book
+            match read_entry(&mut archive, &chapter_path) {
+                Ok(html) => chapters.push(html_blocks(&html)),
+                Err(e) => eprintln!("Skipping EPUB chapter {}: {}", chapter_path, e),
+            }
+        }
+
+        Ok(Self { metadata, chapters })
+    }
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> Result<String, EpubError> {
+    let mut entry = archive.by_name(name)?;
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// A piece of XML or XHTML markup
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    /// An opening tag, with its attributes unparsed
+    Start {
+        name: String,
+        attributes: &'a str,
+        self_closing: bool,
+    },
+    End {
+        name: String,
+    },
+    Text(&'a str),
+}
+
+/// Split markup into tags and text. Comments, doctypes and processing
+/// instructions are dropped; element names are lowercased, without namespace.
+fn tokenize(markup: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = markup;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>").unwrap_or(after.len());
+            tokens.push(Token::Text(&after[..end]));
+            rest = after.get(end + 3..).unwrap_or("");
+        } else {
+            let Some(end) = rest.find('>') else {
+                break;
+            };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            if tag.starts_with('!') || tag.starts_with('?') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                tokens.push(Token::End {
+                    name: local_name(name.trim()),
+                });
+            } else {
+                let self_closing = tag.ends_with('/');
+                let tag = tag.trim_end_matches('/');
+                let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+                tokens.push(Token::Start {
+                    name: local_name(name),
+                    attributes,
+                    self_closing,
+                });
+            }
+        }
+    }
+
+    tokens
+}
+
+/// `dc:Title` -> `title`
+fn local_name(name: &str) -> String {
+    name.rsplit(':').next().unwrap_or(name).to_lowercase()
+}
+
+/// Value of the attribute `name` (matched without namespace) in a tag's attributes
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    loop {
+        rest = rest.trim_start();
+        let equals = rest.find('=')?;
+        let key = rest[..equals].trim();
+        let value_start = rest[equals + 1..].trim_start();
+        let quote = value_start.chars().next()?;
+
+        let (value, after) = if quote == '"' || quote == '\'' {
+            let inner = &value_start[1..];
+            let end = inner.find(quote)?;
+            (&inner[..end], &inner[end + 1..])
+        } else {
+            let end = value_start
+                .find(char::is_whitespace)
+                .unwrap_or(value_start.len());
+            (&value_start[..end], &value_start[end..])
+        };
+
+        if local_name(key) == name {
+            return Some(decode_entities(value));
+        }
+        rest = after;
+    }
+}
+
+/// Replace character references and the common named entities of ebooks
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                "shy" => Some('\u{ad}'),
+                "ndash" => Some('–'),
+                "mdash" => Some('—'),
+                "hellip" => Some('…'),
+                "lsquo" => Some('‘'),
+                "rsquo" => Some('’'),
+                "ldquo" => Some('“'),
+                "rdquo" => Some('”'),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#')?.parse().ok())
+                    .and_then(char::from_u32),
+            };
+            decoded.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                // Soft hyphens only say where a word may break
+                if c != '\u{ad}' {
+                    output.push(c);
+                }
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Decode `%20`-style escapes of an href
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Path inside the archive of `href`, relative to the directory `base_dir`
+fn resolve_href(base_dir: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or(href);
+    let mut parts: Vec<&str> = base_dir.split('/').filter(|p| !p.is_empty()).collect();
+    let href = percent_decode(href);
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Metadata values and the chapter paths of the spine, in reading order, from
+/// the package document
+fn parse_package(package: &str, package_dir: &str) -> (Vec<(String, String)>, Vec<String>) {
+    let mut metadata = Vec::new();
+    let mut manifest: HashMap<String, String> = HashMap::new();
+    let mut spine = Vec::new();
+    let mut field: Option<(String, String)> = None;
+
+    for token in tokenize(package) {
+        match token {
+            Token::Start {
+                name, attributes, ..
+            } => match name.as_str() {
+                "item" => {
+                    if let (Some(id), Some(href)) =
+                        (attribute(attributes, "id"), attribute(attributes, "href"))
+                    {
+                        manifest.insert(id, resolve_href(package_dir, &href));
+                    }
+                }
+                // Non-linear items are footnotes and the like, reached by links only
+                "itemref" if attribute(attributes, "linear").as_deref() != Some("no") => {
+                    spine.extend(attribute(attributes, "idref"));
+                }
+                name if METADATA_FIELDS.contains(&name) => {
+                    field = Some((name.to_string(), String::new()));
+                }
+                _ => {}
+            },
+            Token::Text(text) => {
+                if let Some((_, value)) = field.as_mut() {
+                    value.push_str(&decode_entities(text));
+                }
+            }
+            Token::End { name } => {
+                if let Some((field_name, value)) = field.take_if(|(n, _)| *n == name) {
+                    let value = value.trim();
+                    if !value.is_empty() {
+                        metadata.push((field_name, value.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    let spine = spine
+        .into_iter()
+        .filter_map(|id| manifest.get(&id).cloned())
+        .collect();
+    (metadata, spine)
+}
+
+/// The blocks of text of a chapter's XHTML
+fn html_blocks(html: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut kinds: Vec<BlockKind> = Vec::new();
+    let mut text = String::new();
+    let mut skip_depth = 0usize;
+
+    let flush = |text: &mut String, kinds: &[BlockKind], blocks: &mut Vec<Block>| {
+        let trimmed = text.trim();
+        if !trimmed.is_empty() {
+            blocks.push(Block {
+                kind: kinds.last().copied().unwrap_or(BlockKind::Paragraph),
+                text: trimmed.to_string(),
+            });
+        }
+        text.clear();
+    };
+
+    for token in tokenize(html) {
+        match token {
+            Token::Start {
+                name, self_closing, ..
+            } => {
+                if SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    if !self_closing {
+                        skip_depth += 1;
+                    }
+                    continue;
+                }
+                if skip_depth > 0 {
+                    continue;
+                }
+                if name == "br" {
+                    text.push('\n');
+                } else if name == "hr" {
+                    flush(&mut text, &kinds, &mut blocks);
+                } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                    flush(&mut text, &kinds, &mut blocks);
+                    if !self_closing {
+                        let parent = kinds.last().copied();
+                        kinds.push(block_kind(&name, parent));
+                    }
+                }
+            }
+            Token::End { name } => {
+                if SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    skip_depth = skip_depth.saturating_sub(1);
+                } else if skip_depth == 0 && BLOCK_ELEMENTS.contains(&name.as_str()) {
+                    flush(&mut text, &kinds, &mut blocks);
+                    kinds.pop();
+                }
+            }
+            Token::Text(raw) if skip_depth == 0 => {
+                let decoded = decode_entities(raw);
+                if kinds.last() == Some(&BlockKind::Preformatted) {
+                    text.push_str(&decoded);
+                    continue;
+                }
+                // Runs of whitespace collapse into one space, like browsers do
+                for c in decoded.chars() {
+                    if c.is_ascii_whitespace() {
+                        if !text.is_empty() && !text.ends_with([' ', '\n']) {
+                            text.push(' ');
+                        }
+                    } else {
+                        text.push(c);
+                    }
+                }
+            }
+            Token::Text(_) => {}
+        }
+    }
+    flush(&mut text, &kinds, &mut blocks);
+
+    blocks
+}
+
+/// Kind of the block an element starts. Paragraphs inside a list item or quote
+/// keep its kind.
+fn block_kind(name: &str, parent: Option<BlockKind>) -> BlockKind {
+    match name {
+        "h1" => BlockKind::Heading(1),
+        "h2" => BlockKind::Heading(2),
+        "h3" => BlockKind::Heading(3),
+        "h4" => BlockKind::Heading(4),
+        "h5" => BlockKind::Heading(5),
+        "h6" => BlockKind::Heading(6),
+        "li" => BlockKind::ListItem,
+        "blockquote" => BlockKind::Quote,
+        "pre" => BlockKind::Preformatted,
+        _ => match parent {
+            Some(kind @ (BlockKind::ListItem | BlockKind::Quote | BlockKind::Preformatted)) => kind,
+            _ => BlockKind::Paragraph,
+        },
+    }
+}
+
+/// Lines of a laid out block, as (top, bottom) in points from the top of the block
+struct BlockLines {
+    lines: Vec<(f64, f64)>,
+    space_before: f64,
+    /// Chapters start on a new page
+    new_page: bool,
+}
+
+/// The part of a block shown on a page, in points
+#[derive(Debug, Clone, PartialEq)]
+struct Fragment {
+    block: usize,
+    /// Lines shown, from the top of the block
+    layout_top: f64,
+    layout_bottom: f64,
+    /// Where the first of them goes, from the top of the page's text area
+    page_top: f64,
+}
+
+/// Distribute blocks over pages of `content_height`, breaking them between
+/// lines. Pages hold at least one line, even one taller than the page.
+fn paginate(blocks: &[BlockLines], content_height: f64) -> Vec<Vec<Fragment>> {
+    let mut pages: Vec<Vec<Fragment>> = vec![Vec::new()];
+    let mut y = 0.0;
+
+    for (index, block) in blocks.iter().enumerate() {
+        if block.new_page && pages.last().is_some_and(|page| !page.is_empty()) {
+            pages.push(Vec::new());
+            y = 0.0;
+        }
+        if y > 0.0 {
+            y += block.space_before;
+        }
+
+        let mut fragment: Option<Fragment> = None;
+        for &(top, bottom) in &block.lines {
+            if y > 0.0 && y + (bottom - top) > content_height {
+                pages.last_mut().unwrap().extend(fragment.take());
+                pages.push(Vec::new());
+                y = 0.0;
+            }
+            match fragment.as_mut() {
+                Some(fragment) => fragment.layout_bottom = bottom,
+                None => {
+                    fragment = Some(Fragment {
+                        block: index,
+                        layout_top: top,
+                        layout_bottom: bottom,
+                        page_top: y,
+                    })
+                }
+            }
+            y += bottom - top;
+        }
+        pages.last_mut().unwrap().extend(fragment);
+    }
+
+    pages
+}
+
+/// Nest headings, as (level, title, page index), into a table of contents
+fn outline_entries(headings: &[(u8, String, usize)]) -> Vec<BookmarkEntry> {
+    let mut entries: Vec<BookmarkEntry> = Vec::new();
+    // Levels of the entries new ones may nest under, the deepest last
+    let mut open_levels: Vec<u8> = Vec::new();
+
+    for (level, title, page_index) in headings {
+        while open_levels.last().is_some_and(|open| open >= level) {
+            open_levels.pop();
+        }
+
+        // Each open level is the last entry at its depth, so the parents exist
+        let depth = open_levels.len();
+        let mut siblings = &mut entries;
+        for _ in 0..depth {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+        siblings.push(BookmarkEntry {
+            title: title.clone(),
+            page_index: u16::try_from(*page_index).unwrap_or(u16::MAX),
+            children: Vec::new(),
+            depth,
+        });
+        open_levels.push(*level);
+    }
+
+    entries
+}
+
+/// EPUB ebooks, laid out with pango on pages of a fixed size. The text reflows
+/// into pages once when the book is opened, so every page keeps its words at
+/// any zoom level like a PDF page does.
+pub struct EpubBackend {
+    metadata: Vec<(String, String)>,
+    blocks: Vec<Block>,
+    layouts: Vec<pango::Layout>,
+    pages: Vec<Vec<Fragment>>,
+    outline: Vec<BookmarkEntry>,
+}
+
+impl EpubBackend {
+    /// Whether `path` is opened with this backend rather than as a PDF
+    pub fn handles(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("epub"))
+    }
+
+    pub fn open(path: &Path) -> Result<Self, EpubError> {
+        let book = Book::open(path)?;
+
+        let font_map = pangocairo::FontMap::new();
+        let context = font_map.create_context();
+        // One pango point per page point, with metrics that don't depend on the
+        // zoom level the page is later drawn at
+        pangocairo::functions::context_set_resolution(&context, 72.0);
+        let mut font_options = cairo::FontOptions::new()
+            .map_err(|e| EpubError::InvalidBook(format!("Could not set up fonts: {}", e)))?;
+        font_options.set_hint_metrics(cairo::HintMetrics::Off);
+        pangocairo::functions::context_set_font_options(&context, Some(&font_options));
+
+        let mut blocks = Vec::new();
+        let mut block_lines = Vec::new();
+        for chapter in book.chapters {
+            for (i, block) in chapter.into_iter().enumerate() {
+                let layout = pango::Layout::new(&context);
+                layout.set_font_description(Some(&block.kind.font()));
+                layout.set_width(
+                    ((CONTENT_WIDTH - block.kind.indent()) * pango::SCALE as f64) as i32,
+                );
+                layout.set_wrap(pango::WrapMode::WordChar);
+                layout.set_text(&block.text);
+
+                block_lines.push(BlockLines {
+                    lines: Self::line_ranges(&layout),
+                    space_before: block.kind.space_before(),
+                    new_page: i == 0,
+                });
+                blocks.push((block, layout));
+            }
+        }
+        let pages = paginate(&block_lines, CONTENT_HEIGHT);
+
+        let mut headings = Vec::new();
+        for (page_index, page) in pages.iter().enumerate() {
+            for fragment in page {
+                let (block, _) = &blocks[fragment.block];
+                match block.kind {
+                    BlockKind::Heading(level)
+                        if level <= OUTLINE_MAX_LEVEL && fragment.layout_top == 0.0 =>
+                    {
+                        headings.push((level, block.text.replace('\n', " "), page_index));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let (blocks, layouts) = blocks.into_iter().unzip();
+        Ok(Self {
+            metadata: book.metadata,
+            blocks,
+            layouts,
+            pages,
+            outline: outline_entries(&headings),
+        })
+    }
+
+    /// (top, bottom) of each line of a layout, in points
+    fn line_ranges(layout: &pango::Layout) -> Vec<(f64, f64)> {
+        let scale = pango::SCALE as f64;
+        let mut lines = Vec::new();
+        let mut iter = layout.iter();
+        loop {
+            let (top, bottom) = iter.line_yrange();
+            lines.push((top as f64 / scale, bottom as f64 / scale));
+            if !iter.next_line() {
+                break;
+            }
+        }
+        lines
+    }
+
+    /// Where a fragment's lines start on the page, from the top-left corner
+    fn fragment_origin(&self, fragment: &Fragment) -> (f64, f64) {
+        (
+            MARGIN + self.blocks[fragment.block].kind.indent(),
+            MARGIN + fragment.page_top,
+        )
+    }
+}
+
+impl DocumentBackend for EpubBackend {
+    fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn page_size(&self, page_index: usize) -> Option<(f64, f64)> {
+        (page_index < self.pages.len()).then_some((PAGE_WIDTH, PAGE_HEIGHT))
+    }
+
+    fn render_page(&self, page_index: usize, width: i32) -> Option<RenderedPage> {
+        let page = self.pages.get(page_index)?;
+        let scale = width as f64 / PAGE_WIDTH;
+        let height = (PAGE_HEIGHT * scale).round() as i32;
+        let mut surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height).ok()?;
+
+        {
+            let cr = cairo::Context::new(&surface).ok()?;
+            cr.set_source_rgb(1.0, 1.0, 1.0);
+            cr.paint().ok()?;
+            cr.scale(scale, scale);
+            cr.set_source_rgb(0.0, 0.0, 0.0);
+
+            for fragment in page {
+                let block = &self.blocks[fragment.block];
+                let (x, y) = self.fragment_origin(fragment);
+                let height = fragment.layout_bottom - fragment.layout_top;
+
+                cr.save().ok()?;
+                match block.kind {
+                    BlockKind::Quote => {
+                        cr.set_source_rgb(0.6, 0.6, 0.6);
+                        cr.rectangle(MARGIN + 4.0, y, 2.0, height);
+                        cr.fill().ok()?;
+                        cr.set_source_rgb(0.0, 0.0, 0.0);
+                    }
+                    BlockKind::ListItem if fragment.layout_top == 0.0 => {
+                        let line_height = self.layouts[fragment.block].iter().line_yrange().1
+                            as f64
+                            / pango::SCALE as f64;
+                        cr.arc(
+                            MARGIN + 7.0,
+                            y + line_height / 2.0,
+                            2.0,
+                            0.0,
+                            std::f64::consts::TAU,
+                        );
+                        cr.fill().ok()?;
+                    }
+                    _ => {}
+                }
+                cr.rectangle(0.0, y, PAGE_WIDTH, height);
+                cr.clip();
+                cr.translate(x, y - fragment.layout_top);
+                pangocairo::functions::show_layout(&cr, &self.layouts[fragment.block]);
+                cr.restore().ok()?;
+            }
+        }
+
+        surface.flush();
+        let stride = surface.stride() as usize;
+        let pixels = surface.data().ok()?.to_vec();
+        Some(RenderedPage {
+            width,
+            height,
+            stride,
+            pixels,
+        })
+    }
+
+    fn page_chars(&self, page_index: usize) -> Option<Vec<PageChar>> {
+        let page = self.pages.get(page_index)?;
+        let scale = pango::SCALE as f64;
+        let mut chars = Vec::new();
+
+        for fragment in page {
+            let (x, y) = self.fragment_origin(fragment);
+            let layout = &self.layouts[fragment.block];
+
+            for (byte_index, c) in self.blocks[fragment.block].text.char_indices() {
+                let pos = layout.index_to_pos(byte_index as i32);
+                let top = pos.y() as f64 / scale;
+                let height = pos.height() as f64 / scale;
+                let center = top + height / 2.0;
+                if center < fragment.layout_top || center >= fragment.layout_bottom {
+                    continue;
+                }
+
+                // Right-to-left glyphs have a negative width
+                let left = x + (pos.x().min(pos.x() + pos.width())) as f64 / scale;
+                let right = left + pos.width().abs() as f64 / scale;
+                let page_top = y + top - fragment.layout_top;
+                chars.push(PageChar {
+                    char: c,
+                    index: chars.len(),
+                    bounds: (c != '\n').then(|| {
+                        PdfRect::new_from_values(
+                            (PAGE_HEIGHT - page_top - height) as f32,
+                            left as f32,
+                            (PAGE_HEIGHT - page_top) as f32,
+                            right as f32,
+                        )
+                    }),
+                });
+            }
+
+            // Keep the last word of a block apart from the first of the next
+            chars.push(PageChar {
+                char: '\n',
+                index: chars.len(),
+                bounds: None,
+            });
+        }
+
+        Some(chars)
+    }
+
+    fn title(&self) -> Option<String> {
+        self.metadata
+            .iter()
+            .find(|(field, _)| field == "title")
+            .map(|(_, value)| value.clone())
+    }
+
+    fn metadata(&self) -> Vec<String> {
+        self.metadata
+            .iter()
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn outline(&self) -> Vec<BookmarkEntry> {
+        self.outline.clone()
+    }
+
+    fn links(&self, _page_index: usize) -> Vec<PageLink> {
+        Vec::new()
+    }
+
+    fn page_label(&self, _page_index: usize) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(kind: BlockKind, text: &str) -> Block {
+        Block {
+            kind,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize(
+            "<?xml version=\"1.0\"?><!-- note --><dc:Title id=\"t\">A &amp; B</dc:Title><br/>",
+        );
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Start {
+                    name: "title".to_string(),
+                    attributes: "id=\"t\"",
+                    self_closing: false,
+                },
+                Token::Text("A &amp; B"),
+                Token::End {
+                    name: "title".to_string()
+                },
+                Token::Start {
+                    name: "br".to_string(),
+                    attributes: "",
+                    self_closing: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attribute() {
+        let attributes = "id='ch1' href=\"text/ch%201.xhtml\" media-type=application/xhtml+xml";
+        assert_eq!(attribute(attributes, "id"), Some("ch1".to_string()));
+        assert_eq!(
+            attribute(attributes, "href"),
+            Some("text/ch%201.xhtml".to_string())
+        );
+        assert_eq!(
+            attribute(attributes, "media-type"),
+            Some("application/xhtml+xml".to_string())
+        );
+        assert_eq!(attribute(attributes, "idref"), None);
+        assert_eq!(
+            attribute("opf:full-path=\"OEBPS/content.opf\"", "full-path"),
+            Some("OEBPS/content.opf".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("Tom &amp; Jerry &#8212; &#x41;&hellip;"),
+            "Tom & Jerry — A…"
+        );
+        assert_eq!(decode_entities("a & b &unknown;"), "a & b &unknown;");
+        assert_eq!(decode_entities("hy&shy;phen"), "hyphen");
+    }
+
+    #[test]
+    fn test_resolve_href() {
+        assert_eq!(
+            resolve_href("OEBPS", "text/ch1.xhtml"),
+            "OEBPS/text/ch1.xhtml"
+        );
+        assert_eq!(
+            resolve_href("OEBPS/text", "../ch%202.xhtml#top"),
+            "OEBPS/ch 2.xhtml"
+        );
+        assert_eq!(resolve_href("", "./ch1.xhtml"), "ch1.xhtml");
+    }
+
+    #[test]
+    fn test_parse_package() {
+        let package = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:title>Moby Dick</dc:title>
+                <dc:creator opf:role="aut">Herman Melville</dc:creator>
+                <meta name="cover" content="cover-image"/>
+              </metadata>
+              <manifest>
+                <item id="c1" href="text/c1.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c2" href="text/c2.xhtml" media-type="application/xhtml+xml"/>
+                <item id="notes" href="text/notes.xhtml" media-type="application/xhtml+xml"/>
+              </manifest>
+              <spine toc="ncx">
+                <itemref idref="c2"/>
+                <itemref idref="notes" linear="no"/>
+                <itemref idref="c1"/>
+                <itemref idref="missing"/>
+              </spine>
+            </package>"#;
+
+        let (metadata, spine) = parse_package(package, "OEBPS");
+        assert_eq!(
+            metadata,
+            vec![
+                ("title".to_string(), "Moby Dick".to_string()),
+                ("creator".to_string(), "Herman Melville".to_string()),
+            ]
+        );
+        assert_eq!(spine, vec!["OEBPS/text/c2.xhtml", "OEBPS/text/c1.xhtml"]);
+    }
+
+    #[test]
+    fn test_html_blocks() {
+        let html = r#"<html><head><title>Ignored</title><style>p { }</style></head>
+            <body>
+              <h1>Chapter
+                One</h1>
+              <p>Call me <i>Ishmael</i>.  Some years ago&#8212;never mind</p>
+              <blockquote><p>A quoted line</p></blockquote>
+              <ul><li>First</li><li>Second<br/>line</li></ul>
+              <pre>  let x = 1;
+  x</pre>
+              Trailing text
+            </body></html>"#;
+
+        assert_eq!(
+            html_blocks(html),
+            vec![
+                block(BlockKind::Heading(1), "Chapter One"),
+                block(
+                    BlockKind::Paragraph,
+                    "Call me Ishmael. Some years ago—never mind"
+                ),
+                block(BlockKind::Quote, "A quoted line"),
+                block(BlockKind::ListItem, "First"),
+                block(BlockKind::ListItem, "Second\nline"),
+                block(BlockKind::Preformatted, "let x = 1;\n  x"),
+                block(BlockKind::Paragraph, "Trailing text"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paginate() {
+        let lines = |count: usize, new_page: bool| BlockLines {
+            lines: (0..count)
+                .map(|i| (i as f64 * 10.0, (i + 1) as f64 * 10.0))
+                .collect(),
+            space_before: 5.0,
+            new_page,
+        };
+        let blocks = [lines(3, true), lines(4, false), lines(1, true)];
+
+        let fragment = |block, layout_top, layout_bottom, page_top| Fragment {
+            block,
+            layout_top,
+            layout_bottom,
+            page_top,
+        };
+        assert_eq!(
+            paginate(&blocks, 60.0),
+            vec![
+                // 30 points of the first block, 5 of space, then two lines fit
+                vec![fragment(0, 0.0, 30.0, 0.0), fragment(1, 0.0, 20.0, 35.0)],
+                vec![fragment(1, 20.0, 40.0, 0.0)],
+                // A new chapter starts a page
+                vec![fragment(2, 0.0, 10.0, 0.0)],
+            ]
+        );
+
+        // A line taller than the page still gets one
+        let tall = [BlockLines {
+            lines: vec![(0.0, 100.0)],
+            space_before: 0.0,
+            new_page: true,
+        }];
+        assert_eq!(
+            paginate(&tall, 60.0),
+            vec![vec![fragment(0, 0.0, 100.0, 0.0)]]
+        );
+    }
+
+    #[test]
+    fn test_outline_entries() {
+        let headings = [
+            (1, "Part One".to_string(), 0),
+            (2, "Chapter 1".to_string(), 1),
+            (3, "Section".to_string(), 2),
+            (2, "Chapter 2".to_string(), 5),
+            (1, "Part Two".to_string(), 9),
+        ];
+
+        let outline = outline_entries(&headings);
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].title, "Part One");
+        assert_eq!(outline[0].children.len(), 2);
+        assert_eq!(outline[0].children[0].depth, 1);
+        assert_eq!(outline[0].children[0].children[0].title, "Section");
+        assert_eq!(outline[0].children[0].children[0].depth, 2);
+        assert_eq!(outline[0].children[1].page_index, 5);
+        assert_eq!(outline[1].title, "Part Two");
+        assert_eq!(outline[1].depth, 0);
+    }
+}
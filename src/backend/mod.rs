@@ -0,0 +1,101 @@
+pub mod epub;
+pub mod ocr_layer;
+pub mod pdfium;
+pub mod render_worker;
+
+use std::path::Path;
+
+use pdfium_render::prelude::{PdfRect, Pdfium};
+
+use crate::services::bookmarks::BookmarkEntry;
+
+pub use self::epub::EpubBackend;
+pub use self::ocr_layer::{OcrLayer, RecognizedPages};
+pub use self::pdfium::PdfiumBackend;
+pub use self::render_worker::{RenderResult, RenderWorker};
+
+/// A single character extracted from a page, in page points with the origin at the
+/// bottom-left corner of the visible page area, as displayed after the page's /Rotate
+#[derive(Debug, Clone)]
+pub struct PageChar {
+    pub char: char,
+    /// Index of the character in the page's text, as used for click lookups
+    pub index: usize,
+    /// Tight bounds of the glyph. Generated spaces and line breaks usually have none.
+    pub bounds: Option<PdfRect>,
+}
+
+/// Pixels of a rendered page in B8G8R8A8 order
+pub struct RenderedPage {
+    pub width: i32,
+    pub height: i32,
+    pub stride: usize,
+    pub pixels: Vec<u8>,
+}
+
+/// Where a link on a page points to
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    /// A page inside the same document
+    Page { page_index: usize },
+    /// An external URI
+    Uri(String),
+}
+
+/// A clickable area on a page
+#[derive(Debug, Clone)]
+pub struct PageLink {
+    /// Area of the link in page points, same coordinate space as `PageChar::bounds`
+    pub bounds: PdfRect,
+    pub target: LinkTarget,
+}
+
+/// Everything the viewer needs from an open document. PdfView, the text map and the
+/// navigation code only talk to this trait, so other formats (EPUB, DjVu, plain images)
+/// can be supported by adding a backend instead of touching them.
+pub trait DocumentBackend {
+    /// Number of pages in the document
+    fn page_count(&self) -> usize;
+
+    /// Page size in points as (width, height), as displayed after the page's /Rotate
+    fn page_size(&self, page_index: usize) -> Option<(f64, f64)>;
+
+    /// Render a page upright scaled to the given pixel width, keeping its aspect ratio
+    fn render_page(&self, page_index: usize, width: i32) -> Option<RenderedPage>;
+
+    /// All characters of a page in content order
+    fn page_chars(&self, page_index: usize) -> Option<Vec<PageChar>>;
+
+    /// Title from the document metadata, if it has one
+    fn title(&self) -> Option<String>;
+
+    /// All metadata values of the document (title, author, subject, keywords...)
+    fn metadata(&self) -> Vec<String>;
+
+    /// Table of contents of the document
+    fn outline(&self) -> Vec<BookmarkEntry>;
+
+    /// Links on a page
+    fn links(&self, page_index: usize) -> Vec<PageLink>;
+
+    /// Page number printed on a page, from the document's page labels ("iv", "12")
+    fn page_label(&self, page_index: usize) -> Option<String>;
+}
+
+/// Open the document at `path` with the backend for its format: EPUB for `.epub`
+/// files, pdfium for everything else
+pub fn open_document(
+    pdfium: &'static Pdfium,
+    path: &Path,
+) -> Result<Box<dyn DocumentBackend>, String> {
+    if EpubBackend::handles(path) {
+        let document =
+            EpubBackend::open(path).map_err(|e| format!("Failed to open EPUB: {}", e))?;
+        return Ok(Box::new(document));
+    }
+
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|e| format!("Failed to open PDF: {}", e))?;
+    Ok(Box::new(PdfiumBackend::new(document)))
+}
@@ -0,0 +1,68 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::backend::{DocumentBackend, PageChar, PageLink, RenderedPage};
+use crate::services::bookmarks::BookmarkEntry;
+
+/// Characters recognized by OCR on scanned pages, by page index. Shared between
+/// the view, which fills it, and the `OcrLayer` reading from it.
+pub type RecognizedPages = Rc<RefCell<HashMap<usize, Vec<PageChar>>>>;
+
+/// A document whose scanned pages read as text once recognized: their OCR
+/// characters stand in for the missing text layer, everything else is the
+/// wrapped document's
+pub struct OcrLayer {
+    document: Box<dyn DocumentBackend>,
+    recognized: RecognizedPages,
+}
+
+impl OcrLayer {
+    pub fn new(document: Box<dyn DocumentBackend>, recognized: RecognizedPages) -> Self {
+        Self {
+            document,
+            recognized,
+        }
+    }
+}
+
+impl DocumentBackend for OcrLayer {
+    fn page_count(&self) -> usize {
+        self.document.page_count()
+    }
+
+    fn page_size(&self, page_index: usize) -> Option<(f64, f64)> {
+        self.document.page_size(page_index)
+    }
+
+    fn render_page(&self, page_index: usize, width: i32) -> Option<RenderedPage> {
+        self.document.render_page(page_index, width)
+    }
+
+    fn page_chars(&self, page_index: usize) -> Option<Vec<PageChar>> {
+        if let Some(chars) = self.recognized.borrow().get(&page_index) {
+            return Some(chars.clone());
+        }
+        self.document.page_chars(page_index)
+    }
+
+    fn title(&self) -> Option<String> {
+        self.document.title()
+    }
+
+    fn metadata(&self) -> Vec<String> {
+        self.document.metadata()
+    }
+
+    fn outline(&self) -> Vec<BookmarkEntry> {
+        self.document.outline()
+    }
+
+    fn links(&self, page_index: usize) -> Vec<PageLink> {
+        self.document.links(page_index)
+    }
+
+    fn page_label(&self, page_index: usize) -> Option<String> {
+        self.document.page_label(page_index)
+    }
+}
@@ -0,0 +1,216 @@
+use pdfium_render::prelude::*;
+
+use crate::backend::{DocumentBackend, LinkTarget, PageChar, PageLink, RenderedPage};
+use crate::services::bookmarks::{self, BookmarkEntry};
+use crate::services::rotation::Rotation;
+
+/// PDF documents opened through pdfium
+pub struct PdfiumBackend {
+    document: PdfDocument<'static>,
+}
+
+impl PdfiumBackend {
+    pub fn new(document: PdfDocument<'static>) -> Self {
+        Self { document }
+    }
+
+    fn page(&self, page_index: usize) -> Option<PdfPage<'_>> {
+        let page_index = u16::try_from(page_index).ok()?;
+        self.document.pages().get(page_index).ok()
+    }
+
+    /// The visible area of the page. Char and link bounds are reported relative to the
+    /// media box, so they are shifted by this box's origin.
+    fn crop_box(page: &PdfPage) -> PdfRect {
+        let boundaries = page.boundaries();
+        boundaries
+            .crop()
+            .map(|b| b.bounds)
+            .or_else(|_| boundaries.media().map(|b| b.bounds))
+            .or_else(|_| boundaries.trim().map(|b| b.bounds))
+            .or_else(|_| boundaries.bleed().map(|b| b.bounds))
+            .or_else(|_| boundaries.art().map(|b| b.bounds))
+            .unwrap_or_else(|_| PdfRect::new_from_values(0.0, 0.0, 0.0, 0.0))
+    }
+
+    /// Rotation the page is displayed with, from its /Rotate entry. pdfium applies it
+    /// to the page size and when rendering, but not to char and link bounds.
+    fn page_rotation(page: &PdfPage) -> Rotation {
+        let quarter_turns = match page.rotation() {
+            Ok(PdfPageRenderRotation::Degrees90) => 1,
+            Ok(PdfPageRenderRotation::Degrees180) => 2,
+            Ok(PdfPageRenderRotation::Degrees270) => 3,
+            _ => 0,
+        };
+        Rotation::NONE.rotated(quarter_turns)
+    }
+
+    /// Bounds relative to the crop box, turned like the page is displayed
+    fn relative_to(bounds: PdfRect, crop_box: &PdfRect, rotation: Rotation) -> PdfRect {
+        let left = (bounds.left().value - crop_box.left().value) as f64;
+        let bottom = (bounds.bottom().value - crop_box.bottom().value) as f64;
+        let right = (bounds.right().value - crop_box.left().value) as f64;
+        let top = (bounds.top().value - crop_box.bottom().value) as f64;
+        let size = (
+            (crop_box.right().value - crop_box.left().value) as f64,
+            (crop_box.top().value - crop_box.bottom().value) as f64,
+        );
+
+        let (left, bottom, right, top) =
+            rotation.pdf_rect_to_rotated((left, bottom, right, top), size);
+        PdfRect::new_from_values(bottom as f32, left as f32, top as f32, right as f32)
+    }
+
+    /// Bounds given like `relative_to` returns them, back in the coordinates of the
+    /// page as stored in the file
+    fn absolute_from(bounds: PdfRect, crop_box: &PdfRect, rotation: Rotation) -> PdfRect {
+        let size = (
+            (crop_box.right().value - crop_box.left().value) as f64,
+            (crop_box.top().value - crop_box.bottom().value) as f64,
+        );
+        let (left, bottom, right, top) = rotation.inverse().pdf_rect_to_rotated(
+            (
+                bounds.left().value as f64,
+                bounds.bottom().value as f64,
+                bounds.right().value as f64,
+                bounds.top().value as f64,
+            ),
+            rotation.size(size),
+        );
+
+        let (x, y) = (crop_box.left().value as f64, crop_box.bottom().value as f64);
+        PdfRect::new_from_values(
+            (bottom + y) as f32,
+            (left + x) as f32,
+            (top + y) as f32,
+            (right + x) as f32,
+        )
+    }
+
+    /// Rects in the space of `PageChar` bounds, in the coordinates of `page` as
+    /// stored in the file, where annotations written into it are placed
+    pub fn to_page_space(page: &PdfPage, rects: &[PdfRect]) -> Vec<PdfRect> {
+        let crop_box = Self::crop_box(page);
+        let rotation = Self::page_rotation(page);
+        rects
+            .iter()
+            .map(|rect| Self::absolute_from(*rect, &crop_box, rotation))
+            .collect()
+    }
+
+    fn link_target(link: &PdfLink) -> Option<LinkTarget> {
+        if let Some(dest) = link.destination() {
+            let page_index = dest.page_index().ok()?;
+            return Some(LinkTarget::Page {
+                page_index: page_index as usize,
+            });
+        }
+
+        match link.action()? {
+            PdfAction::LocalDestination(action) => {
+                let page_index = action.destination().ok()?.page_index().ok()?;
+                Some(LinkTarget::Page {
+                    page_index: page_index as usize,
+                })
+            }
+            PdfAction::Uri(action) => action.uri().ok().map(LinkTarget::Uri),
+            _ => None,
+        }
+    }
+}
+
+impl DocumentBackend for PdfiumBackend {
+    fn page_count(&self) -> usize {
+        self.document.pages().len() as usize
+    }
+
+    fn page_size(&self, page_index: usize) -> Option<(f64, f64)> {
+        let page = self.page(page_index)?;
+        Some((page.width().value as f64, page.height().value as f64))
+    }
+
+    fn render_page(&self, page_index: usize, width: i32) -> Option<RenderedPage> {
+        let page = self.page(page_index)?;
+        let config = PdfRenderConfig::new()
+            .set_target_width(width)
+            .set_format(PdfBitmapFormat::BGRA);
+        let bitmap = page.render_with_config(&config).ok()?;
+
+        let width = bitmap.width();
+        Some(RenderedPage {
+            width,
+            height: bitmap.height(),
+            stride: (width * 4) as usize,
+            pixels: bitmap.as_raw_bytes(),
+        })
+    }
+
+    fn page_chars(&self, page_index: usize) -> Option<Vec<PageChar>> {
+        let page = self.page(page_index)?;
+        let text_page = page.text().ok()?;
+        let crop_box = Self::crop_box(&page);
+        let rotation = Self::page_rotation(&page);
+
+        let chars = text_page
+            .chars()
+            .iter()
+            .filter_map(|char_obj| {
+                Some(PageChar {
+                    char: char_obj.unicode_char()?,
+                    index: char_obj.index(),
+                    bounds: char_obj
+                        .tight_bounds()
+                        .ok()
+                        .map(|bounds| Self::relative_to(bounds, &crop_box, rotation)),
+                })
+            })
+            .collect();
+
+        Some(chars)
+    }
+
+    fn title(&self) -> Option<String> {
+        self.document
+            .metadata()
+            .get(PdfDocumentMetadataTagType::Title)
+            .map(|tag| tag.value().trim().to_string())
+            .filter(|title| !title.is_empty())
+    }
+
+    fn metadata(&self) -> Vec<String> {
+        self.document
+            .metadata()
+            .iter()
+            .map(|tag| tag.value().to_string())
+            .collect()
+    }
+
+    fn outline(&self) -> Vec<BookmarkEntry> {
+        bookmarks::extract_bookmarks(&self.document)
+    }
+
+    fn page_label(&self, page_index: usize) -> Option<String> {
+        self.page(page_index)?
+            .label()
+            .map(|label| label.to_string())
+    }
+
+    fn links(&self, page_index: usize) -> Vec<PageLink> {
+        let Some(page) = self.page(page_index) else {
+            return Vec::new();
+        };
+        let crop_box = Self::crop_box(&page);
+        let rotation = Self::page_rotation(&page);
+
+        page.links()
+            .iter()
+            .filter_map(|link| {
+                let bounds = link.rect().ok()?;
+                Some(PageLink {
+                    bounds: Self::relative_to(bounds, &crop_box, rotation),
+                    target: Self::link_target(&link)?,
+                })
+            })
+            .collect()
+    }
+}
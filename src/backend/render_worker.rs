@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use super::{DocumentBackend, RenderedPage};
+
+/// Opens the worker's own copy of the document. Backends are not Send, so the
+/// worker cannot share the one the view reads text and sizes from.
+pub type DocumentOpener = Box<dyn FnOnce() -> Option<Box<dyn DocumentBackend>> + Send>;
+
+struct RenderRequest {
+    generation: u64,
+    page_index: usize,
+    width: i32,
+}
+
+/// Outcome of a render request
+pub struct RenderResult {
+    pub page_index: usize,
+    /// Pixel width the page was requested at
+    pub width: i32,
+    /// None when the backend failed to render the page
+    pub page: Option<RenderedPage>,
+}
+
+/// Renders pages on a background thread so the main loop keeps running while
+/// large pages are rasterized. Finished pages are received from `take_results`.
+pub struct RenderWorker {
+    requests: Sender<RenderRequest>,
+    /// None while taken to wait for finished pages
+    results: Option<Receiver<RenderResult>>,
+    /// Requests queued before the current generation are skipped
+    generation: Arc<AtomicU64>,
+}
+
+impl RenderWorker {
+    /// Start the worker thread. It stops once the worker is dropped.
+    pub fn spawn(open: DocumentOpener) -> Self {
+        let (request_sender, request_receiver) = mpsc::channel::<RenderRequest>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let generation = Arc::new(AtomicU64::new(0));
+        let current_generation = generation.clone();
+
+        std::thread::spawn(move || {
+            let Some(document) = open() else {
+                eprintln!("Render worker could not open the document");
+                return;
+            };
+
+            for request in request_receiver {
+                if request.generation < current_generation.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let result = RenderResult {
+                    page_index: request.page_index,
+                    width: request.width,
+                    page: document.render_page(request.page_index, request.width),
+                };
+                if result_sender.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests: request_sender,
+            results: Some(result_receiver),
+            generation,
+        }
+    }
+
+    /// Queue a page to be rendered at the given pixel width
+    pub fn request(&self, page_index: usize, width: i32) {
+        let _ = self.requests.send(RenderRequest {
+            generation: self.generation.load(Ordering::Relaxed),
+            page_index,
+            width,
+        });
+    }
+
+    /// Skip every request not started yet, e.g. after a zoom change
+    pub fn cancel_pending(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Receiver of the finished pages, unless already taken. It disconnects once
+    /// the worker thread stopped, most likely because it could not open the document.
+    pub fn take_results(&mut self) -> Option<Receiver<RenderResult>> {
+        self.results.take()
+    }
+
+    /// Put back the receiver taken with `take_results`, while no page is expected
+    pub fn return_results(&mut self, results: Receiver<RenderResult>) {
+        self.results = Some(results);
+    }
+}
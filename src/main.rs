@@ -1,3 +1,4 @@
+mod backend;
 mod modes;
 mod objects;
 mod services;
@@ -6,6 +7,9 @@ mod widgets;
 
 use gtk::prelude::*;
 use gtk::{Application, CssProvider, gdk, gio, glib};
+use services::database::{self, DatabaseLocation};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use widgets::EyersWindow;
 
 const APP_ID: &str = "org.gtk_rs.eyers";
@@ -29,14 +33,77 @@ fn main() -> glib::ExitCode {
 
     app.connect_startup(|_| load_css());
 
+    // SyncTeX forward search: `eyers --synctex-forward LINE:COLUMN:FILE document.pdf`
+    app.add_main_option(
+        "synctex-forward",
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Jump to where a LaTeX source line was typeset",
+        Some("LINE:COLUMN:FILE"),
+    );
+
+    // Open at a page and zoom: `eyers --page 42 --zoom 1.5 document.pdf`
+    app.add_main_option(
+        "page",
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::Int,
+        "Open the document at this page",
+        Some("PAGE"),
+    );
+    app.add_main_option(
+        "zoom",
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::Double,
+        "Open the document at this zoom level (0.5 to 3.0)",
+        Some("ZOOM"),
+    );
+
+    // Keep annotations, positions, marks and the rest elsewhere:
+    // `eyers --annotations-db ~/thesis/annotations.db`, or
+    // `--annotations-db :memory:` to forget them all on exit
+    app.add_main_option(
+        "annotations-db",
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Store annotations and reading data in this database file, or :memory: for none",
+        Some("FILE"),
+    );
+
+    let synctex_forward: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    let start_page: Rc<Cell<Option<i32>>> = Rc::new(Cell::new(None));
+    let start_zoom: Rc<Cell<Option<f64>>> = Rc::new(Cell::new(None));
+    let forward = synctex_forward.clone();
+    let page = start_page.clone();
+    let zoom = start_zoom.clone();
+    app.connect_handle_local_options(move |_, options| {
+        if let Ok(Some(spec)) = options.lookup::<String>("synctex-forward") {
+            forward.replace(Some(spec));
+        }
+        if let Ok(Some(value)) = options.lookup::<i32>("page") {
+            page.set(Some(value));
+        }
+        if let Ok(Some(value)) = options.lookup::<f64>("zoom") {
+            zoom.set(Some(value));
+        }
+        if let Ok(Some(value)) = options.lookup::<String>("annotations-db") {
+            database::set_location(DatabaseLocation::parse(&value));
+        }
+        std::ops::ControlFlow::Continue(())
+    });
+
     // Handle activation without file (just open window)
     app.connect_activate(|app| {
         let window = EyersWindow::new(app);
         window.present();
+        window.offer_journal_restore();
     });
 
     // Handle opening files from command line
-    app.connect_open(|app, files, _| {
+    app.connect_open(move |app, files, _| {
         let window = EyersWindow::new(app);
 
         if let Some(file) = files.first() {
@@ -44,8 +111,19 @@ fn main() -> glib::ExitCode {
                 window.open_file(&path);
             }
         }
+        // The other documents wait in tabs until switched to
+        for path in files.iter().skip(1).filter_map(|file| file.path()) {
+            window.add_tab(&path);
+        }
 
         window.present();
+        if start_page.get().is_some() || start_zoom.get().is_some() {
+            window.go_to_start_location(start_page.take(), start_zoom.take());
+        }
+        if let Some(spec) = synctex_forward.take() {
+            window.synctex_forward(&spec);
+        }
+        window.offer_journal_restore();
     });
 
     app.run()
@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::AppMode;
+
+/// Oldest events are dropped past this many entries
+const MAX_EVENTS: usize = 1000;
+
+/// Something that happened while recording
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedEvent {
+    /// A key action was executed, with the mode it left
+    Action {
+        action: String,
+        handled: bool,
+        mode: String,
+    },
+    /// The mode changed, by a key action or otherwise (mouse, panels...)
+    ModeChange { from: String, to: String },
+}
+
+/// Ring buffer of the last key actions and mode transitions, dumped to a file to
+/// attach to bug reports. Nothing is kept until recording is switched on.
+#[derive(Debug)]
+pub struct ActionRecorder {
+    events: VecDeque<(Duration, RecordedEvent)>,
+    recording: bool,
+    started: Instant,
+    /// Mode seen by the last event, to notice transitions
+    last_mode: Option<String>,
+}
+
+impl Default for ActionRecorder {
+    fn default() -> Self {
+        Self {
+            events: VecDeque::new(),
+            recording: false,
+            started: Instant::now(),
+            last_mode: None,
+        }
+    }
+}
+
+impl ActionRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Start or stop recording. Starting discards earlier events.
+    pub fn set_recording(&mut self, recording: bool) {
+        if recording && !self.recording {
+            self.events.clear();
+            self.started = Instant::now();
+            self.last_mode = None;
+        }
+        self.recording = recording;
+    }
+
+    /// Record the mode, as a transition when it differs from the last one seen
+    pub fn observe_mode(&mut self, mode: &AppMode) {
+        let mode = describe_mode(mode);
+        let last = self.last_mode.replace(mode.clone());
+        if let Some(from) = last.filter(|from| *from != mode) {
+            self.push(RecordedEvent::ModeChange { from, to: mode });
+        }
+    }
+
+    /// Record an executed action and the mode it left
+    pub fn record_action(&mut self, action: String, handled: bool, mode: &AppMode) {
+        self.observe_mode(mode);
+        self.push(RecordedEvent::Action {
+            action,
+            handled,
+            mode: describe_mode(mode),
+        });
+    }
+
+    fn push(&mut self, event: RecordedEvent) {
+        if !self.recording {
+            return;
+        }
+        self.events.push_back((self.started.elapsed(), event));
+        if self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The last `count` events (all when None), one per line, oldest first
+    pub fn dump(&self, count: Option<usize>) -> String {
+        let skip = count.map_or(0, |count| self.events.len().saturating_sub(count));
+        self.events
+            .iter()
+            .skip(skip)
+            .map(|(at, event)| format!("{}\n", format_event(*at, event)))
+            .collect()
+    }
+}
+
+/// Where `:dumpkeys` writes without a path
+pub fn default_dump_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|p| p.join("eyers").join("keylog.txt"))
+}
+
+/// The mode without the cursor position, which changes with every motion
+fn describe_mode(mode: &AppMode) -> String {
+    match mode {
+        AppMode::Normal => "Normal".to_string(),
+        AppMode::Visual {
+            selection_anchor: None,
+            ..
+        } => "Visual".to_string(),
+        AppMode::Visual {
+            selection_anchor: Some(_),
+            ..
+        } => "Visual (selecting)".to_string(),
+    }
+}
+
+fn format_event(at: Duration, event: &RecordedEvent) -> String {
+    let at = format!("[{:>9.3}s]", at.as_secs_f64());
+    match event {
+        RecordedEvent::Action {
+            action,
+            handled,
+            mode,
+        } => {
+            let unhandled = if *handled { "" } else { " (unhandled)" };
+            format!("{} {}{} -> {}", at, action, unhandled, mode)
+        }
+        RecordedEvent::ModeChange { from, to } => format!("{} mode {} => {}", at, from, to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::WordCursor;
+
+    fn visual(selecting: bool) -> AppMode {
+        AppMode::Visual {
+            cursor: WordCursor::new(0, 3),
+            selection_anchor: selecting.then(|| WordCursor::new(0, 1)),
+        }
+    }
+
+    fn lines(recorder: &ActionRecorder, count: Option<usize>) -> Vec<String> {
+        recorder
+            .dump(count)
+            .lines()
+            .map(|line| line.split_once("] ").unwrap().1.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_records_only_while_recording() {
+        let mut recorder = ActionRecorder::default();
+        recorder.record_action("ScrollDown".to_string(), true, &AppMode::Normal);
+        assert!(recorder.is_empty());
+
+        recorder.set_recording(true);
+        recorder.record_action("ScrollDown".to_string(), true, &AppMode::Normal);
+        assert_eq!(lines(&recorder, None).len(), 1);
+    }
+
+    #[test]
+    fn test_mode_transitions() {
+        let mut recorder = ActionRecorder::default();
+        recorder.set_recording(true);
+        recorder.observe_mode(&AppMode::Normal);
+        recorder.record_action("EnterVisual".to_string(), true, &visual(false));
+        recorder.observe_mode(&visual(true));
+        recorder.record_action("Copy".to_string(), false, &visual(true));
+
+        assert_eq!(
+            lines(&recorder, None),
+            vec![
+                "mode Normal => Visual",
+                "EnterVisual -> Visual",
+                "mode Visual => Visual (selecting)",
+                "Copy (unhandled) -> Visual (selecting)",
+            ]
+        );
+        assert_eq!(
+            lines(&recorder, Some(1)),
+            vec!["Copy (unhandled) -> Visual (selecting)"]
+        );
+    }
+
+    #[test]
+    fn test_drops_oldest_events() {
+        let mut recorder = ActionRecorder::default();
+        recorder.set_recording(true);
+        for i in 0..MAX_EVENTS + 5 {
+            recorder.record_action(i.to_string(), true, &AppMode::Normal);
+        }
+        assert_eq!(lines(&recorder, None).len(), MAX_EVENTS);
+        assert_eq!(lines(&recorder, None)[0], "5 -> Normal");
+    }
+}
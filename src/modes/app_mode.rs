@@ -111,6 +111,14 @@ impl AppMode {
         }
     }
 
+    /// Select from `anchor` to `cursor`, entering Visual mode if needed
+    pub fn select(&mut self, anchor: WordCursor, cursor: WordCursor) {
+        *self = AppMode::Visual {
+            cursor,
+            selection_anchor: Some(anchor),
+        };
+    }
+
     /// Clear selection anchor only
     pub fn clear_selection(&mut self) {
         if let AppMode::Visual {
@@ -0,0 +1,635 @@
+use std::path::{Path, PathBuf};
+
+use crate::services::annotations::AnnotationColor;
+use crate::services::ink::InkTool;
+
+/// What `:crop` does with the margins of the pages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CropAction {
+    /// Switch cutting the blank margins found on each page on or off
+    Toggle,
+    /// Move the edges of the crop box by hand
+    Edit,
+    /// Show the pages whole
+    Off,
+}
+
+/// A command typed on the `:` command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Re-open the current document from disk, keeping the reading state
+    Reload,
+    /// Export the view (or the selection) as a PNG with an attribution footer.
+    /// Saved to `path` when given, copied to the clipboard otherwise.
+    Snippet { path: Option<PathBuf> },
+    /// Copy the BibTeX citation of the document, fetched from its DOI or ISBN
+    Citation,
+    /// Show the document properties
+    Properties,
+    /// Remove the highlights of the last search
+    ClearSearch,
+    /// Export the annotations of every annotated document into `dir` (asked for
+    /// when missing), one markdown file per document or a single `combined` one
+    ExportAll {
+        dir: Option<PathBuf>,
+        combined: bool,
+    },
+    /// Open the PDF at `path`, or the file chooser when missing
+    Edit { path: Option<PathBuf> },
+    /// Like `Edit`, in a new tab
+    TabEdit { path: Option<PathBuf> },
+    /// Close the current tab, or the window with the last one
+    CloseTab,
+    /// Explain `term` in the glossary of the document, or of every document when
+    /// `global`
+    Glossary {
+        term: String,
+        explanation: String,
+        global: bool,
+    },
+    /// Remove `term` from the glossary of the document, or the global one
+    RemoveGlossary { term: String, global: bool },
+    /// Start or stop recording key actions and mode transitions
+    KeyLog,
+    /// Write the last `count` recorded events (all when missing) to `path`, or to
+    /// the default log file
+    DumpKeys {
+        count: Option<usize>,
+        path: Option<PathBuf>,
+    },
+    /// Show the text of the document wrapped to the window instead of the pages,
+    /// or go back to the pages
+    Reflow,
+    /// Show the pages shaded by their number of annotations and lookups
+    HeatMap,
+    /// Show the spots visited in the document, newest first
+    History,
+    /// Show statistics of the whole library
+    LibraryStats,
+    /// Show a second view of the document next to the pages (`vsplit`) or under
+    /// them (`split`)
+    Split { side_by_side: bool },
+    /// Close the second view
+    CloseSplit,
+    /// Show one page at a time fit to the screen, without the bars and panels
+    Present,
+    /// Crop the margins of the pages, see `CropAction`
+    Crop { action: CropAction },
+    /// Write the reading journal, grouped by day, to `path` or to the default
+    /// journal file
+    ReadingLog { path: Option<PathBuf> },
+    /// Draw on the pages with `tool` (stop drawing when None), in `color` or the
+    /// last color drawn with
+    Draw {
+        tool: Option<InkTool>,
+        color: Option<AnnotationColor>,
+    },
+    /// Remove the last drawing made on the current page
+    Erase,
+    /// Recognize the text of the current scanned page with OCR, or of every page
+    /// without text with `all`
+    Ocr { all: bool },
+    /// Save a copy of the document with its annotations written into it at `path`,
+    /// asked for when missing
+    ExportPdf { path: Option<PathBuf> },
+}
+
+impl Command {
+    /// Parse the text typed after `:`. Returns None for unknown commands.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim().trim_start_matches(':').trim();
+        let (name, args) = match input.split_once(char::is_whitespace) {
+            Some((name, args)) => (name, args.trim()),
+            None => (input, ""),
+        };
+
+        match name {
+            "reload" | "rel" => Some(Command::Reload),
+            "snippet" | "snip" => Some(Command::Snippet {
+                path: (!args.is_empty()).then(|| expand_home(args)),
+            }),
+            "citation" | "cite" => Some(Command::Citation),
+            "properties" | "props" => Some(Command::Properties),
+            "nohlsearch" | "noh" => Some(Command::ClearSearch),
+            "exportall" | "exportall!" => Some(Command::ExportAll {
+                dir: (!args.is_empty()).then(|| expand_home(args)),
+                combined: name.ends_with('!'),
+            }),
+            "edit" | "e" => Some(Command::Edit {
+                path: (!args.is_empty()).then(|| expand_home(args)),
+            }),
+            "tabedit" | "tabe" | "tabnew" => Some(Command::TabEdit {
+                path: (!args.is_empty()).then(|| expand_home(args)),
+            }),
+            "quit" | "q" | "tabclose" | "tabc" => Some(Command::CloseTab),
+            "glossary" | "glossary!" | "gloss" | "gloss!" => {
+                let (term, explanation) = args.split_once('=')?;
+                let (term, explanation) = (term.trim(), explanation.trim());
+                (!term.is_empty() && !explanation.is_empty()).then(|| Command::Glossary {
+                    term: term.to_string(),
+                    explanation: explanation.to_string(),
+                    global: name.ends_with('!'),
+                })
+            }
+            "keylog" => Some(Command::KeyLog),
+            "reflow" => Some(Command::Reflow),
+            "heatmap" | "heat" => Some(Command::HeatMap),
+            "history" | "hist" => Some(Command::History),
+            "stats" | "library" => Some(Command::LibraryStats),
+            "split" | "sp" => Some(Command::Split {
+                side_by_side: false,
+            }),
+            "vsplit" | "vs" => Some(Command::Split { side_by_side: true }),
+            "only" | "on" => Some(Command::CloseSplit),
+            "present" | "pres" => Some(Command::Present),
+            "crop" => {
+                let action = match args {
+                    "" => CropAction::Toggle,
+                    "edit" => CropAction::Edit,
+                    "off" => CropAction::Off,
+                    _ => return None,
+                };
+                Some(Command::Crop { action })
+            }
+            "draw" => parse_draw(args),
+            "erase" => Some(Command::Erase),
+            "ocr" => Some(Command::Ocr { all: false }),
+            "ocr!" => Some(Command::Ocr { all: true }),
+            "exportpdf" => Some(Command::ExportPdf {
+                path: (!args.is_empty()).then(|| expand_home(args)),
+            }),
+            "readinglog" | "rlog" => Some(Command::ReadingLog {
+                path: (!args.is_empty()).then(|| expand_home(args)),
+            }),
+            "dumpkeys" => {
+                let (count, path) = match args.split_once(char::is_whitespace) {
+                    Some((first, rest)) => (first, rest.trim()),
+                    None => (args, ""),
+                };
+                match count.parse::<usize>() {
+                    Ok(count) => Some(Command::DumpKeys {
+                        count: Some(count),
+                        path: (!path.is_empty()).then(|| expand_home(path)),
+                    }),
+                    Err(_) => Some(Command::DumpKeys {
+                        count: None,
+                        path: (!args.is_empty()).then(|| expand_home(args)),
+                    }),
+                }
+            }
+            "unglossary" | "unglossary!" | "ungloss" | "ungloss!" => {
+                (!args.is_empty()).then(|| Command::RemoveGlossary {
+                    term: args.to_string(),
+                    global: name.ends_with('!'),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// `:draw [pen|rect|off] [color]`, the tool and color in any order
+fn parse_draw(args: &str) -> Option<Command> {
+    let mut tool = Some(InkTool::Pen);
+    let mut color = None;
+    for arg in args.split_whitespace() {
+        match arg {
+            "pen" => tool = Some(InkTool::Pen),
+            "rect" | "rectangle" => tool = Some(InkTool::Rectangle),
+            "off" => tool = None,
+            _ => {
+                color = Some(
+                    AnnotationColor::ALL
+                        .into_iter()
+                        .find(|color| color.as_str() == arg)?,
+                )
+            }
+        }
+    }
+    Some(Command::Draw { tool, color })
+}
+
+/// Commands whose argument is completed as a path
+const PATH_COMMANDS: [&str; 2] = ["e", "edit"];
+
+/// Complete the path argument of `:e` in the command line `input`. Returns
+/// every matching directory (with a trailing `/`) and PDF as a full command
+/// line, sorted, keeping the typed `~` and directory as they are.
+pub fn path_completions(input: &str) -> Vec<String> {
+    let Some((name, arg)) = input.trim_start().split_once(' ') else {
+        return Vec::new();
+    };
+    if !PATH_COMMANDS.contains(&name.trim_start_matches(':')) {
+        return Vec::new();
+    }
+    let arg = arg.trim_start();
+    let prefix = &input[..input.len() - arg.len()];
+
+    // The typed directory part stays as is, only the last component is completed
+    let (dir_part, partial) = match arg.rfind('/') {
+        Some(idx) => arg.split_at(idx + 1),
+        None => ("", arg),
+    };
+    let dir = if dir_part.is_empty() {
+        PathBuf::from(".")
+    } else {
+        expand_home(dir_part)
+    };
+
+    let mut completions: Vec<String> = matching_entries(&dir, partial)
+        .into_iter()
+        .map(|entry| format!("{}{}{}", prefix, dir_part, entry))
+        .collect();
+    completions.sort();
+    completions
+}
+
+/// Names of the directories (suffixed with `/`) and PDFs in `dir` starting with
+/// `partial`. Hidden entries only match when `partial` starts with a dot.
+fn matching_entries(dir: &Path, partial: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            if !name.starts_with(partial) || (name.starts_with('.') && !partial.starts_with('.')) {
+                return None;
+            }
+            // Follows symlinks, so linked directories complete like directories
+            let path = entry.path();
+            if path.is_dir() {
+                Some(format!("{}/", name))
+            } else {
+                path.extension()
+                    .filter(|ext| {
+                        ext.eq_ignore_ascii_case("pdf") || ext.eq_ignore_ascii_case("epub")
+                    })
+                    .map(|_| name)
+            }
+        })
+        .collect()
+}
+
+/// Longest prefix shared by all `candidates`
+pub fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .map(|((idx, a), _)| idx + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+/// What the command line starts with to search only within the Visual
+/// selection, like the range of vim's `:'<,'>`
+pub const SELECTION_SEARCH_PROMPT: &str = "'<,'>/";
+
+/// A search typed on the command line: its query, and whether it is limited to
+/// the selection. None for commands.
+pub fn parse_search(input: &str) -> Option<(&str, bool)> {
+    if let Some(query) = input.strip_prefix(SELECTION_SEARCH_PROMPT) {
+        return Some((query, true));
+    }
+    input.strip_prefix('/').map(|query| (query, false))
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reload() {
+        assert_eq!(Command::parse("reload"), Some(Command::Reload));
+        assert_eq!(Command::parse(" :rel "), Some(Command::Reload));
+    }
+
+    #[test]
+    fn test_parse_reflow() {
+        assert_eq!(Command::parse("reflow"), Some(Command::Reflow));
+    }
+
+    #[test]
+    fn test_parse_heat_map() {
+        assert_eq!(Command::parse("heatmap"), Some(Command::HeatMap));
+        assert_eq!(Command::parse(":heat"), Some(Command::HeatMap));
+    }
+
+    #[test]
+    fn test_parse_tabs() {
+        assert_eq!(
+            Command::parse("tabe ~/b.pdf"),
+            Some(Command::TabEdit {
+                path: Some(expand_home("~/b.pdf"))
+            })
+        );
+        assert_eq!(
+            Command::parse("tabnew"),
+            Some(Command::TabEdit { path: None })
+        );
+        assert_eq!(Command::parse("q"), Some(Command::CloseTab));
+        assert_eq!(Command::parse("tabclose"), Some(Command::CloseTab));
+    }
+
+    #[test]
+    fn test_parse_history() {
+        assert_eq!(Command::parse("history"), Some(Command::History));
+        assert_eq!(Command::parse("hist"), Some(Command::History));
+    }
+
+    #[test]
+    fn test_parse_library_stats() {
+        assert_eq!(Command::parse("stats"), Some(Command::LibraryStats));
+        assert_eq!(Command::parse(":library"), Some(Command::LibraryStats));
+    }
+
+    #[test]
+    fn test_parse_split() {
+        assert_eq!(
+            Command::parse("vs"),
+            Some(Command::Split { side_by_side: true })
+        );
+        assert_eq!(
+            Command::parse("split"),
+            Some(Command::Split {
+                side_by_side: false
+            })
+        );
+        assert_eq!(Command::parse("only"), Some(Command::CloseSplit));
+    }
+
+    #[test]
+    fn test_parse_present() {
+        assert_eq!(Command::parse("present"), Some(Command::Present));
+        assert_eq!(Command::parse("pres"), Some(Command::Present));
+    }
+
+    #[test]
+    fn test_parse_crop() {
+        assert_eq!(
+            Command::parse("crop"),
+            Some(Command::Crop {
+                action: CropAction::Toggle
+            })
+        );
+        assert_eq!(
+            Command::parse("crop edit"),
+            Some(Command::Crop {
+                action: CropAction::Edit
+            })
+        );
+        assert_eq!(
+            Command::parse("crop off"),
+            Some(Command::Crop {
+                action: CropAction::Off
+            })
+        );
+        assert_eq!(Command::parse("crop more"), None);
+    }
+
+    #[test]
+    fn test_parse_snippet() {
+        assert_eq!(
+            Command::parse("snippet"),
+            Some(Command::Snippet { path: None })
+        );
+        assert_eq!(
+            Command::parse("snip /tmp/quote.png"),
+            Some(Command::Snippet {
+                path: Some(PathBuf::from("/tmp/quote.png"))
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_reading_log() {
+        assert_eq!(
+            Command::parse("readinglog"),
+            Some(Command::ReadingLog { path: None })
+        );
+        assert_eq!(
+            Command::parse("rlog /tmp/journal.md"),
+            Some(Command::ReadingLog {
+                path: Some(PathBuf::from("/tmp/journal.md"))
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_draw() {
+        assert_eq!(
+            Command::parse("draw"),
+            Some(Command::Draw {
+                tool: Some(InkTool::Pen),
+                color: None
+            })
+        );
+        assert_eq!(
+            Command::parse("draw rect blue"),
+            Some(Command::Draw {
+                tool: Some(InkTool::Rectangle),
+                color: Some(AnnotationColor::Blue)
+            })
+        );
+        assert_eq!(
+            Command::parse("draw green"),
+            Some(Command::Draw {
+                tool: Some(InkTool::Pen),
+                color: Some(AnnotationColor::Green)
+            })
+        );
+        assert_eq!(
+            Command::parse("draw off"),
+            Some(Command::Draw {
+                tool: None,
+                color: None
+            })
+        );
+        assert_eq!(Command::parse("draw circle"), None);
+        assert_eq!(Command::parse("erase"), Some(Command::Erase));
+    }
+
+    #[test]
+    fn test_parse_glossary() {
+        assert_eq!(
+            Command::parse("gloss Eigenvalue = scalar λ with Av = λv"),
+            Some(Command::Glossary {
+                term: "Eigenvalue".to_string(),
+                explanation: "scalar λ with Av = λv".to_string(),
+                global: false
+            })
+        );
+        assert_eq!(
+            Command::parse("ungloss! naive set"),
+            Some(Command::RemoveGlossary {
+                term: "naive set".to_string(),
+                global: true
+            })
+        );
+        assert_eq!(Command::parse("glossary term"), None);
+        assert_eq!(Command::parse("glossary = explanation"), None);
+    }
+
+    #[test]
+    fn test_parse_dump_keys() {
+        assert_eq!(
+            Command::parse("dumpkeys"),
+            Some(Command::DumpKeys {
+                count: None,
+                path: None
+            })
+        );
+        assert_eq!(
+            Command::parse("dumpkeys 50"),
+            Some(Command::DumpKeys {
+                count: Some(50),
+                path: None
+            })
+        );
+        assert_eq!(
+            Command::parse("dumpkeys 50 /tmp/keys.log"),
+            Some(Command::DumpKeys {
+                count: Some(50),
+                path: Some(PathBuf::from("/tmp/keys.log"))
+            })
+        );
+        assert_eq!(
+            Command::parse("dumpkeys /tmp/keys.log"),
+            Some(Command::DumpKeys {
+                count: None,
+                path: Some(PathBuf::from("/tmp/keys.log"))
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_export_all() {
+        assert_eq!(
+            Command::parse("exportall"),
+            Some(Command::ExportAll {
+                dir: None,
+                combined: false
+            })
+        );
+        assert_eq!(
+            Command::parse("exportall! /tmp/notes"),
+            Some(Command::ExportAll {
+                dir: Some(PathBuf::from("/tmp/notes")),
+                combined: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_ocr() {
+        assert_eq!(Command::parse("ocr"), Some(Command::Ocr { all: false }));
+        assert_eq!(Command::parse("ocr!"), Some(Command::Ocr { all: true }));
+    }
+
+    #[test]
+    fn test_parse_search() {
+        assert_eq!(parse_search("/whale"), Some(("whale", false)));
+        assert_eq!(
+            parse_search("'<,'>/white whale"),
+            Some(("white whale", true))
+        );
+        assert_eq!(parse_search(":reload"), None);
+        assert_eq!(parse_search("reload"), None);
+    }
+
+    #[test]
+    fn test_parse_export_pdf() {
+        assert_eq!(
+            Command::parse("exportpdf"),
+            Some(Command::ExportPdf { path: None })
+        );
+        assert_eq!(
+            Command::parse("exportpdf /tmp/out.pdf"),
+            Some(Command::ExportPdf {
+                path: Some(PathBuf::from("/tmp/out.pdf"))
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_edit() {
+        assert_eq!(Command::parse("e"), Some(Command::Edit { path: None }));
+        assert_eq!(
+            Command::parse("edit /tmp/paper.pdf"),
+            Some(Command::Edit {
+                path: Some(PathBuf::from("/tmp/paper.pdf"))
+            })
+        );
+    }
+
+    #[test]
+    fn test_path_completions() {
+        let dir = std::env::temp_dir().join(format!("eyers-complete-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("papers")).unwrap();
+        std::fs::create_dir_all(dir.join(".hidden")).unwrap();
+        std::fs::write(dir.join("paper.pdf"), b"").unwrap();
+        std::fs::write(dir.join("Paper.PDF"), b"").unwrap();
+        std::fs::write(dir.join("paper.txt"), b"").unwrap();
+        std::fs::write(dir.join("paper.epub"), b"").unwrap();
+
+        let root = format!(":e {}/", dir.display());
+        assert_eq!(
+            path_completions(&format!("{}pa", root)),
+            vec![
+                format!("{}paper.epub", root),
+                format!("{}paper.pdf", root),
+                format!("{}papers/", root)
+            ]
+        );
+        assert_eq!(
+            path_completions(&root),
+            vec![
+                format!("{}Paper.PDF", root),
+                format!("{}paper.epub", root),
+                format!("{}paper.pdf", root),
+                format!("{}papers/", root)
+            ]
+        );
+        assert_eq!(
+            path_completions(&format!("{}.h", root)),
+            vec![format!("{}.hidden/", root)]
+        );
+        assert!(path_completions(&format!(":snippet {}pa", root)).is_empty());
+        assert!(path_completions(":e").is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let candidates = vec![":e paper.pdf".to_string(), ":e papers/".to_string()];
+        assert_eq!(common_prefix(&candidates), ":e paper");
+        assert_eq!(common_prefix(&candidates[..1]), ":e paper.pdf");
+        assert_eq!(common_prefix(&[]), "");
+    }
+
+    #[test]
+    fn test_parse_unknown() {
+        assert_eq!(Command::parse(""), None);
+        assert_eq!(Command::parse("reloaded"), None);
+    }
+}
@@ -0,0 +1,107 @@
+use super::WordCursor;
+
+/// Oldest positions are dropped past this many entries
+const MAX_ENTRIES: usize = 100;
+
+/// Positions left by jumps (TOC entries, gg/G, search hits...), walked backward and
+/// forward like a browser history
+#[derive(Debug, Default)]
+pub struct JumpList {
+    entries: Vec<WordCursor>,
+    /// Entry currently visited; `entries.len()` when at a position not in the list
+    index: usize,
+}
+
+impl JumpList {
+    /// Record the position a jump is about to leave. Forward history is discarded.
+    pub fn record(&mut self, position: WordCursor) {
+        self.entries.truncate(self.index);
+        if self.entries.last() != Some(&position) {
+            self.entries.push(position);
+        }
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len();
+    }
+
+    /// Position before the current one. `current` is remembered so `forward` can
+    /// come back to it.
+    pub fn back(&mut self, current: WordCursor) -> Option<WordCursor> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        if self.index >= self.entries.len() {
+            if self.entries.last() != Some(&current) {
+                self.entries.push(current);
+            }
+            self.index = self.entries.len() - 1;
+        }
+        if self.index == 0 {
+            return None;
+        }
+        self.index -= 1;
+        Some(self.entries[self.index])
+    }
+
+    /// Position a previous `back` left
+    pub fn forward(&mut self) -> Option<WordCursor> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        Some(self.entries[self.index])
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(page_index: usize) -> WordCursor {
+        WordCursor::new(page_index, 0)
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut jumps = JumpList::default();
+        jumps.record(at(1));
+        jumps.record(at(5));
+
+        assert_eq!(jumps.back(at(9)), Some(at(5)));
+        assert_eq!(jumps.back(at(5)), Some(at(1)));
+        assert_eq!(jumps.back(at(1)), None);
+        assert_eq!(jumps.forward(), Some(at(5)));
+        assert_eq!(jumps.forward(), Some(at(9)));
+        assert_eq!(jumps.forward(), None);
+    }
+
+    #[test]
+    fn test_record_discards_forward_history() {
+        let mut jumps = JumpList::default();
+        jumps.record(at(1));
+        jumps.record(at(5));
+        jumps.back(at(9));
+        jumps.back(at(5));
+
+        jumps.record(at(1));
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(at(3)), Some(at(1)));
+    }
+
+    #[test]
+    fn test_back_from_recorded_position() {
+        let mut jumps = JumpList::default();
+        assert_eq!(jumps.back(at(0)), None);
+
+        jumps.record(at(2));
+        jumps.record(at(4));
+        // Already at the last recorded position
+        assert_eq!(jumps.back(at(4)), Some(at(2)));
+    }
+}
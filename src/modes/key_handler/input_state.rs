@@ -15,6 +15,16 @@ pub enum InputState {
     PendingElementForward,
     /// Waiting for an element to search it backward ([+a for annotations for example)
     PendingElementBackward,
+    /// Waiting for the text object to select after `i` (w, s or p)
+    PendingTextObject,
+    /// Waiting for the zoom mode after `z` (w, p or z)
+    PendingZ,
+    /// Waiting for the letter to store the position under after `m`
+    PendingMark,
+    /// Waiting for the letter of the mark to jump to after `'`
+    PendingMarkJump,
+    /// Waiting for the split view command after `Ctrl+w` (v, s, w, q or o)
+    PendingWindow,
 }
 
 impl InputState {
@@ -32,6 +42,11 @@ impl InputState {
             InputState::PendingFBackward => "F",
             InputState::PendingElementForward => "]",
             InputState::PendingElementBackward => "[",
+            InputState::PendingTextObject => "i",
+            InputState::PendingZ => "z",
+            InputState::PendingMark => "m",
+            InputState::PendingMarkJump => "'",
+            InputState::PendingWindow => "^W",
         }
     }
 }
@@ -1,5 +1,6 @@
 use crate::modes::app_mode::WordCursor;
 use crate::services::annotations::AnnotationId;
+use crate::services::pdf_text::ZoomMode;
 
 /// Direction for scrolling operations
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -18,12 +19,35 @@ pub enum KeyAction {
 
     // === File Operations ===
     OpenFile,
+    OpenRecentFiles,
     OpenSettings,
     ExportAnnotations,
+    ShowSavedPositions,
+    /// Timeline of the spots visited in the document
+    ShowHistory,
+    ReloadDocument,
 
     // === UI Toggle ===
     ToggleHeaderBar,
+    /// Hide the pointer and the bars while reading
+    ToggleDistractionFree,
+    /// Show one page at a time fit to the screen, without the bars and panels
+    TogglePresentation,
     ToggleTOC,
+    ToggleTwoPage,
+    /// Show one page at a time instead of scrolling continuously
+    TogglePaged,
+    OpenCommandLine,
+    OpenSearch,
+    /// Show a second view of the document next to the pages (`Ctrl+w v`) or
+    /// under them (`Ctrl+w s`)
+    Split {
+        side_by_side: bool,
+    },
+    /// Exchange what the two views show (`Ctrl+w w`)
+    SwapSplit,
+    /// Close the second view (`Ctrl+w q` / `Ctrl+w o`)
+    CloseSplit,
 
     // === Scrolling ===
     ScrollHalfPage(ScrollDir),
@@ -31,11 +55,43 @@ pub enum KeyAction {
         x_percent: f64,
         y_percent: f64,
     },
+    /// `[count]gg` / `[count]G`: page number `count`, or the page labelled
+    /// `count` when page labels are used
     ScrollToPage {
         page: u32,
     },
+    /// `[count]gc`: start of the Nth top-level chapter of the outline
+    ScrollToChapter {
+        chapter: u32,
+    },
     ScrollToStart,
     ScrollToEnd,
+    /// Next/previous page (pair of pages in the two-page layout) at once
+    FlipPage {
+        forward: bool,
+    },
+    /// Next/previous tab (`gt` / `gT`), or with a count the `count`th tab /
+    /// `count` tabs back
+    SwitchTab {
+        forward: bool,
+        count: Option<u32>,
+    },
+    /// Search the selection or the word under the cursor on the web (`gx`), or
+    /// on Wikipedia (`gX`)
+    WebSearch {
+        wikipedia: bool,
+    },
+    /// Walk the jump list backward/forward (Ctrl+O / Ctrl+I)
+    JumpBack,
+    JumpForward,
+    /// Store the position under a letter (`m{a-z}`)
+    SetMark {
+        letter: char,
+    },
+    /// Go back to the position stored under a letter (`'{a-z}`)
+    JumpToMark {
+        letter: char,
+    },
 
     // === TOC Navigation ===
     ScrollTOC(ScrollDir),
@@ -44,6 +100,10 @@ pub enum KeyAction {
     ScrollTocToEnd,
     EditTocAnnotation,
     DeleteTocAnnotation,
+    /// Focus the filter entry of the TOC panel (/)
+    FocusTocSearch,
+    /// Clear the filter of the TOC panel (Escape)
+    ClearTocSearch,
 
     // === Mode Changes ===
     EnterVisual,
@@ -55,6 +115,11 @@ pub enum KeyAction {
     },
     ToggleSelection,
     ClearSelection,
+    /// Select from `start` to `end`, leaving the cursor at `end`
+    SelectRange {
+        start: WordCursor,
+        end: WordCursor,
+    },
     ShowDefinition {
         cursor: WordCursor,
     },
@@ -71,6 +136,17 @@ pub enum KeyAction {
         cursor: WordCursor,
         selection: Option<(WordCursor, WordCursor)>,
     },
+    /// Note anchored at the first visible word, from Normal mode
+    AnnotateViewport,
+    /// Read the selection aloud, or from the cursor on without one. Pauses and
+    /// resumes while reading.
+    ReadAloud {
+        cursor: WordCursor,
+        selection: Option<(WordCursor, WordCursor)>,
+    },
+    StopReading,
+    /// Say the selection, or the word under the cursor, once (gs in Visual mode)
+    Pronounce,
 
     // === Find Operations ===
     FindForward {
@@ -83,7 +159,24 @@ pub enum KeyAction {
     SearchAnnotationForward,
     SearchAnnotationBackward,
 
+    /// Jump to the next/previous match of the last `/` search
+    SearchNext,
+    SearchPrevious,
+
+    /// Skip to the next/previous page with a text layer, past image-only pages
+    SkipToTextPageForward,
+    SkipToTextPageBackward,
+
     // === Zoom ===
     ZoomIn,
     ZoomOut,
+    /// `zw` / `zp` / `[count]zz`: fit width, fit page, or `count`%
+    SetZoomMode(ZoomMode),
+
+    // === Rotation ===
+    /// Turn the pages a quarter turn, every page or only the current one
+    Rotate {
+        clockwise: bool,
+        whole_document: bool,
+    },
 }
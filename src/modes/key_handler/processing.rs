@@ -1,8 +1,10 @@
 use gtk::gdk::{self, ModifierType};
-use pdfium_render::prelude::PdfDocument;
 
+use crate::backend::DocumentBackend;
 use crate::modes::app_mode::{AppMode, WordCursor};
-use crate::text_map::{NavDirection, TextMapCache, navigate};
+use crate::services::marks;
+use crate::services::pdf_text::ZoomMode;
+use crate::text_map::{NavDirection, TextMapCache, TextObject, navigate, text_object_range};
 use crate::widgets::TocMode;
 
 use super::handler::KeyHandler;
@@ -72,7 +74,11 @@ pub fn handle_toc_key(
     match keyval {
         gdk::Key::Escape => {
             handler.reset();
-            KeyResult::Action(KeyAction::None)
+            KeyResult::Action(KeyAction::ClearTocSearch)
+        }
+        gdk::Key::slash => {
+            handler.reset();
+            KeyResult::Action(KeyAction::FocusTocSearch)
         }
         gdk::Key::j | gdk::Key::Down => KeyResult::Action(KeyAction::ScrollTOC(ScrollDir::Down)),
         gdk::Key::k | gdk::Key::Up => KeyResult::Action(KeyAction::ScrollTOC(ScrollDir::Up)),
@@ -104,22 +110,50 @@ pub fn handle_pre_global_key(
     keyval: gdk::Key,
     modifiers: ModifierType,
 ) -> KeyResult {
+    let input_state = handler.input_state();
+
+    // Handle the command after Ctrl+w, typed with or without Ctrl like in vim
+    if matches!(input_state, InputState::PendingWindow) {
+        let action = match keyval {
+            gdk::Key::v => KeyAction::Split { side_by_side: true },
+            gdk::Key::s => KeyAction::Split {
+                side_by_side: false,
+            },
+            gdk::Key::w => KeyAction::SwapSplit,
+            gdk::Key::q | gdk::Key::o | gdk::Key::c => KeyAction::CloseSplit,
+            // Keep waiting while Ctrl is pressed again
+            _ if keyval.to_unicode().is_none() && keyval != gdk::Key::Escape => {
+                return KeyResult::StateChanged;
+            }
+            // Any other key cancels the pending Ctrl+w
+            _ => KeyAction::None,
+        };
+        handler.reset();
+        return KeyResult::Action(action);
+    }
+
     // Handle Ctrl+key combinations
     if modifiers.contains(ModifierType::CONTROL_MASK) {
         return match keyval {
             gdk::Key::d => KeyResult::Action(KeyAction::ScrollHalfPage(ScrollDir::Down)),
             gdk::Key::u => KeyResult::Action(KeyAction::ScrollHalfPage(ScrollDir::Up)),
+            gdk::Key::o => KeyResult::Action(KeyAction::JumpBack),
+            gdk::Key::i => KeyResult::Action(KeyAction::JumpForward),
+            gdk::Key::w => {
+                handler.set_input_state(InputState::PendingWindow);
+                KeyResult::StateChanged
+            }
             _ => KeyResult::Unhandled,
         };
     }
 
     // Handle pending states that need a character
-    let input_state = handler.input_state();
     match input_state {
         InputState::PendingFForward
         | InputState::PendingFBackward
         | InputState::PendingElementForward
-        | InputState::PendingElementBackward => {
+        | InputState::PendingElementBackward
+        | InputState::PendingTextObject => {
             // These are handled in visual mode key handler
             return KeyResult::Unhandled;
         }
@@ -138,6 +172,41 @@ pub fn handle_pre_global_key(
                     None => KeyResult::Action(KeyAction::ScrollToStart),
                 }
             }
+            gdk::Key::c => {
+                // gc or [count]gc - go to the first or Nth chapter
+                let chapter = handler.pending_count().unwrap_or(1);
+                handler.reset();
+                KeyResult::Action(KeyAction::ScrollToChapter { chapter })
+            }
+            gdk::Key::t | gdk::Key::T => {
+                // gt / gT or [count]gt - next/previous or Nth tab
+                let count = handler.pending_count();
+                handler.reset();
+                KeyResult::Action(KeyAction::SwitchTab {
+                    forward: keyval == gdk::Key::t,
+                    count,
+                })
+            }
+            gdk::Key::x | gdk::Key::X => {
+                // gx / gX - search the selection on the web / on Wikipedia
+                handler.reset();
+                KeyResult::Action(KeyAction::WebSearch {
+                    wikipedia: keyval == gdk::Key::X,
+                })
+            }
+            gdk::Key::s => {
+                // gs - hear the word under the cursor or the selection
+                handler.reset();
+                KeyResult::Action(KeyAction::Pronounce)
+            }
+            gdk::Key::greater | gdk::Key::less => {
+                // g> / g< - turn only the current page
+                handler.reset();
+                KeyResult::Action(KeyAction::Rotate {
+                    clockwise: keyval == gdk::Key::greater,
+                    whole_document: false,
+                })
+            }
             _ => {
                 // Any other key cancels the pending g
                 handler.reset();
@@ -146,6 +215,36 @@ pub fn handle_pre_global_key(
         };
     }
 
+    // Handle PendingZ state
+    if matches!(input_state, InputState::PendingZ) {
+        let count = handler.pending_count();
+        handler.reset();
+        let mode = match keyval {
+            gdk::Key::w => ZoomMode::FitWidth,
+            gdk::Key::p => ZoomMode::FitPage,
+            // zz or [count]zz - 100% or count%
+            gdk::Key::z => ZoomMode::Percentage(count.unwrap_or(100) as f64 / 100.0),
+            _ => return KeyResult::Action(KeyAction::None),
+        };
+        return KeyResult::Action(KeyAction::SetZoomMode(mode));
+    }
+
+    // Handle the letter of a mark after m / '
+    if matches!(
+        input_state,
+        InputState::PendingMark | InputState::PendingMarkJump
+    ) {
+        handler.reset();
+        let Some(letter) = keyval.to_unicode().filter(|c| marks::is_mark_letter(*c)) else {
+            // Any other key cancels the pending mark
+            return KeyResult::Action(KeyAction::None);
+        };
+        return KeyResult::Action(match input_state {
+            InputState::PendingMark => KeyAction::SetMark { letter },
+            _ => KeyAction::JumpToMark { letter },
+        });
+    }
+
     // Handle number accumulation
     if let Some(digit) = get_number_from_key(keyval) {
         if digit == 0 && handler.pending_count().is_none() {
@@ -162,10 +261,29 @@ pub fn handle_pre_global_key(
             KeyResult::Unhandled
         }
         gdk::Key::Tab => KeyResult::Action(KeyAction::ToggleTOC),
+        gdk::Key::space => {
+            // Space / Shift+Space - next/previous page
+            handler.reset();
+            KeyResult::Action(KeyAction::FlipPage {
+                forward: !modifiers.contains(ModifierType::SHIFT_MASK),
+            })
+        }
         gdk::Key::g => {
             handler.set_input_state(InputState::PendingG);
             KeyResult::StateChanged
         }
+        gdk::Key::z => {
+            handler.set_input_state(InputState::PendingZ);
+            KeyResult::StateChanged
+        }
+        gdk::Key::m => {
+            handler.set_input_state(InputState::PendingMark);
+            KeyResult::StateChanged
+        }
+        gdk::Key::apostrophe => {
+            handler.set_input_state(InputState::PendingMarkJump);
+            KeyResult::StateChanged
+        }
         gdk::Key::G => {
             // G or [count]G - go to end or page
             let count = handler.pending_count();
@@ -183,9 +301,30 @@ pub fn handle_pre_global_key(
 pub fn handle_post_global_key(handler: &KeyHandler, keyval: gdk::Key) -> KeyResult {
     let result = match keyval {
         gdk::Key::o => KeyResult::Action(KeyAction::OpenFile),
+        gdk::Key::O => KeyResult::Action(KeyAction::OpenRecentFiles),
         gdk::Key::b => KeyResult::Action(KeyAction::ToggleHeaderBar),
+        gdk::Key::Z => KeyResult::Action(KeyAction::ToggleDistractionFree),
+        gdk::Key::F11 => KeyResult::Action(KeyAction::TogglePresentation),
         gdk::Key::p => KeyResult::Action(KeyAction::OpenSettings),
         gdk::Key::e => KeyResult::Action(KeyAction::ExportAnnotations),
+        gdk::Key::S => KeyResult::Action(KeyAction::ShowSavedPositions),
+        gdk::Key::H => KeyResult::Action(KeyAction::ShowHistory),
+        gdk::Key::D => KeyResult::Action(KeyAction::ToggleTwoPage),
+        gdk::Key::P => KeyResult::Action(KeyAction::TogglePaged),
+        gdk::Key::J => KeyResult::Action(KeyAction::FlipPage { forward: true }),
+        gdk::Key::K => KeyResult::Action(KeyAction::FlipPage { forward: false }),
+        gdk::Key::colon => KeyResult::Action(KeyAction::OpenCommandLine),
+        gdk::Key::slash => KeyResult::Action(KeyAction::OpenSearch),
+        gdk::Key::n => KeyResult::Action(KeyAction::SearchNext),
+        gdk::Key::N => KeyResult::Action(KeyAction::SearchPrevious),
+        gdk::Key::greater => KeyResult::Action(KeyAction::Rotate {
+            clockwise: true,
+            whole_document: true,
+        }),
+        gdk::Key::less => KeyResult::Action(KeyAction::Rotate {
+            clockwise: false,
+            whole_document: true,
+        }),
         _ => KeyResult::Unhandled,
     };
 
@@ -199,6 +338,28 @@ pub fn handle_post_global_key(handler: &KeyHandler, keyval: gdk::Key) -> KeyResu
 
 /// Process keys in Normal mode
 pub fn handle_normal_mode_key(handler: &KeyHandler, keyval: gdk::Key) -> KeyResult {
+    let input_state = handler.input_state();
+
+    if matches!(input_state, InputState::PendingElementForward) {
+        return match keyval {
+            gdk::Key::t => KeyResult::Action(KeyAction::SkipToTextPageForward),
+            _ => {
+                handler.reset();
+                KeyResult::Action(KeyAction::None)
+            }
+        };
+    }
+
+    if matches!(input_state, InputState::PendingElementBackward) {
+        return match keyval {
+            gdk::Key::t => KeyResult::Action(KeyAction::SkipToTextPageBackward),
+            _ => {
+                handler.reset();
+                KeyResult::Action(KeyAction::None)
+            }
+        };
+    }
+
     let result = match keyval {
         gdk::Key::h | gdk::Key::Left => KeyResult::Action(KeyAction::ScrollViewport {
             x_percent: -10.0,
@@ -217,8 +378,18 @@ pub fn handle_normal_mode_key(handler: &KeyHandler, keyval: gdk::Key) -> KeyResu
             y_percent: 10.0,
         }),
         gdk::Key::v => KeyResult::Action(KeyAction::EnterVisual),
+        gdk::Key::a => KeyResult::Action(KeyAction::AnnotateViewport),
+        gdk::Key::f => KeyResult::Action(KeyAction::TogglePresentation),
         gdk::Key::plus | gdk::Key::equal => KeyResult::Action(KeyAction::ZoomIn),
         gdk::Key::minus => KeyResult::Action(KeyAction::ZoomOut),
+        gdk::Key::bracketright => {
+            handler.set_input_state(InputState::PendingElementForward);
+            return KeyResult::StateChanged;
+        }
+        gdk::Key::bracketleft => {
+            handler.set_input_state(InputState::PendingElementBackward);
+            return KeyResult::StateChanged;
+        }
         _ => KeyResult::Unhandled,
     };
 
@@ -236,7 +407,7 @@ pub fn handle_visual_mode_key(
     keyval: gdk::Key,
     mode: &AppMode,
     cache: &mut TextMapCache,
-    document: &PdfDocument,
+    document: &dyn DocumentBackend,
 ) -> KeyResult {
     let (cursor, has_selection) = match mode {
         AppMode::Visual {
@@ -248,6 +419,30 @@ pub fn handle_visual_mode_key(
 
     let input_state = handler.input_state();
 
+    // Handle pending text objects: iw, is, ip
+    if matches!(input_state, InputState::PendingTextObject) {
+        handler.reset();
+        let object = match keyval {
+            gdk::Key::w => TextObject::Word,
+            gdk::Key::s => TextObject::Sentence,
+            gdk::Key::p => TextObject::Paragraph,
+            _ => return KeyResult::Action(KeyAction::None),
+        };
+        return match text_object_range(
+            cache,
+            document,
+            cursor.page_index,
+            cursor.word_index,
+            object,
+        ) {
+            Some((start, end)) => KeyResult::Action(KeyAction::SelectRange {
+                start: WordCursor::new(start.page_index, start.word_index),
+                end: WordCursor::new(end.page_index, end.word_index),
+            }),
+            None => KeyResult::Action(KeyAction::None),
+        };
+    }
+
     // Handle pending find operations
     if matches!(input_state, InputState::PendingFForward) {
         if let Some(letter) = keyval.to_unicode() {
@@ -268,6 +463,7 @@ pub fn handle_visual_mode_key(
     if matches!(input_state, InputState::PendingElementForward) {
         return match keyval {
             gdk::Key::a => KeyResult::Action(KeyAction::SearchAnnotationForward),
+            gdk::Key::t => KeyResult::Action(KeyAction::SkipToTextPageForward),
             _ => {
                 handler.reset();
                 KeyResult::Action(KeyAction::None)
@@ -278,6 +474,7 @@ pub fn handle_visual_mode_key(
     if matches!(input_state, InputState::PendingElementBackward) {
         return match keyval {
             gdk::Key::a => KeyResult::Action(KeyAction::SearchAnnotationBackward),
+            gdk::Key::t => KeyResult::Action(KeyAction::SkipToTextPageBackward),
             _ => {
                 handler.reset();
                 KeyResult::Action(KeyAction::None)
@@ -330,7 +527,7 @@ pub fn handle_visual_mode_key(
         // now i would let it like this because for me is not a problem
         gdk::Key::_0 | gdk::Key::Start => {
             if let Some(new_cursor) =
-                navigate_line_edge(cache, document, cursor, NavDirection::Left)
+                navigate_with_count(cache, document, cursor, NavDirection::LineStart, 1)
             {
                 KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
             } else {
@@ -339,7 +536,34 @@ pub fn handle_visual_mode_key(
         }
         gdk::Key::dollar | gdk::Key::End => {
             if let Some(new_cursor) =
-                navigate_line_edge(cache, document, cursor, NavDirection::Right)
+                navigate_with_count(cache, document, cursor, NavDirection::LineEnd, 1)
+            {
+                KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
+            } else {
+                KeyResult::Action(KeyAction::None)
+            }
+        }
+        gdk::Key::w => {
+            if let Some(new_cursor) =
+                navigate_with_count(cache, document, cursor, NavDirection::NextWord, count)
+            {
+                KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
+            } else {
+                KeyResult::Action(KeyAction::None)
+            }
+        }
+        gdk::Key::b => {
+            if let Some(new_cursor) =
+                navigate_with_count(cache, document, cursor, NavDirection::PreviousWord, count)
+            {
+                KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
+            } else {
+                KeyResult::Action(KeyAction::None)
+            }
+        }
+        gdk::Key::e => {
+            if let Some(new_cursor) =
+                navigate_with_count(cache, document, cursor, NavDirection::WordEnd, count)
             {
                 KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
             } else {
@@ -347,6 +571,59 @@ pub fn handle_visual_mode_key(
             }
         }
 
+        gdk::Key::parenright => {
+            if let Some(new_cursor) = navigate_with_count(
+                cache,
+                document,
+                cursor,
+                NavDirection::SentenceForward,
+                count,
+            ) {
+                KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
+            } else {
+                KeyResult::Action(KeyAction::None)
+            }
+        }
+        gdk::Key::parenleft => {
+            if let Some(new_cursor) = navigate_with_count(
+                cache,
+                document,
+                cursor,
+                NavDirection::SentenceBackward,
+                count,
+            ) {
+                KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
+            } else {
+                KeyResult::Action(KeyAction::None)
+            }
+        }
+        gdk::Key::braceright => {
+            if let Some(new_cursor) = navigate_with_count(
+                cache,
+                document,
+                cursor,
+                NavDirection::ParagraphForward,
+                count,
+            ) {
+                KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
+            } else {
+                KeyResult::Action(KeyAction::None)
+            }
+        }
+        gdk::Key::braceleft => {
+            if let Some(new_cursor) = navigate_with_count(
+                cache,
+                document,
+                cursor,
+                NavDirection::ParagraphBackward,
+                count,
+            ) {
+                KeyResult::Action(KeyAction::CursorMoved { cursor: new_cursor })
+            } else {
+                KeyResult::Action(KeyAction::None)
+            }
+        }
+
         gdk::Key::v => KeyResult::Action(KeyAction::ExitVisual),
 
         gdk::Key::Escape => {
@@ -359,6 +636,11 @@ pub fn handle_visual_mode_key(
 
         gdk::Key::s => KeyResult::Action(KeyAction::ToggleSelection),
 
+        gdk::Key::i => {
+            handler.set_input_state(InputState::PendingTextObject);
+            KeyResult::StateChanged
+        }
+
         gdk::Key::d => {
             if !has_selection {
                 KeyResult::Action(KeyAction::ShowDefinition { cursor })
@@ -402,6 +684,12 @@ pub fn handle_visual_mode_key(
             selection: mode.selection_range(),
         }),
 
+        gdk::Key::r => KeyResult::Action(KeyAction::ReadAloud {
+            cursor,
+            selection: mode.selection_range(),
+        }),
+        gdk::Key::R => KeyResult::Action(KeyAction::StopReading),
+
         gdk::Key::plus | gdk::Key::equal => KeyResult::Action(KeyAction::ZoomIn),
         gdk::Key::minus => KeyResult::Action(KeyAction::ZoomOut),
 
@@ -419,7 +707,7 @@ pub fn handle_visual_mode_key(
 /// Navigate multiple times based on count
 fn navigate_with_count(
     cache: &mut TextMapCache,
-    document: &PdfDocument,
+    document: &dyn DocumentBackend,
     start_cursor: WordCursor,
     direction: NavDirection,
     count: u32,
@@ -448,38 +736,3 @@ fn navigate_with_count(
         None
     }
 }
-
-fn navigate_line_edge(
-    cache: &mut TextMapCache,
-    document: &PdfDocument,
-    start_cursor: WordCursor,
-    direction: NavDirection,
-) -> Option<WordCursor> {
-    let mut current = start_cursor;
-    let mut cursor_line: Option<usize> = None;
-    loop {
-        if let Some(result) = navigate(
-            cache,
-            document,
-            current.page_index,
-            current.word_index,
-            direction,
-        ) {
-            let _ = cursor_line.get_or_insert(result.line_index);
-            if result.line_index != cursor_line.expect("value should exist") {
-                break;
-            }
-            current = WordCursor::new(result.page_index, result.word_index);
-        } else {
-            // Stop if navigation fails
-            break;
-        }
-    }
-
-    // Only return if we actually moved
-    if current != start_cursor {
-        Some(current)
-    } else {
-        None
-    }
-}
@@ -1,8 +1,18 @@
+pub mod action_recorder;
 pub mod app_mode;
+pub mod command;
+pub mod jump_list;
 pub mod key_handler;
+pub mod tab_list;
+pub mod visit_history;
 
+pub use action_recorder::ActionRecorder;
 pub use app_mode::{AppMode, WordCursor};
+pub use command::{Command, CropAction};
+pub use jump_list::JumpList;
 pub use key_handler::{
     KeyAction, KeyHandler, KeyResult, ScrollDir, handle_normal_mode_key, handle_post_global_key,
     handle_pre_global_key, handle_toc_key, handle_visual_mode_key,
 };
+pub use tab_list::{DocumentTab, TabList};
+pub use visit_history::{Visit, VisitHistory};
@@ -0,0 +1,232 @@
+use std::path::{Path, PathBuf};
+
+/// A document open in a tab, with what the window keeps of it while another
+/// tab is shown
+#[derive(Debug)]
+pub struct DocumentTab<S> {
+    pub path: PathBuf,
+    pub state: S,
+}
+
+impl<S> DocumentTab<S> {
+    pub fn new(path: &Path, state: S) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            state,
+        }
+    }
+
+    /// File name shown on the tab
+    pub fn title(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+/// The tabs of a window, in order, and the one shown. A document is open in
+/// one tab at most.
+#[derive(Debug)]
+pub struct TabList<S> {
+    tabs: Vec<DocumentTab<S>>,
+    active: usize,
+}
+
+impl<S> Default for TabList<S> {
+    fn default() -> Self {
+        Self {
+            tabs: Vec::new(),
+            active: 0,
+        }
+    }
+}
+
+impl<S> TabList<S> {
+    pub fn tabs(&self) -> &[DocumentTab<S>] {
+        &self.tabs
+    }
+
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> Option<&DocumentTab<S>> {
+        self.tabs.get(self.active)
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut DocumentTab<S>> {
+        self.tabs.get_mut(self.active)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&DocumentTab<S>> {
+        self.tabs.get(index)
+    }
+
+    /// Index of the tab showing `path`, if any
+    pub fn position(&self, path: &Path) -> Option<usize> {
+        self.tabs.iter().position(|tab| tab.path == path)
+    }
+
+    /// Show `path` in the active tab, or in a first tab when there is none.
+    /// Returns the tab it replaced. When another tab already shows `path`, that
+    /// one becomes active instead and `state` is dropped.
+    pub fn replace_active(&mut self, path: &Path, state: S) -> Option<DocumentTab<S>> {
+        if let Some(index) = self.position(path) {
+            self.active = index;
+            return None;
+        }
+        let tab = DocumentTab::new(path, state);
+        match self.tabs.get_mut(self.active) {
+            Some(active) => Some(std::mem::replace(active, tab)),
+            None => {
+                self.tabs.push(tab);
+                self.active = self.tabs.len() - 1;
+                None
+            }
+        }
+    }
+
+    /// Add a tab for `path` right after the active one and make it active, or
+    /// make the tab already showing `path` active, dropping `state`. Returns
+    /// the index of the tab.
+    pub fn open(&mut self, path: &Path, state: S) -> usize {
+        if let Some(index) = self.position(path) {
+            self.active = index;
+            return index;
+        }
+        let index = if self.tabs.is_empty() {
+            0
+        } else {
+            self.active + 1
+        };
+        self.tabs.insert(index, DocumentTab::new(path, state));
+        self.active = index;
+        index
+    }
+
+    /// Add a tab for `path` at the end, leaving the active one shown. Returns
+    /// false when a tab already shows `path`.
+    pub fn push(&mut self, path: &Path, state: S) -> bool {
+        if self.position(path).is_some() {
+            return false;
+        }
+        self.tabs.push(DocumentTab::new(path, state));
+        true
+    }
+
+    pub fn set_active(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.active = index;
+        }
+    }
+
+    /// Tab `gt`/`gT` go to: the next/previous one, wrapping around, or the
+    /// 1-based `count`th one with a count
+    pub fn target(&self, forward: bool, count: Option<usize>) -> Option<usize> {
+        let len = self.tabs.len();
+        if len == 0 {
+            return None;
+        }
+        match count {
+            Some(count) if forward => (1..=len).contains(&count).then(|| count - 1),
+            // Like vim, `3gT` goes back three tabs
+            Some(count) => Some((self.active + len - count % len) % len),
+            None if forward => Some((self.active + 1) % len),
+            None => Some((self.active + len - 1) % len),
+        }
+    }
+
+    /// Remove the tab at `index`. The tab after it becomes active when it was
+    /// the active one, or the one before when it was the last.
+    pub fn close(&mut self, index: usize) -> Option<DocumentTab<S>> {
+        if index >= self.tabs.len() {
+            return None;
+        }
+        let tab = self.tabs.remove(index);
+        if self.active > index || self.active >= self.tabs.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        Some(tab)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tabs(names: &[&str]) -> TabList<()> {
+        let mut list = TabList::default();
+        for name in names {
+            list.push(Path::new(name), ());
+        }
+        list
+    }
+
+    fn titles<S>(list: &TabList<S>) -> Vec<String> {
+        list.tabs().iter().map(DocumentTab::title).collect()
+    }
+
+    #[test]
+    fn test_open_after_active() {
+        let mut list = tabs(&["a.pdf", "b.pdf"]);
+        assert_eq!(list.open(Path::new("/docs/c.pdf"), ()), 1);
+        assert_eq!(titles(&list), ["a", "c", "b"]);
+        assert_eq!(list.active_index(), 1);
+        assert_eq!(list.position(Path::new("b.pdf")), Some(2));
+
+        // An open document is gone to rather than opened twice
+        assert_eq!(list.open(Path::new("a.pdf"), ()), 0);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.active_index(), 0);
+        assert!(!list.push(Path::new("b.pdf"), ()));
+    }
+
+    #[test]
+    fn test_replace_active() {
+        let mut list = TabList::default();
+        assert!(list.replace_active(Path::new("a.pdf"), 1).is_none());
+        let replaced = list.replace_active(Path::new("b.pdf"), 2);
+        assert_eq!(replaced.map(|tab| tab.state), Some(1));
+        assert_eq!(titles(&list), ["b"]);
+
+        list.push(Path::new("c.pdf"), 3);
+        assert!(list.replace_active(Path::new("c.pdf"), 4).is_none());
+        assert_eq!(titles(&list), ["b", "c"]);
+        assert_eq!(list.active().map(|tab| tab.state), Some(3));
+    }
+
+    #[test]
+    fn test_target() {
+        let mut list = tabs(&["a.pdf", "b.pdf", "c.pdf"]);
+        assert_eq!(list.target(true, None), Some(1));
+        assert_eq!(list.target(false, None), Some(2));
+        assert_eq!(list.target(true, Some(3)), Some(2));
+        assert_eq!(list.target(true, Some(4)), None);
+
+        list.set_active(1);
+        assert_eq!(list.target(false, Some(2)), Some(2));
+        assert_eq!(TabList::<()>::default().target(true, None), None);
+    }
+
+    #[test]
+    fn test_close() {
+        let mut list = tabs(&["a.pdf", "b.pdf", "c.pdf"]);
+        list.set_active(2);
+        list.close(2);
+        assert_eq!(list.active_index(), 1);
+
+        list.close(0);
+        assert_eq!(titles(&list), ["b"]);
+        assert_eq!(list.active_index(), 0);
+        assert!(list.close(3).is_none());
+    }
+}
@@ -0,0 +1,94 @@
+use super::WordCursor;
+
+/// Oldest visits are dropped past this many entries
+const MAX_VISITS: usize = 500;
+
+/// A reading spot left at `left_at` (seconds since the Unix epoch)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Visit {
+    pub cursor: WordCursor,
+    pub left_at: i64,
+}
+
+/// Every spot read before a jump, in the order they were visited. Unlike the
+/// jump list, going back never discards anything: returning to a page adds it
+/// again, so the history shows the path taken through the document.
+#[derive(Debug, Default)]
+pub struct VisitHistory {
+    visits: Vec<Visit>,
+}
+
+impl VisitHistory {
+    /// Record leaving `cursor` at `left_at`. Leaving the page of the last visit
+    /// again only moves that visit.
+    pub fn record(&mut self, cursor: WordCursor, left_at: i64) {
+        match self.visits.last_mut() {
+            Some(last) if last.cursor.page_index == cursor.page_index => {
+                *last = Visit { cursor, left_at };
+            }
+            _ => self.visits.push(Visit { cursor, left_at }),
+        }
+        if self.visits.len() > MAX_VISITS {
+            self.visits.remove(0);
+        }
+    }
+
+    /// The visits, oldest first
+    pub fn visits(&self) -> &[Visit] {
+        &self.visits
+    }
+
+    pub fn clear(&mut self) {
+        self.visits.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(page_index: usize) -> WordCursor {
+        WordCursor::new(page_index, 0)
+    }
+
+    #[test]
+    fn test_record_keeps_returns() {
+        let mut history = VisitHistory::default();
+        history.record(at(3), 10);
+        history.record(at(40), 20);
+        history.record(at(3), 30);
+
+        let pages: Vec<usize> = history
+            .visits()
+            .iter()
+            .map(|v| v.cursor.page_index)
+            .collect();
+        assert_eq!(pages, [3, 40, 3]);
+    }
+
+    #[test]
+    fn test_record_merges_same_page() {
+        let mut history = VisitHistory::default();
+        history.record(at(3), 10);
+        history.record(WordCursor::new(3, 25), 20);
+
+        assert_eq!(
+            history.visits(),
+            [Visit {
+                cursor: WordCursor::new(3, 25),
+                left_at: 20
+            }]
+        );
+    }
+
+    #[test]
+    fn test_record_drops_oldest() {
+        let mut history = VisitHistory::default();
+        for page_index in 0..=MAX_VISITS {
+            history.record(at(page_index), page_index as i64);
+        }
+
+        assert_eq!(history.visits().len(), MAX_VISITS);
+        assert_eq!(history.visits()[0].cursor, at(1));
+    }
+}
@@ -0,0 +1,183 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const RELEASES_URL: &str = "https://api.github.com/repos/gregovilardo/eyers/releases/latest";
+const USER_AGENT: &str = concat!("eyers/", env!("CARGO_PKG_VERSION"));
+
+/// Details about the running build, meant to be pasted into bug reports
+#[derive(Debug, Clone)]
+pub struct EnvironmentInfo {
+    pub app_version: String,
+    /// Path of the pdfium shared library loaded into the process
+    pub pdfium_library: Option<PathBuf>,
+    /// Chromium build number of pdfium, when the library ships a VERSION file
+    pub pdfium_build: Option<String>,
+}
+
+impl EnvironmentInfo {
+    pub fn collect() -> Self {
+        let pdfium_library = loaded_pdfium_library();
+        let pdfium_build = pdfium_library
+            .as_deref()
+            .and_then(Path::parent)
+            .and_then(read_pdfium_build);
+
+        Self {
+            app_version: APP_VERSION.to_string(),
+            pdfium_library,
+            pdfium_build,
+        }
+    }
+
+    /// Plain text summary for the clipboard
+    pub fn report(&self) -> String {
+        format!(
+            "Eyers {}\npdfium build: {}\npdfium library: {}\nOS: {} ({})",
+            self.app_version,
+            self.pdfium_build.as_deref().unwrap_or("unknown"),
+            self.pdfium_library
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+        )
+    }
+}
+
+/// Find the pdfium library among the shared objects mapped into this process
+fn loaded_pdfium_library() -> Option<PathBuf> {
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .find(|path| {
+            Path::new(path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.contains("pdfium"))
+        })
+        .map(PathBuf::from)
+}
+
+/// pdfium-binaries releases put a VERSION file (`BUILD=6721` etc.) next to or one level
+/// above the library
+fn read_pdfium_build(lib_dir: &Path) -> Option<String> {
+    [Some(lib_dir), lib_dir.parent()]
+        .into_iter()
+        .flatten()
+        .filter_map(|dir| std::fs::read_to_string(dir.join("VERSION")).ok())
+        .find_map(|contents| parse_pdfium_build(&contents))
+}
+
+fn parse_pdfium_build(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("BUILD="))
+        .map(|build| build.trim().to_string())
+        .filter(|build| !build.is_empty())
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+}
+
+/// Result of checking GitHub for a newer release
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateStatus {
+    UpToDate,
+    Available { version: String, url: String },
+}
+
+#[derive(Debug)]
+pub enum UpdateError {
+    RequestFailed(String),
+    ParseFailed(String),
+}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::RequestFailed(msg) => write!(f, "Request failed: {}", msg),
+            UpdateError::ParseFailed(msg) => write!(f, "Parse failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {}
+
+/// Ask GitHub for the latest release and compare it with the running version
+pub fn check_for_update() -> Result<UpdateStatus, UpdateError> {
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| UpdateError::RequestFailed(e.to_string()))?;
+
+    let response = client
+        .get(RELEASES_URL)
+        .send()
+        .map_err(|e| UpdateError::RequestFailed(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(UpdateError::RequestFailed(format!(
+            "Status: {}",
+            response.status()
+        )));
+    }
+
+    let release: Release = response
+        .json()
+        .map_err(|e| UpdateError::ParseFailed(e.to_string()))?;
+
+    let latest = release.tag_name.trim_start_matches('v');
+    if is_newer(latest, APP_VERSION) {
+        Ok(UpdateStatus::Available {
+            version: latest.to_string(),
+            url: release.html_url,
+        })
+    } else {
+        Ok(UpdateStatus::UpToDate)
+    }
+}
+
+/// Compare dotted version numbers; anything unparsable counts as 0
+fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parts(version: &str) -> Vec<u64> {
+        let mut parts: Vec<u64> = version
+            .split(['.', '-'])
+            .take(3)
+            .map(|part| part.parse().unwrap_or(0))
+            .collect();
+        parts.resize(3, 0);
+        parts
+    }
+
+    parts(candidate) > parts(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("0.1.10", "0.1.9"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+        assert!(!is_newer("0.1", "0.1.0"));
+    }
+
+    #[test]
+    fn test_parse_pdfium_build() {
+        assert_eq!(
+            parse_pdfium_build("MAJOR=133\nMINOR=0\nBUILD=6921\nPATCH=0\n"),
+            Some("6921".to_string())
+        );
+        assert_eq!(parse_pdfium_build("MAJOR=133\n"), None);
+    }
+}
@@ -1,11 +1,74 @@
 use gtk::glib;
-use rusqlite::{Connection, OpenFlags, params};
-use std::{cmp::Ordering, path::PathBuf};
+use pdfium_render::prelude::{
+    PdfColor, PdfPageAnnotationCommon, PdfQuadPoints, PdfRect, Pdfium, PdfiumError,
+};
+use rusqlite::{ErrorCode, OptionalExtension, params};
+use std::cmp::Ordering;
+use std::path::Path;
 
+use crate::backend::PdfiumBackend;
 use crate::modes::WordCursor;
+use crate::services::bookmarks::BookmarkEntry;
+use crate::services::database::{self, DatabaseError};
 
 pub type AnnotationId = i64;
 
+/// Highlight color of an annotation, doubling as its category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AnnotationColor {
+    #[default]
+    Yellow,
+    Green,
+    Red,
+    Blue,
+}
+
+impl AnnotationColor {
+    pub const ALL: [AnnotationColor; 4] = [
+        AnnotationColor::Yellow,
+        AnnotationColor::Green,
+        AnnotationColor::Red,
+        AnnotationColor::Blue,
+    ];
+
+    /// Name stored in the database, also used in CSS class names
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AnnotationColor::Yellow => "yellow",
+            AnnotationColor::Green => "green",
+            AnnotationColor::Red => "red",
+            AnnotationColor::Blue => "blue",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AnnotationColor::Yellow => "Yellow",
+            AnnotationColor::Green => "Green",
+            AnnotationColor::Red => "Red",
+            AnnotationColor::Blue => "Blue",
+        }
+    }
+
+    /// Parse a stored color name, falling back to yellow for unknown names
+    pub fn from_name(name: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|color| color.as_str() == name)
+            .unwrap_or_default()
+    }
+
+    /// Color of the highlight annotation written into a PDF
+    fn pdf_color(self) -> PdfColor {
+        match self {
+            AnnotationColor::Yellow => PdfColor::new(255, 235, 60, 255),
+            AnnotationColor::Green => PdfColor::new(110, 220, 110, 255),
+            AnnotationColor::Red => PdfColor::new(255, 110, 110, 255),
+            AnnotationColor::Blue => PdfColor::new(110, 170, 255, 255),
+        }
+    }
+}
+
 /// Represents an annotation on a PDF document
 #[derive(Debug, Clone, Default)]
 pub struct Annotation {
@@ -17,6 +80,9 @@ pub struct Annotation {
     pub end_word: usize,
     pub selected_text: String,
     pub note: String,
+    pub color: AnnotationColor,
+    /// Free-form labels, stored comma-separated
+    pub tags: Vec<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -26,15 +92,68 @@ pub struct Annotation {
 pub enum AnnotationError {
     DatabaseError(String),
     NotFound,
+    /// Another Eyers window or process kept the database busy for too long
+    Locked,
+    /// Reading or writing a PDF file failed
+    PdfError(String),
 }
 
 impl Annotation {
     pub fn get_start_word_cursor(&self) -> WordCursor {
         WordCursor::new(self.start_page, self.start_word)
     }
+
+    /// Whether the annotation was added or edited at or after the Unix time `since`
+    pub fn changed_since(&self, since: i64) -> bool {
+        self.updated_at >= since
+    }
+
     pub fn get_id(&self) -> AnnotationId {
         self.id
     }
+
+    /// Page and beginning of the text, to refer to the annotation in little space
+    pub fn short_label(&self) -> String {
+        const MAX_CHARS: usize = 30;
+        let text = self
+            .selected_text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut label: String = text.chars().take(MAX_CHARS).collect();
+        if text.chars().count() > MAX_CHARS {
+            label.push('…');
+        }
+        format!("p. {}: {}", self.start_page + 1, label)
+    }
+}
+
+/// Split comma-separated tags, trimming them and dropping empty and repeated ones
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Tags as stored in the database and shown in the editor
+pub fn join_tags(tags: &[String]) -> String {
+    tags.join(", ")
+}
+
+/// Every tag used by `annotations`, sorted case-insensitively
+pub fn all_tags(annotations: &[Annotation]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in annotations.iter().flat_map(|ann| &ann.tags) {
+        if !tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+    tags.sort_by_key(|tag| tag.to_lowercase());
+    tags
 }
 
 impl PartialEq for Annotation {
@@ -58,68 +177,43 @@ impl std::fmt::Display for AnnotationError {
         match self {
             AnnotationError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
             AnnotationError::NotFound => write!(f, "Annotation not found"),
+            AnnotationError::Locked => write!(
+                f,
+                "Annotations are being saved by another Eyers window or process, try again"
+            ),
+            AnnotationError::PdfError(msg) => write!(f, "PDF error: {}", msg),
         }
     }
 }
 
 impl std::error::Error for AnnotationError {}
 
-impl From<rusqlite::Error> for AnnotationError {
-    fn from(err: rusqlite::Error) -> Self {
-        AnnotationError::DatabaseError(err.to_string())
+impl From<PdfiumError> for AnnotationError {
+    fn from(err: PdfiumError) -> Self {
+        AnnotationError::PdfError(err.to_string())
     }
 }
 
-/// Returns the path to the annotations database
-fn get_db_path() -> Option<PathBuf> {
-    dirs::data_dir().map(|p| p.join("eyers").join("annotations.db"))
+impl From<rusqlite::Error> for AnnotationError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err.sqlite_error_code() {
+            Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => AnnotationError::Locked,
+            _ => AnnotationError::DatabaseError(err.to_string()),
+        }
+    }
 }
 
-/// Opens a connection to the annotations database, creating it if necessary
-fn open_db() -> Result<Connection, AnnotationError> {
-    let path = get_db_path().ok_or_else(|| {
-        AnnotationError::DatabaseError("Could not determine data directory".to_string())
-    })?;
-
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| {
-            AnnotationError::DatabaseError(format!("Could not create data directory: {}", e))
-        })?;
+impl From<DatabaseError> for AnnotationError {
+    fn from(err: DatabaseError) -> Self {
+        match err {
+            DatabaseError::Failed(msg) => AnnotationError::DatabaseError(msg),
+            DatabaseError::Locked => AnnotationError::Locked,
+        }
     }
-
-    let conn = Connection::open_with_flags(
-        &path,
-        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
-    )?;
-
-    // Initialize the schema if needed
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS annotations (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            pdf_path TEXT NOT NULL,
-            start_page INTEGER NOT NULL,
-            start_word INTEGER NOT NULL,
-            end_page INTEGER NOT NULL,
-            end_word INTEGER NOT NULL,
-            selected_text TEXT NOT NULL,
-            note TEXT NOT NULL,
-            created_at INTEGER NOT NULL,
-            updated_at INTEGER NOT NULL
-        )",
-        [],
-    )?;
-
-    // Create index for faster lookups by PDF path
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_annotations_pdf_path ON annotations(pdf_path)",
-        [],
-    )?;
-
-    Ok(conn)
 }
 
 /// Save a new annotation to the database
+#[allow(clippy::too_many_arguments)]
 pub fn save_annotation(
     pdf_path: &str,
     start_page: usize,
@@ -128,33 +222,39 @@ pub fn save_annotation(
     end_word: usize,
     selected_text: &str,
     note: &str,
+    color: AnnotationColor,
+    tags: &[String],
 ) -> Result<i64, AnnotationError> {
-    let conn = open_db()?;
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs() as i64;
 
-    conn.execute(
-        "INSERT INTO annotations (pdf_path, start_page, start_word, end_page, end_word, selected_text, note, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![
-            pdf_path,
-            start_page as i64,
-            start_word as i64,
-            end_page as i64,
-            end_word as i64,
-            selected_text,
-            note,
-            now,
-            now
-        ],
-    )?;
-
-    Ok(conn.last_insert_rowid())
+    database::write(|conn| {
+        conn.execute(
+            "INSERT INTO annotations (pdf_path, start_page, start_word, end_page, end_word, selected_text, note, color, tags, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                pdf_path,
+                start_page as i64,
+                start_word as i64,
+                end_page as i64,
+                end_word as i64,
+                selected_text,
+                note,
+                color.as_str(),
+                join_tags(tags),
+                now,
+                now
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    })
+    .map_err(AnnotationError::from)
 }
 
-/// Update an existing annotation's note and selection range
+/// Update an existing annotation's note, color, tags and selection range
+#[allow(clippy::too_many_arguments)]
 pub fn update_annotation(
     id: i64,
     start_page: usize,
@@ -163,26 +263,31 @@ pub fn update_annotation(
     end_word: usize,
     selected_text: &str,
     note: &str,
+    color: AnnotationColor,
+    tags: &[String],
 ) -> Result<(), AnnotationError> {
-    let conn = open_db()?;
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs() as i64;
 
-    let rows_affected = conn.execute(
-        "UPDATE annotations SET start_page = ?1, start_word = ?2, end_page = ?3, end_word = ?4, selected_text = ?5, note = ?6, updated_at = ?7 WHERE id = ?8",
-        params![
-            start_page as i64,
-            start_word as i64,
-            end_page as i64,
-            end_word as i64,
-            selected_text,
-            note,
-            now,
-            id
-        ],
-    )?;
+    let rows_affected = database::write(|conn| {
+        conn.execute(
+            "UPDATE annotations SET start_page = ?1, start_word = ?2, end_page = ?3, end_word = ?4, selected_text = ?5, note = ?6, color = ?7, tags = ?8, updated_at = ?9 WHERE id = ?10",
+            params![
+                start_page as i64,
+                start_word as i64,
+                end_page as i64,
+                end_word as i64,
+                selected_text,
+                note,
+                color.as_str(),
+                join_tags(tags),
+                now,
+                id
+            ],
+        )
+    })?;
 
     if rows_affected == 0 {
         return Err(AnnotationError::NotFound);
@@ -193,9 +298,13 @@ pub fn update_annotation(
 
 /// Delete an annotation by ID
 pub fn delete_annotation(id: i64) -> Result<(), AnnotationError> {
-    let conn = open_db()?;
-
-    let rows_affected = conn.execute("DELETE FROM annotations WHERE id = ?1", params![id])?;
+    let rows_affected = database::write(|conn| {
+        conn.execute(
+            "DELETE FROM annotation_links WHERE first_id = ?1 OR second_id = ?1",
+            params![id],
+        )?;
+        conn.execute("DELETE FROM annotations WHERE id = ?1", params![id])
+    })?;
 
     if rows_affected == 0 {
         return Err(AnnotationError::NotFound);
@@ -204,12 +313,80 @@ pub fn delete_annotation(id: i64) -> Result<(), AnnotationError> {
     Ok(())
 }
 
+/// A link as stored: the smaller ID first, so each pair is stored once
+fn link_key(a: AnnotationId, b: AnnotationId) -> (AnnotationId, AnnotationId) {
+    (a.min(b), a.max(b))
+}
+
+/// Link two annotations as "see also" of each other. Linking an annotation to
+/// itself, or a pair already linked, does nothing.
+pub fn link_annotations(a: AnnotationId, b: AnnotationId) -> Result<(), AnnotationError> {
+    if a == b {
+        return Ok(());
+    }
+    let (first, second) = link_key(a, b);
+    database::write(|conn| {
+        conn.execute(
+            "INSERT OR IGNORE INTO annotation_links (first_id, second_id) VALUES (?1, ?2)",
+            params![first, second],
+        )?;
+        Ok(())
+    })
+    .map_err(AnnotationError::from)
+}
+
+/// Remove the link between two annotations
+pub fn unlink_annotations(a: AnnotationId, b: AnnotationId) -> Result<(), AnnotationError> {
+    let (first, second) = link_key(a, b);
+    database::write(|conn| {
+        conn.execute(
+            "DELETE FROM annotation_links WHERE first_id = ?1 AND second_id = ?2",
+            params![first, second],
+        )?;
+        Ok(())
+    })
+    .map_err(AnnotationError::from)
+}
+
+/// Links between the annotations of a document, as ID pairs
+pub fn load_links_for_pdf(
+    pdf_path: &str,
+) -> Result<Vec<(AnnotationId, AnnotationId)>, AnnotationError> {
+    let conn = database::open()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT l.first_id, l.second_id FROM annotation_links l
+         JOIN annotations a ON a.id = l.first_id
+         JOIN annotations b ON b.id = l.second_id
+         WHERE a.pdf_path = ?1 AND b.pdf_path = ?1",
+    )?;
+
+    let links = stmt
+        .query_map(params![pdf_path], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(links)
+}
+
+/// IDs of the annotations linked to `id`, in the order of `links`
+pub fn linked_ids(links: &[(AnnotationId, AnnotationId)], id: AnnotationId) -> Vec<AnnotationId> {
+    links
+        .iter()
+        .filter_map(|&(a, b)| match (a == id, b == id) {
+            (true, false) => Some(b),
+            (false, true) => Some(a),
+            _ => None,
+        })
+        .collect()
+}
+
 /// Load all annotations for a specific PDF file
 pub fn load_annotations_for_pdf(pdf_path: &str) -> Result<Vec<Annotation>, AnnotationError> {
-    let conn = open_db()?;
+    let conn = database::open()?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, pdf_path, start_page, start_word, end_page, end_word, selected_text, note, created_at, updated_at
+        "SELECT id, pdf_path, start_page, start_word, end_page, end_word, selected_text, note, color, tags, created_at, updated_at
          FROM annotations WHERE pdf_path = ?1 ORDER BY start_page, start_word",
     )?;
 
@@ -224,8 +401,10 @@ pub fn load_annotations_for_pdf(pdf_path: &str) -> Result<Vec<Annotation>, Annot
                 end_word: row.get::<_, i64>(5)? as usize,
                 selected_text: row.get(6)?,
                 note: row.get(7)?,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
+                color: AnnotationColor::from_name(&row.get::<_, String>(8)?),
+                tags: parse_tags(&row.get::<_, String>(9)?),
+                created_at: row.get(10)?,
+                updated_at: row.get(11)?,
             })
         })?
         .filter_map(|r| r.ok())
@@ -236,10 +415,10 @@ pub fn load_annotations_for_pdf(pdf_path: &str) -> Result<Vec<Annotation>, Annot
 
 /// Get a single annotation by ID
 pub fn get_annotation(id: i64) -> Result<Annotation, AnnotationError> {
-    let conn = open_db()?;
+    let conn = database::open()?;
 
     conn.query_row(
-        "SELECT id, pdf_path, start_page, start_word, end_page, end_word, selected_text, note, created_at, updated_at
+        "SELECT id, pdf_path, start_page, start_word, end_page, end_word, selected_text, note, color, tags, created_at, updated_at
          FROM annotations WHERE id = ?1",
         params![id],
         |row| {
@@ -252,8 +431,10 @@ pub fn get_annotation(id: i64) -> Result<Annotation, AnnotationError> {
                 end_word: row.get::<_, i64>(5)? as usize,
                 selected_text: row.get(6)?,
                 note: row.get(7)?,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
+                color: AnnotationColor::from_name(&row.get::<_, String>(8)?),
+                tags: parse_tags(&row.get::<_, String>(9)?),
+                created_at: row.get(10)?,
+                updated_at: row.get(11)?,
             })
         },
     )
@@ -263,42 +444,198 @@ pub fn get_annotation(id: i64) -> Result<Annotation, AnnotationError> {
     })
 }
 
-pub fn find_prev_annotation_at_position(
-    pdf_path: &str,
-    page_index: usize,
-    word_index: usize,
-) -> Result<Option<Annotation>, AnnotationError> {
-    let annotations = load_annotations_for_pdf(pdf_path)?;
+/// Number of annotations starting on each page of a document, as (page, count)
+/// pairs for the pages that have any
+pub fn annotation_counts_by_page(pdf_path: &str) -> Result<Vec<(usize, usize)>, AnnotationError> {
+    let conn = database::open()?;
 
-    for ann in annotations.into_iter().rev() {
-        let pos = (page_index, word_index);
-        let end = (ann.end_page, ann.end_word);
-        // If annotations come ordered from start to finish it would work find
-        if pos > end {
-            return Ok(Some(ann));
-        }
-    }
+    let mut stmt = conn.prepare(
+        "SELECT start_page, COUNT(*) FROM annotations WHERE pdf_path = ?1
+         GROUP BY start_page ORDER BY start_page",
+    )?;
+    let counts = stmt
+        .query_map(params![pdf_path], |row| {
+            Ok((
+                row.get::<_, i64>(0)? as usize,
+                row.get::<_, i64>(1)? as usize,
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
 
-    Ok(None)
+    Ok(counts)
 }
 
-pub fn find_next_annotation_at_position(
-    pdf_path: &str,
-    page_index: usize,
-    word_index: usize,
-) -> Result<Option<Annotation>, AnnotationError> {
-    let annotations = load_annotations_for_pdf(pdf_path)?;
+/// Paths of every document with at least one annotation, sorted
+pub fn annotated_documents() -> Result<Vec<String>, AnnotationError> {
+    let conn = database::open()?;
 
-    for ann in annotations {
-        let pos = (page_index, word_index);
-        let start = (ann.start_page, ann.start_word);
-        // If annotations come ordered from start to finish it would work find
-        if pos < start {
-            return Ok(Some(ann));
+    let mut stmt = conn.prepare("SELECT DISTINCT pdf_path FROM annotations ORDER BY pdf_path")?;
+    let paths = stmt
+        .query_map([], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(paths)
+}
+
+/// Number of annotations in every document
+pub fn annotation_count() -> Result<usize, AnnotationError> {
+    let conn = database::open()?;
+
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM annotations", [], |row| row.get(0))?;
+
+    Ok(count as usize)
+}
+
+/// Default name of the markdown file the annotations of `pdf_path` are exported to
+pub fn export_file_name(pdf_path: &str) -> String {
+    let pdf_name = std::path::Path::new(pdf_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("annotations");
+    format!("{}_annotations.md", pdf_name)
+}
+
+/// Suggested name of the copy of a PDF with its annotations written into it
+pub fn annotated_pdf_file_name(pdf_path: &str) -> String {
+    let pdf_name = Path::new(pdf_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("document");
+    format!("{}_annotated.pdf", pdf_name)
+}
+
+/// An annotation as written into a PDF, on one of its pages
+#[derive(Debug, Clone)]
+pub struct PdfHighlight {
+    pub page_index: usize,
+    /// One rect per line, in the coordinate space of `PageChar` bounds
+    pub rects: Vec<PdfRect>,
+    /// The note, only on the first page of annotations spanning several
+    pub note: String,
+    pub color: AnnotationColor,
+}
+
+/// Save a copy of the PDF at `source` to `output` with `highlights` embedded as
+/// highlight annotations, their notes as the annotations' contents, so other PDF
+/// readers show them. Returns how many annotations were written.
+pub fn write_highlights_to_pdf(
+    pdfium: &Pdfium,
+    source: &Path,
+    output: &Path,
+    highlights: &[PdfHighlight],
+) -> Result<usize, AnnotationError> {
+    // pdfium reads the source lazily, it can't be overwritten while open
+    if source == output {
+        return Err(AnnotationError::PdfError(
+            "Choose another file than the document itself".to_string(),
+        ));
+    }
+
+    let document = pdfium.load_pdf_from_file(source, None)?;
+    let mut written = 0;
+
+    for highlight in highlights.iter().filter(|h| !h.rects.is_empty()) {
+        let Ok(page_index) = u16::try_from(highlight.page_index) else {
+            continue;
+        };
+        let mut page = document.pages().get(page_index)?;
+        let rects = PdfiumBackend::to_page_space(&page, &highlight.rects);
+
+        let mut annotation = page.annotations_mut().create_highlight_annotation()?;
+        annotation.set_bounds(bounding_rect(&rects))?;
+        for rect in &rects {
+            annotation
+                .attachment_points_mut()
+                .create_attachment_point_at_end(PdfQuadPoints::from_rect(rect))?;
+        }
+        annotation.set_stroke_color(highlight.color.pdf_color())?;
+        annotation.set_creator("Eyers")?;
+        if !highlight.note.is_empty() {
+            annotation.set_contents(&highlight.note)?;
         }
+        written += 1;
     }
 
-    Ok(None)
+    document.save_to_file(output)?;
+    Ok(written)
+}
+
+/// Smallest rect containing all of `rects`
+fn bounding_rect(rects: &[PdfRect]) -> PdfRect {
+    let (mut bottom, mut left, mut top, mut right) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for rect in rects {
+        bottom = bottom.min(rect.bottom().value);
+        left = left.min(rect.left().value);
+        top = top.max(rect.top().value);
+        right = right.max(rect.right().value);
+    }
+    PdfRect::new_from_values(bottom, left, top, right)
+}
+
+/// Export file names for several documents, numbered when documents in different
+/// directories share a name so no export overwrites another
+pub fn unique_export_file_names(pdf_paths: &[String]) -> Vec<String> {
+    let mut used = std::collections::HashSet::new();
+    pdf_paths
+        .iter()
+        .map(|path| {
+            let name = export_file_name(path);
+            let stem = name.trim_end_matches(".md").to_string();
+            let mut candidate = name;
+            let mut number = 2;
+            while !used.insert(candidate.clone()) {
+                candidate = format!("{}_{}.md", stem, number);
+                number += 1;
+            }
+            candidate
+        })
+        .collect()
+}
+
+/// Name the annotations of `pdf_path` get in an export of the whole library, so
+/// live-synced files and `unique_export_file_names` agree
+pub fn library_export_file_name(pdf_path: &str) -> Result<String, AnnotationError> {
+    let pdf_paths = annotated_documents()?;
+    let name = pdf_paths
+        .iter()
+        .zip(unique_export_file_names(&pdf_paths))
+        .find(|(path, _)| path.as_str() == pdf_path)
+        .map(|(_, name)| name)
+        .unwrap_or_else(|| export_file_name(pdf_path));
+    Ok(name)
+}
+
+/// Last annotation starting before `position`, in annotations sorted by start
+/// position as `load_annotations_for_pdf` returns them. With `wrap`, the last
+/// annotation of the document when none starts before it.
+pub fn prev_annotation(
+    annotations: &[Annotation],
+    position: (usize, usize),
+    wrap: bool,
+) -> Option<&Annotation> {
+    let before = annotations.partition_point(|ann| (ann.start_page, ann.start_word) < position);
+
+    before
+        .checked_sub(1)
+        .and_then(|index| annotations.get(index))
+        .or_else(|| annotations.last().filter(|_| wrap))
+}
+
+/// First annotation starting after `position`, in annotations sorted by start
+/// position. With `wrap`, the first annotation of the document when none starts
+/// after it.
+pub fn next_annotation(
+    annotations: &[Annotation],
+    position: (usize, usize),
+    wrap: bool,
+) -> Option<&Annotation> {
+    let after = annotations.partition_point(|ann| (ann.start_page, ann.start_word) <= position);
+
+    annotations
+        .get(after)
+        .or_else(|| annotations.first().filter(|_| wrap))
 }
 
 /// Find an annotation that contains a specific word position
@@ -363,49 +700,283 @@ fn ranges_overlap(
     ann_start <= sel_end && sel_start <= ann_end
 }
 
-/// Export annotations for a PDF to markdown format
+/// Order of annotations inside an exported document (or inside each chapter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportSort {
+    /// Document order
+    #[default]
+    Position,
+    /// Oldest annotation first
+    CreatedAt,
+}
+
+/// Options controlling the markdown export
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    /// Group annotations under the heading of the chapter they belong to
+    pub group_by_chapter: bool,
+    /// Group annotations under a heading per tag instead, taking precedence over
+    /// chapters. An annotation with several tags is listed under each.
+    pub group_by_tag: bool,
+    pub sort: ExportSort,
+    /// Only the annotations added or edited at or after this Unix time, all of
+    /// them when None
+    pub since: Option<i64>,
+}
+
+/// Export annotations for a PDF to markdown format, with the document's BibTeX
+/// `citation` under the title when one was fetched.
 /// Each annotation is formatted as:
 /// > "highlighted text" (Page X)
 ///
 /// User's note
-pub fn export_to_markdown(pdf_path: &str, pdf_name: &str) -> Result<String, AnnotationError> {
+pub fn export_to_markdown(
+    pdf_path: &str,
+    pdf_name: &str,
+    citation: Option<&str>,
+    chapters: &[BookmarkEntry],
+    options: &ExportOptions,
+) -> Result<String, AnnotationError> {
     let annotations = load_annotations_for_pdf(pdf_path)?;
+    Ok(format_markdown(
+        annotations,
+        pdf_name,
+        citation,
+        chapters,
+        options,
+    ))
+}
+
+/// Unix time the annotations of `pdf_path` were last exported to markdown
+pub fn last_export(pdf_path: &str) -> Result<Option<i64>, AnnotationError> {
+    let conn = database::open()?;
+    Ok(conn
+        .query_row(
+            "SELECT exported_at FROM exports WHERE pdf_path = ?1",
+            params![pdf_path],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Remember that the annotations of `pdf_path` were exported at `exported_at`
+pub fn record_export(pdf_path: &str, exported_at: i64) -> Result<(), AnnotationError> {
+    database::write(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO exports (pdf_path, exported_at) VALUES (?1, ?2)",
+            params![pdf_path, exported_at],
+        )?;
+        Ok(())
+    })
+    .map_err(AnnotationError::from)
+}
+
+fn format_markdown(
+    mut annotations: Vec<Annotation>,
+    pdf_name: &str,
+    citation: Option<&str>,
+    chapters: &[BookmarkEntry],
+    options: &ExportOptions,
+) -> String {
+    let mut output = format!("# Annotations for {}\n\n", pdf_name);
+    if let Some(citation) = citation {
+        output.push_str(&format!("```bibtex\n{}\n```\n\n", citation.trim()));
+    }
+
+    if let Some(since) = options.since {
+        annotations.retain(|ann| ann.changed_since(since));
+    }
 
     if annotations.is_empty() {
-        return Ok(format!(
-            "# Annotations for {}\n\nNo annotations found.\n",
-            pdf_name
-        ));
+        output.push_str("No annotations found.\n");
+        return output;
     }
 
-    let mut output = format!("# Annotations for {}\n\n", pdf_name);
+    if options.group_by_tag {
+        push_tag_groups(&mut output, annotations, options.sort);
+        return output;
+    }
 
+    let chapters = flatten_chapters(chapters);
+    if !options.group_by_chapter || chapters.is_empty() {
+        sort_annotations(&mut annotations, options.sort);
+        for ann in &annotations {
+            push_annotation(&mut output, ann);
+        }
+        return output;
+    }
+
+    // Annotations before the first chapter go into a group without heading
+    let mut groups: Vec<(Option<usize>, Vec<Annotation>)> = Vec::new();
     for ann in annotations {
-        // Page number is 1-indexed for display
-        let page_num = ann.start_page + 1;
+        let chapter_idx = chapters
+            .iter()
+            .rposition(|c| c.page_index as usize <= ann.start_page);
 
-        // Quote the highlighted text
-        output.push_str(&format!(
-            "> **\"{}\"** (Page {})\n\n",
-            ann.selected_text, page_num
-        ));
+        match groups.last_mut() {
+            Some((idx, anns)) if *idx == chapter_idx => anns.push(ann),
+            _ => groups.push((chapter_idx, vec![ann])),
+        }
+    }
+
+    for (chapter_idx, mut anns) in groups {
+        if let Some(chapter) = chapter_idx.map(|idx| chapters[idx]) {
+            // Top level chapters are `##`, nested sections go one level deeper each
+            let level = "#".repeat((chapter.depth + 2).min(6));
+            output.push_str(&format!("{} {}\n\n", level, chapter.title));
+        }
 
-        // Add the user's note
-        if !ann.note.is_empty() {
-            output.push_str(&ann.note);
-            output.push_str("\n\n");
+        sort_annotations(&mut anns, options.sort);
+        for ann in &anns {
+            push_annotation(&mut output, ann);
         }
+    }
+
+    output
+}
 
-        output.push_str("---\n\n");
+/// Annotations under a `##` heading per tag, in tag order, then the untagged ones
+fn push_tag_groups(output: &mut String, mut annotations: Vec<Annotation>, sort: ExportSort) {
+    sort_annotations(&mut annotations, sort);
+
+    for tag in all_tags(&annotations) {
+        output.push_str(&format!("## {}\n\n", tag));
+        for ann in annotations
+            .iter()
+            .filter(|ann| ann.tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)))
+        {
+            push_annotation(output, ann);
+        }
     }
 
-    Ok(output)
+    let untagged: Vec<&Annotation> = annotations
+        .iter()
+        .filter(|ann| ann.tags.is_empty())
+        .collect();
+    if !untagged.is_empty() {
+        output.push_str("## Untagged\n\n");
+        for ann in untagged {
+            push_annotation(output, ann);
+        }
+    }
+}
+
+/// Flatten the bookmark tree into a list ordered by page (then by outline order)
+fn flatten_chapters(chapters: &[BookmarkEntry]) -> Vec<&BookmarkEntry> {
+    fn walk<'a>(entries: &'a [BookmarkEntry], out: &mut Vec<&'a BookmarkEntry>) {
+        for entry in entries {
+            out.push(entry);
+            walk(&entry.children, out);
+        }
+    }
+
+    let mut flat = Vec::new();
+    walk(chapters, &mut flat);
+    flat.sort_by_key(|c| c.page_index);
+    flat
+}
+
+fn sort_annotations(annotations: &mut [Annotation], sort: ExportSort) {
+    match sort {
+        ExportSort::Position => {
+            annotations.sort_by_key(|a| (a.start_page, a.start_word));
+        }
+        ExportSort::CreatedAt => {
+            annotations.sort_by_key(|a| a.created_at);
+        }
+    }
+}
+
+fn push_annotation(output: &mut String, ann: &Annotation) {
+    // Page number is 1-indexed for display
+    let page_num = ann.start_page + 1;
+
+    // Quote the highlighted text
+    output.push_str(&format!(
+        "> **\"{}\"** (Page {})\n\n",
+        ann.selected_text, page_num
+    ));
+
+    if !ann.tags.is_empty() {
+        let tags: Vec<String> = ann.tags.iter().map(|tag| format!("`{}`", tag)).collect();
+        output.push_str(&format!("Tags: {}\n\n", tags.join(" ")));
+    }
+
+    // Add the user's note
+    if !ann.note.is_empty() {
+        output.push_str(&ann.note);
+        output.push_str("\n\n");
+    }
+
+    output.push_str("---\n\n");
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_annotated_pdf_file_name() {
+        assert_eq!(
+            annotated_pdf_file_name("/home/me/paper.pdf"),
+            "paper_annotated.pdf"
+        );
+    }
+
+    #[test]
+    fn test_bounding_rect() {
+        let rect = bounding_rect(&[
+            PdfRect::new_from_values(10.0, 20.0, 20.0, 80.0),
+            PdfRect::new_from_values(0.0, 5.0, 8.0, 60.0),
+        ]);
+        assert_eq!(
+            (
+                rect.bottom().value,
+                rect.left().value,
+                rect.top().value,
+                rect.right().value
+            ),
+            (0.0, 5.0, 20.0, 80.0)
+        );
+    }
+
+    #[test]
+    fn test_in_memory_database() {
+        // The only test touching the database, so it can't disturb others
+        database::set_location(database::DatabaseLocation::Memory);
+
+        let id = save_annotation(
+            "/docs/book.pdf",
+            2,
+            5,
+            2,
+            9,
+            "some words",
+            "a note",
+            AnnotationColor::Green,
+            &["method".to_string()],
+        )
+        .unwrap();
+        // Another connection sees the same database
+        let annotations = load_annotations_for_pdf("/docs/book.pdf").unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].id, id);
+        assert_eq!(annotations[0].note, "a note");
+        assert_eq!(annotations[0].color, AnnotationColor::Green);
+        assert_eq!(annotations[0].tags, vec!["method".to_string()]);
+
+        delete_annotation(id).unwrap();
+        assert!(
+            load_annotations_for_pdf("/docs/book.pdf")
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            delete_annotation(id),
+            Err(AnnotationError::NotFound)
+        ));
+    }
+
     #[test]
     fn test_is_position_in_annotation() {
         let ann = Annotation {
@@ -417,6 +988,8 @@ mod tests {
             end_word: 10,
             selected_text: "test".to_string(),
             note: "note".to_string(),
+            color: AnnotationColor::Yellow,
+            tags: Vec::new(),
             created_at: 0,
             updated_at: 0,
         };
@@ -433,6 +1006,61 @@ mod tests {
         assert!(!is_position_in_annotation(&ann, 0, 11));
     }
 
+    #[test]
+    fn test_annotation_color_names() {
+        for color in AnnotationColor::ALL {
+            assert_eq!(AnnotationColor::from_name(color.as_str()), color);
+        }
+        assert_eq!(
+            AnnotationColor::from_name("purple"),
+            AnnotationColor::Yellow
+        );
+    }
+
+    #[test]
+    fn test_unique_export_file_names() {
+        let paths = vec![
+            "/books/paper.pdf".to_string(),
+            "/papers/paper.pdf".to_string(),
+            "/books/novel.pdf".to_string(),
+        ];
+        assert_eq!(
+            unique_export_file_names(&paths),
+            vec![
+                "paper_annotations.md",
+                "paper_annotations_2.md",
+                "novel_annotations.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_next_and_prev_annotation() {
+        let ann = |id: i64, page: usize, word: usize| Annotation {
+            id,
+            start_page: page,
+            start_word: word,
+            end_page: page,
+            end_word: word + 3,
+            ..Default::default()
+        };
+        let annotations = vec![ann(1, 0, 5), ann(2, 2, 0), ann(3, 2, 8)];
+        let id = |ann: Option<&Annotation>| ann.map(|ann| ann.id);
+
+        assert_eq!(id(next_annotation(&annotations, (0, 0), false)), Some(1));
+        // The annotation the cursor is on is skipped
+        assert_eq!(id(next_annotation(&annotations, (2, 0), false)), Some(3));
+        assert_eq!(id(next_annotation(&annotations, (2, 8), false)), None);
+        assert_eq!(id(next_annotation(&annotations, (2, 8), true)), Some(1));
+
+        assert_eq!(id(prev_annotation(&annotations, (2, 8), false)), Some(2));
+        assert_eq!(id(prev_annotation(&annotations, (2, 5), false)), Some(2));
+        assert_eq!(id(prev_annotation(&annotations, (0, 5), false)), None);
+        assert_eq!(id(prev_annotation(&annotations, (0, 5), true)), Some(3));
+
+        assert_eq!(next_annotation(&[], (0, 0), true), None);
+    }
+
     #[test]
     fn test_ranges_overlap() {
         let ann = Annotation {
@@ -444,6 +1072,8 @@ mod tests {
             end_word: 10,
             selected_text: "test".to_string(),
             note: "note".to_string(),
+            color: AnnotationColor::Yellow,
+            tags: Vec::new(),
             created_at: 0,
             updated_at: 0,
         };
@@ -457,4 +1087,166 @@ mod tests {
         // No overlap (after)
         assert!(!ranges_overlap(&ann, 0, 11, 0, 15));
     }
+
+    #[test]
+    fn test_export_grouped_by_chapter() {
+        let ann = |id: i64, page: usize, created_at: i64| Annotation {
+            id,
+            start_page: page,
+            end_page: page,
+            selected_text: format!("text {}", id),
+            created_at,
+            ..Default::default()
+        };
+        let chapter = |title: &str, page_index: u16| BookmarkEntry {
+            title: title.to_string(),
+            page_index,
+            children: Vec::new(),
+            depth: 0,
+        };
+
+        let annotations = vec![ann(1, 0, 30), ann(2, 3, 20), ann(3, 4, 10)];
+        let chapters = vec![chapter("One", 2), chapter("Two", 4)];
+        let options = ExportOptions {
+            group_by_chapter: true,
+            group_by_tag: false,
+            sort: ExportSort::Position,
+            since: None,
+        };
+
+        let output = format_markdown(annotations, "test.pdf", None, &chapters, &options);
+
+        let pos = |needle: &str| output.find(needle).unwrap();
+        assert!(pos("text 1") < pos("## One"));
+        assert!(pos("## One") < pos("text 2"));
+        assert!(pos("text 2") < pos("## Two"));
+        assert!(pos("## Two") < pos("text 3"));
+    }
+
+    #[test]
+    fn test_export_sorted_by_creation_date() {
+        let ann = |id: i64, page: usize, created_at: i64| Annotation {
+            id,
+            start_page: page,
+            end_page: page,
+            selected_text: format!("text {}", id),
+            created_at,
+            ..Default::default()
+        };
+
+        let annotations = vec![ann(1, 0, 30), ann(2, 1, 10)];
+        let options = ExportOptions {
+            group_by_chapter: false,
+            group_by_tag: false,
+            sort: ExportSort::CreatedAt,
+            since: None,
+        };
+
+        let output = format_markdown(annotations, "test.pdf", None, &[], &options);
+
+        assert!(output.find("text 2").unwrap() < output.find("text 1").unwrap());
+    }
+
+    #[test]
+    fn test_export_since() {
+        let ann = |id: i64, updated_at: i64| Annotation {
+            id,
+            selected_text: format!("text {}", id),
+            created_at: 10,
+            updated_at,
+            ..Default::default()
+        };
+        let annotations = vec![ann(1, 10), ann(2, 50), ann(3, 100)];
+        assert_eq!(
+            annotations
+                .iter()
+                .filter(|ann| ann.changed_since(50))
+                .count(),
+            2
+        );
+
+        let options = ExportOptions {
+            since: Some(50),
+            ..Default::default()
+        };
+        let output = format_markdown(annotations, "test.pdf", None, &[], &options);
+        assert!(!output.contains("text 1"));
+        assert!(output.contains("text 2"));
+        assert!(output.contains("text 3"));
+
+        let output = format_markdown(vec![ann(1, 10)], "test.pdf", None, &[], &options);
+        assert!(output.ends_with("No annotations found.\n"));
+    }
+
+    #[test]
+    fn test_linked_ids() {
+        assert_eq!(link_key(7, 3), (3, 7));
+        assert_eq!(link_key(3, 7), (3, 7));
+
+        let ann = Annotation {
+            start_page: 4,
+            selected_text: "a\nvery long   highlight that goes on and on".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(ann.short_label(), "p. 5: a very long highlight that goe…");
+
+        let links = vec![(1, 2), (2, 5), (3, 4)];
+        assert_eq!(linked_ids(&links, 2), vec![1, 5]);
+        assert_eq!(linked_ids(&links, 4), vec![3]);
+        assert!(linked_ids(&links, 9).is_empty());
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(
+            parse_tags(" method, ,todo,Method , open question"),
+            vec!["method", "todo", "open question"]
+        );
+        assert!(parse_tags("").is_empty());
+        assert_eq!(join_tags(&parse_tags("a,b")), "a, b");
+    }
+
+    #[test]
+    fn test_export_grouped_by_tag() {
+        let ann = |id: i64, page: usize, tags: &str| Annotation {
+            id,
+            start_page: page,
+            end_page: page,
+            selected_text: format!("text {}", id),
+            tags: parse_tags(tags),
+            ..Default::default()
+        };
+
+        let annotations = vec![ann(1, 0, "todo"), ann(2, 1, ""), ann(3, 2, "method, Todo")];
+        let options = ExportOptions {
+            group_by_tag: true,
+            ..Default::default()
+        };
+
+        let output = format_markdown(annotations, "test.pdf", None, &[], &options);
+
+        let pos = |needle: &str| output.find(needle).unwrap();
+        assert!(pos("## method") < pos("text 3"));
+        assert!(pos("text 3") < pos("## todo"));
+        assert!(pos("## todo") < pos("text 1"));
+        assert!(pos("text 1") < output.rfind("text 3").unwrap());
+        assert!(pos("## Untagged") < pos("text 2"));
+        assert!(output.contains("Tags: `method` `Todo`"));
+    }
+
+    #[test]
+    fn test_export_with_citation() {
+        let output = format_markdown(
+            Vec::new(),
+            "test.pdf",
+            Some("@book{key,\n  title = {Title}\n}\n"),
+            &[],
+            &ExportOptions::default(),
+        );
+
+        assert_eq!(
+            output,
+            "# Annotations for test.pdf\n\n```bibtex\n@book{key,\n  title = {Title}\n}\n```\n\nNo annotations found.\n"
+        );
+    }
 }
@@ -1,5 +1,8 @@
 use pdfium_render::prelude::*;
 
+use std::collections::HashSet;
+use std::ops::Range;
+
 #[derive(Debug, Clone)]
 pub struct BookmarkEntry {
     pub title: String,
@@ -46,3 +49,111 @@ fn process_bookmark(bookmark: &PdfBookmark, depth: usize) -> Option<BookmarkEntr
         depth,
     })
 }
+
+/// How much of a chapter has been read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterProgress {
+    Unread,
+    Partial,
+    Done,
+}
+
+impl ChapterProgress {
+    /// Progress of the chapter spanning `pages` given the pages read so far
+    pub fn for_pages(pages: Range<usize>, read_pages: &HashSet<usize>) -> Self {
+        let total = pages.len();
+        let read = pages.filter(|page| read_pages.contains(page)).count();
+        match read {
+            0 => ChapterProgress::Unread,
+            read if read >= total => ChapterProgress::Done,
+            _ => ChapterProgress::Partial,
+        }
+    }
+}
+
+/// Page ranges of flattened chapters given as `(start page, depth)` in document
+/// order. A chapter ends where the next chapter at the same or a shallower depth
+/// starts, so a parent covers its sub-chapters. Every chapter spans at least its
+/// start page.
+pub fn chapter_ranges(chapters: &[(usize, usize)], page_count: usize) -> Vec<Range<usize>> {
+    chapters
+        .iter()
+        .enumerate()
+        .map(|(i, &(start, depth))| {
+            let end = chapters[i + 1..]
+                .iter()
+                .find(|(_, next_depth)| *next_depth <= depth)
+                .map_or(page_count, |(next_start, _)| *next_start);
+            start..end.max(start + 1)
+        })
+        .collect()
+}
+
+/// The innermost chapter `page_index` belongs to: the last outline entry, in
+/// document order, starting on or before it
+pub fn chapter_at(entries: &[BookmarkEntry], page_index: usize) -> Option<&BookmarkEntry> {
+    let mut found = None;
+    for entry in entries {
+        if entry.page_index as usize > page_index {
+            break;
+        }
+        found = Some(chapter_at(&entry.children, page_index).unwrap_or(entry));
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chapter_ranges() {
+        let chapters = [(0, 0), (2, 1), (4, 1), (6, 0), (6, 1)];
+        assert_eq!(
+            chapter_ranges(&chapters, 10),
+            vec![0..6, 2..4, 4..6, 6..10, 6..10]
+        );
+    }
+
+    #[test]
+    fn test_chapter_at() {
+        let entry = |title: &str, page_index, children| BookmarkEntry {
+            title: title.to_string(),
+            page_index,
+            children,
+            depth: 0,
+        };
+        let chapters = vec![
+            entry("Intro", 0, Vec::new()),
+            entry(
+                "Methods",
+                4,
+                vec![entry("Data", 5, Vec::new()), entry("Model", 8, Vec::new())],
+            ),
+        ];
+
+        let title = |page_index| chapter_at(&chapters, page_index).map(|c| c.title.as_str());
+        assert_eq!(title(2), Some("Intro"));
+        assert_eq!(title(4), Some("Methods"));
+        assert_eq!(title(6), Some("Data"));
+        assert_eq!(title(20), Some("Model"));
+        assert_eq!(chapter_at(&[], 3).map(|c| c.page_index), None);
+    }
+
+    #[test]
+    fn test_chapter_progress() {
+        let read: HashSet<usize> = [2, 3, 4].into_iter().collect();
+        assert_eq!(
+            ChapterProgress::for_pages(0..2, &read),
+            ChapterProgress::Unread
+        );
+        assert_eq!(
+            ChapterProgress::for_pages(2..4, &read),
+            ChapterProgress::Done
+        );
+        assert_eq!(
+            ChapterProgress::for_pages(4..6, &read),
+            ChapterProgress::Partial
+        );
+    }
+}
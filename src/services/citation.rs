@@ -0,0 +1,330 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const DOI_RESOLVER_URL: &str = "https://doi.org";
+const OPEN_LIBRARY_URL: &str = "https://openlibrary.org/api/books";
+const USER_AGENT: &str = concat!("eyers/", env!("CARGO_PKG_VERSION"));
+
+/// Identifier a citation can be fetched for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Identifier {
+    Doi(String),
+    /// ISBN digits without separators
+    Isbn(String),
+}
+
+impl std::fmt::Display for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Identifier::Doi(doi) => write!(f, "DOI {}", doi),
+            Identifier::Isbn(isbn) => write!(f, "ISBN {}", isbn),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CitationError {
+    NotFound,
+    RequestFailed(String),
+    ParseFailed(String),
+}
+
+impl std::fmt::Display for CitationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CitationError::NotFound => write!(f, "No citation found"),
+            CitationError::RequestFailed(msg) => write!(f, "Request failed: {}", msg),
+            CitationError::ParseFailed(msg) => write!(f, "Parse failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CitationError {}
+
+/// Find the first DOI or ISBN in `texts`, searched in order. Pass the document
+/// metadata before the page text, since references at the end of a paper cite
+/// other works.
+pub fn find_identifier<'a>(texts: impl IntoIterator<Item = &'a str>) -> Option<Identifier> {
+    texts
+        .into_iter()
+        .find_map(|text| find_doi(text).or_else(|| find_isbn(text)))
+}
+
+/// DOIs look like `10.<4-9 digit registrant>/<suffix>`
+fn find_doi(text: &str) -> Option<Identifier> {
+    for (start, _) in text.match_indices("10.") {
+        // Skip numbers that merely contain "10."
+        if text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+        {
+            continue;
+        }
+
+        let rest = &text[start + 3..];
+        let registrant_len = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        if !(4..=9).contains(&registrant_len) || !rest[registrant_len..].starts_with('/') {
+            continue;
+        }
+
+        let suffix_start = start + 3 + registrant_len + 1;
+        let suffix_len: usize = text[suffix_start..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '<' | '>'))
+            .map(char::len_utf8)
+            .sum();
+        let doi = text[start..suffix_start + suffix_len]
+            .trim_end_matches(['.', ',', ';', ':', ')', ']', '}', '\'']);
+        if doi.len() > suffix_start - start {
+            return Some(Identifier::Doi(doi.to_string()));
+        }
+    }
+    None
+}
+
+/// ISBNs are only trusted after an "ISBN" label and with a valid check digit
+fn find_isbn(text: &str) -> Option<Identifier> {
+    let upper = text.to_ascii_uppercase();
+    for (start, _) in upper.match_indices("ISBN") {
+        let mut rest = upper[start + 4..].trim_start_matches(['-', ' ']);
+        for label in ["13", "10"] {
+            if let Some(after) = rest.strip_prefix(label) {
+                if after.starts_with([':', ' ']) {
+                    rest = after;
+                }
+            }
+        }
+        let rest = rest.trim_start_matches([':', ' ']);
+
+        let digits: String = rest
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || matches!(c, '-' | ' ' | 'X'))
+            .filter(|c| *c != '-' && *c != ' ')
+            .take(13)
+            .collect();
+
+        if digits.len() == 13 && is_valid_isbn13(&digits) {
+            return Some(Identifier::Isbn(digits));
+        }
+        if digits.len() >= 10 && is_valid_isbn10(&digits[..10]) {
+            return Some(Identifier::Isbn(digits[..10].to_string()));
+        }
+    }
+    None
+}
+
+fn is_valid_isbn13(digits: &str) -> bool {
+    let values: Option<Vec<u32>> = digits.chars().map(|c| c.to_digit(10)).collect();
+    let Some(values) = values else {
+        return false;
+    };
+    let sum: u32 = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| if i % 2 == 0 { *v } else { v * 3 })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn is_valid_isbn10(digits: &str) -> bool {
+    let mut sum = 0;
+    for (i, c) in digits.chars().enumerate() {
+        let value = match c {
+            'X' if i == 9 => 10,
+            _ => match c.to_digit(10) {
+                Some(v) => v,
+                None => return false,
+            },
+        };
+        sum += value * (10 - i as u32);
+    }
+    sum % 11 == 0
+}
+
+#[derive(Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct OpenLibraryBook {
+    title: String,
+    #[serde(default)]
+    authors: Vec<Named>,
+    #[serde(default)]
+    publishers: Vec<Named>,
+    publish_date: Option<String>,
+}
+
+/// Fetch a BibTeX entry: DOIs through doi.org content negotiation, ISBNs through
+/// Open Library
+pub fn fetch_bibtex(identifier: &Identifier) -> Result<String, CitationError> {
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| CitationError::RequestFailed(e.to_string()))?;
+
+    match identifier {
+        Identifier::Doi(doi) => {
+            let response = client
+                .get(format!("{}/{}", DOI_RESOLVER_URL, doi))
+                .header("Accept", "application/x-bibtex")
+                .send()
+                .map_err(|e| CitationError::RequestFailed(e.to_string()))?;
+
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Err(CitationError::NotFound);
+            }
+            if !response.status().is_success() {
+                return Err(CitationError::RequestFailed(format!(
+                    "Status: {}",
+                    response.status()
+                )));
+            }
+
+            let bibtex = response
+                .text()
+                .map_err(|e| CitationError::ParseFailed(e.to_string()))?;
+            Ok(bibtex.trim().to_string())
+        }
+        Identifier::Isbn(isbn) => {
+            let key = format!("ISBN:{}", isbn);
+            let response = client
+                .get(OPEN_LIBRARY_URL)
+                .query(&[
+                    ("bibkeys", key.as_str()),
+                    ("format", "json"),
+                    ("jscmd", "data"),
+                ])
+                .send()
+                .map_err(|e| CitationError::RequestFailed(e.to_string()))?;
+
+            if !response.status().is_success() {
+                return Err(CitationError::RequestFailed(format!(
+                    "Status: {}",
+                    response.status()
+                )));
+            }
+
+            let mut books: HashMap<String, OpenLibraryBook> = response
+                .json()
+                .map_err(|e| CitationError::ParseFailed(e.to_string()))?;
+            let book = books.remove(&key).ok_or(CitationError::NotFound)?;
+            Ok(format_book_bibtex(&book, isbn))
+        }
+    }
+}
+
+fn format_book_bibtex(book: &OpenLibraryBook, isbn: &str) -> String {
+    let year = book.publish_date.as_deref().and_then(find_year);
+    let first_author_surname = book
+        .authors
+        .first()
+        .and_then(|a| a.name.split_whitespace().last())
+        .map(|name| {
+            name.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|name| !name.is_empty());
+
+    let key = match (first_author_surname, year) {
+        (Some(name), Some(year)) => format!("{}{}", name, year),
+        (Some(name), None) => name,
+        _ => format!("isbn{}", isbn),
+    };
+
+    let mut fields = vec![("title", book.title.clone())];
+    if !book.authors.is_empty() {
+        let authors: Vec<&str> = book.authors.iter().map(|a| a.name.as_str()).collect();
+        fields.push(("author", authors.join(" and ")));
+    }
+    if let Some(publisher) = book.publishers.first() {
+        fields.push(("publisher", publisher.name.clone()));
+    }
+    if let Some(year) = year {
+        fields.push(("year", year.to_string()));
+    }
+    fields.push(("isbn", isbn.to_string()));
+
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(name, value)| format!("  {} = {{{}}}", name, value))
+        .collect();
+    format!("@book{{{},\n{}\n}}", key, body.join(",\n"))
+}
+
+/// First four digit number in a free-form date such as "March 3, 2009"
+fn find_year(date: &str) -> Option<&str> {
+    date.split(|c: char| !c.is_ascii_digit())
+        .find(|part| part.len() == 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_doi() {
+        assert_eq!(
+            find_identifier(["Available at https://doi.org/10.1145/3368089.3409704."]),
+            Some(Identifier::Doi("10.1145/3368089.3409704".to_string()))
+        );
+        assert_eq!(find_identifier(["version 110.1234/abc"]), None);
+        assert_eq!(find_identifier(["10.12/abc"]), None);
+    }
+
+    #[test]
+    fn test_find_isbn() {
+        assert_eq!(
+            find_identifier(["ISBN-13: 978-0-306-40615-7"]),
+            Some(Identifier::Isbn("9780306406157".to_string()))
+        );
+        assert_eq!(
+            find_identifier(["isbn 0-306-40615-2"]),
+            Some(Identifier::Isbn("0306406152".to_string()))
+        );
+        // Bad check digit
+        assert_eq!(find_identifier(["ISBN 978-0-306-40615-8"]), None);
+    }
+
+    #[test]
+    fn test_metadata_searched_first() {
+        assert_eq!(
+            find_identifier(["doi:10.1000/meta", "see 10.1000/body"]),
+            Some(Identifier::Doi("10.1000/meta".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_format_book_bibtex() {
+        let book = OpenLibraryBook {
+            title: "Structure and Interpretation of Computer Programs".to_string(),
+            authors: vec![
+                Named {
+                    name: "Harold Abelson".to_string(),
+                },
+                Named {
+                    name: "Gerald Jay Sussman".to_string(),
+                },
+            ],
+            publishers: vec![Named {
+                name: "MIT Press".to_string(),
+            }],
+            publish_date: Some("July 25, 1996".to_string()),
+        };
+
+        assert_eq!(
+            format_book_bibtex(&book, "0262510871"),
+            "@book{abelson1996,\n  \
+             title = {Structure and Interpretation of Computer Programs},\n  \
+             author = {Harold Abelson and Gerald Jay Sussman},\n  \
+             publisher = {MIT Press},\n  \
+             year = {1996},\n  \
+             isbn = {0262510871}\n}"
+        );
+    }
+}
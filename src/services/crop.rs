@@ -0,0 +1,240 @@
+use crate::backend::RenderedPage;
+
+/// Darkest channel value still counted as paper when looking for the content
+const INK_THRESHOLD: u8 = 200;
+/// Share of a row or column that must be ink for it to count as content, so
+/// specks on scans don't stop the crop
+const MIN_INK_SHARE: f64 = 0.002;
+/// Blank space kept around the content found on a page, in points
+const CROP_PADDING_PTS: f64 = 8.0;
+/// Smallest part of a page a crop leaves, in points
+const MIN_CROPPED_PTS: f64 = 36.0;
+
+/// Edge of the crop box moved in the crop adjustment mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CropEdge {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+/// Margins cut off a page before it is shown, in points of the unrotated page
+/// measured inward from each edge. Points are in screen orientation (y grows
+/// downward) relative to the top-left corner of the page.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PageCrop {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl PageCrop {
+    pub const NONE: PageCrop = PageCrop {
+        left: 0.0,
+        top: 0.0,
+        right: 0.0,
+        bottom: 0.0,
+    };
+
+    /// Size of a `(width, height)` page once cropped
+    pub fn size(self, (width, height): (f64, f64)) -> (f64, f64) {
+        (
+            (width - self.left - self.right).max(1.0),
+            (height - self.top - self.bottom).max(1.0),
+        )
+    }
+
+    /// Point of the whole page, at `scale` pixels per point, shown at `(x, y)` on
+    /// the cropped one
+    pub fn to_page(self, (x, y): (f64, f64), scale: f64) -> (f64, f64) {
+        (x + self.left * scale, y + self.top * scale)
+    }
+
+    /// Where a point of the whole page, at `scale` pixels per point, is shown on
+    /// the cropped one
+    pub fn to_cropped(self, (x, y): (f64, f64), scale: f64) -> (f64, f64) {
+        (x - self.left * scale, y - self.top * scale)
+    }
+
+    /// `(x, y, width, height)` of the pixels kept of a page rendered
+    /// `width` x `height` at `scale` pixels per point. At least one pixel is kept.
+    pub fn pixel_rect(self, (width, height): (i32, i32), scale: f64) -> (i32, i32, i32, i32) {
+        let pixels = |points: f64| (points * scale).round().max(0.0) as i32;
+        let x = pixels(self.left).min(width - 1).max(0);
+        let y = pixels(self.top).min(height - 1).max(0);
+        let right = pixels(self.right).min(width - x - 1).max(0);
+        let bottom = pixels(self.bottom).min(height - y - 1).max(0);
+        (
+            x,
+            y,
+            (width - x - right).max(1),
+            (height - y - bottom).max(1),
+        )
+    }
+
+    /// Cut the margins off the pixels of a page rendered at `scale` pixels per point
+    pub fn crop_page(self, page: RenderedPage, scale: f64) -> RenderedPage {
+        if self == PageCrop::NONE {
+            return page;
+        }
+
+        let (x, y, width, height) = self.pixel_rect((page.width, page.height), scale);
+        let stride = width as usize * 4;
+        let mut pixels = Vec::with_capacity(stride * height as usize);
+        for row in y as usize..(y + height) as usize {
+            let start = row * page.stride + x as usize * 4;
+            pixels.extend_from_slice(&page.pixels[start..start + stride]);
+        }
+
+        RenderedPage {
+            width,
+            height,
+            stride,
+            pixels,
+        }
+    }
+
+    /// Margins around the content of a page rendered at `scale` pixels per
+    /// point, found in its pixels, with a little blank space left around it.
+    /// Blank pages are not cropped.
+    pub fn detect(page: &RenderedPage, scale: f64) -> PageCrop {
+        let (width, height) = (page.width.max(0) as usize, page.height.max(0) as usize);
+        let mut column_ink = vec![0usize; width];
+        let mut row_ink = vec![0usize; height];
+        for (y, row_count) in row_ink.iter_mut().enumerate() {
+            let row = &page.pixels[y * page.stride..y * page.stride + width * 4];
+            for (x, pixel) in row.chunks_exact(4).enumerate() {
+                if pixel[..3].iter().any(|&channel| channel < INK_THRESHOLD) {
+                    column_ink[x] += 1;
+                    *row_count += 1;
+                }
+            }
+        }
+
+        // First and past-the-last rows or columns holding content
+        let content = |counts: &[usize], length: usize| {
+            let min = ((length as f64 * MIN_INK_SHARE).ceil() as usize).max(1);
+            let first = counts.iter().position(|&count| count >= min)?;
+            let last = counts.iter().rposition(|&count| count >= min)?;
+            Some((first, last + 1))
+        };
+        let (Some((left, right)), Some((top, bottom))) =
+            (content(&column_ink, height), content(&row_ink, width))
+        else {
+            return PageCrop::NONE;
+        };
+
+        let margin = |pixels: usize| (pixels as f64 / scale - CROP_PADDING_PTS).max(0.0);
+        PageCrop {
+            left: margin(left),
+            top: margin(top),
+            right: margin(width - right),
+            bottom: margin(height - bottom),
+        }
+    }
+
+    /// The crop with `edge` moved `points` outward (negative) or inward
+    /// (positive), keeping some of a `(width, height)` page shown
+    pub fn adjusted(self, edge: CropEdge, points: f64, (width, height): (f64, f64)) -> PageCrop {
+        let mut crop = self;
+        let (margin, room) = match edge {
+            CropEdge::Left => (&mut crop.left, width - self.right),
+            CropEdge::Right => (&mut crop.right, width - self.left),
+            CropEdge::Top => (&mut crop.top, height - self.bottom),
+            CropEdge::Bottom => (&mut crop.bottom, height - self.top),
+        };
+        *margin = (*margin + points).min(room - MIN_CROPPED_PTS).max(0.0);
+        crop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White page of `width` x `height` pixels with a black rect
+    fn page_with_block(
+        width: i32,
+        height: i32,
+        (x, y, w, h): (i32, i32, i32, i32),
+    ) -> RenderedPage {
+        let stride = width as usize * 4;
+        let mut pixels = vec![255; stride * height as usize];
+        for row in y..y + h {
+            for column in x..x + w {
+                let start = row as usize * stride + column as usize * 4;
+                pixels[start..start + 3].copy_from_slice(&[0, 0, 0]);
+            }
+        }
+        RenderedPage {
+            width,
+            height,
+            stride,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn test_size_and_points() {
+        let crop = PageCrop {
+            left: 10.0,
+            top: 20.0,
+            right: 30.0,
+            bottom: 40.0,
+        };
+        assert_eq!(crop.size((100.0, 200.0)), (60.0, 140.0));
+        assert_eq!(crop.to_page((5.0, 5.0), 2.0), (25.0, 45.0));
+        assert_eq!(
+            crop.to_cropped(crop.to_page((5.0, 5.0), 2.0), 2.0),
+            (5.0, 5.0)
+        );
+        assert_eq!(crop.pixel_rect((200, 400), 2.0), (20, 40, 120, 280));
+    }
+
+    #[test]
+    fn test_detect() {
+        // Content from (20, 30) to (60, 90) on a 100x120 page at 1 pixel per point
+        let page = page_with_block(100, 120, (20, 30, 40, 60));
+        assert_eq!(
+            PageCrop::detect(&page, 1.0),
+            PageCrop {
+                left: 12.0,
+                top: 22.0,
+                right: 32.0,
+                bottom: 22.0,
+            }
+        );
+        // Margins are measured in points at any zoom
+        let page = page_with_block(200, 240, (40, 60, 80, 120));
+        assert_eq!(PageCrop::detect(&page, 2.0).left, 12.0);
+
+        let blank = page_with_block(100, 120, (0, 0, 0, 0));
+        assert_eq!(PageCrop::detect(&blank, 1.0), PageCrop::NONE);
+    }
+
+    #[test]
+    fn test_crop_page() {
+        let page = page_with_block(100, 120, (20, 30, 40, 60));
+        let crop = PageCrop::detect(&page, 1.0);
+        let cropped = crop.crop_page(page, 1.0);
+        assert_eq!((cropped.width, cropped.height), (56, 76));
+        assert_eq!(cropped.stride, 56 * 4);
+        // The padding stays white and the content starts after it
+        assert_eq!(&cropped.pixels[..4], &[255, 255, 255, 255]);
+        let content = 8 * cropped.stride + 8 * 4;
+        assert_eq!(&cropped.pixels[content..content + 3], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn test_adjusted() {
+        let size = (100.0, 200.0);
+        let crop = PageCrop::NONE.adjusted(CropEdge::Left, 10.0, size);
+        assert_eq!(crop.left, 10.0);
+        assert_eq!(crop.adjusted(CropEdge::Left, -20.0, size).left, 0.0);
+        // Some of the page is always left
+        let crop = crop.adjusted(CropEdge::Right, 100.0, size);
+        assert_eq!(crop.right, 100.0 - 10.0 - MIN_CROPPED_PTS);
+    }
+}
@@ -0,0 +1,469 @@
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use std::fs::{File, TryLockError};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a statement waits for another process to finish with the database,
+/// and a write for the lock file
+const BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Tries of a write that still found the database locked after `BUSY_TIMEOUT`
+const WRITE_ATTEMPTS: u32 = 3;
+const WRITE_RETRY_DELAY: Duration = Duration::from_millis(250);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Error type for opening and writing the database
+#[derive(Debug)]
+pub enum DatabaseError {
+    /// The database could not be opened, or a statement failed
+    Failed(String),
+    /// Another Eyers window or process kept the database busy for too long
+    Locked,
+}
+
+impl std::fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseError::Failed(msg) => write!(f, "{}", msg),
+            DatabaseError::Locked => write!(
+                f,
+                "The database is being written by another Eyers window or process, try again"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<rusqlite::Error> for DatabaseError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err.sqlite_error_code() {
+            Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => DatabaseError::Locked,
+            _ => DatabaseError::Failed(err.to_string()),
+        }
+    }
+}
+
+/// Where annotations, drawings, sessions, positions, marks, recent files,
+/// lookups, the glossary, the reading log and recognized text are stored
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DatabaseLocation {
+    /// `annotations.db` in the data directory
+    #[default]
+    DataDir,
+    /// A database file of the user's choosing, e.g. one per project next to its PDFs
+    File(PathBuf),
+    /// A database that only lives as long as the process, for tests and
+    /// throwaway sessions
+    Memory,
+}
+
+impl DatabaseLocation {
+    /// Parse a `--annotations-db` value: `:memory:` or a file path
+    pub fn parse(value: &str) -> Self {
+        match value {
+            ":memory:" => DatabaseLocation::Memory,
+            path => DatabaseLocation::File(PathBuf::from(path)),
+        }
+    }
+}
+
+/// Name of the shared in-memory database, so every connection of the process
+/// opens the same one
+const MEMORY_DB_URI: &str = "file:eyers-annotations?mode=memory&cache=shared";
+
+static DATABASE_LOCATION: Mutex<DatabaseLocation> = Mutex::new(DatabaseLocation::DataDir);
+/// Connection holding the in-memory database open: SQLite drops it with its
+/// last connection
+static MEMORY_DB: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// Store everything at `location` from now on. Meant to be called once at
+/// startup, before anything is read.
+pub fn set_location(location: DatabaseLocation) {
+    *DATABASE_LOCATION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = location;
+}
+
+fn location() -> DatabaseLocation {
+    DATABASE_LOCATION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Returns the path to the database, None for the in-memory one
+fn db_path() -> Result<Option<PathBuf>, DatabaseError> {
+    match location() {
+        DatabaseLocation::DataDir => dirs::data_dir()
+            .map(|p| Some(p.join("eyers").join("annotations.db")))
+            .ok_or_else(|| DatabaseError::Failed("Could not determine data directory".to_string())),
+        DatabaseLocation::File(path) => Ok(Some(path)),
+        DatabaseLocation::Memory => Ok(None),
+    }
+}
+
+/// Opens a connection to the in-memory database, creating it on first use
+fn open_memory_db() -> Result<Connection, DatabaseError> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_WRITE
+        | OpenFlags::SQLITE_OPEN_CREATE
+        | OpenFlags::SQLITE_OPEN_URI;
+    let mut keeper = MEMORY_DB
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if keeper.is_none() {
+        *keeper = Some(Connection::open_with_flags(MEMORY_DB_URI, flags)?);
+    }
+    Ok(Connection::open_with_flags(MEMORY_DB_URI, flags)?)
+}
+
+/// Opens a connection to the database, creating it and its tables if necessary
+pub fn open() -> Result<Connection, DatabaseError> {
+    let conn = match db_path()? {
+        Some(path) => {
+            // Create parent directory if it doesn't exist
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    DatabaseError::Failed(format!("Could not create data directory: {}", e))
+                })?;
+            }
+
+            Connection::open_with_flags(
+                &path,
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+            )?
+        }
+        None => open_memory_db()?,
+    };
+
+    // Wait for other instances instead of failing right away, and let them read
+    // while one of them writes
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+
+    create_schema(&conn)?;
+    Ok(conn)
+}
+
+/// Creates the tables of every feature keeping data in the database
+fn create_schema(conn: &Connection) -> rusqlite::Result<()> {
+    // Annotations, their "see also" links, stored once per pair with the smaller
+    // ID first, and when each document's annotations were last exported
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS annotations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pdf_path TEXT NOT NULL,
+            start_page INTEGER NOT NULL,
+            start_word INTEGER NOT NULL,
+            end_page INTEGER NOT NULL,
+            end_word INTEGER NOT NULL,
+            selected_text TEXT NOT NULL,
+            note TEXT NOT NULL,
+            color TEXT NOT NULL DEFAULT 'yellow',
+            tags TEXT NOT NULL DEFAULT '',
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS annotation_links (
+            first_id INTEGER NOT NULL,
+            second_id INTEGER NOT NULL,
+            PRIMARY KEY (first_id, second_id)
+        );
+        CREATE TABLE IF NOT EXISTS exports (
+            pdf_path TEXT PRIMARY KEY,
+            exported_at INTEGER NOT NULL
+        );",
+    )?;
+    // Databases created before colors and tags existed lack the columns
+    add_missing_column(
+        conn,
+        "annotations",
+        "color",
+        "TEXT NOT NULL DEFAULT 'yellow'",
+    )?;
+    add_missing_column(conn, "annotations", "tags", "TEXT NOT NULL DEFAULT ''")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_annotations_pdf_path ON annotations(pdf_path)",
+        [],
+    )?;
+
+    // Drawings, with their points as a JSON array of [x, y] pairs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ink_drawings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pdf_path TEXT NOT NULL,
+            page_index INTEGER NOT NULL,
+            tool TEXT NOT NULL,
+            points TEXT NOT NULL,
+            color TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Named positions and the pages read, per document
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS saved_positions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pdf_path TEXT NOT NULL,
+            name TEXT NOT NULL,
+            page_index INTEGER NOT NULL,
+            word_index INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_saved_positions_pdf_path ON saved_positions(pdf_path);
+        CREATE TABLE IF NOT EXISTS read_pages (
+            pdf_path TEXT NOT NULL,
+            page_index INTEGER NOT NULL,
+            read_at INTEGER NOT NULL,
+            PRIMARY KEY (pdf_path, page_index)
+        );",
+    )?;
+
+    // Where each document was left, and the languages documents override
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            pdf_path TEXT PRIMARY KEY,
+            scroll_x REAL NOT NULL,
+            scroll_y REAL NOT NULL,
+            zoom REAL NOT NULL,
+            zoom_mode TEXT,
+            paged_page INTEGER,
+            cursor_page INTEGER,
+            cursor_word INTEGER,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS document_languages (
+            pdf_path TEXT PRIMARY KEY,
+            language TEXT NOT NULL
+        );",
+    )?;
+    // Databases created before zoom modes and the paged mode existed lack the columns
+    add_missing_column(conn, "sessions", "zoom_mode", "TEXT")?;
+    add_missing_column(conn, "sessions", "paged_page", "INTEGER")?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS marks (
+            pdf_path TEXT NOT NULL,
+            letter TEXT NOT NULL,
+            page_index INTEGER NOT NULL,
+            word_index INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            PRIMARY KEY (pdf_path, letter)
+        );
+        CREATE TABLE IF NOT EXISTS recent_files (
+            pdf_path TEXT PRIMARY KEY,
+            opened_at INTEGER NOT NULL
+        );",
+    )?;
+
+    // A word looked up again in the same document keeps only its latest context
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS lookups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            word TEXT NOT NULL,
+            definition TEXT NOT NULL,
+            context TEXT NOT NULL,
+            pdf_path TEXT NOT NULL,
+            page_index INTEGER NOT NULL,
+            looked_up_at INTEGER NOT NULL,
+            language TEXT NOT NULL DEFAULT '',
+            UNIQUE(word, pdf_path)
+        )",
+        [],
+    )?;
+    // Databases created before languages were recorded lack the column
+    add_missing_column(conn, "lookups", "language", "TEXT NOT NULL DEFAULT ''")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS glossary (
+            term TEXT NOT NULL,
+            pdf_path TEXT NOT NULL,
+            explanation TEXT NOT NULL,
+            PRIMARY KEY (term, pdf_path)
+        )",
+        [],
+    )?;
+
+    // Day and time are local to when the event happened, so a journal written
+    // after moving to another timezone keeps the days as they were lived
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reading_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            pdf_path TEXT NOT NULL,
+            first_page INTEGER NOT NULL,
+            last_page INTEGER NOT NULL,
+            detail TEXT NOT NULL,
+            day TEXT NOT NULL,
+            time TEXT NOT NULL,
+            happened_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Words as a JSON array, so a page is recognized only once
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ocr_pages (
+            pdf_path TEXT NOT NULL,
+            page_index INTEGER NOT NULL,
+            words TEXT NOT NULL,
+            PRIMARY KEY (pdf_path, page_index)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Add `column` to `table` when a database created before it existed lacks it
+fn add_missing_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    let select = format!("SELECT {} FROM {} LIMIT 0", column, table);
+    if conn.prepare(&select).is_err() {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Take the lock file next to the database, so writes of concurrent Eyers
+/// processes (and the HTTP API) take turns. Released when the file is dropped.
+/// The in-memory database has no file, nor other processes to wait for.
+fn lock_for_writing() -> Result<Option<File>, DatabaseError> {
+    let Some(path) = db_path()? else {
+        return Ok(None);
+    };
+    let path = path.with_extension("lock");
+    let file = File::create(&path)
+        .map_err(|e| DatabaseError::Failed(format!("Could not open lock file: {}", e)))?;
+
+    let deadline = Instant::now() + BUSY_TIMEOUT;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(Some(file)),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(LOCK_POLL_INTERVAL);
+            }
+            Err(TryLockError::WouldBlock) => return Err(DatabaseError::Locked),
+            Err(TryLockError::Error(e)) => {
+                return Err(DatabaseError::Failed(format!(
+                    "Could not lock the database: {}",
+                    e
+                )));
+            }
+        }
+    }
+}
+
+/// Run `write` on the database while holding the lock file, trying again a few
+/// times when another process still has the database locked. Waits up to several
+/// seconds, so it belongs on a worker thread rather than the main loop.
+pub fn write<T>(write: impl Fn(&Connection) -> rusqlite::Result<T>) -> Result<T, DatabaseError> {
+    let _lock = lock_for_writing()?;
+    let conn = open()?;
+    retry_locked(WRITE_ATTEMPTS, WRITE_RETRY_DELAY, || Ok(write(&conn)?))
+}
+
+/// Call `attempt` until it succeeds, fails for another reason than a locked
+/// database, or was tried `attempts` times
+fn retry_locked<T>(
+    attempts: u32,
+    delay: Duration,
+    mut attempt: impl FnMut() -> Result<T, DatabaseError>,
+) -> Result<T, DatabaseError> {
+    let mut tries = 1;
+    loop {
+        match attempt() {
+            Err(DatabaseError::Locked) if tries < attempts => {
+                tries += 1;
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_database_location_parse() {
+        assert_eq!(
+            DatabaseLocation::parse(":memory:"),
+            DatabaseLocation::Memory
+        );
+        assert_eq!(
+            DatabaseLocation::parse("/papers/thesis/annotations.db"),
+            DatabaseLocation::File(PathBuf::from("/papers/thesis/annotations.db"))
+        );
+    }
+
+    #[test]
+    fn test_retry_locked() {
+        let mut calls = 0;
+        let result = retry_locked(3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 {
+                Err(DatabaseError::Locked)
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.ok(), Some(3));
+
+        let mut calls = 0;
+        let result: Result<(), _> = retry_locked(2, Duration::ZERO, || {
+            calls += 1;
+            Err(DatabaseError::Locked)
+        });
+        assert!(matches!(result, Err(DatabaseError::Locked)));
+        assert_eq!(calls, 2);
+
+        // Other errors are not retried
+        let mut calls = 0;
+        let result: Result<(), _> = retry_locked(3, Duration::ZERO, || {
+            calls += 1;
+            Err(DatabaseError::Failed("no such table".to_string()))
+        });
+        assert!(matches!(result, Err(DatabaseError::Failed(_))));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        // An annotations table from before colors and tags existed
+        conn.execute(
+            "CREATE TABLE annotations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                pdf_path TEXT NOT NULL,
+                start_page INTEGER NOT NULL,
+                start_word INTEGER NOT NULL,
+                end_page INTEGER NOT NULL,
+                end_word INTEGER NOT NULL,
+                selected_text TEXT NOT NULL,
+                note TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+
+        create_schema(&conn).unwrap();
+        // Creating it again finds everything in place
+        create_schema(&conn).unwrap();
+        assert!(conn.prepare("SELECT color, tags FROM annotations").is_ok());
+        assert!(conn.prepare("SELECT name FROM saved_positions").is_ok());
+        assert!(conn.prepare("SELECT zoom FROM sessions").is_ok());
+        assert!(conn.prepare("SELECT letter FROM marks").is_ok());
+    }
+}
@@ -1,35 +1,104 @@
 use gtk::glib;
 use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
-/// The language mode for dictionary lookups.
-#[derive(Debug, Clone, Copy, Default)]
-pub enum Language {
-    #[default]
-    English,
-    Spanish,
+use super::local_dictionary::LocalDictionaries;
+use super::lru_cache::{LruCache, SqliteCache};
+
+const ONLINE_URL: &str = "https://api.dictionaryapi.dev/api/v2/entries";
+
+/// Words shorter than this are too common to be worth prefetching
+pub const PREFETCH_MIN_CHARS: usize = 7;
+/// Most words prefetched for one line
+pub const PREFETCH_MAX_WORDS: usize = 4;
+/// Pause between two prefetched lookups, so moving quickly through a page
+/// doesn't keep the dictionary busy
+const PREFETCH_INTERVAL: Duration = Duration::from_millis(150);
+/// Prefetched lookups kept, the least recently used are dropped past it
+const PREFETCH_CACHE_SIZE: usize = 256;
+/// Online definitions kept in the cache, the least recently used are dropped past it
+pub const DEFINITION_CACHE_SIZE: usize = 5000;
+
+/// A language words can be looked up in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Language {
+    code: &'static str,
+    name: &'static str,
 }
 
+pub const ENGLISH: Language = Language::new("en", "English");
+pub const SPANISH: Language = Language::new("es", "Spanish");
+
+/// Every language offered in the settings, sorted by name.
+pub const LANGUAGES: &[Language] = &[
+    Language::new("ar", "Arabic"),
+    Language::new("zh", "Chinese"),
+    Language::new("nl", "Dutch"),
+    ENGLISH,
+    Language::new("fr", "French"),
+    Language::new("de", "German"),
+    Language::new("el", "Greek"),
+    Language::new("hi", "Hindi"),
+    Language::new("it", "Italian"),
+    Language::new("ja", "Japanese"),
+    Language::new("ko", "Korean"),
+    Language::new("la", "Latin"),
+    Language::new("pl", "Polish"),
+    Language::new("pt", "Portuguese"),
+    Language::new("ru", "Russian"),
+    SPANISH,
+    Language::new("sv", "Swedish"),
+    Language::new("tr", "Turkish"),
+];
+
+/// Languages of the Wiktionary database, which has translations between them.
+const WIKTIONARY_LANGUAGES: &[Language] = &[ENGLISH, SPANISH];
+/// Languages the Free Dictionary API has entries for.
+const ONLINE_LANGUAGES: &[Language] = &[ENGLISH];
+
 impl Language {
+    const fn new(code: &'static str, name: &'static str) -> Self {
+        Self { code, name }
+    }
+
     /// Returns the ISO 639-1 code for this language.
     pub fn code(&self) -> &'static str {
-        match self {
-            Language::English => "en",
-            Language::Spanish => "es",
-        }
+        self.code
+    }
+
+    /// Returns the English name shown in the settings.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Parses an ISO 639-1 code as returned by `code`.
+    pub fn from_code(code: &str) -> Option<Language> {
+        LANGUAGES.iter().find(|lang| lang.code == code).copied()
     }
 
     /// Returns the target language code for translations.
     pub fn translation_target(&self) -> &'static str {
-        match self {
-            Language::English => "es",
-            Language::Spanish => "en",
+        if *self == ENGLISH {
+            SPANISH.code
+        } else {
+            ENGLISH.code
         }
     }
 }
 
+impl Default for Language {
+    fn default() -> Self {
+        ENGLISH
+    }
+}
+
 /// A single sense (definition) of a word.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Sense {
     pub pos: String,
     pub gloss: String,
@@ -38,19 +107,184 @@ pub struct Sense {
 }
 
 /// A translation of a sense to another language.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Translation {
     pub word: String,
     pub romanization: Option<String>,
 }
 
 /// Result of a dictionary lookup.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LookupResult {
     pub word: String,
     pub senses: Vec<Sense>,
 }
 
+/// A source of word definitions
+pub trait DictionaryBackend {
+    fn lookup(&self, word: &str, lang: Language) -> Option<LookupResult>;
+
+    /// Languages words can be looked up in
+    fn languages(&self) -> Vec<Language>;
+
+    /// Language selected when switching to this backend and the current one is not supported
+    fn default_language(&self) -> Language {
+        let languages = self.languages();
+        if languages.contains(&ENGLISH) {
+            ENGLISH
+        } else {
+            languages.first().copied().unwrap_or_default()
+        }
+    }
+}
+
+/// Where definitions are looked up
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DictionarySource {
+    /// The Wiktionary database in the data directory
+    #[default]
+    Builtin,
+    /// The Free Dictionary API
+    Online,
+    /// StarDict and dictd dictionaries in a directory
+    Local(PathBuf),
+}
+
+impl DictionarySource {
+    pub fn backend(&self) -> Box<dyn DictionaryBackend> {
+        match self {
+            DictionarySource::Builtin => Box::new(WiktionaryDatabase),
+            DictionarySource::Online => Box::new(OnlineDictionary),
+            DictionarySource::Local(dir) => Box::new(LocalDictionaries::new(dir.clone())),
+        }
+    }
+
+    /// Heading of the source's column when comparing sources
+    pub fn label(&self) -> &'static str {
+        match self {
+            DictionarySource::Builtin => "Wiktionary",
+            DictionarySource::Online => "Free Dictionary",
+            DictionarySource::Local(_) => "Local",
+        }
+    }
+
+    /// Whether lookups are answered without network access
+    pub fn is_offline(&self) -> bool {
+        !matches!(self, DictionarySource::Online)
+    }
+
+    /// Every source that can be queried: the database and the online API need no
+    /// setup, local dictionaries only when their directory is the selected source.
+    pub fn configured(&self) -> Vec<DictionarySource> {
+        let mut sources = vec![DictionarySource::Builtin, DictionarySource::Online];
+        if let DictionarySource::Local(_) = self {
+            sources.push(self.clone());
+        }
+        sources
+    }
+}
+
+/// The Wiktionary database built from the kaikki.org dumps
+pub struct WiktionaryDatabase;
+
+impl DictionaryBackend for WiktionaryDatabase {
+    fn lookup(&self, word: &str, lang: Language) -> Option<LookupResult> {
+        lookup(word, lang)
+    }
+
+    fn languages(&self) -> Vec<Language> {
+        WIKTIONARY_LANGUAGES.to_vec()
+    }
+}
+
+/// The Free Dictionary API (dictionaryapi.dev)
+pub struct OnlineDictionary;
+
+#[derive(Deserialize)]
+struct OnlineEntry {
+    #[serde(default)]
+    meanings: Vec<OnlineMeaning>,
+}
+
+#[derive(Deserialize)]
+struct OnlineMeaning {
+    #[serde(rename = "partOfSpeech", default)]
+    part_of_speech: String,
+    #[serde(default)]
+    definitions: Vec<OnlineDefinition>,
+}
+
+#[derive(Deserialize)]
+struct OnlineDefinition {
+    definition: String,
+}
+
+impl OnlineDictionary {
+    fn fetch(word: &str, lang: Language) -> Option<LookupResult> {
+        let mut url = reqwest::Url::parse(ONLINE_URL).ok()?;
+        url.path_segments_mut().ok()?.push(lang.code()).push(word);
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .ok()?;
+        let response = client.get(url).send().ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let entries: Vec<OnlineEntry> = response.json().ok()?;
+
+        let senses: Vec<Sense> = entries
+            .into_iter()
+            .flat_map(|entry| entry.meanings)
+            .flat_map(|meaning| {
+                let pos = meaning.part_of_speech;
+                meaning.definitions.into_iter().map(move |d| Sense {
+                    pos: pos.clone(),
+                    gloss: d.definition,
+                    etymology: None,
+                    translations: Vec::new(),
+                })
+            })
+            .collect();
+
+        if senses.is_empty() {
+            return None;
+        }
+
+        Some(LookupResult {
+            word: word.to_string(),
+            senses,
+        })
+    }
+}
+
+impl DictionaryBackend for OnlineDictionary {
+    /// Definitions fetched before come from the cache, without network access
+    fn lookup(&self, word: &str, lang: Language) -> Option<LookupResult> {
+        // Lookups are stored as JSON, keyed by word whatever its case and language
+        let word_key = word.to_lowercase();
+        let key = [word_key.as_str(), lang.code()];
+        let cache = open_cache();
+        if let Some(result) = cache
+            .as_ref()
+            .and_then(|cache| cache.get(&key))
+            .and_then(|json| serde_json::from_str(&json).ok())
+        {
+            return Some(result);
+        }
+        let result = Self::fetch(word, lang)?;
+        if let (Some(cache), Ok(json)) = (cache, serde_json::to_string(&result)) {
+            cache.insert(&key, &json);
+        }
+        Some(result)
+    }
+
+    fn languages(&self) -> Vec<Language> {
+        ONLINE_LANGUAGES.to_vec()
+    }
+}
+
 /// Returns the path to the dictionary database.
 fn get_db_path() -> Option<PathBuf> {
     dirs::data_dir().map(|p| p.join("eyers").join("dictionary.db"))
@@ -65,6 +299,21 @@ fn open_db() -> Option<Connection> {
     Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()
 }
 
+/// Opens the cache of online definitions, creating it if necessary.
+fn open_cache() -> Option<SqliteCache> {
+    SqliteCache::open("definition_cache.db", "definitions", DEFINITION_CACHE_SIZE)
+}
+
+/// Number of definitions in the cache.
+pub fn definition_cache_len() -> usize {
+    open_cache().map_or(0, |cache| cache.entry_count())
+}
+
+/// Forgets every cached definition. Returns false when the cache couldn't be emptied.
+pub fn clear_definition_cache() -> bool {
+    open_cache().is_some_and(|cache| cache.clear())
+}
+
 /// Looks up a word in the dictionary.
 pub fn lookup(word: &str, lang: Language) -> Option<LookupResult> {
     let conn = open_db()?;
@@ -138,11 +387,137 @@ fn get_translations(conn: &Connection, sense_id: i64, target_lang: &str) -> Vec<
 
 /// Fetches and formats a definition for display.
 /// This is the main entry point called by the UI.
-pub fn fetch_definition(lookup_word: &str, display_word: &str, lang: Language) -> Option<String> {
-    let result = lookup(lookup_word, lang)?;
+pub fn fetch_definition(
+    lookup_word: &str,
+    display_word: &str,
+    lang: Language,
+    source: &DictionarySource,
+) -> Option<String> {
+    let result = match prefetched(lookup_word, lang, source) {
+        Some(result) => result,
+        None => source.backend().lookup(lookup_word, lang)?,
+    };
     format_result(&result, display_word)
 }
 
+type PrefetchKey = (String, Language, DictionarySource);
+
+/// Lookups made ahead of time. Words without a result aren't kept, as an online
+/// source gives none when the request fails too.
+fn prefetch_cache() -> std::sync::MutexGuard<'static, LruCache<PrefetchKey, LookupResult>> {
+    static CACHE: OnceLock<Mutex<LruCache<PrefetchKey, LookupResult>>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| Mutex::new(LruCache::new(PREFETCH_CACHE_SIZE)))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// The prefetched lookup of a word, None when it wasn't prefetched
+fn prefetched(
+    lookup_word: &str,
+    lang: Language,
+    source: &DictionarySource,
+) -> Option<LookupResult> {
+    prefetch_cache().get(&(lookup_word.to_string(), lang, source.clone()))
+}
+
+/// Words of a line worth looking up ahead of time: the long ones, which are
+/// the rare ones in most texts, lowercased as `d` looks them up, without
+/// duplicates and at most `PREFETCH_MAX_WORDS`
+pub fn rare_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut rare: Vec<String> = Vec::new();
+    for word in words {
+        let word = word.to_lowercase();
+        if word.chars().count() < PREFETCH_MIN_CHARS
+            || !word
+                .chars()
+                .all(|c| c.is_alphabetic() || c == '\'' || c == '-')
+            || rare.contains(&word)
+        {
+            continue;
+        }
+        rare.push(word);
+        if rare.len() == PREFETCH_MAX_WORDS {
+            break;
+        }
+    }
+    rare
+}
+
+/// Bumped by every prefetch, so the one for a line the cursor already left stops
+static PREFETCH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Look up `words` ahead of time, one every `PREFETCH_INTERVAL`, so showing
+/// their definitions later doesn't wait for the dictionary. Words already
+/// prefetched are skipped, and an online source is only queried with
+/// `allow_online`. Blocks until done or until a newer prefetch starts, so it
+/// runs on a worker thread.
+pub fn prefetch(words: Vec<String>, lang: Language, source: DictionarySource, allow_online: bool) {
+    let generation = PREFETCH_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    if !source.is_offline() && !allow_online {
+        return;
+    }
+
+    let backend = source.backend();
+    let mut lookups = 0;
+    for word in words {
+        if PREFETCH_GENERATION.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        let key = (word, lang, source.clone());
+        if prefetch_cache().contains_key(&key) {
+            continue;
+        }
+        if lookups > 0 {
+            std::thread::sleep(PREFETCH_INTERVAL);
+        }
+        lookups += 1;
+
+        let Some(result) = backend.lookup(&key.0, lang) else {
+            continue;
+        };
+        prefetch_cache().insert(key, result);
+    }
+}
+
+/// Combined lookup for a short phrase: its own entry when the dictionary has one
+/// (idioms, phrasal verbs), followed by the entries of its words.
+pub fn fetch_phrase_definition(
+    lookup_phrase: &str,
+    display_phrase: &str,
+    lang: Language,
+    source: &DictionarySource,
+) -> Option<String> {
+    let backend = source.backend();
+    let phrase_entry = backend
+        .lookup(lookup_phrase, lang)
+        .and_then(|result| format_result(&result, display_phrase))
+        .unwrap_or_else(|| {
+            format!(
+                "<span size='large' weight='bold'>{}</span>\n\n<i>No entry for the whole phrase.</i>",
+                glib::markup_escape_text(display_phrase)
+            )
+        });
+
+    let mut sections = vec![phrase_entry];
+    let mut seen = HashSet::new();
+    for word in display_phrase.split_whitespace() {
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
+        let lookup_word = word.to_lowercase();
+        if lookup_word.is_empty() || !seen.insert(lookup_word.clone()) {
+            continue;
+        }
+        if let Some(entry) = backend
+            .lookup(&lookup_word, lang)
+            .and_then(|result| format_result(&result, word))
+        {
+            sections.push(entry);
+        }
+    }
+
+    Some(sections.join("\n\n"))
+}
+
 /// Formats a lookup result as Pango markup for display.
 fn format_result(result: &LookupResult, display_word: &str) -> Option<String> {
     let mut output = String::new();
@@ -202,3 +577,89 @@ fn format_result(result: &LookupResult, display_word: &str) -> Option<String> {
         Some(final_output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_codes_round_trip() {
+        for lang in LANGUAGES {
+            assert_eq!(Language::from_code(lang.code()), Some(*lang));
+        }
+        assert_eq!(Language::from_code("xx"), None);
+    }
+
+    #[test]
+    fn test_configured_sources() {
+        assert_eq!(
+            DictionarySource::Online.configured(),
+            vec![DictionarySource::Builtin, DictionarySource::Online]
+        );
+        let local = DictionarySource::Local(PathBuf::from("/dicts"));
+        assert_eq!(
+            local.configured(),
+            vec![DictionarySource::Builtin, DictionarySource::Online, local]
+        );
+    }
+
+    #[test]
+    fn test_rare_words() {
+        let line = [
+            "The",
+            "Ineffable",
+            "quality",
+            "of",
+            "ineffable",
+            "self-evident",
+            "things",
+            "1234567",
+        ];
+        assert_eq!(
+            rare_words(line),
+            vec!["ineffable", "quality", "self-evident"]
+        );
+
+        let long = [
+            "abundance",
+            "benevolent",
+            "cacophony",
+            "debonair",
+            "ephemeral",
+        ];
+        assert_eq!(rare_words(long).len(), PREFETCH_MAX_WORDS);
+    }
+
+    #[test]
+    fn test_sources_offline() {
+        assert!(DictionarySource::Builtin.is_offline());
+        assert!(DictionarySource::Local(PathBuf::new()).is_offline());
+        assert!(!DictionarySource::Online.is_offline());
+    }
+
+    #[test]
+    fn test_languages_sorted_by_name() {
+        let names: Vec<&str> = LANGUAGES.iter().map(|lang| lang.name()).collect();
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        assert_eq!(names, sorted);
+    }
+
+    #[test]
+    fn test_default_language_per_source() {
+        assert_eq!(
+            DictionarySource::Builtin.backend().default_language(),
+            ENGLISH
+        );
+        assert_eq!(
+            DictionarySource::Online.backend().languages(),
+            vec![ENGLISH]
+        );
+        assert_eq!(
+            DictionarySource::Local(PathBuf::new())
+                .backend()
+                .languages(),
+            LANGUAGES.to_vec()
+        );
+    }
+}
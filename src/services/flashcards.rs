@@ -0,0 +1,279 @@
+use rusqlite::params;
+use std::path::Path;
+
+use crate::services::annotations::{self, AnnotationError};
+use crate::services::database::{self, DatabaseError};
+
+/// Suggested file name for an export
+pub const EXPORT_FILE_NAME: &str = "eyers-flashcards.txt";
+
+/// A dictionary lookup, kept to review the word later
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flashcard {
+    pub word: String,
+    /// Pango markup as shown in the definition popover
+    pub definition: String,
+    /// The sentence the word was looked up in
+    pub context: String,
+    pub pdf_path: String,
+    pub page_index: usize,
+    /// ISO 639-1 code of the dictionary language, empty for lookups recorded
+    /// before languages were and for annotation cards
+    pub language: String,
+}
+
+/// Error type for flashcard operations
+#[derive(Debug)]
+pub enum FlashcardError {
+    DatabaseError(String),
+}
+
+impl std::fmt::Display for FlashcardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlashcardError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for FlashcardError {}
+
+impl From<rusqlite::Error> for FlashcardError {
+    fn from(err: rusqlite::Error) -> Self {
+        FlashcardError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for FlashcardError {
+    fn from(err: DatabaseError) -> Self {
+        FlashcardError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<AnnotationError> for FlashcardError {
+    fn from(err: AnnotationError) -> Self {
+        FlashcardError::DatabaseError(err.to_string())
+    }
+}
+
+/// Record a dictionary lookup
+pub fn record_lookup(card: &Flashcard) -> Result<(), FlashcardError> {
+    let conn = database::open()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO lookups
+            (word, definition, context, pdf_path, page_index, looked_up_at, language)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            card.word,
+            card.definition,
+            card.context,
+            card.pdf_path,
+            card.page_index as i64,
+            now,
+            card.language
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Every recorded lookup, oldest first
+pub fn load_lookups() -> Result<Vec<Flashcard>, FlashcardError> {
+    let conn = database::open()?;
+    let mut stmt = conn.prepare(
+        "SELECT word, definition, context, pdf_path, page_index, language
+         FROM lookups ORDER BY looked_up_at, id",
+    )?;
+
+    let cards = stmt
+        .query_map([], |row| {
+            Ok(Flashcard {
+                word: row.get(0)?,
+                definition: row.get(1)?,
+                context: row.get(2)?,
+                pdf_path: row.get(3)?,
+                page_index: row.get::<_, i64>(4)? as usize,
+                language: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(cards)
+}
+
+/// Number of words looked up on each page of a document, as (page, count) pairs
+/// for the pages that have any
+pub fn lookup_counts_by_page(pdf_path: &str) -> Result<Vec<(usize, usize)>, FlashcardError> {
+    let conn = database::open()?;
+    let mut stmt = conn.prepare(
+        "SELECT page_index, COUNT(*) FROM lookups WHERE pdf_path = ?1
+         GROUP BY page_index ORDER BY page_index",
+    )?;
+
+    let counts = stmt
+        .query_map(params![pdf_path], |row| {
+            Ok((
+                row.get::<_, i64>(0)? as usize,
+                row.get::<_, i64>(1)? as usize,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(counts)
+}
+
+/// Number of words looked up in each dictionary language, as (language code,
+/// count) pairs sorted by code. Lookups recorded before languages were have an
+/// empty code.
+pub fn lookup_counts_by_language() -> Result<Vec<(String, usize)>, FlashcardError> {
+    let conn = database::open()?;
+    let mut stmt =
+        conn.prepare("SELECT language, COUNT(*) FROM lookups GROUP BY language ORDER BY language")?;
+
+    let counts = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(counts)
+}
+
+/// Cards for the annotations with a note, the highlighted text on the front and
+/// the note on the back
+pub fn annotation_cards() -> Result<Vec<Flashcard>, FlashcardError> {
+    let mut cards = Vec::new();
+    for pdf_path in annotations::annotated_documents()? {
+        for annotation in annotations::load_annotations_for_pdf(&pdf_path)? {
+            if annotation.note.trim().is_empty() {
+                continue;
+            }
+            cards.push(Flashcard {
+                word: annotation.selected_text,
+                definition: escape_html(&annotation.note),
+                context: String::new(),
+                pdf_path: annotation.pdf_path,
+                page_index: annotation.start_page,
+                language: String::new(),
+            });
+        }
+    }
+    Ok(cards)
+}
+
+/// Anki's plain text import format: a header naming the separator, then one note
+/// per line with the front, the back and the tags. Fields are HTML, so line breaks
+/// become `<br>` and tabs can't break the columns.
+pub fn to_anki_tsv(cards: &[Flashcard]) -> String {
+    let mut tsv = String::from("#separator:tab\n#html:true\n#tags column:3\n");
+
+    for card in cards {
+        let mut back = card.definition.replace('\n', "<br>");
+        if !card.context.is_empty() {
+            back.push_str(&format!(
+                "<br><br><i>{}</i>",
+                highlight_word(&escape_html(&card.context), &escape_html(&card.word))
+            ));
+        }
+        back.push_str(&format!(
+            "<br><small>{}, p. {}</small>",
+            escape_html(&document_name(&card.pdf_path)),
+            card.page_index + 1
+        ));
+
+        tsv.push_str(&format!(
+            "{}\t{}\teyers {}\n",
+            field(&escape_html(&card.word)),
+            field(&back),
+            document_tag(&card.pdf_path)
+        ));
+    }
+
+    tsv
+}
+
+fn field(html: &str) -> String {
+    html.replace(['\t', '\r'], " ").replace('\n', "<br>")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Make the first occurrence of `word` in `context` bold, ignoring case
+fn highlight_word(context: &str, word: &str) -> String {
+    let lower = context.to_lowercase();
+    let needle = word.to_lowercase();
+    // Lowercasing can change byte lengths; only highlight when positions line up
+    let found = lower
+        .find(&needle)
+        .filter(|_| lower.len() == context.len() && !needle.is_empty())
+        .and_then(|start| {
+            let end = start + needle.len();
+            Some((
+                context.get(..start)?,
+                context.get(start..end)?,
+                context.get(end..)?,
+            ))
+        });
+    match found {
+        Some((before, word, after)) => format!("{}<b>{}</b>{}", before, word, after),
+        None => context.to_string(),
+    }
+}
+
+fn document_name(pdf_path: &str) -> String {
+    Path::new(pdf_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(pdf_path)
+        .to_string()
+}
+
+/// Anki tags are separated by spaces
+fn document_tag(pdf_path: &str) -> String {
+    Path::new(pdf_path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("document")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_anki_tsv() {
+        let cards = [Flashcard {
+            word: "gave".to_string(),
+            definition: "<b>give</b>\n1. To\ttransfer".to_string(),
+            context: "He Gave up & left.".to_string(),
+            pdf_path: "/books/My Novel.pdf".to_string(),
+            page_index: 4,
+            language: "en".to_string(),
+        }];
+        assert_eq!(
+            to_anki_tsv(&cards),
+            "#separator:tab\n#html:true\n#tags column:3\n\
+             gave\t<b>give</b><br>1. To transfer<br><br><i>He <b>Gave</b> up &amp; left.</i>\
+             <br><small>My Novel.pdf, p. 5</small>\teyers My_Novel\n"
+        );
+    }
+
+    #[test]
+    fn test_highlight_word() {
+        assert_eq!(highlight_word("a word", "missing"), "a word");
+        assert_eq!(highlight_word("Word word", "word"), "<b>Word</b> word");
+    }
+}
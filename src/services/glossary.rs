@@ -0,0 +1,130 @@
+use gtk::glib;
+use rusqlite::{OptionalExtension, params};
+
+use crate::services::database::{self, DatabaseError};
+
+/// Document path stored for entries that apply to every document
+const GLOBAL: &str = "";
+
+/// A term explained by the user, in one document or in all of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlossaryEntry {
+    pub term: String,
+    pub explanation: String,
+    /// None for a global entry
+    pub pdf_path: Option<String>,
+}
+
+impl GlossaryEntry {
+    /// Pango markup shown above the dictionary's definition
+    pub fn markup(&self) -> String {
+        let scope = if self.pdf_path.is_some() {
+            "Glossary"
+        } else {
+            "Global glossary"
+        };
+        format!(
+            "<span size='large' weight='bold'>{}</span>  <i>{}</i>\n{}",
+            glib::markup_escape_text(&self.term),
+            scope,
+            glib::markup_escape_text(&self.explanation)
+        )
+    }
+}
+
+/// Error type for glossary operations
+#[derive(Debug)]
+pub enum GlossaryError {
+    DatabaseError(String),
+}
+
+impl std::fmt::Display for GlossaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlossaryError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GlossaryError {}
+
+impl From<rusqlite::Error> for GlossaryError {
+    fn from(err: rusqlite::Error) -> Self {
+        GlossaryError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for GlossaryError {
+    fn from(err: DatabaseError) -> Self {
+        GlossaryError::DatabaseError(err.to_string())
+    }
+}
+
+/// Terms are matched ignoring case and spacing, like lookups
+pub fn normalize_term(term: &str) -> String {
+    term.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Add or replace the explanation of `term`, for `pdf_path` only or globally
+pub fn save_entry(
+    term: &str,
+    explanation: &str,
+    pdf_path: Option<&str>,
+) -> Result<(), GlossaryError> {
+    let conn = database::open()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO glossary (term, pdf_path, explanation) VALUES (?1, ?2, ?3)",
+        params![
+            normalize_term(term),
+            pdf_path.unwrap_or(GLOBAL),
+            explanation.trim()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Remove the entry for `term`. Returns whether there was one.
+pub fn delete_entry(term: &str, pdf_path: Option<&str>) -> Result<bool, GlossaryError> {
+    let conn = database::open()?;
+    let deleted = conn.execute(
+        "DELETE FROM glossary WHERE term = ?1 AND pdf_path = ?2",
+        params![normalize_term(term), pdf_path.unwrap_or(GLOBAL)],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// The explanation of `term` for the document, falling back to a global one
+pub fn lookup(term: &str, pdf_path: Option<&str>) -> Result<Option<GlossaryEntry>, GlossaryError> {
+    let conn = database::open()?;
+    let entry = conn
+        .query_row(
+            "SELECT term, explanation, pdf_path FROM glossary
+             WHERE term = ?1 AND pdf_path IN (?2, ?3)
+             ORDER BY pdf_path = ?3
+             LIMIT 1",
+            params![normalize_term(term), pdf_path.unwrap_or(GLOBAL), GLOBAL],
+            |row| {
+                let pdf_path: String = row.get(2)?;
+                Ok(GlossaryEntry {
+                    term: row.get(0)?,
+                    explanation: row.get(1)?,
+                    pdf_path: (pdf_path != GLOBAL).then_some(pdf_path),
+                })
+            },
+        )
+        .optional()?;
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_term() {
+        assert_eq!(normalize_term("  Eigen\n Value "), "eigen value");
+    }
+}
@@ -0,0 +1,80 @@
+/// Shades of the heat map, from untouched pages to the busiest ones
+pub const HEAT_LEVELS: usize = 5;
+
+/// What was done on a page: annotations made and words looked up
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PageActivity {
+    pub annotations: usize,
+    pub lookups: usize,
+}
+
+impl PageActivity {
+    pub fn total(&self) -> usize {
+        self.annotations + self.lookups
+    }
+}
+
+/// Activity of every page of a document from per-page counts. Counts of pages
+/// past `page_count` (e.g. from an older version of the file) are dropped.
+pub fn page_activity(
+    page_count: usize,
+    annotation_counts: &[(usize, usize)],
+    lookup_counts: &[(usize, usize)],
+) -> Vec<PageActivity> {
+    let mut pages = vec![PageActivity::default(); page_count];
+    for &(page, count) in annotation_counts {
+        if let Some(activity) = pages.get_mut(page) {
+            activity.annotations += count;
+        }
+    }
+    for &(page, count) in lookup_counts {
+        if let Some(activity) = pages.get_mut(page) {
+            activity.lookups += count;
+        }
+    }
+    pages
+}
+
+/// Shade of a page with `total` activity, relative to the busiest page: 0 for none,
+/// then 1 to `HEAT_LEVELS - 1`
+pub fn heat_level(total: usize, max_total: usize) -> usize {
+    if total == 0 || max_total == 0 {
+        return 0;
+    }
+    let steps = HEAT_LEVELS - 1;
+    (total * steps).div_ceil(max_total).clamp(1, steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_activity() {
+        let pages = page_activity(3, &[(0, 2), (2, 1), (7, 4)], &[(2, 3)]);
+        assert_eq!(
+            pages,
+            vec![
+                PageActivity {
+                    annotations: 2,
+                    lookups: 0
+                },
+                PageActivity::default(),
+                PageActivity {
+                    annotations: 1,
+                    lookups: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heat_level() {
+        assert_eq!(heat_level(0, 10), 0);
+        assert_eq!(heat_level(1, 10), 1);
+        assert_eq!(heat_level(5, 10), 2);
+        assert_eq!(heat_level(8, 10), 4);
+        assert_eq!(heat_level(10, 10), 4);
+        assert_eq!(heat_level(3, 0), 0);
+    }
+}
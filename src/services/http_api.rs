@@ -0,0 +1,506 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::services::annotations::{self, Annotation, AnnotationColor, AnnotationError};
+use crate::services::positions;
+use crate::services::session;
+
+/// Largest request body accepted, annotations are small
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// A client that stops sending is dropped instead of blocking the server
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Error type for the local HTTP API
+#[derive(Debug)]
+pub enum ApiError {
+    IoError(String),
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::IoError(msg) => write!(f, "HTTP API error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<std::io::Error> for ApiError {
+    fn from(err: std::io::Error) -> Self {
+        ApiError::IoError(err.to_string())
+    }
+}
+
+/// Changes made through the API that the window has to pick up
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiEvent {
+    AnnotationCreated { pdf_path: String },
+}
+
+/// A parsed HTTP request
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Decoded query parameters, in order
+    pub query: Vec<(String, String)>,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A JSON response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// Body of `POST /annotations`
+#[derive(Debug, Deserialize)]
+struct NewAnnotation {
+    pdf_path: String,
+    start_page: usize,
+    start_word: usize,
+    end_page: usize,
+    end_word: usize,
+    #[serde(default)]
+    selected_text: String,
+    #[serde(default)]
+    note: String,
+    /// Color name, yellow when missing or unknown
+    #[serde(default)]
+    color: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// HTTP server on 127.0.0.1 answering on its own thread until dropped
+pub struct ApiServer {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ApiServer {
+    /// Listen on `port` of the loopback interface. Changes made through the API
+    /// are reported on `events`.
+    pub fn start(port: u16, events: Sender<ApiEvent>) -> Result<Self, ApiError> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let address = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                if let Err(e) = handle_connection(stream, &events) {
+                    eprintln!("{}", e);
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wake the thread blocked in accept so it sees the stop flag
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn handle_connection(stream: TcpStream, events: &Sender<ApiEvent>) -> Result<(), ApiError> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let response = match read_request(&mut reader) {
+        Ok(Some(request)) => {
+            let response = route(&request);
+            let created = response.body["pdf_path"]
+                .as_str()
+                .filter(|_| request.method == "POST" && response.status == 201);
+            if let Some(pdf_path) = created {
+                let _ = events.send(ApiEvent::AnnotationCreated {
+                    pdf_path: pdf_path.to_string(),
+                });
+            }
+            response
+        }
+        // Connection closed without a request, e.g. the wake-up on drop
+        Ok(None) => return Ok(()),
+        Err(message) => Response::error(400, message),
+    };
+
+    write_response(stream, &response)
+}
+
+/// Read one request. Ok(None) when the connection closed before sending one,
+/// Err with a message for malformed requests.
+pub fn read_request(reader: &mut impl BufRead) -> Result<Option<Request>, String> {
+    let mut request_line = String::new();
+    if reader
+        .read_line(&mut request_line)
+        .map_err(|e| e.to_string())?
+        == 0
+    {
+        return Ok(None);
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("Malformed request line".to_string());
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut request = Request {
+        method: method.to_ascii_uppercase(),
+        path: percent_decode(path),
+        query: parse_query(query),
+        ..Default::default()
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                content_length = value
+                    .parse()
+                    .map_err(|_| "Invalid Content-Length".to_string())?;
+            }
+            "content-type" => request.content_type = Some(value.to_ascii_lowercase()),
+            _ => {}
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err("Request body too large".to_string());
+    }
+    request.body = vec![0; content_length];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(request))
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> Result<(), ApiError> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        415 => "Unsupported Media Type",
+        _ => "Internal Server Error",
+    };
+    let body = response.body.to_string();
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Answer a request
+///
+/// - `GET /documents`: paths of the annotated documents
+/// - `GET /annotations?pdf=PATH`: annotations of a document, of all documents without `pdf`
+/// - `GET /positions?pdf=PATH`: saved positions and last cursor of a document
+/// - `POST /annotations`: create an annotation from a JSON body
+pub fn route(request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/documents") => match annotations::annotated_documents() {
+            Ok(paths) => Response::ok(json!(paths)),
+            Err(e) => Response::error(500, e.to_string()),
+        },
+        ("GET", "/annotations") => get_annotations(request.query_param("pdf")),
+        ("GET", "/positions") => match request.query_param("pdf") {
+            Some(pdf_path) => get_positions(pdf_path),
+            None => Response::error(400, "Missing pdf parameter"),
+        },
+        ("POST", "/annotations") => post_annotation(request),
+        (_, "/documents" | "/annotations" | "/positions") => {
+            Response::error(405, "Method not allowed")
+        }
+        _ => Response::error(404, "Not found"),
+    }
+}
+
+fn get_annotations(pdf_path: Option<&str>) -> Response {
+    let pdf_paths = match pdf_path {
+        Some(path) => vec![path.to_string()],
+        None => match annotations::annotated_documents() {
+            Ok(paths) => paths,
+            Err(e) => return Response::error(500, e.to_string()),
+        },
+    };
+
+    let mut list = Vec::new();
+    for path in pdf_paths {
+        match annotations::load_annotations_for_pdf(&path) {
+            Ok(anns) => list.extend(anns.iter().map(annotation_json)),
+            Err(e) => return Response::error(500, e.to_string()),
+        }
+    }
+    Response::ok(Value::Array(list))
+}
+
+fn get_positions(pdf_path: &str) -> Response {
+    let saved = match positions::load_positions_for_pdf(pdf_path) {
+        Ok(saved) => saved,
+        Err(e) => return Response::error(500, e.to_string()),
+    };
+    let saved: Vec<Value> = saved
+        .iter()
+        .map(|position| {
+            json!({
+                "id": position.id,
+                "name": position.name,
+                "page": position.page_index,
+                "word": position.word_index,
+                "created_at": position.created_at,
+            })
+        })
+        .collect();
+    let last_cursor = session::load_session(pdf_path)
+        .ok()
+        .flatten()
+        .and_then(|session| session.cursor)
+        .map(|cursor| json!({ "page": cursor.page_index, "word": cursor.word_index }));
+
+    Response::ok(json!({
+        "pdf_path": pdf_path,
+        "saved": saved,
+        "last_cursor": last_cursor,
+    }))
+}
+
+fn post_annotation(request: &Request) -> Response {
+    // Browsers preflight JSON requests, which this server never allows, so web
+    // pages cannot create annotations behind the user's back
+    let is_json = request
+        .content_type
+        .as_deref()
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if !is_json {
+        return Response::error(415, "Content-Type must be application/json");
+    }
+
+    let new: NewAnnotation = match serde_json::from_slice(&request.body) {
+        Ok(new) => new,
+        Err(e) => return Response::error(400, format!("Invalid annotation: {}", e)),
+    };
+    if (new.start_page, new.start_word) > (new.end_page, new.end_word) {
+        return Response::error(400, "Annotation ends before it starts");
+    }
+
+    let result = annotations::save_annotation(
+        &new.pdf_path,
+        new.start_page,
+        new.start_word,
+        new.end_page,
+        new.end_word,
+        &new.selected_text,
+        &new.note,
+        AnnotationColor::from_name(&new.color),
+        &annotations::parse_tags(&new.tags.join(",")),
+    )
+    .and_then(annotations::get_annotation);
+
+    match result {
+        Ok(annotation) => Response {
+            status: 201,
+            body: annotation_json(&annotation),
+        },
+        // Another instance is writing, the client can try again
+        Err(e @ AnnotationError::Locked) => Response::error(503, e.to_string()),
+        Err(e) => Response::error(500, e.to_string()),
+    }
+}
+
+fn annotation_json(annotation: &Annotation) -> Value {
+    json!({
+        "id": annotation.id,
+        "pdf_path": annotation.pdf_path,
+        "start_page": annotation.start_page,
+        "start_word": annotation.start_word,
+        "end_page": annotation.end_page,
+        "end_word": annotation.end_word,
+        "selected_text": annotation.selected_text,
+        "note": annotation.note,
+        "color": annotation.color.as_str(),
+        "tags": annotation.tags,
+        "created_at": annotation.created_at,
+        "updated_at": annotation.updated_at,
+    })
+}
+
+/// Split `a=1&b=2` into decoded pairs
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&key.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes, keeping invalid escapes as they are
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            percent_decode("%2Fhome%2Fme%2Fa%20b.pdf"),
+            "/home/me/a b.pdf"
+        );
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("pdf=%2Ftmp%2Fa+b.pdf&flag"),
+            vec![
+                ("pdf".to_string(), "/tmp/a b.pdf".to_string()),
+                ("flag".to_string(), String::new())
+            ]
+        );
+        assert!(parse_query("").is_empty());
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /annotations?pdf=%2Fa.pdf HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+        let request = read_request(&mut raw.as_bytes()).unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/annotations");
+        assert_eq!(request.query_param("pdf"), Some("/a.pdf"));
+        assert_eq!(request.content_type.as_deref(), Some("application/json"));
+        assert_eq!(request.body, b"{}");
+
+        assert_eq!(read_request(&mut "".as_bytes()), Ok(None));
+        assert!(read_request(&mut "GARBAGE\r\n\r\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_route_rejects_without_touching_the_database() {
+        let request = |method: &str, path: &str| Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(route(&request("GET", "/nothing")).status, 404);
+        assert_eq!(route(&request("DELETE", "/annotations")).status, 405);
+        assert_eq!(route(&request("GET", "/positions")).status, 400);
+        // Form posts from web pages are refused
+        assert_eq!(route(&request("POST", "/annotations")).status, 415);
+
+        let mut bad_body = request("POST", "/annotations");
+        bad_body.content_type = Some("application/json".to_string());
+        bad_body.body = b"{\"note\": 1}".to_vec();
+        assert_eq!(route(&bad_body).status, 400);
+    }
+}
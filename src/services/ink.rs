@@ -0,0 +1,202 @@
+use rusqlite::params;
+
+use crate::services::annotations::{AnnotationColor, AnnotationError};
+use crate::services::database;
+
+/// Strokes keep a point only once the pen moved this far from the last one, in
+/// PDF points, so slow drawing doesn't store hundreds of points per line
+pub const MIN_POINT_DISTANCE: f64 = 1.0;
+
+/// What is drawn while ink mode is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InkTool {
+    /// A free-hand line following the pointer
+    #[default]
+    Pen,
+    /// A rectangle from the press to the release
+    Rectangle,
+}
+
+impl InkTool {
+    /// Name stored in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InkTool::Pen => "pen",
+            InkTool::Rectangle => "rectangle",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InkTool::Pen => "Pen",
+            InkTool::Rectangle => "Rectangle",
+        }
+    }
+
+    /// Parse a stored tool name, falling back to the pen for unknown names
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "rectangle" => InkTool::Rectangle,
+            _ => InkTool::Pen,
+        }
+    }
+}
+
+/// A drawing on a page. Points are in PDF points (origin at the bottom-left of
+/// the unrotated page), so it follows the page at any zoom and rotation.
+/// Rectangles have their two opposite corners as points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InkDrawing {
+    pub id: i64,
+    pub pdf_path: String,
+    pub page_index: usize,
+    pub tool: InkTool,
+    pub points: Vec<(f64, f64)>,
+    pub color: AnnotationColor,
+}
+
+/// Save a drawing, returning its ID
+pub fn save_drawing(
+    pdf_path: &str,
+    page_index: usize,
+    tool: InkTool,
+    points: &[(f64, f64)],
+    color: AnnotationColor,
+) -> Result<i64, AnnotationError> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let points =
+        serde_json::to_string(points).map_err(|e| AnnotationError::DatabaseError(e.to_string()))?;
+
+    database::write(|conn| {
+        conn.execute(
+            "INSERT INTO ink_drawings (pdf_path, page_index, tool, points, color, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                pdf_path,
+                page_index as i64,
+                tool.as_str(),
+                points,
+                color.as_str(),
+                now
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    })
+    .map_err(AnnotationError::from)
+}
+
+/// Delete a drawing by ID
+pub fn delete_drawing(id: i64) -> Result<(), AnnotationError> {
+    let rows_affected = database::write(|conn| {
+        conn.execute("DELETE FROM ink_drawings WHERE id = ?1", params![id])
+    })?;
+    if rows_affected == 0 {
+        return Err(AnnotationError::NotFound);
+    }
+    Ok(())
+}
+
+/// Every drawing of a document, in the order they were made
+pub fn load_drawings_for_pdf(pdf_path: &str) -> Result<Vec<InkDrawing>, AnnotationError> {
+    let conn = database::open()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, pdf_path, page_index, tool, points, color FROM ink_drawings
+         WHERE pdf_path = ?1 ORDER BY id",
+    )?;
+
+    let drawings = stmt
+        .query_map(params![pdf_path], |row| {
+            let points: String = row.get(4)?;
+            Ok(InkDrawing {
+                id: row.get(0)?,
+                pdf_path: row.get(1)?,
+                page_index: row.get::<_, i64>(2)? as usize,
+                tool: InkTool::from_name(&row.get::<_, String>(3)?),
+                points: serde_json::from_str(&points).unwrap_or_default(),
+                color: AnnotationColor::from_name(&row.get::<_, String>(5)?),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(drawings)
+}
+
+/// The points kept from a drawing in progress: every point of a pen stroke at
+/// least `MIN_POINT_DISTANCE` from the previous kept one (the last point is always
+/// kept so the line ends under the pointer), or the first and last corners of a
+/// rectangle
+pub fn finish_points(tool: InkTool, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+        return Vec::new();
+    };
+    if tool == InkTool::Rectangle {
+        return vec![first, last];
+    }
+
+    let mut kept = vec![first];
+    for &point in &points[1..] {
+        let &(x, y) = kept.last().unwrap_or(&first);
+        if (point.0 - x).hypot(point.1 - y) >= MIN_POINT_DISTANCE {
+            kept.push(point);
+        }
+    }
+    if kept.last() != Some(&last) {
+        kept.push(last);
+    }
+    kept
+}
+
+/// The most recent drawing on `page_index`, the one `:erase` removes
+pub fn last_drawing_on_page(drawings: &[InkDrawing], page_index: usize) -> Option<&InkDrawing> {
+    drawings.iter().rev().find(|d| d.page_index == page_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ink_tool_names() {
+        for tool in [InkTool::Pen, InkTool::Rectangle] {
+            assert_eq!(InkTool::from_name(tool.as_str()), tool);
+        }
+        assert_eq!(InkTool::from_name("unknown"), InkTool::Pen);
+    }
+
+    #[test]
+    fn test_finish_points() {
+        assert!(finish_points(InkTool::Pen, &[]).is_empty());
+
+        let points = [(0.0, 0.0), (0.2, 0.2), (2.0, 0.0), (2.5, 0.0), (2.6, 0.0)];
+        assert_eq!(
+            finish_points(InkTool::Pen, &points),
+            vec![(0.0, 0.0), (2.0, 0.0), (2.6, 0.0)]
+        );
+        assert_eq!(
+            finish_points(InkTool::Rectangle, &points),
+            vec![(0.0, 0.0), (2.6, 0.0)]
+        );
+        assert_eq!(finish_points(InkTool::Pen, &[(1.0, 1.0)]), vec![(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_last_drawing_on_page() {
+        let drawing = |id: i64, page_index: usize| InkDrawing {
+            id,
+            pdf_path: "doc.pdf".to_string(),
+            page_index,
+            tool: InkTool::Pen,
+            points: vec![(0.0, 0.0)],
+            color: AnnotationColor::Red,
+        };
+        let drawings = [drawing(1, 0), drawing(2, 1), drawing(3, 0)];
+
+        assert_eq!(last_drawing_on_page(&drawings, 0).map(|d| d.id), Some(3));
+        assert_eq!(last_drawing_on_page(&drawings, 1).map(|d| d.id), Some(2));
+        assert_eq!(last_drawing_on_page(&drawings, 2), None);
+    }
+}
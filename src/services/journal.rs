@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::modes::WordCursor;
+
+/// An annotation note that was being written but not saved yet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationDraft {
+    /// Set when an existing annotation was being edited
+    pub annotation_id: Option<i64>,
+    pub start_page: usize,
+    pub start_word: usize,
+    pub end_page: usize,
+    pub end_word: usize,
+    pub note: String,
+}
+
+impl AnnotationDraft {
+    pub fn range(&self) -> (WordCursor, WordCursor) {
+        (
+            WordCursor::new(self.start_page, self.start_word),
+            WordCursor::new(self.end_page, self.end_word),
+        )
+    }
+}
+
+/// Snapshot of what the user was doing, written periodically while a document is open
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub pdf_path: String,
+    pub page_index: usize,
+    pub word_index: usize,
+    pub draft: Option<AnnotationDraft>,
+}
+
+impl JournalEntry {
+    pub fn cursor(&self) -> WordCursor {
+        WordCursor::new(self.page_index, self.word_index)
+    }
+}
+
+/// Error type for journal operations
+#[derive(Debug)]
+pub enum JournalError {
+    IoError(String),
+    SerializeError(String),
+}
+
+impl std::fmt::Display for JournalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JournalError::IoError(msg) => write!(f, "IO error: {}", msg),
+            JournalError::SerializeError(msg) => write!(f, "Serialize error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for JournalError {}
+
+impl From<std::io::Error> for JournalError {
+    fn from(err: std::io::Error) -> Self {
+        JournalError::IoError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for JournalError {
+    fn from(err: serde_json::Error) -> Self {
+        JournalError::SerializeError(err.to_string())
+    }
+}
+
+/// Returns the path to the journal file. It only exists while Eyers is running, so
+/// finding one at startup means the previous run did not exit cleanly.
+fn get_journal_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|p| p.join("eyers").join("journal.json"))
+}
+
+/// Replace the journal with `entry`. Written to a temporary file first so a crash
+/// mid-write never leaves a truncated journal behind.
+pub fn write_journal(entry: &JournalEntry) -> Result<(), JournalError> {
+    let path = get_journal_path()
+        .ok_or_else(|| JournalError::IoError("Could not determine data directory".to_string()))?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec(entry)?)?;
+    std::fs::rename(&tmp_path, &path)?;
+
+    Ok(())
+}
+
+/// Read the journal left by a previous run, if any
+pub fn read_journal() -> Option<JournalEntry> {
+    let contents = std::fs::read(get_journal_path()?).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+/// Remove the journal, called on clean exit and once a crash was handled
+pub fn clear_journal() -> Result<(), JournalError> {
+    let Some(path) = get_journal_path() else {
+        return Ok(());
+    };
+
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
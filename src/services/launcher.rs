@@ -0,0 +1,86 @@
+use gtk::gio;
+use gtk::glib::{self, variant::ToVariant};
+use std::cell::{Cell, RefCell};
+
+/// Desktop file the launcher entry belongs to, as docks and task managers know it
+const APP_URI: &str = "application://org.gtk_rs.eyers.desktop";
+const LAUNCHER_ENTRY_PATH: &str = "/com/canonical/unity/launcherentry/eyers";
+const LAUNCHER_ENTRY_INTERFACE: &str = "com.canonical.Unity.LauncherEntry";
+
+thread_local! {
+    /// Session bus, looked up on the first update. None once it turned out unavailable.
+    static BUS: RefCell<Option<Option<gio::DBusConnection>>> = const { RefCell::new(None) };
+    /// Last progress sent, in whole percents, to only signal changes
+    static LAST_PERCENT: Cell<Option<Option<u32>>> = const { Cell::new(None) };
+}
+
+/// "paper.pdf — page 12/40 (30%)", or just the file name while the document
+/// has no pages yet
+pub fn window_title(file_name: &str, page: usize, page_count: usize, percent: f64) -> String {
+    if page_count == 0 {
+        return file_name.to_string();
+    }
+    format!(
+        "{} — page {}/{} ({:.0}%)",
+        file_name, page, page_count, percent
+    )
+}
+
+/// Show `percent` (0 to 100) as a progress bar on the application's launcher
+/// icon, or hide it with None. Uses the Unity launcher entry protocol, which
+/// docks like Plank and Dash to Dock and the KDE task manager follow; does
+/// nothing where the session bus is unavailable.
+pub fn set_progress(percent: Option<f64>) {
+    let rounded = percent.map(|percent| percent.round().clamp(0.0, 100.0) as u32);
+    if LAST_PERCENT.get() == Some(rounded) {
+        return;
+    }
+    LAST_PERCENT.set(Some(rounded));
+
+    let Some(connection) = session_bus() else {
+        return;
+    };
+
+    let properties = glib::VariantDict::new(None);
+    properties.insert_value(
+        "progress",
+        &(rounded.unwrap_or(0) as f64 / 100.0).to_variant(),
+    );
+    properties.insert_value("progress-visible", &rounded.is_some().to_variant());
+    let parameters = glib::Variant::tuple_from_iter([APP_URI.to_variant(), properties.end()]);
+
+    if let Err(e) = connection.emit_signal(
+        None,
+        LAUNCHER_ENTRY_PATH,
+        LAUNCHER_ENTRY_INTERFACE,
+        "Update",
+        Some(&parameters),
+    ) {
+        eprintln!("Failed to update launcher progress: {}", e);
+    }
+}
+
+fn session_bus() -> Option<gio::DBusConnection> {
+    BUS.with_borrow_mut(|bus| {
+        bus.get_or_insert_with(|| {
+            gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE)
+                .map_err(|e| eprintln!("No session bus for launcher progress: {}", e))
+                .ok()
+        })
+        .clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_title() {
+        assert_eq!(
+            window_title("paper.pdf", 12, 40, 29.6),
+            "paper.pdf — page 12/40 (30%)"
+        );
+        assert_eq!(window_title("paper.pdf", 0, 0, 0.0), "paper.pdf");
+    }
+}
@@ -0,0 +1,297 @@
+use gtk::glib;
+use std::collections::HashMap;
+
+use crate::services::dictionary::Language;
+use crate::services::reading_log::{LoggedEvent, ReadingEvent};
+
+/// Longest pause between two events in a document still counted as reading, in
+/// minutes. Longer ones are breaks.
+pub const MAX_READING_GAP_MINUTES: u32 = 10;
+/// Documents listed as the most read
+const MOST_READ_COUNT: usize = 5;
+/// Days in the reading time chart, ending today
+pub const CHART_DAYS: usize = 14;
+
+/// Time spent in a document and pages read of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentReading {
+    pub pdf_path: String,
+    pub minutes: u32,
+    pub pages: usize,
+}
+
+/// Summary of everything recorded about the library
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryStats {
+    pub documents: usize,
+    pub annotations: usize,
+    /// Words looked up per language name, the most looked up language first
+    pub lookups_by_language: Vec<(String, usize)>,
+    /// Documents read the longest, the first one most
+    pub most_read: Vec<DocumentReading>,
+    /// Minutes read on each of the chart days, oldest first
+    pub daily_minutes: Vec<(String, u32)>,
+}
+
+impl LibraryStats {
+    /// Summarize the library from the recorded data.
+    ///
+    /// - `known_documents`: paths of documents opened or annotated, repeats allowed
+    /// - `lookup_counts`: words looked up per language code
+    /// - `events`: the reading log, oldest first
+    /// - `days`: `YYYY-MM-DD` days of the chart, oldest first
+    pub fn summarize(
+        known_documents: &[String],
+        annotations: usize,
+        lookup_counts: &[(String, usize)],
+        events: &[LoggedEvent],
+        days: &[String],
+    ) -> Self {
+        let mut documents: Vec<&str> = known_documents
+            .iter()
+            .map(String::as_str)
+            .chain(events.iter().map(|event| event.pdf_path.as_str()))
+            .collect();
+        documents.sort_unstable();
+        documents.dedup();
+
+        let minutes = reading_minutes(events);
+        let daily_minutes = days
+            .iter()
+            .map(|day| {
+                let total: u32 = minutes
+                    .iter()
+                    .filter(|((minutes_day, _), _)| *minutes_day == day.as_str())
+                    .map(|(_, minutes)| minutes)
+                    .sum();
+                (day.clone(), total)
+            })
+            .collect();
+
+        Self {
+            documents: documents.len(),
+            annotations,
+            lookups_by_language: lookups_by_language(lookup_counts),
+            most_read: most_read(events, &minutes),
+            daily_minutes,
+        }
+    }
+
+    /// Words looked up in every language
+    pub fn lookups(&self) -> usize {
+        self.lookups_by_language
+            .iter()
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// Counts per language code merged by language name, codes no longer offered
+/// and lookups recorded without one counting as "Unknown"
+fn lookups_by_language(lookup_counts: &[(String, usize)]) -> Vec<(String, usize)> {
+    let mut by_name: Vec<(String, usize)> = Vec::new();
+    for (code, count) in lookup_counts {
+        let name = Language::from_code(code)
+            .map(|language| language.name())
+            .unwrap_or("Unknown");
+        match by_name.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, total)) => *total += count,
+            None => by_name.push((name.to_string(), *count)),
+        }
+    }
+    by_name.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    by_name
+}
+
+/// Minutes of an `HH:MM` time since midnight
+fn minute_of_day(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    Some(hours.parse::<u32>().ok()? * 60 + minutes.parse::<u32>().ok()?)
+}
+
+/// Minutes spent reading per (day, document). The time between two events of
+/// the same document on the same day counts as reading, unless it is longer
+/// than `MAX_READING_GAP_MINUTES`.
+fn reading_minutes(events: &[LoggedEvent]) -> HashMap<(&str, &str), u32> {
+    let mut minutes = HashMap::new();
+    for pair in events.windows(2) {
+        let (earlier, later) = (&pair[0], &pair[1]);
+        if earlier.day != later.day || earlier.pdf_path != later.pdf_path {
+            continue;
+        }
+        let (Some(start), Some(end)) = (minute_of_day(&earlier.time), minute_of_day(&later.time))
+        else {
+            continue;
+        };
+        // Going back in time happens when the clock is changed
+        let gap = end.saturating_sub(start);
+        if gap <= MAX_READING_GAP_MINUTES {
+            *minutes
+                .entry((later.day.as_str(), later.pdf_path.as_str()))
+                .or_insert(0) += gap;
+        }
+    }
+    minutes
+}
+
+/// Documents read the longest, then with the most pages read
+fn most_read(events: &[LoggedEvent], minutes: &HashMap<(&str, &str), u32>) -> Vec<DocumentReading> {
+    let mut documents: HashMap<&str, DocumentReading> = HashMap::new();
+    for (&(_, pdf_path), &spent) in minutes {
+        document_entry(&mut documents, pdf_path).minutes += spent;
+    }
+    for event in events {
+        if let ReadingEvent::PagesRead {
+            first_page,
+            last_page,
+        } = event.event
+        {
+            document_entry(&mut documents, &event.pdf_path).pages +=
+                last_page.saturating_sub(first_page) + 1;
+        }
+    }
+
+    let mut most_read: Vec<DocumentReading> = documents.into_values().collect();
+    most_read.sort_by(|a, b| {
+        b.minutes
+            .cmp(&a.minutes)
+            .then_with(|| b.pages.cmp(&a.pages))
+            .then_with(|| a.pdf_path.cmp(&b.pdf_path))
+    });
+    most_read.truncate(MOST_READ_COUNT);
+    most_read
+}
+
+/// Reading of a document, added when missing
+fn document_entry<'a, 'b>(
+    documents: &'b mut HashMap<&'a str, DocumentReading>,
+    pdf_path: &'a str,
+) -> &'b mut DocumentReading {
+    documents
+        .entry(pdf_path)
+        .or_insert_with(|| DocumentReading {
+            pdf_path: pdf_path.to_string(),
+            minutes: 0,
+            pages: 0,
+        })
+}
+
+/// The last `count` local days as `YYYY-MM-DD`, oldest first and ending today
+pub fn last_days(count: usize) -> Vec<String> {
+    let Ok(today) = glib::DateTime::now_local() else {
+        return Vec::new();
+    };
+    (0..count)
+        .rev()
+        .filter_map(|days_ago| today.add_days(-(days_ago as i32)).ok())
+        .filter_map(|day| day.format("%Y-%m-%d").ok())
+        .map(|day| day.to_string())
+        .collect()
+}
+
+/// "45 min" or "2 h 05 min"
+pub fn format_minutes(minutes: u32) -> String {
+    if minutes < 60 {
+        format!("{} min", minutes)
+    } else {
+        format!("{} h {:02} min", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logged(pdf_path: &str, day: &str, time: &str, event: ReadingEvent) -> LoggedEvent {
+        LoggedEvent {
+            pdf_path: pdf_path.to_string(),
+            day: day.to_string(),
+            time: time.to_string(),
+            event,
+        }
+    }
+
+    fn read(first_page: usize, last_page: usize) -> ReadingEvent {
+        ReadingEvent::PagesRead {
+            first_page,
+            last_page,
+        }
+    }
+
+    #[test]
+    fn test_lookups_by_language() {
+        let counts = [
+            (String::new(), 1),
+            ("en".to_string(), 2),
+            ("es".to_string(), 5),
+            ("xx".to_string(), 3),
+        ];
+        assert_eq!(
+            lookups_by_language(&counts),
+            vec![
+                ("Spanish".to_string(), 5),
+                ("Unknown".to_string(), 4),
+                ("English".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reading_minutes() {
+        let events = [
+            logged("/a.pdf", "2024-03-01", "10:00", ReadingEvent::Opened),
+            logged("/a.pdf", "2024-03-01", "10:05", read(0, 3)),
+            // A break
+            logged("/a.pdf", "2024-03-01", "11:00", read(4, 4)),
+            logged("/a.pdf", "2024-03-01", "11:08", read(5, 6)),
+            // Switching documents isn't reading either of them
+            logged("/b.pdf", "2024-03-01", "11:09", ReadingEvent::Opened),
+            logged("/b.pdf", "2024-03-02", "09:00", read(0, 0)),
+        ];
+        let minutes = reading_minutes(&events);
+        assert_eq!(minutes.get(&("2024-03-01", "/a.pdf")), Some(&13));
+        assert_eq!(minutes.get(&("2024-03-01", "/b.pdf")), None);
+        assert_eq!(minutes.len(), 1);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let events = [
+            logged("/a.pdf", "2024-03-01", "10:00", ReadingEvent::Opened),
+            logged("/a.pdf", "2024-03-01", "10:05", read(0, 3)),
+            logged("/b.pdf", "2024-03-02", "09:00", ReadingEvent::Opened),
+            logged("/b.pdf", "2024-03-02", "09:10", read(0, 0)),
+            logged("/c.pdf", "2024-03-02", "09:30", read(0, 9)),
+        ];
+        let known = ["/a.pdf".to_string(), "/d.pdf".to_string()];
+        let days = ["2024-03-01".to_string(), "2024-03-02".to_string()];
+        let stats = LibraryStats::summarize(&known, 7, &[("en".to_string(), 3)], &events, &days);
+
+        assert_eq!(stats.documents, 4);
+        assert_eq!(stats.annotations, 7);
+        assert_eq!(stats.lookups(), 3);
+        assert_eq!(
+            stats.daily_minutes,
+            vec![
+                ("2024-03-01".to_string(), 5),
+                ("2024-03-02".to_string(), 10)
+            ]
+        );
+        let most_read: Vec<(&str, u32, usize)> = stats
+            .most_read
+            .iter()
+            .map(|document| (document.pdf_path.as_str(), document.minutes, document.pages))
+            .collect();
+        assert_eq!(
+            most_read,
+            vec![("/b.pdf", 10, 1), ("/a.pdf", 5, 4), ("/c.pdf", 0, 10)]
+        );
+    }
+
+    #[test]
+    fn test_format_minutes() {
+        assert_eq!(format_minutes(0), "0 min");
+        assert_eq!(format_minutes(45), "45 min");
+        assert_eq!(format_minutes(125), "2 h 05 min");
+    }
+}
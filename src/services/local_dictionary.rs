@@ -0,0 +1,569 @@
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::dictionary::{DictionaryBackend, LANGUAGES, Language, LookupResult, Sense};
+
+const IFO_MAGIC: &str = "StarDict's dict ifo file";
+const DICTD_BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Error type for reading local dictionary files
+#[derive(Debug)]
+pub enum LocalDictionaryError {
+    IoError(String),
+    InvalidFormat(String),
+}
+
+impl std::fmt::Display for LocalDictionaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalDictionaryError::IoError(msg) => write!(f, "IO error: {}", msg),
+            LocalDictionaryError::InvalidFormat(msg) => write!(f, "Invalid format: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LocalDictionaryError {}
+
+impl From<std::io::Error> for LocalDictionaryError {
+    fn from(err: std::io::Error) -> Self {
+        LocalDictionaryError::IoError(err.to_string())
+    }
+}
+
+/// Header of a StarDict dictionary (.ifo file)
+#[derive(Debug, PartialEq)]
+struct IfoInfo {
+    book_name: String,
+    /// Width of the article offsets in the .idx file, 32 or 64
+    offset_bits: u32,
+    /// Field types shared by every article, in which case they are not stored per article
+    same_type_sequence: Option<String>,
+}
+
+#[derive(Debug)]
+enum Format {
+    StarDict { same_type_sequence: Option<String> },
+    Dictd,
+}
+
+/// A StarDict or dictd dictionary loaded in memory
+#[derive(Debug)]
+pub struct LocalDictionary {
+    pub name: String,
+    format: Format,
+    /// Lowercased headword -> (offset, size) of its articles in `data`
+    index: HashMap<String, Vec<(u64, u64)>>,
+    data: Vec<u8>,
+}
+
+impl LocalDictionary {
+    /// Open a StarDict dictionary from its .ifo file, next to its .idx and .dict(.dz)
+    pub fn open_stardict(ifo_path: &Path) -> Result<Self, LocalDictionaryError> {
+        let info = parse_ifo(&std::fs::read_to_string(ifo_path)?)?;
+        let index_bytes = read_possibly_compressed(&ifo_path.with_extension("idx"))?;
+        let data = read_possibly_compressed(&ifo_path.with_extension("dict"))?;
+
+        Ok(Self {
+            name: info.book_name,
+            format: Format::StarDict {
+                same_type_sequence: info.same_type_sequence,
+            },
+            index: group_entries(parse_stardict_index(&index_bytes, info.offset_bits)?),
+            data,
+        })
+    }
+
+    /// Open a dictd dictionary from its .index file, next to its .dict(.dz)
+    pub fn open_dictd(index_path: &Path) -> Result<Self, LocalDictionaryError> {
+        let entries = parse_dictd_index(&std::fs::read_to_string(index_path)?)?;
+        let data = read_possibly_compressed(&index_path.with_extension("dict"))?;
+
+        let mut dictionary = Self {
+            name: file_stem(index_path),
+            format: Format::Dictd,
+            index: group_entries(entries),
+            data,
+        };
+        // dictd keeps the dictionary name as an article under a reserved headword
+        let short_name = ["00databaseshort", "00-database-short"]
+            .iter()
+            .find_map(|key| dictionary.articles(key).into_iter().next())
+            .and_then(|article| {
+                article
+                    .lines()
+                    .map(str::trim)
+                    .rev()
+                    .find(|line| !line.is_empty())
+                    .map(str::to_string)
+            });
+        if let Some(name) = short_name {
+            dictionary.name = name;
+        }
+        Ok(dictionary)
+    }
+
+    /// Articles of `word`, as plain text
+    pub fn articles(&self, word: &str) -> Vec<String> {
+        let Some(locations) = self.index.get(&word.to_lowercase()) else {
+            return Vec::new();
+        };
+
+        locations
+            .iter()
+            .filter_map(|&(offset, size)| {
+                let start = usize::try_from(offset).ok()?;
+                let end = start.checked_add(usize::try_from(size).ok()?)?;
+                let raw = self.data.get(start..end)?;
+                let text = match &self.format {
+                    Format::StarDict { same_type_sequence } => {
+                        stardict_article_text(raw, same_type_sequence.as_deref())
+                    }
+                    Format::Dictd => dictd_article_text(raw, word),
+                };
+                (!text.is_empty()).then_some(text)
+            })
+            .collect()
+    }
+}
+
+/// StarDict and dictd dictionaries found in a directory
+pub struct LocalDictionaries {
+    dir: PathBuf,
+}
+
+impl LocalDictionaries {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl DictionaryBackend for LocalDictionaries {
+    /// Local dictionaries are not tagged with a language, so every one of them is searched
+    fn lookup(&self, word: &str, _lang: Language) -> Option<LookupResult> {
+        let senses: Vec<Sense> = dictionaries_in(&self.dir)
+            .iter()
+            .flat_map(|dictionary| {
+                dictionary.articles(word).into_iter().map(|article| Sense {
+                    pos: dictionary.name.clone(),
+                    gloss: article,
+                    etymology: None,
+                    translations: Vec::new(),
+                })
+            })
+            .collect();
+
+        if senses.is_empty() {
+            return None;
+        }
+
+        Some(LookupResult {
+            word: word.to_string(),
+            senses,
+        })
+    }
+
+    fn languages(&self) -> Vec<Language> {
+        LANGUAGES.to_vec()
+    }
+}
+
+/// Directory the dictionaries were last loaded from, with its dictionaries
+type LoadedDictionaries = Option<(PathBuf, Arc<Vec<LocalDictionary>>)>;
+
+/// Dictionaries of `dir`, loaded once and kept until another directory is used
+fn dictionaries_in(dir: &Path) -> Arc<Vec<LocalDictionary>> {
+    static LOADED: OnceLock<Mutex<LoadedDictionaries>> = OnceLock::new();
+
+    let mut loaded = LOADED
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some((_, dictionaries)) = loaded.as_ref().filter(|(loaded_dir, _)| loaded_dir == dir) {
+        return dictionaries.clone();
+    }
+
+    let dictionaries = Arc::new(load_dir(dir));
+    *loaded = Some((dir.to_path_buf(), dictionaries.clone()));
+    dictionaries
+}
+
+/// Load every dictionary in `dir` and its direct subdirectories, where StarDict
+/// packages usually unpack to
+pub fn load_dir(dir: &Path) -> Vec<LocalDictionary> {
+    let mut dictionaries = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        dirs.extend(
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir()),
+        );
+    }
+
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            let result = match path.extension().and_then(|e| e.to_str()) {
+                Some("ifo") => LocalDictionary::open_stardict(&path),
+                Some("index") => LocalDictionary::open_dictd(&path),
+                _ => continue,
+            };
+            match result {
+                Ok(dictionary) => dictionaries.push(dictionary),
+                Err(e) => eprintln!("Failed to load dictionary {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    dictionaries
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Read `path`, or its gzip/dictzip compressed `.gz` or `.dz` variant
+fn read_possibly_compressed(path: &Path) -> Result<Vec<u8>, LocalDictionaryError> {
+    if path.exists() {
+        return Ok(std::fs::read(path)?);
+    }
+
+    for suffix in ["dz", "gz"] {
+        let mut compressed = path.as_os_str().to_owned();
+        compressed.push(".");
+        compressed.push(suffix);
+        let compressed = PathBuf::from(compressed);
+        if compressed.exists() {
+            let mut data = Vec::new();
+            GzDecoder::new(std::fs::File::open(&compressed)?).read_to_end(&mut data)?;
+            return Ok(data);
+        }
+    }
+
+    Err(LocalDictionaryError::IoError(format!(
+        "{} not found",
+        path.display()
+    )))
+}
+
+fn group_entries(entries: Vec<(String, u64, u64)>) -> HashMap<String, Vec<(u64, u64)>> {
+    let mut index: HashMap<String, Vec<(u64, u64)>> = HashMap::new();
+    for (word, offset, size) in entries {
+        index
+            .entry(word.to_lowercase())
+            .or_default()
+            .push((offset, size));
+    }
+    index
+}
+
+fn parse_ifo(text: &str) -> Result<IfoInfo, LocalDictionaryError> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some(IFO_MAGIC) {
+        return Err(LocalDictionaryError::InvalidFormat(
+            "Missing StarDict header".to_string(),
+        ));
+    }
+
+    let mut info = IfoInfo {
+        book_name: String::new(),
+        offset_bits: 32,
+        same_type_sequence: None,
+    };
+    for line in lines {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "bookname" => info.book_name = value.to_string(),
+            "idxoffsetbits" => {
+                info.offset_bits = value.parse().map_err(|_| {
+                    LocalDictionaryError::InvalidFormat(format!("Bad idxoffsetbits {}", value))
+                })?
+            }
+            "sametypesequence" if !value.is_empty() => {
+                info.same_type_sequence = Some(value.to_string())
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+/// Parse a StarDict .idx file: NUL-terminated headwords, each followed by the
+/// big-endian offset and size of its article
+fn parse_stardict_index(
+    bytes: &[u8],
+    offset_bits: u32,
+) -> Result<Vec<(String, u64, u64)>, LocalDictionaryError> {
+    let offset_len = if offset_bits == 64 { 8 } else { 4 };
+    let mut entries = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let nul = bytes[pos..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| LocalDictionaryError::InvalidFormat("Truncated index".to_string()))?;
+        let word = String::from_utf8_lossy(&bytes[pos..pos + nul]).to_string();
+        pos += nul + 1;
+
+        let fields = bytes
+            .get(pos..pos + offset_len + 4)
+            .ok_or_else(|| LocalDictionaryError::InvalidFormat("Truncated index".to_string()))?;
+        let offset = fields[..offset_len]
+            .iter()
+            .fold(0u64, |n, &b| (n << 8) | b as u64);
+        let size = fields[offset_len..]
+            .iter()
+            .fold(0u64, |n, &b| (n << 8) | b as u64);
+        pos += offset_len + 4;
+
+        entries.push((word, offset, size));
+    }
+
+    Ok(entries)
+}
+
+/// Decode a dictd index number, written in base64 digits
+fn decode_dictd_number(s: &str) -> Option<u64> {
+    s.bytes().try_fold(0u64, |n, b| {
+        let digit = DICTD_BASE64.iter().position(|&d| d == b)? as u64;
+        n.checked_mul(64)?.checked_add(digit)
+    })
+}
+
+/// Parse a dictd .index file: one "headword<TAB>offset<TAB>size" line per article
+fn parse_dictd_index(text: &str) -> Result<Vec<(String, u64, u64)>, LocalDictionaryError> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t');
+            let word = fields.next().unwrap_or_default();
+            let offset = fields.next().and_then(decode_dictd_number);
+            let size = fields.next().and_then(decode_dictd_number);
+            match (offset, size) {
+                (Some(offset), Some(size)) => Ok((word.to_string(), offset, size)),
+                _ => Err(LocalDictionaryError::InvalidFormat(format!(
+                    "Bad index line: {}",
+                    line
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Text of a StarDict article. Each field is a type letter followed by its data;
+/// with a `same_type_sequence` the letters are left out.
+fn stardict_article_text(raw: &[u8], same_type_sequence: Option<&str>) -> String {
+    let mut fields: Vec<(char, &[u8])> = Vec::new();
+    let mut rest = raw;
+
+    match same_type_sequence {
+        Some(types) => {
+            let types: Vec<char> = types.chars().collect();
+            for (i, &kind) in types.iter().enumerate() {
+                if i + 1 == types.len() {
+                    // The last field runs to the end of the article
+                    fields.push((kind, rest));
+                    break;
+                }
+                let Some((data, remaining)) = split_field(kind, rest) else {
+                    break;
+                };
+                fields.push((kind, data));
+                rest = remaining;
+            }
+        }
+        None => {
+            while let Some((&kind, remaining)) = rest.split_first() {
+                let kind = kind as char;
+                let Some((data, remaining)) = split_field(kind, remaining) else {
+                    break;
+                };
+                fields.push((kind, data));
+                rest = remaining;
+            }
+        }
+    }
+
+    fields
+        .into_iter()
+        .filter_map(|(kind, data)| {
+            let text = String::from_utf8_lossy(data);
+            let text = text.trim_end_matches('\0');
+            match kind {
+                'm' | 'l' | 't' | 'y' => Some(text.trim().to_string()),
+                'g' | 'h' | 'x' | 'k' => Some(strip_tags(text)),
+                _ => None,
+            }
+        })
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Split one field off: lowercase types are NUL-terminated strings, uppercase ones
+/// are prefixed with their big-endian u32 size
+fn split_field(kind: char, data: &[u8]) -> Option<(&[u8], &[u8])> {
+    if kind.is_ascii_uppercase() {
+        let size = u32::from_be_bytes(data.get(..4)?.try_into().ok()?) as usize;
+        let end = 4usize.checked_add(size)?;
+        Some((data.get(4..end)?, &data[end..]))
+    } else {
+        let nul = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        Some((&data[..nul], data.get(nul + 1..).unwrap_or_default()))
+    }
+}
+
+/// Text of a dictd article, without the headword line most dictionaries repeat
+fn dictd_article_text(raw: &[u8], word: &str) -> String {
+    let text = String::from_utf8_lossy(raw);
+    let text = text.trim();
+    match text.split_once('\n') {
+        Some((first, rest)) if first.trim().eq_ignore_ascii_case(word) => rest.trim().to_string(),
+        _ => text.to_string(),
+    }
+}
+
+/// Drop the markup of HTML/XDXF/Pango articles, keeping line breaks
+fn strip_tags(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut chars = markup.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            text.push(c);
+            continue;
+        }
+        let tag: String = chars.by_ref().take_while(|&c| c != '>').collect();
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if matches!(name.as_str(), "br" | "p" | "div" | "li") && !text.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ifo() {
+        let info = parse_ifo(
+            "StarDict's dict ifo file\nversion=2.4.2\nbookname=WordNet\nwordcount=2\nsametypesequence=m\n",
+        )
+        .unwrap();
+        assert_eq!(
+            info,
+            IfoInfo {
+                book_name: "WordNet".to_string(),
+                offset_bits: 32,
+                same_type_sequence: Some("m".to_string()),
+            }
+        );
+        assert!(parse_ifo("bookname=WordNet").is_err());
+    }
+
+    #[test]
+    fn test_parse_stardict_index() {
+        let mut bytes = b"apple\0".to_vec();
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 12]);
+        bytes.extend_from_slice(b"pear\0");
+        bytes.extend_from_slice(&[0, 0, 0, 12, 0, 0, 1, 0]);
+
+        let entries = parse_stardict_index(&bytes, 32).unwrap();
+        assert_eq!(
+            entries,
+            vec![("apple".to_string(), 0, 12), ("pear".to_string(), 12, 256)]
+        );
+        assert!(parse_stardict_index(b"apple\0\0\0", 32).is_err());
+    }
+
+    #[test]
+    fn test_decode_dictd_number() {
+        assert_eq!(decode_dictd_number("A"), Some(0));
+        assert_eq!(decode_dictd_number("/"), Some(63));
+        assert_eq!(decode_dictd_number("BA"), Some(64));
+        assert_eq!(decode_dictd_number("b2"), Some(27 * 64 + 54));
+        assert_eq!(decode_dictd_number("#"), None);
+    }
+
+    #[test]
+    fn test_parse_dictd_index() {
+        let entries = parse_dictd_index("apple\tA\tq\npear\tq\tBA\n").unwrap();
+        assert_eq!(
+            entries,
+            vec![("apple".to_string(), 0, 42), ("pear".to_string(), 42, 64)]
+        );
+        assert!(parse_dictd_index("apple\tA").is_err());
+    }
+
+    #[test]
+    fn test_stardict_article_text() {
+        assert_eq!(
+            stardict_article_text(b"a round fruit", Some("m")),
+            "a round fruit"
+        );
+        assert_eq!(
+            stardict_article_text(b"/'pl/\0a round <b>fruit</b>", Some("th")),
+            "/'pl/\na round fruit"
+        );
+
+        let mut raw = b"ma round fruit\0".to_vec();
+        raw.push(b'W');
+        raw.extend_from_slice(&[0, 0, 0, 2, 0xff, 0xfe]);
+        raw.extend_from_slice(b"hsee <i>pear</i>\0");
+        assert_eq!(stardict_article_text(&raw, None), "a round fruit\nsee pear");
+    }
+
+    #[test]
+    fn test_dictd_article_text() {
+        assert_eq!(
+            dictd_article_text(b"Apple\n   a round fruit\n", "apple"),
+            "a round fruit"
+        );
+        assert_eq!(
+            dictd_article_text(b"a round fruit", "apple"),
+            "a round fruit"
+        );
+    }
+
+    #[test]
+    fn test_strip_tags() {
+        assert_eq!(
+            strip_tags("<b>apple</b><br/>a <i>round</i> fruit &amp; tree"),
+            "apple\na round fruit & tree"
+        );
+        assert_eq!(strip_tags("<p>one</p><p>two</p>"), "one\ntwo");
+    }
+}
@@ -0,0 +1,213 @@
+use rusqlite::Connection;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// Separates the parts of a key in the `key` column
+const KEY_SEPARATOR: &str = "\u{1f}";
+
+/// Values kept in memory, the least recently used first in `order` and dropped
+/// past `limit`
+pub struct LruCache<K, V> {
+    entries: HashMap<K, V>,
+    order: VecDeque<K>,
+    limit: usize,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> LruCache<K, V> {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            limit,
+        }
+    }
+
+    /// The value stored for a key, marked as just used
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let value = self.entries.get(key)?.clone();
+        self.touch(key);
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Store a value, dropping the least recently used past the limit
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.entries.insert(key.clone(), value).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > self.limit {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn touch(&mut self, key: &K) {
+        let Some(position) = self.order.iter().position(|existing| existing == key) else {
+            return;
+        };
+        if let Some(key) = self.order.remove(position) {
+            self.order.push_back(key);
+        }
+    }
+}
+
+/// Values kept in a table of an SQLite database in the data directory, keyed by
+/// several strings such as a word and its language. The least recently used are
+/// dropped past `limit`.
+pub struct SqliteCache {
+    conn: Connection,
+    table: &'static str,
+    limit: usize,
+}
+
+impl SqliteCache {
+    /// Opens `table` in the database `file_name`, creating them if necessary.
+    pub fn open(file_name: &str, table: &'static str, limit: usize) -> Option<Self> {
+        let path = dirs::data_dir()?.join("eyers").join(file_name);
+        std::fs::create_dir_all(path.parent()?).ok()?;
+        let conn = Connection::open(&path).ok()?;
+        Self::with_connection(conn, table, limit).ok()
+    }
+
+    fn with_connection(
+        conn: Connection,
+        table: &'static str,
+        limit: usize,
+    ) -> rusqlite::Result<Self> {
+        // Tables of caches laid out differently are only caches, so start over
+        let select = format!("SELECT key, value, used_at FROM {} LIMIT 0", table);
+        if conn.prepare(&select).is_err() {
+            conn.execute(&format!("DROP TABLE IF EXISTS {}", table), [])?;
+        }
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    key TEXT PRIMARY KEY,
+                    value TEXT NOT NULL,
+                    used_at INTEGER NOT NULL
+                )",
+                table
+            ),
+            [],
+        )?;
+        Ok(Self { conn, table, limit })
+    }
+
+    /// Increasing with every use of the cache, so the least recently used entries
+    /// are known even when several uses fall in the same second
+    fn clock(&self) -> i64 {
+        self.conn
+            .query_row(
+                &format!("SELECT COALESCE(MAX(used_at), 0) + 1 FROM {}", self.table),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(0)
+    }
+
+    /// The value stored for a key, marked as just used.
+    pub fn get(&self, key: &[&str]) -> Option<String> {
+        let key = key.join(KEY_SEPARATOR);
+        let value = self
+            .conn
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", self.table),
+                [&key],
+                |row| row.get(0),
+            )
+            .ok()?;
+        let _ = self.conn.execute(
+            &format!("UPDATE {} SET used_at = ?1 WHERE key = ?2", self.table),
+            rusqlite::params![self.clock(), key],
+        );
+        Some(value)
+    }
+
+    /// Store a value, dropping the least recently used entries past the limit.
+    pub fn insert(&self, key: &[&str], value: &str) {
+        let _ = self.conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} (key, value, used_at) VALUES (?1, ?2, ?3)",
+                self.table
+            ),
+            rusqlite::params![key.join(KEY_SEPARATOR), value, self.clock()],
+        );
+        let _ = self.conn.execute(
+            &format!(
+                "DELETE FROM {table} WHERE rowid NOT IN
+                    (SELECT rowid FROM {table} ORDER BY used_at DESC LIMIT ?1)",
+                table = self.table
+            ),
+            [self.limit as i64],
+        );
+    }
+
+    /// Number of entries in the cache.
+    pub fn entry_count(&self) -> usize {
+        self.conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", self.table), [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap_or(0) as usize
+    }
+
+    /// Forgets every entry. Returns false when the cache couldn't be emptied.
+    pub fn clear(&self) -> bool {
+        self.conn
+            .execute(&format!("DELETE FROM {}", self.table), [])
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_cache() {
+        let mut cache = LruCache::new(2);
+        cache.insert("one", 1);
+        cache.insert("two", 2);
+        assert_eq!(cache.get(&"one"), Some(1));
+
+        // Reading "one" made "two" the least recently used
+        cache.insert("three", 3);
+        assert!(!cache.contains_key(&"two"));
+        assert_eq!(cache.get(&"one"), Some(1));
+        assert_eq!(cache.get(&"three"), Some(3));
+
+        cache.insert("three", 4);
+        assert_eq!(cache.get(&"three"), Some(4));
+        assert_eq!(cache.order.len(), 2);
+    }
+
+    #[test]
+    fn test_sqlite_cache() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE entries (word TEXT, result TEXT)", [])
+            .unwrap();
+        let cache = SqliteCache::with_connection(conn, "entries", 2).unwrap();
+
+        cache.insert(&["word", "en"], "definition");
+        assert_eq!(cache.get(&["word", "en"]), Some("definition".to_string()));
+        assert_eq!(cache.get(&["word", "es"]), None);
+        // Parts are kept apart, so different splits are different keys
+        assert_eq!(cache.get(&["wor", "den"]), None);
+
+        cache.insert(&["other", "en"], "other definition");
+        cache.get(&["word", "en"]);
+        cache.insert(&["third", "en"], "third definition");
+        assert_eq!(cache.entry_count(), 2);
+        assert!(cache.get(&["other", "en"]).is_none());
+        assert!(cache.get(&["word", "en"]).is_some());
+
+        assert!(cache.clear());
+        assert_eq!(cache.entry_count(), 0);
+    }
+}
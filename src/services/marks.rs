@@ -0,0 +1,152 @@
+use rusqlite::params;
+
+use crate::modes::WordCursor;
+use crate::services::database::{self, DatabaseError};
+
+/// A position stored under a letter with `m{a-z}`, jumped back to with `'{a-z}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mark {
+    pub pdf_path: String,
+    pub letter: char,
+    pub page_index: usize,
+    pub word_index: usize,
+    pub created_at: i64,
+}
+
+impl Mark {
+    pub fn cursor(&self) -> WordCursor {
+        WordCursor::new(self.page_index, self.word_index)
+    }
+}
+
+/// Error type for mark operations
+#[derive(Debug)]
+pub enum MarkError {
+    DatabaseError(String),
+    InvalidLetter(char),
+}
+
+impl std::fmt::Display for MarkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarkError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+            MarkError::InvalidLetter(letter) => write!(f, "Invalid mark: '{}'", letter),
+        }
+    }
+}
+
+impl std::error::Error for MarkError {}
+
+impl From<rusqlite::Error> for MarkError {
+    fn from(err: rusqlite::Error) -> Self {
+        MarkError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for MarkError {
+    fn from(err: DatabaseError) -> Self {
+        MarkError::DatabaseError(err.to_string())
+    }
+}
+
+/// Marks are named by a lowercase letter, like vim's local marks
+pub fn is_mark_letter(letter: char) -> bool {
+    letter.is_ascii_lowercase()
+}
+
+/// Store `cursor` under `letter` for a PDF, replacing the mark that letter held
+pub fn set_mark(pdf_path: &str, letter: char, cursor: WordCursor) -> Result<(), MarkError> {
+    if !is_mark_letter(letter) {
+        return Err(MarkError::InvalidLetter(letter));
+    }
+
+    let conn = database::open()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    conn.execute(
+        "INSERT INTO marks (pdf_path, letter, page_index, word_index, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(pdf_path, letter) DO UPDATE SET
+            page_index = excluded.page_index,
+            word_index = excluded.word_index,
+            created_at = excluded.created_at",
+        params![
+            pdf_path,
+            letter.to_string(),
+            cursor.page_index as i64,
+            cursor.word_index as i64,
+            now
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Load all marks of a PDF, in alphabetical order
+pub fn load_marks(pdf_path: &str) -> Result<Vec<Mark>, MarkError> {
+    let conn = database::open()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT pdf_path, letter, page_index, word_index, created_at
+         FROM marks WHERE pdf_path = ?1 ORDER BY letter",
+    )?;
+
+    let marks = stmt
+        .query_map(params![pdf_path], |row| {
+            let letter: String = row.get(1)?;
+            Ok(Mark {
+                pdf_path: row.get(0)?,
+                letter: letter.chars().next().unwrap_or('a'),
+                page_index: row.get::<_, i64>(2)? as usize,
+                word_index: row.get::<_, i64>(3)? as usize,
+                created_at: row.get(4)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(marks)
+}
+
+/// The mark stored under `letter`, if any
+pub fn find_mark(marks: &[Mark], letter: char) -> Option<&Mark> {
+    marks.iter().find(|mark| mark.letter == letter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_mark_letter() {
+        assert!(is_mark_letter('a'));
+        assert!(is_mark_letter('z'));
+        assert!(!is_mark_letter('A'));
+        assert!(!is_mark_letter('1'));
+        assert!(!is_mark_letter('\''));
+    }
+
+    #[test]
+    fn test_set_mark_rejects_other_letters() {
+        let result = set_mark("/tmp/doc.pdf", 'Q', WordCursor::new(0, 0));
+        assert!(matches!(result, Err(MarkError::InvalidLetter('Q'))));
+    }
+
+    #[test]
+    fn test_find_mark() {
+        let mark = |letter, page_index| Mark {
+            pdf_path: "/tmp/doc.pdf".to_string(),
+            letter,
+            page_index,
+            word_index: 0,
+            created_at: 0,
+        };
+        let marks = vec![mark('a', 3), mark('c', 7)];
+
+        assert_eq!(find_mark(&marks, 'c').map(|m| m.page_index), Some(7));
+        assert_eq!(find_mark(&marks, 'b'), None);
+    }
+}
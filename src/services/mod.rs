@@ -1,5 +1,35 @@
+pub mod about;
 pub mod annotations;
 pub mod bookmarks;
+pub mod citation;
+pub mod crop;
+pub mod database;
 pub mod dictionary;
+pub mod flashcards;
+pub mod glossary;
+pub mod heat_map;
+pub mod http_api;
+pub mod ink;
+pub mod journal;
+pub mod launcher;
+pub mod library_stats;
+pub mod local_dictionary;
+pub mod lru_cache;
+pub mod marks;
+pub mod ocr;
+pub mod page_labels;
 pub mod pdf_text;
+pub mod positions;
+pub mod post_export;
+pub mod reading_log;
+pub mod reading_progress;
+pub mod recent_files;
+pub mod rotation;
+pub mod session;
+pub mod settings;
+pub mod startup;
+pub mod synctex;
+pub mod theme;
 pub mod translation;
+pub mod tts;
+pub mod web_search;
@@ -0,0 +1,351 @@
+use pdfium_render::prelude::PdfRect;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::Command;
+
+use crate::backend::{PageChar, RenderedPage};
+use crate::services::database::{self, DatabaseError};
+use crate::services::dictionary::Language;
+
+/// Tesseract's command line program
+const OCR_COMMAND: &str = "tesseract";
+
+/// Resolution scanned pages are rendered at for recognition
+pub const OCR_DPI: f64 = 300.0;
+
+/// TSV rows of this level are words
+const WORD_LEVEL: &str = "5";
+
+/// Error type for OCR operations
+#[derive(Debug)]
+pub enum OcrError {
+    /// `tesseract` is missing
+    NotInstalled,
+    /// Tesseract ran but could not read the page, with its message
+    Failed(String),
+    IoError(String),
+    DatabaseError(String),
+}
+
+impl std::fmt::Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::NotInstalled => write!(
+                f,
+                "Recognizing scanned pages needs Tesseract ({} not found)",
+                OCR_COMMAND
+            ),
+            OcrError::Failed(msg) => write!(f, "OCR failed: {}", msg),
+            OcrError::IoError(msg) => write!(f, "IO error: {}", msg),
+            OcrError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+impl From<std::io::Error> for OcrError {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == std::io::ErrorKind::NotFound {
+            OcrError::NotInstalled
+        } else {
+            OcrError::IoError(err.to_string())
+        }
+    }
+}
+
+impl From<rusqlite::Error> for OcrError {
+    fn from(err: rusqlite::Error) -> Self {
+        OcrError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for OcrError {
+    fn from(err: DatabaseError) -> Self {
+        OcrError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for OcrError {
+    fn from(err: serde_json::Error) -> Self {
+        OcrError::DatabaseError(err.to_string())
+    }
+}
+
+/// A word recognized on a page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrWord {
+    pub text: String,
+    /// (left, bottom, right, top) in page points, with the origin at the
+    /// bottom-left corner like `PageChar` bounds
+    pub bounds: (f64, f64, f64, f64),
+    /// Words with the same number are on one line, in reading order
+    pub line: usize,
+}
+
+/// Tesseract's name for the traineddata of `language`
+pub fn tesseract_language(language: Language) -> &'static str {
+    match language.code() {
+        "ar" => "ara",
+        "zh" => "chi_sim",
+        "nl" => "nld",
+        "fr" => "fra",
+        "de" => "deu",
+        "el" => "ell",
+        "hi" => "hin",
+        "it" => "ita",
+        "ja" => "jpn",
+        "ko" => "kor",
+        "la" => "lat",
+        "pl" => "pol",
+        "pt" => "por",
+        "ru" => "rus",
+        "es" => "spa",
+        "sv" => "swe",
+        "tr" => "tur",
+        _ => "eng",
+    }
+}
+
+/// Recognize the words of a rendered page with Tesseract. `scale` is the page
+/// points per pixel of the image and `page_height` the page's height in points.
+/// Blocks until Tesseract is done, so it runs on a worker thread.
+pub fn recognize(
+    page: &RenderedPage,
+    scale: f64,
+    page_height: f64,
+    language: &str,
+) -> Result<Vec<OcrWord>, OcrError> {
+    let image_path = std::env::temp_dir().join(format!("eyers-ocr-{}.ppm", std::process::id()));
+    write_ppm(page, &image_path)?;
+
+    let output = Command::new(OCR_COMMAND)
+        .arg(&image_path)
+        .arg("stdout")
+        .args(["-l", language])
+        .arg("tsv")
+        .output();
+    let _ = std::fs::remove_file(&image_path);
+    let output = output?;
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(OcrError::Failed(
+            message
+                .lines()
+                .last()
+                .unwrap_or("unknown error")
+                .to_string(),
+        ));
+    }
+
+    Ok(parse_tsv(
+        &String::from_utf8_lossy(&output.stdout),
+        scale,
+        page_height,
+    ))
+}
+
+/// Save the page as a binary PPM, which Tesseract reads without extra libraries
+fn write_ppm(page: &RenderedPage, path: &std::path::Path) -> Result<(), OcrError> {
+    let mut data = format!("P6\n{} {}\n255\n", page.width, page.height).into_bytes();
+    data.reserve(page.width as usize * page.height as usize * 3);
+    for row in page.pixels.chunks(page.stride).take(page.height as usize) {
+        for pixel in row.chunks_exact(4).take(page.width as usize) {
+            // B8G8R8A8 to R8G8B8
+            data.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+        }
+    }
+
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(&data)?;
+    Ok(())
+}
+
+/// The words of Tesseract's TSV output, in reading order. Pixel boxes measured
+/// from the top-left corner become page points from the bottom-left one.
+pub fn parse_tsv(tsv: &str, scale: f64, page_height: f64) -> Vec<OcrWord> {
+    let mut words = Vec::new();
+    let mut line = 0;
+    let mut last_line_key = None;
+
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != WORD_LEVEL {
+            continue;
+        }
+        let text = columns[11].trim();
+        if text.is_empty() {
+            continue;
+        }
+        let numbers: Option<Vec<f64>> = columns[6..10].iter().map(|c| c.parse().ok()).collect();
+        let Some([left, top, width, height]) = numbers.and_then(|n| <[f64; 4]>::try_from(n).ok())
+        else {
+            continue;
+        };
+
+        // Block, paragraph and line number together tell lines apart
+        let line_key = (columns[2], columns[3], columns[4]);
+        if last_line_key.is_some_and(|key| key != line_key) {
+            line += 1;
+        }
+        last_line_key = Some(line_key);
+
+        words.push(OcrWord {
+            text: text.to_string(),
+            bounds: (
+                left * scale,
+                page_height - (top + height) * scale,
+                (left + width) * scale,
+                page_height - top * scale,
+            ),
+            line,
+        });
+    }
+
+    words
+}
+
+/// Characters for the text map of a page, as if the page had a text layer: each
+/// word's box split evenly between its letters, spaces between the words of a
+/// line and line breaks between lines
+pub fn words_to_chars(words: &[OcrWord]) -> Vec<PageChar> {
+    let mut chars: Vec<PageChar> = Vec::new();
+
+    for (i, word) in words.iter().enumerate() {
+        if let Some(previous) = i.checked_sub(1).map(|p| &words[p]) {
+            chars.push(PageChar {
+                char: if previous.line == word.line {
+                    ' '
+                } else {
+                    '\n'
+                },
+                index: chars.len(),
+                bounds: None,
+            });
+        }
+
+        let (left, bottom, right, top) = word.bounds;
+        let count = word.text.chars().count().max(1) as f64;
+        let width = (right - left) / count;
+        for (j, c) in word.text.chars().enumerate() {
+            let char_left = left + j as f64 * width;
+            chars.push(PageChar {
+                char: c,
+                index: chars.len(),
+                bounds: Some(PdfRect::new_from_values(
+                    bottom as f32,
+                    char_left as f32,
+                    top as f32,
+                    (char_left + width) as f32,
+                )),
+            });
+        }
+    }
+
+    chars
+}
+
+/// Keep the words recognized on a page, replacing earlier ones
+pub fn save_page(pdf_path: &str, page_index: usize, words: &[OcrWord]) -> Result<(), OcrError> {
+    let conn = database::open()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO ocr_pages (pdf_path, page_index, words) VALUES (?1, ?2, ?3)",
+        params![pdf_path, page_index as i64, serde_json::to_string(words)?],
+    )?;
+    Ok(())
+}
+
+/// Every recognized page of a document, as (page index, words)
+pub fn load_pages(pdf_path: &str) -> Result<Vec<(usize, Vec<OcrWord>)>, OcrError> {
+    let conn = database::open()?;
+    let mut stmt = conn.prepare("SELECT page_index, words FROM ocr_pages WHERE pdf_path = ?1")?;
+
+    let pages = stmt
+        .query_map(params![pdf_path], |row| {
+            Ok((row.get::<_, i64>(0)? as usize, row.get::<_, String>(1)?))
+        })?
+        .filter_map(|r| r.ok())
+        .filter_map(|(page_index, words)| Some((page_index, serde_json::from_str(&words).ok()?)))
+        .collect();
+
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TSV: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t1000\t2000\t-1\t
+4\t1\t1\t1\t1\t0\t100\t100\t400\t50\t-1\t
+5\t1\t1\t1\t1\t1\t100\t100\t200\t50\t96.5\tHello
+5\t1\t1\t1\t1\t2\t320\t100\t180\t50\t95.1\tworld
+5\t1\t1\t1\t2\t1\t100\t200\t100\t50\t91.0\tagain
+5\t1\t1\t1\t2\t2\t220\t200\t100\t50\t-1\t
+";
+
+    #[test]
+    fn test_parse_tsv() {
+        // 2 pixels per point, on a page 1000 points high
+        let words = parse_tsv(TSV, 0.5, 1000.0);
+        assert_eq!(
+            words,
+            vec![
+                OcrWord {
+                    text: "Hello".to_string(),
+                    bounds: (50.0, 925.0, 150.0, 950.0),
+                    line: 0,
+                },
+                OcrWord {
+                    text: "world".to_string(),
+                    bounds: (160.0, 925.0, 250.0, 950.0),
+                    line: 0,
+                },
+                OcrWord {
+                    text: "again".to_string(),
+                    bounds: (50.0, 875.0, 100.0, 900.0),
+                    line: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_words_to_chars() {
+        let words = parse_tsv(TSV, 0.5, 1000.0);
+        let chars = words_to_chars(&words);
+
+        let text: String = chars.iter().map(|c| c.char).collect();
+        assert_eq!(text, "Hello world\nagain");
+        assert!(chars.iter().enumerate().all(|(i, c)| c.index == i));
+        assert!(chars[5].bounds.is_none());
+
+        // "Hello" spans 50 to 150: 20 points per letter
+        let bounds = chars[1].bounds.unwrap();
+        assert_eq!(bounds.left().value, 70.0);
+        assert_eq!(bounds.right().value, 90.0);
+        assert_eq!(bounds.bottom().value, 925.0);
+        assert_eq!(bounds.top().value, 950.0);
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let page = RenderedPage {
+            width: 2,
+            height: 1,
+            // Padded stride, blue then red pixel
+            stride: 12,
+            pixels: vec![255, 0, 0, 255, 0, 0, 255, 255, 9, 9, 9, 9],
+        };
+        let path = std::env::temp_dir().join(format!("eyers-ppm-test-{}.ppm", std::process::id()));
+        write_ppm(&page, &path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[0, 0, 255, 255, 0, 0]);
+        assert_eq!(data, expected);
+    }
+}
@@ -0,0 +1,40 @@
+/// Page index `[count]gg` goes to. With page labels, the first page labelled
+/// `count` (as printed in the document, after roman-numbered front matter for
+/// example); otherwise, or when no page has that label, page number `count`.
+/// Counts past the end go to the last page.
+pub fn page_index_for_count(labels: &[Option<String>], page_count: usize, count: u32) -> usize {
+    let label = count.to_string();
+    labels
+        .iter()
+        .position(|page_label| page_label.as_deref().map(str::trim) == Some(label.as_str()))
+        .unwrap_or_else(|| (count as usize).saturating_sub(1))
+        .min(page_count.saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_index_without_labels() {
+        assert_eq!(page_index_for_count(&[], 10, 1), 0);
+        assert_eq!(page_index_for_count(&[], 10, 4), 3);
+        assert_eq!(page_index_for_count(&[], 10, 42), 9);
+    }
+
+    #[test]
+    fn test_page_index_with_labels() {
+        let labels: Vec<Option<String>> = ["i", "ii", "1", "2", "3"]
+            .iter()
+            .map(|label| Some(label.to_string()))
+            .collect();
+        assert_eq!(page_index_for_count(&labels, 5, 1), 2);
+        assert_eq!(page_index_for_count(&labels, 5, 3), 4);
+        // No page labelled 5: fall back to the physical page
+        assert_eq!(page_index_for_count(&labels, 5, 5), 4);
+
+        let partial = vec![None, Some("7".to_string())];
+        assert_eq!(page_index_for_count(&partial, 2, 7), 1);
+        assert_eq!(page_index_for_count(&partial, 2, 1), 0);
+    }
+}
@@ -1,15 +1,166 @@
 use gtk;
 use gtk::prelude::WidgetExt;
-use pdfium_render::prelude::*;
+use pdfium_render::prelude::PdfRect;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::backend::PageChar;
+use crate::services::crop::PageCrop;
+use crate::services::rotation::Rotation;
 
 pub const RENDER_WIDTH: i32 = 1000;
-const CLICK_TOLERANCE: f64 = 5.0;
+/// How far from a word, in PDF points, a click still lands on it
+pub const CLICK_TOLERANCE: f64 = 5.0;
+/// Most words a Shift+click phrase lookup can span
+pub const PHRASE_MAX_WORDS: usize = 6;
+/// Farthest a sentence reaches on each side of the looked up word
+const SENTENCE_MAX_REACH: usize = 200;
+
+/// Ligatures and typographic marks NFKC leaves alone, with the plain text
+/// looked up, copied and exported in their place
+const TEXT_REPLACEMENTS: &[(char, &str)] = &[
+    ('\u{A732}', "AA"),
+    ('\u{A733}', "aa"),
+    ('\u{A74E}', "OO"),
+    ('\u{A74F}', "oo"),
+    ('\u{A728}', "TZ"),
+    ('\u{A729}', "tz"),
+    ('\u{1D6B}', "ue"),
+    ('\u{2018}', "'"),
+    ('\u{2019}', "'"),
+    ('\u{201A}', "'"),
+    ('\u{201B}', "'"),
+    ('\u{2032}', "'"),
+    ('\u{201C}', "\""),
+    ('\u{201D}', "\""),
+    ('\u{201E}', "\""),
+    ('\u{201F}', "\""),
+    // Soft hyphens only show where a line breaks
+    ('\u{AD}', ""),
+];
+
+/// Zoom level bounds, as a factor of RENDER_WIDTH
+pub const MIN_ZOOM: f64 = 0.5;
+pub const MAX_ZOOM: f64 = 3.0;
 
 /// Get the effective render width for a given zoom level
 pub fn get_render_width_for_zoom(zoom: f64) -> i32 {
     (RENDER_WIDTH as f64 * zoom) as i32
 }
 
+/// Render width of a page `page_width_pts` wide. Pages as wide as the reference
+/// get the zoom's render width, the others keep their size relative to them.
+pub fn get_render_width_for_page(zoom: f64, page_width_pts: f64, reference_width_pts: f64) -> i32 {
+    get_render_width_for_zoom(zoom * page_width_pts / reference_width_pts)
+}
+
+/// Width most pages are, in points, the first one seen winning ties
+pub fn reference_page_width(widths: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let mut counts: Vec<(f64, usize)> = Vec::new();
+    for width in widths {
+        match counts.iter_mut().find(|(w, _)| (w - width).abs() < 1.0) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((width, 1)),
+        }
+    }
+
+    let mut best: Option<(f64, usize)> = None;
+    for (width, count) in counts {
+        if best.is_none_or(|(_, best_count)| count > best_count) {
+            best = Some((width, count));
+        }
+    }
+    best.map(|(width, _)| width)
+}
+
+/// Top of each page in the layout given the page heights and the row each page is
+/// in. Rows are as tall as their tallest page. Hidden pages have no height and no
+/// top, and rows of hidden pages take no space.
+pub fn page_tops(heights: &[Option<f64>], rows: &[usize], spacing: f64) -> Vec<Option<f64>> {
+    let mut tops = Vec::with_capacity(heights.len());
+    let mut row_top = 0.0;
+    let mut row_height: Option<f64> = None;
+
+    for (index, &height) in heights.iter().enumerate() {
+        if index > 0 && rows[index] != rows[index - 1] {
+            row_top += row_height.take().map_or(0.0, |h| h + spacing);
+        }
+        if let Some(height) = height {
+            row_height = Some(row_height.map_or(height, |h: f64| h.max(height)));
+        }
+        tops.push(height.map(|_| row_top));
+    }
+    tops
+}
+
+/// How the zoom level is chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZoomMode {
+    /// Pages as wide as the view, following its size
+    FitWidth,
+    /// Whole pages in the view, following its size
+    FitPage,
+    /// A fixed zoom level (1.0 = 100%)
+    Percentage(f64),
+}
+
+impl Default for ZoomMode {
+    fn default() -> Self {
+        ZoomMode::Percentage(1.0)
+    }
+}
+
+impl ZoomMode {
+    /// Zoom level fitting a page of `page_size` pixels at 100% in a view of
+    /// `view_size` pixels. Fit modes give None until both sizes are known.
+    pub fn zoom_level(
+        &self,
+        page_size: Option<(f64, f64)>,
+        view_size: Option<(f64, f64)>,
+    ) -> Option<f64> {
+        let zoom = match self {
+            ZoomMode::Percentage(zoom) => *zoom,
+            ZoomMode::FitWidth => view_size?.0 / page_size?.0,
+            ZoomMode::FitPage => {
+                let ((page_width, page_height), (view_width, view_height)) =
+                    (page_size?, view_size?);
+                (view_width / page_width).min(view_height / page_height)
+            }
+        };
+        Some(zoom.clamp(MIN_ZOOM, MAX_ZOOM))
+    }
+
+    /// Whether the zoom level follows the size of the view
+    pub fn fits_view(&self) -> bool {
+        !matches!(self, ZoomMode::Percentage(_))
+    }
+
+    /// Name stored in the session
+    pub fn code(&self) -> String {
+        match self {
+            ZoomMode::FitWidth => "fit-width".to_string(),
+            ZoomMode::FitPage => "fit-page".to_string(),
+            ZoomMode::Percentage(zoom) => zoom.to_string(),
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<ZoomMode> {
+        match code {
+            "fit-width" => Some(ZoomMode::FitWidth),
+            "fit-page" => Some(ZoomMode::FitPage),
+            _ => code.parse().ok().map(ZoomMode::Percentage),
+        }
+    }
+
+    /// Human readable name for toasts
+    pub fn label(&self) -> String {
+        match self {
+            ZoomMode::FitWidth => "Fit width".to_string(),
+            ZoomMode::FitPage => "Fit page".to_string(),
+            ZoomMode::Percentage(zoom) => format!("{:.0}%", zoom * 100.0),
+        }
+    }
+}
+
 /// Data extracted from a click event on a PDF page
 pub struct ClickData {
     pub pdf_x: f64,
@@ -24,13 +175,6 @@ pub struct ExtractedWord {
     pub lowercase: String,
 }
 
-/// Configuration for rendering a PDF page
-pub struct PageRenderConfig {
-    pub width: i32,
-    pub height: i32,
-    pub stride: usize,
-}
-
 /// Calculate the horizontal offset when a Picture is centered in its parent
 pub fn calculate_picture_offset(picture: &gtk::Picture) -> f64 {
     let alloc_width = picture.width();
@@ -46,25 +190,50 @@ pub fn calculate_picture_offset(picture: &gtk::Picture) -> f64 {
 pub fn calculate_click_coordinates_with_offset(
     x: f64,
     y: f64,
-    page: &PdfPage,
+    (page_width_pts, page_height_pts): (f64, f64),
     picture_offset: f64,
-    zoom_level: f64,
+    render_width: i32,
+    rotation: Rotation,
+    crop: PageCrop,
 ) -> ClickData {
-    let page_width_pts = page.width().value as f64;
-    let page_height_pts = page.height().value as f64;
-    let render_width = get_render_width_for_zoom(zoom_level);
     let scale = render_width as f64 / page_width_pts;
+    let (shown_width, shown_height) = crop.size((page_width_pts, page_height_pts));
 
-    let adjusted_x = x - picture_offset;
+    // Undo the rotation and the crop to get the point on the page as rendered by
+    // the backend
+    let (page_x, page_y) = crop.to_page(
+        rotation.to_page(
+            (x - picture_offset, y),
+            (shown_width * scale, shown_height * scale),
+        ),
+        scale,
+    );
 
     ClickData {
-        pdf_x: adjusted_x / scale,
-        pdf_y: page_height_pts - (y / scale),
+        pdf_x: page_x / scale,
+        pdf_y: page_height_pts - (page_y / scale),
         screen_x: x,
         screen_y: y,
     }
 }
 
+/// Position of a point in PDF points on the picture showing its page, before
+/// the centering offset
+pub fn pdf_point_to_screen(
+    (pdf_x, pdf_y): (f64, f64),
+    (page_width_pts, page_height_pts): (f64, f64),
+    render_width: i32,
+    rotation: Rotation,
+    crop: PageCrop,
+) -> (f64, f64) {
+    let scale = render_width as f64 / page_width_pts;
+    let (shown_width, shown_height) = crop.size((page_width_pts, page_height_pts));
+    rotation.to_screen(
+        crop.to_cropped((pdf_x * scale, (page_height_pts - pdf_y) * scale), scale),
+        (shown_width * scale, shown_height * scale),
+    )
+}
+
 pub fn create_click_rect(click: &ClickData) -> PdfRect {
     PdfRect::new_from_values(
         (click.pdf_y - CLICK_TOLERANCE) as f32,
@@ -74,11 +243,36 @@ pub fn create_click_rect(click: &ClickData) -> PdfRect {
     )
 }
 
-pub fn find_char_index_at_click(text_page: &PdfPageText, click: &ClickData) -> Option<usize> {
+/// Position in `chars` of the first character near the click
+pub fn find_char_index_at_click(chars: &[PageChar], click: &ClickData) -> Option<usize> {
     let rect = create_click_rect(click);
-    let chars = text_page.chars_inside_rect(rect).ok()?;
-    let char_obj = chars.iter().next()?;
-    Some(char_obj.index() as usize)
+    chars.iter().position(|c| {
+        c.bounds.is_some_and(|bounds| {
+            bounds.left().value <= rect.right().value
+                && bounds.right().value >= rect.left().value
+                && bounds.bottom().value <= rect.top().value
+                && bounds.top().value >= rect.bottom().value
+        })
+    })
+}
+
+/// The text of a page, with one char per entry of `chars` so positions line up
+pub fn page_text(chars: &[PageChar]) -> String {
+    chars.iter().map(|c| c.char).collect()
+}
+
+/// Extracted text as it is looked up, copied and exported: compatibility forms
+/// folded by NFKC (so "\u{FB01}nd" becomes "find"), the ligatures NFKC keeps
+/// spelled out, typographic quotes made straight and soft hyphens dropped
+pub fn normalize_text(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.nfkc() {
+        match TEXT_REPLACEMENTS.iter().find(|(from, _)| *from == c) {
+            Some((_, to)) => normalized.push_str(to),
+            None => normalized.push(c),
+        }
+    }
+    normalized
 }
 
 pub fn extract_word_at_index(full_text: &str, idx: usize) -> Option<ExtractedWord> {
@@ -93,7 +287,7 @@ pub fn extract_word_at_index(full_text: &str, idx: usize) -> Option<ExtractedWor
     if start > end {
         return None;
     }
-    let original: String = chars_vec[start..end].iter().collect();
+    let original = normalize_text(&chars_vec[start..end].iter().collect::<String>());
     let lowercase = original.to_lowercase();
     Some(ExtractedWord {
         original,
@@ -101,6 +295,88 @@ pub fn extract_word_at_index(full_text: &str, idx: usize) -> Option<ExtractedWor
     })
 }
 
+/// Extract the phrase from the word at `first_idx` to the word at `last_idx` (in
+/// either order), with line breaks and runs of spaces collapsed. None when it has
+/// no words or more than PHRASE_MAX_WORDS.
+pub fn extract_phrase_between(
+    full_text: &str,
+    first_idx: usize,
+    last_idx: usize,
+) -> Option<ExtractedWord> {
+    let chars_vec: Vec<char> = full_text.chars().collect();
+    let (first, last) = (first_idx.min(last_idx), first_idx.max(last_idx));
+    if last >= chars_vec.len() {
+        return None;
+    }
+
+    let start = find_word_start(&chars_vec, first);
+    let end = find_word_end(&chars_vec, last).max(start);
+    let text = normalize_text(&chars_vec[start..end].iter().collect::<String>());
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() || words.len() > PHRASE_MAX_WORDS {
+        return None;
+    }
+
+    let original = words.join(" ");
+    let lowercase = original.to_lowercase();
+    Some(ExtractedWord {
+        original,
+        lowercase,
+    })
+}
+
+/// The word or short phrase copied in another application, trimmed of the
+/// punctuation around it. None for text that isn't worth a lookup: longer than
+/// PHRASE_MAX_WORDS words, without letters, or a link.
+pub fn extract_copied_term(text: &str) -> Option<ExtractedWord> {
+    let text = normalize_text(text);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() || words.len() > PHRASE_MAX_WORDS || text.contains("://") {
+        return None;
+    }
+
+    let original = words
+        .join(" ")
+        .trim_matches(|c: char| !is_word_char(c))
+        .to_string();
+    if !original.chars().any(char::is_alphabetic) {
+        return None;
+    }
+    let lowercase = original.to_lowercase();
+    Some(ExtractedWord {
+        original,
+        lowercase,
+    })
+}
+
+/// The sentence around the char at `idx`, with line breaks and runs of spaces
+/// collapsed. Sentences end at '.', '!' or '?' followed by a space, and very long
+/// ones are cut SENTENCE_MAX_REACH chars away from `idx`.
+pub fn extract_sentence_at(full_text: &str, idx: usize) -> Option<String> {
+    let chars_vec: Vec<char> = full_text.chars().collect();
+    if idx >= chars_vec.len() {
+        return None;
+    }
+    let ends_sentence = |i: usize| {
+        matches!(chars_vec[i], '.' | '!' | '?')
+            && chars_vec.get(i + 1).is_none_or(|c| c.is_whitespace())
+    };
+
+    let min_start = idx.saturating_sub(SENTENCE_MAX_REACH);
+    let start = (min_start..idx)
+        .rev()
+        .find(|&i| ends_sentence(i))
+        .map_or(min_start, |i| i + 1);
+    let max_end = (idx + SENTENCE_MAX_REACH).min(chars_vec.len());
+    let end = (idx..max_end)
+        .find(|&i| ends_sentence(i))
+        .map_or(max_end, |i| i + 1);
+
+    let text = normalize_text(&chars_vec[start..end].iter().collect::<String>());
+    let sentence = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!sentence.is_empty()).then_some(sentence)
+}
+
 fn find_word_start(chars: &[char], idx: usize) -> usize {
     let mut start = idx;
     while start > 0 && is_word_char(chars[start]) {
@@ -121,23 +397,189 @@ fn find_word_end(chars: &[char], idx: usize) -> usize {
 }
 
 fn is_word_char(c: char) -> bool {
-    c.is_alphanumeric() || c == '\''
+    // Typographic apostrophes too, so "don\u{2019}t" is one word
+    c.is_alphanumeric() || c == '\'' || c == '\u{2019}'
 }
 
-pub fn calculate_page_dimensions(bitmap: &PdfBitmap) -> PageRenderConfig {
-    let width = bitmap.width();
-    let height = bitmap.height();
-    PageRenderConfig {
-        width,
-        height,
-        stride: (width * 4) as usize,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_phrase_between() {
+        let text = "He gave\r\nup on it, then left.";
+        let phrase = extract_phrase_between(text, 4, 9).unwrap();
+        assert_eq!(phrase.original, "gave up");
+        assert_eq!(phrase.lowercase, "gave up");
+
+        // Clicked from the last word back to the first
+        let phrase = extract_phrase_between(text, 14, 0).unwrap();
+        assert_eq!(phrase.original, "He gave up on");
+
+        assert!(extract_phrase_between("a b c d e f g", 0, 12).is_none());
+        assert!(extract_phrase_between(text, 0, 100).is_none());
+    }
+
+    #[test]
+    fn test_extract_copied_term() {
+        let term = extract_copied_term("  “Serendipity,”\n").unwrap();
+        assert_eq!(term.original, "Serendipity");
+        assert_eq!(term.lowercase, "serendipity");
+        assert_eq!(
+            extract_copied_term("give\nup.")
+                .map(|t| t.original)
+                .as_deref(),
+            Some("give up")
+        );
+
+        assert!(extract_copied_term("one two three four five six seven").is_none());
+        assert!(extract_copied_term("https://example.org").is_none());
+        assert!(extract_copied_term("42 %").is_none());
     }
-}
 
-/// Create a render config with a specific zoom level
-pub fn create_render_config_with_zoom(zoom: f64) -> PdfRenderConfig {
-    let width = get_render_width_for_zoom(zoom);
-    PdfRenderConfig::new()
-        .set_target_width(width)
-        .set_format(PdfBitmapFormat::BGRA)
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(
+            normalize_text("\u{FB01}nd \u{FB02}ow e\u{FB00}ort"),
+            "find flow effort"
+        );
+        assert_eq!(normalize_text("\u{201C}It\u{2019}s\u{201D}"), "\"It's\"");
+        assert_eq!(normalize_text("ex\u{AD}ample \u{A733}"), "example aa");
+        // Accents survive, composed
+        assert_eq!(normalize_text("cafe\u{301}"), "caf\u{E9}");
+
+        // Looked up words are normalized, but stay at the clicked chars
+        let text = "a \u{FB01}ne day, don\u{2019}t";
+        let word = extract_word_at_index(text, 3).unwrap();
+        assert_eq!(word.original, "fine");
+        let word = extract_word_at_index(text, 13).unwrap();
+        assert_eq!(word.lowercase, "don't");
+    }
+
+    #[test]
+    fn test_extract_sentence_at() {
+        let text = "First one. The word\r\nis here! Last 3.5 one";
+        assert_eq!(
+            extract_sentence_at(text, 15).as_deref(),
+            Some("The word is here!")
+        );
+        assert_eq!(extract_sentence_at(text, 2).as_deref(), Some("First one."));
+        // A decimal point doesn't end a sentence
+        assert_eq!(
+            extract_sentence_at(text, text.len() - 1).as_deref(),
+            Some("Last 3.5 one")
+        );
+        assert!(extract_sentence_at(text, 100).is_none());
+    }
+
+    #[test]
+    fn test_click_coordinates_follow_rotation() {
+        let page = (500.0, 800.0);
+        for turns in 0..4 {
+            let rotation = Rotation::NONE.rotated(turns);
+            let (x, y) = pdf_point_to_screen((120.0, 700.0), page, 1500, rotation, PageCrop::NONE);
+            let click = calculate_click_coordinates_with_offset(
+                x + 30.0,
+                y,
+                page,
+                30.0,
+                1500,
+                rotation,
+                PageCrop::NONE,
+            );
+            assert!((click.pdf_x - 120.0).abs() < 1e-9);
+            assert!((click.pdf_y - 700.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_click_coordinates_follow_crop() {
+        let page = (500.0, 800.0);
+        let crop = PageCrop {
+            left: 50.0,
+            top: 60.0,
+            right: 40.0,
+            bottom: 70.0,
+        };
+        // At 1 pixel per point, the top-left corner of the cropped page
+        let (x, y) = pdf_point_to_screen((50.0, 740.0), page, 500, Rotation::NONE, crop);
+        assert!(x.abs() < 1e-9 && y.abs() < 1e-9);
+
+        for turns in 0..4 {
+            let rotation = Rotation::NONE.rotated(turns);
+            let (x, y) = pdf_point_to_screen((120.0, 700.0), page, 1500, rotation, crop);
+            let click =
+                calculate_click_coordinates_with_offset(x, y, page, 0.0, 1500, rotation, crop);
+            assert!((click.pdf_x - 120.0).abs() < 1e-9);
+            assert!((click.pdf_y - 700.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_mixed_page_sizes() {
+        // Mostly A4 with a letter page and a landscape A4 one
+        let widths = [595.0, 612.0, 595.3, 842.0, 595.0];
+        assert_eq!(reference_page_width(widths), Some(595.0));
+        assert_eq!(reference_page_width([]), None);
+        assert_eq!(get_render_width_for_page(1.0, 595.0, 595.0), 1000);
+        assert_eq!(get_render_width_for_page(2.0, 842.0, 595.0), 2830);
+
+        let heights = [Some(100.0), Some(60.0), Some(80.0), Some(120.0), Some(90.0)];
+        assert_eq!(
+            page_tops(&heights, &[0, 1, 2, 3, 4], 10.0),
+            vec![
+                Some(0.0),
+                Some(110.0),
+                Some(180.0),
+                Some(270.0),
+                Some(400.0)
+            ]
+        );
+        // Two-page rows take the height of their taller page
+        assert_eq!(
+            page_tops(&heights, &[0, 1, 1, 2, 2], 10.0),
+            vec![
+                Some(0.0),
+                Some(110.0),
+                Some(110.0),
+                Some(200.0),
+                Some(200.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_page_tops_skip_hidden_pages() {
+        // Paged mode showing the second row only
+        let heights = [None, Some(60.0), Some(80.0), None, None];
+        assert_eq!(
+            page_tops(&heights, &[0, 1, 1, 2, 2], 10.0),
+            vec![None, Some(0.0), Some(0.0), None, None]
+        );
+    }
+
+    #[test]
+    fn test_zoom_mode_levels() {
+        let page = Some((1000.0, 1400.0));
+        let view = Some((1500.0, 700.0));
+        assert_eq!(ZoomMode::FitWidth.zoom_level(page, view), Some(1.5));
+        assert_eq!(ZoomMode::FitPage.zoom_level(page, view), Some(0.5));
+        assert_eq!(ZoomMode::FitWidth.zoom_level(page, None), None);
+        assert_eq!(
+            ZoomMode::Percentage(9.0).zoom_level(None, None),
+            Some(MAX_ZOOM)
+        );
+    }
+
+    #[test]
+    fn test_zoom_mode_codes() {
+        for mode in [
+            ZoomMode::FitWidth,
+            ZoomMode::FitPage,
+            ZoomMode::Percentage(1.25),
+        ] {
+            assert_eq!(ZoomMode::from_code(&mode.code()), Some(mode));
+        }
+        assert_eq!(ZoomMode::from_code("fit-height"), None);
+    }
 }
@@ -0,0 +1,173 @@
+use rusqlite::params;
+use std::collections::HashSet;
+
+use crate::modes::WordCursor;
+use crate::services::database::{self, DatabaseError};
+
+pub type PositionId = i64;
+
+/// A named reading position saved for a PDF document
+#[derive(Debug, Clone, Default)]
+pub struct SavedPosition {
+    pub id: PositionId,
+    pub pdf_path: String,
+    pub name: String,
+    pub page_index: usize,
+    pub word_index: usize,
+    pub created_at: i64,
+}
+
+impl SavedPosition {
+    pub fn cursor(&self) -> WordCursor {
+        WordCursor::new(self.page_index, self.word_index)
+    }
+}
+
+/// Error type for saved position operations
+#[derive(Debug)]
+pub enum PositionError {
+    DatabaseError(String),
+    NotFound,
+}
+
+impl std::fmt::Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PositionError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+            PositionError::NotFound => write!(f, "Saved position not found"),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl From<rusqlite::Error> for PositionError {
+    fn from(err: rusqlite::Error) -> Self {
+        PositionError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for PositionError {
+    fn from(err: DatabaseError) -> Self {
+        PositionError::DatabaseError(err.to_string())
+    }
+}
+
+/// Save a named position for a PDF. Saving again with an existing name moves that position.
+pub fn save_position(
+    pdf_path: &str,
+    name: &str,
+    cursor: WordCursor,
+) -> Result<PositionId, PositionError> {
+    let conn = database::open()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    let rows_affected = conn.execute(
+        "UPDATE saved_positions SET page_index = ?1, word_index = ?2, created_at = ?3
+         WHERE pdf_path = ?4 AND name = ?5",
+        params![
+            cursor.page_index as i64,
+            cursor.word_index as i64,
+            now,
+            pdf_path,
+            name
+        ],
+    )?;
+
+    if rows_affected > 0 {
+        return conn
+            .query_row(
+                "SELECT id FROM saved_positions WHERE pdf_path = ?1 AND name = ?2",
+                params![pdf_path, name],
+                |row| row.get(0),
+            )
+            .map_err(PositionError::from);
+    }
+
+    conn.execute(
+        "INSERT INTO saved_positions (pdf_path, name, page_index, word_index, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            pdf_path,
+            name,
+            cursor.page_index as i64,
+            cursor.word_index as i64,
+            now
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Delete a saved position by ID
+pub fn delete_position(id: PositionId) -> Result<(), PositionError> {
+    let conn = database::open()?;
+
+    let rows_affected = conn.execute("DELETE FROM saved_positions WHERE id = ?1", params![id])?;
+
+    if rows_affected == 0 {
+        return Err(PositionError::NotFound);
+    }
+
+    Ok(())
+}
+
+/// Load all saved positions for a PDF, in document order
+pub fn load_positions_for_pdf(pdf_path: &str) -> Result<Vec<SavedPosition>, PositionError> {
+    let conn = database::open()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, pdf_path, name, page_index, word_index, created_at
+         FROM saved_positions WHERE pdf_path = ?1 ORDER BY page_index, word_index",
+    )?;
+
+    let positions = stmt
+        .query_map(params![pdf_path], |row| {
+            Ok(SavedPosition {
+                id: row.get(0)?,
+                pdf_path: row.get(1)?,
+                name: row.get(2)?,
+                page_index: row.get::<_, i64>(3)? as usize,
+                word_index: row.get::<_, i64>(4)? as usize,
+                created_at: row.get(5)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(positions)
+}
+
+/// Remember that a page of a PDF has been read
+pub fn record_page_read(pdf_path: &str, page_index: usize) -> Result<(), PositionError> {
+    let conn = database::open()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    conn.execute(
+        "INSERT INTO read_pages (pdf_path, page_index, read_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(pdf_path, page_index) DO UPDATE SET read_at = excluded.read_at",
+        params![pdf_path, page_index as i64, now],
+    )?;
+
+    Ok(())
+}
+
+/// Load the indices of all pages of a PDF that have been read
+pub fn load_read_pages(pdf_path: &str) -> Result<HashSet<usize>, PositionError> {
+    let conn = database::open()?;
+
+    let mut stmt = conn.prepare("SELECT page_index FROM read_pages WHERE pdf_path = ?1")?;
+
+    let pages = stmt
+        .query_map(params![pdf_path], |row| Ok(row.get::<_, i64>(0)? as usize))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(pages)
+}
@@ -0,0 +1,141 @@
+use gtk::glib;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A file written by an annotation export, handed to the post-export commands
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportedFile {
+    pub output: PathBuf,
+    /// Name of the document the annotations belong to, without its extension
+    pub document: String,
+}
+
+impl ExportedFile {
+    /// The export of the annotations of `pdf_path` to `output`
+    pub fn new(output: &Path, pdf_path: &str) -> Self {
+        Self {
+            output: output.to_path_buf(),
+            document: Path::new(pdf_path)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or(pdf_path)
+                .to_string(),
+        }
+    }
+}
+
+/// Error type for post-export commands
+#[derive(Debug)]
+pub enum PostExportError {
+    /// A command line that can't be split into arguments
+    InvalidCommand(String),
+    /// A program that couldn't be started
+    SpawnFailed { program: String, message: String },
+    /// A command that exited with an error
+    Failed { program: String, message: String },
+}
+
+impl std::fmt::Display for PostExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostExportError::InvalidCommand(msg) => write!(f, "Invalid command: {}", msg),
+            PostExportError::SpawnFailed { program, message } => {
+                write!(f, "Could not run {}: {}", program, message)
+            }
+            PostExportError::Failed { program, message } => write!(f, "{}: {}", program, message),
+        }
+    }
+}
+
+impl std::error::Error for PostExportError {}
+
+/// The commands of the settings, one per line. Blank lines and lines starting
+/// with `#` are skipped.
+pub fn parse_commands(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// Substitute `%{output}`, `%{dir}` and `%{document}` in one argument of a command
+pub fn expand_arg(arg: &str, file: &ExportedFile) -> String {
+    let dir = file
+        .output
+        .parent()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+    arg.replace("%{output}", &file.output.to_string_lossy())
+        .replace("%{dir}", &dir)
+        .replace("%{document}", &file.document)
+}
+
+/// Run `commands` for an exported file, one after the other, stopping at the
+/// first that fails. Commands run in the directory of the file and are waited
+/// for, so this runs on a worker thread. Returns how many ran.
+pub fn run_commands(commands: &str, file: &ExportedFile) -> Result<usize, PostExportError> {
+    let commands = parse_commands(commands);
+    for command in &commands {
+        let args: Vec<String> = glib::shell_parse_argv(command)
+            .map_err(|e| PostExportError::InvalidCommand(e.to_string()))?
+            .iter()
+            .map(|arg| expand_arg(&arg.to_string_lossy(), file))
+            .collect();
+        let Some((program, args)) = args.split_first() else {
+            continue;
+        };
+
+        let mut process = Command::new(program);
+        process.args(args);
+        if let Some(dir) = file.output.parent().filter(|dir| dir.is_dir()) {
+            process.current_dir(dir);
+        }
+        let output = process.output().map_err(|e| PostExportError::SpawnFailed {
+            program: program.clone(),
+            message: e.to_string(),
+        })?;
+
+        if !output.status.success() {
+            // The last line of the error output usually says what went wrong
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let message = stderr
+                .lines()
+                .rev()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| output.status.to_string());
+            return Err(PostExportError::Failed {
+                program: program.clone(),
+                message,
+            });
+        }
+    }
+    Ok(commands.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        let text = "pandoc %{output} -o %{document}.pdf\n\n  # a comment\n  git add -A  \n";
+        assert_eq!(
+            parse_commands(text),
+            vec!["pandoc %{output} -o %{document}.pdf", "git add -A"]
+        );
+        assert!(parse_commands("").is_empty());
+    }
+
+    #[test]
+    fn test_expand_arg() {
+        let file = ExportedFile::new(Path::new("/notes/paper.md"), "/books/paper.pdf");
+        assert_eq!(file.document, "paper");
+        assert_eq!(expand_arg("%{output}", &file), "/notes/paper.md");
+        assert_eq!(
+            expand_arg("Notes on %{document} in %{dir}", &file),
+            "Notes on paper in /notes"
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use gtk::glib;
+use rusqlite::params;
+use std::path::{Path, PathBuf};
+
+use crate::services::database::{self, DatabaseError};
+
+/// Something done while reading, kept for the reading journal
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadingEvent {
+    Opened,
+    /// Pages read one after the other, both inclusive
+    PagesRead {
+        first_page: usize,
+        last_page: usize,
+    },
+    Annotated {
+        page_index: usize,
+        text: String,
+    },
+    LookedUp {
+        page_index: usize,
+        word: String,
+    },
+}
+
+impl ReadingEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            ReadingEvent::Opened => "opened",
+            ReadingEvent::PagesRead { .. } => "read",
+            ReadingEvent::Annotated { .. } => "annotated",
+            ReadingEvent::LookedUp { .. } => "looked_up",
+        }
+    }
+
+    /// Pages and text stored alongside the kind
+    fn columns(&self) -> (usize, usize, &str) {
+        match self {
+            ReadingEvent::Opened => (0, 0, ""),
+            ReadingEvent::PagesRead {
+                first_page,
+                last_page,
+            } => (*first_page, *last_page, ""),
+            ReadingEvent::Annotated { page_index, text } => (*page_index, *page_index, text),
+            ReadingEvent::LookedUp { page_index, word } => (*page_index, *page_index, word),
+        }
+    }
+
+    fn from_columns(
+        kind: &str,
+        first_page: usize,
+        last_page: usize,
+        detail: String,
+    ) -> Option<Self> {
+        match kind {
+            "opened" => Some(ReadingEvent::Opened),
+            "read" => Some(ReadingEvent::PagesRead {
+                first_page,
+                last_page,
+            }),
+            "annotated" => Some(ReadingEvent::Annotated {
+                page_index: first_page,
+                text: detail,
+            }),
+            "looked_up" => Some(ReadingEvent::LookedUp {
+                page_index: first_page,
+                word: detail,
+            }),
+            _ => None,
+        }
+    }
+
+    /// One line of the journal, pages counting from 1
+    fn describe(&self) -> String {
+        match self {
+            ReadingEvent::Opened => "Opened".to_string(),
+            ReadingEvent::PagesRead {
+                first_page,
+                last_page,
+            } if first_page == last_page => format!("Read page {}", first_page + 1),
+            ReadingEvent::PagesRead {
+                first_page,
+                last_page,
+            } => format!("Read pages {}-{}", first_page + 1, last_page + 1),
+            ReadingEvent::Annotated { page_index, text } => {
+                format!("Annotated page {}: \"{}\"", page_index + 1, text)
+            }
+            ReadingEvent::LookedUp { page_index, word } => {
+                format!("Looked up \"{}\" on page {}", word, page_index + 1)
+            }
+        }
+    }
+}
+
+/// An event with the local day (`YYYY-MM-DD`) and time (`HH:MM`) it happened at
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedEvent {
+    pub pdf_path: String,
+    pub day: String,
+    pub time: String,
+    pub event: ReadingEvent,
+}
+
+/// Error type for reading log operations
+#[derive(Debug)]
+pub enum ReadingLogError {
+    DatabaseError(String),
+}
+
+impl std::fmt::Display for ReadingLogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadingLogError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ReadingLogError {}
+
+impl From<rusqlite::Error> for ReadingLogError {
+    fn from(err: rusqlite::Error) -> Self {
+        ReadingLogError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for ReadingLogError {
+    fn from(err: DatabaseError) -> Self {
+        ReadingLogError::DatabaseError(err.to_string())
+    }
+}
+
+/// Where `:readinglog` writes without a path
+pub fn default_export_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|p| p.join("eyers").join("reading-journal.md"))
+}
+
+/// Record `event` in `pdf_path` as happening now
+pub fn record_event(pdf_path: &str, event: &ReadingEvent) -> Result<(), ReadingLogError> {
+    let conn = database::open()?;
+    let now = glib::DateTime::now_local()
+        .map_err(|e| ReadingLogError::DatabaseError(format!("Could not read the clock: {}", e)))?;
+    let day = now
+        .format("%Y-%m-%d")
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    let time = now
+        .format("%H:%M")
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    let (first_page, last_page, detail) = event.columns();
+
+    conn.execute(
+        "INSERT INTO reading_events
+            (kind, pdf_path, first_page, last_page, detail, day, time, happened_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            event.kind(),
+            pdf_path,
+            first_page as i64,
+            last_page as i64,
+            detail,
+            day,
+            time,
+            now.to_unix()
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Every recorded event, oldest first
+pub fn load_events() -> Result<Vec<LoggedEvent>, ReadingLogError> {
+    let conn = database::open()?;
+    let mut stmt = conn.prepare(
+        "SELECT kind, pdf_path, first_page, last_page, detail, day, time
+         FROM reading_events ORDER BY happened_at, id",
+    )?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)? as usize,
+                row.get::<_, i64>(3)? as usize,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Kinds written by a newer version are skipped
+    Ok(rows
+        .into_iter()
+        .filter_map(|(kind, pdf_path, first, last, detail, day, time)| {
+            Some(LoggedEvent {
+                pdf_path,
+                day,
+                time,
+                event: ReadingEvent::from_columns(&kind, first, last, detail)?,
+            })
+        })
+        .collect())
+}
+
+/// Extend the range of pages being read with `page_index`. Moving to a page next
+/// to the range (or inside it) grows it; jumping elsewhere starts a new range and
+/// returns the finished one.
+pub fn track_page(
+    range: Option<(usize, usize)>,
+    page_index: usize,
+) -> ((usize, usize), Option<(usize, usize)>) {
+    match range {
+        Some((first, last)) if page_index + 1 >= first && page_index <= last + 1 => {
+            ((first.min(page_index), last.max(page_index)), None)
+        }
+        Some(finished) => ((page_index, page_index), Some(finished)),
+        None => ((page_index, page_index), None),
+    }
+}
+
+/// The reading journal in markdown: a `##` heading per day, then a `###` heading
+/// per document with its events in the order they happened
+pub fn format_journal(events: &[LoggedEvent]) -> String {
+    let mut output = String::from("# Reading Journal\n\n");
+    if events.is_empty() {
+        output.push_str("Nothing recorded yet.\n");
+        return output;
+    }
+
+    let mut days: Vec<&str> = Vec::new();
+    for event in events {
+        if !days.contains(&event.day.as_str()) {
+            days.push(&event.day);
+        }
+    }
+
+    for day in days {
+        output.push_str(&format!("## {}\n\n", day));
+        let day_events: Vec<&LoggedEvent> = events.iter().filter(|e| e.day == day).collect();
+
+        let mut documents: Vec<&str> = Vec::new();
+        for event in &day_events {
+            if !documents.contains(&event.pdf_path.as_str()) {
+                documents.push(&event.pdf_path);
+            }
+        }
+
+        for pdf_path in documents {
+            let name = Path::new(pdf_path)
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or(pdf_path);
+            output.push_str(&format!("### {}\n\n", name));
+            for event in day_events.iter().filter(|e| e.pdf_path == pdf_path) {
+                output.push_str(&format!("- {} {}\n", event.time, event.event.describe()));
+            }
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logged(pdf_path: &str, day: &str, time: &str, event: ReadingEvent) -> LoggedEvent {
+        LoggedEvent {
+            pdf_path: pdf_path.to_string(),
+            day: day.to_string(),
+            time: time.to_string(),
+            event,
+        }
+    }
+
+    #[test]
+    fn test_track_page() {
+        assert_eq!(track_page(None, 4), ((4, 4), None));
+        assert_eq!(track_page(Some((4, 4)), 5), ((4, 5), None));
+        assert_eq!(track_page(Some((4, 5)), 4), ((4, 5), None));
+        assert_eq!(track_page(Some((4, 5)), 3), ((3, 5), None));
+        assert_eq!(track_page(Some((4, 5)), 9), ((9, 9), Some((4, 5))));
+        assert_eq!(track_page(Some((0, 0)), 0), ((0, 0), None));
+    }
+
+    #[test]
+    fn test_event_columns_round_trip() {
+        let events = [
+            ReadingEvent::Opened,
+            ReadingEvent::PagesRead {
+                first_page: 2,
+                last_page: 7,
+            },
+            ReadingEvent::Annotated {
+                page_index: 3,
+                text: "a quote".to_string(),
+            },
+            ReadingEvent::LookedUp {
+                page_index: 5,
+                word: "ephemeral".to_string(),
+            },
+        ];
+        for event in events {
+            let (first, last, detail) = event.columns();
+            assert_eq!(
+                ReadingEvent::from_columns(event.kind(), first, last, detail.to_string()),
+                Some(event)
+            );
+        }
+        assert_eq!(
+            ReadingEvent::from_columns("unknown", 0, 0, String::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_journal() {
+        let events = [
+            logged(
+                "/docs/book.pdf",
+                "2026-10-15",
+                "09:12",
+                ReadingEvent::Opened,
+            ),
+            logged(
+                "/docs/paper.pdf",
+                "2026-10-15",
+                "09:30",
+                ReadingEvent::LookedUp {
+                    page_index: 0,
+                    word: "lemma".to_string(),
+                },
+            ),
+            logged(
+                "/docs/book.pdf",
+                "2026-10-15",
+                "09:40",
+                ReadingEvent::PagesRead {
+                    first_page: 0,
+                    last_page: 11,
+                },
+            ),
+            logged(
+                "/docs/book.pdf",
+                "2026-10-16",
+                "20:05",
+                ReadingEvent::Annotated {
+                    page_index: 12,
+                    text: "key idea".to_string(),
+                },
+            ),
+        ];
+
+        assert_eq!(
+            format_journal(&events),
+            "# Reading Journal\n\n\
+             ## 2026-10-15\n\n\
+             ### book.pdf\n\n\
+             - 09:12 Opened\n\
+             - 09:40 Read pages 1-12\n\n\
+             ### paper.pdf\n\n\
+             - 09:30 Looked up \"lemma\" on page 1\n\n\
+             ## 2026-10-16\n\n\
+             ### book.pdf\n\n\
+             - 20:05 Annotated page 13: \"key idea\"\n\n"
+        );
+    }
+
+    #[test]
+    fn test_format_empty_journal() {
+        assert_eq!(
+            format_journal(&[]),
+            "# Reading Journal\n\nNothing recorded yet.\n"
+        );
+    }
+}
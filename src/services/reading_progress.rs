@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+
+/// Only the reading of the last minutes counts towards the speed, so the
+/// estimate follows the current pace rather than the whole session's
+const SPEED_WINDOW_SECS: f64 = 15.0 * 60.0;
+/// Moving further than this at once is a jump, not reading
+const MAX_STEP_PAGES: f64 = 3.0;
+/// Staying longer than this on a position is a break, not reading
+const MAX_STEP_SECS: f64 = 10.0 * 60.0;
+/// Pages to read before the speed is trusted for an estimate
+const MIN_PAGES_READ: f64 = 0.5;
+/// Positions closer in time than this are merged into one sample
+const MIN_SAMPLE_SECS: f64 = 1.0;
+
+/// Share of the document read at `position` (in pages, see
+/// `PdfView::reading_position`), from 0.0 to 100.0
+pub fn progress_percent(position: f64, page_count: usize) -> f64 {
+    if page_count == 0 {
+        return 0.0;
+    }
+    (position / page_count as f64 * 100.0).clamp(0.0, 100.0)
+}
+
+/// Reading speed measured from the positions reached over time. Forward moves
+/// of a few pages count as reading; jumps, going back and long breaks don't.
+#[derive(Debug, Default)]
+pub struct ReadingSpeed {
+    /// (seconds, position in pages), oldest first
+    samples: VecDeque<(f64, f64)>,
+}
+
+impl ReadingSpeed {
+    /// Record that `position` was reached at `at` seconds (any monotonic clock)
+    pub fn record(&mut self, at: f64, position: f64) {
+        if let Some(last) = self.samples.back_mut() {
+            if at - last.0 < MIN_SAMPLE_SECS {
+                last.1 = position;
+                return;
+            }
+        }
+        self.samples.push_back((at, position));
+
+        while self
+            .samples
+            .front()
+            .is_some_and(|&(first, _)| at - first > SPEED_WINDOW_SECS)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Forget the samples, e.g. when another document is opened
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Seconds spent per page recently, None until enough was read
+    pub fn seconds_per_page(&self) -> Option<f64> {
+        let (mut pages, mut seconds) = (0.0, 0.0);
+        for (&(t1, p1), &(t2, p2)) in self.samples.iter().zip(self.samples.iter().skip(1)) {
+            let (step_pages, step_secs) = (p2 - p1, t2 - t1);
+            if step_pages > 0.0 && step_pages <= MAX_STEP_PAGES && step_secs <= MAX_STEP_SECS {
+                pages += step_pages;
+                seconds += step_secs;
+            }
+        }
+        (pages >= MIN_PAGES_READ).then(|| seconds / pages)
+    }
+
+    /// Seconds left to reach the end of a `page_count` pages document from `position`
+    pub fn time_left(&self, position: f64, page_count: usize) -> Option<f64> {
+        let remaining = (page_count as f64 - position).max(0.0);
+        Some(remaining * self.seconds_per_page()?)
+    }
+}
+
+/// "2 h 05 min left", "12 min left" or "< 1 min left"
+pub fn format_time_left(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    if minutes == 0 {
+        "< 1 min left".to_string()
+    } else if minutes < 60 {
+        format!("{} min left", minutes)
+    } else {
+        format!("{} h {:02} min left", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_percent() {
+        assert_eq!(progress_percent(0.0, 10), 0.0);
+        assert_eq!(progress_percent(2.5, 10), 25.0);
+        assert_eq!(progress_percent(12.0, 10), 100.0);
+        assert_eq!(progress_percent(1.0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_reading_speed() {
+        let mut speed = ReadingSpeed::default();
+        speed.record(0.0, 0.0);
+        speed.record(60.0, 0.25);
+        assert_eq!(speed.seconds_per_page(), None);
+
+        speed.record(120.0, 0.5);
+        speed.record(240.0, 1.0);
+        assert_eq!(speed.seconds_per_page(), Some(240.0));
+
+        // Jumps, going back and breaks don't change the speed
+        speed.record(250.0, 40.0);
+        speed.record(260.0, 1.0);
+        speed.record(900.0, 1.5);
+        assert_eq!(speed.seconds_per_page(), Some(240.0));
+
+        assert_eq!(speed.time_left(1.0, 11), Some(2400.0));
+        assert_eq!(speed.time_left(20.0, 11), Some(0.0));
+    }
+
+    #[test]
+    fn test_samples_merged_and_expired() {
+        let mut speed = ReadingSpeed::default();
+        speed.record(0.0, 0.0);
+        speed.record(0.5, 0.1);
+        assert_eq!(speed.samples, [(0.0, 0.1)]);
+
+        speed.record(SPEED_WINDOW_SECS + 10.0, 0.2);
+        assert_eq!(speed.samples, [(SPEED_WINDOW_SECS + 10.0, 0.2)]);
+    }
+
+    #[test]
+    fn test_format_time_left() {
+        assert_eq!(format_time_left(20.0), "< 1 min left");
+        assert_eq!(format_time_left(12.0 * 60.0), "12 min left");
+        assert_eq!(format_time_left(125.0 * 60.0), "2 h 05 min left");
+    }
+}
@@ -0,0 +1,206 @@
+use rusqlite::params;
+use std::cmp::Reverse;
+
+use crate::services::database::{self, DatabaseError};
+
+/// Files beyond this many are forgotten, oldest first
+const MAX_RECENT_FILES: usize = 50;
+
+/// A PDF opened before
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentFile {
+    pub pdf_path: String,
+    /// Unix timestamp of the last time it was opened
+    pub opened_at: i64,
+}
+
+impl RecentFile {
+    /// File name shown in the list, the full path when it has none
+    pub fn file_name(&self) -> String {
+        std::path::Path::new(&self.pdf_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.pdf_path.clone())
+    }
+}
+
+/// Error type for recent file operations
+#[derive(Debug)]
+pub enum RecentFilesError {
+    DatabaseError(String),
+}
+
+impl std::fmt::Display for RecentFilesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecentFilesError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RecentFilesError {}
+
+impl From<rusqlite::Error> for RecentFilesError {
+    fn from(err: rusqlite::Error) -> Self {
+        RecentFilesError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for RecentFilesError {
+    fn from(err: DatabaseError) -> Self {
+        RecentFilesError::DatabaseError(err.to_string())
+    }
+}
+
+/// Record that a PDF was just opened, moving it to the top of the list
+pub fn record_opened(pdf_path: &str) -> Result<(), RecentFilesError> {
+    let conn = database::open()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO recent_files (pdf_path, opened_at) VALUES (?1, ?2)",
+        params![pdf_path, now],
+    )?;
+    conn.execute(
+        "DELETE FROM recent_files WHERE pdf_path NOT IN
+         (SELECT pdf_path FROM recent_files ORDER BY opened_at DESC LIMIT ?1)",
+        params![MAX_RECENT_FILES as i64],
+    )?;
+
+    Ok(())
+}
+
+/// Recently opened PDFs, most recent first
+pub fn load_recent_files() -> Result<Vec<RecentFile>, RecentFilesError> {
+    let conn = database::open()?;
+    let mut stmt = conn.prepare(
+        "SELECT pdf_path, opened_at FROM recent_files ORDER BY opened_at DESC, rowid DESC",
+    )?;
+
+    let files = stmt
+        .query_map([], |row| {
+            Ok(RecentFile {
+                pdf_path: row.get(0)?,
+                opened_at: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// Forget a PDF, e.g. one that was moved or deleted
+pub fn remove_recent_file(pdf_path: &str) -> Result<(), RecentFilesError> {
+    let conn = database::open()?;
+    conn.execute(
+        "DELETE FROM recent_files WHERE pdf_path = ?1",
+        params![pdf_path],
+    )?;
+    Ok(())
+}
+
+/// Score how well `query` matches `candidate` as a case-insensitive subsequence,
+/// None when some query character is missing. Consecutive characters and matches
+/// at word starts score higher, so "tpl" ranks "the_pragmatic_lisp.pdf" first.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let offset = candidate[next..].iter().position(|&c| c == query_char)?;
+        let index = next + offset;
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        // Matches close to the start of the name are preferred
+        score -= offset.min(10) as i32 / 2;
+
+        previous_match = Some(index);
+        next = index + 1;
+    }
+
+    Some(score)
+}
+
+/// Files matching `query` by file name (or path), best matches first. An empty
+/// query keeps every file in recency order.
+pub fn filter_recent_files<'a>(files: &'a [RecentFile], query: &str) -> Vec<&'a RecentFile> {
+    if query.trim().is_empty() {
+        return files.iter().collect();
+    }
+
+    let mut matches: Vec<(i32, &RecentFile)> = files
+        .iter()
+        .filter_map(|file| {
+            let score = fuzzy_score(query, &file.file_name())
+                .map(|score| score + 10)
+                .or_else(|| fuzzy_score(query, &file.pdf_path))?;
+            Some((score, file))
+        })
+        .collect();
+    // Stable, so equal scores stay in recency order
+    matches.sort_by_key(|(score, _)| Reverse(*score));
+    matches.into_iter().map(|(_, file)| file).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recent(pdf_path: &str) -> RecentFile {
+        RecentFile {
+            pdf_path: pdf_path.to_string(),
+            opened_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score_requires_subsequence() {
+        assert!(fuzzy_score("rust", "The Rust Book.pdf").is_some());
+        assert!(fuzzy_score("RB", "The Rust Book.pdf").is_some());
+        assert_eq!(fuzzy_score("rusty", "The Rust Book.pdf"), None);
+        assert_eq!(fuzzy_score("", "anything.pdf"), Some(0));
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_consecutive_and_word_starts() {
+        let consecutive = fuzzy_score("rust", "rust.pdf").unwrap();
+        let scattered = fuzzy_score("rust", "reading_under_stress_today.pdf").unwrap();
+        assert!(consecutive > scattered);
+
+        let word_starts = fuzzy_score("tpl", "the_pragmatic_lisp.pdf").unwrap();
+        let inside = fuzzy_score("tpl", "stipple.pdf").unwrap();
+        assert!(word_starts > inside);
+    }
+
+    #[test]
+    fn test_filter_recent_files() {
+        let files = vec![
+            recent("/books/algorithms.pdf"),
+            recent("/papers/rust/ownership.pdf"),
+            recent("/books/rust_book.pdf"),
+        ];
+
+        let all = filter_recent_files(&files, " ");
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].pdf_path, "/books/algorithms.pdf");
+
+        // File name matches rank above matches in the directory only
+        let rust = filter_recent_files(&files, "rust");
+        assert_eq!(rust.len(), 2);
+        assert_eq!(rust[0].pdf_path, "/books/rust_book.pdf");
+        assert_eq!(rust[1].pdf_path, "/papers/rust/ownership.pdf");
+
+        assert!(filter_recent_files(&files, "xyz").is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::backend::RenderedPage;
+
+/// Clockwise rotation a page is shown with, in quarter turns. Points are in screen
+/// orientation (y grows downward) relative to the top-left corner of the page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rotation {
+    quarter_turns: u8,
+}
+
+impl Rotation {
+    pub const NONE: Rotation = Rotation { quarter_turns: 0 };
+
+    /// Turned `quarter_turns` further, clockwise when positive
+    pub fn rotated(self, quarter_turns: i32) -> Rotation {
+        Rotation {
+            quarter_turns: (self.quarter_turns as i32 + quarter_turns).rem_euclid(4) as u8,
+        }
+    }
+
+    /// This rotation followed by `other`
+    pub fn combined(self, other: Rotation) -> Rotation {
+        self.rotated(other.quarter_turns as i32)
+    }
+
+    /// The rotation turning a rotated page back upright
+    pub fn inverse(self) -> Rotation {
+        Rotation::NONE.rotated(-(self.quarter_turns as i32))
+    }
+
+    pub fn degrees(self) -> u32 {
+        self.quarter_turns as u32 * 90
+    }
+
+    /// Size of a `(width, height)` page once rotated
+    pub fn size<T>(self, (width, height): (T, T)) -> (T, T) {
+        if self.quarter_turns % 2 == 1 {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Where a point of the unrotated page of `size` ends up once rotated
+    pub fn to_screen(self, (x, y): (f64, f64), (width, height): (f64, f64)) -> (f64, f64) {
+        match self.quarter_turns {
+            1 => (height - y, x),
+            2 => (width - x, height - y),
+            3 => (y, width - x),
+            _ => (x, y),
+        }
+    }
+
+    /// Point of the unrotated page of `size` shown at `(x, y)` on the rotated page
+    pub fn to_page(self, (x, y): (f64, f64), (width, height): (f64, f64)) -> (f64, f64) {
+        match self.quarter_turns {
+            1 => (y, height - x),
+            2 => (width - x, height - y),
+            3 => (width - y, x),
+            _ => (x, y),
+        }
+    }
+
+    /// `(x, y, width, height)` rect of the unrotated page of `size` once rotated
+    pub fn rect_to_screen(
+        self,
+        (x, y, width, height): (f64, f64, f64, f64),
+        size: (f64, f64),
+    ) -> (f64, f64, f64, f64) {
+        let (x1, y1) = self.to_screen((x, y), size);
+        let (x2, y2) = self.to_screen((x + width, y + height), size);
+        (x1.min(x2), y1.min(y2), (x2 - x1).abs(), (y2 - y1).abs())
+    }
+
+    /// `(left, bottom, right, top)` rect in PDF points, where y grows upward, of the
+    /// unrotated page of `size` once rotated
+    pub fn pdf_rect_to_rotated(
+        self,
+        (left, bottom, right, top): (f64, f64, f64, f64),
+        (width, height): (f64, f64),
+    ) -> (f64, f64, f64, f64) {
+        let (x, y, rect_width, rect_height) = self.rect_to_screen(
+            (left, height - top, right - left, top - bottom),
+            (width, height),
+        );
+        let (_, rotated_height) = self.size((width, height));
+        (
+            x,
+            rotated_height - y - rect_height,
+            x + rect_width,
+            rotated_height - y,
+        )
+    }
+
+    /// Turn the pixels of a rendered page
+    pub fn rotate_page(self, page: RenderedPage) -> RenderedPage {
+        if self.quarter_turns == 0 {
+            return page;
+        }
+
+        let (src_width, src_height) = (page.width as usize, page.height as usize);
+        let (width, height) = self.size((src_width, src_height));
+        let stride = width * 4;
+        let mut pixels = vec![0; stride * height];
+
+        for src_y in 0..src_height {
+            let row = &page.pixels[src_y * page.stride..];
+            for src_x in 0..src_width {
+                let (x, y) = match self.quarter_turns {
+                    1 => (src_height - 1 - src_y, src_x),
+                    2 => (src_width - 1 - src_x, src_height - 1 - src_y),
+                    _ => (src_y, src_width - 1 - src_x),
+                };
+                let dst = y * stride + x * 4;
+                pixels[dst..dst + 4].copy_from_slice(&row[src_x * 4..src_x * 4 + 4]);
+            }
+        }
+
+        RenderedPage {
+            width: width as i32,
+            height: height as i32,
+            stride,
+            pixels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: (f64, f64) = (100.0, 200.0);
+
+    #[test]
+    fn test_rotated_wraps_around() {
+        assert_eq!(Rotation::NONE.rotated(-1).degrees(), 270);
+        assert_eq!(Rotation::NONE.rotated(5).degrees(), 90);
+        assert_eq!(
+            Rotation::NONE
+                .rotated(3)
+                .combined(Rotation::NONE.rotated(2)),
+            Rotation::NONE.rotated(1)
+        );
+        assert_eq!(Rotation::NONE.rotated(1).size(SIZE), (200.0, 100.0));
+    }
+
+    #[test]
+    fn test_points_round_trip() {
+        for turns in 0..4 {
+            let rotation = Rotation::NONE.rotated(turns);
+            let screen = rotation.to_screen((10.0, 30.0), SIZE);
+            assert_eq!(rotation.to_page(screen, SIZE), (10.0, 30.0));
+        }
+        // The top-left corner goes to the top-right once turned clockwise
+        assert_eq!(
+            Rotation::NONE.rotated(1).to_screen((0.0, 0.0), SIZE),
+            (200.0, 0.0)
+        );
+        assert_eq!(
+            Rotation::NONE.rotated(3).to_screen((0.0, 0.0), SIZE),
+            (0.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn test_rect_to_screen() {
+        let rotation = Rotation::NONE.rotated(1);
+        assert_eq!(
+            rotation.rect_to_screen((10.0, 20.0, 30.0, 5.0), SIZE),
+            (175.0, 10.0, 5.0, 30.0)
+        );
+    }
+
+    #[test]
+    fn test_pdf_rect_to_rotated() {
+        let rect = (10.0, 20.0, 40.0, 25.0);
+        assert_eq!(Rotation::NONE.pdf_rect_to_rotated(rect, SIZE), rect);
+        // The left edge of the page becomes its top once turned clockwise
+        assert_eq!(
+            Rotation::NONE.rotated(1).pdf_rect_to_rotated(rect, SIZE),
+            (20.0, 60.0, 25.0, 90.0)
+        );
+        assert_eq!(
+            Rotation::NONE.rotated(2).pdf_rect_to_rotated(rect, SIZE),
+            (60.0, 175.0, 90.0, 180.0)
+        );
+    }
+
+    #[test]
+    fn test_pdf_rect_round_trip() {
+        let rect = (10.0, 20.0, 40.0, 25.0);
+        for turns in 0..4 {
+            let rotation = Rotation::NONE.rotated(turns);
+            let rotated = rotation.pdf_rect_to_rotated(rect, SIZE);
+            assert_eq!(
+                rotation
+                    .inverse()
+                    .pdf_rect_to_rotated(rotated, rotation.size(SIZE)),
+                rect
+            );
+        }
+    }
+
+    #[test]
+    fn test_rotate_page() {
+        // 2x1 page, a red pixel then a blue one
+        let page = RenderedPage {
+            width: 2,
+            height: 1,
+            stride: 8,
+            pixels: vec![0, 0, 255, 255, 255, 0, 0, 255],
+        };
+        let rotated = Rotation::NONE.rotated(1).rotate_page(page);
+        assert_eq!((rotated.width, rotated.height, rotated.stride), (1, 2, 4));
+        assert_eq!(rotated.pixels, vec![0, 0, 255, 255, 255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,151 @@
+use rusqlite::{OptionalExtension, params};
+
+use crate::modes::WordCursor;
+use crate::services::database::{self, DatabaseError};
+use crate::services::dictionary::Language;
+use crate::services::pdf_text::ZoomMode;
+
+/// View state of a PDF when it was last closed, restored when it is opened again
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionState {
+    pub pdf_path: String,
+    /// Horizontal and vertical scroll offsets in pixels at `zoom`
+    pub scroll_x: f64,
+    pub scroll_y: f64,
+    pub zoom: f64,
+    /// How `zoom` was chosen, fit modes fit the pages to the view again
+    pub zoom_mode: ZoomMode,
+    /// Page shown in paged mode, None when the pages were scrolled continuously
+    pub paged_page: Option<usize>,
+    /// Visual mode cursor, None when the document was left in Normal mode
+    pub cursor: Option<WordCursor>,
+}
+
+/// Error type for session operations
+#[derive(Debug)]
+pub enum SessionError {
+    DatabaseError(String),
+}
+
+impl std::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<rusqlite::Error> for SessionError {
+    fn from(err: rusqlite::Error) -> Self {
+        SessionError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for SessionError {
+    fn from(err: DatabaseError) -> Self {
+        SessionError::DatabaseError(err.to_string())
+    }
+}
+
+/// Save the view state of a PDF, replacing the previous one
+pub fn save_session(session: &SessionState) -> Result<(), SessionError> {
+    let conn = database::open()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO sessions
+         (pdf_path, scroll_x, scroll_y, zoom, zoom_mode, paged_page, cursor_page, cursor_word,
+          updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            session.pdf_path,
+            session.scroll_x,
+            session.scroll_y,
+            session.zoom,
+            session.zoom_mode.code(),
+            session.paged_page.map(|page| page as i64),
+            session.cursor.map(|c| c.page_index as i64),
+            session.cursor.map(|c| c.word_index as i64),
+            now
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Load the view state a PDF was last closed with, if it was opened before
+pub fn load_session(pdf_path: &str) -> Result<Option<SessionState>, SessionError> {
+    let conn = database::open()?;
+
+    let session = conn
+        .query_row(
+            "SELECT scroll_x, scroll_y, zoom, cursor_page, cursor_word, zoom_mode, paged_page
+             FROM sessions WHERE pdf_path = ?1",
+            params![pdf_path],
+            |row| {
+                let zoom: f64 = row.get(2)?;
+                let cursor_page: Option<i64> = row.get(3)?;
+                let cursor_word: Option<i64> = row.get(4)?;
+                let zoom_mode: Option<String> = row.get(5)?;
+                let paged_page: Option<i64> = row.get(6)?;
+                Ok(SessionState {
+                    pdf_path: pdf_path.to_string(),
+                    scroll_x: row.get(0)?,
+                    scroll_y: row.get(1)?,
+                    zoom,
+                    zoom_mode: zoom_mode
+                        .as_deref()
+                        .and_then(ZoomMode::from_code)
+                        .unwrap_or(ZoomMode::Percentage(zoom)),
+                    paged_page: paged_page.map(|page| page as usize),
+                    cursor: cursor_page
+                        .zip(cursor_word)
+                        .map(|(page, word)| WordCursor::new(page as usize, word as usize)),
+                })
+            },
+        )
+        .optional()?;
+
+    Ok(session)
+}
+
+/// Set the dictionary language of a PDF, or go back to the global language with None
+pub fn save_document_language(
+    pdf_path: &str,
+    language: Option<Language>,
+) -> Result<(), SessionError> {
+    let conn = database::open()?;
+
+    match language {
+        Some(language) => conn.execute(
+            "INSERT OR REPLACE INTO document_languages (pdf_path, language) VALUES (?1, ?2)",
+            params![pdf_path, language.code()],
+        )?,
+        None => conn.execute(
+            "DELETE FROM document_languages WHERE pdf_path = ?1",
+            params![pdf_path],
+        )?,
+    };
+
+    Ok(())
+}
+
+/// Dictionary language a PDF overrides the global one with, if any
+pub fn load_document_language(pdf_path: &str) -> Result<Option<Language>, SessionError> {
+    let conn = database::open()?;
+
+    let code: Option<String> = conn
+        .query_row(
+            "SELECT language FROM document_languages WHERE pdf_path = ?1",
+            params![pdf_path],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(code.as_deref().and_then(Language::from_code))
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::services::theme::Theme;
+use crate::services::web_search::SearchEngine;
+
+/// Where the annotation panel opens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanelPosition {
+    /// Docked under the pages
+    #[default]
+    Bottom,
+    /// Docked right of the pages, leaving their whole height for reading
+    Right,
+    /// In a window of its own
+    Floating,
+}
+
+impl PanelPosition {
+    pub const ALL: [PanelPosition; 3] = [
+        PanelPosition::Bottom,
+        PanelPosition::Right,
+        PanelPosition::Floating,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PanelPosition::Bottom => "Bottom",
+            PanelPosition::Right => "Right",
+            PanelPosition::Floating => "Floating Window",
+        }
+    }
+}
+
+/// Preferences chosen in the settings window, kept across sessions.
+/// Fields missing from the file keep their default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub annotation_panel: PanelPosition,
+    /// URL template `gx` searches the selection with, see `web_search::search_url`
+    pub web_search: String,
+    /// Look up the words copied in other applications
+    pub clipboard_lookup: bool,
+    /// Cut the blank margins found on the pages
+    pub auto_crop: bool,
+    pub theme: Theme,
+    /// Commands run after annotations are exported to markdown, one per line, see
+    /// `post_export::run_commands`
+    pub post_export_commands: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            annotation_panel: PanelPosition::default(),
+            web_search: SearchEngine::DuckDuckGo.template().to_string(),
+            clipboard_lookup: false,
+            auto_crop: false,
+            theme: Theme::default(),
+            post_export_commands: String::new(),
+        }
+    }
+}
+
+/// Error type for settings operations
+#[derive(Debug)]
+pub enum SettingsError {
+    IoError(String),
+    SerializeError(String),
+}
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::IoError(msg) => write!(f, "IO error: {}", msg),
+            SettingsError::SerializeError(msg) => write!(f, "Serialize error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<std::io::Error> for SettingsError {
+    fn from(err: std::io::Error) -> Self {
+        SettingsError::IoError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for SettingsError {
+    fn from(err: serde_json::Error) -> Self {
+        SettingsError::SerializeError(err.to_string())
+    }
+}
+
+/// Returns the path to the settings file
+fn get_settings_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|p| p.join("eyers").join("settings.json"))
+}
+
+/// Read the settings, the built-in ones when none were saved or the file is unreadable
+pub fn load_settings() -> Settings {
+    get_settings_path()
+        .and_then(|path| std::fs::read(path).ok())
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &Settings) -> Result<(), SettingsError> {
+    let path = get_settings_path()
+        .ok_or_else(|| SettingsError::IoError("Could not determine data directory".to_string()))?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(&path, serde_json::to_vec_pretty(settings)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields_keep_defaults() {
+        let settings: Settings =
+            serde_json::from_str(r#"{"annotation_panel": "Right", "auto_crop": true}"#).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                annotation_panel: PanelPosition::Right,
+                auto_crop: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(settings.web_search, SearchEngine::DuckDuckGo.template());
+    }
+}
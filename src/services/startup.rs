@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::services::dictionary::DictionarySource;
+
+/// Lookup mode switched on when Eyers starts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StartupMode {
+    /// No lookups on click, only scrolling and selecting
+    #[default]
+    Normal,
+    Definitions,
+    Translate,
+}
+
+impl StartupMode {
+    pub const ALL: [StartupMode; 3] = [
+        StartupMode::Normal,
+        StartupMode::Definitions,
+        StartupMode::Translate,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StartupMode::Normal => "Normal",
+            StartupMode::Definitions => "Definitions",
+            StartupMode::Translate => "Translate",
+        }
+    }
+}
+
+/// What Eyers looks like when it starts, kept across sessions.
+/// Fields missing from the file keep their default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupDefaults {
+    pub mode: StartupMode,
+    pub show_header_bar: bool,
+    pub show_status_bar: bool,
+    pub dictionary_source: DictionarySource,
+}
+
+impl Default for StartupDefaults {
+    fn default() -> Self {
+        Self {
+            mode: StartupMode::default(),
+            show_header_bar: true,
+            show_status_bar: true,
+            dictionary_source: DictionarySource::default(),
+        }
+    }
+}
+
+/// Error type for startup defaults operations
+#[derive(Debug)]
+pub enum StartupError {
+    IoError(String),
+    SerializeError(String),
+}
+
+impl std::fmt::Display for StartupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartupError::IoError(msg) => write!(f, "IO error: {}", msg),
+            StartupError::SerializeError(msg) => write!(f, "Serialize error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StartupError {}
+
+impl From<std::io::Error> for StartupError {
+    fn from(err: std::io::Error) -> Self {
+        StartupError::IoError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for StartupError {
+    fn from(err: serde_json::Error) -> Self {
+        StartupError::SerializeError(err.to_string())
+    }
+}
+
+/// Returns the path to the startup defaults file
+fn get_startup_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|p| p.join("eyers").join("startup.json"))
+}
+
+/// Read the startup defaults, the built-in ones when none were saved or the file is unreadable
+pub fn load_startup_defaults() -> StartupDefaults {
+    get_startup_path()
+        .and_then(|path| std::fs::read(path).ok())
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_startup_defaults(defaults: &StartupDefaults) -> Result<(), StartupError> {
+    let path = get_startup_path()
+        .ok_or_else(|| StartupError::IoError("Could not determine data directory".to_string()))?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(&path, serde_json::to_vec_pretty(defaults)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields_keep_defaults() {
+        let defaults: StartupDefaults =
+            serde_json::from_str(r#"{"mode": "Definitions", "show_status_bar": false}"#).unwrap();
+        assert_eq!(
+            defaults,
+            StartupDefaults {
+                mode: StartupMode::Definitions,
+                show_status_bar: false,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_round_trip_local_source() {
+        let defaults = StartupDefaults {
+            dictionary_source: DictionarySource::Local(PathBuf::from("/usr/share/stardict")),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&defaults).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StartupDefaults>(&json).unwrap(),
+            defaults
+        );
+    }
+}
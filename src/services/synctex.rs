@@ -0,0 +1,446 @@
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// SyncTeX coordinates are scaled points; this many make one PDF point
+const SP_PER_POINT: f64 = 65781.76;
+
+/// A position on a page in PDF points, measured from the top-left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PagePosition {
+    pub page_index: usize,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// A place in a LaTeX source file
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub input: PathBuf,
+    pub line: u32,
+    pub column: Option<u32>,
+}
+
+/// One typeset node of the SyncTeX file
+#[derive(Debug, Clone)]
+struct Record {
+    page_index: usize,
+    tag: u32,
+    line: u32,
+    column: Option<u32>,
+    x: f64,
+    y: f64,
+    /// Width, height and depth of boxes
+    size: Option<(f64, f64, f64)>,
+}
+
+/// Mapping between a PDF and its LaTeX sources, read from the `.synctex(.gz)` file
+/// pdfTeX/XeTeX/LuaTeX write next to the PDF when run with `-synctex=1`
+#[derive(Debug, Default)]
+pub struct SyncTex {
+    inputs: HashMap<u32, PathBuf>,
+    records: Vec<Record>,
+}
+
+#[derive(Debug)]
+pub enum SyncTexError {
+    IoError(String),
+    ParseFailed(String),
+}
+
+impl std::fmt::Display for SyncTexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncTexError::IoError(msg) => write!(f, "IO error: {}", msg),
+            SyncTexError::ParseFailed(msg) => write!(f, "Parse failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SyncTexError {}
+
+impl From<std::io::Error> for SyncTexError {
+    fn from(err: std::io::Error) -> Self {
+        SyncTexError::IoError(err.to_string())
+    }
+}
+
+/// The SyncTeX file written next to `pdf_path`, if any
+pub fn find_synctex_file(pdf_path: &Path) -> Option<PathBuf> {
+    ["synctex.gz", "synctex"]
+        .iter()
+        .map(|extension| pdf_path.with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+/// Parse a forward search request of the form `LINE:COLUMN:FILE` (as used by
+/// other viewers) or `LINE:FILE`
+pub fn parse_forward_spec(spec: &str) -> Option<(u32, PathBuf)> {
+    let (line, rest) = spec.split_once(':')?;
+    let line = line.trim().parse().ok()?;
+    let file = match rest.split_once(':') {
+        Some((column, file)) if column.trim().parse::<i64>().is_ok() => file,
+        _ => rest,
+    };
+    (!file.is_empty()).then(|| (line, PathBuf::from(file)))
+}
+
+/// Substitute `%{input}`, `%{line}` and `%{column}` in one argument of the editor command
+pub fn expand_editor_arg(arg: &str, location: &SourceLocation) -> String {
+    arg.replace("%{input}", &location.input.to_string_lossy())
+        .replace("%{line}", &location.line.to_string())
+        .replace(
+            "%{column}",
+            &location.column.unwrap_or(0).max(1).to_string(),
+        )
+}
+
+impl SyncTex {
+    /// Read a SyncTeX file, gzipped or not. Relative input paths are resolved
+    /// against the file's directory.
+    pub fn load(path: &Path) -> Result<Self, SyncTexError> {
+        let bytes = std::fs::read(path)?;
+        let mut contents = String::new();
+        if path.extension().is_some_and(|ext| ext == "gz") {
+            GzDecoder::new(bytes.as_slice()).read_to_string(&mut contents)?;
+        } else {
+            contents =
+                String::from_utf8(bytes).map_err(|e| SyncTexError::ParseFailed(e.to_string()))?;
+        }
+
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Self::parse(&contents, base_dir)
+    }
+
+    fn parse(contents: &str, base_dir: &Path) -> Result<Self, SyncTexError> {
+        let mut synctex = SyncTex::default();
+        let mut unit = 1.0;
+        let mut magnification = 1000.0;
+        let mut x_offset = 0.0;
+        let mut y_offset = 0.0;
+        let mut page_index: Option<usize> = None;
+        let mut in_content = false;
+
+        for line in contents.lines() {
+            // Inputs are declared in the preamble, and between pages for files
+            // included later on
+            if let Some(input) = line.strip_prefix("Input:") {
+                let (tag, path) = input.split_once(':').ok_or_else(|| {
+                    SyncTexError::ParseFailed(format!("Bad input line: {}", line))
+                })?;
+                let tag = tag
+                    .parse()
+                    .map_err(|_| SyncTexError::ParseFailed(format!("Bad input tag: {}", line)))?;
+                synctex.inputs.insert(tag, base_dir.join(path));
+                continue;
+            }
+
+            if !in_content {
+                if let Some(value) = line.strip_prefix("Unit:") {
+                    unit = value.trim().parse().unwrap_or(1.0);
+                } else if let Some(value) = line.strip_prefix("Magnification:") {
+                    magnification = value.trim().parse().unwrap_or(1000.0);
+                } else if let Some(value) = line.strip_prefix("X Offset:") {
+                    x_offset = value.trim().parse().unwrap_or(0.0);
+                } else if let Some(value) = line.strip_prefix("Y Offset:") {
+                    y_offset = value.trim().parse().unwrap_or(0.0);
+                } else if line.starts_with("Content:") {
+                    in_content = true;
+                }
+                continue;
+            }
+
+            if line.starts_with("Postamble:") {
+                break;
+            }
+
+            let mut chars = line.chars();
+            let Some(kind) = chars.next() else {
+                continue;
+            };
+            match kind {
+                '{' => {
+                    page_index = chars
+                        .as_str()
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|page| page.checked_sub(1));
+                }
+                '}' => page_index = None,
+                '[' | '(' | 'h' | 'v' | 'x' | 'k' | 'g' | '$' => {
+                    let Some(page_index) = page_index else {
+                        continue;
+                    };
+                    let scale = unit * magnification / 1000.0 / SP_PER_POINT;
+                    if let Some(record) = Self::parse_record(
+                        chars.as_str(),
+                        page_index,
+                        matches!(kind, '[' | '(' | 'h' | 'v'),
+                        scale,
+                        (
+                            x_offset * unit / SP_PER_POINT,
+                            y_offset * unit / SP_PER_POINT,
+                        ),
+                    ) {
+                        synctex.records.push(record);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(synctex)
+    }
+
+    /// Parse `tag,line[,column]:h,v[:W,H,D]`
+    fn parse_record(
+        text: &str,
+        page_index: usize,
+        is_box: bool,
+        scale: f64,
+        (x_offset, y_offset): (f64, f64),
+    ) -> Option<Record> {
+        let mut parts = text.split(':');
+        let mut link = parts.next()?.split(',');
+        let tag = link.next()?.parse().ok()?;
+        let line = link.next()?.parse().ok()?;
+        let column = link
+            .next()
+            .and_then(|c| c.parse::<i64>().ok())
+            .and_then(|c| u32::try_from(c).ok());
+
+        let mut point = parts.next()?.split(',');
+        let x = point.next()?.parse::<f64>().ok()? * scale + x_offset;
+        let y = point.next()?.parse::<f64>().ok()? * scale + y_offset;
+
+        let size = if is_box {
+            parts.next().and_then(|size| {
+                let values: Vec<f64> = size
+                    .split(',')
+                    .filter_map(|v| v.parse::<f64>().ok())
+                    .map(|v| v * scale)
+                    .collect();
+                match values.as_slice() {
+                    [w, h, d] => Some((*w, *h, *d)),
+                    _ => None,
+                }
+            })
+        } else {
+            None
+        };
+
+        Some(Record {
+            page_index,
+            tag,
+            line,
+            column,
+            x,
+            y,
+            size,
+        })
+    }
+
+    /// Tags of the inputs matching `file`: same path, same path relative to the
+    /// SyncTeX file, or at least the same file name
+    fn input_tags(&self, file: &Path) -> Vec<u32> {
+        let canonical = |path: &Path| std::fs::canonicalize(path).ok();
+        let wanted = canonical(file);
+
+        let exact: Vec<u32> = self
+            .inputs
+            .iter()
+            .filter(|(_, input)| {
+                *input == file
+                    || input.ends_with(file)
+                    || (wanted.is_some() && canonical(input) == wanted)
+            })
+            .map(|(tag, _)| *tag)
+            .collect();
+        if !exact.is_empty() {
+            return exact;
+        }
+
+        self.inputs
+            .iter()
+            .filter(|(_, input)| {
+                file.file_name().is_some() && input.file_name() == file.file_name()
+            })
+            .map(|(tag, _)| *tag)
+            .collect()
+    }
+
+    /// Where `line` of `file` was typeset. Uses the first node of that line, or of
+    /// the closest following line when the line itself produced no output.
+    pub fn forward(&self, file: &Path, line: u32) -> Option<PagePosition> {
+        let tags = self.input_tags(file);
+
+        let candidates = self
+            .records
+            .iter()
+            .filter(|record| tags.contains(&record.tag));
+        let best_line = candidates
+            .clone()
+            .map(|record| record.line)
+            .filter(|l| *l >= line)
+            .min()
+            .or_else(|| candidates.clone().map(|record| record.line).max())?;
+
+        candidates
+            .filter(|record| record.line == best_line)
+            .min_by_key(|record| record.page_index)
+            .map(|record| PagePosition {
+                page_index: record.page_index,
+                x: record.x,
+                y: record.y,
+            })
+    }
+
+    /// The source line typeset at `position`: the smallest box containing it, or
+    /// the closest node on the page
+    pub fn reverse(&self, position: PagePosition) -> Option<SourceLocation> {
+        let on_page = self
+            .records
+            .iter()
+            .filter(|record| record.page_index == position.page_index);
+
+        let containing = on_page
+            .clone()
+            .filter_map(|record| {
+                let (w, h, d) = record.size?;
+                let inside = position.x >= record.x
+                    && position.x <= record.x + w
+                    && position.y >= record.y - h
+                    && position.y <= record.y + d;
+                inside.then_some((record, w * (h + d)))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(record, _)| record);
+
+        let record = containing.or_else(|| {
+            on_page.min_by(|a, b| {
+                let distance = |r: &Record| (r.x - position.x).powi(2) + (r.y - position.y).powi(2);
+                distance(a).total_cmp(&distance(b))
+            })
+        })?;
+
+        Some(SourceLocation {
+            input: self.inputs.get(&record.tag)?.clone(),
+            line: record.line,
+            column: record.column,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Unit 65781.76 makes every coordinate below a plain PDF point value
+    const SAMPLE: &str = "SyncTeX Version:1
+Input:1:./main.tex
+Input:2:./chapters/intro.tex
+Output:pdf
+Magnification:1000
+Unit:65781.76
+X Offset:0
+Y Offset:0
+Content:
+!120
+{1
+[1,5:72,72:400,600,0
+(2,10:72,100:400,12,3
+x2,10:80,100
+g2,11:200,100
+)
+(2,14:72,200:400,12,3
+k2,14:90,200:5
+)
+]
+}1
+{2
+[1,20:72,72:400,600,0
+(1,20:72,100:400,12,3
+x1,20:72,100
+)
+]
+}2
+Postamble:
+";
+
+    fn sample() -> SyncTex {
+        SyncTex::parse(SAMPLE, Path::new("/doc")).unwrap()
+    }
+
+    #[test]
+    fn test_forward() {
+        let synctex = sample();
+
+        let position = synctex
+            .forward(Path::new("chapters/intro.tex"), 14)
+            .unwrap();
+        assert_eq!(position.page_index, 0);
+        assert_eq!((position.x, position.y), (72.0, 200.0));
+
+        // Line 12 produced no output, the next typeset line is used
+        let position = synctex
+            .forward(Path::new("/doc/chapters/intro.tex"), 12)
+            .unwrap();
+        assert_eq!(position.y, 200.0);
+
+        let position = synctex.forward(Path::new("main.tex"), 20).unwrap();
+        assert_eq!(position.page_index, 1);
+
+        assert_eq!(synctex.forward(Path::new("other.tex"), 1), None);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let synctex = sample();
+
+        let location = synctex
+            .reverse(PagePosition {
+                page_index: 0,
+                x: 150.0,
+                y: 198.0,
+            })
+            .unwrap();
+        assert_eq!(location.input, PathBuf::from("/doc/./chapters/intro.tex"));
+        assert_eq!(location.line, 14);
+
+        let location = synctex
+            .reverse(PagePosition {
+                page_index: 1,
+                x: 100.0,
+                y: 95.0,
+            })
+            .unwrap();
+        assert_eq!(location.line, 20);
+    }
+
+    #[test]
+    fn test_parse_forward_spec() {
+        assert_eq!(
+            parse_forward_spec("42:7:chapters/intro.tex"),
+            Some((42, PathBuf::from("chapters/intro.tex")))
+        );
+        assert_eq!(
+            parse_forward_spec("42:main.tex"),
+            Some((42, PathBuf::from("main.tex")))
+        );
+        assert_eq!(parse_forward_spec("main.tex"), None);
+    }
+
+    #[test]
+    fn test_expand_editor_arg() {
+        let location = SourceLocation {
+            input: PathBuf::from("/doc/main.tex"),
+            line: 12,
+            column: None,
+        };
+        assert_eq!(
+            expand_editor_arg("%{input}:%{line}:%{column}", &location),
+            "/doc/main.tex:12:1"
+        );
+    }
+}
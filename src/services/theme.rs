@@ -0,0 +1,216 @@
+use gtk::gdk;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::services::annotations::AnnotationColor;
+
+/// Look of the window chosen in the settings, under the user stylesheet
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    /// The GTK theme, light or dark as the desktop asks
+    #[default]
+    System,
+    Light,
+    Dark,
+    /// Black on white with bold outlines and saturated highlights
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [
+        Theme::System,
+        Theme::Light,
+        Theme::Dark,
+        Theme::HighContrast,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::HighContrast => "High Contrast",
+        }
+    }
+
+    /// Whether GTK's dark variant is asked for, or None to leave it to the desktop
+    pub fn prefers_dark(&self) -> Option<bool> {
+        match self {
+            Theme::System => None,
+            Theme::Light | Theme::HighContrast => Some(false),
+            Theme::Dark => Some(true),
+        }
+    }
+
+    /// Stylesheet layered over the app one
+    pub fn css(&self) -> &'static str {
+        match self {
+            Theme::System => "",
+            Theme::Light => include_str!("../resources/theme-light.css"),
+            Theme::Dark => include_str!("../resources/theme-dark.css"),
+            Theme::HighContrast => include_str!("../resources/theme-high-contrast.css"),
+        }
+    }
+}
+
+/// An opaque color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// A CSS color value, e.g. `#f5e663` or `rgb(245, 230, 99)`. None for what
+    /// GDK can't parse on its own, like references to other named colors.
+    fn parse(value: &str) -> Option<Rgb> {
+        let color = gdk::RGBA::parse(value).ok()?;
+        let channel = |value: f32| (value * 255.0).round() as u8;
+        Some(Rgb(
+            channel(color.red()),
+            channel(color.green()),
+            channel(color.blue()),
+        ))
+    }
+
+    /// Red, green and blue from 0.0 to 1.0, for cairo
+    pub fn fractions(self) -> (f64, f64, f64) {
+        (
+            self.0 as f64 / 255.0,
+            self.1 as f64 / 255.0,
+            self.2 as f64 / 255.0,
+        )
+    }
+}
+
+/// Colors the highlights are drawn with on the pages. The pages aren't styled by
+/// CSS, so these are read from the `@define-color eyers_*` lines of the
+/// stylesheets (see `with_stylesheet`). The defaults are those of style.css.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightPalette {
+    pub yellow: Rgb,
+    pub green: Rgb,
+    pub red: Rgb,
+    pub blue: Rgb,
+    pub search: Rgb,
+    pub selection: Rgb,
+    pub cursor: Rgb,
+}
+
+impl Default for HighlightPalette {
+    fn default() -> Self {
+        Self {
+            yellow: Rgb(0xf5, 0xe6, 0x63),
+            green: Rgb(0x73, 0xe6, 0x73),
+            red: Rgb(0xff, 0x73, 0x73),
+            blue: Rgb(0x73, 0xb3, 0xff),
+            search: Rgb(0xff, 0x99, 0x1a),
+            selection: Rgb(0x4d, 0x80, 0xe6),
+            cursor: Rgb(0x33, 0x66, 0xcc),
+        }
+    }
+}
+
+impl HighlightPalette {
+    /// The palette with the highlight colors a stylesheet defines applied
+    pub fn with_stylesheet(mut self, css: &str) -> Self {
+        for statement in strip_comments(css).split(';') {
+            // What follows the end of the previous rule, if any
+            let statement = statement.rsplit(['{', '}']).next().unwrap_or_default();
+            let Some(definition) = statement.trim().strip_prefix("@define-color") else {
+                continue;
+            };
+            let Some((name, value)) = definition.trim().split_once(char::is_whitespace) else {
+                continue;
+            };
+            let Some(color) = Rgb::parse(value.trim()) else {
+                continue;
+            };
+
+            match name {
+                "eyers_highlight_yellow" => self.yellow = color,
+                "eyers_highlight_green" => self.green = color,
+                "eyers_highlight_red" => self.red = color,
+                "eyers_highlight_blue" => self.blue = color,
+                "eyers_search" => self.search = color,
+                "eyers_selection" => self.selection = color,
+                "eyers_cursor" => self.cursor = color,
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Tint of an annotation highlight
+    pub fn annotation(&self, color: AnnotationColor) -> Rgb {
+        match color {
+            AnnotationColor::Yellow => self.yellow,
+            AnnotationColor::Green => self.green,
+            AnnotationColor::Red => self.red,
+            AnnotationColor::Blue => self.blue,
+        }
+    }
+}
+
+/// The CSS without its `/* ... */` comments
+fn strip_comments(css: &str) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Stylesheet of the user, loaded over the theme: `~/.config/eyers/style.css`
+pub fn user_stylesheet_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|p| p.join("eyers").join("style.css"))
+}
+
+/// The user stylesheet, or None when there is none
+pub fn load_user_stylesheet() -> Option<String> {
+    std::fs::read_to_string(user_stylesheet_path()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_palette_matches_stylesheet() {
+        let palette = HighlightPalette::default();
+        assert_eq!(
+            palette.with_stylesheet(include_str!("../resources/style.css")),
+            palette
+        );
+    }
+
+    #[test]
+    fn test_with_stylesheet() {
+        let css = "
+            /* @define-color eyers_search #000000; */
+            .toast-notification { color: red; }
+            @define-color eyers_search #102030;
+            @define-color eyers_highlight_blue rgb(0, 128, 255);
+            @define-color eyers_cursor @theme_selected_bg_color;
+            @define-color other_color #ffffff;
+        ";
+        let palette = HighlightPalette::default().with_stylesheet(css);
+        assert_eq!(palette.search, Rgb(0x10, 0x20, 0x30));
+        assert_eq!(palette.annotation(AnnotationColor::Blue), Rgb(0, 128, 255));
+        // References to other colors can't be resolved here
+        assert_eq!(palette.cursor, HighlightPalette::default().cursor);
+
+        // Later stylesheets win
+        let palette = palette.with_stylesheet("@define-color eyers_search white;");
+        assert_eq!(palette.search, Rgb(255, 255, 255));
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        assert_eq!(strip_comments("a /* b */ c /* d"), "a  c ");
+        assert_eq!(strip_comments("plain"), "plain");
+    }
+}
@@ -1,14 +1,28 @@
 use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
 
-const LIBRETRANSLATE_URL: &str = "http://localhost:5000/translate";
+use super::lru_cache::{LruCache, SqliteCache};
+
+/// Used when no LibreTranslate URL is set
+pub const LIBRETRANSLATE_URL: &str = "http://localhost:5000/translate";
+const DEEPL_FREE_URL: &str = "https://api-free.deepl.com/v2/translate";
+const DEEPL_PRO_URL: &str = "https://api.deepl.com/v2/translate";
 const SOURCE_LANG: &str = "en";
 const TARGET_LANG: &str = "es";
+/// Translations kept in memory, the least recently used are dropped past it
+const MEMORY_CACHE_SIZE: usize = 128;
+/// Translations kept in the cache on disk, the least recently used are dropped past it
+const TRANSLATION_CACHE_SIZE: usize = 2000;
 
 #[derive(Serialize)]
 struct TranslateRequest<'a> {
     q: &'a str,
     source: &'a str,
     target: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
 }
 
 #[derive(Deserialize)]
@@ -17,10 +31,29 @@ struct TranslateResponse {
     translated_text: String,
 }
 
+#[derive(Serialize)]
+struct DeepLRequest<'a> {
+    text: [&'a str; 1],
+    source_lang: String,
+    target_lang: String,
+}
+
+#[derive(Deserialize)]
+struct DeepLResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Deserialize)]
+struct DeepLTranslation {
+    text: String,
+}
+
 #[derive(Debug)]
 pub enum TranslationError {
     RequestFailed(String),
     ParseFailed(String),
+    /// The backend is missing its URL, API key or command
+    NotConfigured(String),
 }
 
 impl std::fmt::Display for TranslationError {
@@ -28,43 +61,357 @@ impl std::fmt::Display for TranslationError {
         match self {
             TranslationError::RequestFailed(msg) => write!(f, "Request failed: {}", msg),
             TranslationError::ParseFailed(msg) => write!(f, "Parse failed: {}", msg),
+            TranslationError::NotConfigured(msg) => write!(f, "Not configured: {}", msg),
+        }
+    }
+}
+
+/// A translation service
+pub trait Translator {
+    fn translate(&self, text: &str, source: &str, target: &str)
+    -> Result<String, TranslationError>;
+}
+
+/// LibreTranslate server, self-hosted by default
+pub struct LibreTranslate {
+    pub url: String,
+    /// Only needed by servers that require one, like libretranslate.com
+    pub api_key: Option<String>,
+}
+
+impl Translator for LibreTranslate {
+    fn translate(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+    ) -> Result<String, TranslationError> {
+        let request = TranslateRequest {
+            q: text,
+            source,
+            target,
+            api_key: self.api_key.as_deref(),
+        };
+
+        let response = reqwest::blocking::Client::new()
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .map_err(|e| TranslationError::RequestFailed(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(TranslationError::RequestFailed(format!(
+                "Status: {}",
+                response.status()
+            )));
+        }
+
+        let result: TranslateResponse = response
+            .json()
+            .map_err(|e| TranslationError::ParseFailed(e.to_string()))?;
+
+        Ok(result.translated_text)
+    }
+}
+
+/// DeepL API, free or pro depending on the key
+pub struct DeepL {
+    pub api_key: String,
+}
+
+impl Translator for DeepL {
+    fn translate(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+    ) -> Result<String, TranslationError> {
+        let request = DeepLRequest {
+            text: [text],
+            source_lang: source.to_uppercase(),
+            target_lang: target.to_uppercase(),
+        };
+
+        let response = reqwest::blocking::Client::new()
+            .post(deepl_url(&self.api_key))
+            .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key))
+            .json(&request)
+            .send()
+            .map_err(|e| TranslationError::RequestFailed(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(TranslationError::RequestFailed(format!(
+                "Status: {}",
+                response.status()
+            )));
+        }
+
+        let result: DeepLResponse = response
+            .json()
+            .map_err(|e| TranslationError::ParseFailed(e.to_string()))?;
+
+        result
+            .translations
+            .into_iter()
+            .next()
+            .map(|translation| translation.text)
+            .ok_or_else(|| TranslationError::ParseFailed("Empty DeepL response".to_string()))
+    }
+}
+
+/// Keys of the free DeepL plan end in `:fx` and use their own endpoint
+fn deepl_url(api_key: &str) -> &'static str {
+    if api_key.trim().ends_with(":fx") {
+        DEEPL_FREE_URL
+    } else {
+        DEEPL_PRO_URL
+    }
+}
+
+/// A local command reading the text on stdin and writing the translation to
+/// stdout, e.g. `argos-translate --from-lang %{source} --to-lang %{target}`
+pub struct LocalCommand {
+    pub command: String,
+}
+
+impl Translator for LocalCommand {
+    fn translate(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+    ) -> Result<String, TranslationError> {
+        let args = command_args(&self.command, source, target);
+        let Some((program, args)) = args.split_first() else {
+            return Err(TranslationError::NotConfigured(
+                "no translation command set".to_string(),
+            ));
+        };
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| TranslationError::RequestFailed(format!("{}: {}", program, e)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| TranslationError::RequestFailed(e.to_string()))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| TranslationError::RequestFailed(e.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(TranslationError::RequestFailed(format!(
+                "{} exited with {}: {}",
+                program,
+                output.status,
+                stderr.trim()
+            )));
         }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
     }
 }
 
-pub fn translate(text: &str) -> Result<String, TranslationError> {
-    translate_with_langs(text, SOURCE_LANG, TARGET_LANG)
+/// Split the command line on whitespace and fill in `%{source}` and `%{target}`
+fn command_args(command: &str, source: &str, target: &str) -> Vec<String> {
+    command
+        .split_whitespace()
+        .map(|arg| {
+            arg.replace("%{source}", source)
+                .replace("%{target}", target)
+        })
+        .collect()
 }
 
-pub fn translate_with_langs(
-    text: &str,
-    source: &str,
-    target: &str,
-) -> Result<String, TranslationError> {
-    let client = reqwest::blocking::Client::new();
+/// Which translation service is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranslationBackend {
+    #[default]
+    LibreTranslate,
+    DeepL,
+    Command,
+}
 
-    let request = TranslateRequest {
-        q: text,
-        source,
-        target,
-    };
+impl TranslationBackend {
+    pub const ALL: [TranslationBackend; 3] = [
+        TranslationBackend::LibreTranslate,
+        TranslationBackend::DeepL,
+        TranslationBackend::Command,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TranslationBackend::LibreTranslate => "LibreTranslate",
+            TranslationBackend::DeepL => "DeepL",
+            TranslationBackend::Command => "Local command",
+        }
+    }
+}
 
-    let response = client
-        .post(LIBRETRANSLATE_URL)
-        .json(&request)
-        .send()
-        .map_err(|e| TranslationError::RequestFailed(e.to_string()))?;
+/// The selected backend with the settings of every backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslatorConfig {
+    pub backend: TranslationBackend,
+    pub libretranslate_url: String,
+    pub libretranslate_api_key: String,
+    pub deepl_api_key: String,
+    pub command: String,
+    pub source_lang: String,
+    pub target_lang: String,
+}
 
-    if !response.status().is_success() {
-        return Err(TranslationError::RequestFailed(format!(
-            "Status: {}",
-            response.status()
-        )));
+impl Default for TranslatorConfig {
+    fn default() -> Self {
+        Self {
+            backend: TranslationBackend::default(),
+            libretranslate_url: String::new(),
+            libretranslate_api_key: String::new(),
+            deepl_api_key: String::new(),
+            command: String::new(),
+            source_lang: SOURCE_LANG.to_string(),
+            target_lang: TARGET_LANG.to_string(),
+        }
     }
+}
 
-    let result: TranslateResponse = response
-        .json()
-        .map_err(|e| TranslationError::ParseFailed(e.to_string()))?;
+impl TranslatorConfig {
+    /// The translator of the selected backend
+    pub fn translator(&self) -> Result<Box<dyn Translator>, TranslationError> {
+        match self.backend {
+            TranslationBackend::LibreTranslate => {
+                let url = self.libretranslate_url.trim();
+                Ok(Box::new(LibreTranslate {
+                    url: if url.is_empty() {
+                        LIBRETRANSLATE_URL.to_string()
+                    } else {
+                        url.to_string()
+                    },
+                    api_key: Some(self.libretranslate_api_key.trim().to_string())
+                        .filter(|key| !key.is_empty()),
+                }))
+            }
+            TranslationBackend::DeepL => {
+                let api_key = self.deepl_api_key.trim();
+                if api_key.is_empty() {
+                    return Err(TranslationError::NotConfigured(
+                        "set a DeepL API key in the settings".to_string(),
+                    ));
+                }
+                Ok(Box::new(DeepL {
+                    api_key: api_key.to_string(),
+                }))
+            }
+            TranslationBackend::Command => Ok(Box::new(LocalCommand {
+                command: self.command.clone(),
+            })),
+        }
+    }
+}
 
-    Ok(result.translated_text)
+/// Translate with the configured backend and languages. Texts translated
+/// before come from the cache, without asking the backend again.
+pub fn translate(text: &str, config: &TranslatorConfig) -> Result<String, TranslationError> {
+    let translator = config.translator()?;
+    let key = cache_key(text, config);
+    if let Some(translated) = memory_cache().get(&key) {
+        return Ok(translated);
+    }
+    let parts = [
+        text,
+        config.source_lang.as_str(),
+        config.target_lang.as_str(),
+    ];
+    let cache = SqliteCache::open(
+        "translation_cache.db",
+        "translations",
+        TRANSLATION_CACHE_SIZE,
+    );
+    if let Some(translated) = cache.as_ref().and_then(|cache| cache.get(&parts)) {
+        memory_cache().insert(key, translated.clone());
+        return Ok(translated);
+    }
+
+    let translated = translator.translate(text, &config.source_lang, &config.target_lang)?;
+    if let Some(cache) = cache {
+        cache.insert(&parts, &translated);
+    }
+    memory_cache().insert(key, translated.clone());
+    Ok(translated)
+}
+
+/// The translation of a text made recently with the same languages, found
+/// without leaving the main thread
+pub fn cached_translation(text: &str, config: &TranslatorConfig) -> Option<String> {
+    memory_cache().get(&cache_key(text, config))
+}
+
+/// Text, source language and target language
+type CacheKey = (String, String, String);
+
+fn cache_key(text: &str, config: &TranslatorConfig) -> CacheKey {
+    (
+        text.to_string(),
+        config.source_lang.clone(),
+        config.target_lang.clone(),
+    )
+}
+
+fn memory_cache() -> std::sync::MutexGuard<'static, LruCache<CacheKey, String>> {
+    static CACHE: OnceLock<Mutex<LruCache<CacheKey, String>>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| Mutex::new(LruCache::new(MEMORY_CACHE_SIZE)))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deepl_url() {
+        assert_eq!(deepl_url("0123-abcd:fx"), DEEPL_FREE_URL);
+        assert_eq!(deepl_url("0123-abcd"), DEEPL_PRO_URL);
+    }
+
+    #[test]
+    fn test_command_args() {
+        assert_eq!(
+            command_args(
+                "argos-translate --from-lang %{source} --to-lang %{target}",
+                "en",
+                "es"
+            ),
+            vec!["argos-translate", "--from-lang", "en", "--to-lang", "es"]
+        );
+        assert!(command_args("  ", "en", "es").is_empty());
+    }
+
+    #[test]
+    fn test_unconfigured_backends() {
+        let config = TranslatorConfig {
+            backend: TranslationBackend::DeepL,
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.translator(),
+            Err(TranslationError::NotConfigured(_))
+        ));
+
+        let config = TranslatorConfig {
+            backend: TranslationBackend::Command,
+            ..Default::default()
+        };
+        assert!(matches!(
+            translate("hello", &config),
+            Err(TranslationError::NotConfigured(_))
+        ));
+    }
 }
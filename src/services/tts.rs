@@ -0,0 +1,252 @@
+use gtk::gio;
+use gtk::glib;
+use std::ffi::OsStr;
+use std::process::{Command, Stdio};
+
+/// speech-dispatcher's command line client
+const SPEAK_COMMAND: &str = "spd-say";
+
+/// Error type for text-to-speech operations
+#[derive(Debug)]
+pub enum TtsError {
+    /// `spd-say` is missing, speech-dispatcher is not installed
+    NotInstalled,
+    IoError(String),
+}
+
+impl std::fmt::Display for TtsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TtsError::NotInstalled => write!(
+                f,
+                "Text-to-speech needs speech-dispatcher ({} not found)",
+                SPEAK_COMMAND
+            ),
+            TtsError::IoError(msg) => write!(f, "Speech error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TtsError {}
+
+impl From<std::io::Error> for TtsError {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == std::io::ErrorKind::NotFound {
+            TtsError::NotInstalled
+        } else {
+            TtsError::IoError(err.to_string())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TtsState {
+    #[default]
+    Stopped,
+    Speaking,
+    Paused,
+}
+
+/// Reads text aloud through speech-dispatcher one sentence at a time, so it
+/// can be paused and resumed at the sentence being read. `next_sentence` has
+/// to be called once the process of `sentence_process` exits.
+#[derive(Debug, Default)]
+pub struct Speaker {
+    sentences: Vec<String>,
+    /// Sentence being read, or read next when paused
+    current: usize,
+    child: Option<gio::Subprocess>,
+    state: TtsState,
+    /// speech-dispatcher language code, e.g. "en"
+    language: String,
+}
+
+impl Speaker {
+    pub fn state(&self) -> TtsState {
+        self.state
+    }
+
+    /// Start reading `text`, replacing whatever was being read
+    pub fn speak(&mut self, text: &str, language: &str) -> Result<(), TtsError> {
+        self.stop();
+        self.sentences = split_sentences(text);
+        self.current = 0;
+        self.language = language.to_string();
+        if self.sentences.is_empty() {
+            return Ok(());
+        }
+        self.state = TtsState::Speaking;
+        self.speak_current()
+    }
+
+    /// Stop reading, keeping the position to resume from the start of the current sentence
+    pub fn pause(&mut self) {
+        if self.state == TtsState::Speaking {
+            self.cancel_child();
+            self.state = TtsState::Paused;
+        }
+    }
+
+    pub fn resume(&mut self) -> Result<(), TtsError> {
+        if self.state != TtsState::Paused {
+            return Ok(());
+        }
+        self.state = TtsState::Speaking;
+        self.speak_current()
+    }
+
+    pub fn stop(&mut self) {
+        self.cancel_child();
+        self.sentences.clear();
+        self.current = 0;
+        self.state = TtsState::Stopped;
+    }
+
+    /// Process reading the current sentence, exiting once it was spoken
+    pub fn sentence_process(&self) -> Option<gio::Subprocess> {
+        self.child.clone()
+    }
+
+    /// Move on to the next sentence once the current one was read. Returns the
+    /// state afterwards: `Stopped` once the whole text was read.
+    pub fn next_sentence(&mut self) -> Result<TtsState, TtsError> {
+        if self.state != TtsState::Speaking {
+            return Ok(self.state);
+        }
+
+        self.child = None;
+        self.current += 1;
+        if self.current < self.sentences.len() {
+            self.speak_current()?;
+        } else {
+            self.stop();
+        }
+
+        Ok(self.state)
+    }
+
+    fn speak_current(&mut self) -> Result<(), TtsError> {
+        let Some(sentence) = self.sentences.get(self.current) else {
+            self.stop();
+            return Ok(());
+        };
+
+        if glib::find_program_in_path(SPEAK_COMMAND).is_none() {
+            self.stop();
+            return Err(TtsError::NotInstalled);
+        }
+
+        // Waiting until it was spoken tells when to go on with `next_sentence`
+        let command = speak_command(sentence, &self.language, true);
+        let argv: Vec<&OsStr> = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .collect();
+        let flags = gio::SubprocessFlags::STDOUT_SILENCE | gio::SubprocessFlags::STDERR_SILENCE;
+
+        match gio::Subprocess::newv(&argv, flags) {
+            Ok(child) => {
+                self.child = Some(child);
+                Ok(())
+            }
+            Err(e) => {
+                self.stop();
+                Err(TtsError::IoError(e.to_string()))
+            }
+        }
+    }
+
+    /// Kill the running `spd-say` and drop what speech-dispatcher is still reading
+    fn cancel_child(&mut self) {
+        if let Some(child) = self.child.take() {
+            child.force_exit();
+            let _ = Command::new(SPEAK_COMMAND)
+                .arg("--cancel")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+}
+
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        self.cancel_child();
+    }
+}
+
+/// `spd-say` saying `text` in `language`, e.g. "en" (the default voice when empty),
+/// exiting only once it was spoken with `wait`
+fn speak_command(text: &str, language: &str, wait: bool) -> Command {
+    let mut command = Command::new(SPEAK_COMMAND);
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+    if wait {
+        command.arg("--wait");
+    }
+    if !language.is_empty() {
+        command.args(["--language", language]);
+    }
+    // `--` so text starting with a dash is not taken as options
+    command.arg("--").arg(text);
+    command
+}
+
+/// Say a word or short selection once, to check its pronunciation. Returns as
+/// soon as speech-dispatcher has queued it.
+pub fn say(text: &str, language: &str) -> Result<(), TtsError> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return Ok(());
+    }
+    speak_command(&text, language, false).status()?;
+    Ok(())
+}
+
+/// Split text into sentences at `.`, `!` and `?` followed by whitespace, with
+/// line breaks joined into spaces
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut sentences = Vec::new();
+    let mut start = 0;
+
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let at_break = matches!(c, '.' | '!' | '?')
+            && chars.peek().is_some_and(|(_, next)| next.is_whitespace());
+        if at_break {
+            let end = index + c.len_utf8();
+            sentences.push(text[start..end].trim().to_string());
+            start = end;
+        }
+    }
+    sentences.push(text[start..].trim().to_string());
+
+    sentences.retain(|sentence| !sentence.is_empty());
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split_sentences("First one. Second\nline! Third? Done"),
+            vec!["First one.", "Second line!", "Third?", "Done"]
+        );
+        assert_eq!(
+            split_sentences("Version 1.5 is out."),
+            vec!["Version 1.5 is out."]
+        );
+        assert!(split_sentences("  \n ").is_empty());
+    }
+
+    #[test]
+    fn test_empty_text_does_not_speak() {
+        let mut speaker = Speaker::default();
+        speaker.speak(" ", "en").unwrap();
+        assert_eq!(speaker.state(), TtsState::Stopped);
+        assert!(speaker.sentence_process().is_none());
+        assert_eq!(speaker.next_sentence().unwrap(), TtsState::Stopped);
+    }
+}
@@ -0,0 +1,121 @@
+use gtk::glib;
+
+/// Longest query sent, in characters; longer selections are cut at a word
+const MAX_QUERY_CHARS: usize = 200;
+
+/// Search engines offered in the settings. Each is a URL template where `%s`
+/// stands for the searched text and `%l` for the dictionary language code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchEngine {
+    DuckDuckGo,
+    Google,
+    Wikipedia,
+    Wiktionary,
+}
+
+impl SearchEngine {
+    pub const ALL: [SearchEngine; 4] = [
+        SearchEngine::DuckDuckGo,
+        SearchEngine::Google,
+        SearchEngine::Wikipedia,
+        SearchEngine::Wiktionary,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchEngine::DuckDuckGo => "DuckDuckGo",
+            SearchEngine::Google => "Google",
+            SearchEngine::Wikipedia => "Wikipedia",
+            SearchEngine::Wiktionary => "Wiktionary",
+        }
+    }
+
+    pub fn template(self) -> &'static str {
+        match self {
+            SearchEngine::DuckDuckGo => "https://duckduckgo.com/?q=%s",
+            SearchEngine::Google => "https://www.google.com/search?q=%s",
+            SearchEngine::Wikipedia => "https://%l.wikipedia.org/w/index.php?search=%s",
+            SearchEngine::Wiktionary => "https://%l.wiktionary.org/w/index.php?search=%s",
+        }
+    }
+
+    /// The engine a template belongs to, None for a custom one
+    pub fn from_template(template: &str) -> Option<SearchEngine> {
+        Self::ALL
+            .into_iter()
+            .find(|engine| engine.template() == template)
+    }
+}
+
+/// The selected text as a query: whitespace and line breaks collapsed, hyphens
+/// at line ends joined, and cut to a reasonable length
+pub fn search_query(text: &str) -> String {
+    let mut query = String::new();
+    for word in text.split_whitespace() {
+        if query.ends_with('-') {
+            query.pop();
+        } else if !query.is_empty() {
+            query.push(' ');
+        }
+        if query.chars().count() + word.chars().count() > MAX_QUERY_CHARS && !query.is_empty() {
+            break;
+        }
+        query.push_str(word);
+    }
+    query.trim_end_matches(' ').to_string()
+}
+
+/// The URL searching `query` with `template`. Queries are percent-encoded;
+/// templates without `%s` get the query appended.
+pub fn search_url(template: &str, query: &str, language_code: &str) -> String {
+    let escaped = glib::Uri::escape_string(query, None, false).to_string();
+    let template = template.trim().replace("%l", language_code);
+    if template.contains("%s") {
+        template.replace("%s", &escaped)
+    } else {
+        template + &escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_query() {
+        assert_eq!(search_query("  the  quick\nbrown "), "the quick brown");
+        assert_eq!(
+            search_query("photo-\nsynthesis rate"),
+            "photosynthesis rate"
+        );
+        assert_eq!(search_query(&"word ".repeat(100)).len(), 199);
+    }
+
+    #[test]
+    fn test_search_url() {
+        assert_eq!(
+            search_url(SearchEngine::DuckDuckGo.template(), "café au lait", "fr"),
+            "https://duckduckgo.com/?q=caf%C3%A9%20au%20lait"
+        );
+        assert_eq!(
+            search_url(SearchEngine::Wikipedia.template(), "Ada Lovelace", "en"),
+            "https://en.wikipedia.org/w/index.php?search=Ada%20Lovelace"
+        );
+        assert_eq!(
+            search_url("https://example.org/find?term=", "a&b", "en"),
+            "https://example.org/find?term=a%26b"
+        );
+    }
+
+    #[test]
+    fn test_from_template() {
+        assert_eq!(
+            SearchEngine::from_template("https://www.google.com/search?q=%s"),
+            Some(SearchEngine::Google)
+        );
+        assert_eq!(
+            SearchEngine::from_template("https://example.org/?q=%s"),
+            None
+        );
+    }
+}
@@ -0,0 +1,101 @@
+/// How line breaks in a multi-line selection are handled when it is copied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyJoinStyle {
+    /// Keep the original line breaks of the PDF
+    #[default]
+    Preserve,
+    /// Join all lines into a single paragraph
+    Unwrap,
+    /// Join all lines and merge words hyphenated across line breaks
+    Dehyphenate,
+}
+
+impl CopyJoinStyle {
+    /// All styles, in the order they are shown in the settings
+    pub const ALL: [CopyJoinStyle; 3] = [
+        CopyJoinStyle::Preserve,
+        CopyJoinStyle::Unwrap,
+        CopyJoinStyle::Dehyphenate,
+    ];
+
+    /// Human readable name for the settings dropdown
+    pub fn label(&self) -> &'static str {
+        match self {
+            CopyJoinStyle::Preserve => "Keep line breaks",
+            CopyJoinStyle::Unwrap => "Join into paragraph",
+            CopyJoinStyle::Dehyphenate => "Join and remove hyphenation",
+        }
+    }
+
+    /// Apply this style to text reconstructed from the text map
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            CopyJoinStyle::Preserve => text.to_string(),
+            CopyJoinStyle::Unwrap => join_lines(text, false),
+            CopyJoinStyle::Dehyphenate => join_lines(text, true),
+        }
+    }
+}
+
+/// Characters PDFs use to break a word at the end of a line
+fn is_hyphen(c: char) -> bool {
+    matches!(c, '-' | '\u{00AD}' | '\u{2010}')
+}
+
+/// Join lines with a single space, optionally merging "hyph-\nenated" words
+fn join_lines(text: &str, dehyphenate: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if result.is_empty() {
+            result.push_str(line);
+            continue;
+        }
+
+        let hyphenated = dehyphenate
+            && line.starts_with(|c: char| c.is_lowercase())
+            && result.ends_with(is_hyphen)
+            && result
+                .chars()
+                .rev()
+                .nth(1)
+                .is_some_and(|c| c.is_alphabetic());
+
+        if hyphenated {
+            result.pop();
+        } else {
+            result.push(' ');
+        }
+        result.push_str(line);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_styles() {
+        let text = "The quick brown fox jum-\nped over the\nlazy dog.";
+
+        assert_eq!(CopyJoinStyle::Preserve.apply(text), text);
+        assert_eq!(
+            CopyJoinStyle::Unwrap.apply(text),
+            "The quick brown fox jum- ped over the lazy dog."
+        );
+        assert_eq!(
+            CopyJoinStyle::Dehyphenate.apply(text),
+            "The quick brown fox jumped over the lazy dog."
+        );
+    }
+
+    #[test]
+    fn test_dehyphenate_keeps_real_dashes() {
+        assert_eq!(
+            CopyJoinStyle::Dehyphenate.apply("well-\nKnown and -\nthen"),
+            "well- Known and - then"
+        );
+    }
+}
@@ -1,7 +1,14 @@
+pub mod join_style;
 pub mod navigation;
 pub mod page_text_map;
+pub mod reflow;
 pub mod text_map_cache;
+pub mod word_grid;
 pub mod word_info;
 
-pub use navigation::{find_word_on_line_starting_with, navigate, NavDirection};
+pub use join_style::CopyJoinStyle;
+pub use navigation::{
+    find_word_on_line_starting_with, navigate, text_object_range, NavDirection, TextObject,
+};
+pub use reflow::ReflowText;
 pub use text_map_cache::TextMapCache;
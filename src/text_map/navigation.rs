@@ -1,15 +1,38 @@
+use crate::backend::DocumentBackend;
 use crate::text_map::page_text_map::PageTextMap;
 use crate::text_map::text_map_cache::TextMapCache;
 
-use pdfium_render::prelude::PdfDocument;
-
 /// Direction for cursor navigation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NavDirection {
-    Left,  // Previous word in reading order (h)
-    Right, // Next word in reading order (l)
-    Up,    // Closest word on line above (k)
-    Down,  // Closest word on line below (j)
+    Left,              // Previous word in reading order (h)
+    Right,             // Next word in reading order (l)
+    Up,                // Closest word on line above (k)
+    Down,              // Closest word on line below (j)
+    NextWord,          // Next word, across lines and pages with text (w)
+    PreviousWord,      // Previous word, across lines and pages with text (b)
+    WordEnd,           // End of the next word, joining words hyphenated across lines (e)
+    LineStart,         // First word of the line (0)
+    LineEnd,           // Last word of the line ($)
+    SentenceForward,   // Start of the next sentence, key )
+    SentenceBackward,  // Start of the sentence, or of the previous one at its start, key (
+    ParagraphForward,  // Start of the next paragraph, key }
+    ParagraphBackward, // Start of the paragraph, or of the previous one at its start, key {
+}
+
+/// Units selected by the `iw`, `is` and `ip` text objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObject {
+    Word,
+    Sentence,
+    Paragraph,
+}
+
+/// Text units the sentence and paragraph motions move between
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextUnit {
+    Sentence,
+    Paragraph,
 }
 
 /// Result of a navigation operation
@@ -24,7 +47,7 @@ pub struct NavResult {
 /// Returns the new position, or None if navigation is not possible
 pub fn navigate(
     cache: &mut TextMapCache,
-    document: &PdfDocument,
+    document: &dyn DocumentBackend,
     current_page: usize,
     current_word: usize,
     direction: NavDirection,
@@ -62,13 +85,263 @@ pub fn navigate(
             current_x,
             line_count,
         ),
+        NavDirection::NextWord => navigate_next_word(cache, document, current_page, current_word),
+        NavDirection::PreviousWord => {
+            navigate_previous_word(cache, document, current_page, current_word)
+        }
+        NavDirection::WordEnd => navigate_word_end(cache, document, current_page, current_word),
+        NavDirection::SentenceForward => navigate_to_unit_start(
+            cache,
+            document,
+            current_page,
+            current_word,
+            TextUnit::Sentence,
+            true,
+        ),
+        NavDirection::SentenceBackward => navigate_to_unit_start(
+            cache,
+            document,
+            current_page,
+            current_word,
+            TextUnit::Sentence,
+            false,
+        ),
+        NavDirection::ParagraphForward => navigate_to_unit_start(
+            cache,
+            document,
+            current_page,
+            current_word,
+            TextUnit::Paragraph,
+            true,
+        ),
+        NavDirection::ParagraphBackward => navigate_to_unit_start(
+            cache,
+            document,
+            current_page,
+            current_word,
+            TextUnit::Paragraph,
+            false,
+        ),
+        NavDirection::LineStart | NavDirection::LineEnd => {
+            let text_map = cache.get_or_build(current_page, document)?;
+            let word_range = text_map.word_indices_on_line(current_line);
+            let word_index = if direction == NavDirection::LineStart {
+                word_range.start
+            } else {
+                word_range.end.checked_sub(1)?
+            };
+            Some(NavResult {
+                page_index: current_page,
+                line_index: current_line,
+                word_index,
+            })
+        }
+    }
+}
+
+/// Navigate to the next word, skipping pages without text
+fn navigate_next_word(
+    cache: &mut TextMapCache,
+    document: &dyn DocumentBackend,
+    current_page: usize,
+    current_word: usize,
+) -> Option<NavResult> {
+    let (page_index, word_index) =
+        if current_word + 1 < cache.get_or_build(current_page, document)?.word_count() {
+            (current_page, current_word + 1)
+        } else {
+            (cache.next_page_with_text(current_page, true, document)?, 0)
+        };
+
+    let word = cache
+        .get_or_build(page_index, document)?
+        .get_word(word_index)?;
+    Some(NavResult {
+        page_index,
+        line_index: word.line_index,
+        word_index,
+    })
+}
+
+/// Navigate to the previous word, skipping pages without text
+fn navigate_previous_word(
+    cache: &mut TextMapCache,
+    document: &dyn DocumentBackend,
+    current_page: usize,
+    current_word: usize,
+) -> Option<NavResult> {
+    let (page_index, word_index) = if current_word > 0 {
+        (current_page, current_word - 1)
+    } else {
+        let page_index = cache.next_page_with_text(current_page, false, document)?;
+        let word_count = cache.get_or_build(page_index, document)?.word_count();
+        (page_index, word_count.checked_sub(1)?)
+    };
+
+    let word = cache
+        .get_or_build(page_index, document)?
+        .get_word(word_index)?;
+    Some(NavResult {
+        page_index,
+        line_index: word.line_index,
+        word_index,
+    })
+}
+
+/// First and last word of the word, sentence or paragraph around a word. A word
+/// hyphenated across lines spans both of its parts.
+pub fn text_object_range(
+    cache: &mut TextMapCache,
+    document: &dyn DocumentBackend,
+    page_index: usize,
+    word_index: usize,
+    object: TextObject,
+) -> Option<(NavResult, NavResult)> {
+    let line_index = cache
+        .get_or_build(page_index, document)?
+        .get_word(word_index)?
+        .line_index;
+    let current = NavResult {
+        page_index,
+        line_index,
+        word_index,
+    };
+
+    let unit = match object {
+        TextObject::Word => {
+            let start = navigate_previous_word(cache, document, page_index, word_index)
+                .filter(|previous| hyphenated_into(cache, document, *previous, current))
+                .unwrap_or(current);
+            let end = navigate_next_word(cache, document, page_index, word_index)
+                .filter(|next| hyphenated_into(cache, document, current, *next))
+                .unwrap_or(current);
+            return Some((start, end));
+        }
+        TextObject::Sentence => TextUnit::Sentence,
+        TextObject::Paragraph => TextUnit::Paragraph,
+    };
+
+    let start = if is_unit_start(cache, document, current, unit) {
+        current
+    } else {
+        navigate_to_unit_start(cache, document, page_index, word_index, unit, false)?
+    };
+    // The word before the next start, unless the document ends first
+    let end = match navigate_to_unit_start(cache, document, page_index, word_index, unit, true) {
+        Some(next) if is_unit_start(cache, document, next, unit) => {
+            navigate_previous_word(cache, document, next.page_index, next.word_index)?
+        }
+        Some(last) => last,
+        None => current,
+    };
+    Some((start, end))
+}
+
+/// Whether `word` ends with a hyphen at the end of its line and continues as
+/// `next` on the following line or page
+fn hyphenated_into(
+    cache: &mut TextMapCache,
+    document: &dyn DocumentBackend,
+    word: NavResult,
+    next: NavResult,
+) -> bool {
+    let on_next_line = next.page_index != word.page_index || next.line_index != word.line_index;
+    on_next_line
+        && cache
+            .get_or_build(word.page_index, document)
+            .and_then(|text_map| text_map.get_word(word.word_index))
+            .is_some_and(|word| word.text.chars().count() > 1 && word.text.ends_with('-'))
+}
+
+/// Navigate to the next start of a sentence or paragraph, or back to the start
+/// of the current one (the previous one when already there). Stops at the first
+/// or last word of the document.
+fn navigate_to_unit_start(
+    cache: &mut TextMapCache,
+    document: &dyn DocumentBackend,
+    current_page: usize,
+    current_word: usize,
+    unit: TextUnit,
+    forward: bool,
+) -> Option<NavResult> {
+    let step = |cache: &mut TextMapCache, pos: NavResult| {
+        if forward {
+            navigate_next_word(cache, document, pos.page_index, pos.word_index)
+        } else {
+            navigate_previous_word(cache, document, pos.page_index, pos.word_index)
+        }
+    };
+
+    let mut pos = if forward {
+        navigate_next_word(cache, document, current_page, current_word)?
+    } else {
+        navigate_previous_word(cache, document, current_page, current_word)?
+    };
+    while !is_unit_start(cache, document, pos, unit) {
+        match step(cache, pos) {
+            Some(next) => pos = next,
+            None => break,
+        }
     }
+    Some(pos)
+}
+
+/// Whether the word at `pos` starts a sentence or paragraph. Paragraphs also
+/// start sentences, so headings without a full stop are kept apart.
+fn is_unit_start(
+    cache: &mut TextMapCache,
+    document: &dyn DocumentBackend,
+    pos: NavResult,
+    unit: TextUnit,
+) -> bool {
+    let Some(previous) = navigate_previous_word(cache, document, pos.page_index, pos.word_index)
+    else {
+        return true;
+    };
+    let previous_ends_sentence = cache
+        .get_or_build(previous.page_index, document)
+        .and_then(|text_map| text_map.get_word(previous.word_index))
+        .is_some_and(|word| word.ends_sentence());
+    if unit == TextUnit::Sentence && previous_ends_sentence {
+        return true;
+    }
+
+    let Some(text_map) = cache.get_or_build(pos.page_index, document) else {
+        return false;
+    };
+    if text_map.word_indices_on_line(pos.line_index).start != pos.word_index {
+        return false;
+    }
+    if previous.page_index != pos.page_index {
+        // Gaps can't be compared across pages: a finished sentence at the bottom
+        // of the previous page ends its paragraph
+        return previous_ends_sentence;
+    }
+    text_map.is_paragraph_start(pos.line_index)
+}
+
+/// Navigate to the end of the next word. A word hyphenated at the end of a line
+/// (or page) ends on the next one, so its first part is skipped over.
+fn navigate_word_end(
+    cache: &mut TextMapCache,
+    document: &dyn DocumentBackend,
+    current_page: usize,
+    current_word: usize,
+) -> Option<NavResult> {
+    let mut result = navigate_next_word(cache, document, current_page, current_word)?;
+
+    while let Some(next) = navigate_next_word(cache, document, result.page_index, result.word_index)
+        .filter(|next| hyphenated_into(cache, document, result, *next))
+    {
+        result = next;
+    }
+    Some(result)
 }
 
 /// Navigate to previous word in reading order
 fn navigate_left(
     cache: &mut TextMapCache,
-    document: &PdfDocument,
+    document: &dyn DocumentBackend,
     current_page: usize,
     _current_line: usize,
     current_word: usize,
@@ -102,7 +375,7 @@ fn navigate_left(
 /// Navigate to next word in reading order
 fn navigate_right(
     cache: &mut TextMapCache,
-    document: &PdfDocument,
+    document: &dyn DocumentBackend,
     current_page: usize,
     _current_line: usize,
     current_word: usize,
@@ -137,7 +410,7 @@ fn navigate_right(
 /// Navigate to closest word on line above
 fn navigate_up(
     cache: &mut TextMapCache,
-    document: &PdfDocument,
+    document: &dyn DocumentBackend,
     current_page: usize,
     current_line: usize,
     current_x: f64,
@@ -174,7 +447,7 @@ fn navigate_up(
 /// Navigate to closest word on line below
 fn navigate_down(
     cache: &mut TextMapCache,
-    document: &PdfDocument,
+    document: &dyn DocumentBackend,
     current_page: usize,
     current_line: usize,
     current_x: f64,
@@ -237,58 +510,91 @@ fn find_closest_word_on_line(
 }
 
 /// Find a word on the same line that starts with the given character (case-insensitive)
-/// Searches forward or backward from current word position
-/// Returns None if no matching word found on the same line
+/// Searches forward or backward from current word position. At the last line of a
+/// page (first line when searching backward) the search continues on the first
+/// (last) line of the next (previous) page with text, like j/k cross pages.
+/// Returns None if no matching word is found
 pub fn find_word_on_line_starting_with(
     cache: &mut TextMapCache,
-    document: &PdfDocument,
+    document: &dyn DocumentBackend,
     page_index: usize,
     current_word: usize,
     target_char: char,
     forward: bool,
 ) -> Option<NavResult> {
-    let text_map = cache.get_or_build(page_index, document)?;
-    let current_word_info = text_map.get_word(current_word)?;
-    let line_index = current_word_info.line_index;
+    let target_lower = target_char.to_lowercase().next()?;
 
-    let word_range = text_map.word_indices_on_line(line_index);
-    if word_range.is_empty() {
+    let at_page_edge = {
+        let text_map = cache.get_or_build(page_index, document)?;
+        let line_index = text_map.get_word(current_word)?.line_index;
+
+        let word_range = text_map.word_indices_on_line(line_index);
+        let candidates = if forward {
+            (current_word + 1).max(word_range.start)..word_range.end
+        } else {
+            word_range.start..current_word.min(word_range.end)
+        };
+        if let Some(word_index) =
+            find_word_starting_with(text_map, candidates, target_lower, forward)
+        {
+            return Some(NavResult {
+                page_index,
+                line_index,
+                word_index,
+            });
+        }
+
+        if forward {
+            line_index + 1 >= text_map.line_count()
+        } else {
+            line_index == 0
+        }
+    };
+
+    if !at_page_edge {
         return None;
     }
 
-    let target_lower = target_char.to_lowercase().next()?;
+    // Continue on the adjoining line of the next (previous) page with text
+    let next_page = cache.next_page_with_text(page_index, forward, document)?;
+    let text_map = cache.get_or_build(next_page, document)?;
+    let next_line = if forward {
+        0
+    } else {
+        text_map.line_count().checked_sub(1)?
+    };
+    let word_index = find_word_starting_with(
+        text_map,
+        text_map.word_indices_on_line(next_line),
+        target_lower,
+        forward,
+    )?;
+
+    Some(NavResult {
+        page_index: next_page,
+        line_index: next_line,
+        word_index,
+    })
+}
+
+/// First word in `candidates` (last when searching backward) whose first character
+/// lowercases to `target_lower`
+fn find_word_starting_with(
+    text_map: &PageTextMap,
+    candidates: std::ops::Range<usize>,
+    target_lower: char,
+    forward: bool,
+) -> Option<usize> {
+    let starts_with_target = |word_idx: &usize| {
+        text_map
+            .get_word(*word_idx)
+            .and_then(|word| word.text.chars().next())
+            .is_some_and(|first_char| first_char.to_lowercase().next() == Some(target_lower))
+    };
 
     if forward {
-        // Search forward from current_word + 1 to end of line
-        for word_idx in (current_word + 1)..word_range.end {
-            if let Some(word) = text_map.get_word(word_idx) {
-                if let Some(first_char) = word.text.chars().next() {
-                    if first_char.to_lowercase().next() == Some(target_lower) {
-                        return Some(NavResult {
-                            page_index,
-                            line_index,
-                            word_index: word_idx,
-                        });
-                    }
-                }
-            }
-        }
+        candidates.into_iter().find(starts_with_target)
     } else {
-        // Search backward from current_word - 1 to start of line
-        for word_idx in (word_range.start..current_word).rev() {
-            if let Some(word) = text_map.get_word(word_idx) {
-                if let Some(first_char) = word.text.chars().next() {
-                    if first_char.to_lowercase().next() == Some(target_lower) {
-                        return Some(NavResult {
-                            page_index,
-                            line_index,
-                            word_index: word_idx,
-                        });
-                    }
-                }
-            }
-        }
+        candidates.into_iter().rev().find(starts_with_target)
     }
-
-    None
 }
@@ -1,9 +1,14 @@
-use pdfium_render::prelude::*;
+use pdfium_render::prelude::PdfRect;
 
+use crate::backend::PageChar;
+use crate::services::pdf_text::normalize_text;
+use crate::text_map::word_grid::WordGrid;
 use crate::text_map::word_info::{LineInfo, WordInfo};
 
 /// Threshold for considering characters on the same line (as percentage of avg char height)
 const LINE_GROUPING_THRESHOLD: f64 = 0.5;
+/// A gap this many times the usual line spacing above a line starts a paragraph
+const PARAGRAPH_GAP_FACTOR: f64 = 1.5;
 
 /// Represents all text data for a single PDF page, organized for efficient navigation
 #[derive(Debug)]
@@ -17,95 +22,84 @@ pub struct PageTextMap {
     /// Page dimensions in PDF points
     pub page_width: f64,
     pub page_height: f64,
+    /// Where the words are on the page, for finding them by position
+    grid: WordGrid,
 }
 
 impl PageTextMap {
-    /// Build a PageTextMap by extracting all words from a PDF page
-    pub fn build_from_page(page: &PdfPage, page_index: usize) -> Option<Self> {
-        let text_page = page.text().ok()?;
-        let page_width = page.width().value as f64;
-        let page_height = page.height().value as f64;
-
-        // Extract all characters with their bounds
-        let chars = text_page.chars();
-        let mut char_data: Vec<CharData> = Vec::new();
-
-        let boundaries = page.boundaries();
-        let crop_box = boundaries
-            .crop()
-            .map(|b| b.bounds)
-            .or_else(|_| boundaries.media().map(|b| b.bounds))
-            .or_else(|_| boundaries.trim().map(|b| b.bounds))
-            .or_else(|_| boundaries.bleed().map(|b| b.bounds))
-            .or_else(|_| boundaries.art().map(|b| b.bounds))
-            .unwrap_or_else(|_| {
-                pdfium_render::prelude::PdfRect::new(
-                    PdfPoints::new(0.0),
-                    PdfPoints::new(0.0),
-                    PdfPoints::new(0.0),
-                    PdfPoints::new(0.0),
-                )
-            });
-
-        for char_obj in chars.iter() {
-            if let (Some(unicode), Ok(bounds)) = (char_obj.unicode_char(), char_obj.tight_bounds())
-            {
-                // Fix bounds if any
-                let bounds = PdfRect::new(
-                    PdfPoints::new(bounds.bottom().value - crop_box.bottom().value),
-                    PdfPoints::new(bounds.left().value - crop_box.left().value),
-                    PdfPoints::new(bounds.top().value - crop_box.bottom().value),
-                    PdfPoints::new(bounds.right().value - crop_box.left().value),
-                );
-
-                char_data.push(CharData {
-                    char: unicode,
-                    index: char_obj.index() as usize,
+    /// Build a PageTextMap from the characters a document backend extracted for a page
+    pub fn build_from_chars(
+        chars: Vec<PageChar>,
+        page_index: usize,
+        page_width: f64,
+        page_height: f64,
+    ) -> Self {
+        let mut char_data: Vec<CharData> = chars
+            .into_iter()
+            .filter_map(|c| match c.bounds {
+                Some(bounds) => Some(CharData {
+                    char: c.char,
+                    index: c.index,
                     bounds,
-                });
-            }
-        }
+                }),
+                // Generated spaces and line breaks usually have no bounds, but they
+                // still tell us where words are separated
+                None if c.char.is_whitespace() => Some(CharData {
+                    char: c.char,
+                    index: c.index,
+                    bounds: PdfRect::new_from_values(0.0, 0.0, 0.0, 0.0),
+                }),
+                None => None,
+            })
+            .collect();
 
         if char_data.is_empty() {
-            return Some(Self {
+            return Self {
                 page_index,
                 words: Vec::new(),
                 lines: Vec::new(),
                 page_width,
                 page_height,
-            });
+                grid: WordGrid::default(),
+            };
         }
 
         // Group characters into words
         let mut words = Self::extract_words(&mut char_data);
 
         if words.is_empty() {
-            return Some(Self {
+            return Self {
                 page_index,
                 words: Vec::new(),
                 lines: Vec::new(),
                 page_width,
                 page_height,
-            });
+                grid: WordGrid::default(),
+            };
         }
 
         // Group words into lines and assign line indices
         let lines = Self::group_into_lines(&mut words);
+        let grid = WordGrid::build(&words, page_width, page_height);
 
-        Some(Self {
+        Self {
             page_index,
             words,
             lines,
             page_width,
             page_height,
-        })
+            grid,
+        }
     }
 
     fn extract_words(char_data: &mut [CharData]) -> Vec<WordInfo> {
         let mut words: Vec<WordInfo> = Vec::new();
         let mut current_word_chars: Vec<&CharData> = Vec::new();
         let mut tilde = false;
-        let mut surround_chars: Vec<char> = Vec::new();
+        // Non-word characters seen since the last word ended
+        let mut gap_chars: Vec<char> = Vec::new();
+        // The gap that preceded each word in `words`
+        let mut gaps_before: Vec<String> = Vec::new();
 
         for char_info in char_data {
             // This is for fixing weirldy formatted spanish pdfs
@@ -129,32 +123,72 @@ impl PageTextMap {
                     current_word_chars.push(char_info);
                 } else {
                     if !current_word_chars.is_empty() {
-                        if let Some(word) =
-                            Self::build_word_from_chars(&current_word_chars, &surround_chars)
-                        {
+                        if let Some(word) = Self::build_word_from_chars(&current_word_chars) {
+                            gaps_before.push(gap_chars.iter().collect());
                             words.push(word);
                         }
                         current_word_chars.clear();
-                        surround_chars.clear();
+                        gap_chars.clear();
                     }
-                    surround_chars.push(char_info.char);
+                    gap_chars.push(char_info.char);
                 }
             }
         }
-        surround_chars.clear();
 
         // Don't forget the last word
         if !current_word_chars.is_empty() {
-            if let Some(word) = Self::build_word_from_chars(&current_word_chars, &surround_chars) {
+            if let Some(word) = Self::build_word_from_chars(&current_word_chars) {
+                gaps_before.push(gap_chars.iter().collect());
                 words.push(word);
             }
+            gap_chars.clear();
         }
 
+        let final_gap: String = gap_chars.iter().collect();
+        Self::attach_gaps(&mut words, &gaps_before, &final_gap);
+
         words
     }
 
+    /// Distribute the characters found between words into each word's `trailing` and
+    /// `leading` text. `gaps_before[i]` holds what was extracted right before `words[i]`.
+    fn attach_gaps(words: &mut [WordInfo], gaps_before: &[String], final_gap: &str) {
+        for (idx, gap) in gaps_before.iter().enumerate() {
+            let gap = normalize_text(gap);
+            let leading = if idx == 0 {
+                // Nothing precedes the first word, so its whole gap is glued to it
+                gap
+            } else {
+                let (trailing, leading) = Self::split_gap(&gap);
+                words[idx - 1].trailing = trailing;
+                leading
+            };
+            if let Some(word) = words.get_mut(idx) {
+                word.leading = leading;
+            }
+        }
+
+        if let Some(last) = words.last_mut() {
+            last.trailing = normalize_text(final_gap);
+        }
+    }
+
+    /// Split a gap between two words into the part that belongs to the previous word
+    /// (closing punctuation and whitespace, e.g. ", ") and the part glued to the next
+    /// one (opening punctuation, e.g. an opening quote). Without whitespace the whole
+    /// gap stays with the previous word ("word—word").
+    fn split_gap(gap: &str) -> (String, String) {
+        match gap.char_indices().rev().find(|(_, c)| c.is_whitespace()) {
+            Some((pos, ws)) => {
+                let split_at = pos + ws.len_utf8();
+                (gap[..split_at].to_string(), gap[split_at..].to_string())
+            }
+            None => (gap.to_string(), String::new()),
+        }
+    }
+
     /// Build a WordInfo from a sequence of characters
-    fn build_word_from_chars(chars: &[&CharData], surround_chars: &Vec<char>) -> Option<WordInfo> {
+    fn build_word_from_chars(chars: &[&CharData]) -> Option<WordInfo> {
         if chars.is_empty() {
             return None;
         }
@@ -179,14 +213,7 @@ impl PageTextMap {
         let bounds = PdfRect::new_from_values(min_bottom, min_left, max_top, max_right);
 
         // line_index will be set later during line grouping
-        Some(WordInfo::new(
-            text,
-            char_start,
-            char_end,
-            bounds,
-            0,
-            Some(surround_chars.iter().collect()),
-        ))
+        Some(WordInfo::new(text, char_start, char_end, bounds, 0))
     }
 
     /// Group words into lines based on y-coordinate proximity and reorder into reading order.
@@ -332,7 +359,7 @@ impl PageTextMap {
 
     /// Check if a character should be part of a word
     fn is_word_char(c: char) -> bool {
-        (!c.is_whitespace() && c.is_alphanumeric()) || c == '\'' || c == '-'
+        (!c.is_whitespace() && c.is_alphanumeric()) || matches!(c, '\'' | '\u{2019}' | '-')
     }
 
     /// Get the word at a specific index
@@ -365,21 +392,103 @@ impl PageTextMap {
 
     /// Find the first word whose bounds intersect with the given rect
     /// Used for finding first visible word in viewport
-    pub fn first_word_in_rect(&self, rect_top: f64, rect_bottom: f64) -> Option<usize> {
+    pub fn first_word_in_rect(
+        &self,
+        rect_left: f64,
+        rect_top: f64,
+        rect_right: f64,
+        rect_bottom: f64,
+    ) -> Option<usize> {
         // In PDF coords, top > bottom
         // We want the first word (in reading order) that overlaps with the viewport
         for (idx, word) in self.words.iter().enumerate() {
             let word_top = word.bounds.top().value as f64;
             let word_bottom = word.bounds.bottom().value as f64;
-
-            // Check if word overlaps with rect vertically
-            if word_top >= rect_bottom && word_bottom <= rect_top {
+            let word_left = word.bounds.left().value as f64;
+            let word_right = word.bounds.right().value as f64;
+
+            // Check if word overlaps with rect, vertically and horizontally (the
+            // viewport is a vertical strip of the page when it is turned sideways)
+            if word_top >= rect_bottom
+                && word_bottom <= rect_top
+                && word_right >= rect_left
+                && word_left <= rect_right
+            {
                 return Some(idx);
             }
         }
         None
     }
 
+    /// Find the word closest to a point in PDF coordinates (0 inside its bounds)
+    pub fn nearest_word(&self, x: f64, y: f64) -> Option<usize> {
+        self.grid.nearest_word(&self.words, x, y)
+    }
+
+    /// Find the word under a point in PDF coordinates, or less than `tolerance`
+    /// points away from it
+    pub fn word_at(&self, x: f64, y: f64, tolerance: f64) -> Option<usize> {
+        self.grid.word_at(&self.words, x, y, tolerance)
+    }
+
+    /// Find case-insensitive occurrences of `query`, as inclusive (first word, last
+    /// word) index pairs. A single-word query matches inside words; with several
+    /// words the first may end and the last may begin a word, while the ones in
+    /// between must match whole words. Punctuation in the query is ignored.
+    pub fn find_matches(&self, query: &str) -> Vec<(usize, usize)> {
+        let terms: Vec<String> = normalize_text(query)
+            .split_whitespace()
+            .map(|term| {
+                term.trim_matches(|c: char| !Self::is_word_char(c))
+                    .to_lowercase()
+            })
+            .filter(|term| !term.is_empty())
+            .collect();
+        let Some(last_term) = terms.len().checked_sub(1) else {
+            return Vec::new();
+        };
+
+        let words: Vec<String> = self.words.iter().map(|w| w.text.to_lowercase()).collect();
+
+        (0..words.len().saturating_sub(last_term))
+            .filter(|&start| {
+                terms.iter().enumerate().all(|(i, term)| {
+                    let word = &words[start + i];
+                    match (i, last_term) {
+                        (_, 0) => word.contains(term.as_str()),
+                        (0, _) => word.ends_with(term.as_str()),
+                        (i, last) if i == last => word.starts_with(term.as_str()),
+                        _ => word == term,
+                    }
+                })
+            })
+            .map(|start| (start, start + last_term))
+            .collect()
+    }
+
+    /// Whether a line starts a paragraph, judging by a wider than usual gap
+    /// above it. Lines that go back up the page, like the top of a new column,
+    /// also start one. The first line of the page can't be told apart here.
+    pub fn is_paragraph_start(&self, line_index: usize) -> bool {
+        let gap = |index: usize| self.lines[index - 1].y_center - self.lines[index].y_center;
+        if line_index == 0 || line_index >= self.lines.len() {
+            return false;
+        }
+
+        let mut gaps: Vec<f64> = (1..self.lines.len())
+            .map(gap)
+            .filter(|gap| *gap > 0.0)
+            .collect();
+        if gaps.is_empty() {
+            return true;
+        }
+        gaps.sort_by(f64::total_cmp);
+        let usual_gap = gaps[gaps.len() / 2];
+
+        let gap = gap(line_index);
+        gap <= 0.0 || gap > usual_gap * PARAGRAPH_GAP_FACTOR
+    }
+
     /// Total number of words on this page
     pub fn word_count(&self) -> usize {
         self.words.len()
@@ -415,4 +524,183 @@ mod tests {
         assert!(!PageTextMap::is_word_char('.'));
         assert!(!PageTextMap::is_word_char(','));
     }
+
+    #[test]
+    fn test_split_gap() {
+        assert_eq!(
+            PageTextMap::split_gap(", \u{201C}"),
+            (", ".to_string(), "\u{201C}".to_string())
+        );
+        assert_eq!(
+            PageTextMap::split_gap("\u{2014}"),
+            ("\u{2014}".to_string(), String::new())
+        );
+        assert_eq!(
+            PageTextMap::split_gap(".\r\n"),
+            (".\r\n".to_string(), String::new())
+        );
+        assert_eq!(PageTextMap::split_gap(""), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn test_nearest_word() {
+        let chars = "ab cd"
+            .chars()
+            .enumerate()
+            .map(|(index, char)| {
+                let left = index as f32 * 10.0;
+                PageChar {
+                    char,
+                    index,
+                    bounds: (!char.is_whitespace())
+                        .then(|| PdfRect::new_from_values(100.0, left, 110.0, left + 8.0)),
+                }
+            })
+            .collect();
+        let map = PageTextMap::build_from_chars(chars, 0, 200.0, 200.0);
+
+        assert_eq!(map.nearest_word(5.0, 105.0), Some(0));
+        assert_eq!(map.nearest_word(45.0, 150.0), Some(1));
+        // Off the page
+        assert_eq!(map.nearest_word(-50.0, 500.0), Some(0));
+    }
+
+    #[test]
+    fn test_word_at() {
+        let map = map_from_lines(&[("alpha beta", 700.0), ("gamma delta", 300.0)]);
+
+        // Inside "beta", then just right of it
+        assert_eq!(map.word_at(75.0, 705.0, 0.0), Some(1));
+        assert_eq!(map.word_at(100.0, 705.0, 5.0), Some(1));
+        assert_eq!(map.word_at(100.0, 705.0, 0.0), None);
+        assert_eq!(map.word_at(20.0, 305.0, 5.0), Some(2));
+        // Between the lines
+        assert_eq!(map.word_at(20.0, 500.0, 5.0), None);
+    }
+
+    #[test]
+    fn test_nearest_word_matches_scan() {
+        let map = map_from_lines(&[
+            ("one two three", 750.0),
+            ("four", 700.0),
+            ("five six seven eight", 400.0),
+            ("nine", 40.0),
+        ]);
+        let distance = |word: &WordInfo, x: f64, y: f64| {
+            let dx = (word.bounds.left().value as f64 - x)
+                .max(x - word.bounds.right().value as f64)
+                .max(0.0);
+            let dy = (word.bounds.bottom().value as f64 - y)
+                .max(y - word.bounds.top().value as f64)
+                .max(0.0);
+            dx.hypot(dy)
+        };
+
+        for x in (-40..440).step_by(23) {
+            for y in (-40..840).step_by(31) {
+                let (x, y) = (x as f64, y as f64);
+                let nearest = map.nearest_word(x, y).unwrap();
+                let best = map
+                    .words
+                    .iter()
+                    .map(|word| distance(word, x, y))
+                    .fold(f64::MAX, f64::min);
+                assert_eq!(distance(&map.words[nearest], x, y), best, "at ({x}, {y})");
+            }
+        }
+    }
+
+    /// A page with one line of text per entry, at the given baseline
+    fn map_from_lines(lines: &[(&str, f32)]) -> PageTextMap {
+        let mut chars = Vec::new();
+        for (text, bottom) in lines {
+            for (column, char) in text.chars().chain(std::iter::once('\n')).enumerate() {
+                let left = column as f32 * 10.0;
+                chars.push(PageChar {
+                    char,
+                    index: chars.len(),
+                    bounds: (!char.is_whitespace()).then(|| {
+                        PdfRect::new_from_values(*bottom, left, bottom + 10.0, left + 8.0)
+                    }),
+                });
+            }
+        }
+        PageTextMap::build_from_chars(chars, 0, 400.0, 800.0)
+    }
+
+    #[test]
+    fn test_ends_sentence() {
+        let map = map_from_lines(&[("One, two. \u{201C}Three?\u{201D} Four (five.) six", 700.0)]);
+        let ends: Vec<bool> = map.words.iter().map(|w| w.ends_sentence()).collect();
+        assert_eq!(ends, vec![false, true, true, false, true, false]);
+    }
+
+    #[test]
+    fn test_is_paragraph_start() {
+        let map = map_from_lines(&[
+            ("First paragraph", 700.0),
+            ("goes on.", 686.0),
+            ("Second one", 650.0),
+            ("ends here.", 636.0),
+            ("Next column", 700.0),
+        ]);
+        assert_eq!(map.line_count(), 5);
+        assert!(!map.is_paragraph_start(0));
+        assert!(!map.is_paragraph_start(1));
+        assert!(map.is_paragraph_start(2));
+        assert!(!map.is_paragraph_start(3));
+        assert!(map.is_paragraph_start(4));
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let chars = "The cat sat on the catalog"
+            .chars()
+            .enumerate()
+            .map(|(index, char)| {
+                let left = index as f32 * 10.0;
+                PageChar {
+                    char,
+                    index,
+                    bounds: (!char.is_whitespace())
+                        .then(|| PdfRect::new_from_values(100.0, left, 110.0, left + 8.0)),
+                }
+            })
+            .collect();
+        let map = PageTextMap::build_from_chars(chars, 0, 400.0, 200.0);
+
+        assert_eq!(map.find_matches("CAT"), vec![(1, 1), (5, 5)]);
+        assert_eq!(map.find_matches("the cat"), vec![(0, 1), (4, 5)]);
+        assert_eq!(map.find_matches("at on th"), vec![(2, 4)]);
+        assert_eq!(map.find_matches("cat, sat."), vec![(1, 2)]);
+        assert!(map.find_matches("  ").is_empty());
+        assert!(map.find_matches("dog").is_empty());
+    }
+
+    #[test]
+    fn test_normalized_words() {
+        let chars = "\u{201C}\u{FB01}rst\u{201D} don\u{2019}t"
+            .chars()
+            .enumerate()
+            .map(|(index, char)| {
+                let left = index as f32 * 10.0;
+                PageChar {
+                    char,
+                    index,
+                    bounds: (!char.is_whitespace())
+                        .then(|| PdfRect::new_from_values(100.0, left, 110.0, left + 8.0)),
+                }
+            })
+            .collect();
+        let map = PageTextMap::build_from_chars(chars, 0, 400.0, 200.0);
+
+        assert_eq!(map.words[0].text, "first");
+        assert_eq!(map.words[0].raw, "\u{FB01}rst");
+        assert_eq!(map.words[0].leading, "\"");
+        assert_eq!(map.words[0].trailing, "\" ");
+        assert_eq!(map.words[1].text, "don't");
+        // Positions stay those of the extracted chars
+        assert_eq!((map.words[0].char_start, map.words[0].char_end), (1, 4));
+        assert_eq!(map.find_matches("\u{FB01}rst"), vec![(0, 0)]);
+    }
 }
@@ -0,0 +1,230 @@
+use crate::modes::WordCursor;
+use crate::text_map::page_text_map::PageTextMap;
+use crate::text_map::word_info::WordInfo;
+
+/// A word of the reflowed text: where it sits in the document and in the text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReflowWord {
+    pub cursor: WordCursor,
+    /// Character offsets of the word in the text, end exclusive
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Text of a document with its original layout dropped: the lines of a paragraph are
+/// joined so the text can be wrapped to any width, while every word keeps its
+/// document position for annotations and navigation.
+#[derive(Debug, Default)]
+pub struct ReflowText {
+    pub text: String,
+    /// Words in document order
+    pub words: Vec<ReflowWord>,
+    /// Length of `text` in characters
+    char_len: usize,
+    /// The last word pushed, still waiting for its separator
+    last_word: Option<WordInfo>,
+}
+
+impl ReflowText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append the words of a page. A page continues the paragraph of the previous
+    /// one unless that ended a sentence.
+    pub fn push_page(&mut self, text_map: &PageTextMap) {
+        for (word_index, word) in text_map.words.iter().enumerate() {
+            let line_break =
+                word_index == 0 || word.line_index != text_map.words[word_index - 1].line_index;
+            let separator = match &self.last_word {
+                None => "",
+                Some(prev) if word_index == 0 => {
+                    if prev.ends_sentence() {
+                        "\n\n"
+                    } else {
+                        " "
+                    }
+                }
+                Some(_) if line_break => {
+                    if text_map.is_paragraph_start(word.line_index) {
+                        "\n\n"
+                    } else {
+                        " "
+                    }
+                }
+                Some(prev) if prev.has_trailing_space() || prev.trailing.is_empty() => " ",
+                // Punctuation glued between the words ("word—word")
+                Some(_) => "",
+            };
+            self.finish_word(separator, line_break);
+            self.push_str(&word.leading);
+
+            let start = self.char_len;
+            self.push_str(&word.text);
+            self.words.push(ReflowWord {
+                cursor: WordCursor::new(text_map.page_index, word_index),
+                start,
+                end: self.char_len,
+            });
+            self.last_word = Some(word.clone());
+        }
+    }
+
+    /// Close the text after the last page
+    pub fn finish(&mut self) {
+        self.finish_word("\n", true);
+    }
+
+    /// Push the punctuation of the last word and the separator to the next one. A
+    /// hyphen splitting the word across lines is dropped along with the separator.
+    fn finish_word(&mut self, separator: &str, line_break: bool) {
+        let Some(prev) = self.last_word.take() else {
+            self.push_str(separator);
+            return;
+        };
+
+        let hyphenated = line_break
+            && separator == " "
+            && prev.trailing_punctuation().is_empty()
+            && prev.text.ends_with('-')
+            && prev
+                .text
+                .chars()
+                .rev()
+                .nth(1)
+                .is_some_and(char::is_alphabetic);
+        if hyphenated {
+            // Keep the hyphen out of the word's span, so "jum-" + "ped" reads "jumped"
+            self.text.pop();
+            self.char_len -= 1;
+            if let Some(last) = self.words.last_mut() {
+                last.end -= 1;
+            }
+            return;
+        }
+
+        if separator.is_empty() {
+            self.push_str(&prev.trailing);
+        } else {
+            self.push_str(&prev.trailing_punctuation());
+            self.push_str(separator);
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.text.push_str(s);
+        self.char_len += s.chars().count();
+    }
+
+    /// Whether no page had any text
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// The word at a character offset, or the closest one before it
+    pub fn word_at(&self, offset: usize) -> Option<WordCursor> {
+        let index = self.words.partition_point(|word| word.start <= offset);
+        self.words
+            .get(index.saturating_sub(1))
+            .map(|word| word.cursor)
+    }
+
+    /// Character offsets spanning the words from `start` to `end` (both inclusive).
+    /// Cursors between words of the text are moved to the nearest word inside the range.
+    pub fn offsets(&self, start: WordCursor, end: WordCursor) -> Option<(usize, usize)> {
+        let (start, end) = if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        let first = self.words.partition_point(|word| word.cursor < start);
+        let last = self.words.partition_point(|word| word.cursor <= end);
+        if first >= last {
+            return None;
+        }
+        Some((self.words[first].start, self.words[last - 1].end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::PageChar;
+    use pdfium_render::prelude::PdfRect;
+
+    /// A page with one line of text per entry, at the given baseline
+    fn page(page_index: usize, lines: &[(&str, f32)]) -> PageTextMap {
+        let mut chars = Vec::new();
+        for (text, bottom) in lines {
+            for (column, char) in text.chars().chain(std::iter::once('\n')).enumerate() {
+                let left = column as f32 * 10.0;
+                chars.push(PageChar {
+                    char,
+                    index: chars.len(),
+                    bounds: (!char.is_whitespace()).then(|| {
+                        PdfRect::new_from_values(*bottom, left, bottom + 10.0, left + 8.0)
+                    }),
+                });
+            }
+        }
+        PageTextMap::build_from_chars(chars, page_index, 400.0, 800.0)
+    }
+
+    fn reflow(pages: &[PageTextMap]) -> ReflowText {
+        let mut text = ReflowText::new();
+        for page in pages {
+            text.push_page(page);
+        }
+        text.finish();
+        text
+    }
+
+    #[test]
+    fn test_joins_lines_and_paragraphs() {
+        let text = reflow(&[
+            page(
+                0,
+                &[
+                    ("The quick brown", 700.0),
+                    ("fox jum-", 686.0),
+                    ("ped. Then", 672.0),
+                    ("A new one,", 640.0),
+                ],
+            ),
+            page(1, &[("across pages.", 700.0)]),
+            page(2, &[("Next page.", 700.0)]),
+        ]);
+
+        assert_eq!(
+            text.text,
+            "The quick brown fox jumped. Then\n\nA new one, across pages.\n\nNext page.\n"
+        );
+    }
+
+    #[test]
+    fn test_maps_words_and_offsets() {
+        let text = reflow(&[
+            page(0, &[("One, two", 700.0)]),
+            page(1, &[("three", 700.0)]),
+        ]);
+        assert_eq!(text.text, "One, two three\n");
+
+        assert_eq!(text.word_at(0), Some(WordCursor::new(0, 0)));
+        assert_eq!(text.word_at(3), Some(WordCursor::new(0, 0)));
+        assert_eq!(text.word_at(5), Some(WordCursor::new(0, 1)));
+        assert_eq!(text.word_at(20), Some(WordCursor::new(1, 0)));
+
+        assert_eq!(
+            text.offsets(WordCursor::new(0, 1), WordCursor::new(1, 0)),
+            Some((5, 14))
+        );
+        assert_eq!(
+            text.offsets(WordCursor::new(1, 0), WordCursor::new(0, 0)),
+            Some((0, 14))
+        );
+        assert_eq!(
+            text.offsets(WordCursor::new(2, 0), WordCursor::new(2, 3)),
+            None
+        );
+    }
+}
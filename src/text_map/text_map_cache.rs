@@ -1,8 +1,13 @@
 use std::collections::HashMap;
 
-use pdfium_render::prelude::*;
-
+use crate::backend::DocumentBackend;
+use crate::modes::WordCursor;
 use crate::text_map::page_text_map::PageTextMap;
+use crate::text_map::word_info::WordInfo;
+
+/// Horizontal gap between two words (relative to the word height) above which they are
+/// treated as separate words even if the PDF has no space character between them
+const WORD_GAP_THRESHOLD: f64 = 0.15;
 
 /// Lazy cache for PageTextMap instances across a PDF document
 #[derive(Debug)]
@@ -27,7 +32,7 @@ impl TextMapCache {
     pub fn get_or_build(
         &mut self,
         page_index: usize,
-        document: &PdfDocument,
+        document: &dyn DocumentBackend,
     ) -> Option<&PageTextMap> {
         if page_index >= self.page_count {
             return None;
@@ -35,9 +40,7 @@ impl TextMapCache {
 
         // Build if not cached
         if !self.maps.contains_key(&page_index) {
-            let pages = document.pages();
-            let page = pages.get(page_index as u16).ok()?;
-            let text_map = PageTextMap::build_from_page(&page, page_index)?;
+            let text_map = Self::build_page(page_index, document)?;
             self.maps.insert(page_index, text_map);
         }
 
@@ -55,6 +58,12 @@ impl TextMapCache {
         self.maps.contains_key(&page_index)
     }
 
+    /// Forget the text map of a page, so it is built again from the page's
+    /// current text, e.g. once it was recognized by OCR
+    pub fn invalidate(&mut self, page_index: usize) {
+        self.maps.remove(&page_index);
+    }
+
     /// Clear all cached data
     pub fn clear(&mut self) {
         self.maps.clear();
@@ -66,15 +75,178 @@ impl TextMapCache {
     }
 
     /// Pre-build text maps for a range of pages (useful for background loading)
-    pub fn prebuild_range(&mut self, start: usize, end: usize, document: &PdfDocument) {
+    pub fn prebuild_range(&mut self, start: usize, end: usize, document: &dyn DocumentBackend) {
         for page_index in start..end.min(self.page_count) {
             if !self.is_cached(page_index) {
-                if let Ok(page) = document.pages().get(page_index as u16) {
-                    if let Some(text_map) = PageTextMap::build_from_page(&page, page_index) {
-                        self.maps.insert(page_index, text_map);
-                    }
+                if let Some(text_map) = Self::build_page(page_index, document) {
+                    self.maps.insert(page_index, text_map);
                 }
             }
         }
     }
+
+    /// Whether a page has a text layer. Scanned pages without OCR only contain images.
+    pub fn has_text(&mut self, page_index: usize, document: &dyn DocumentBackend) -> bool {
+        self.get_or_build(page_index, document)
+            .is_some_and(|text_map| text_map.word_count() > 0)
+    }
+
+    /// Closest page after (or before) `page_index` that has a text layer
+    pub fn next_page_with_text(
+        &mut self,
+        page_index: usize,
+        forward: bool,
+        document: &dyn DocumentBackend,
+    ) -> Option<usize> {
+        if forward {
+            (page_index + 1..self.page_count).find(|&page| self.has_text(page, document))
+        } else {
+            (0..page_index.min(self.page_count))
+                .rev()
+                .find(|&page| self.has_text(page, document))
+        }
+    }
+
+    /// Indices of all pages without a text layer. Builds the text map of every page.
+    pub fn image_only_pages(&mut self, document: &dyn DocumentBackend) -> Vec<usize> {
+        (0..self.page_count)
+            .filter(|&page| !self.has_text(page, document))
+            .collect()
+    }
+
+    /// Find `query` on every page (see `PageTextMap::find_matches`), as inclusive
+    /// (first word, last word) pairs in document order. Matches don't span pages.
+    pub fn search(
+        &mut self,
+        query: &str,
+        document: &dyn DocumentBackend,
+    ) -> Vec<(WordCursor, WordCursor)> {
+        self.search_pages(query, document, 0..self.page_count)
+    }
+
+    /// Like `search`, but only the matches lying entirely between `first` and
+    /// `last` (both inclusive), visiting only their pages
+    pub fn search_within(
+        &mut self,
+        query: &str,
+        document: &dyn DocumentBackend,
+        first: WordCursor,
+        last: WordCursor,
+    ) -> Vec<(WordCursor, WordCursor)> {
+        let pages = first.page_index..(last.page_index + 1).min(self.page_count);
+        let mut matches = self.search_pages(query, document, pages);
+        matches.retain(|(start, end)| *start >= first && *end <= last);
+        matches
+    }
+
+    fn search_pages(
+        &mut self,
+        query: &str,
+        document: &dyn DocumentBackend,
+        pages: std::ops::Range<usize>,
+    ) -> Vec<(WordCursor, WordCursor)> {
+        let mut matches = Vec::new();
+        for page_index in pages {
+            let Some(text_map) = self.get_or_build(page_index, document) else {
+                continue;
+            };
+            matches.extend(
+                text_map
+                    .find_matches(query)
+                    .into_iter()
+                    .map(|(start, end)| {
+                        (
+                            WordCursor::new(page_index, start),
+                            WordCursor::new(page_index, end),
+                        )
+                    }),
+            );
+        }
+        matches
+    }
+
+    fn build_page(page_index: usize, document: &dyn DocumentBackend) -> Option<PageTextMap> {
+        let (page_width, page_height) = document.page_size(page_index)?;
+        let chars = document.page_chars(page_index)?;
+        Some(PageTextMap::build_from_chars(
+            chars,
+            page_index,
+            page_width,
+            page_height,
+        ))
+    }
+
+    /// Reconstruct the text between two word positions (both inclusive), possibly across
+    /// pages. Punctuation around and between the selected words and the spacing between
+    /// them are kept as extracted, while line and page breaks become newlines. Pages
+    /// must already be cached.
+    pub fn extract_text(&self, start: WordCursor, end: WordCursor) -> String {
+        let (first, last) = if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        };
+
+        let mut text = String::new();
+        let mut prev: Option<(usize, &WordInfo)> = None;
+
+        for page_index in first.page_index..=last.page_index {
+            let Some(text_map) = self.get(page_index) else {
+                continue;
+            };
+
+            let word_start = if page_index == first.page_index {
+                first.word_index
+            } else {
+                0
+            };
+            let word_end = if page_index == last.page_index {
+                (last.word_index + 1).min(text_map.word_count())
+            } else {
+                text_map.word_count()
+            };
+
+            for word in text_map.words.get(word_start..word_end).unwrap_or_default() {
+                if let Some((prev_page, prev_word)) = prev {
+                    text.push_str(&prev_word.trailing_punctuation());
+                    text.push_str(Self::word_separator(
+                        prev_word,
+                        word,
+                        prev_page == page_index,
+                    ));
+                }
+                text.push_str(&word.leading);
+                text.push_str(&word.text);
+                prev = Some((page_index, word));
+            }
+        }
+
+        if let Some((_, last_word)) = prev {
+            text.push_str(&last_word.trailing_punctuation());
+        }
+        text
+    }
+
+    /// Separator to put between two consecutive words when reconstructing text
+    fn word_separator(prev: &WordInfo, next: &WordInfo, same_page: bool) -> &'static str {
+        if !same_page || prev.line_index != next.line_index {
+            return "\n";
+        }
+
+        if prev.has_trailing_space() {
+            return " ";
+        }
+
+        // Punctuation glued between the words ("word—word") means there was no space
+        if !prev.trailing.is_empty() {
+            return "";
+        }
+
+        let gap = (next.bounds.left().value - prev.bounds.right().value) as f64;
+        if gap > prev.height() * WORD_GAP_THRESHOLD {
+            " "
+        } else {
+            ""
+        }
+    }
 }
@@ -0,0 +1,142 @@
+use crate::text_map::word_info::WordInfo;
+
+/// Side of a grid cell in PDF points, about two lines of body text
+const GRID_CELL_PTS: f64 = 24.0;
+
+/// Words of a page bucketed by the cells of a grid laid over it, so the words
+/// near a point are found without going through the whole page. Cells are in
+/// PDF points, so the grid holds at any zoom.
+#[derive(Debug, Default)]
+pub struct WordGrid {
+    columns: usize,
+    rows: usize,
+    /// Indices of the words overlapping each cell, row by row from the bottom
+    cells: Vec<Vec<usize>>,
+}
+
+impl WordGrid {
+    /// Index the words of a `page_width` x `page_height` page. Words sticking out
+    /// of the page go in the cells at its edge.
+    pub fn build(words: &[WordInfo], page_width: f64, page_height: f64) -> Self {
+        let columns = (page_width / GRID_CELL_PTS).ceil().max(1.0) as usize;
+        let rows = (page_height / GRID_CELL_PTS).ceil().max(1.0) as usize;
+        let mut grid = Self {
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows],
+        };
+
+        for (index, word) in words.iter().enumerate() {
+            let (left, bottom, right, top) = bounds(word);
+            let (first_column, first_row) = grid.cell_at(left, bottom);
+            let (last_column, last_row) = grid.cell_at(right, top);
+            for row in first_row..=last_row {
+                for column in first_column..=last_column {
+                    grid.cells[row * columns + column].push(index);
+                }
+            }
+        }
+        grid
+    }
+
+    /// Word whose bounds, grown by `tolerance` points on each side, contain the
+    /// point. The closest one wins when several do.
+    pub fn word_at(&self, words: &[WordInfo], x: f64, y: f64, tolerance: f64) -> Option<usize> {
+        if self.cells.is_empty() {
+            return None;
+        }
+        let (first_column, first_row) = self.cell_at(x - tolerance, y - tolerance);
+        let (last_column, last_row) = self.cell_at(x + tolerance, y + tolerance);
+
+        let mut best: Option<(usize, f64)> = None;
+        for row in first_row..=last_row {
+            for column in first_column..=last_column {
+                for &index in &self.cells[row * self.columns + column] {
+                    let distance = distance_to(&words[index], x, y);
+                    if distance <= tolerance
+                        && best.is_none_or(|best| is_closer(index, distance, best))
+                    {
+                        best = Some((index, distance));
+                    }
+                }
+            }
+        }
+        best.map(|(index, _)| index)
+    }
+
+    /// Word closest to the point, 0 away when inside its bounds. Cells are
+    /// visited in rings around the point until no farther one can hold a closer
+    /// word.
+    pub fn nearest_word(&self, words: &[WordInfo], x: f64, y: f64) -> Option<usize> {
+        if self.cells.is_empty() {
+            return None;
+        }
+        let (center_column, center_row) = self.cell_at(x, y);
+        let mut best: Option<(usize, f64)> = None;
+
+        for ring in 0..self.columns.max(self.rows) {
+            // Cells `ring` cells away are at least `ring - 1` cells from the point
+            let nearest_possible = ring.saturating_sub(1) as f64 * GRID_CELL_PTS;
+            if best.is_some_and(|(_, distance)| distance <= nearest_possible) {
+                break;
+            }
+
+            for (column, row) in self.ring_cells(center_column, center_row, ring) {
+                for &index in &self.cells[row * self.columns + column] {
+                    let distance = distance_to(&words[index], x, y);
+                    if best.is_none_or(|best| is_closer(index, distance, best)) {
+                        best = Some((index, distance));
+                    }
+                }
+            }
+        }
+        best.map(|(index, _)| index)
+    }
+
+    /// Cell holding a point, the nearest edge cell for points off the page
+    fn cell_at(&self, x: f64, y: f64) -> (usize, usize) {
+        let clamp = |value: f64, count: usize| {
+            ((value / GRID_CELL_PTS).floor().max(0.0) as usize).min(count - 1)
+        };
+        (clamp(x, self.columns), clamp(y, self.rows))
+    }
+
+    /// Cells exactly `ring` cells away from a cell, within the grid
+    fn ring_cells(
+        &self,
+        column: usize,
+        row: usize,
+        ring: usize,
+    ) -> impl Iterator<Item = (usize, usize)> {
+        let (columns, rows) = (self.columns as isize, self.rows as isize);
+        let (column, row, ring) = (column as isize, row as isize, ring as isize);
+        (row - ring..=row + ring)
+            .flat_map(move |r| (column - ring..=column + ring).map(move |c| (c, r)))
+            .filter(move |&(c, r)| (c - column).abs() == ring || (r - row).abs() == ring)
+            .filter(move |&(c, r)| c >= 0 && c < columns && r >= 0 && r < rows)
+            .map(|(c, r)| (c as usize, r as usize))
+    }
+}
+
+/// `(left, bottom, right, top)` of a word in PDF points
+fn bounds(word: &WordInfo) -> (f64, f64, f64, f64) {
+    (
+        word.bounds.left().value as f64,
+        word.bounds.bottom().value as f64,
+        word.bounds.right().value as f64,
+        word.bounds.top().value as f64,
+    )
+}
+
+/// Distance from a point to the bounds of a word, 0 inside them
+fn distance_to(word: &WordInfo, x: f64, y: f64) -> f64 {
+    let (left, bottom, right, top) = bounds(word);
+    let dx = (left - x).max(x - right).max(0.0);
+    let dy = (bottom - y).max(y - top).max(0.0);
+    dx.hypot(dy)
+}
+
+/// Whether a word beats the best one so far, the first in reading order on a tie
+fn is_closer(index: usize, distance: f64, (best_index, best_distance): (usize, f64)) -> bool {
+    distance < best_distance || (distance == best_distance && index < best_index)
+}
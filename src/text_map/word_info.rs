@@ -1,10 +1,14 @@
 use pdfium_render::prelude::PdfRect;
 
+use crate::services::pdf_text::normalize_text;
+
 /// Information about a single word extracted from a PDF page
 #[derive(Debug, Clone)]
 pub struct WordInfo {
-    /// The word text
+    /// The word text, normalized for lookups, copies and exports (see `normalize_text`)
     pub text: String,
+    /// The word as extracted from the PDF, for drawing over the page
+    pub raw: String,
     /// Start character index in the page's full text
     pub char_start: usize,
     /// End character index (exclusive) in the page's full text
@@ -16,33 +20,63 @@ pub struct WordInfo {
     pub center_y: f64,
     /// Which line this word belongs to (for j/k navigation)
     pub line_index: usize,
-    pub surround_left: Option<String>,
+    /// Punctuation glued to the start of the word (e.g. an opening quote), normalized
+    pub leading: String,
+    /// Punctuation and whitespace that follow the word, normalized
+    pub trailing: String,
 }
 
 impl WordInfo {
-    /// Create a new WordInfo with computed center
+    /// Create a new WordInfo from its extracted text, with computed center
     pub fn new(
-        text: String,
+        raw: String,
         char_start: usize,
         char_end: usize,
         bounds: PdfRect,
         line_index: usize,
-        surround_left: Option<String>,
     ) -> Self {
         let center_x = (bounds.left().value as f64 + bounds.right().value as f64) / 2.0;
         let center_y = (bounds.bottom().value as f64 + bounds.top().value as f64) / 2.0;
 
         Self {
-            text,
+            text: normalize_text(&raw),
+            raw,
             char_start,
             char_end,
             bounds,
             center_x,
             center_y,
             line_index,
-            surround_left,
+            leading: String::new(),
+            trailing: String::new(),
         }
     }
+
+    /// Trailing punctuation with the whitespace stripped (e.g. "," or ".\u{201D}")
+    pub fn trailing_punctuation(&self) -> String {
+        self.trailing
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect()
+    }
+
+    /// Whether the PDF had whitespace after this word
+    pub fn has_trailing_space(&self) -> bool {
+        self.trailing.chars().any(|c| c.is_whitespace())
+    }
+
+    /// Whether the word closes a sentence: its trailing punctuation ends with `.`,
+    /// `!`, `?` or `…`, possibly followed by closing quotes or brackets
+    pub fn ends_sentence(&self) -> bool {
+        self.trailing_punctuation()
+            .trim_end_matches(['"', '\'', '\u{201D}', '\u{2019}', '\u{BB}', ')', ']'])
+            .ends_with(['.', '!', '?', '\u{2026}'])
+    }
+
+    /// Height of the word's bounding box in PDF points
+    pub fn height(&self) -> f64 {
+        (self.bounds.top().value - self.bounds.bottom().value) as f64
+    }
 }
 
 /// Information about a line of text on a page
@@ -0,0 +1,211 @@
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Button, Grid, Label, Orientation, Window};
+
+use crate::services::about::{self, EnvironmentInfo, UpdateStatus};
+
+mod imp {
+    use super::*;
+
+    pub struct AboutWindow {
+        pub info: EnvironmentInfo,
+        pub update_button: Button,
+        pub update_label: Label,
+        pub copy_button: Button,
+    }
+
+    impl Default for AboutWindow {
+        fn default() -> Self {
+            Self {
+                info: EnvironmentInfo::collect(),
+                update_button: Button::with_label("Check for Updates"),
+                update_label: Label::builder()
+                    .halign(gtk::Align::Start)
+                    .wrap(true)
+                    .css_classes(["dim-label"])
+                    .build(),
+                copy_button: Button::with_label("Copy Details"),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AboutWindow {
+        const NAME: &'static str = "AboutWindow";
+        type Type = super::AboutWindow;
+        type ParentType = Window;
+    }
+
+    impl ObjectImpl for AboutWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+    }
+
+    impl WidgetImpl for AboutWindow {}
+    impl WindowImpl for AboutWindow {}
+}
+
+glib::wrapper! {
+    pub struct AboutWindow(ObjectSubclass<imp::AboutWindow>)
+        @extends Window, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl AboutWindow {
+    pub fn new(parent: &impl IsA<Window>) -> Self {
+        glib::Object::builder()
+            .property("transient-for", parent)
+            .property("modal", true)
+            .property("title", "About Eyers")
+            .property("default-width", 460)
+            .property("default-height", 260)
+            .build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+
+        self.add_css_class("about-window");
+
+        let main_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(12)
+            .margin_start(24)
+            .margin_end(24)
+            .margin_top(24)
+            .margin_bottom(24)
+            .build();
+
+        let title = Label::builder()
+            .label(format!("Eyers {}", imp.info.app_version))
+            .halign(gtk::Align::Start)
+            .css_classes(["title-2"])
+            .build();
+        main_box.append(&title);
+
+        let grid = Grid::builder().row_spacing(6).column_spacing(12).build();
+        let library = imp
+            .info
+            .pdfium_library
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let rows = [
+            (
+                "pdfium build",
+                imp.info.pdfium_build.as_deref().unwrap_or("unknown"),
+            ),
+            ("pdfium library", library.as_str()),
+        ];
+        for (row, (name, value)) in rows.iter().enumerate() {
+            let name_label = Label::builder()
+                .label(*name)
+                .halign(gtk::Align::Start)
+                .css_classes(["dim-label"])
+                .build();
+            let value_label = Label::builder()
+                .label(*value)
+                .halign(gtk::Align::Start)
+                .hexpand(true)
+                .selectable(true)
+                .wrap(true)
+                .wrap_mode(gtk::pango::WrapMode::Char)
+                .build();
+            grid.attach(&name_label, 0, row as i32, 1, 1);
+            grid.attach(&value_label, 1, row as i32, 1, 1);
+        }
+        main_box.append(&grid);
+        main_box.append(&imp.update_label);
+
+        let button_box = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .halign(gtk::Align::End)
+            .vexpand(true)
+            .valign(gtk::Align::End)
+            .build();
+
+        let close_button = Button::with_label("Close");
+        button_box.append(&imp.copy_button);
+        button_box.append(&imp.update_button);
+        button_box.append(&close_button);
+        main_box.append(&button_box);
+
+        self.set_child(Some(&main_box));
+
+        let window_weak = self.downgrade();
+        close_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.close();
+            }
+        });
+
+        let window_weak = self.downgrade();
+        imp.copy_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.clipboard().set_text(&window.imp().info.report());
+                window
+                    .imp()
+                    .update_label
+                    .set_text("Details copied to clipboard.");
+            }
+        });
+
+        let window_weak = self.downgrade();
+        imp.update_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.check_for_update();
+            }
+        });
+    }
+
+    /// Query GitHub releases on a worker thread; nothing is sent unless the user asks
+    fn check_for_update(&self) {
+        let imp = self.imp();
+        imp.update_button.set_sensitive(false);
+        imp.update_label.set_text("Checking for updates…");
+
+        // Resumed on the main loop as soon as the checking thread returns
+        let window_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(about::check_for_update)
+                .await
+                .unwrap_or_else(|_| {
+                    Err(about::UpdateError::RequestFailed(
+                        "the request stopped".to_string(),
+                    ))
+                });
+            if let Some(window) = window_weak.upgrade() {
+                window.show_update_result(result);
+            }
+        });
+    }
+
+    fn show_update_result(&self, result: Result<UpdateStatus, about::UpdateError>) {
+        let imp = self.imp();
+        imp.update_button.set_sensitive(true);
+
+        match result {
+            Ok(UpdateStatus::UpToDate) => {
+                imp.update_label
+                    .set_text("You are running the latest version.");
+            }
+            Ok(UpdateStatus::Available { version, url }) => {
+                imp.update_label.set_markup(&format!(
+                    "Eyers {} is available: <a href=\"{}\">{}</a>",
+                    glib::markup_escape_text(&version),
+                    glib::markup_escape_text(&url),
+                    glib::markup_escape_text(&url),
+                ));
+            }
+            Err(e) => {
+                imp.update_label
+                    .set_text(&format!("Could not check for updates: {}", e));
+            }
+        }
+    }
+}
@@ -2,12 +2,19 @@ use gtk::glib;
 use gtk::glib::signal::SignalHandlerId;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
-use gtk::{Box, Button, Label, Orientation, ScrolledWindow, Separator, TextView};
+use gtk::{
+    Box, Button, Entry, Label, Orientation, ScrolledWindow, Separator, TextView, ToggleButton,
+};
 use std::cell::{Cell, RefCell};
 use std::sync::OnceLock;
 
+use crate::services::annotations::{self, Annotation, AnnotationColor, AnnotationId};
+use crate::services::settings::PanelPosition;
+
 const MIN_PANEL_HEIGHT: i32 = 120;
 const DEFAULT_PANEL_HEIGHT: i32 = 150;
+/// Width of the panel when docked right of the pages
+const SIDE_PANEL_WIDTH: i32 = 340;
 
 mod imp {
     use super::*;
@@ -16,6 +23,15 @@ mod imp {
         pub selected_text_label: Label,
         pub text_view: TextView,
         pub scrolled_window: ScrolledWindow,
+        /// Comma-separated tags of the annotation
+        pub tags_entry: Entry,
+        /// "See also" links of an annotation being edited: one chip per linked
+        /// annotation and a button to link another one
+        pub links_row: Box,
+        pub link_chips: Box,
+        pub link_button: gtk::MenuButton,
+        pub link_candidates: gtk::ListBox,
+        pub candidate_ids: RefCell<Vec<AnnotationId>>,
         pub save_button: Button,
         pub cancel_button: Button,
         pub delete_button: Button,
@@ -23,6 +39,9 @@ mod imp {
         pub panel_height: RefCell<i32>,
         /// The annotation ID if we're editing an existing annotation
         pub annotation_id: Cell<Option<i64>>,
+        /// Color picked for the annotation, one toggle button per color
+        pub color: Cell<AnnotationColor>,
+        pub color_buttons: RefCell<Vec<(AnnotationColor, ToggleButton)>>,
         /// Signal handler for key press on text view
         pub key_handler_id: RefCell<Option<SignalHandlerId>>,
     }
@@ -33,12 +52,20 @@ mod imp {
                 selected_text_label: Label::new(None),
                 text_view: TextView::new(),
                 scrolled_window: ScrolledWindow::new(),
+                tags_entry: Entry::new(),
+                links_row: Box::new(Orientation::Horizontal, 8),
+                link_chips: Box::new(Orientation::Horizontal, 4),
+                link_button: gtk::MenuButton::new(),
+                link_candidates: gtk::ListBox::new(),
+                candidate_ids: RefCell::new(Vec::new()),
                 save_button: Button::new(),
                 cancel_button: Button::new(),
                 delete_button: Button::new(),
                 resize_handle: Separator::new(Orientation::Horizontal),
                 panel_height: RefCell::new(DEFAULT_PANEL_HEIGHT),
                 annotation_id: Cell::new(None),
+                color: Cell::new(AnnotationColor::default()),
+                color_buttons: RefCell::new(Vec::new()),
                 key_handler_id: RefCell::new(None),
             }
         }
@@ -71,6 +98,18 @@ mod imp {
                     glib::subclass::Signal::builder("delete-requested")
                         .param_types([i64::static_type()])
                         .build(),
+                    // Emitted with the ID of the annotation to link to the edited one
+                    glib::subclass::Signal::builder("link-requested")
+                        .param_types([i64::static_type()])
+                        .build(),
+                    // Emitted with the ID of the linked annotation to unlink
+                    glib::subclass::Signal::builder("unlink-requested")
+                        .param_types([i64::static_type()])
+                        .build(),
+                    // Emitted with the ID of the linked annotation whose chip was clicked
+                    glib::subclass::Signal::builder("link-activated")
+                        .param_types([i64::static_type()])
+                        .build(),
                 ]
             })
         }
@@ -133,6 +172,8 @@ impl AnnotationPanel {
             .add_css_class("annotation-selected-text");
         header_box.append(&imp.selected_text_label);
 
+        header_box.append(&self.create_color_picker());
+
         content_box.append(&header_box);
 
         // Text input area
@@ -146,6 +187,13 @@ impl AnnotationPanel {
         imp.scrolled_window.add_css_class("annotation-scroll");
         content_box.append(&imp.scrolled_window);
 
+        imp.tags_entry
+            .set_placeholder_text(Some("Tags, comma-separated"));
+        imp.tags_entry.add_css_class("annotation-tags");
+        content_box.append(&imp.tags_entry);
+
+        content_box.append(&self.create_links_row());
+
         // Button row
         let button_box = Box::builder()
             .orientation(Orientation::Horizontal)
@@ -189,6 +237,101 @@ impl AnnotationPanel {
         self.setup_keyboard_handling();
     }
 
+    /// Linked toggle buttons, one per annotation color
+    fn create_color_picker(&self) -> Box {
+        let picker = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .valign(gtk::Align::Center)
+            .build();
+        picker.add_css_class("linked");
+        picker.add_css_class("annotation-color-picker");
+
+        let mut buttons: Vec<(AnnotationColor, ToggleButton)> = Vec::new();
+        for (index, color) in AnnotationColor::ALL.into_iter().enumerate() {
+            let button = ToggleButton::new();
+            button.add_css_class("annotation-color-button");
+            button.add_css_class(&format!("annotation-color-{}", color.as_str()));
+            button.set_tooltip_text(Some(&format!("{} (Ctrl+{})", color.label(), index + 1)));
+            if let Some((_, first)) = buttons.first() {
+                button.set_group(Some(first));
+            }
+
+            let panel_weak = self.downgrade();
+            button.connect_toggled(move |button| {
+                if let Some(panel) = panel_weak.upgrade().filter(|_| button.is_active()) {
+                    panel.imp().color.set(color);
+                }
+            });
+
+            picker.append(&button);
+            buttons.push((color, button));
+        }
+        self.imp().color_buttons.replace(buttons);
+        self.set_color(AnnotationColor::default());
+
+        picker
+    }
+
+    /// Chips of the linked annotations and the "Link…" picker, hidden until an
+    /// existing annotation is edited
+    fn create_links_row(&self) -> Box {
+        let imp = self.imp();
+
+        let label = Label::new(Some("See also:"));
+        label.add_css_class("dim-label");
+        imp.links_row.append(&label);
+
+        let chips_scroll = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Automatic)
+            .vscrollbar_policy(gtk::PolicyType::Never)
+            .hexpand(true)
+            .child(&imp.link_chips)
+            .build();
+        imp.links_row.append(&chips_scroll);
+
+        imp.link_candidates
+            .set_selection_mode(gtk::SelectionMode::None);
+        imp.link_candidates.set_activate_on_single_click(true);
+        let candidates_scroll = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .min_content_height(40)
+            .max_content_height(300)
+            .propagate_natural_height(true)
+            .child(&imp.link_candidates)
+            .build();
+        let popover = gtk::Popover::builder().child(&candidates_scroll).build();
+
+        let panel_weak = self.downgrade();
+        let popover_weak = popover.downgrade();
+        imp.link_candidates.connect_row_activated(move |_, row| {
+            let Some(panel) = panel_weak.upgrade() else {
+                return;
+            };
+            if let Some(popover) = popover_weak.upgrade() {
+                popover.popdown();
+            }
+            let id = panel
+                .imp()
+                .candidate_ids
+                .borrow()
+                .get(row.index() as usize)
+                .copied();
+            if let Some(id) = id {
+                panel.emit_by_name::<()>("link-requested", &[&id]);
+            }
+        });
+
+        imp.link_button.set_label("Link…");
+        imp.link_button
+            .set_tooltip_text(Some("Link another annotation of the document"));
+        imp.link_button.set_popover(Some(&popover));
+        imp.links_row.append(&imp.link_button);
+
+        imp.links_row.add_css_class("annotation-links");
+        imp.links_row.set_visible(false);
+        imp.links_row.clone()
+    }
+
     fn setup_button_signals(&self) {
         let imp = self.imp();
 
@@ -221,7 +364,19 @@ impl AnnotationPanel {
 
     fn setup_keyboard_handling(&self) {
         let imp = self.imp();
+        imp.text_view.add_controller(self.create_key_controller());
+        imp.tags_entry.add_controller(self.create_key_controller());
+
+        // Enter in the tags saves, there are no newlines to type there
+        let panel_weak = self.downgrade();
+        imp.tags_entry.connect_activate(move |_| {
+            if let Some(panel) = panel_weak.upgrade() {
+                panel.emit_save();
+            }
+        });
+    }
 
+    fn create_key_controller(&self) -> gtk::EventControllerKey {
         let controller = gtk::EventControllerKey::new();
         let panel_weak = self.downgrade();
 
@@ -240,11 +395,24 @@ impl AnnotationPanel {
                     panel.emit_save();
                     return glib::Propagation::Stop;
                 }
+
+                // Ctrl+1..4 to pick the color
+                if modifiers.contains(gtk::gdk::ModifierType::CONTROL_MASK) {
+                    let color = key
+                        .to_unicode()
+                        .and_then(|c| c.to_digit(10))
+                        .and_then(|digit| (digit as usize).checked_sub(1))
+                        .and_then(|index| AnnotationColor::ALL.get(index).copied());
+                    if let Some(color) = color {
+                        panel.set_color(color);
+                        return glib::Propagation::Stop;
+                    }
+                }
             }
             glib::Propagation::Proceed
         });
 
-        imp.text_view.add_controller(controller);
+        controller
     }
 
     fn emit_save(&self) {
@@ -296,13 +464,106 @@ impl AnnotationPanel {
         self.imp().annotation_id.get()
     }
 
+    /// Select the color of the annotation being edited
+    pub fn set_color(&self, color: AnnotationColor) {
+        let imp = self.imp();
+        imp.color.set(color);
+        for (button_color, button) in imp.color_buttons.borrow().iter() {
+            if *button_color == color {
+                button.set_active(true);
+            }
+        }
+    }
+
+    pub fn color(&self) -> AnnotationColor {
+        self.imp().color.get()
+    }
+
+    /// Show the annotations linked to the one edited, and offer `candidates` to
+    /// link. Hidden for new annotations, which have no ID to link yet.
+    pub fn set_links(&self, linked: &[Annotation], candidates: &[Annotation]) {
+        let imp = self.imp();
+        imp.links_row.set_visible(imp.annotation_id.get().is_some());
+
+        while let Some(child) = imp.link_chips.first_child() {
+            imp.link_chips.remove(&child);
+        }
+        for ann in linked {
+            imp.link_chips.append(&self.create_link_chip(ann));
+        }
+
+        imp.link_candidates.remove_all();
+        for ann in candidates {
+            let label = Label::builder()
+                .label(ann.short_label())
+                .xalign(0.0)
+                .margin_start(6)
+                .margin_end(6)
+                .margin_top(4)
+                .margin_bottom(4)
+                .build();
+            imp.link_candidates.append(&label);
+        }
+        imp.candidate_ids
+            .replace(candidates.iter().map(|ann| ann.id).collect());
+        imp.link_button.set_sensitive(!candidates.is_empty());
+    }
+
+    /// A button jumping to the linked annotation, with another one to unlink it
+    fn create_link_chip(&self, ann: &Annotation) -> Box {
+        let chip = Box::new(Orientation::Horizontal, 0);
+        chip.add_css_class("linked");
+        chip.add_css_class("annotation-link-chip");
+
+        let id = ann.id;
+        let jump_button = Button::builder()
+            .label(ann.short_label())
+            .tooltip_text(ann.selected_text.as_str())
+            .build();
+        let panel_weak = self.downgrade();
+        jump_button.connect_clicked(move |_| {
+            if let Some(panel) = panel_weak.upgrade() {
+                panel.emit_by_name::<()>("link-activated", &[&id]);
+            }
+        });
+        chip.append(&jump_button);
+
+        let unlink_button = Button::builder()
+            .icon_name("window-close-symbolic")
+            .tooltip_text("Unlink")
+            .build();
+        let panel_weak = self.downgrade();
+        unlink_button.connect_clicked(move |_| {
+            if let Some(panel) = panel_weak.upgrade() {
+                panel.emit_by_name::<()>("unlink-requested", &[&id]);
+            }
+        });
+        chip.append(&unlink_button);
+
+        chip
+    }
+
+    /// Fill the tags of the annotation being edited
+    pub fn set_tags(&self, tags: &[String]) {
+        self.imp()
+            .tags_entry
+            .set_text(&annotations::join_tags(tags));
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        annotations::parse_tags(&self.imp().tags_entry.text())
+    }
+
     /// Clear the panel and reset to initial state
     pub fn clear(&self) {
         let imp = self.imp();
         imp.selected_text_label.set_text("");
         imp.text_view.buffer().set_text("");
+        imp.tags_entry.set_text("");
         imp.annotation_id.set(None);
+        self.set_links(&[], &[]);
         imp.delete_button.set_visible(false);
+        self.set_color(AnnotationColor::default());
     }
 
     /// Focus the text input
@@ -331,6 +592,19 @@ impl AnnotationPanel {
     pub fn panel_height(&self) -> i32 {
         *self.imp().panel_height.borrow()
     }
+
+    /// Size the panel for where it is: a strip under the pages, a column right of
+    /// them, or the whole of its own window
+    pub fn set_dock_position(&self, position: PanelPosition) {
+        let imp = self.imp();
+        imp.resize_handle
+            .set_visible(position == PanelPosition::Bottom);
+        match position {
+            PanelPosition::Bottom => self.set_size_request(-1, self.panel_height()),
+            PanelPosition::Right => self.set_size_request(SIDE_PANEL_WIDTH, -1),
+            PanelPosition::Floating => self.set_size_request(-1, -1),
+        }
+    }
 }
 
 impl Default for AnnotationPanel {
@@ -1,15 +1,49 @@
+use gtk::gio;
 use gtk::glib;
+use gtk::glib::subclass::Signal;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
-use gtk::{Box, Button, Label, Orientation, PolicyType, Popover, ScrolledWindow};
-use std::cell::RefCell;
+use gtk::{
+    Box, Button, GestureDrag, Label, Notebook, Orientation, PolicyType, Popover, ScrolledWindow,
+    Separator, Spinner,
+};
+use std::cell::{Cell, RefCell};
+use std::sync::{Arc, OnceLock};
 
 use crate::services::dictionary;
-use crate::services::dictionary::Language;
+use crate::services::dictionary::{DictionarySource, Language};
+use crate::services::glossary;
+use crate::services::pdf_text;
 
-const POPOVER_WIDTH: i32 = 500;
-const POPOVER_HEIGHT: i32 = 200;
-const DEFINITION_POLL_MS: u64 = 500;
+/// Default maximum size the popover grows to when fitting its content
+pub const DEFAULT_MAX_WIDTH: i32 = 500;
+pub const DEFAULT_MAX_HEIGHT: i32 = 300;
+const MIN_WIDTH: i32 = 200;
+const MIN_HEIGHT: i32 = 60;
+/// Notebook page of the side-by-side comparison
+const COMPARE_PAGE: u32 = 1;
+
+/// Fetches the markup of the current lookup from one source
+type SourceFetch = Arc<dyn Fn(&DictionarySource) -> Option<String> + Send + Sync>;
+
+/// Definitions kept to go back to after following words in them
+const HISTORY_LIMIT: usize = 20;
+
+/// The current lookup, kept to repeat it against every source or for another word
+pub struct LookupRequest {
+    /// The word or phrase looked up
+    word: String,
+    not_found: String,
+    fetch: SourceFetch,
+    lang: Language,
+    source: DictionarySource,
+}
+
+/// A definition left by looking up a word inside it
+pub struct HistoryEntry {
+    markup: String,
+    request: LookupRequest,
+}
 
 mod imp {
     use super::*;
@@ -17,6 +51,25 @@ mod imp {
     #[derive(Default)]
     pub struct DefinitionPopover {
         pub label: RefCell<Option<Label>>,
+        pub scroller: RefCell<Option<ScrolledWindow>>,
+        pub notebook: RefCell<Option<Notebook>>,
+        /// One column per source on the compare page
+        pub compare_columns: RefCell<Option<Box>>,
+        pub compare_scroller: RefCell<Option<ScrolledWindow>>,
+        pub lookup_request: RefCell<Option<LookupRequest>>,
+        /// Previous definitions, the most recent last
+        pub history: RefCell<Vec<HistoryEntry>>,
+        pub back_button: RefCell<Option<Button>>,
+        /// Document whose glossary is checked before the dictionary
+        pub glossary_document: RefCell<Option<String>>,
+        /// Bumped on every new definition, so a slower earlier fetch doesn't replace it
+        pub generation: Cell<u32>,
+        /// Whether the compare page shows the current lookup
+        pub compared: Cell<bool>,
+        /// Spins while the definition is being fetched
+        pub spinner: RefCell<Option<Spinner>>,
+        /// Content size when a resize drag started
+        pub drag_origin: Cell<(i32, i32)>,
     }
 
     #[glib::object_subclass]
@@ -31,6 +84,27 @@ mod imp {
             self.parent_constructed();
             self.obj().setup_widgets();
         }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // Emitted when the user finished resizing, with (width, height)
+                    Signal::builder("resized")
+                        .param_types([i32::static_type(), i32::static_type()])
+                        .build(),
+                    // Emitted when a definition was found for the word the popover was
+                    // opened for, with (word, definition markup)
+                    Signal::builder("definition-found")
+                        .param_types([String::static_type(), String::static_type()])
+                        .build(),
+                    // Emitted to hear the word looked up, with (word, language code)
+                    Signal::builder("pronounce-requested")
+                        .param_types([String::static_type(), String::static_type()])
+                        .build(),
+                ]
+            })
+        }
     }
 
     impl WidgetImpl for DefinitionPopover {}
@@ -62,16 +136,34 @@ impl DefinitionPopover {
             .selectable(true)
             .build();
         label.add_css_class("definition-text");
+        self.setup_word_clicks(&label);
 
-        let scroller = ScrolledWindow::builder()
-            .hscrollbar_policy(PolicyType::Never)
-            .vscrollbar_policy(PolicyType::Automatic)
-            .vexpand_set(true)
-            .min_content_width(POPOVER_WIDTH)
-            .min_content_height(POPOVER_HEIGHT)
-            .child(&label)
+        // Fit the content, up to the maximum size
+        let scroller = Self::create_scroller(PolicyType::Never);
+        scroller.set_child(Some(&label));
+
+        let compare_columns = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
             .build();
-        scroller.add_css_class("definition-scroller");
+        compare_columns.add_css_class("definition-compare");
+        let compare_scroller = Self::create_scroller(PolicyType::Automatic);
+        compare_scroller.set_child(Some(&compare_columns));
+
+        let notebook = Notebook::builder().show_border(false).build();
+        notebook.add_css_class("definition-notebook");
+        notebook.append_page(&scroller, Some(&Label::new(Some("Definition"))));
+        notebook.append_page(&compare_scroller, Some(&Label::new(Some("Compare"))));
+
+        // Other sources are only queried once their page is opened
+        let popover_weak = self.downgrade();
+        notebook.connect_switch_page(move |_, _, page| {
+            if let Some(popover) = popover_weak.upgrade() {
+                if page == COMPARE_PAGE && !popover.imp().compared.get() {
+                    popover.compare_sources();
+                }
+            }
+        });
 
         let close_button = self.create_close_button();
 
@@ -85,13 +177,150 @@ impl DefinitionPopover {
             .build();
         container.add_css_class("definition-container");
 
-        container.append(&scroller);
-        container.append(&close_button);
+        let bottom_row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(4)
+            .build();
+        let spinner = Spinner::builder()
+            .valign(gtk::Align::Center)
+            .margin_top(8)
+            .visible(false)
+            .build();
+        spinner.add_css_class("definition-spinner");
+        bottom_row.append(&spinner);
+        let back_button = self.create_back_button();
+        bottom_row.append(&back_button);
+        bottom_row.append(&self.create_pronounce_button());
+        close_button.set_hexpand(true);
+        bottom_row.append(&close_button);
+        bottom_row.append(&self.create_resize_grip());
+
+        container.append(&notebook);
+        container.append(&bottom_row);
 
         self.set_child(Some(&container));
-        self.set_size_request(POPOVER_WIDTH, POPOVER_HEIGHT);
+        self.setup_back_key();
 
         self.imp().label.replace(Some(label));
+        self.imp().scroller.replace(Some(scroller));
+        self.imp().notebook.replace(Some(notebook));
+        self.imp().compare_columns.replace(Some(compare_columns));
+        self.imp().compare_scroller.replace(Some(compare_scroller));
+        self.imp().spinner.replace(Some(spinner));
+        self.imp().back_button.replace(Some(back_button));
+    }
+
+    fn create_scroller(hscrollbar_policy: PolicyType) -> ScrolledWindow {
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(hscrollbar_policy)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand_set(true)
+            .propagate_natural_width(true)
+            .propagate_natural_height(true)
+            .min_content_width(MIN_WIDTH)
+            .max_content_width(DEFAULT_MAX_WIDTH)
+            .max_content_height(DEFAULT_MAX_HEIGHT)
+            .build();
+        scroller.add_css_class("definition-scroller");
+        scroller
+    }
+
+    /// The scrollers of both pages, which share one size
+    fn scrollers(&self) -> Vec<ScrolledWindow> {
+        let imp = self.imp();
+        [&imp.scroller, &imp.compare_scroller]
+            .into_iter()
+            .filter_map(|scroller| scroller.borrow().clone())
+            .collect()
+    }
+
+    fn on_compare_page(&self) -> bool {
+        self.imp()
+            .notebook
+            .borrow()
+            .as_ref()
+            .and_then(|notebook| notebook.current_page())
+            == Some(COMPARE_PAGE)
+    }
+
+    /// The scroller of the page being shown
+    fn current_scroller(&self) -> Option<ScrolledWindow> {
+        let imp = self.imp();
+        if self.on_compare_page() {
+            imp.compare_scroller.borrow().clone()
+        } else {
+            imp.scroller.borrow().clone()
+        }
+    }
+
+    fn create_resize_grip(&self) -> Label {
+        let grip = Label::builder()
+            .label("◢")
+            .valign(gtk::Align::End)
+            .tooltip_text("Drag to resize")
+            .build();
+        grip.add_css_class("definition-resize-grip");
+        grip.set_cursor_from_name(Some("se-resize"));
+
+        let gesture = GestureDrag::new();
+
+        let popover_weak = self.downgrade();
+        gesture.connect_drag_begin(move |_, _, _| {
+            if let Some(popover) = popover_weak.upgrade() {
+                if let Some(scroller) = popover.current_scroller() {
+                    popover
+                        .imp()
+                        .drag_origin
+                        .set((scroller.width(), scroller.height()));
+                }
+            }
+        });
+
+        let popover_weak = self.downgrade();
+        gesture.connect_drag_update(move |_, offset_x, offset_y| {
+            if let Some(popover) = popover_weak.upgrade() {
+                let (width, height) = popover.imp().drag_origin.get();
+                popover.set_fixed_size(width + offset_x as i32, height + offset_y as i32);
+            }
+        });
+
+        let popover_weak = self.downgrade();
+        gesture.connect_drag_end(move |_, _, _| {
+            if let Some(popover) = popover_weak.upgrade() {
+                if let Some(scroller) = popover.current_scroller() {
+                    let size = (scroller.min_content_width(), scroller.min_content_height());
+                    popover.emit_by_name::<()>("resized", &[&size.0, &size.1]);
+                }
+            }
+        });
+
+        grip.add_controller(gesture);
+        grip
+    }
+
+    /// Let the popover fit its content up to the given size
+    pub fn set_max_size(&self, max_width: i32, max_height: i32) {
+        for scroller in self.scrollers() {
+            scroller.set_min_content_width(MIN_WIDTH.min(max_width));
+            scroller.set_min_content_height(-1);
+            scroller.set_max_content_width(max_width.max(MIN_WIDTH));
+            scroller.set_max_content_height(max_height.max(MIN_HEIGHT));
+        }
+    }
+
+    /// Use an exact content size instead of fitting the content
+    pub fn set_fixed_size(&self, width: i32, height: i32) {
+        let width = width.max(MIN_WIDTH);
+        let height = height.max(MIN_HEIGHT);
+        for scroller in self.scrollers() {
+            // Unset the maxima first, GTK rejects a minimum above the maximum
+            scroller.set_max_content_width(-1);
+            scroller.set_max_content_height(-1);
+            scroller.set_min_content_width(width);
+            scroller.set_min_content_height(height);
+            scroller.set_max_content_width(width);
+            scroller.set_max_content_height(height);
+        }
     }
 
     fn create_close_button(&self) -> Button {
@@ -108,38 +337,369 @@ impl DefinitionPopover {
         button
     }
 
+    fn create_back_button(&self) -> Button {
+        let button = Button::builder()
+            .icon_name("go-previous-symbolic")
+            .tooltip_text("Back (Backspace)")
+            .margin_top(8)
+            .sensitive(false)
+            .build();
+        button.add_css_class("definition-back-btn");
+
+        let popover_weak = self.downgrade();
+        button.connect_clicked(move |_| {
+            if let Some(popover) = popover_weak.upgrade() {
+                popover.go_back();
+            }
+        });
+
+        button
+    }
+
+    fn create_pronounce_button(&self) -> Button {
+        let button = Button::builder()
+            .icon_name("audio-volume-high-symbolic")
+            .tooltip_text("Pronounce")
+            .margin_top(8)
+            .build();
+        button.add_css_class("definition-pronounce-btn");
+
+        let popover_weak = self.downgrade();
+        button.connect_clicked(move |_| {
+            if let Some(popover) = popover_weak.upgrade() {
+                popover.pronounce();
+            }
+        });
+
+        button
+    }
+
+    /// Ask to hear the word or phrase currently looked up
+    fn pronounce(&self) {
+        let Some((word, language)) = self
+            .imp()
+            .lookup_request
+            .borrow()
+            .as_ref()
+            .map(|request| (request.word.clone(), request.lang.code().to_string()))
+        else {
+            return;
+        };
+        self.emit_by_name::<()>("pronounce-requested", &[&word, &language]);
+    }
+
+    /// Backspace and Alt+Left return to the previous definition
+    fn setup_back_key(&self) {
+        let key_controller = gtk::EventControllerKey::new();
+        let popover_weak = self.downgrade();
+        key_controller.connect_key_pressed(move |_, key, _, modifiers| {
+            let Some(popover) = popover_weak.upgrade() else {
+                return glib::Propagation::Proceed;
+            };
+            let back = key == gtk::gdk::Key::BackSpace
+                || (key == gtk::gdk::Key::Left
+                    && modifiers.contains(gtk::gdk::ModifierType::ALT_MASK));
+            if back && !popover.imp().history.borrow().is_empty() {
+                popover.go_back();
+                glib::Propagation::Stop
+            } else {
+                glib::Propagation::Proceed
+            }
+        });
+        self.add_controller(key_controller);
+    }
+
+    /// A click on a word of the definition looks it up in its place; drags still
+    /// select text
+    fn setup_word_clicks(&self, label: &Label) {
+        let gesture = gtk::GestureClick::new();
+        let popover_weak = self.downgrade();
+        gesture.connect_released(move |gesture, n_press, x, y| {
+            let Some(popover) = popover_weak.upgrade() else {
+                return;
+            };
+            let Some(label) = gesture.widget().and_downcast::<Label>() else {
+                return;
+            };
+            if n_press != 1 || label.selection_bounds().is_some() {
+                return;
+            }
+
+            let (offset_x, offset_y) = label.layout_offsets();
+            let (inside, byte_idx, _) = label.layout().xy_to_index(
+                (x as i32 - offset_x) * gtk::pango::SCALE,
+                (y as i32 - offset_y) * gtk::pango::SCALE,
+            );
+            if !inside {
+                return;
+            }
+            let text = label.text();
+            let char_idx = text
+                .get(..byte_idx as usize)
+                .map(|prefix| prefix.chars().count());
+            if let Some(word) = char_idx.and_then(|idx| pdf_text::extract_word_at_index(&text, idx))
+            {
+                popover.follow_word(word.original, word.lowercase);
+            }
+        });
+        label.add_controller(gesture);
+    }
+
+    /// Look up a word of the current definition, keeping the definition to go
+    /// back to
+    fn follow_word(&self, original_word: String, lookup_word: String) {
+        let imp = self.imp();
+        let Some(request) = imp.lookup_request.take() else {
+            return;
+        };
+        let (lang, source) = (request.lang, request.source.clone());
+        let markup = imp
+            .label
+            .borrow()
+            .as_ref()
+            .map(|label| label.label().to_string())
+            .unwrap_or_default();
+
+        {
+            let mut history = imp.history.borrow_mut();
+            history.push(HistoryEntry { markup, request });
+            if history.len() > HISTORY_LIMIT {
+                history.remove(0);
+            }
+        }
+        self.fetch_and_display(original_word, lookup_word, lang, source);
+    }
+
+    /// Return to the definition the last followed word was in
+    pub fn go_back(&self) {
+        let imp = self.imp();
+        let Some(entry) = imp.history.borrow_mut().pop() else {
+            return;
+        };
+
+        imp.generation.set(imp.generation.get().wrapping_add(1));
+        self.set_loading(false);
+        if let Some(label) = imp.label.borrow().as_ref() {
+            label.set_markup(&entry.markup);
+        }
+        imp.lookup_request.replace(Some(entry.request));
+        self.lookup_changed();
+    }
+
+    /// Refresh what depends on the current lookup: the back button and the
+    /// compare page
+    fn lookup_changed(&self) {
+        let imp = self.imp();
+        if let Some(button) = imp.back_button.borrow().as_ref() {
+            button.set_sensitive(!imp.history.borrow().is_empty());
+        }
+        imp.compared.set(false);
+        if self.on_compare_page() {
+            self.compare_sources();
+        }
+    }
+
+    /// Check the glossary of `pdf_path` (and the global one) on lookups
+    pub fn set_glossary_document(&self, pdf_path: Option<String>) {
+        self.imp().glossary_document.replace(pdf_path);
+    }
+
     pub fn show_at(&self, parent: &impl IsA<gtk::Widget>, x: f64, y: f64) {
         self.set_parent(parent.as_ref());
         self.set_pointing_to(Some(&gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
         self.popup();
     }
 
-    pub fn fetch_and_display(&self, original_word: String, lookup_word: String, lang: Language) {
-        let (sender, receiver) = std::sync::mpsc::channel::<String>();
+    pub fn fetch_and_display(
+        &self,
+        original_word: String,
+        lookup_word: String,
+        lang: Language,
+        source: DictionarySource,
+    ) {
+        let not_found = format!(
+            "Definition for <b>{}</b> not found.",
+            glib::markup_escape_text(&lookup_word)
+        );
+        self.fetch_from_source(
+            lookup_word.clone(),
+            not_found,
+            lang,
+            source,
+            Arc::new(move |source: &DictionarySource| {
+                dictionary::fetch_definition(&lookup_word, &original_word, lang, source)
+            }),
+        );
+    }
+
+    /// Look up a phrase along with each of its words
+    pub fn fetch_and_display_phrase(
+        &self,
+        original_phrase: String,
+        lookup_phrase: String,
+        lang: Language,
+        source: DictionarySource,
+    ) {
+        let not_found = format!(
+            "Definition for <b>{}</b> not found.",
+            glib::markup_escape_text(&lookup_phrase)
+        );
+        self.fetch_from_source(
+            lookup_phrase.clone(),
+            not_found,
+            lang,
+            source,
+            Arc::new(move |source: &DictionarySource| {
+                dictionary::fetch_phrase_definition(&lookup_phrase, &original_phrase, lang, source)
+            }),
+        );
+    }
+
+    /// Show the lookup from the selected source, keeping it for the compare page
+    fn fetch_from_source(
+        &self,
+        word: String,
+        not_found: String,
+        lang: Language,
+        source: DictionarySource,
+        fetch: SourceFetch,
+    ) {
+        let document = self.imp().glossary_document.borrow().clone();
+        let term = word.clone();
+        self.imp().lookup_request.replace(Some(LookupRequest {
+            word,
+            not_found: not_found.clone(),
+            fetch: fetch.clone(),
+            lang,
+            source: source.clone(),
+        }));
+        self.lookup_changed();
 
-        std::thread::spawn(move || {
-            let definition = dictionary::fetch_definition(&lookup_word, &original_word, lang)
-                .unwrap_or_else(|| {
-                    format!("Definition for <b>{lookup_word}</b> not found.").to_string()
-                });
-            let _ = sender.send(definition);
+        // The user's own explanation comes first, above the dictionary's entry
+        self.fetch_markup(not_found, move || {
+            let glossary = glossary::lookup(&term, document.as_deref())
+                .map_err(|e| eprintln!("Failed to read the glossary: {}", e))
+                .ok()
+                .flatten()
+                .map(|entry| entry.markup());
+            match (glossary, fetch(&source)) {
+                (Some(glossary), Some(definition)) => {
+                    Some(format!("{}\n\n{}", glossary, definition))
+                }
+                (glossary, definition) => glossary.or(definition),
+            }
         });
+    }
+
+    /// Repeat the current lookup against every configured source at once, each
+    /// in its own column as soon as it answers
+    fn compare_sources(&self) {
+        let imp = self.imp();
+        let Some(columns) = imp.compare_columns.borrow().clone() else {
+            return;
+        };
+        while let Some(child) = columns.first_child() {
+            columns.remove(&child);
+        }
+
+        let request = imp.lookup_request.borrow();
+        let Some(request) = request.as_ref() else {
+            return;
+        };
+        imp.compared.set(true);
+
+        for (i, source) in request.source.configured().iter().enumerate() {
+            if i > 0 {
+                columns.append(&Separator::new(Orientation::Vertical));
+            }
+
+            let heading = Label::builder().label(source.label()).xalign(0.0).build();
+            heading.add_css_class("definition-compare-heading");
+            let label = Label::builder()
+                .label("Loading definition...")
+                .wrap(true)
+                .xalign(0.0)
+                .yalign(0.0)
+                .selectable(true)
+                .width_request(MIN_WIDTH)
+                .build();
+            label.add_css_class("definition-text");
+
+            let column = Box::builder()
+                .orientation(Orientation::Vertical)
+                .spacing(4)
+                .hexpand(true)
+                .build();
+            column.append(&heading);
+            column.append(&label);
+            columns.append(&column);
+
+            let fetch = request.fetch.clone();
+            let source = source.clone();
+            let not_found = request.not_found.clone();
+            glib::spawn_future_local(async move {
+                let definition = gio::spawn_blocking(move || fetch(&source))
+                    .await
+                    .ok()
+                    .flatten()
+                    .unwrap_or(not_found);
+                label.set_markup(&definition);
+            });
+        }
+    }
+
+    /// Show a message instead of a definition
+    pub fn show_message(&self, message: &str) {
+        if let Some(label) = self.imp().label.borrow().as_ref() {
+            label.set_text(message);
+        }
+    }
 
-        let label_weak = self.imp().label.borrow().as_ref().map(|l| l.downgrade());
-
-        if let Some(label_weak) = label_weak {
-            glib::timeout_add_local(
-                std::time::Duration::from_millis(DEFINITION_POLL_MS),
-                move || {
-                    if let Ok(definition) = receiver.try_recv() {
-                        if let Some(label) = label_weak.upgrade() {
-                            label.set_markup(&definition);
-                        }
-                        return glib::ControlFlow::Break;
+    /// Show the markup `fetch` returns, or `not_found` when it returns None
+    fn fetch_markup(
+        &self,
+        not_found: String,
+        fetch: impl FnOnce() -> Option<String> + Send + 'static,
+    ) {
+        self.set_loading(true);
+        let generation = self.imp().generation.get().wrapping_add(1);
+        self.imp().generation.set(generation);
+
+        // Resumed on the main loop as soon as the fetch thread returns
+        let popover_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let definition = gio::spawn_blocking(fetch).await.ok().flatten();
+
+            if let Some(popover) = popover_weak
+                .upgrade()
+                .filter(|popover| popover.imp().generation.get() == generation)
+            {
+                popover.set_loading(false);
+                if let Some(label) = popover.imp().label.borrow().as_ref() {
+                    label.set_markup(definition.as_deref().unwrap_or(&not_found));
+                }
+
+                // Words followed inside definitions aren't from the document
+                let word = popover
+                    .imp()
+                    .lookup_request
+                    .borrow()
+                    .as_ref()
+                    .map(|request| request.word.clone());
+                if let (Some(word), Some(definition)) = (word, definition) {
+                    if popover.imp().history.borrow().is_empty() {
+                        popover.emit_by_name::<()>("definition-found", &[&word, &definition]);
                     }
-                    glib::ControlFlow::Continue
-                },
-            );
+                }
+            }
+        });
+    }
+
+    /// Show the spinner while a definition is being fetched
+    fn set_loading(&self, loading: bool) {
+        if let Some(spinner) = self.imp().spinner.borrow().as_ref() {
+            spinner.set_visible(loading);
+            spinner.set_spinning(loading);
         }
     }
 }
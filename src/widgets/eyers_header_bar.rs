@@ -2,9 +2,14 @@ use glib::Properties;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
-use gtk::{Button, HeaderBar, ToggleButton};
+use gtk::{Button, HeaderBar, MenuButton, ToggleButton};
 use std::cell::Cell;
 
+use super::recent_files_popover::RecentFilesPopover;
+
+/// Buttons per row of the menu the header bar collapses into
+const MENU_COLUMNS: usize = 5;
+
 mod imp {
     use super::*;
 
@@ -13,10 +18,23 @@ mod imp {
     pub struct EyersHeaderBar {
         pub header_bar: HeaderBar,
         pub open_button: Button,
+        pub recent_button: MenuButton,
+        pub recent_popover: RecentFilesPopover,
         pub settings_button: Button,
+        pub about_button: Button,
+        pub reload_button: Button,
+        pub flashcards_button: Button,
         pub annotate_button: Button,
         pub definitions_toggle: ToggleButton,
         pub translate_toggle: ToggleButton,
+        pub two_page_toggle: ToggleButton,
+        pub paged_toggle: ToggleButton,
+        pub rotate_left_button: Button,
+        pub rotate_right_button: Button,
+        /// Holds the buttons that don't fit in narrow windows
+        pub menu_button: MenuButton,
+        pub menu_grid: gtk::Grid,
+        pub compact: Cell<bool>,
 
         #[property(get, set, default = false)]
         pub definitions_enabled: Cell<bool>,
@@ -68,6 +86,14 @@ impl EyersHeaderBar {
         imp.open_button.add_css_class("header-open-btn");
         imp.header_bar.pack_start(&imp.open_button);
 
+        // Recent files button (icon)
+        imp.recent_button
+            .set_icon_name("document-open-recent-symbolic");
+        imp.recent_button.set_tooltip_text(Some("Recent files (O)"));
+        imp.recent_button.add_css_class("header-recent-btn");
+        imp.recent_button.set_popover(Some(&imp.recent_popover));
+        imp.header_bar.pack_start(&imp.recent_button);
+
         // Definitions toggle button (icon)
         imp.definitions_toggle
             .set_icon_name("accessories-dictionary-symbolic");
@@ -85,12 +111,74 @@ impl EyersHeaderBar {
         imp.annotate_button.set_sensitive(false); // Disabled until in visual mode with selection
         imp.header_bar.pack_start(&imp.annotate_button);
 
+        // Two-page layout toggle (icon)
+        imp.two_page_toggle.set_icon_name("view-dual-symbolic");
+        imp.two_page_toggle
+            .set_tooltip_text(Some("Two-page layout (D)"));
+        imp.two_page_toggle.add_css_class("header-two-page-toggle");
+        imp.header_bar.pack_start(&imp.two_page_toggle);
+
+        // Paged mode toggle (icon), one page at a time
+        imp.paged_toggle.set_icon_name("view-paged-symbolic");
+        imp.paged_toggle
+            .set_tooltip_text(Some("One page at a time (P)"));
+        imp.paged_toggle.add_css_class("header-paged-toggle");
+        imp.header_bar.pack_start(&imp.paged_toggle);
+
+        // Rotate buttons (icons), turning every page
+        imp.rotate_left_button
+            .set_icon_name("object-rotate-left-symbolic");
+        imp.rotate_left_button
+            .set_tooltip_text(Some("Rotate pages counterclockwise (<)"));
+        imp.rotate_left_button.add_css_class("header-rotate-btn");
+        imp.header_bar.pack_start(&imp.rotate_left_button);
+
+        imp.rotate_right_button
+            .set_icon_name("object-rotate-right-symbolic");
+        imp.rotate_right_button
+            .set_tooltip_text(Some("Rotate pages clockwise (>)"));
+        imp.rotate_right_button.add_css_class("header-rotate-btn");
+        imp.header_bar.pack_start(&imp.rotate_right_button);
+
         // Settings button (icon)
         imp.settings_button.set_icon_name("emblem-system-symbolic");
         imp.settings_button.set_tooltip_text(Some("Settings"));
         imp.settings_button.add_css_class("header-settings-btn");
         imp.header_bar.pack_start(&imp.settings_button);
 
+        // Menu of the buttons moved out of the way in narrow windows
+        imp.menu_button.set_icon_name("open-menu-symbolic");
+        imp.menu_button.set_tooltip_text(Some("More"));
+        imp.menu_button.add_css_class("header-menu-btn");
+        imp.menu_grid.set_column_spacing(4);
+        imp.menu_grid.set_row_spacing(4);
+        imp.menu_button
+            .set_popover(Some(&gtk::Popover::builder().child(&imp.menu_grid).build()));
+        imp.menu_button.set_visible(false);
+        imp.header_bar.pack_end(&imp.menu_button);
+
+        // About button (icon)
+        imp.about_button.set_icon_name("help-about-symbolic");
+        imp.about_button.set_tooltip_text(Some("About Eyers"));
+        imp.about_button.add_css_class("header-about-btn");
+        imp.header_bar.pack_end(&imp.about_button);
+
+        // Reload button (icon)
+        imp.reload_button.set_icon_name("view-refresh-symbolic");
+        imp.reload_button
+            .set_tooltip_text(Some("Reload document (:reload)"));
+        imp.reload_button.add_css_class("header-reload-btn");
+        imp.reload_button.set_sensitive(false); // Disabled until a document is open
+        imp.header_bar.pack_end(&imp.reload_button);
+
+        // Flashcards export button (icon)
+        imp.flashcards_button
+            .set_icon_name("document-save-as-symbolic");
+        imp.flashcards_button
+            .set_tooltip_text(Some("Export flashcards for Anki"));
+        imp.flashcards_button.add_css_class("header-flashcards-btn");
+        imp.header_bar.pack_end(&imp.flashcards_button);
+
         // Translate toggle button (disabled for now - TODO: implement translation feature)
         // imp.translate_toggle.set_icon_name("...");
         // imp.translate_toggle.set_active(false);
@@ -137,6 +225,69 @@ impl EyersHeaderBar {
         });
     }
 
+    /// Buttons packed at the start that go into the menu in narrow windows, in order
+    fn collapsible_start_buttons(&self) -> [gtk::Widget; 7] {
+        let imp = self.imp();
+        [
+            imp.definitions_toggle.clone().upcast(),
+            imp.annotate_button.clone().upcast(),
+            imp.two_page_toggle.clone().upcast(),
+            imp.paged_toggle.clone().upcast(),
+            imp.rotate_left_button.clone().upcast(),
+            imp.rotate_right_button.clone().upcast(),
+            imp.settings_button.clone().upcast(),
+        ]
+    }
+
+    /// Buttons packed at the end that go into the menu, from the right edge inward
+    fn collapsible_end_buttons(&self) -> [gtk::Widget; 3] {
+        let imp = self.imp();
+        [
+            imp.about_button.clone().upcast(),
+            imp.reload_button.clone().upcast(),
+            imp.flashcards_button.clone().upcast(),
+        ]
+    }
+
+    /// Move the toggles and most buttons into a menu, leaving room for the
+    /// window buttons in narrow windows, or put them back in the bar
+    pub fn set_compact(&self, compact: bool) {
+        let imp = self.imp();
+        if imp.compact.replace(compact) == compact {
+            return;
+        }
+
+        let start = self.collapsible_start_buttons();
+        let end = self.collapsible_end_buttons();
+        if compact {
+            for (index, button) in start.iter().chain(end.iter().rev()).enumerate() {
+                imp.header_bar.remove(button);
+                imp.menu_grid.attach(
+                    button,
+                    (index % MENU_COLUMNS) as i32,
+                    (index / MENU_COLUMNS) as i32,
+                    1,
+                    1,
+                );
+            }
+        } else {
+            // Packed again in their order, after the buttons that stayed
+            for button in &start {
+                imp.menu_grid.remove(button);
+                imp.header_bar.pack_start(button);
+            }
+            for button in &end {
+                imp.menu_grid.remove(button);
+                imp.header_bar.pack_end(button);
+            }
+        }
+
+        imp.menu_button.set_visible(compact);
+        if let Some(title) = imp.header_bar.title_widget() {
+            title.set_visible(!compact);
+        }
+    }
+
     /// Returns the HeaderBar widget to be used with set_titlebar()
     pub fn widget(&self) -> &HeaderBar {
         &self.imp().header_bar
@@ -146,10 +297,30 @@ impl EyersHeaderBar {
         &self.imp().open_button
     }
 
+    pub fn recent_button(&self) -> &MenuButton {
+        &self.imp().recent_button
+    }
+
+    pub fn recent_popover(&self) -> &RecentFilesPopover {
+        &self.imp().recent_popover
+    }
+
     pub fn settings_button(&self) -> &Button {
         &self.imp().settings_button
     }
 
+    pub fn about_button(&self) -> &Button {
+        &self.imp().about_button
+    }
+
+    pub fn reload_button(&self) -> &Button {
+        &self.imp().reload_button
+    }
+
+    pub fn flashcards_button(&self) -> &Button {
+        &self.imp().flashcards_button
+    }
+
     pub fn annotate_button(&self) -> &Button {
         &self.imp().annotate_button
     }
@@ -161,6 +332,22 @@ impl EyersHeaderBar {
     pub fn translate_toggle(&self) -> &ToggleButton {
         &self.imp().translate_toggle
     }
+
+    pub fn two_page_toggle(&self) -> &ToggleButton {
+        &self.imp().two_page_toggle
+    }
+
+    pub fn paged_toggle(&self) -> &ToggleButton {
+        &self.imp().paged_toggle
+    }
+
+    pub fn rotate_left_button(&self) -> &Button {
+        &self.imp().rotate_left_button
+    }
+
+    pub fn rotate_right_button(&self) -> &Button {
+        &self.imp().rotate_right_button
+    }
 }
 
 impl Default for EyersHeaderBar {
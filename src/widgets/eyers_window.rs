@@ -1,31 +1,207 @@
 use gtk::gio;
 use gtk::glib;
 use gtk::glib::closure_local;
+use gtk::graphene;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::{ApplicationWindow, Box, Orientation, Paned, PolicyType, ScrolledWindow};
 use pdfium_render::prelude::*;
 use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
+use crate::backend::{self, DocumentBackend, EpubBackend};
+use crate::modes::action_recorder;
+use crate::modes::command;
 use crate::modes::{
-    AppMode, KeyAction, KeyHandler, KeyResult, ScrollDir, WordCursor, handle_normal_mode_key,
+    ActionRecorder, AppMode, Command, CropAction, DocumentTab, JumpList, KeyAction, KeyHandler,
+    KeyResult, ScrollDir, TabList, VisitHistory, WordCursor, handle_normal_mode_key,
     handle_post_global_key, handle_pre_global_key, handle_toc_key, handle_visual_mode_key,
 };
-use crate::services::annotations::find_next_annotation_at_position;
-use crate::services::annotations::find_prev_annotation_at_position;
-use crate::services::annotations::{self, Annotation};
-use crate::services::dictionary::Language;
-use crate::services::pdf_text::calculate_picture_offset;
-use crate::text_map::{TextMapCache, find_word_on_line_starting_with};
+use crate::services::annotations::{
+    self, Annotation, AnnotationColor, AnnotationError, AnnotationId, ExportOptions, PdfHighlight,
+};
+use crate::services::bookmarks::BookmarkEntry;
+use crate::services::citation::{self, CitationError};
+use crate::services::crop::{CropEdge, PageCrop};
+use crate::services::dictionary::{self, Language};
+use crate::services::flashcards::{self, Flashcard};
+use crate::services::glossary;
+use crate::services::heat_map;
+use crate::services::http_api::{ApiEvent, ApiServer};
+use crate::services::ink::{self, InkDrawing, InkTool};
+use crate::services::journal;
+use crate::services::launcher;
+use crate::services::library_stats::{self, LibraryStats};
+use crate::services::marks::{self, Mark};
+use crate::services::ocr::{self, OcrError, OcrWord};
+use crate::services::page_labels;
+use crate::services::pdf_text::{
+    CLICK_TOLERANCE, MAX_ZOOM, MIN_ZOOM, ZoomMode, calculate_click_coordinates_with_offset,
+    calculate_picture_offset, extract_copied_term, extract_sentence_at, page_text,
+    pdf_point_to_screen,
+};
+use crate::services::positions;
+use crate::services::post_export::{self, ExportedFile};
+use crate::services::reading_log::{self, ReadingEvent};
+use crate::services::reading_progress::{self, ReadingSpeed};
+use crate::services::recent_files;
+use crate::services::rotation::Rotation;
+use crate::services::session::{self, SessionState};
+use crate::services::settings::{self, PanelPosition, Settings};
+use crate::services::startup::{self, StartupDefaults, StartupMode};
+use crate::services::synctex::{self, PagePosition, SyncTex};
+use crate::services::theme::{self, HighlightPalette, Theme};
+use crate::services::translation::TranslatorConfig;
+use crate::services::tts::{self, Speaker, TtsState};
+use crate::services::web_search::{self, SearchEngine};
+use crate::text_map::page_text_map::PageTextMap;
+use crate::text_map::{CopyJoinStyle, ReflowText, TextMapCache, find_word_on_line_starting_with};
 use crate::widgets::toc_panel::TocMode;
 use crate::widgets::{
-    AnnotationPanel, EyersHeaderBar, HighlightRect, PdfView, PendingKeyBox, SettingsWindow,
-    StatusBar, TocPanel, TranslationPanel,
+    AboutWindow, AnnotationPanel, BionicWord, EyersHeaderBar, HeatMapWindow, HighlightRect,
+    HistoryWindow, InkMark, LibraryStatsWindow, PdfView, PendingKeyBox, PositionsWindow,
+    PropertiesWindow, RecentFilesPopover, ReflowView, SettingsWindow, StatusBar, TabBar, TocPanel,
+    TranslationPanel, UiFont, WordBoxes, render_snippet,
 };
 
 const DEFAULT_VIEWPORT_OFFSET: f64 = 0.2;
+const JOURNAL_INTERVAL_SECS: u32 = 5;
+const MOUSE_BUTTON_BACK: u32 = 8;
+const MOUSE_BUTTON_FORWARD: u32 = 9;
+/// Margin kept around the selection when exporting it as a snippet
+const SNIPPET_PADDING: f32 = 16.0;
+/// Pages searched for a DOI or ISBN, after the metadata
+const CITATION_SEARCH_PAGES: usize = 3;
+/// Length of the "Last Week" range of the annotations export, in seconds
+const EXPORT_WEEK_SECS: i64 = 7 * 24 * 60 * 60;
+/// How long an annotation reached with ]a/[a stays emphasized
+const ANNOTATION_FLASH_MS: u64 = 600;
+/// Inactivity before distraction-free mode hides the pointer and the bars
+const DISTRACTION_FREE_IDLE_SECS: u32 = 3;
+/// How long the cursor stays on a line before its definitions are prefetched
+const PREFETCH_DELAY_MS: u64 = 400;
+/// Wait for the view to stop resizing before fitting the zoom to it
+const ZOOM_FIT_DELAY_MS: u64 = 150;
+/// Length of the fade between pages in presentation mode
+const PRESENTATION_FADE_MS: i64 = 250;
+/// How far an edge of the crop box moves per key press, in points
+const CROP_STEP_PTS: f64 = 4.0;
+/// Windows narrower than this get the compact layout, e.g. tiled to half a laptop screen
+const COMPACT_WIDTH: i32 = 700;
+/// Width of the table of contents sliding over the pages in the compact layout
+const TOC_SHEET_WIDTH: i32 = 300;
+
+/// Highlight rects of each annotation, valid for a single zoom level.
+/// An entry is recomputed only when the zoom changes or its annotation range changes.
+/// Rotating pages clears the whole cache.
+#[derive(Debug, Default)]
+pub(super) struct AnnotationRectCache {
+    zoom_level: f64,
+    entries: HashMap<AnnotationId, AnnotationRects>,
+}
+
+#[derive(Debug)]
+struct AnnotationRects {
+    /// (start_page, start_word, end_page, end_word) the rects were computed for
+    range: (usize, usize, usize, usize),
+    rects: Vec<(usize, HighlightRect)>,
+}
+
+impl AnnotationRectCache {
+    /// Drop everything on zoom change, and entries of annotations that no longer exist
+    fn retain(&mut self, zoom_level: f64, annotations: &[Annotation]) {
+        if self.zoom_level != zoom_level {
+            self.entries.clear();
+            self.zoom_level = zoom_level;
+        }
+        self.entries
+            .retain(|id, _| annotations.iter().any(|ann| ann.id == *id));
+    }
+
+    fn get_or_compute(
+        &mut self,
+        ann: &Annotation,
+        compute: impl FnOnce() -> Vec<(usize, HighlightRect)>,
+    ) -> &[(usize, HighlightRect)] {
+        let range = (ann.start_page, ann.start_word, ann.end_page, ann.end_word);
+        let stale = self
+            .entries
+            .get(&ann.id)
+            .is_none_or(|entry| entry.range != range);
+
+        if stale {
+            self.entries.insert(
+                ann.id,
+                AnnotationRects {
+                    range,
+                    rects: compute(),
+                },
+            );
+        }
+
+        &self.entries[&ann.id].rects
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// A drawing being made with the pointer, in coordinates of its page's picture
+#[derive(Debug, Clone)]
+pub(super) struct InkStroke {
+    page_index: usize,
+    start: (f64, f64),
+    points: Vec<(f64, f64)>,
+}
+
+/// What presentation mode hid and changed, restored when it ends
+#[derive(Debug, Clone)]
+pub(super) struct PresentationState {
+    /// Bars and panels hidden, with whether each was shown
+    chrome: Vec<(gtk::Widget, bool)>,
+    zoom_mode: ZoomMode,
+    paged: bool,
+    two_page: bool,
+    fullscreen: bool,
+}
+
+/// A tab's own view, and the state of the window that belongs to its document,
+/// kept while another tab is shown
+pub(super) struct TabState {
+    view: PdfView,
+    scrolled_window: ScrolledWindow,
+    app_mode: AppMode,
+    text_cache: Option<TextMapCache>,
+    search_matches: Vec<(WordCursor, WordCursor)>,
+    search_index: Option<usize>,
+    jump_list: JumpList,
+    visit_history: VisitHistory,
+    synctex: Option<SyncTex>,
+    page_labels: Option<Vec<Option<String>>>,
+    document_language: Option<Language>,
+}
+
+impl TabState {
+    fn new(view: PdfView, scrolled_window: ScrolledWindow) -> Self {
+        Self {
+            view,
+            scrolled_window,
+            app_mode: AppMode::exit_to_normal(),
+            text_cache: None,
+            search_matches: Vec::new(),
+            search_index: None,
+            jump_list: JumpList::default(),
+            visit_history: VisitHistory::default(),
+            synctex: None,
+            page_labels: None,
+            document_language: None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub(super) struct MouseSelectionState {
@@ -41,13 +217,44 @@ mod imp {
     pub struct EyersWindow {
         pub header_bar: EyersHeaderBar,
         pub status_bar: StatusBar,
-        pub pdf_view: PdfView,
+        /// View of the document of the active tab
+        pub pdf_view: RefCell<PdfView>,
         pub toc_panel: TocPanel,
+        /// One tab per open document, above the pages
+        pub tab_bar: TabBar,
+        pub(super) tabs: RefCell<TabList<TabState>>,
+        /// Holds the view of each tab, scrolled on its own, showing the active one
+        pub tab_stack: gtk::Stack,
+        /// Scrolled window of the active tab's view
         pub scrolled_window: RefCell<Option<ScrolledWindow>>,
+        /// Second view of the document, scrolled on its own, shown by the split view
+        pub split_view: PdfView,
+        pub split_scrolled_window: RefCell<Option<ScrolledWindow>>,
+        /// Properties the second view follows from the active tab's view
+        pub split_bindings: RefCell<Vec<glib::Binding>>,
+        /// Holds the pages and, when split, the second view next to or under them
+        pub split_paned: RefCell<Option<Paned>>,
+        pub split_fit_pending: Cell<bool>,
+        /// The document text wrapped to the window, shown instead of the pages
+        /// in reflow mode
+        pub reflow_view: ReflowView,
+        /// Switches between the pages and the reflowed text
+        pub view_stack: gtk::Stack,
         pub translation_panel: TranslationPanel,
         pub annotation_panel: AnnotationPanel,
         pub pdfium: RefCell<Option<&'static Pdfium>>,
         pub paned: RefCell<Option<Paned>>,
+        /// Pages, status bar and the panels docked under them
+        pub main_box: RefCell<Option<Box>>,
+        /// Pages and table of contents, and the annotation panel when docked right
+        pub content_row: RefCell<Option<Box>>,
+        pub annotation_panel_position: Cell<PanelPosition>,
+        /// Window holding the annotation panel when it floats
+        pub annotation_dialog: RefCell<Option<gtk::Window>>,
+        /// Whether the window is narrow enough for the compact layout
+        pub compact: Cell<bool>,
+        /// Holds the table of contents over the pages in the compact layout
+        pub toc_sheet: gtk::Revealer,
         pub app_mode: RefCell<AppMode>,
         pub text_cache: RefCell<Option<TextMapCache>>,
         /// Toast revealer for copy feedback
@@ -60,14 +267,137 @@ mod imp {
         pub pendingkey_box: PendingKeyBox,
         /// Dictionary language setting
         pub dictionary_language: Cell<Language>,
+        /// Whether ]a/[a wrap around the ends of the document
+        pub annotation_wrap: Cell<bool>,
+        /// Directory annotations are live-synced to, empty when off
+        pub notes_sync_dir: RefCell<String>,
+        /// Local HTTP API, while enabled in the settings
+        pub api_server: RefCell<Option<ApiServer>>,
+        /// Hide the pointer and the bars while reading without input
+        pub distraction_free: Cell<bool>,
+        /// Visibility of the header and status bars before distraction-free
+        /// mode hid them, None while they are shown
+        pub hidden_chrome: Cell<Option<(bool, bool)>>,
+        /// Timer hiding the chrome after inactivity
+        pub idle_timeout: RefCell<Option<glib::SourceId>>,
+        /// What presentation mode changed, None while not presenting
+        pub(super) presentation: RefCell<Option<PresentationState>>,
+        /// Whether pages fade in when turned in presentation mode
+        pub presentation_fade: Cell<bool>,
+        /// Fade of the page being turned to
+        pub presentation_fade_tick: RefCell<Option<gtk::TickCallbackId>>,
+        /// Whether keys move the edges of the crop box (`:crop edit`)
+        pub crop_adjusting: Cell<bool>,
+        /// Whether the rare words of the cursor's line are looked up ahead of time
+        pub prefetch_definitions: Cell<bool>,
+        /// Whether prefetching may query the online dictionary
+        pub prefetch_online: Cell<bool>,
+        /// (page, line) of the cursor the last prefetch was scheduled for
+        pub prefetch_line: Cell<Option<(usize, usize)>>,
+        /// Timer starting the prefetch once the cursor stays on a line
+        pub prefetch_timeout: RefCell<Option<glib::SourceId>>,
+        /// Last pointer position, to tell real movement from relayouts
+        pub last_pointer: Cell<Option<(f64, f64)>>,
+        /// Height the cursor lands at after jumps, as a fraction of the viewport
+        pub viewport_anchor: Cell<f64>,
+        /// Language the open document overrides the global setting with
+        pub document_language: Cell<Option<Language>>,
+        /// How multi-line selections are joined when copied
+        pub copy_join_style: Cell<CopyJoinStyle>,
+        /// Experimental bionic reading mode (first half of each word bolded)
+        pub bionic_reading: Cell<bool>,
+        /// Debug view outlining the words and lines of the text map
+        pub show_word_boxes: Cell<bool>,
+        /// A zoom fit is waiting for the view to stop resizing
+        pub zoom_fit_pending: Cell<bool>,
+        /// Whether `[count]gg` counts in printed page labels
+        pub use_page_labels: Cell<bool>,
+        /// Page labels of the open document, read on the first labelled jump
+        pub page_labels: RefCell<Option<Vec<Option<String>>>>,
+        /// Options used when exporting annotations to markdown
+        pub export_options: Cell<ExportOptions>,
         /// Current PDF file path (for annotations)
         pub current_pdf_path: RefCell<Option<String>>,
         /// Loaded annotations for the current PDF
         pub annotations: RefCell<Vec<Annotation>>,
+        /// "See also" links between the loaded annotations
+        pub annotation_links: RefCell<Vec<(AnnotationId, AnnotationId)>>,
+        /// Pages whose overlay currently shows cursor or selection highlights
+        pub highlighted_pages: RefCell<HashSet<usize>>,
+        /// Whether a cursor/selection highlight update is queued
+        pub highlight_update_pending: Cell<bool>,
+        /// Selection last published to the primary clipboard
+        pub primary_selection: Cell<Option<(WordCursor, WordCursor)>>,
+        /// Whether publishing the selection to the primary clipboard is queued
+        pub primary_selection_pending: Cell<bool>,
+        /// Reads text aloud
+        pub speaker: RefCell<Speaker>,
+        /// Page read next once the speaker finishes, when reading on from the cursor
+        pub tts_next_page: Cell<Option<usize>>,
+        /// Cached highlight rects of the loaded annotations
+        pub(super) annotation_rects: RefCell<AnnotationRectCache>,
         /// Pending annotation state: (start, end) cursors being annotated
         pub pending_annotation: RefCell<Option<(WordCursor, WordCursor)>>,
+        /// Whether the annotation in the panel is being written to the database
+        pub saving_annotation: Cell<bool>,
         /// Mouse selection state for drag-to-select
         pub mouse_selection_state: RefCell<MouseSelectionState>,
+        /// What drags draw while the pdf view is drawing
+        pub ink_tool: Cell<InkTool>,
+        /// Color of new drawings
+        pub ink_color: Cell<AnnotationColor>,
+        /// Drawings of the current PDF
+        pub ink_drawings: RefCell<Vec<InkDrawing>>,
+        /// Drawing being made, between the press and the release
+        pub(super) ink_stroke: RefCell<Option<InkStroke>>,
+        /// Scanned pages waiting to be recognized by OCR, one at a time
+        pub ocr_queue: RefCell<VecDeque<usize>>,
+        /// Page being recognized on the worker thread
+        pub ocr_page: Cell<Option<usize>>,
+        /// Font of popover and panel text
+        pub ui_font: RefCell<UiFont>,
+        /// Stylesheet applying `ui_font`, layered above the app stylesheet
+        pub ui_font_provider: gtk::CssProvider,
+        pub theme: Cell<Theme>,
+        /// Stylesheet of `theme`, layered above the app stylesheet
+        pub theme_provider: gtk::CssProvider,
+        /// Contents of ~/.config/eyers/style.css, layered above everything else
+        pub user_css: RefCell<String>,
+        pub user_css_provider: gtk::CssProvider,
+        /// Reloads the user stylesheet when it is saved
+        pub user_css_monitor: RefCell<Option<gio::FileMonitor>>,
+        /// Journal left behind by a previous run that did not exit cleanly
+        pub recovered_journal: RefCell<Option<journal::JournalEntry>>,
+        /// Last state written to the journal, to skip unchanged writes
+        pub last_journal: RefCell<Option<journal::JournalEntry>>,
+        /// BibTeX citations fetched this session, by PDF path
+        pub citations: RefCell<HashMap<String, String>>,
+        /// SyncTeX data of the current PDF, when LaTeX wrote it next to the file
+        pub synctex: RefCell<Option<SyncTex>>,
+        /// Command run on reverse search, with `%{input}`, `%{line}` and `%{column}`
+        pub synctex_editor: RefCell<String>,
+        /// Pages of the current PDF read so far, for chapter progress in the TOC
+        pub read_pages: RefCell<HashSet<usize>>,
+        /// Pages read one after the other since the last jump, logged once it ends
+        pub reading_range: Cell<Option<(usize, usize)>>,
+        /// Recent reading speed, for the time left shown in the status bar
+        pub reading_speed: RefCell<ReadingSpeed>,
+        /// Matches of the last `/` search, as inclusive word ranges in document order
+        pub search_matches: RefCell<Vec<(WordCursor, WordCursor)>>,
+        /// Match last jumped to with `n`/`N`
+        pub search_index: Cell<Option<usize>>,
+        /// Positions left by jumps, for back/forward navigation
+        pub jump_list: RefCell<JumpList>,
+        /// Spots left by jumps, kept in visiting order for the `H` timeline
+        pub visit_history: RefCell<VisitHistory>,
+        /// Positions stored with `m{a-z}` in the open document
+        pub marks: RefCell<Vec<Mark>>,
+        /// Key actions and mode transitions kept for bug reports, while `:keylog` is on
+        pub action_recorder: RefCell<ActionRecorder>,
+        /// Lookup mode, bars and dictionary source applied at startup
+        pub startup_defaults: RefCell<StartupDefaults>,
+        /// Preferences chosen in the settings window, saved for the next start
+        pub settings: RefCell<Settings>,
     }
 
     impl Default for EyersWindow {
@@ -84,13 +414,29 @@ mod imp {
             Self {
                 header_bar: EyersHeaderBar::new(),
                 status_bar: StatusBar::new(),
-                pdf_view: PdfView::new(),
+                pdf_view: RefCell::new(PdfView::new()),
                 toc_panel: TocPanel::new(),
+                tab_bar: TabBar::new(),
+                tabs: RefCell::new(TabList::default()),
+                tab_stack: gtk::Stack::new(),
                 scrolled_window: RefCell::new(None),
+                split_view: PdfView::new(),
+                split_scrolled_window: RefCell::new(None),
+                split_bindings: RefCell::new(Vec::new()),
+                split_paned: RefCell::new(None),
+                split_fit_pending: Cell::new(false),
+                reflow_view: ReflowView::new(),
+                view_stack: gtk::Stack::new(),
                 translation_panel: TranslationPanel::new(),
                 annotation_panel: AnnotationPanel::new(),
                 pdfium: RefCell::new(None),
                 paned: RefCell::new(None),
+                main_box: RefCell::new(None),
+                content_row: RefCell::new(None),
+                annotation_panel_position: Cell::new(PanelPosition::default()),
+                annotation_dialog: RefCell::new(None),
+                compact: Cell::new(false),
+                toc_sheet: gtk::Revealer::new(),
                 app_mode: RefCell::new(AppMode::default()),
                 text_cache: RefCell::new(None),
                 toast_revealer,
@@ -98,14 +444,83 @@ mod imp {
                 key_handler: KeyHandler::new(),
                 pendingkey_box: PendingKeyBox::new(),
                 dictionary_language: Cell::new(Language::default()),
+                viewport_anchor: Cell::new(DEFAULT_VIEWPORT_OFFSET),
+                annotation_wrap: Cell::new(false),
+                notes_sync_dir: RefCell::new(String::new()),
+                api_server: RefCell::new(None),
+                distraction_free: Cell::new(false),
+                hidden_chrome: Cell::new(None),
+                idle_timeout: RefCell::new(None),
+                presentation: RefCell::new(None),
+                presentation_fade: Cell::new(true),
+                presentation_fade_tick: RefCell::new(None),
+                crop_adjusting: Cell::new(false),
+                prefetch_definitions: Cell::new(false),
+                prefetch_online: Cell::new(false),
+                prefetch_line: Cell::new(None),
+                prefetch_timeout: RefCell::new(None),
+                last_pointer: Cell::new(None),
+                document_language: Cell::new(None),
+                copy_join_style: Cell::new(CopyJoinStyle::default()),
+                bionic_reading: Cell::new(false),
+                show_word_boxes: Cell::new(false),
+                zoom_fit_pending: Cell::new(false),
+                use_page_labels: Cell::new(false),
+                page_labels: RefCell::new(None),
+                export_options: Cell::new(ExportOptions::default()),
                 current_pdf_path: RefCell::new(None),
                 annotations: RefCell::new(Vec::new()),
+                annotation_links: RefCell::new(Vec::new()),
+                highlighted_pages: RefCell::new(HashSet::new()),
+                highlight_update_pending: Cell::new(false),
+                primary_selection: Cell::new(None),
+                primary_selection_pending: Cell::new(false),
+                speaker: RefCell::new(Speaker::default()),
+                tts_next_page: Cell::new(None),
+                annotation_rects: RefCell::new(AnnotationRectCache::default()),
                 pending_annotation: RefCell::new(None),
+                saving_annotation: Cell::new(false),
                 mouse_selection_state: RefCell::new(MouseSelectionState::default()),
+                ink_tool: Cell::new(InkTool::default()),
+                ink_color: Cell::new(AnnotationColor::Red),
+                ink_drawings: RefCell::new(Vec::new()),
+                ink_stroke: RefCell::new(None),
+                ocr_queue: RefCell::new(VecDeque::new()),
+                ocr_page: Cell::new(None),
+                ui_font: RefCell::new(UiFont::default()),
+                ui_font_provider: gtk::CssProvider::new(),
+                theme: Cell::new(Theme::default()),
+                theme_provider: gtk::CssProvider::new(),
+                user_css: RefCell::new(String::new()),
+                user_css_provider: gtk::CssProvider::new(),
+                user_css_monitor: RefCell::new(None),
+                recovered_journal: RefCell::new(None),
+                last_journal: RefCell::new(None),
+                citations: RefCell::new(HashMap::new()),
+                synctex: RefCell::new(None),
+                synctex_editor: RefCell::new(String::new()),
+                read_pages: RefCell::new(HashSet::new()),
+                reading_range: Cell::new(None),
+                reading_speed: RefCell::new(ReadingSpeed::default()),
+                search_matches: RefCell::new(Vec::new()),
+                search_index: Cell::new(None),
+                jump_list: RefCell::new(JumpList::default()),
+                visit_history: RefCell::new(VisitHistory::default()),
+                marks: RefCell::new(Vec::new()),
+                action_recorder: RefCell::new(ActionRecorder::default()),
+                startup_defaults: RefCell::new(StartupDefaults::default()),
+                settings: RefCell::new(Settings::default()),
             }
         }
     }
 
+    impl EyersWindow {
+        /// View of the active tab, changing when another tab is shown
+        pub fn pdf_view(&self) -> PdfView {
+            self.pdf_view.borrow().clone()
+        }
+    }
+
     #[glib::object_subclass]
     impl ObjectSubclass for EyersWindow {
         const NAME: &'static str = "EyersWindow";
@@ -120,7 +535,12 @@ mod imp {
         }
     }
 
-    impl WidgetImpl for EyersWindow {}
+    impl WidgetImpl for EyersWindow {
+        fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
+            self.parent_size_allocate(width, height, baseline);
+            self.obj().update_compact_layout(width);
+        }
+    }
     impl WindowImpl for EyersWindow {}
     impl ApplicationWindowImpl for EyersWindow {}
 }
@@ -142,6 +562,10 @@ impl EyersWindow {
             .build();
 
         window.init_pdfium();
+        window.setup_journal();
+        window.setup_session();
+        window.apply_startup_defaults();
+        window.apply_settings();
         window
     }
 
@@ -155,7 +579,7 @@ impl EyersWindow {
         // std::boxed::Box::leak(std::boxed::Box::new(Pdfium::new(bindings)));
 
         self.imp().pdfium.replace(Some(pdfium));
-        self.imp().pdf_view.set_pdfium(pdfium);
+        self.imp().pdf_view().set_pdfium(pdfium);
     }
 
     fn setup_widgets(&self) {
@@ -164,24 +588,34 @@ impl EyersWindow {
         self.set_titlebar(Some(imp.header_bar.widget()));
         self.setup_open_button();
         self.setup_settings_button();
+        self.setup_about_button();
+        self.setup_reload_button();
+        self.setup_rotate_buttons();
+        self.setup_flashcards();
+        self.setup_recent_files();
+        self.setup_tts_controls();
+        self.setup_ui_font();
+        self.setup_theme();
+        self.setup_clipboard_lookup();
 
         // Setup all widget components
-        self.setup_header_bar_bindings();
         let main_box = self.setup_main_layout();
         self.setup_panels_visibility(&main_box);
         self.setup_overlay_structure(&main_box);
 
         self.setup_key_handler_binding();
         self.setup_toast();
+        self.setup_command_line();
         self.setup_keyboard_controller();
         self.setup_translation_panel();
         self.setup_annotation_panel();
         self.setup_annotate_button();
         self.setup_toc_panel();
-        self.setup_scroll_tracking();
-        self.setup_drag_selection();
-        self.setup_page_indicator_label();
+        self.setup_tab_bar();
+        self.setup_split_view();
+        self.setup_jump_navigation();
         self.setup_highlight_update_on_resize();
+        self.setup_distraction_free();
     }
 
     fn setup_highlight_update_on_resize(&self) {
@@ -194,6 +628,7 @@ impl EyersWindow {
                     let current_area = width * height;
                     if current_area != last_area.get() {
                         win.update_highlights();
+                        win.refresh_text_overlays();
                         last_area.set(current_area);
                     }
                 });
@@ -201,16 +636,69 @@ impl EyersWindow {
         });
     }
 
-    fn setup_header_bar_bindings(&self) {
+    /// Connect the view of a tab and its scrolled window to the window
+    fn setup_tab_view(&self, view: &PdfView, scrolled_window: &ScrolledWindow) {
+        self.setup_header_bar_bindings(view);
+        self.setup_view_lookups(view);
+        self.setup_scroll_tracking(view, scrolled_window);
+        self.setup_drag_selection(view);
+        self.setup_synctex(view);
+        self.setup_links(view);
+        self.setup_page_indicator_label(view);
+        self.setup_zoom_fitting(view, scrolled_window);
+        self.setup_text_overlays(view);
+        self.setup_crop_tracking(view);
+    }
+
+    /// Whether `view` is the view of the active tab
+    fn is_active_view(&self, view: &PdfView) -> bool {
+        *self.imp().pdf_view.borrow() == *view
+    }
+
+    /// Views of every tab, which settings apply to alike
+    fn tab_views(&self) -> Vec<PdfView> {
+        let imp = self.imp();
+        let mut views: Vec<PdfView> = imp
+            .tabs
+            .borrow()
+            .tabs()
+            .iter()
+            .map(|tab| tab.state.view.clone())
+            .collect();
+        // Before a document is open, the view shown is in no tab yet
+        let active = imp.pdf_view();
+        if !views.contains(&active) {
+            views.push(active);
+        }
+        views
+    }
+
+    /// Every tab view follows the lookup mode and page layout of the header bar,
+    /// and changing the layout in one changes it in all
+    fn setup_header_bar_bindings(&self, view: &PdfView) {
         let imp = self.imp();
 
         imp.header_bar
-            .bind_property("definitions-enabled", &imp.pdf_view, "definitions-enabled")
+            .bind_property("definitions-enabled", view, "definitions-enabled")
+            .sync_create()
+            .build();
+
+        imp.header_bar
+            .bind_property("translate-enabled", view, "translate-enabled")
+            .sync_create()
+            .build();
+
+        imp.header_bar
+            .two_page_toggle()
+            .bind_property("active", view, "two-page")
+            .bidirectional()
             .sync_create()
             .build();
 
         imp.header_bar
-            .bind_property("translate-enabled", &imp.pdf_view, "translate-enabled")
+            .paged_toggle()
+            .bind_property("active", view, "paged")
+            .bidirectional()
             .sync_create()
             .build();
     }
@@ -218,16 +706,34 @@ impl EyersWindow {
     fn setup_main_layout(&self) -> gtk::Box {
         let imp = self.imp();
 
-        // Scrolled window for PDF view
-        let scrolled_window = ScrolledWindow::builder()
+        // The view of the first tab, shown before any document is open
+        let scrolled_window = self.add_tab_page(&imp.pdf_view());
+        imp.scrolled_window.replace(Some(scrolled_window));
+
+        imp.view_stack.add_named(&imp.tab_stack, Some("pages"));
+        imp.view_stack.add_named(&imp.reflow_view, Some("reflow"));
+        imp.view_stack.set_visible_child_name("pages");
+
+        // The split view, hidden until Ctrl+w v / Ctrl+w s
+        let split_scrolled_window = ScrolledWindow::builder()
             .hscrollbar_policy(PolicyType::Automatic)
             .vscrollbar_policy(PolicyType::Automatic)
             .vexpand(true)
             .hexpand(true)
-            .child(&imp.pdf_view)
+            .visible(false)
+            .child(&imp.split_view)
             .build();
-        scrolled_window.add_css_class("pdf-scrolled-window");
-        imp.scrolled_window.replace(Some(scrolled_window.clone()));
+        split_scrolled_window.add_css_class("pdf-scrolled-window");
+        imp.split_scrolled_window
+            .replace(Some(split_scrolled_window.clone()));
+
+        let split_paned = Paned::builder()
+            .orientation(Orientation::Horizontal)
+            .start_child(&imp.view_stack)
+            .end_child(&split_scrolled_window)
+            .wide_handle(true)
+            .build();
+        imp.split_paned.replace(Some(split_paned.clone()));
 
         // Horizontal paned container
         let paned = Paned::builder()
@@ -235,7 +741,7 @@ impl EyersWindow {
             .build();
         paned.add_css_class("eyers-paned");
         paned.set_wide_handle(true);
-        paned.set_start_child(Some(&scrolled_window));
+        paned.set_start_child(Some(&split_paned));
         paned.set_end_child(Some(&imp.toc_panel));
         paned.set_resize_start_child(true);
         paned.set_shrink_start_child(true);
@@ -244,12 +750,22 @@ impl EyersWindow {
         paned.set_position(500);
         imp.paned.replace(Some(paned.clone()));
 
+        // The annotation panel goes next to the paned when docked right
+        let content_row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .vexpand(true)
+            .build();
+        content_row.append(&paned);
+        imp.content_row.replace(Some(content_row.clone()));
+
         // Main vertical box
         let main_box = Box::builder().orientation(Orientation::Vertical).build();
         main_box.add_css_class("eyers-main-content");
-        main_box.append(&paned);
+        main_box.append(&imp.tab_bar);
+        main_box.append(&content_row);
 
         main_box.append(imp.status_bar.widget());
+        imp.main_box.replace(Some(main_box.clone()));
 
         return main_box;
     }
@@ -272,9 +788,61 @@ impl EyersWindow {
         overlay.add_overlay(&imp.toast_revealer);
         overlay.add_overlay(&imp.pendingkey_box);
 
+        // The table of contents slides in over the pages in narrow windows
+        imp.toc_sheet
+            .set_transition_type(gtk::RevealerTransitionType::SlideLeft);
+        imp.toc_sheet.set_halign(gtk::Align::End);
+        imp.toc_sheet.add_css_class("toc-sheet");
+        imp.toc_sheet.set_visible(false);
+        overlay.add_overlay(&imp.toc_sheet);
+        let sheet = imp.toc_sheet.clone();
+        imp.toc_panel
+            .connect_visible_notify(move |panel| sheet.set_reveal_child(panel.is_visible()));
+
         self.set_child(Some(&overlay));
     }
 
+    /// Switch to the compact layout when the window gets narrower than
+    /// COMPACT_WIDTH, or back
+    fn update_compact_layout(&self, width: i32) {
+        let compact = width < COMPACT_WIDTH;
+        if self.imp().compact.replace(compact) == compact {
+            return;
+        }
+        // Moving widgets around has to wait for the end of the allocation
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.apply_compact_layout();
+            }
+        });
+    }
+
+    /// Collapse the header bar buttons into a menu, slide the table of contents
+    /// over the pages and write notes in a window of their own, or undo it
+    fn apply_compact_layout(&self) {
+        let imp = self.imp();
+        let compact = imp.compact.get();
+        imp.header_bar.set_compact(compact);
+
+        if let Some(paned) = imp.paned.borrow().as_ref() {
+            if compact {
+                paned.set_end_child(None::<&gtk::Widget>);
+                imp.toc_panel.set_size_request(TOC_SHEET_WIDTH, -1);
+                imp.toc_sheet.set_child(Some(&imp.toc_panel));
+            } else {
+                imp.toc_sheet.set_child(None::<&gtk::Widget>);
+                imp.toc_panel.set_size_request(-1, -1);
+                paned.set_end_child(Some(&imp.toc_panel));
+            }
+        }
+        imp.toc_sheet.set_visible(compact);
+        imp.toc_sheet
+            .set_reveal_child(compact && imp.toc_panel.is_visible());
+
+        self.place_annotation_panel();
+    }
+
     /// Set up binding between KeyHandler and PendingKeyBox
     fn setup_key_handler_binding(&self) {
         let imp = self.imp();
@@ -317,15 +885,30 @@ impl EyersWindow {
         imp.toast_revealer.set_child(Some(&toast_box));
     }
 
-    fn setup_scroll_tracking(&self) {
-        let pdf_view = self.imp().pdf_view.clone();
-        if let Some(scrolled_window) = self.imp().scrolled_window.borrow().as_ref() {
-            let adjustment = scrolled_window.vadjustment();
+    /// Scrolled window holding the view of a new tab, added to the tab stack
+    fn add_tab_page(&self, view: &PdfView) -> ScrolledWindow {
+        let scrolled_window = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Automatic)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .vexpand(true)
+            .hexpand(true)
+            .child(view)
+            .build();
+        scrolled_window.add_css_class("pdf-scrolled-window");
+        self.imp().tab_stack.add_child(&scrolled_window);
+        self.setup_tab_view(view, &scrolled_window);
+        scrolled_window
+    }
 
-            adjustment.connect_value_changed(move |_| {
-                pdf_view.schedule_page_update();
+    fn setup_scroll_tracking(&self, view: &PdfView, scrolled_window: &ScrolledWindow) {
+        let view_weak = view.downgrade();
+        scrolled_window
+            .vadjustment()
+            .connect_value_changed(move |_| {
+                if let Some(view) = view_weak.upgrade() {
+                    view.schedule_page_update();
+                }
             });
-        }
     }
 
     fn setup_translation_panel(&self) {
@@ -338,9 +921,13 @@ impl EyersWindow {
                 panel.set_visible(false);
                 panel.clear();
             });
+    }
 
-        let panel = imp.translation_panel.clone();
-        imp.pdf_view.connect_closure(
+    /// Translate, pronounce and record for flashcards the words looked up in
+    /// the popovers of `view`
+    fn setup_view_lookups(&self, view: &PdfView) {
+        let panel = self.imp().translation_panel.clone();
+        view.connect_closure(
             "translate-requested",
             false,
             glib::closure_local!(move |_view: &PdfView, text: &str| {
@@ -348,191 +935,641 @@ impl EyersWindow {
                 panel.translate(text.to_string());
             }),
         );
-    }
 
-    fn setup_drag_selection(&self) {
-        let imp = self.imp();
+        let window_weak = self.downgrade();
+        view.connect_closure(
+            "word-looked-up",
+            false,
+            closure_local!(move |pdf_view: &PdfView,
+                                 word: &str,
+                                 definition: &str,
+                                 context: &str,
+                                 page_index: u32| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                let Some(pdf_path) = window.imp().current_pdf_path.borrow().clone() else {
+                    return;
+                };
+                let card = Flashcard {
+                    word: word.to_string(),
+                    definition: definition.to_string(),
+                    context: context.to_string(),
+                    pdf_path,
+                    page_index: page_index as usize,
+                    language: pdf_view.dictionary_language().code().to_string(),
+                };
+                if let Err(e) = flashcards::record_lookup(&card) {
+                    eprintln!("Failed to record lookup: {}", e);
+                }
+                window.log_reading_event(ReadingEvent::LookedUp {
+                    page_index: card.page_index,
+                    word: card.word,
+                });
+            }),
+        );
+
+        let window_weak = self.downgrade();
+        view.connect_closure(
+            "pronounce-requested",
+            false,
+            closure_local!(move |_pdf_view: &PdfView, word: &str, language: &str| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.pronounce(word, language);
+                }
+            }),
+        );
+    }
 
+    fn setup_drag_selection(&self, view: &PdfView) {
         // Connect drag-started signal
         let weak_self = self.downgrade();
-        imp.pdf_view
-            .connect_local("drag-started", false, move |values| {
-                let window = weak_self.upgrade()?;
-                let x = values.get(1)?.get::<f64>().ok()?;
-                let y = values.get(2)?.get::<f64>().ok()?;
-                let page_index = values.get(3)?.get::<u32>().ok()? as usize;
-                window.handle_drag_started(x, y, page_index);
-                None
-            });
+        view.connect_local("drag-started", false, move |values| {
+            let window = weak_self.upgrade()?;
+            let x = values.get(1)?.get::<f64>().ok()?;
+            let y = values.get(2)?.get::<f64>().ok()?;
+            let page_index = values.get(3)?.get::<u32>().ok()? as usize;
+            window.handle_drag_started(x, y, page_index);
+            None
+        });
 
         // Connect drag-motion signal
         let weak_self = self.downgrade();
-        imp.pdf_view
-            .connect_local("drag-motion", false, move |values| {
-                let window = weak_self.upgrade()?;
-                let x = values.get(1)?.get::<f64>().ok()?;
-                let y = values.get(2)?.get::<f64>().ok()?;
-                window.handle_drag_motion(x, y);
-                None
-            });
+        view.connect_local("drag-motion", false, move |values| {
+            let window = weak_self.upgrade()?;
+            let x = values.get(1)?.get::<f64>().ok()?;
+            let y = values.get(2)?.get::<f64>().ok()?;
+            window.handle_drag_motion(x, y);
+            None
+        });
 
-        // Connect drag-ended signal
         let weak_self = self.downgrade();
-        imp.pdf_view
-            .connect_local("drag-ended", false, move |_values| {
-                let window = weak_self.upgrade()?;
-                window.handle_drag_ended();
-                None
-            });
-    }
-
-    fn setup_toc_panel(&self) {
-        let imp = self.imp();
-
-        let panel = imp.toc_panel.clone();
-        imp.toc_panel.close_button().connect_clicked(move |_| {
-            panel.set_visible(false);
+        view.connect_local("drag-updated", false, move |values| {
+            let window = weak_self.upgrade()?;
+            let offset_x = values.get(1)?.get::<f64>().ok()?;
+            let offset_y = values.get(2)?.get::<f64>().ok()?;
+            window.extend_ink_stroke(offset_x, offset_y);
+            None
         });
 
-        let pdf_view = imp.pdf_view.clone();
+        // Connect drag-ended signal
         let weak_self = self.downgrade();
-        imp.toc_panel.connect_closure(
-            "toc-entry-selected",
-            false,
-            glib::closure_local!(
-                move |_panel: &TocPanel, page_index: u32, annotation_cursor: Option<WordCursor>| {
-                    let Some(this) = weak_self.upgrade() else {
-                        return;
-                    };
-                    pdf_view.scroll_to_page(page_index as u16);
-                    let app_mode = this.imp().app_mode.borrow().clone();
-                    match app_mode {
-                        AppMode::Visual {
-                            cursor: _cursor,
-                            selection_anchor: _,
-                        } => {
-                            if let Some(cursor) = annotation_cursor {
-                                this.move_cursor(cursor);
-                                return;
-                            }
-                            if let Some(cursor) =
-                                this.compute_word_at_viewport_offset(DEFAULT_VIEWPORT_OFFSET)
-                            {
-                                this.move_cursor(cursor);
-                            }
-                        }
+        view.connect_local("drag-ended", false, move |_values| {
+            let window = weak_self.upgrade()?;
+            window.handle_drag_ended();
+            None
+        });
+    }
 
-                        AppMode::Normal => {}
-                    };
+    fn setup_synctex(&self, view: &PdfView) {
+        let window_weak = self.downgrade();
+        view.connect_closure(
+            "page-ctrl-clicked",
+            false,
+            closure_local!(
+                move |_pdf_view: &PdfView, x: f64, y: f64, page_index: u32| {
+                    if let Some(window) = window_weak.upgrade() {
+                        window.synctex_reverse(page_index as usize, x, y);
+                    }
                 }
             ),
         );
+    }
 
-        // Connect annotation-edit-requested signal
+    fn setup_links(&self, view: &PdfView) {
         let window_weak = self.downgrade();
-        imp.toc_panel.connect_closure(
-            "annotation-edit-requested",
+        view.connect_closure(
+            "link-activated",
             false,
-            glib::closure_local!(move |_panel: &TocPanel, annotation_id: i64| {
+            closure_local!(move |_pdf_view: &PdfView, page_index: u32| {
                 if let Some(window) = window_weak.upgrade() {
-                    window.edit_annotation_from_toc(annotation_id);
+                    window.scroll_to_page(page_index as u16);
                 }
             }),
         );
+    }
+
+    fn setup_split_view(&self) {
+        let imp = self.imp();
+
+        self.bind_split_view();
+        self.setup_view_lookups(&imp.split_view);
 
-        // Connect annotation-delete-requested signal
         let window_weak = self.downgrade();
-        imp.toc_panel.connect_closure(
-            "annotation-delete-requested",
+        imp.split_view
+            .connect_local("crop-changed", false, move |_| {
+                window_weak.upgrade()?.update_split_highlights();
+                None
+            });
+
+        // Links are followed in the view they were clicked in
+        imp.split_view.connect_closure(
+            "link-activated",
             false,
-            glib::closure_local!(move |_panel: &TocPanel, annotation_id: i64| {
-                if let Some(window) = window_weak.upgrade() {
-                    window.show_delete_annotation_dialog(annotation_id);
-                }
+            closure_local!(move |split_view: &PdfView, page_index: u32| {
+                split_view.scroll_to_page(page_index as u16);
             }),
         );
-    }
 
-    fn setup_keyboard_controller(&self) {
-        let controller = gtk::EventControllerKey::new();
-        // controller.set_propagation_phase(gtk::PropagationPhase::Capture);
-        let window_weak = self.downgrade();
+        let Some(scrolled) = imp.split_scrolled_window.borrow().clone() else {
+            return;
+        };
 
-        controller.connect_key_pressed(move |_, key, _, modifiers| {
-            if let Some(window) = window_weak.upgrade() {
-                let imp = window.imp();
-                let is_toc_visible = imp.toc_panel.is_visible();
-                if is_toc_visible {
-                    match handle_toc_key(&imp.key_handler, key, modifiers, imp.toc_panel.toc_mode())
-                    {
-                        KeyResult::Action(action) => {
-                            if window.execute_key_action(action) {
-                                return glib::Propagation::Stop;
-                            }
-                        }
-                        KeyResult::StateChanged => {
-                            return glib::Propagation::Stop;
-                        }
-                        KeyResult::Unhandled => return glib::Propagation::Stop,
-                    }
-                }
+        let split_view = imp.split_view.clone();
+        scrolled.vadjustment().connect_value_changed(move |_| {
+            split_view.schedule_page_update();
+        });
 
-                // Try pre-global keys first
-                match handle_pre_global_key(&imp.key_handler, key, modifiers) {
-                    KeyResult::Action(action) => {
-                        if window.execute_key_action(action) {
-                            return glib::Propagation::Stop;
-                        }
-                    }
-                    KeyResult::StateChanged => {
-                        return glib::Propagation::Stop;
-                    }
-                    KeyResult::Unhandled => {}
-                }
+        // Annotations follow the pages once they are laid out at a new size
+        let window_weak = self.downgrade();
+        scrolled.vadjustment().connect_upper_notify(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.update_split_highlights();
+            }
+        });
 
-                // Handle mode-based key events
-                if window.handle_mode_key(key) {
-                    return glib::Propagation::Stop;
+        for adjustment in [scrolled.hadjustment(), scrolled.vadjustment()] {
+            let window_weak = self.downgrade();
+            adjustment.connect_page_size_notify(move |_| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.schedule_split_zoom_fit();
+                    window.update_split_highlights();
                 }
+            });
+        }
+    }
 
-                // Try post-global keys
-                match handle_post_global_key(&imp.key_handler, key) {
-                    KeyResult::Action(action) => {
-                        if window.execute_key_action(action) {
-                            return glib::Propagation::Stop;
-                        }
-                    }
-                    KeyResult::StateChanged => {
-                        return glib::Propagation::Stop;
-                    }
-                    KeyResult::Unhandled => {}
-                }
-            }
-            glib::Propagation::Proceed
+    /// Clicks on the second view work as on the pages of the active tab
+    fn bind_split_view(&self) {
+        let imp = self.imp();
+        let view = imp.pdf_view();
+        let bindings = [
+            "definitions-enabled",
+            "translate-enabled",
+            "popover-autohide",
+            "two-page",
+            "cover-page",
+            "auto-crop",
+        ]
+        .map(|property| {
+            view.bind_property(property, &imp.split_view, property)
+                .sync_create()
+                .build()
         });
+        for binding in imp.split_bindings.replace(bindings.into()) {
+            binding.unbind();
+        }
+    }
 
-        self.add_controller(controller);
+    /// Whether the second view of the document is shown
+    fn split_active(&self) -> bool {
+        self.imp()
+            .split_scrolled_window
+            .borrow()
+            .as_ref()
+            .is_some_and(|scrolled| scrolled.is_visible())
     }
 
-    /// Handle key press based on current mode
-    fn handle_mode_key(&self, key: gtk::gdk::Key) -> bool {
+    /// Show the document a second time next to the pages or under them, at the
+    /// same place to start with; it then scrolls on its own. An open split only
+    /// changes direction.
+    fn open_split(&self, side_by_side: bool) {
         let imp = self.imp();
+        let (Some(split_paned), Some(scrolled)) = (
+            imp.split_paned.borrow().clone(),
+            imp.split_scrolled_window.borrow().clone(),
+        ) else {
+            return;
+        };
 
-        // Need document to be loaded for other mode operations
-        if !imp.pdf_view.has_document() {
-            return false;
+        if !self.split_active() {
+            if let Err(e) = imp.split_view.share_document(&imp.pdf_view()) {
+                self.show_toast(&e);
+                return;
+            }
+            scrolled.set_visible(true);
+            let value = imp
+                .scrolled_window
+                .borrow()
+                .as_ref()
+                .map_or(0.0, |main| main.vadjustment().value());
+            self.scroll_split_view(value);
         }
 
-        let mode = imp.app_mode.borrow().clone();
+        let orientation = if side_by_side {
+            Orientation::Horizontal
+        } else {
+            Orientation::Vertical
+        };
+        split_paned.set_orientation(orientation);
+        // Share the room evenly
+        let size = if side_by_side {
+            split_paned.width()
+        } else {
+            split_paned.height()
+        };
+        split_paned.set_position(size / 2);
+    }
+
+    /// Hide the second view, leaving the pages the whole room
+    fn close_split(&self) {
+        let imp = self.imp();
+        if let Some(scrolled) = imp.split_scrolled_window.borrow().as_ref() {
+            scrolled.set_visible(false);
+        }
+        imp.split_view.close_current_popover();
+    }
+
+    /// Scroll the second view to `value` once its pages are laid out, then draw
+    /// the annotations on them
+    fn scroll_split_view(&self, value: f64) {
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let imp = window.imp();
+            if let Some(scrolled) = imp.split_scrolled_window.borrow().as_ref() {
+                scrolled.vadjustment().set_value(value);
+            }
+            imp.split_view.render_visible_pages();
+            window.update_split_highlights();
+        });
+    }
+
+    /// Show the document just (re)loaded in the second view too, keeping the
+    /// place it shows
+    fn refresh_split_view(&self) {
+        if !self.split_active() {
+            return;
+        }
+        let imp = self.imp();
+        let value = imp
+            .split_scrolled_window
+            .borrow()
+            .as_ref()
+            .map_or(0.0, |scrolled| scrolled.vadjustment().value());
+        if let Err(e) = imp.split_view.share_document(&imp.pdf_view()) {
+            eprintln!("{}", e);
+            self.close_split();
+            return;
+        }
+        self.scroll_split_view(value);
+    }
+
+    /// Exchange the places shown by the two views, so the keys move on from
+    /// where the second view was. Counts as a jump, `Ctrl+o` goes back.
+    fn swap_split(&self) {
+        let imp = self.imp();
+        if !self.split_active() {
+            self.show_toast("No split view (Ctrl+w v or Ctrl+w s)");
+            return;
+        }
+        let (Some(main), Some(split)) = (
+            imp.scrolled_window.borrow().clone(),
+            imp.split_scrolled_window.borrow().clone(),
+        ) else {
+            return;
+        };
+
+        // As a ratio of the scrollable height, the two views may be zoomed apart
+        let ratio = |adjustment: &gtk::Adjustment| {
+            let upper = adjustment.upper() - adjustment.page_size();
+            if upper > 0.0 {
+                adjustment.value() / upper
+            } else {
+                0.0
+            }
+        };
+        let (main_adj, split_adj) = (main.vadjustment(), split.vadjustment());
+        let (main_ratio, split_ratio) = (ratio(&main_adj), ratio(&split_adj));
+
+        self.record_jump();
+        main_adj.set_value(split_ratio * (main_adj.upper() - main_adj.page_size()));
+        split_adj.set_value(main_ratio * (split_adj.upper() - split_adj.page_size()));
+
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(window) = window_weak.upgrade() {
+                if let Some(cursor) = window.compute_word_at_viewport_anchor() {
+                    window.move_cursor(cursor);
+                }
+            }
+        });
+    }
+
+    /// Fit the second view to its own size once it settles, in fit modes
+    fn schedule_split_zoom_fit(&self) {
+        let imp = self.imp();
+        if !self.split_active()
+            || !imp.split_view.zoom_mode().fits_view()
+            || imp.split_fit_pending.replace(true)
+        {
+            return;
+        }
+
+        let window_weak = self.downgrade();
+        glib::timeout_add_local_once(
+            std::time::Duration::from_millis(ZOOM_FIT_DELAY_MS),
+            move || {
+                if let Some(window) = window_weak.upgrade() {
+                    let split_view = &window.imp().split_view;
+                    window.imp().split_fit_pending.set(false);
+                    if split_view.set_zoom_mode(split_view.zoom_mode()) {
+                        window.update_split_highlights();
+                    }
+                }
+            },
+        );
+    }
+
+    /// Load the SyncTeX file LaTeX wrote next to the PDF, if any
+    fn load_synctex(&self, pdf_path: &Path) {
+        let synctex = synctex::find_synctex_file(pdf_path).and_then(|path| {
+            SyncTex::load(&path)
+                .map_err(|e| eprintln!("Failed to read {}: {}", path.display(), e))
+                .ok()
+        });
+        self.imp()
+            .pdf_view()
+            .set_reverse_search_enabled(synctex.is_some());
+        self.imp().synctex.replace(synctex);
+    }
+
+    /// Forward search: scroll to where a LaTeX source line was typeset. `spec` is
+    /// `LINE:COLUMN:FILE` or `LINE:FILE`.
+    pub fn synctex_forward(&self, spec: &str) {
+        let Some((line, file)) = synctex::parse_forward_spec(spec) else {
+            eprintln!("Invalid SyncTeX position: {}", spec);
+            return;
+        };
+
+        let position = self
+            .imp()
+            .synctex
+            .borrow()
+            .as_ref()
+            .and_then(|synctex| synctex.forward(&file, line));
+        let Some(position) = position else {
+            self.show_toast(&format!(
+                "No SyncTeX position for {}:{}",
+                file.display(),
+                line
+            ));
+            return;
+        };
+
+        // Wait for freshly loaded pages to be laid out before scrolling
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let imp = window.imp();
+
+            let cursor = {
+                let pdf_view = imp.pdf_view();
+                let doc_borrow = pdf_view.document();
+                let mut cache = imp.text_cache.borrow_mut();
+                let (Some(doc), Some(cache)) = (doc_borrow.as_ref(), cache.as_mut()) else {
+                    return;
+                };
+                cache
+                    .get_or_build(position.page_index, doc.as_ref())
+                    .and_then(|text_map| {
+                        text_map.nearest_word(position.x, text_map.page_height - position.y)
+                    })
+                    .map(|word_index| WordCursor::new(position.page_index, word_index))
+            };
+
+            window.record_jump();
+            match cursor {
+                Some(cursor) => window.jump_to_position(cursor),
+                None => imp.pdf_view().scroll_to_page(position.page_index as u16),
+            }
+        });
+    }
+
+    /// Reverse search: hand the LaTeX source line typeset at a Ctrl+clicked point
+    /// (in PDF points) to the configured editor command, or print it if none is set
+    fn synctex_reverse(&self, page_index: usize, pdf_x: f64, pdf_y: f64) {
+        let imp = self.imp();
+
+        if imp.synctex.borrow().is_none() {
+            self.show_toast("No SyncTeX file found for this document");
+            return;
+        }
+
+        let Some((_, page_height)) = imp
+            .pdf_view()
+            .document()
+            .as_ref()
+            .and_then(|doc| doc.page_size(page_index))
+        else {
+            return;
+        };
+
+        // SyncTeX measures from the top of the page
+        let position = PagePosition {
+            page_index,
+            x: pdf_x,
+            y: page_height - pdf_y,
+        };
+        let location = imp
+            .synctex
+            .borrow()
+            .as_ref()
+            .and_then(|synctex| synctex.reverse(position));
+        let Some(location) = location else {
+            return;
+        };
+
+        let target = format!("{}:{}", location.input.display(), location.line);
+
+        let command = imp.synctex_editor.borrow().clone();
+        if command.trim().is_empty() {
+            self.show_toast(&target);
+            return;
+        }
+
+        let args: Vec<String> = match glib::shell_parse_argv(&command) {
+            Ok(args) => args
+                .iter()
+                .map(|arg| synctex::expand_editor_arg(&arg.to_string_lossy(), &location))
+                .collect(),
+            Err(e) => {
+                eprintln!("Invalid editor command: {}", e);
+                self.show_toast("Invalid SyncTeX editor command");
+                return;
+            }
+        };
+        let Some((program, args)) = args.split_first() else {
+            return;
+        };
+
+        match std::process::Command::new(program).args(args).spawn() {
+            Ok(mut child) => {
+                // Reap the editor process when it exits
+                std::thread::spawn(move || {
+                    let _ = child.wait();
+                });
+            }
+            Err(e) => {
+                eprintln!("Failed to run {}: {}", program, e);
+                self.show_toast(&format!("Could not run {}", program));
+            }
+        }
+    }
+
+    fn setup_toc_panel(&self) {
+        let imp = self.imp();
+
+        let panel = imp.toc_panel.clone();
+        imp.toc_panel.close_button().connect_clicked(move |_| {
+            panel.set_visible(false);
+        });
+
+        let weak_self = self.downgrade();
+        imp.toc_panel.connect_closure(
+            "toc-entry-selected",
+            false,
+            glib::closure_local!(
+                move |_panel: &TocPanel, page_index: u32, annotation_cursor: Option<WordCursor>| {
+                    let Some(this) = weak_self.upgrade() else {
+                        return;
+                    };
+                    this.record_jump();
+                    this.pdf_view().scroll_to_page(page_index as u16);
+                    let app_mode = this.imp().app_mode.borrow().clone();
+                    match app_mode {
+                        AppMode::Visual {
+                            cursor: _cursor,
+                            selection_anchor: _,
+                        } => {
+                            if let Some(cursor) = annotation_cursor {
+                                this.move_cursor(cursor);
+                                return;
+                            }
+                            if let Some(cursor) = this.compute_word_at_viewport_anchor() {
+                                this.move_cursor(cursor);
+                            }
+                        }
+
+                        AppMode::Normal => {}
+                    };
+                }
+            ),
+        );
+
+        // Connect annotation-edit-requested signal
+        let window_weak = self.downgrade();
+        imp.toc_panel.connect_closure(
+            "annotation-edit-requested",
+            false,
+            glib::closure_local!(move |_panel: &TocPanel, annotation_id: i64| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.edit_annotation_from_toc(annotation_id);
+                }
+            }),
+        );
+
+        // Connect annotation-delete-requested signal
+        let window_weak = self.downgrade();
+        imp.toc_panel.connect_closure(
+            "annotation-delete-requested",
+            false,
+            glib::closure_local!(move |_panel: &TocPanel, annotation_id: i64| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.show_delete_annotation_dialog(annotation_id);
+                }
+            }),
+        );
+    }
+
+    fn setup_keyboard_controller(&self) {
+        let controller = gtk::EventControllerKey::new();
+        // controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let window_weak = self.downgrade();
+
+        controller.connect_key_pressed(move |_, key, _, modifiers| {
+            if let Some(window) = window_weak.upgrade() {
+                let imp = window.imp();
+                if window.handle_presentation_key(key) || window.handle_crop_key(key) {
+                    return glib::Propagation::Stop;
+                }
+                let is_toc_visible = imp.toc_panel.is_visible();
+                if is_toc_visible {
+                    match handle_toc_key(&imp.key_handler, key, modifiers, imp.toc_panel.toc_mode())
+                    {
+                        KeyResult::Action(action) => {
+                            if window.execute_key_action(action) {
+                                return glib::Propagation::Stop;
+                            }
+                        }
+                        KeyResult::StateChanged => {
+                            return glib::Propagation::Stop;
+                        }
+                        KeyResult::Unhandled => return glib::Propagation::Stop,
+                    }
+                }
+
+                // Try pre-global keys first
+                match handle_pre_global_key(&imp.key_handler, key, modifiers) {
+                    KeyResult::Action(action) => {
+                        if window.execute_key_action(action) {
+                            return glib::Propagation::Stop;
+                        }
+                    }
+                    KeyResult::StateChanged => {
+                        return glib::Propagation::Stop;
+                    }
+                    KeyResult::Unhandled => {}
+                }
+
+                // Handle mode-based key events
+                if window.handle_mode_key(key) {
+                    return glib::Propagation::Stop;
+                }
+
+                // Try post-global keys
+                match handle_post_global_key(&imp.key_handler, key) {
+                    KeyResult::Action(action) => {
+                        if window.execute_key_action(action) {
+                            return glib::Propagation::Stop;
+                        }
+                    }
+                    KeyResult::StateChanged => {
+                        return glib::Propagation::Stop;
+                    }
+                    KeyResult::Unhandled => {}
+                }
+            }
+            glib::Propagation::Proceed
+        });
+
+        self.add_controller(controller);
+    }
+
+    /// Handle key press based on current mode
+    fn handle_mode_key(&self, key: gtk::gdk::Key) -> bool {
+        let imp = self.imp();
+
+        // Need document to be loaded for other mode operations
+        if !imp.pdf_view().has_document() {
+            return false;
+        }
+
+        let mode = imp.app_mode.borrow().clone();
 
         let result = match &mode {
             AppMode::Normal => handle_normal_mode_key(&imp.key_handler, key),
             AppMode::Visual { .. } => {
-                let doc_borrow = imp.pdf_view.document();
+                let pdf_view = imp.pdf_view();
+                let doc_borrow = pdf_view.document();
                 if let Some(ref doc) = *doc_borrow {
                     let mut cache = imp.text_cache.borrow_mut();
                     if let Some(ref mut cache) = *cache {
-                        handle_visual_mode_key(&imp.key_handler, key, &mode, cache, doc)
+                        handle_visual_mode_key(&imp.key_handler, key, &mode, cache, doc.as_ref())
                     } else {
                         KeyResult::Unhandled
                     }
@@ -552,6 +1589,26 @@ impl EyersWindow {
     /// Execute a key action
     fn execute_key_action(&self, action: KeyAction) -> bool {
         let imp = self.imp();
+        if !imp.action_recorder.borrow().is_recording() {
+            return self.perform_key_action(action);
+        }
+
+        // Transitions made since the last action (mouse, panels) are logged first
+        let description = format!("{:?}", action);
+        imp.action_recorder
+            .borrow_mut()
+            .observe_mode(&imp.app_mode.borrow());
+        let handled = self.perform_key_action(action);
+        imp.action_recorder.borrow_mut().record_action(
+            description,
+            handled,
+            &imp.app_mode.borrow(),
+        );
+        handled
+    }
+
+    fn perform_key_action(&self, action: KeyAction) -> bool {
+        let imp = self.imp();
 
         match action {
             KeyAction::None => true,
@@ -577,12 +1634,47 @@ impl EyersWindow {
                 true
             }
 
-            KeyAction::ScrollTOC(ScrollDir::Down) => {
-                let repeat = self.key_handler().count();
-                self.key_handler().reset();
-                for _ in 0..repeat {
-                    let result = self.toc_panel().select_next();
-                    if !result {
+            KeyAction::ToggleDistractionFree => {
+                self.toggle_distraction_free();
+                true
+            }
+
+            KeyAction::TogglePresentation => {
+                self.toggle_presentation();
+                true
+            }
+
+            KeyAction::ToggleTwoPage => {
+                imp.pdf_view().set_two_page(!imp.pdf_view().two_page());
+                true
+            }
+
+            KeyAction::TogglePaged => {
+                imp.pdf_view().set_paged(!imp.pdf_view().paged());
+                true
+            }
+
+            KeyAction::OpenCommandLine => {
+                imp.status_bar.show_command_line(":");
+                true
+            }
+
+            KeyAction::OpenSearch => {
+                let prompt = if imp.app_mode.borrow().has_selection() {
+                    command::SELECTION_SEARCH_PROMPT
+                } else {
+                    "/"
+                };
+                imp.status_bar.show_command_line(prompt);
+                true
+            }
+
+            KeyAction::ScrollTOC(ScrollDir::Down) => {
+                let repeat = self.key_handler().count();
+                self.key_handler().reset();
+                for _ in 0..repeat {
+                    let result = self.toc_panel().select_next();
+                    if !result {
                         break;
                     }
                 }
@@ -625,8 +1717,26 @@ impl EyersWindow {
                 true
             }
 
+            KeyAction::FocusTocSearch => {
+                self.toc_panel().focus_search();
+                true
+            }
+
+            KeyAction::ClearTocSearch => {
+                if self.toc_panel().has_search_query() {
+                    self.toc_panel().clear_search();
+                    self.toc_panel().select_first();
+                }
+                true
+            }
+
             KeyAction::OpenFile => {
-                self.show_open_dialog();
+                self.show_open_dialog(false);
+                true
+            }
+
+            KeyAction::OpenRecentFiles => {
+                self.show_recent_files();
                 true
             }
 
@@ -644,7 +1754,56 @@ impl EyersWindow {
             }
 
             KeyAction::ScrollToPage { page } => {
-                self.scroll_to_page(page as u16);
+                self.scroll_to_page_count(page);
+                true
+            }
+
+            KeyAction::ScrollToChapter { chapter } => {
+                self.scroll_to_chapter(chapter);
+                true
+            }
+
+            KeyAction::SwitchTab { forward, count } => {
+                let target = imp
+                    .tabs
+                    .borrow()
+                    .target(forward, count.map(|count| count as usize));
+                match target {
+                    Some(index) => self.switch_to_tab(index),
+                    None => self.show_toast("No such tab"),
+                }
+                true
+            }
+
+            KeyAction::JumpBack => {
+                self.jump_back();
+                true
+            }
+
+            KeyAction::JumpForward => {
+                self.jump_forward();
+                true
+            }
+
+            KeyAction::SetMark { letter } => {
+                self.set_mark(letter);
+                true
+            }
+
+            KeyAction::JumpToMark { letter } => {
+                self.jump_to_mark(letter);
+                true
+            }
+
+            KeyAction::FlipPage { forward } => {
+                imp.pdf_view().flip_page(forward);
+                if imp.presentation.borrow().is_some() {
+                    self.fade_in_pages();
+                }
+                // Keep the Visual mode cursor on the page shown
+                if let Some(cursor) = self.compute_word_at_viewport_anchor() {
+                    self.move_cursor(cursor);
+                }
                 true
             }
 
@@ -668,11 +1827,12 @@ impl EyersWindow {
                     *mode = AppMode::enter_visual(cursor);
                     drop(mode);
                     self.update_mode_display();
-                    imp.pdf_view.set_cursor(Some(cursor));
+                    imp.pdf_view().set_cursor(Some(cursor));
                     self.update_highlights();
                     true
                 } else {
                     println!("Could not find first visible word");
+                    self.show_toast("No text on this page. :ocr recognizes scanned pages");
                     false
                 }
             }
@@ -683,9 +1843,9 @@ impl EyersWindow {
                 *mode = AppMode::exit_to_normal();
                 drop(mode);
                 self.update_mode_display();
-                imp.pdf_view.set_cursor(None);
-                imp.pdf_view.clear_selection();
-                imp.pdf_view.clear_all_highlights();
+                imp.pdf_view().set_cursor(None);
+                imp.pdf_view().clear_selection();
+                imp.pdf_view().clear_all_highlights();
                 true
             }
 
@@ -694,12 +1854,20 @@ impl EyersWindow {
                     let mut mode = imp.app_mode.borrow_mut();
                     mode.set_cursor(cursor);
                 }
-                imp.pdf_view.set_cursor(Some(cursor));
+                imp.pdf_view().set_cursor(Some(cursor));
                 self.update_selection_display();
                 self.ensure_cursor_visible(cursor);
                 true
             }
 
+            KeyAction::SelectRange { start, end } => {
+                imp.app_mode.borrow_mut().select(start, end);
+                imp.pdf_view().set_cursor(Some(end));
+                self.update_selection_display();
+                self.ensure_cursor_visible(end);
+                true
+            }
+
             KeyAction::ToggleSelection => {
                 {
                     let mut mode = imp.app_mode.borrow_mut();
@@ -710,14 +1878,14 @@ impl EyersWindow {
             }
 
             KeyAction::ClearSelection => {
-                imp.pdf_view.clear_selection();
+                imp.pdf_view().clear_selection();
                 self.update_highlights();
                 true
             }
 
             KeyAction::ShowDefinition { cursor } => {
-                if imp.pdf_view.has_popover() {
-                    imp.pdf_view.close_current_popover();
+                if imp.pdf_view().has_popover() {
+                    imp.pdf_view().close_current_popover();
                 } else {
                     self.show_definition_for_cursor(cursor);
                 }
@@ -738,16 +1906,77 @@ impl EyersWindow {
                 true
             }
 
+            KeyAction::ReadAloud { cursor, selection } => {
+                self.read_aloud(cursor, selection);
+                true
+            }
+
+            KeyAction::StopReading => {
+                self.stop_reading();
+                true
+            }
+
+            KeyAction::Pronounce => self.pronounce_at_cursor(),
+
+            KeyAction::Split { side_by_side } => {
+                self.open_split(side_by_side);
+                true
+            }
+
+            KeyAction::SwapSplit => {
+                self.swap_split();
+                true
+            }
+
+            KeyAction::CloseSplit => {
+                self.close_split();
+                true
+            }
+
+            KeyAction::WebSearch { wikipedia } => {
+                self.search_on_web(wikipedia);
+                true
+            }
+
             KeyAction::Annotate { cursor, selection } => {
                 self.handle_annotate_action(cursor, selection);
                 true
             }
 
+            KeyAction::AnnotateViewport => {
+                if self.reflow_active() {
+                    // The words selected in the text, or the first visible one
+                    let selection = imp.reflow_view.selection();
+                    let cursor = selection
+                        .map(|(start, _)| start)
+                        .or_else(|| imp.reflow_view.top_word());
+                    if let Some(cursor) = cursor {
+                        self.handle_annotate_action(cursor, selection);
+                    }
+                } else if let Some(cursor) = self.compute_first_visible_word() {
+                    // Anchor at the first visible word, staying in Normal mode
+                    self.handle_annotate_action(cursor, None);
+                }
+                true
+            }
+
             KeyAction::ExportAnnotations => {
                 self.show_export_annotations_dialog();
                 true
             }
 
+            KeyAction::ShowSavedPositions => {
+                self.show_positions_window();
+                true
+            }
+
+            KeyAction::ShowHistory => {
+                self.show_history_window();
+                true
+            }
+
+            KeyAction::ReloadDocument => self.reload_document(),
+
             KeyAction::FindForward { letter } => {
                 let repeat = self.key_handler().count();
                 self.key_handler().reset();
@@ -797,6 +2026,28 @@ impl EyersWindow {
                 true
             }
 
+            KeyAction::SearchNext => {
+                self.jump_to_search_match(true);
+                true
+            }
+
+            KeyAction::SearchPrevious => {
+                self.jump_to_search_match(false);
+                true
+            }
+
+            KeyAction::SkipToTextPageForward => {
+                self.key_handler().reset();
+                self.skip_to_text_page(true);
+                true
+            }
+
+            KeyAction::SkipToTextPageBackward => {
+                self.key_handler().reset();
+                self.skip_to_text_page(false);
+                true
+            }
+
             KeyAction::ZoomIn => {
                 self.zoom_in();
                 true
@@ -806,11 +2057,30 @@ impl EyersWindow {
                 self.zoom_out();
                 true
             }
+
+            KeyAction::SetZoomMode(mode) => {
+                self.apply_zoom_mode(mode);
+                self.show_toast(&format!("Zoom: {}", mode.label()));
+                true
+            }
+
+            KeyAction::Rotate {
+                clockwise,
+                whole_document,
+            } => {
+                self.rotate_pages(if clockwise { 1 } else { -1 }, whole_document);
+                true
+            }
         }
     }
 
     /// Scroll the viewport by a percentage
     fn scroll_by_percent(&self, x_percent: f64, y_percent: f64) {
+        if self.reflow_active() {
+            self.imp().reflow_view.scroll_by_percent(y_percent);
+            return;
+        }
+
         if let Some(scrolled) = self.imp().scrolled_window.borrow().as_ref() {
             if y_percent != 0.0 {
                 let vadj = scrolled.vadjustment();
@@ -842,12 +2112,15 @@ impl EyersWindow {
         };
 
         self.scroll_by_percent(0.0, y_percent);
+        // There is no cursor in the reflowed text
+        if self.reflow_active() {
+            return;
+        }
         match direction {
             ScrollDir::Up => {
-                // In Visual mode, update cursor to word at ~20% from viewport top
-                // This feels more natural than the very first word at the top edge
-                if let Some(cursor) = self.compute_word_at_viewport_offset(DEFAULT_VIEWPORT_OFFSET)
-                {
+                // In Visual mode, update cursor to the word at the cursor anchor
+                // (20% from viewport top by default), more natural than the top edge
+                if let Some(cursor) = self.compute_word_at_viewport_anchor() {
                     self.move_cursor(cursor);
                 }
             }
@@ -855,8 +2128,7 @@ impl EyersWindow {
             // below so it stays on the same page... its still buggy but is a workaround
             // TODO: fix it
             ScrollDir::Down => {
-                if let Some(cursor) = self.compute_word_at_viewport_offset(DEFAULT_VIEWPORT_OFFSET)
-                {
+                if let Some(cursor) = self.compute_word_at_viewport_anchor() {
                     let mut new_cursor: Option<WordCursor> = Some(cursor);
                     if let Some(current_cursor) = self.imp().app_mode.borrow().cursor() {
                         if current_cursor == cursor {
@@ -874,19 +2146,61 @@ impl EyersWindow {
     }
 
     fn scroll_to_page(&self, page_number: u16) {
-        let pdf_view = &self.imp().pdf_view;
-        pdf_view.scroll_to_page(page_number);
-        if let Some(cursor) = self.compute_word_at_viewport_offset(DEFAULT_VIEWPORT_OFFSET) {
+        self.record_jump();
+        self.pdf_view().scroll_to_page(page_number);
+        if let Some(cursor) = self.compute_word_at_viewport_anchor() {
             self.move_cursor(cursor)
         }
     }
 
+    /// Scroll to page `count` of `[count]gg`, a printed page number when page
+    /// labels are used
+    fn scroll_to_page_count(&self, count: u32) {
+        let imp = self.imp();
+        let page_count = imp.pdf_view().page_count();
+        if page_count == 0 {
+            return;
+        }
+
+        if imp.use_page_labels.get() && imp.page_labels.borrow().is_none() {
+            let labels = imp.pdf_view().document().as_ref().map(|doc| {
+                (0..doc.page_count())
+                    .map(|page_index| doc.page_label(page_index))
+                    .collect()
+            });
+            imp.page_labels.replace(labels);
+        }
+
+        let page_index = if imp.use_page_labels.get() {
+            let labels = imp.page_labels.borrow();
+            page_labels::page_index_for_count(labels.as_deref().unwrap_or(&[]), page_count, count)
+        } else {
+            page_labels::page_index_for_count(&[], page_count, count)
+        };
+        self.scroll_to_page(page_index as u16);
+    }
+
+    /// Scroll to the Nth (1-based) top-level chapter of the outline
+    fn scroll_to_chapter(&self, chapter: u32) {
+        let bookmarks = self.imp().pdf_view().bookmarks();
+        if bookmarks.is_empty() {
+            self.show_toast("This document has no outline");
+            return;
+        }
+
+        match bookmarks.get((chapter as usize).saturating_sub(1)) {
+            Some(entry) => self.scroll_to_page(entry.page_index),
+            None => self.show_toast(&format!("Only {} chapters", bookmarks.len())),
+        }
+    }
+
     /// Scroll to the start of the document (gg in vim)
     fn scroll_to_document_start(&self) {
         let imp = self.imp();
+        self.record_jump();
 
         // Scroll to page 0
-        imp.pdf_view.scroll_to_page(0);
+        imp.pdf_view().scroll_to_page(0);
 
         // In Visual mode, move cursor to first word of first page
         if let Some(cursor) = self.compute_first_word_of_page(0) {
@@ -896,11 +2210,13 @@ impl EyersWindow {
 
     fn scroll_to_document_end(&self) {
         let imp = self.imp();
+        self.record_jump();
 
-        let doc_borrow = imp.pdf_view.document();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
         let last_page = match doc_borrow.as_ref() {
             Some(doc) => {
-                let page_count = doc.pages().len();
+                let page_count = doc.page_count();
                 if page_count > 0 {
                     page_count - 1
                 } else {
@@ -911,9 +2227,9 @@ impl EyersWindow {
         };
         drop(doc_borrow);
 
-        imp.pdf_view.scroll_to_page(last_page);
+        pdf_view.scroll_to_page(last_page as u16);
 
-        if let Some(cursor) = self.compute_last_word_of_page(last_page as usize) {
+        if let Some(cursor) = self.compute_last_word_of_page(last_page) {
             self.move_cursor(cursor);
         }
     }
@@ -925,7 +2241,7 @@ impl EyersWindow {
                 let mut mode = imp.app_mode.borrow_mut();
                 mode.set_cursor(cursor);
             }
-            imp.pdf_view.set_cursor(Some(cursor));
+            imp.pdf_view().set_cursor(Some(cursor));
             self.update_selection_display();
             self.ensure_cursor_visible(cursor);
             self.print_cursor_word(cursor);
@@ -936,14 +2252,15 @@ impl EyersWindow {
     fn compute_first_word_of_page(&self, page_index: usize) -> Option<WordCursor> {
         let imp = self.imp();
 
-        let doc_borrow = imp.pdf_view.document();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
         let doc = doc_borrow.as_ref()?;
 
         let mut cache = imp.text_cache.borrow_mut();
 
         let cache = cache.as_mut()?;
 
-        if let Some(text_map) = cache.get_or_build(page_index, doc) {
+        if let Some(text_map) = cache.get_or_build(page_index, doc.as_ref()) {
             if text_map.word_count() > 0 {
                 return Some(WordCursor::new(page_index, 0));
             }
@@ -956,13 +2273,14 @@ impl EyersWindow {
     fn compute_last_word_of_page(&self, page_index: usize) -> Option<WordCursor> {
         let imp = self.imp();
 
-        let doc_borrow = imp.pdf_view.document();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
         let doc = doc_borrow.as_ref()?;
 
         let mut cache = imp.text_cache.borrow_mut();
         let cache = cache.as_mut()?;
 
-        if let Some(text_map) = cache.get_or_build(page_index, doc) {
+        if let Some(text_map) = cache.get_or_build(page_index, doc.as_ref()) {
             let word_count = text_map.word_count();
             if word_count > 0 {
                 return Some(WordCursor::new(page_index, word_count - 1));
@@ -975,27 +2293,27 @@ impl EyersWindow {
     /// Zoom in by 10%, max 300%
     fn zoom_in(&self) {
         let imp = self.imp();
-        let current_zoom = imp.pdf_view.zoom_level();
-        let new_zoom = (current_zoom * 1.1).min(3.0);
+        let current_zoom = imp.pdf_view().zoom_level();
+        let new_zoom = (current_zoom * 1.1).min(MAX_ZOOM);
 
         if (new_zoom - current_zoom).abs() > 0.001 {
-            self.apply_zoom(new_zoom);
+            self.apply_zoom_mode(ZoomMode::Percentage(new_zoom));
         }
     }
 
     /// Zoom out by 10%, min 50%
     fn zoom_out(&self) {
         let imp = self.imp();
-        let current_zoom = imp.pdf_view.zoom_level();
-        let new_zoom = (current_zoom / 1.1).max(0.5);
+        let current_zoom = imp.pdf_view().zoom_level();
+        let new_zoom = (current_zoom / 1.1).max(MIN_ZOOM);
 
         if (new_zoom - current_zoom).abs() > 0.001 {
-            self.apply_zoom(new_zoom);
+            self.apply_zoom_mode(ZoomMode::Percentage(new_zoom));
         }
     }
 
-    /// Apply a new zoom level, preserving scroll position
-    fn apply_zoom(&self, new_zoom: f64) {
+    /// Apply a zoom mode, preserving scroll position
+    fn apply_zoom_mode(&self, mode: ZoomMode) {
         let imp = self.imp();
 
         // Get current scroll position as a ratio
@@ -1011,8 +2329,10 @@ impl EyersWindow {
             0.0
         };
 
-        // Apply the new zoom level (this re-renders all pages)
-        imp.pdf_view.set_zoom_level(new_zoom);
+        // Apply the new zoom level (this re-renders all pages when it changed)
+        if !imp.pdf_view().set_zoom_mode(mode) {
+            return;
+        }
 
         // Restore scroll position after a brief delay to allow layout to update
         let window_weak = self.downgrade();
@@ -1032,11 +2352,102 @@ impl EyersWindow {
                 } else {
                     // Always update annotations even when not in visual mode
                     window.update_annotation_highlights();
+                    window.update_search_highlights();
+                    window.update_ink_highlights();
+                }
+            }
+        });
+
+        println!("Zoom: {:.0}%", imp.pdf_view().zoom_level() * 100.0);
+    }
+
+    /// Fit the zoom level to the view again once its size settles, in fit modes
+    fn schedule_zoom_fit(&self) {
+        let imp = self.imp();
+        if !imp.pdf_view().zoom_mode().fits_view() || imp.zoom_fit_pending.replace(true) {
+            return;
+        }
+
+        let window_weak = self.downgrade();
+        glib::timeout_add_local_once(
+            std::time::Duration::from_millis(ZOOM_FIT_DELAY_MS),
+            move || {
+                if let Some(window) = window_weak.upgrade() {
+                    window.imp().zoom_fit_pending.set(false);
+                    window.apply_zoom_mode(window.imp().pdf_view().zoom_mode());
+                }
+            },
+        );
+    }
+
+    /// Fit modes follow the size of the view, and the page layout
+    fn setup_zoom_fitting(&self, view: &PdfView, scrolled: &ScrolledWindow) {
+        for adjustment in [scrolled.hadjustment(), scrolled.vadjustment()] {
+            let window_weak = self.downgrade();
+            adjustment.connect_page_size_notify(move |_| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.schedule_zoom_fit();
+                }
+            });
+        }
+
+        let window_weak = self.downgrade();
+        view.connect_two_page_notify(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.schedule_zoom_fit();
+            }
+        });
+    }
+
+    /// Turn every page, or only the current one, a quarter turn clockwise when
+    /// `quarter_turns` is positive
+    fn rotate_pages(&self, quarter_turns: i32, whole_document: bool) {
+        let imp = self.imp();
+        if imp.pdf_view().document().is_none() {
+            return;
+        }
+
+        let page_index = imp.pdf_view().current_page() as usize;
+        let rotation = if whole_document {
+            imp.pdf_view().rotate_document(quarter_turns);
+            imp.pdf_view().document_rotation()
+        } else {
+            imp.pdf_view().rotate_page(page_index, quarter_turns);
+            imp.pdf_view().page_rotation(page_index)
+        };
+        imp.annotation_rects.borrow_mut().clear();
+        self.schedule_zoom_fit();
+
+        // Page sizes changed, stay on the page and move the highlights with it
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().pdf_view().scroll_to_page(page_index as u16);
+
+                if window.imp().app_mode.borrow().is_visual() {
+                    window.update_highlights();
+                } else {
+                    window.update_annotation_highlights();
+                    window.update_search_highlights();
+                    window.update_ink_highlights();
                 }
             }
         });
 
-        println!("Zoom: {:.0}%", new_zoom * 100.0);
+        if whole_document {
+            self.show_toast(&format!("Pages rotated {}°", rotation.degrees()));
+        } else {
+            self.show_toast(&format!(
+                "Page {} rotated {}°",
+                page_index + 1,
+                rotation.degrees()
+            ));
+        }
+    }
+
+    /// Word at the configured cursor anchor, where the cursor lands after jumps
+    fn compute_word_at_viewport_anchor(&self) -> Option<WordCursor> {
+        self.compute_word_at_viewport_offset(self.imp().viewport_anchor.get())
     }
 
     /// Compute a word at a given offset from the top of the viewport
@@ -1053,47 +2464,53 @@ impl EyersWindow {
         // Target position in screen coordinates (absolute, not relative to page)
         let target_y = scroll_y + viewport_height * offset_percent;
 
-        let doc_borrow = imp.pdf_view.document();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
         let doc = doc_borrow.as_ref()?;
 
         let mut cache = imp.text_cache.borrow_mut();
         let cache = cache.as_mut()?;
 
-        let page_pictures = imp.pdf_view.page_pictures();
-        const SPACING: f64 = 10.0;
+        let page_tops = pdf_view.page_tops();
+        let page_pictures = pdf_view.page_pictures();
 
         for (page_index, picture) in page_pictures.iter().enumerate() {
             let nat_size = picture.preferred_size().1;
             let picture_height = nat_size.height() as f64;
 
-            let page_top = page_index as f64 * (picture_height + SPACING);
+            let Some(page_top) = page_tops[page_index] else {
+                continue;
+            };
             let page_bottom = page_top + picture_height;
 
             // Check if the target Y falls within this page
             if target_y >= page_top && target_y < page_bottom {
-                if let Some(text_map) = cache.get_or_build(page_index, doc) {
+                if let Some(text_map) = cache.get_or_build(page_index, doc.as_ref()) {
                     if text_map.word_count() > 0 {
-                        let page_width_pts = text_map.page_width;
-                        let page_height_pts = text_map.page_height;
-                        let render_width = crate::services::pdf_text::get_render_width_for_zoom(
-                            imp.pdf_view.zoom_level(),
-                        );
-                        let scale = render_width as f64 / page_width_pts;
+                        let page_size = (text_map.page_width, text_map.page_height);
+                        let render_width = pdf_view.page_render_width(page_index);
+                        let rotation = pdf_view.page_rotation(page_index);
+                        let crop = pdf_view.page_crop(page_index);
 
                         // Convert target_y to position within page (screen coords relative to page)
                         let target_y_in_page = target_y - page_top;
 
-                        // Convert to PDF coords (y is flipped)
-                        let target_pdf_y = page_height_pts - (target_y_in_page / scale);
-
                         // Find word closest to this y-coordinate
-                        // We'll search for a word whose center_y is closest to target_pdf_y
+                        // We'll search for a word whose center is closest to it on screen,
+                        // which is along the PDF x axis on a page turned sideways
                         let mut best_word_idx: Option<usize> = None;
                         let mut best_distance = f64::MAX;
 
                         for idx in 0..text_map.word_count() {
                             if let Some(word) = text_map.get_word(idx) {
-                                let distance = (word.center_y - target_pdf_y).abs();
+                                let (_, word_y) = pdf_point_to_screen(
+                                    (word.center_x, word.center_y),
+                                    page_size,
+                                    render_width,
+                                    rotation,
+                                    crop,
+                                );
+                                let distance = (word_y - target_y_in_page).abs();
                                 if distance < best_distance {
                                     best_distance = distance;
                                     best_word_idx = Some(idx);
@@ -1126,49 +2543,70 @@ impl EyersWindow {
         let scroll_y = vadj.value();
         let viewport_height = vadj.page_size();
 
-        let doc_borrow = imp.pdf_view.document();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
         let doc = doc_borrow.as_ref()?;
 
         let mut cache = imp.text_cache.try_borrow_mut().ok()?;
         let cache = cache.as_mut()?;
 
         // Find which page is at the top of the viewport
-        let page_pictures = imp.pdf_view.page_pictures();
-        let spacing = 10.0;
+        let page_tops = pdf_view.page_tops();
+        let page_pictures = pdf_view.page_pictures();
 
         for (page_index, picture) in page_pictures.iter().enumerate() {
             let nat_size = picture.preferred_size().1;
             let picture_height = nat_size.height() as f64;
 
-            let page_top = page_index as f64 * (picture_height + spacing);
+            let Some(page_top) = page_tops[page_index] else {
+                continue;
+            };
             let page_bottom = page_top + picture_height;
 
             // Check if this page is visible
             if page_bottom > scroll_y && page_top < scroll_y + viewport_height {
                 // Get or build text map for this page
-                if let Some(text_map) = cache.get_or_build(page_index, doc) {
+                if let Some(text_map) = cache.get_or_build(page_index, doc.as_ref()) {
                     if text_map.word_count() > 0 {
                         // Calculate viewport rect in PDF coordinates
-                        let page_width_pts = text_map.page_width;
-                        let page_height_pts = text_map.page_height;
-                        let render_width = crate::services::pdf_text::get_render_width_for_zoom(
-                            imp.pdf_view.zoom_level(),
-                        );
-                        let scale = render_width as f64 / page_width_pts;
+                        let page_size = (text_map.page_width, text_map.page_height);
+                        let render_width = pdf_view.page_render_width(page_index);
+                        let rotation = pdf_view.page_rotation(page_index);
+                        let crop = pdf_view.page_crop(page_index);
 
                         // Visible portion of this page in screen coords
                         let visible_top_screen = (scroll_y - page_top).max(0.0);
                         let visible_bottom_screen =
                             ((scroll_y + viewport_height) - page_top).min(picture_height);
 
-                        // Convert to PDF coords (y is flipped)
-                        let visible_top_pdf = page_height_pts - (visible_top_screen / scale);
-                        let visible_bottom_pdf = page_height_pts - (visible_bottom_screen / scale);
+                        // Convert its corners to PDF coords (y is flipped, and the page
+                        // may be turned)
+                        let top_left = calculate_click_coordinates_with_offset(
+                            0.0,
+                            visible_top_screen,
+                            page_size,
+                            0.0,
+                            render_width,
+                            rotation,
+                            crop,
+                        );
+                        let bottom_right = calculate_click_coordinates_with_offset(
+                            nat_size.width() as f64,
+                            visible_bottom_screen,
+                            page_size,
+                            0.0,
+                            render_width,
+                            rotation,
+                            crop,
+                        );
 
                         // Find first word in this rect
-                        if let Some(word_index) =
-                            text_map.first_word_in_rect(visible_top_pdf, visible_bottom_pdf)
-                        {
+                        if let Some(word_index) = text_map.first_word_in_rect(
+                            top_left.pdf_x.min(bottom_right.pdf_x),
+                            top_left.pdf_y.max(bottom_right.pdf_y),
+                            top_left.pdf_x.max(bottom_right.pdf_x),
+                            top_left.pdf_y.min(bottom_right.pdf_y),
+                        ) {
                             return Some(WordCursor::new(page_index, word_index));
                         }
 
@@ -1191,6 +2629,9 @@ impl EyersWindow {
         // Enable/disable annotate button based on mode
         let is_visual = mode.is_visual();
         imp.header_bar.annotate_button().set_sensitive(is_visual);
+        if !is_visual {
+            imp.status_bar.set_selection_count(None);
+        }
     }
 
     /// Debug helper: print the word at cursor position
@@ -1210,182 +2651,221 @@ impl EyersWindow {
     fn update_selection_display(&self) {
         let mode = self.imp().app_mode.borrow();
         if let Some((start, end)) = mode.selection_range() {
-            self.imp().pdf_view.set_selection(Some((start, end)));
+            self.imp().pdf_view().set_selection(Some((start, end)));
         } else {
-            self.imp().pdf_view.clear_selection();
+            self.imp().pdf_view().clear_selection();
         }
         drop(mode);
-        self.update_highlights();
+        self.schedule_highlight_update();
+        self.schedule_primary_selection_update();
     }
 
-    /// Update all highlight overlays based on current cursor and selection
-    fn update_highlights(&self) {
+    /// Publish the Visual mode selection to the primary clipboard on idle, so
+    /// middle-click paste, clipboard managers and accessibility tools see it
+    /// without an explicit `y`, and count its words in the status bar
+    fn schedule_primary_selection_update(&self) {
         let imp = self.imp();
 
-        // Clear all existing highlights first
-        imp.pdf_view.clear_all_highlights();
+        if imp.primary_selection_pending.get() {
+            return;
+        }
+        imp.primary_selection_pending.set(true);
+
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().primary_selection_pending.set(false);
+                window.update_selection_count();
+                window.publish_primary_selection();
+            }
+        });
+    }
 
-        let cursor = imp.pdf_view.cursor();
-        let selection = imp.pdf_view.selection();
+    /// Show the words and characters of the selection as it would be copied
+    fn update_selection_count(&self) {
+        let imp = self.imp();
 
-        // Scope the cache borrow so it's dropped before calling update_annotation_highlights
-        {
+        let count = imp
+            .app_mode
+            .borrow()
+            .selection_range()
+            .and_then(|(start, end)| {
+                let cache = imp.text_cache.borrow();
+                let text = imp
+                    .copy_join_style
+                    .get()
+                    .apply(&cache.as_ref()?.extract_text(start, end));
+                Some((text.split_whitespace().count(), text.chars().count()))
+            });
+        imp.status_bar.set_selection_count(count);
+    }
+
+    fn publish_primary_selection(&self) {
+        let imp = self.imp();
+
+        // The primary clipboard keeps the last selection after it is cleared, as
+        // in other applications
+        let Some((start, end)) = imp.app_mode.borrow().selection_range() else {
+            return;
+        };
+        if imp.primary_selection.get() == Some((start, end)) {
+            return;
+        }
+
+        let text = {
             let cache = imp.text_cache.borrow();
-            let cache = match cache.as_ref() {
-                Some(c) => c,
+            match cache.as_ref() {
+                Some(c) => imp.copy_join_style.get().apply(&c.extract_text(start, end)),
                 None => return,
-            };
+            }
+        };
 
-            // Get page pictures for calculating offsets
-            let page_pictures = imp.pdf_view.page_pictures();
+        if !text.is_empty() {
+            self.primary_clipboard().set_text(&text);
+            imp.primary_selection.set(Some((start, end)));
+        }
+    }
 
-            // Get effective render width based on zoom level
-            let render_width =
-                crate::services::pdf_text::get_render_width_for_zoom(imp.pdf_view.zoom_level());
+    /// Rebuild all highlight overlays from scratch (after zoom, resize or mode changes)
+    fn update_highlights(&self) {
+        let imp = self.imp();
 
-            // Helper closure to get x_offset for a page
-            let get_x_offset = |page_index: usize| -> f64 {
-                page_pictures
-                    .get(page_index)
-                    .map(|pic| calculate_picture_offset(pic))
-                    .unwrap_or(0.0)
-            };
+        // Clear all existing highlights first
+        imp.pdf_view().clear_all_highlights();
+        imp.highlighted_pages.borrow_mut().clear();
 
-            // Build a map of page_index -> (cursor_rect, selection_rects)
-            let mut page_highlights: std::collections::HashMap<
-                usize,
-                (Option<HighlightRect>, Vec<HighlightRect>),
-            > = std::collections::HashMap::new();
+        self.apply_highlight_changes();
 
-            // Add cursor highlight
-            if let Some(cursor) = cursor {
-                if let Some(text_map) = cache.get(cursor.page_index) {
-                    if let Some(word) = text_map.get_word(cursor.word_index) {
-                        let x_offset = get_x_offset(cursor.page_index);
-                        let rect = HighlightRect::from_pdf_bounds(
-                            &word.bounds,
-                            text_map.page_width,
-                            text_map.page_height,
-                            x_offset,
-                            render_width,
-                        );
-                        page_highlights
-                            .entry(cursor.page_index)
-                            .or_insert((None, Vec::new()))
-                            .0 = Some(rect);
-                    }
-                }
-            }
+        // Now update annotation highlights with the current offset values
+        self.update_annotation_highlights();
+        self.update_search_highlights();
+        self.update_ink_highlights();
+    }
 
-            // Add selection highlights
-            if let Some((start, end)) = selection {
-                let (first, last) =
-                    if (start.page_index, start.word_index) <= (end.page_index, end.word_index) {
-                        (start, end)
-                    } else {
-                        (end, start)
-                    };
-
-                if first.page_index == last.page_index {
-                    // Same page selection
-                    if let Some(text_map) = cache.get(first.page_index) {
-                        let x_offset = get_x_offset(first.page_index);
-                        for idx in first.word_index..=last.word_index {
-                            if let Some(word) = text_map.get_word(idx) {
-                                let rect = HighlightRect::from_pdf_bounds(
-                                    &word.bounds,
-                                    text_map.page_width,
-                                    text_map.page_height,
-                                    x_offset,
-                                    render_width,
-                                );
-                                page_highlights
-                                    .entry(first.page_index)
-                                    .or_insert((None, Vec::new()))
-                                    .1
-                                    .push(rect);
-                            }
-                        }
-                    }
-                } else {
-                    // Cross-page selection
-                    // First page: from first.word_index to end
-                    if let Some(text_map) = cache.get(first.page_index) {
-                        let x_offset = get_x_offset(first.page_index);
-                        for idx in first.word_index..text_map.word_count() {
-                            if let Some(word) = text_map.get_word(idx) {
-                                let rect = HighlightRect::from_pdf_bounds(
-                                    &word.bounds,
-                                    text_map.page_width,
-                                    text_map.page_height,
-                                    x_offset,
-                                    render_width,
-                                );
-                                page_highlights
-                                    .entry(first.page_index)
-                                    .or_insert((None, Vec::new()))
-                                    .1
-                                    .push(rect);
-                            }
-                        }
-                    }
+    /// Coalesce cursor/selection highlight updates into one pass on idle
+    fn schedule_highlight_update(&self) {
+        let imp = self.imp();
 
-                    // Middle pages
-                    for page_idx in (first.page_index + 1)..last.page_index {
-                        if let Some(text_map) = cache.get(page_idx) {
-                            let x_offset = get_x_offset(page_idx);
-                            for idx in 0..text_map.word_count() {
-                                if let Some(word) = text_map.get_word(idx) {
-                                    let rect = HighlightRect::from_pdf_bounds(
-                                        &word.bounds,
-                                        text_map.page_width,
-                                        text_map.page_height,
-                                        x_offset,
-                                        render_width,
-                                    );
-                                    page_highlights
-                                        .entry(page_idx)
-                                        .or_insert((None, Vec::new()))
-                                        .1
-                                        .push(rect);
-                                }
-                            }
-                        }
-                    }
+        if imp.highlight_update_pending.get() {
+            return;
+        }
+        imp.highlight_update_pending.set(true);
 
-                    // Last page: from 0 to last.word_index
-                    if let Some(text_map) = cache.get(last.page_index) {
-                        let x_offset = get_x_offset(last.page_index);
-                        for idx in 0..=last.word_index {
-                            if let Some(word) = text_map.get_word(idx) {
-                                let rect = HighlightRect::from_pdf_bounds(
-                                    &word.bounds,
-                                    text_map.page_width,
-                                    text_map.page_height,
-                                    x_offset,
-                                    render_width,
-                                );
-                                page_highlights
-                                    .entry(last.page_index)
-                                    .or_insert((None, Vec::new()))
-                                    .1
-                                    .push(rect);
-                            }
-                        }
-                    }
-                }
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().highlight_update_pending.set(false);
+                window.apply_highlight_changes();
             }
+        });
+    }
 
-            // Apply highlights to overlays
-            for (page_index, (cursor_rect, selection_rects)) in page_highlights {
-                if let Some(overlay) = imp.pdf_view.highlight_overlay(page_index) {
-                    overlay.set_highlights(cursor_rect, selection_rects);
-                }
+    /// Bring cursor and selection highlights up to date, only touching the overlays
+    /// of pages whose highlighted words changed
+    fn apply_highlight_changes(&self) {
+        let imp = self.imp();
+
+        let cursor = imp.pdf_view().cursor();
+        let selection = imp.pdf_view().selection();
+
+        let cache = imp.text_cache.borrow();
+        let cache = match cache.as_ref() {
+            Some(c) => c,
+            None => return,
+        };
+
+        // Wanted (cursor word, selected word range) per page
+        let mut wanted: HashMap<usize, (Option<usize>, Range<usize>)> = HashMap::new();
+
+        if let Some(cursor) = cursor {
+            wanted.entry(cursor.page_index).or_insert((None, 0..0)).0 = Some(cursor.word_index);
+        }
+
+        if let Some((start, end)) = selection {
+            let (first, last) = if start <= end {
+                (start, end)
+            } else {
+                (end, start)
+            };
+
+            for page_index in first.page_index..=last.page_index {
+                let Some(text_map) = cache.get(page_index) else {
+                    continue;
+                };
+
+                let word_start = if page_index == first.page_index {
+                    first.word_index
+                } else {
+                    0
+                };
+                let word_end = if page_index == last.page_index {
+                    (last.word_index + 1).min(text_map.word_count())
+                } else {
+                    text_map.word_count()
+                };
+
+                wanted.entry(page_index).or_insert((None, 0..0)).1 = word_start..word_end;
             }
-        } // cache borrow is dropped here
+        }
 
-        // Now update annotation highlights with the current offset values
-        self.update_annotation_highlights();
+        let pdf_view = imp.pdf_view();
+        let page_pictures = pdf_view.page_pictures();
+
+        // Pages highlighted before and pages to highlight now
+        let mut highlighted_pages = imp.highlighted_pages.borrow_mut();
+        let mut pages: Vec<usize> = highlighted_pages
+            .iter()
+            .chain(wanted.keys())
+            .copied()
+            .collect();
+        pages.sort_unstable();
+        pages.dedup();
+
+        for page_index in pages {
+            let Some(overlay) = pdf_view.highlight_overlay(page_index) else {
+                continue;
+            };
+
+            let (cursor_word, selected_range) =
+                wanted.get(&page_index).cloned().unwrap_or((None, 0..0));
+
+            let x_offset = page_pictures
+                .get(page_index)
+                .map(|pic| calculate_picture_offset(pic))
+                .unwrap_or(0.0);
+            let render_width = pdf_view.page_render_width(page_index);
+            let rotation = pdf_view.page_rotation(page_index);
+            let crop = pdf_view.page_crop(page_index);
+            let word_rect = |word_index: usize| -> Option<HighlightRect> {
+                let text_map = cache.get(page_index)?;
+                let word = text_map.get_word(word_index)?;
+                Some(HighlightRect::from_pdf_bounds(
+                    &word.bounds,
+                    text_map.page_width,
+                    text_map.page_height,
+                    x_offset,
+                    render_width,
+                    rotation,
+                    crop,
+                ))
+            };
+
+            overlay.set_cursor(cursor_word.and_then(&word_rect));
+
+            let current = overlay.selection_words();
+            let remove: Vec<usize> = current
+                .iter()
+                .copied()
+                .filter(|idx| !selected_range.contains(idx))
+                .collect();
+            let add: Vec<(usize, HighlightRect)> = selected_range
+                .filter(|idx| current.binary_search(idx).is_err())
+                .filter_map(|idx| word_rect(idx).map(|rect| (idx, rect)))
+                .collect();
+            overlay.update_selection(&remove, add);
+        }
+
+        *highlighted_pages = wanted.into_keys().collect();
     }
 
     /// Ensure the cursor is visible, auto-scrolling if needed
@@ -1398,7 +2878,8 @@ impl EyersWindow {
             None => return,
         };
 
-        let doc_borrow = imp.pdf_view.document();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
         if doc_borrow.is_none() {
             return;
         }
@@ -1420,44 +2901,42 @@ impl EyersWindow {
         };
 
         // Calculate word position in screen coordinates
-        let page_pictures = imp.pdf_view.page_pictures();
+        let page_pictures = pdf_view.page_pictures();
         let picture = match page_pictures.get(cursor.page_index) {
             Some(p) => p,
             None => return,
         };
 
-        let nat_size = picture.preferred_size().1;
-        let picture_height = nat_size.height() as f64;
-        let spacing = 10.0;
+        // Paged mode may not show the page the cursor moved to yet
+        if !pdf_view.is_page_shown(cursor.page_index) {
+            pdf_view.scroll_to_page(cursor.page_index as u16);
+        }
+        let Some(&Some(page_top)) = pdf_view.page_tops().get(cursor.page_index) else {
+            return;
+        };
 
-        let page_top = cursor.page_index as f64 * (picture_height + spacing);
+        // Pages sit side by side in the two-page layout and are centered when
+        // narrower than the view
+        let page_left = picture
+            .compute_point(&pdf_view, &graphene::Point::zero())
+            .map(|origin| origin.x() as f64)
+            .unwrap_or(0.0)
+            + calculate_picture_offset(picture);
 
         // Convert word center to screen coords
-        let render_width =
-            crate::services::pdf_text::get_render_width_for_zoom(imp.pdf_view.zoom_level());
-        let scale = render_width as f64 / text_map.page_width;
-        let word_y_screen = page_top + (text_map.page_height - word.center_y) * scale;
-
-        // Get viewport info
-        let vadj = scrolled.vadjustment();
-        let scroll_y = vadj.value();
-        let viewport_height = vadj.page_size();
-
-        // 20% margin
-        let margin = viewport_height * 0.2;
-        let visible_top = scroll_y + margin;
-        let visible_bottom = scroll_y + viewport_height - margin;
+        let (word_x, word_y) = pdf_point_to_screen(
+            (word.center_x, word.center_y),
+            (text_map.page_width, text_map.page_height),
+            pdf_view.page_render_width(cursor.page_index),
+            pdf_view.page_rotation(cursor.page_index),
+            pdf_view.page_crop(cursor.page_index),
+        );
+        let word_x_screen = page_left + word_x;
+        let word_y_screen = page_top + word_y;
 
-        // Auto-scroll if cursor is outside the comfortable zone
-        if word_y_screen < visible_top {
-            // Scroll up
-            let new_scroll = word_y_screen - margin;
-            vadj.set_value(new_scroll.max(vadj.lower()));
-        } else if word_y_screen > visible_bottom {
-            // Scroll down
-            let new_scroll = word_y_screen - viewport_height + margin;
-            vadj.set_value(new_scroll.min(vadj.upper() - viewport_height));
-        }
+        // 20% margin vertically, 10% horizontally
+        scroll_into_comfort_zone(&scrolled.vadjustment(), word_y_screen, 0.2);
+        scroll_into_comfort_zone(&scrolled.hadjustment(), word_x_screen, 0.1);
     }
 
     /// Show definition for the word at cursor position
@@ -1484,637 +2963,4839 @@ impl EyersWindow {
         let word_text = word.text.clone();
         println!("Definition for: {}", word_text);
 
-        // Use the definition popover
-        let page_pictures = imp.pdf_view.page_pictures();
-        if let Some(pic) = page_pictures.get(cursor.page_index) {
-            // Calculate screen position for popover (including x_offset for centering)
-            let render_width =
-                crate::services::pdf_text::get_render_width_for_zoom(imp.pdf_view.zoom_level());
-            let scale = render_width as f64 / text_map.page_width;
-            let x_offset = calculate_picture_offset(pic);
-            let screen_x = word.center_x * scale + x_offset;
-            let screen_y = (text_map.page_height - word.center_y) * scale;
-
-            let popover = crate::widgets::DefinitionPopover::new();
-            popover.show_at(pic, screen_x, screen_y);
-            popover.fetch_and_display(
-                word_text.clone(),
-                word_text.to_lowercase(),
-                imp.dictionary_language.get(),
-            );
+        // Use the definition popover
+        let pdf_view = imp.pdf_view();
+        let page_pictures = pdf_view.page_pictures();
+        if let Some(pic) = page_pictures.get(cursor.page_index) {
+            // Calculate screen position for popover (including x_offset for centering)
+            let (word_x, screen_y) = pdf_point_to_screen(
+                (word.center_x, word.center_y),
+                (text_map.page_width, text_map.page_height),
+                pdf_view.page_render_width(cursor.page_index),
+                pdf_view.page_rotation(cursor.page_index),
+                pdf_view.page_crop(cursor.page_index),
+            );
+            let screen_x = word_x + calculate_picture_offset(pic);
+
+            let popover = pdf_view.create_definition_popover();
+            pdf_view.track_lookup(
+                &popover,
+                cursor.page_index,
+                sentence_around_word(text_map, cursor.word_index).unwrap_or_default(),
+            );
+            popover.show_at(pic, screen_x, screen_y);
+            popover.fetch_and_display(
+                word_text.clone(),
+                word_text.to_lowercase(),
+                self.dictionary_language(),
+                pdf_view.dictionary_source(),
+            );
+
+            pdf_view.set_current_popover(Some(popover));
+        }
+    }
+
+    fn setup_clipboard_lookup(&self) {
+        let window_weak = self.downgrade();
+        self.clipboard().connect_changed(move |clipboard| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            // Text copied in Eyers itself, e.g. with `y`, isn't looked up
+            if !window.imp().settings.borrow().clipboard_lookup || clipboard.is_local() {
+                return;
+            }
+
+            let window_weak = window.downgrade();
+            clipboard.read_text_async(None::<&gio::Cancellable>, move |result| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                match result {
+                    Ok(Some(text)) => window.show_definition_for_copied_text(&text),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Failed to read the clipboard: {}", e),
+                }
+            });
+        });
+    }
+
+    /// Show the definition of a word or phrase copied in another application,
+    /// pointing at the top of the pages since it isn't on them
+    fn show_definition_for_copied_text(&self, text: &str) {
+        let Some(term) = extract_copied_term(text) else {
+            return;
+        };
+        let imp = self.imp();
+
+        let popover = imp.pdf_view().create_definition_popover();
+        let anchor = &imp.view_stack;
+        popover.show_at(anchor, anchor.width() as f64 / 2.0, 0.0);
+        if term.original.contains(' ') {
+            popover.fetch_and_display_phrase(
+                term.original,
+                term.lowercase,
+                self.dictionary_language(),
+                imp.pdf_view().dictionary_source(),
+            );
+        } else {
+            popover.fetch_and_display(
+                term.original,
+                term.lowercase,
+                self.dictionary_language(),
+                imp.pdf_view().dictionary_source(),
+            );
+        }
+
+        imp.pdf_view().set_current_popover(Some(popover));
+    }
+
+    /// Translate the text between start and end cursors
+    fn translate_range(&self, start: WordCursor, end: WordCursor) {
+        let imp = self.imp();
+
+        let cache = imp.text_cache.borrow();
+        let cache = match cache.as_ref() {
+            Some(c) => c,
+            None => return,
+        };
+
+        let text = cache.extract_text(start, end);
+        if !text.is_empty() {
+            imp.translation_panel.set_visible(true);
+            imp.translation_panel.translate(text);
+        }
+    }
+
+    /// Execute a find operation (f/F + char)
+    fn execute_find(&self, target_char: char, forward: bool) -> bool {
+        let imp = self.imp();
+
+        // Only works in Visual mode
+        let cursor = match imp.app_mode.borrow().cursor() {
+            Some(c) => c,
+            None => return false,
+        };
+
+        // Find the target word - scope the borrows
+        let new_cursor = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let doc = match doc_borrow.as_ref() {
+                Some(d) => d,
+                None => return false,
+            };
+
+            let mut cache = imp.text_cache.borrow_mut();
+            let cache = match cache.as_mut() {
+                Some(c) => c,
+                None => return false,
+            };
+
+            // Find word on same line starting with target_char
+            find_word_on_line_starting_with(
+                cache,
+                doc.as_ref(),
+                cursor.page_index,
+                cursor.word_index,
+                target_char,
+                forward,
+            )
+            .map(|result| WordCursor::new(result.page_index, result.word_index))
+        };
+
+        // Update cursor if found
+        if let Some(new_cursor) = new_cursor {
+            self.update_cursor(new_cursor);
+            true
+        } else {
+            // No match found, do nothing
+            false
+        }
+    }
+
+    // returns true if it finds one
+    fn search_annotation_forward(&self) -> bool {
+        self.jump_to_adjacent_annotation(true)
+    }
+
+    // returns true if it finds one
+    fn search_annotation_backward(&self) -> bool {
+        self.jump_to_adjacent_annotation(false)
+    }
+
+    /// Move the cursor to the start of the next (or previous) annotation in the
+    /// loaded annotations, wrapping around the document if enabled
+    fn jump_to_adjacent_annotation(&self, forward: bool) -> bool {
+        // Only works in Visual mode
+        let imp = self.imp();
+        let cursor = match imp.app_mode.borrow().cursor() {
+            Some(c) => c,
+            None => return false,
+        };
+
+        let position = (cursor.page_index, cursor.word_index);
+        let wrap = imp.annotation_wrap.get();
+        let target = {
+            let annotations = imp.annotations.borrow();
+            let target = if forward {
+                annotations::next_annotation(&annotations, position, wrap)
+            } else {
+                annotations::prev_annotation(&annotations, position, wrap)
+            };
+            target.cloned()
+        };
+        let Some(annotation) = target else {
+            return false;
+        };
+
+        let new_cursor = WordCursor::new(annotation.start_page, annotation.start_word);
+        let wrapped = if forward {
+            new_cursor <= cursor
+        } else {
+            new_cursor >= cursor
+        };
+        if wrapped {
+            self.show_toast(if forward {
+                "Wrapped to the first annotation"
+            } else {
+                "Wrapped to the last annotation"
+            });
+        }
+
+        self.record_jump();
+        self.update_cursor(new_cursor);
+        self.flash_annotation(&annotation);
+        true
+    }
+
+    /// Briefly emphasize an annotation so it is obvious which one a jump reached
+    fn flash_annotation(&self, annotation: &Annotation) {
+        let imp = self.imp();
+
+        let rects = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
+                return;
+            };
+            let mut cache = imp.text_cache.borrow_mut();
+            let Some(cache) = cache.as_mut() else {
+                return;
+            };
+            Self::compute_annotation_rects(cache, doc.as_ref(), annotation, &pdf_view)
+        };
+
+        let mut page_rects: HashMap<usize, Vec<HighlightRect>> = HashMap::new();
+        {
+            let pdf_view = imp.pdf_view();
+            let page_pictures = pdf_view.page_pictures();
+            for (page_index, mut rect) in rects {
+                rect.x += page_pictures
+                    .get(page_index)
+                    .map(calculate_picture_offset)
+                    .unwrap_or(0.0);
+                page_rects.entry(page_index).or_default().push(rect);
+            }
+        }
+
+        let flashed_pages: Vec<usize> = page_rects.keys().copied().collect();
+        {
+            let pdf_view = imp.pdf_view();
+            let overlays = pdf_view.highlight_overlays();
+            for (page_index, rects) in page_rects {
+                if let Some(overlay) = overlays.get(page_index) {
+                    overlay.set_flash(rects);
+                }
+            }
+        }
+
+        let window_weak = self.downgrade();
+        glib::timeout_add_local_once(
+            std::time::Duration::from_millis(ANNOTATION_FLASH_MS),
+            move || {
+                if let Some(window) = window_weak.upgrade() {
+                    let pdf_view = window.imp().pdf_view();
+                    let overlays = pdf_view.highlight_overlays();
+                    for page_index in flashed_pages {
+                        if let Some(overlay) = overlays.get(page_index) {
+                            overlay.set_flash(Vec::new());
+                        }
+                    }
+                }
+            },
+        );
+    }
+
+    // TODO
+    // fn update_cursor_from_annotation()
+
+    fn update_cursor(&self, new_cursor: WordCursor) {
+        {
+            let mut mode = self.imp().app_mode.borrow_mut();
+            mode.set_cursor(new_cursor);
+        }
+        self.imp().pdf_view().set_cursor(Some(new_cursor));
+        self.update_selection_display();
+        self.ensure_cursor_visible(new_cursor);
+        self.print_cursor_word(new_cursor);
+        self.schedule_definition_prefetch(new_cursor);
+    }
+
+    /// Look up the rare words of the cursor's line once the cursor stays on it
+    /// for `PREFETCH_DELAY_MS`, so `d` shows their definitions without waiting
+    fn schedule_definition_prefetch(&self, cursor: WordCursor) {
+        let imp = self.imp();
+        if !imp.prefetch_definitions.get() {
+            return;
+        }
+
+        let line_index = {
+            let cache = imp.text_cache.borrow();
+            cache
+                .as_ref()
+                .and_then(|cache| cache.get(cursor.page_index))
+                .and_then(|text_map| text_map.get_word(cursor.word_index))
+                .map(|word| word.line_index)
+        };
+        let Some(line_index) = line_index else {
+            return;
+        };
+        let line = (cursor.page_index, line_index);
+        if imp.prefetch_line.get() == Some(line) {
+            return;
+        }
+        imp.prefetch_line.set(Some(line));
+
+        if let Some(source) = imp.prefetch_timeout.take() {
+            source.remove();
+        }
+        let window_weak = self.downgrade();
+        let source = glib::timeout_add_local_once(
+            std::time::Duration::from_millis(PREFETCH_DELAY_MS),
+            move || {
+                if let Some(window) = window_weak.upgrade() {
+                    window.imp().prefetch_timeout.take();
+                    window.prefetch_line_definitions(line);
+                }
+            },
+        );
+        imp.prefetch_timeout.replace(Some(source));
+    }
+
+    /// Look up the rare words of a (page, line) on a worker thread
+    fn prefetch_line_definitions(&self, (page_index, line_index): (usize, usize)) {
+        let imp = self.imp();
+        let words = {
+            let cache = imp.text_cache.borrow();
+            let Some(text_map) = cache.as_ref().and_then(|cache| cache.get(page_index)) else {
+                return;
+            };
+            dictionary::rare_words(
+                text_map
+                    .words_on_line(line_index)
+                    .iter()
+                    .map(|word| word.text.as_str()),
+            )
+        };
+        if words.is_empty() {
+            return;
+        }
+
+        let lang = self.dictionary_language();
+        let source = imp.pdf_view().dictionary_source();
+        let allow_online = imp.prefetch_online.get();
+        std::thread::spawn(move || dictionary::prefetch(words, lang, source, allow_online));
+    }
+
+    /// Copy text range to clipboard and show feedback popup
+    fn copy_range_to_clipboard(&self, start: WordCursor, end: WordCursor) {
+        let imp = self.imp();
+
+        // Extract text with scoped borrow
+        let text = {
+            let cache = imp.text_cache.borrow();
+            match cache.as_ref() {
+                Some(c) => imp.copy_join_style.get().apply(&c.extract_text(start, end)),
+                None => return,
+            }
+        };
+
+        if !text.is_empty() {
+            let clipboard = self.clipboard();
+            clipboard.set_text(&text);
+            self.show_copy_feedback(&text);
+        }
+    }
+
+    /// Show a brief toast notification when text is copied
+    fn show_copy_feedback(&self, text: &str) {
+        // Format the message with a preview of copied text
+        let preview = if text.len() > 40 {
+            format!("Copied: \"{}...\"", &text[..37])
+        } else {
+            format!("Copied: \"{}\"", text)
+        };
+
+        self.show_toast(&preview);
+    }
+
+    fn show_toast(&self, message: &str) {
+        let imp = self.imp();
+
+        imp.toast_label.set_text(message);
+
+        // Show the toast
+        imp.toast_revealer.set_reveal_child(true);
+
+        // Auto-hide after 1.5 seconds
+        let revealer = imp.toast_revealer.clone();
+        glib::timeout_add_local_once(std::time::Duration::from_millis(1500), move || {
+            revealer.set_reveal_child(false);
+        });
+    }
+
+    fn toggle_toc_panel(&self) {
+        let imp = self.imp();
+        let is_visible = imp.toc_panel.is_visible();
+        let toc_panel = self.toc_panel();
+
+        if is_visible {
+            match toc_panel.toc_mode() {
+                TocMode::Chapters => {
+                    toc_panel.set_toc_mode(TocMode::Annotations);
+                }
+                TocMode::Annotations => {
+                    toc_panel.set_toc_mode(TocMode::Marks);
+                }
+                TocMode::Marks => {
+                    toc_panel.set_toc_mode(TocMode::Chapters);
+                    toc_panel.set_visible(false);
+                }
+            }
+        }
+
+        if !is_visible {
+            imp.toc_panel.set_visible(true);
+            imp.toc_panel.grab_focus();
+            let current_page = imp.pdf_view().current_page();
+            imp.toc_panel.select_current_chapter(current_page);
+        }
+
+        let window_weak = self.downgrade();
+        glib::timeout_add_local_once(std::time::Duration::from_millis(100), move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.update_highlights();
+            }
+        });
+    }
+
+    fn toggle_header_bar(&self) {
+        let imp = self.imp();
+        let header = imp.header_bar.widget();
+        let is_visible = header.is_visible();
+        header.set_visible(!is_visible);
+    }
+
+    // ============ Distraction-free mode ============
+
+    /// Wake the chrome on any key press or pointer movement
+    fn setup_distraction_free(&self) {
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let window_weak = self.downgrade();
+        key_controller.connect_key_pressed(move |_, _, _, _| {
+            if let Some(window) = window_weak.upgrade() {
+                window.wake_chrome();
+            }
+            glib::Propagation::Proceed
+        });
+        self.add_controller(key_controller);
+
+        let motion_controller = gtk::EventControllerMotion::new();
+        let window_weak = self.downgrade();
+        motion_controller.connect_motion(move |_, x, y| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            // Hiding the bars relayouts the content, which reports a still
+            // pointer again at the same place
+            if window.imp().last_pointer.replace(Some((x, y))) != Some((x, y)) {
+                window.wake_chrome();
+            }
+        });
+        self.add_controller(motion_controller);
+    }
+
+    fn toggle_distraction_free(&self) {
+        let imp = self.imp();
+        let enabled = !imp.distraction_free.get();
+        imp.distraction_free.set(enabled);
+
+        if enabled {
+            self.show_toast("Distraction-free mode on");
+            self.schedule_hide_chrome();
+        } else {
+            if let Some(source) = imp.idle_timeout.take() {
+                source.remove();
+            }
+            self.show_chrome();
+            self.show_toast("Distraction-free mode off");
+        }
+    }
+
+    /// Show the chrome and hide it again after a while without input
+    fn wake_chrome(&self) {
+        let imp = self.imp();
+        if !imp.distraction_free.get() || imp.presentation.borrow().is_some() {
+            return;
+        }
+        self.show_chrome();
+        self.schedule_hide_chrome();
+    }
+
+    fn schedule_hide_chrome(&self) {
+        let imp = self.imp();
+        if let Some(source) = imp.idle_timeout.take() {
+            source.remove();
+        }
+
+        let window_weak = self.downgrade();
+        let source = glib::timeout_add_seconds_local_once(DISTRACTION_FREE_IDLE_SECS, move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().idle_timeout.take();
+                window.hide_chrome();
+            }
+        });
+        imp.idle_timeout.replace(Some(source));
+    }
+
+    fn toggle_presentation(&self) {
+        if self.imp().presentation.borrow().is_some() {
+            self.exit_presentation();
+        } else {
+            self.enter_presentation();
+        }
+    }
+
+    /// Go fullscreen without the bars and panels, showing one page at a time
+    /// fit to the screen
+    fn enter_presentation(&self) {
+        let imp = self.imp();
+        if !imp.pdf_view().has_document() {
+            return;
+        }
+        // Presentation hides the chrome for good, not just while idle
+        if let Some(source) = imp.idle_timeout.take() {
+            source.remove();
+        }
+        self.show_chrome();
+
+        let mut chrome: Vec<gtk::Widget> = vec![
+            imp.header_bar.widget().clone().upcast(),
+            imp.status_bar.widget().clone().upcast(),
+            imp.tab_bar.clone().upcast(),
+            imp.toc_panel.clone().upcast(),
+            imp.annotation_panel.clone().upcast(),
+            imp.translation_panel.clone().upcast(),
+        ];
+        if let Some(split) = imp.split_scrolled_window.borrow().as_ref() {
+            chrome.push(split.clone().upcast());
+        }
+        let state = PresentationState {
+            chrome: chrome
+                .into_iter()
+                .map(|widget| {
+                    let visible = widget.is_visible();
+                    (widget, visible)
+                })
+                .collect(),
+            zoom_mode: imp.pdf_view().zoom_mode(),
+            paged: imp.pdf_view().paged(),
+            two_page: imp.pdf_view().two_page(),
+            fullscreen: self.is_fullscreen(),
+        };
+        for (widget, _) in &state.chrome {
+            widget.set_visible(false);
+        }
+        imp.presentation.replace(Some(state));
+
+        imp.pdf_view().set_two_page(false);
+        imp.pdf_view()
+            .set_paged_page(Some(imp.pdf_view().current_page() as usize));
+        self.add_css_class("presentation");
+        self.set_cursor_from_name(Some("none"));
+        self.fullscreen();
+        // Fit modes follow the view, so the page is fitted again once fullscreen
+        self.apply_zoom_mode(ZoomMode::FitPage);
+        self.show_toast("Presentation mode, Esc to leave");
+    }
+
+    /// Restore what enter_presentation hid and changed
+    fn exit_presentation(&self) {
+        let imp = self.imp();
+        let Some(state) = imp.presentation.take() else {
+            return;
+        };
+        if let Some(tick) = imp.presentation_fade_tick.take() {
+            tick.remove();
+        }
+        if let Some(scrolled) = imp.scrolled_window.borrow().as_ref() {
+            scrolled.set_opacity(1.0);
+        }
+
+        for (widget, visible) in state.chrome {
+            widget.set_visible(visible);
+        }
+        if !state.fullscreen {
+            self.unfullscreen();
+        }
+        self.remove_css_class("presentation");
+        self.set_cursor_from_name(None);
+
+        imp.pdf_view().set_two_page(state.two_page);
+        if !state.paged {
+            imp.pdf_view().set_paged_page(None);
+        }
+        self.apply_zoom_mode(state.zoom_mode);
+        if let Some(cursor) = self.compute_word_at_viewport_anchor() {
+            self.move_cursor(cursor);
+        }
+        if imp.distraction_free.get() {
+            self.schedule_hide_chrome();
+        }
+    }
+
+    /// Keys of presentation mode: arrows and page keys turn pages, Escape
+    /// leaves. Returns whether the key was used.
+    fn handle_presentation_key(&self, key: gtk::gdk::Key) -> bool {
+        if self.imp().presentation.borrow().is_none() {
+            return false;
+        }
+        let forward = match key {
+            gtk::gdk::Key::Right
+            | gtk::gdk::Key::Down
+            | gtk::gdk::Key::Page_Down
+            | gtk::gdk::Key::l
+            | gtk::gdk::Key::j => true,
+            gtk::gdk::Key::Left
+            | gtk::gdk::Key::Up
+            | gtk::gdk::Key::Page_Up
+            | gtk::gdk::Key::BackSpace
+            | gtk::gdk::Key::h
+            | gtk::gdk::Key::k => false,
+            gtk::gdk::Key::Escape => {
+                self.exit_presentation();
+                return true;
+            }
+            _ => return false,
+        };
+        self.execute_key_action(KeyAction::FlipPage { forward })
+    }
+
+    /// Fade the pages in after a page turn in presentation mode, when enabled
+    fn fade_in_pages(&self) {
+        let imp = self.imp();
+        if !imp.presentation_fade.get() {
+            return;
+        }
+        let Some(scrolled) = imp.scrolled_window.borrow().clone() else {
+            return;
+        };
+        if let Some(tick) = imp.presentation_fade_tick.take() {
+            tick.remove();
+        }
+
+        scrolled.set_opacity(0.0);
+        let started = Cell::new(None);
+        let window_weak = self.downgrade();
+        let tick = scrolled.add_tick_callback(move |widget, clock| {
+            let now = clock.frame_time();
+            let start = started.get().unwrap_or(now);
+            started.set(Some(start));
+            // Frame times are in microseconds
+            let progress = (now - start) as f64 / (PRESENTATION_FADE_MS * 1000) as f64;
+            widget.set_opacity(progress.min(1.0));
+            if progress < 1.0 {
+                return glib::ControlFlow::Continue;
+            }
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().presentation_fade_tick.take();
+            }
+            glib::ControlFlow::Break
+        });
+        imp.presentation_fade_tick.replace(Some(tick));
+    }
+
+    /// `:crop`, `:crop edit` and `:crop off`
+    fn crop_pages(&self, action: CropAction) {
+        let imp = self.imp();
+        if !imp.pdf_view().has_document() {
+            return;
+        }
+
+        match action {
+            CropAction::Toggle => {
+                let enabled = !imp.pdf_view().auto_crop();
+                self.set_manual_crop(None);
+                imp.pdf_view().set_auto_crop(enabled);
+                self.show_toast(if enabled {
+                    "Cropping the page margins"
+                } else {
+                    "Showing the pages whole"
+                });
+            }
+            CropAction::Off => {
+                self.set_manual_crop(None);
+                imp.pdf_view().set_auto_crop(false);
+                self.show_toast("Showing the pages whole");
+            }
+            CropAction::Edit => {
+                // Start from the margins the current page is shown with
+                let page_index = imp.pdf_view().current_page() as usize;
+                self.set_manual_crop(Some(imp.pdf_view().page_crop(page_index)));
+                imp.crop_adjusting.set(true);
+                self.show_toast("h/l, H/L, k/j, K/J move the left, right, top and bottom edges · Enter when done");
+            }
+        }
+    }
+
+    /// Cut the same margins off every page of both views, or go back to
+    /// auto-crop with None
+    fn set_manual_crop(&self, crop: Option<PageCrop>) {
+        let imp = self.imp();
+        imp.pdf_view().set_manual_crop(crop);
+        imp.split_view.set_manual_crop(crop);
+    }
+
+    /// Keys of the crop adjustment mode: `h`/`l` move the left edge of the crop
+    /// box, `H`/`L` the right one, `k`/`j` the top and `K`/`J` the bottom, `0`
+    /// shows the pages whole and `=` goes back to auto-crop. Enter or Escape
+    /// ends it. Returns whether the key was used.
+    fn handle_crop_key(&self, key: gtk::gdk::Key) -> bool {
+        let imp = self.imp();
+        if !imp.crop_adjusting.get() {
+            return false;
+        }
+
+        let (edge, points) = match key {
+            gtk::gdk::Key::h => (CropEdge::Left, -CROP_STEP_PTS),
+            gtk::gdk::Key::l => (CropEdge::Left, CROP_STEP_PTS),
+            gtk::gdk::Key::H => (CropEdge::Right, CROP_STEP_PTS),
+            gtk::gdk::Key::L => (CropEdge::Right, -CROP_STEP_PTS),
+            gtk::gdk::Key::k => (CropEdge::Top, -CROP_STEP_PTS),
+            gtk::gdk::Key::j => (CropEdge::Top, CROP_STEP_PTS),
+            gtk::gdk::Key::K => (CropEdge::Bottom, CROP_STEP_PTS),
+            gtk::gdk::Key::J => (CropEdge::Bottom, -CROP_STEP_PTS),
+            gtk::gdk::Key::_0 => {
+                self.set_manual_crop(Some(PageCrop::NONE));
+                return true;
+            }
+            gtk::gdk::Key::equal => {
+                self.set_manual_crop(None);
+                return true;
+            }
+            gtk::gdk::Key::Return | gtk::gdk::Key::KP_Enter | gtk::gdk::Key::Escape => {
+                imp.crop_adjusting.set(false);
+                self.show_toast("Crop set for every page");
+                return true;
+            }
+            // Other keys are ignored until the crop is done
+            _ => return true,
+        };
+
+        let page_index = imp.pdf_view().current_page() as usize;
+        let Some(page_size) = imp
+            .pdf_view()
+            .document()
+            .as_ref()
+            .and_then(|doc| doc.page_size(page_index))
+        else {
+            return true;
+        };
+        let crop = imp
+            .pdf_view()
+            .page_crop(page_index)
+            .adjusted(edge, points, page_size);
+        self.set_manual_crop(Some(crop));
+        true
+    }
+
+    /// Hide the pointer and the bars, and dim annotation highlights
+    fn hide_chrome(&self) {
+        let imp = self.imp();
+        if imp.hidden_chrome.get().is_some() {
+            return;
+        }
+
+        let header = imp.header_bar.widget();
+        let status_bar = imp.status_bar.widget();
+        imp.hidden_chrome
+            .set(Some((header.is_visible(), status_bar.is_visible())));
+        header.set_visible(false);
+        status_bar.set_visible(false);
+        self.set_cursor_from_name(Some("none"));
+        for overlay in imp.pdf_view().highlight_overlays().iter() {
+            overlay.set_annotations_dimmed(true);
+        }
+    }
+
+    /// Restore what hide_chrome hid
+    fn show_chrome(&self) {
+        let imp = self.imp();
+        let Some((header_visible, status_bar_visible)) = imp.hidden_chrome.take() else {
+            return;
+        };
+
+        imp.header_bar.widget().set_visible(header_visible);
+        imp.status_bar.widget().set_visible(status_bar_visible);
+        self.set_cursor_from_name(None);
+        for overlay in imp.pdf_view().highlight_overlays().iter() {
+            overlay.set_annotations_dimmed(false);
+        }
+    }
+
+    fn toggle_status_bar(&self) {
+        let imp = self.imp();
+        let status_bar = imp.status_bar.widget();
+        let is_visible = status_bar.is_visible();
+        status_bar.set_visible(!is_visible);
+    }
+
+    fn setup_open_button(&self) {
+        let window_weak = self.downgrade();
+
+        self.imp()
+            .header_bar
+            .open_button()
+            .connect_clicked(move |_| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.show_open_dialog(false);
+                }
+            });
+    }
+
+    fn setup_settings_button(&self) {
+        let window_weak = self.downgrade();
+
+        self.imp()
+            .header_bar
+            .settings_button()
+            .connect_clicked(move |_| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.show_settings_window();
+                }
+            });
+    }
+
+    fn setup_about_button(&self) {
+        let window_weak = self.downgrade();
+
+        self.imp()
+            .header_bar
+            .about_button()
+            .connect_clicked(move |_| {
+                if let Some(window) = window_weak.upgrade() {
+                    AboutWindow::new(&window).present();
+                }
+            });
+    }
+
+    fn setup_reload_button(&self) {
+        let window_weak = self.downgrade();
+
+        self.imp()
+            .header_bar
+            .reload_button()
+            .connect_clicked(move |_| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.reload_document();
+                }
+            });
+    }
+
+    /// The header bar rotate buttons turn every page
+    fn setup_rotate_buttons(&self) {
+        let header_bar = &self.imp().header_bar;
+
+        let window_weak = self.downgrade();
+        header_bar.rotate_left_button().connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.rotate_pages(-1, true);
+            }
+        });
+
+        let window_weak = self.downgrade();
+        header_bar.rotate_right_button().connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.rotate_pages(1, true);
+            }
+        });
+    }
+
+    /// Export the lookups recorded for flashcards from the header bar
+    fn setup_flashcards(&self) {
+        let window_weak = self.downgrade();
+        self.imp()
+            .header_bar
+            .flashcards_button()
+            .connect_clicked(move |_| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.show_flashcards_export_dialog();
+                }
+            });
+    }
+
+    fn setup_recent_files(&self) {
+        let window_weak = self.downgrade();
+
+        self.imp().header_bar.recent_popover().connect_closure(
+            "file-activated",
+            false,
+            glib::closure_local!(move |_popover: &RecentFilesPopover, path: &str| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.open_recent_file(path);
+                }
+            }),
+        );
+    }
+
+    /// Open the recent files popover, showing the header bar if it was hidden
+    fn show_recent_files(&self) {
+        let header = self.imp().header_bar.widget();
+        if !header.is_visible() {
+            header.set_visible(true);
+        }
+
+        // Wait for the header bar to be allocated before anchoring the popover to it
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().header_bar.recent_button().popup();
+            }
+        });
+    }
+
+    fn open_recent_file(&self, path: &str) {
+        let path = PathBuf::from(path);
+        if path.exists() {
+            self.open_file(&path);
+            return;
+        }
+
+        self.show_toast("File no longer exists, removed from recent files");
+        if let Err(e) = recent_files::remove_recent_file(&path.to_string_lossy()) {
+            eprintln!("Failed to remove recent file: {}", e);
+        }
+    }
+
+    fn setup_tts_controls(&self) {
+        let imp = self.imp();
+
+        let window_weak = self.downgrade();
+        imp.status_bar.tts_pause_button().connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.toggle_reading_pause();
+            }
+        });
+
+        let window_weak = self.downgrade();
+        imp.status_bar.tts_stop_button().connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.stop_reading();
+            }
+        });
+
+        self.connect_close_request(|window| {
+            window.imp().speaker.borrow_mut().stop();
+            glib::Propagation::Proceed
+        });
+    }
+
+    /// Read the selection aloud, or the text from the cursor on, page after page.
+    /// While reading, pause or resume instead.
+    fn read_aloud(&self, cursor: WordCursor, selection: Option<(WordCursor, WordCursor)>) {
+        let imp = self.imp();
+
+        if imp.speaker.borrow().state() != TtsState::Stopped {
+            self.toggle_reading_pause();
+            return;
+        }
+
+        let (start, end, next_page) = match selection {
+            Some((start, end)) => (start, end, None),
+            None => {
+                let pdf_view = imp.pdf_view();
+                let doc_borrow = pdf_view.document();
+                let Some(doc) = doc_borrow.as_ref() else {
+                    return;
+                };
+                let mut cache = imp.text_cache.borrow_mut();
+                let Some(cache) = cache.as_mut() else {
+                    return;
+                };
+                let Some(word_count) = cache
+                    .get_or_build(cursor.page_index, doc.as_ref())
+                    .map(|text_map| text_map.word_count())
+                else {
+                    return;
+                };
+                let page_end = WordCursor::new(cursor.page_index, word_count.saturating_sub(1));
+                (cursor, page_end, Some(cursor.page_index + 1))
+            }
+        };
+
+        let text = match imp.text_cache.borrow().as_ref() {
+            Some(cache) => cache.extract_text(start, end),
+            None => return,
+        };
+        imp.tts_next_page.set(next_page);
+        self.speak(&text);
+    }
+
+    /// Say the selection, or the word under the cursor, in the dictionary language.
+    /// Returns false outside Visual mode.
+    fn pronounce_at_cursor(&self) -> bool {
+        let imp = self.imp();
+        let (cursor, selection) = {
+            let mode = imp.app_mode.borrow();
+            let Some(cursor) = mode.cursor() else {
+                return false;
+            };
+            (cursor, mode.selection_range())
+        };
+        let (start, end) = selection.unwrap_or((cursor, cursor));
+
+        let text = match imp.text_cache.borrow().as_ref() {
+            Some(cache) => cache.extract_text(start, end),
+            None => return false,
+        };
+        self.pronounce(&text, self.dictionary_language().code());
+        true
+    }
+
+    /// Search the selection, or the word under the cursor, with the search
+    /// engine of the settings, or on Wikipedia with `wikipedia`
+    fn search_on_web(&self, wikipedia: bool) {
+        let imp = self.imp();
+        let range = if self.reflow_active() {
+            imp.reflow_view.selection()
+        } else {
+            let mode = imp.app_mode.borrow();
+            mode.selection_range()
+                .or_else(|| mode.cursor().map(|cursor| (cursor, cursor)))
+        };
+        let Some((start, end)) = range else {
+            self.show_toast("Select the text to search in Visual mode (v)");
+            return;
+        };
+
+        let text = match imp.text_cache.borrow().as_ref() {
+            Some(cache) => cache.extract_text(start, end),
+            None => return,
+        };
+        let query = web_search::search_query(&text);
+        if query.is_empty() {
+            return;
+        }
+
+        let template = if wikipedia {
+            SearchEngine::Wikipedia.template().to_string()
+        } else {
+            imp.settings.borrow().web_search.clone()
+        };
+        let url = web_search::search_url(&template, &query, self.dictionary_language().code());
+        gtk::UriLauncher::new(&url).launch(Some(self), None::<&gio::Cancellable>, move |result| {
+            if let Err(e) = result {
+                eprintln!("Failed to open {}: {}", url, e);
+            }
+        });
+    }
+
+    /// Say a word or short selection once, pausing the reading aloud so both
+    /// don't mix
+    fn pronounce(&self, text: &str, language: &str) {
+        if self.imp().speaker.borrow().state() == TtsState::Speaking {
+            self.toggle_reading_pause();
+        }
+        if let Err(e) = tts::say(text, language) {
+            self.show_toast(&e.to_string());
+        }
+    }
+
+    /// Start reading `text` and follow the speaker until it is done
+    fn speak(&self, text: &str) {
+        let imp = self.imp();
+        let language = self.dictionary_language();
+
+        if let Err(e) = imp.speaker.borrow_mut().speak(text, language.code()) {
+            self.show_toast(&e.to_string());
+            imp.tts_next_page.set(None);
+        }
+        self.update_tts_controls();
+        self.follow_speaker();
+    }
+
+    /// Go on with the next sentence, or the next page, once the sentence being
+    /// read was spoken
+    fn follow_speaker(&self) {
+        let Some(process) = self.imp().speaker.borrow().sentence_process() else {
+            return;
+        };
+
+        // Resumed on the main loop as soon as the sentence was spoken
+        let window_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let _ = process.wait_future().await;
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let imp = window.imp();
+            // Paused, stopped or replaced meanwhile, which is followed on its own
+            if imp.speaker.borrow().sentence_process().as_ref() != Some(&process) {
+                return;
+            }
+
+            let state = imp
+                .speaker
+                .borrow_mut()
+                .next_sentence()
+                .unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    TtsState::Stopped
+                });
+            if state == TtsState::Stopped {
+                window.read_next_page();
+            }
+            window.update_tts_controls();
+            window.follow_speaker();
+        });
+    }
+
+    /// Go on reading with the next page with text, if reading on from the cursor
+    fn read_next_page(&self) {
+        let imp = self.imp();
+        let Some(page_index) = imp.tts_next_page.take() else {
+            return;
+        };
+
+        let text = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
+                return;
+            };
+            let mut cache = imp.text_cache.borrow_mut();
+            let Some(cache) = cache.as_mut() else {
+                return;
+            };
+            let page = if cache.has_text(page_index, doc.as_ref()) {
+                Some(page_index)
+            } else {
+                cache.next_page_with_text(page_index, true, doc.as_ref())
+            };
+            let Some(page) = page else {
+                return;
+            };
+            let Some(word_count) = cache
+                .get_or_build(page, doc.as_ref())
+                .map(|text_map| text_map.word_count())
+            else {
+                return;
+            };
+            imp.tts_next_page.set(Some(page + 1));
+            cache.extract_text(
+                WordCursor::new(page, 0),
+                WordCursor::new(page, word_count.saturating_sub(1)),
+            )
+        };
+
+        let result = imp
+            .speaker
+            .borrow_mut()
+            .speak(&text, self.dictionary_language().code());
+        if let Err(e) = result {
+            self.show_toast(&e.to_string());
+            imp.tts_next_page.set(None);
+        }
+    }
+
+    fn toggle_reading_pause(&self) {
+        let imp = self.imp();
+        let state = imp.speaker.borrow().state();
+        match state {
+            TtsState::Speaking => imp.speaker.borrow_mut().pause(),
+            TtsState::Paused => {
+                let result = imp.speaker.borrow_mut().resume();
+                match result {
+                    Ok(()) => self.follow_speaker(),
+                    Err(e) => self.show_toast(&e.to_string()),
+                }
+            }
+            TtsState::Stopped => {}
+        }
+        self.update_tts_controls();
+    }
+
+    fn stop_reading(&self) {
+        let imp = self.imp();
+        imp.tts_next_page.set(None);
+        imp.speaker.borrow_mut().stop();
+        self.update_tts_controls();
+    }
+
+    fn update_tts_controls(&self) {
+        let imp = self.imp();
+        imp.status_bar.set_tts_state(imp.speaker.borrow().state());
+    }
+
+    fn setup_command_line(&self) {
+        let window_weak = self.downgrade();
+
+        self.imp()
+            .status_bar
+            .command_entry()
+            .connect_activate(move |entry| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                let input = entry.text().to_string();
+                window.imp().status_bar.hide_command_line();
+                match command::parse_search(&input) {
+                    Some((query, in_selection)) => window.search(query, in_selection),
+                    None => window.run_command(&input),
+                }
+            });
+    }
+
+    /// Execute a command entered on the `:` command line
+    fn run_command(&self, input: &str) {
+        let Some(command) = Command::parse(input) else {
+            let input = input.trim().trim_start_matches(':').trim();
+            if !input.is_empty() {
+                self.show_toast(&format!("Unknown command: {}", input));
+            }
+            return;
+        };
+
+        match command {
+            Command::Reload => {
+                self.execute_key_action(KeyAction::ReloadDocument);
+            }
+            Command::Snippet { path } => self.export_snippet(path),
+            Command::Citation => self.copy_citation(),
+            Command::Properties => self.show_properties_window(),
+            Command::ClearSearch => {
+                let imp = self.imp();
+                imp.search_matches.borrow_mut().clear();
+                imp.search_index.set(None);
+                self.update_search_highlights();
+            }
+            Command::ExportAll { dir, combined } => self.export_all_annotations(dir, combined),
+            Command::Edit { path: None } => self.show_open_dialog(false),
+            Command::Edit { path: Some(path) } => {
+                if path.is_file() {
+                    self.open_file(&path);
+                } else {
+                    self.show_toast(&format!("No such file: {}", path.display()));
+                }
+            }
+            Command::TabEdit { path: None } => self.show_open_dialog(true),
+            Command::TabEdit { path: Some(path) } => {
+                if path.is_file() {
+                    self.open_file_in_new_tab(&path);
+                } else {
+                    self.show_toast(&format!("No such file: {}", path.display()));
+                }
+            }
+            // Like in vim, :q closes the split view first
+            Command::CloseTab if self.split_active() => self.close_split(),
+            Command::CloseTab => {
+                let active = self.imp().tabs.borrow().active_index();
+                self.close_tab(active);
+            }
+            Command::Split { side_by_side } => self.open_split(side_by_side),
+            Command::CloseSplit => self.close_split(),
+            Command::Present => self.toggle_presentation(),
+            Command::Crop { action } => self.crop_pages(action),
+            Command::Glossary {
+                term,
+                explanation,
+                global,
+            } => self.save_glossary_entry(&term, &explanation, global),
+            Command::RemoveGlossary { term, global } => self.remove_glossary_entry(&term, global),
+            Command::KeyLog => {
+                let mut recorder = self.imp().action_recorder.borrow_mut();
+                let recording = !recorder.is_recording();
+                recorder.set_recording(recording);
+                drop(recorder);
+                self.show_toast(if recording {
+                    "Recording key actions, :dumpkeys writes them to a file"
+                } else {
+                    "Stopped recording key actions"
+                });
+            }
+            Command::DumpKeys { count, path } => self.dump_key_log(count, path),
+            Command::Reflow => self.toggle_reflow(),
+            Command::HeatMap => self.show_heat_map_window(),
+            Command::History => self.show_history_window(),
+            Command::LibraryStats => self.show_library_stats_window(),
+            Command::ReadingLog { path } => self.export_reading_log(path),
+            Command::Draw { tool, color } => self.set_ink_tool(tool, color),
+            Command::Erase => self.erase_last_drawing(),
+            Command::Ocr { all } => self.recognize_pages(all),
+            Command::ExportPdf { path } => self.export_annotated_pdf(path),
+        }
+    }
+
+    /// Record `event` in the reading log of the open document
+    fn log_reading_event(&self, event: ReadingEvent) {
+        let Some(pdf_path) = self.imp().current_pdf_path.borrow().clone() else {
+            return;
+        };
+        if let Err(e) = reading_log::record_event(&pdf_path, &event) {
+            eprintln!("Failed to record reading event: {}", e);
+        }
+    }
+
+    /// Write the reading journal of every document, one section per day
+    fn export_reading_log(&self, path: Option<PathBuf>) {
+        // Pages being read right now belong in the journal too
+        self.finish_reading_range();
+        let events = match reading_log::load_events() {
+            Ok(events) => events,
+            Err(e) => {
+                self.show_toast(&format!("Failed to load reading log: {}", e));
+                return;
+            }
+        };
+
+        let Some(path) = path.or_else(reading_log::default_export_path) else {
+            self.show_toast("Could not determine data directory");
+            return;
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, reading_log::format_journal(&events)));
+        match written {
+            Ok(()) => self.show_toast(&format!("Wrote reading journal to {}", path.display())),
+            Err(e) => self.show_toast(&format!("Failed to write {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Overview of where the open document was annotated and looked up the most
+    fn show_heat_map_window(&self) {
+        let imp = self.imp();
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            self.show_toast("No document open");
+            return;
+        };
+
+        let annotation_counts =
+            annotations::annotation_counts_by_page(&pdf_path).unwrap_or_else(|e| {
+                eprintln!("Failed to count annotations: {}", e);
+                Vec::new()
+            });
+        let lookup_counts = flashcards::lookup_counts_by_page(&pdf_path).unwrap_or_else(|e| {
+            eprintln!("Failed to count lookups: {}", e);
+            Vec::new()
+        });
+        let pages = heat_map::page_activity(
+            imp.pdf_view().page_count(),
+            &annotation_counts,
+            &lookup_counts,
+        );
+
+        let window = HeatMapWindow::new(self);
+        window.set_pages(&pages);
+
+        let window_weak = self.downgrade();
+        window.connect_closure(
+            "page-selected",
+            false,
+            glib::closure_local!(move |_heat_map: &HeatMapWindow, page_index: u32| {
+                if let Some(this) = window_weak.upgrade() {
+                    this.scroll_to_page(page_index as u16);
+                }
+            }),
+        );
+
+        window.present();
+    }
+
+    fn show_history_window(&self) {
+        let imp = self.imp();
+        if imp.current_pdf_path.borrow().is_none() {
+            self.show_toast("No document open");
+            return;
+        }
+
+        let window = HistoryWindow::new(self);
+        window.set_visits(
+            imp.visit_history.borrow().visits(),
+            self.current_reading_position(),
+            &imp.pdf_view().bookmarks(),
+            imp.pdf_view().page_count(),
+        );
+
+        let window_weak = self.downgrade();
+        window.connect_closure(
+            "visit-selected",
+            false,
+            glib::closure_local!(move |_history: &HistoryWindow, cursor: WordCursor| {
+                if let Some(this) = window_weak.upgrade() {
+                    this.record_jump();
+                    this.jump_to_position(cursor);
+                }
+            }),
+        );
+
+        window.present();
+    }
+
+    /// Summary of everything recorded across documents, read from the databases
+    fn show_library_stats_window(&self) {
+        let mut documents: Vec<String> = recent_files::load_recent_files()
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load recent files: {}", e);
+                Vec::new()
+            })
+            .into_iter()
+            .map(|file| file.pdf_path)
+            .collect();
+        documents.extend(annotations::annotated_documents().unwrap_or_else(|e| {
+            eprintln!("Failed to load annotated documents: {}", e);
+            Vec::new()
+        }));
+        let annotation_count = annotations::annotation_count().unwrap_or_else(|e| {
+            eprintln!("Failed to count annotations: {}", e);
+            0
+        });
+        let lookup_counts = flashcards::lookup_counts_by_language().unwrap_or_else(|e| {
+            eprintln!("Failed to count lookups: {}", e);
+            Vec::new()
+        });
+        let events = reading_log::load_events().unwrap_or_else(|e| {
+            eprintln!("Failed to load the reading log: {}", e);
+            Vec::new()
+        });
+
+        let window = LibraryStatsWindow::new(self);
+        window.set_stats(LibraryStats::summarize(
+            &documents,
+            annotation_count,
+            &lookup_counts,
+            &events,
+            &library_stats::last_days(library_stats::CHART_DAYS),
+        ));
+        window.present();
+    }
+
+    // ============ Reflow Mode ============
+
+    fn reflow_active(&self) -> bool {
+        self.imp().view_stack.visible_child_name().as_deref() == Some("reflow")
+    }
+
+    /// Switch between the pages and their text wrapped to the window, keeping the
+    /// reading position
+    fn toggle_reflow(&self) {
+        let imp = self.imp();
+
+        if self.reflow_active() {
+            let top_word = imp.reflow_view.top_word();
+            imp.view_stack.set_visible_child_name("pages");
+            // Scroll once the pages are allocated again
+            let window_weak = self.downgrade();
+            glib::idle_add_local_once(move || {
+                if let (Some(window), Some(cursor)) = (window_weak.upgrade(), top_word) {
+                    window.jump_to_position(cursor);
+                }
+            });
+            self.show_toast("Reflow mode off");
+            return;
+        }
+
+        let position = self.current_reading_position();
+        if !self.build_reflow_text() {
+            self.show_toast("No text to reflow");
+            return;
+        }
+        imp.view_stack.set_visible_child_name("reflow");
+        if let Some(cursor) = position {
+            imp.reflow_view.scroll_to_word(cursor);
+        }
+        self.show_toast("Reflow mode on, :reflow to go back to the pages");
+    }
+
+    /// Fill the reflow view with the text of every page and the annotations over it.
+    /// Returns whether the document has any text.
+    fn build_reflow_text(&self) -> bool {
+        let imp = self.imp();
+        let text = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let mut cache = imp.text_cache.borrow_mut();
+            let (Some(doc), Some(cache)) = (doc_borrow.as_ref(), cache.as_mut()) else {
+                return false;
+            };
+
+            let mut text = ReflowText::new();
+            for page_index in 0..cache.page_count() {
+                if let Some(text_map) = cache.get_or_build(page_index, doc.as_ref()) {
+                    text.push_page(text_map);
+                }
+            }
+            text.finish();
+            text
+        };
+
+        if text.is_empty() {
+            return false;
+        }
+        imp.reflow_view.set_text(text);
+        imp.reflow_view.set_annotations(&imp.annotations.borrow());
+        true
+    }
+
+    /// Write the recorded key actions to `path`, or the default log file
+    fn dump_key_log(&self, count: Option<usize>, path: Option<PathBuf>) {
+        let recorder = self.imp().action_recorder.borrow();
+        if recorder.is_empty() {
+            let hint = if recorder.is_recording() {
+                "No key actions recorded yet"
+            } else {
+                "No key actions recorded, start recording with :keylog"
+            };
+            drop(recorder);
+            self.show_toast(hint);
+            return;
+        }
+        let log = recorder.dump(count);
+        let event_count = log.lines().count();
+        drop(recorder);
+
+        let Some(path) = path.or_else(action_recorder::default_dump_path) else {
+            self.show_toast("Could not determine data directory");
+            return;
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, log));
+        match written {
+            Ok(()) => self.show_toast(&format!(
+                "Wrote {} event(s) to {}",
+                event_count,
+                path.display()
+            )),
+            Err(e) => self.show_toast(&format!("Failed to write {}: {}", path.display(), e)),
+        }
+    }
+
+    /// The document a glossary command applies to: None for the global glossary,
+    /// or an error toast when no document is open
+    fn glossary_document(&self, global: bool) -> Result<Option<String>, ()> {
+        if global {
+            return Ok(None);
+        }
+        match self.imp().current_pdf_path.borrow().clone() {
+            Some(path) => Ok(Some(path)),
+            None => {
+                self.show_toast("Open a document first, or use the global glossary with !");
+                Err(())
+            }
+        }
+    }
+
+    fn save_glossary_entry(&self, term: &str, explanation: &str, global: bool) {
+        let Ok(document) = self.glossary_document(global) else {
+            return;
+        };
+        match glossary::save_entry(term, explanation, document.as_deref()) {
+            Ok(()) => self.show_toast(&format!("Added to the glossary: {}", term)),
+            Err(e) => self.show_toast(&format!("Failed to save glossary entry: {}", e)),
+        }
+    }
+
+    fn remove_glossary_entry(&self, term: &str, global: bool) {
+        let Ok(document) = self.glossary_document(global) else {
+            return;
+        };
+        match glossary::delete_entry(term, document.as_deref()) {
+            Ok(true) => self.show_toast(&format!("Removed from the glossary: {}", term)),
+            Ok(false) => self.show_toast(&format!("Not in the glossary: {}", term)),
+            Err(e) => self.show_toast(&format!("Failed to remove glossary entry: {}", e)),
+        }
+    }
+
+    // ============ Search ============
+
+    /// Search the whole document for `query`, highlight every match and jump to the
+    /// first one after the reading position. With `in_selection`, only the Visual
+    /// selection is searched, from its start.
+    fn search(&self, query: &str, in_selection: bool) {
+        let imp = self.imp();
+        if query.trim().is_empty() {
+            return;
+        }
+
+        let selection = imp.app_mode.borrow().selection_range();
+        let scope = match selection {
+            Some(range) if in_selection => Some(range),
+            None if in_selection => {
+                self.show_toast("No selection to search in");
+                return;
+            }
+            _ => None,
+        };
+
+        let matches = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
+                return;
+            };
+            let mut cache = imp.text_cache.borrow_mut();
+            let Some(cache) = cache.as_mut() else {
+                return;
+            };
+            match scope {
+                Some((first, last)) => cache.search_within(query, doc.as_ref(), first, last),
+                None => cache.search(query, doc.as_ref()),
+            }
+        };
+
+        if scope.is_some() {
+            // Going from match to match moves the cursor, which would reshape the
+            // selection; the matches stay limited to it
+            imp.app_mode.borrow_mut().clear_selection();
+            self.update_selection_display();
+        }
+
+        let from = match scope {
+            Some((first, _)) => Some(first),
+            None => self.current_reading_position(),
+        };
+        let index = from.map_or(0, |from| {
+            matches
+                .iter()
+                .position(|(start, _)| *start >= from)
+                .unwrap_or(0)
+        });
+        let found = !matches.is_empty();
+
+        imp.search_matches.replace(matches);
+        imp.search_index.set(None);
+        self.update_search_highlights();
+
+        if found {
+            self.go_to_search_match(index);
+        } else if scope.is_some() {
+            self.show_toast(&format!("Pattern not found in selection: {}", query.trim()));
+        } else {
+            self.show_toast(&format!("Pattern not found: {}", query.trim()));
+        }
+    }
+
+    /// Jump to the next (or previous) search match, wrapping around the document
+    fn jump_to_search_match(&self, forward: bool) {
+        let imp = self.imp();
+        let count = imp.search_matches.borrow().len();
+        if count == 0 {
+            return;
+        }
+
+        let index = match imp.search_index.get() {
+            Some(index) if forward => (index + 1) % count,
+            Some(index) => (index + count - 1) % count,
+            None if forward => 0,
+            None => count - 1,
+        };
+        self.go_to_search_match(index);
+    }
+
+    fn go_to_search_match(&self, index: usize) {
+        let imp = self.imp();
+        let (count, start) = {
+            let matches = imp.search_matches.borrow();
+            let Some((start, _)) = matches.get(index) else {
+                return;
+            };
+            (matches.len(), *start)
+        };
+
+        imp.search_index.set(Some(index));
+        self.record_jump();
+        self.jump_to_position(start);
+        self.show_toast(&format!("Match {} of {}", index + 1, count));
+    }
+
+    /// Draw the search matches on the overlay of every page
+    fn update_search_highlights(&self) {
+        let imp = self.imp();
+        let matches = imp.search_matches.borrow();
+        let cache = imp.text_cache.borrow();
+        let pdf_view = imp.pdf_view();
+        let page_pictures = pdf_view.page_pictures();
+
+        let mut page_rects: HashMap<usize, Vec<HighlightRect>> = HashMap::new();
+        if let Some(cache) = cache.as_ref() {
+            for (start, end) in matches.iter() {
+                let Some(text_map) = cache.get(start.page_index) else {
+                    continue;
+                };
+                let x_offset = page_pictures
+                    .get(start.page_index)
+                    .map(|pic| calculate_picture_offset(pic))
+                    .unwrap_or(0.0);
+                let render_width = pdf_view.page_render_width(start.page_index);
+                let rotation = pdf_view.page_rotation(start.page_index);
+                let crop = pdf_view.page_crop(start.page_index);
+
+                let rects = page_rects.entry(start.page_index).or_default();
+                for word_index in start.word_index..=end.word_index {
+                    if let Some(word) = text_map.get_word(word_index) {
+                        rects.push(HighlightRect::from_pdf_bounds(
+                            &word.bounds,
+                            text_map.page_width,
+                            text_map.page_height,
+                            x_offset,
+                            render_width,
+                            rotation,
+                            crop,
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (page_index, overlay) in pdf_view.highlight_overlays().iter().enumerate() {
+            overlay.set_search_matches(page_rects.remove(&page_index).unwrap_or_default());
+        }
+    }
+
+    fn show_properties_window(&self) {
+        let imp = self.imp();
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            return;
+        };
+
+        let (title, page_count, image_only) = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
+                return;
+            };
+            let mut cache = imp.text_cache.borrow_mut();
+            let image_only = cache
+                .as_mut()
+                .map(|cache| cache.image_only_pages(doc.as_ref()))
+                .unwrap_or_default();
+            (doc.title(), doc.page_count(), image_only)
+        };
+
+        let image_only_summary = if image_only.is_empty() {
+            "0".to_string()
+        } else {
+            format!(
+                "{} (pages {})",
+                image_only.len(),
+                format_page_ranges(&image_only)
+            )
+        };
+
+        let window = PropertiesWindow::new(self);
+        window.set_rows(&[
+            ("File", pdf_path),
+            ("Title", title.unwrap_or_else(|| "—".to_string())),
+            ("Pages", page_count.to_string()),
+            ("Image-only pages", image_only_summary),
+        ]);
+        window.present();
+    }
+
+    /// Jump to the closest page with a text layer after (or before) the current one,
+    /// skipping scanned pages that have no text to read
+    fn skip_to_text_page(&self, forward: bool) {
+        let imp = self.imp();
+        let from = imp
+            .app_mode
+            .borrow()
+            .cursor()
+            .map(|cursor| cursor.page_index)
+            .unwrap_or_else(|| imp.pdf_view().current_page() as usize);
+
+        let page_index = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
+                return;
+            };
+            let mut cache = imp.text_cache.borrow_mut();
+            let Some(cache) = cache.as_mut() else {
+                return;
+            };
+            cache.next_page_with_text(from, forward, doc.as_ref())
+        };
+
+        match page_index {
+            Some(page_index) => {
+                imp.pdf_view().scroll_to_page(page_index as u16);
+                self.move_cursor(WordCursor::new(page_index, 0));
+            }
+            None => self.show_toast("No more pages with text"),
+        }
+    }
+
+    /// Render the visible part of the document, or the selection in Visual mode, with
+    /// an attribution footer (title and page). Saved as PNG to `path` when given,
+    /// copied to the clipboard otherwise.
+    fn export_snippet(&self, path: Option<PathBuf>) {
+        let imp = self.imp();
+
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            return;
+        };
+
+        let area = match self.selection_snippet_area() {
+            Some(found) => Some(found),
+            None => self
+                .viewport_snippet_area()
+                .map(|area| (area, imp.pdf_view().current_page() as usize)),
+        };
+        let Some((area, page_index)) = area else {
+            return;
+        };
+
+        let title = imp
+            .pdf_view()
+            .document()
+            .as_ref()
+            .and_then(|doc| doc.title())
+            .unwrap_or_else(|| {
+                Path::new(&pdf_path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| pdf_path.clone())
+            });
+        let footer = format!("{} — page {}", title, page_index + 1);
+
+        let Some(texture) = render_snippet(&imp.pdf_view(), &area, &footer) else {
+            self.show_toast("Could not render snippet");
+            return;
+        };
+
+        match path {
+            Some(path) => match texture.save_to_png(&path) {
+                Ok(()) => self.show_toast(&format!("Snippet saved to {}", path.display())),
+                Err(e) => {
+                    eprintln!("Failed to save snippet: {}", e);
+                    self.show_toast("Could not save snippet");
+                }
+            },
+            None => {
+                self.clipboard().set_texture(&texture);
+                self.show_toast("Snippet copied to clipboard");
+            }
+        }
+    }
+
+    /// Copy the document's BibTeX citation to the clipboard. The DOI or ISBN is looked
+    /// up in the metadata and the first pages, then resolved online on a worker thread.
+    fn copy_citation(&self) {
+        let imp = self.imp();
+
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            return;
+        };
+
+        if let Some(citation) = imp.citations.borrow().get(&pdf_path) {
+            self.clipboard().set_text(citation);
+            self.show_toast("Citation copied to clipboard");
+            return;
+        }
+
+        let identifier = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
+                return;
+            };
+            let mut texts = doc.metadata();
+            texts.extend(
+                (0..doc.page_count().min(CITATION_SEARCH_PAGES))
+                    .filter_map(|page_index| doc.page_chars(page_index))
+                    .map(|chars| page_text(&chars)),
+            );
+            citation::find_identifier(texts.iter().map(String::as_str))
+        };
+
+        let Some(identifier) = identifier else {
+            self.show_toast("No DOI or ISBN found in this document");
+            return;
+        };
+
+        self.show_toast(&format!("Fetching citation for {}…", identifier));
+
+        // Resumed on the main loop as soon as the fetching thread returns
+        let window_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || citation::fetch_bibtex(&identifier))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(CitationError::RequestFailed(
+                        "the request stopped".to_string(),
+                    ))
+                });
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            match result {
+                Ok(bibtex) => {
+                    window.clipboard().set_text(&bibtex);
+                    window.imp().citations.borrow_mut().insert(pdf_path, bibtex);
+                    window.show_toast("Citation copied to clipboard");
+                }
+                Err(e) => {
+                    eprintln!("Failed to fetch citation: {}", e);
+                    window.show_toast(&format!("Could not fetch citation: {}", e));
+                }
+            }
+        });
+    }
+
+    /// The visible part of the document, in PdfView coordinates
+    fn viewport_snippet_area(&self) -> Option<graphene::Rect> {
+        let imp = self.imp();
+        let scrolled = imp.scrolled_window.borrow().clone()?;
+
+        let origin = scrolled.compute_point(&imp.pdf_view(), &graphene::Point::zero())?;
+        let viewport = graphene::Rect::new(
+            origin.x(),
+            origin.y(),
+            scrolled.width() as f32,
+            scrolled.height() as f32,
+        );
+        let bounds = graphene::Rect::new(
+            0.0,
+            0.0,
+            imp.pdf_view().width() as f32,
+            imp.pdf_view().height() as f32,
+        );
+        viewport.intersection(&bounds)
+    }
+
+    /// Padded bounds of the Visual mode selection in PdfView coordinates, with the
+    /// page the selection starts on
+    fn selection_snippet_area(&self) -> Option<(graphene::Rect, usize)> {
+        let imp = self.imp();
+        let (start, end) = imp.app_mode.borrow().selection_range()?;
+
+        let cache = imp.text_cache.borrow();
+        let cache = cache.as_ref()?;
+
+        let mut area: Option<graphene::Rect> = None;
+        for page_index in start.page_index..=end.page_index {
+            let (Some(text_map), Some(overlay), Some(picture)) = (
+                cache.get(page_index),
+                imp.pdf_view().highlight_overlay(page_index),
+                imp.pdf_view().page_picture(page_index as u16),
+            ) else {
+                continue;
+            };
+            let Some(origin) = overlay.compute_point(&imp.pdf_view(), &graphene::Point::zero())
+            else {
+                continue;
+            };
+            let x_offset = calculate_picture_offset(&picture);
+            let render_width = imp.pdf_view().page_render_width(page_index);
+            let rotation = imp.pdf_view().page_rotation(page_index);
+            let crop = imp.pdf_view().page_crop(page_index);
+
+            let first = if page_index == start.page_index {
+                start.word_index
+            } else {
+                0
+            };
+            let last = if page_index == end.page_index {
+                (end.word_index + 1).min(text_map.word_count())
+            } else {
+                text_map.word_count()
+            };
+
+            for word_index in first..last {
+                let Some(word) = text_map.get_word(word_index) else {
+                    continue;
+                };
+                let rect = HighlightRect::from_pdf_bounds(
+                    &word.bounds,
+                    text_map.page_width,
+                    text_map.page_height,
+                    x_offset,
+                    render_width,
+                    rotation,
+                    crop,
+                );
+                let rect = graphene::Rect::new(
+                    origin.x() + rect.x as f32,
+                    origin.y() + rect.y as f32,
+                    rect.width as f32,
+                    rect.height as f32,
+                );
+                area = Some(match area {
+                    Some(area) => area.union(&rect),
+                    None => rect,
+                });
+            }
+        }
+
+        let bounds = graphene::Rect::new(
+            0.0,
+            0.0,
+            imp.pdf_view().width() as f32,
+            imp.pdf_view().height() as f32,
+        );
+        let area = area?
+            .inset_r(-SNIPPET_PADDING, -SNIPPET_PADDING)
+            .intersection(&bounds)?;
+        Some((area, start.page_index))
+    }
+
+    /// Re-open the current document from disk (e.g. after it was edited externally),
+    /// rebuilding the text caches while keeping scroll, zoom, mode and cursor
+    fn reload_document(&self) -> bool {
+        let imp = self.imp();
+
+        let Some(path) = imp.current_pdf_path.borrow().clone() else {
+            return false;
+        };
+
+        let scroll = imp
+            .scrolled_window
+            .borrow()
+            .as_ref()
+            .map(|s| (s.hadjustment().value(), s.vadjustment().value()));
+        let mode = imp.app_mode.borrow().clone();
+
+        // The zoom level lives on the view and survives the reload
+        if let Err(e) = imp.pdf_view().load_document(PathBuf::from(&path)) {
+            eprintln!("{}", e);
+            self.show_toast("Could not reload document");
+            return false;
+        }
+
+        self.init_text_cache();
+        imp.page_labels.replace(None);
+        self.reload_annotations();
+        self.extract_and_populate_toc_entries();
+        self.load_synctex(Path::new(&path));
+        self.refresh_split_view();
+
+        // The document may have lost pages or words since it was opened
+        let mode = match mode.cursor() {
+            Some(cursor) if !self.word_exists(cursor) => AppMode::exit_to_normal(),
+            _ => mode,
+        };
+        imp.pdf_view().set_cursor(mode.cursor());
+        imp.app_mode.replace(mode);
+        self.update_mode_display();
+        self.update_selection_display();
+
+        // Wait for the new pages to be laid out before scrolling back
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            if let (Some((x, y)), Some(scrolled)) =
+                (scroll, window.imp().scrolled_window.borrow().as_ref())
+            {
+                scrolled.hadjustment().set_value(x);
+                scrolled.vadjustment().set_value(y);
+            }
+            window.update_highlights();
+            window.refresh_text_overlays();
+        });
+
+        self.show_toast("Document reloaded");
+        true
+    }
+
+    /// Whether `cursor` points at a word of the loaded document
+    fn word_exists(&self, cursor: WordCursor) -> bool {
+        let imp = self.imp();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
+        let mut cache = imp.text_cache.borrow_mut();
+        let (Some(doc), Some(cache)) = (doc_borrow.as_ref(), cache.as_mut()) else {
+            return false;
+        };
+        cache
+            .get_or_build(cursor.page_index, doc.as_ref())
+            .is_some_and(|text_map| cursor.word_index < text_map.words.len())
+    }
+
+    fn setup_ui_font(&self) {
+        gtk::style_context_add_provider_for_display(
+            &WidgetExt::display(self),
+            &self.imp().ui_font_provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 1,
+        );
+    }
+
+    fn set_ui_font(&self, font: UiFont) {
+        let imp = self.imp();
+        imp.ui_font_provider.load_from_string(&font.css());
+        imp.ui_font.replace(font);
+    }
+
+    fn setup_theme(&self) {
+        let imp = self.imp();
+        let display = WidgetExt::display(self);
+        gtk::style_context_add_provider_for_display(
+            &display,
+            &imp.theme_provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 1,
+        );
+        gtk::style_context_add_provider_for_display(
+            &display,
+            &imp.user_css_provider,
+            gtk::STYLE_PROVIDER_PRIORITY_USER,
+        );
+        self.load_user_stylesheet();
+
+        let Some(path) = theme::user_stylesheet_path() else {
+            return;
+        };
+        let monitor = match gio::File::for_path(&path)
+            .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+        {
+            Ok(monitor) => monitor,
+            Err(e) => {
+                eprintln!("Failed to watch {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let window_weak = self.downgrade();
+        monitor.connect_changed(move |_, _, _, event| {
+            // Editors save in several writes, or by moving a new file in place
+            if !matches!(
+                event,
+                gio::FileMonitorEvent::ChangesDoneHint
+                    | gio::FileMonitorEvent::Created
+                    | gio::FileMonitorEvent::Deleted
+                    | gio::FileMonitorEvent::MovedIn
+                    | gio::FileMonitorEvent::Renamed
+            ) {
+                return;
+            }
+            if let Some(window) = window_weak.upgrade() {
+                window.load_user_stylesheet();
+                window.show_toast("Reloaded style.css");
+            }
+        });
+        imp.user_css_monitor.replace(Some(monitor));
+    }
+
+    /// Load ~/.config/eyers/style.css over the theme, or drop it when it's gone
+    fn load_user_stylesheet(&self) {
+        let imp = self.imp();
+        let css = theme::load_user_stylesheet().unwrap_or_default();
+        imp.user_css_provider.load_from_string(&css);
+        imp.user_css.replace(css);
+        self.update_highlight_palette();
+    }
+
+    fn set_theme(&self, theme: Theme) {
+        let imp = self.imp();
+        imp.theme.set(theme);
+        imp.theme_provider.load_from_string(theme.css());
+        if let Some(settings) = gtk::Settings::default() {
+            match theme.prefers_dark() {
+                Some(dark) => settings.set_gtk_application_prefer_dark_theme(dark),
+                None => settings.reset_property("gtk-application-prefer-dark-theme"),
+            }
+        }
+        self.update_highlight_palette();
+    }
+
+    /// Draw the highlights with the colors of the theme and the user stylesheet
+    fn update_highlight_palette(&self) {
+        let imp = self.imp();
+        let palette = HighlightPalette::default()
+            .with_stylesheet(imp.theme.get().css())
+            .with_stylesheet(&imp.user_css.borrow());
+        for view in self.tab_views() {
+            view.set_highlight_palette(palette);
+        }
+        imp.split_view.set_highlight_palette(palette);
+    }
+
+    fn show_settings_window(&self) {
+        let settings = SettingsWindow::new(self);
+        // The source decides which languages the dropdown offers, so it goes first
+        settings.set_dictionary_source(&self.imp().pdf_view().dictionary_source());
+        settings.set_language(self.dictionary_language());
+        settings.set_language_per_document(self.imp().document_language.get().is_some());
+        settings.set_has_document(self.imp().current_pdf_path.borrow().is_some());
+
+        let window_weak = self.downgrade();
+        settings.connect_selected_language_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                let lang = settings.language();
+                if window.imp().document_language.get().is_some() {
+                    window.set_document_language(Some(lang));
+                } else {
+                    window.imp().dictionary_language.set(lang);
+                    window.apply_dictionary_language();
+                }
+            }
+        });
+
+        let window_weak = self.downgrade();
+        settings.connect_language_per_document_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                let lang = settings
+                    .language_per_document()
+                    .then(|| settings.language());
+                window.set_document_language(lang);
+                settings.set_language(window.dictionary_language());
+            }
+        });
+
+        let window_weak = self.downgrade();
+        let update_dictionary_source = move |settings: &SettingsWindow| {
+            if let Some(window) = window_weak.upgrade() {
+                for view in window.tab_views() {
+                    view.set_dictionary_source(settings.dictionary_source());
+                }
+                window.update_startup_defaults(|defaults| {
+                    defaults.dictionary_source = settings.dictionary_source();
+                });
+            }
+        };
+        settings.connect_selected_dictionary_source_notify(update_dictionary_source.clone());
+        settings.connect_local_dictionary_dir_notify(update_dictionary_source);
+
+        let startup_defaults = self.imp().startup_defaults.borrow().clone();
+        settings.set_startup_mode(startup_defaults.mode);
+        settings.set_startup_header_bar(startup_defaults.show_header_bar);
+        settings.set_startup_status_bar(startup_defaults.show_status_bar);
+
+        let window_weak = self.downgrade();
+        let update_startup = move |settings: &SettingsWindow| {
+            if let Some(window) = window_weak.upgrade() {
+                window.update_startup_defaults(|defaults| {
+                    defaults.mode = settings.startup_mode();
+                    defaults.show_header_bar = settings.startup_header_bar();
+                    defaults.show_status_bar = settings.startup_status_bar();
+                });
+            }
+        };
+        settings.connect_selected_startup_mode_notify(update_startup.clone());
+        settings.connect_startup_header_bar_notify(update_startup.clone());
+        settings.connect_startup_status_bar_notify(update_startup);
+
+        settings.set_annotation_panel_position(self.imp().annotation_panel_position.get());
+
+        let saved_settings = self.imp().settings.borrow().clone();
+        settings.set_web_search(saved_settings.web_search.clone());
+
+        let window_weak = self.downgrade();
+        settings.connect_web_search_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                let template = settings.web_search();
+                window.update_settings(|saved| {
+                    saved.web_search = template;
+                });
+            }
+        });
+
+        settings.set_post_export_commands(saved_settings.post_export_commands.clone());
+
+        let window_weak = self.downgrade();
+        settings.connect_post_export_commands_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                let commands = settings.post_export_commands();
+                window.update_settings(|saved| {
+                    saved.post_export_commands = commands;
+                });
+            }
+        });
+
+        settings.set_theme(self.imp().theme.get());
+
+        let window_weak = self.downgrade();
+        settings.connect_selected_theme_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                let theme = settings.theme();
+                window.set_theme(theme);
+                window.update_settings(|saved| {
+                    saved.theme = theme;
+                });
+            }
+        });
+
+        settings.set_auto_crop(self.imp().pdf_view().auto_crop());
+
+        let window_weak = self.downgrade();
+        settings.connect_auto_crop_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                for view in window.tab_views() {
+                    view.set_auto_crop(settings.auto_crop());
+                }
+                window.update_settings(|saved| {
+                    saved.auto_crop = settings.auto_crop();
+                });
+            }
+        });
+
+        settings.set_clipboard_lookup(saved_settings.clipboard_lookup);
+
+        let window_weak = self.downgrade();
+        settings.connect_clipboard_lookup_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window.update_settings(|saved| {
+                    saved.clipboard_lookup = settings.clipboard_lookup();
+                });
+            }
+        });
+
+        let window_weak = self.downgrade();
+        settings.connect_selected_annotation_panel_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                let position = settings.annotation_panel_position();
+                window.set_annotation_panel_position(position);
+                window.update_settings(|saved| {
+                    saved.annotation_panel = position;
+                });
+            }
+        });
+
+        let (max_width, max_height) = self.imp().pdf_view().popover_max_size();
+        settings.set_popover_max_size(max_width, max_height);
+
+        let window_weak = self.downgrade();
+        let update_popover_size = move |settings: &SettingsWindow| {
+            if let Some(window) = window_weak.upgrade() {
+                for view in window.tab_views() {
+                    view.set_popover_max_size(
+                        settings.popover_max_width(),
+                        settings.popover_max_height(),
+                    );
+                }
+            }
+        };
+        settings.connect_popover_max_width_notify(update_popover_size.clone());
+        settings.connect_popover_max_height_notify(update_popover_size);
+
+        settings.set_ui_font(&self.imp().ui_font.borrow());
+
+        let window_weak = self.downgrade();
+        let update_ui_font = move |settings: &SettingsWindow| {
+            if let Some(window) = window_weak.upgrade() {
+                window.set_ui_font(settings.ui_font());
+            }
+        };
+        settings.connect_ui_font_family_notify(update_ui_font.clone());
+        settings.connect_ui_font_size_notify(update_ui_font);
+
+        settings.set_synctex_editor(self.imp().synctex_editor.borrow().clone());
+
+        let window_weak = self.downgrade();
+        settings.connect_synctex_editor_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window
+                    .imp()
+                    .synctex_editor
+                    .replace(settings.synctex_editor());
+            }
+        });
+
+        settings.set_popover_autohide(self.imp().pdf_view().popover_autohide());
+
+        let window_weak = self.downgrade();
+        settings.connect_popover_autohide_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                for view in window.tab_views() {
+                    view.set_popover_autohide(settings.popover_autohide());
+                }
+            }
+        });
+
+        settings.set_notes_sync_dir(self.imp().notes_sync_dir.borrow().clone());
+
+        let window_weak = self.downgrade();
+        settings.connect_notes_sync_dir_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window
+                    .imp()
+                    .notes_sync_dir
+                    .replace(settings.notes_sync_dir());
+            }
+        });
+
+        let api_port = self.imp().api_server.borrow().as_ref().map(ApiServer::port);
+        settings.set_api_port(api_port.unwrap_or(0) as u32);
+
+        let window_weak = self.downgrade();
+        settings.connect_api_port_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window.set_api_port(settings.api_port() as u16);
+            }
+        });
+
+        settings.set_presentation_fade(self.imp().presentation_fade.get());
+
+        let window_weak = self.downgrade();
+        settings.connect_presentation_fade_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window
+                    .imp()
+                    .presentation_fade
+                    .set(settings.presentation_fade());
+            }
+        });
+
+        settings.set_annotation_wrap(self.imp().annotation_wrap.get());
+
+        let window_weak = self.downgrade();
+        settings.connect_annotation_wrap_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().annotation_wrap.set(settings.annotation_wrap());
+            }
+        });
+
+        settings.set_prefetch_definitions(self.imp().prefetch_definitions.get());
+        settings.set_prefetch_online(self.imp().prefetch_online.get());
+
+        let window_weak = self.downgrade();
+        let update_prefetch = move |settings: &SettingsWindow| {
+            if let Some(window) = window_weak.upgrade() {
+                let imp = window.imp();
+                imp.prefetch_definitions
+                    .set(settings.prefetch_definitions());
+                imp.prefetch_online.set(settings.prefetch_online());
+                // Prefetch again for the current line with the new settings
+                imp.prefetch_line.set(None);
+            }
+        };
+        settings.connect_prefetch_definitions_notify(update_prefetch.clone());
+        settings.connect_prefetch_online_notify(update_prefetch);
+
+        settings.set_viewport_anchor(self.imp().viewport_anchor.get());
+
+        let window_weak = self.downgrade();
+        settings.connect_viewport_anchor_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().viewport_anchor.set(settings.viewport_anchor());
+            }
+        });
+
+        settings.set_cover_page(self.imp().pdf_view().cover_page());
+
+        let window_weak = self.downgrade();
+        settings.connect_cover_page_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                for view in window.tab_views() {
+                    view.set_cover_page(settings.cover_page());
+                }
+            }
+        });
+
+        settings.set_lookup_modifier(self.imp().pdf_view().lookup_modifier());
+
+        let window_weak = self.downgrade();
+        settings.connect_selected_lookup_modifier_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                for view in window.tab_views() {
+                    view.set_lookup_modifier(settings.lookup_modifier());
+                }
+            }
+        });
+
+        settings.set_join_style(self.imp().copy_join_style.get());
+        settings.set_bionic_reading(self.imp().bionic_reading.get());
+
+        let window_weak = self.downgrade();
+        settings.connect_bionic_reading_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().bionic_reading.set(settings.bionic_reading());
+                window.refresh_text_overlays();
+            }
+        });
+
+        settings.set_show_word_boxes(self.imp().show_word_boxes.get());
+
+        let window_weak = self.downgrade();
+        settings.connect_show_word_boxes_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().show_word_boxes.set(settings.show_word_boxes());
+                window.refresh_text_overlays();
+            }
+        });
+
+        settings.set_use_page_labels(self.imp().use_page_labels.get());
+
+        let window_weak = self.downgrade();
+        settings.connect_use_page_labels_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().use_page_labels.set(settings.use_page_labels());
+            }
+        });
+
+        settings.set_export_options(self.imp().export_options.get());
+
+        let window_weak = self.downgrade();
+        let update_export_options = move |settings: &SettingsWindow| {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().export_options.set(settings.export_options());
+            }
+        };
+        settings.connect_export_group_by_chapter_notify(update_export_options.clone());
+        settings.connect_export_group_by_tag_notify(update_export_options.clone());
+        settings.connect_selected_export_sort_notify(update_export_options);
+
+        settings.set_translator_config(&self.imp().translation_panel.translator_config());
+
+        let window_weak = self.downgrade();
+        let update_translator_config = move |settings: &SettingsWindow| {
+            if let Some(window) = window_weak.upgrade() {
+                let panel = &window.imp().translation_panel;
+                let current = panel.translator_config();
+                panel.set_translator_config(TranslatorConfig {
+                    source_lang: current.source_lang,
+                    target_lang: current.target_lang,
+                    ..settings.translator_config()
+                });
+            }
+        };
+        settings.connect_selected_translation_backend_notify(update_translator_config.clone());
+        settings.connect_libretranslate_url_notify(update_translator_config.clone());
+        settings.connect_libretranslate_api_key_notify(update_translator_config.clone());
+        settings.connect_deepl_api_key_notify(update_translator_config.clone());
+        settings.connect_translation_command_notify(update_translator_config);
+
+        let window_weak = self.downgrade();
+        settings.connect_selected_join_style_notify(move |settings| {
+            if let Some(window) = window_weak.upgrade() {
+                window.imp().copy_join_style.set(settings.join_style());
+            }
+        });
+
+        settings.present();
+    }
+
+    fn show_positions_window(&self) {
+        let pdf_path = match self.imp().current_pdf_path.borrow().clone() {
+            Some(p) => p,
+            None => return,
+        };
+
+        let window = PositionsWindow::new(self);
+        window.set_positions(&positions::load_positions_for_pdf(&pdf_path).unwrap_or_default());
+
+        let window_weak = self.downgrade();
+        let path = pdf_path.clone();
+        window.connect_closure(
+            "position-save-requested",
+            false,
+            glib::closure_local!(move |positions_window: &PositionsWindow, name: &str| {
+                let Some(this) = window_weak.upgrade() else {
+                    return;
+                };
+                let Some(cursor) = this.current_reading_position() else {
+                    return;
+                };
+
+                match positions::save_position(&path, name, cursor) {
+                    Ok(_) => {
+                        let saved = positions::load_positions_for_pdf(&path).unwrap_or_default();
+                        positions_window.set_positions(&saved);
+                    }
+                    Err(e) => eprintln!("Failed to save position: {}", e),
+                }
+            }),
+        );
+
+        let window_weak = self.downgrade();
+        let path = pdf_path.clone();
+        window.connect_closure(
+            "position-selected",
+            false,
+            glib::closure_local!(move |_positions_window: &PositionsWindow, id: i64| {
+                let Some(this) = window_weak.upgrade() else {
+                    return;
+                };
+                let saved = positions::load_positions_for_pdf(&path).unwrap_or_default();
+                if let Some(position) = saved.iter().find(|p| p.id == id) {
+                    this.jump_to_position(position.cursor());
+                }
+            }),
+        );
+
+        let path = pdf_path;
+        window.connect_closure(
+            "position-delete-requested",
+            false,
+            glib::closure_local!(move |positions_window: &PositionsWindow, id: i64| {
+                if let Err(e) = positions::delete_position(id) {
+                    eprintln!("Failed to delete position: {}", e);
+                }
+                let saved = positions::load_positions_for_pdf(&path).unwrap_or_default();
+                positions_window.set_positions(&saved);
+            }),
+        );
+
+        window.present();
+    }
+
+    /// The position to remember: the cursor in Visual mode, otherwise the top of the viewport
+    fn current_reading_position(&self) -> Option<WordCursor> {
+        let cursor = self.imp().app_mode.borrow().cursor();
+        cursor.or_else(|| self.compute_first_visible_word())
+    }
+
+    // ============ OCR ============
+
+    /// Recognize the text of the current page with OCR when it has none, or of
+    /// every page without text with `all`, one page after the other
+    fn recognize_pages(&self, all: bool) {
+        let imp = self.imp();
+        let pages = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
+                self.show_toast("No document open");
+                return;
+            };
+            let mut cache = imp.text_cache.borrow_mut();
+            let Some(cache) = cache.as_mut() else {
+                return;
+            };
+            if all {
+                cache.image_only_pages(doc.as_ref())
+            } else {
+                let page_index = pdf_view.current_page() as usize;
+                if cache.has_text(page_index, doc.as_ref()) {
+                    self.show_toast("This page already has text");
+                    return;
+                }
+                vec![page_index]
+            }
+        };
+        if pages.is_empty() {
+            self.show_toast("Every page already has text");
+            return;
+        }
+
+        let mut queue = imp.ocr_queue.borrow_mut();
+        for page_index in pages {
+            if !queue.contains(&page_index) && imp.ocr_page.get() != Some(page_index) {
+                queue.push_back(page_index);
+            }
+        }
+        drop(queue);
+
+        if imp.ocr_page.get().is_none() {
+            self.recognize_next_page();
+        }
+    }
+
+    /// Render the next queued page and recognize it on a worker thread
+    fn recognize_next_page(&self) {
+        let imp = self.imp();
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            return;
+        };
+        let Some(page_index) = imp.ocr_queue.borrow_mut().pop_front() else {
+            return;
+        };
+
+        let rendered = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
+                return;
+            };
+            doc.page_size(page_index)
+                .and_then(|(page_width, page_height)| {
+                    let width = (page_width * ocr::OCR_DPI / 72.0).round() as i32;
+                    let page = doc.render_page(page_index, width)?;
+                    Some((page, page_width / width as f64, page_height))
+                })
+        };
+        let Some((page, scale, page_height)) = rendered else {
+            self.show_toast(&format!("Could not render page {}", page_index + 1));
+            self.recognize_next_page();
+            return;
+        };
+
+        imp.ocr_page.set(Some(page_index));
+        let remaining = imp.ocr_queue.borrow().len();
+        if remaining > 0 {
+            self.show_toast(&format!(
+                "Recognizing page {} ({} more)…",
+                page_index + 1,
+                remaining
+            ));
+        } else {
+            self.show_toast(&format!("Recognizing page {}…", page_index + 1));
+        }
+
+        let language = ocr::tesseract_language(self.dictionary_language());
+        // Resumed on the main loop as soon as the recognition thread returns
+        let window_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let result =
+                gio::spawn_blocking(move || ocr::recognize(&page, scale, page_height, language))
+                    .await;
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            window.imp().ocr_page.set(None);
+            let Ok(result) = result else {
+                window.show_toast(&format!("Could not recognize page {}", page_index + 1));
+                window.recognize_next_page();
+                return;
+            };
+
+            // Another document was opened meanwhile
+            if window.imp().current_pdf_path.borrow().as_deref() != Some(pdf_path.as_str()) {
+                return;
+            }
+
+            match result {
+                Ok(words) => {
+                    if let Err(e) = ocr::save_page(&pdf_path, page_index, &words) {
+                        eprintln!("Failed to save recognized text: {}", e);
+                    }
+                    window.apply_recognized_text(page_index, &words);
+                    if window.imp().ocr_queue.borrow().is_empty() {
+                        window.show_toast(&format!(
+                            "Recognized {} words on page {}",
+                            words.len(),
+                            page_index + 1
+                        ));
+                    }
+                    window.recognize_next_page();
+                }
+                Err(e @ OcrError::NotInstalled) => {
+                    window.imp().ocr_queue.borrow_mut().clear();
+                    window.show_toast(&e.to_string());
+                }
+                Err(e) => {
+                    eprintln!("Failed to recognize page {}: {}", page_index + 1, e);
+                    window.show_toast(&e.to_string());
+                    window.recognize_next_page();
+                }
+            }
+        });
+    }
+
+    /// Make the words recognized on a page its text, for lookups, selections and
+    /// annotations
+    fn apply_recognized_text(&self, page_index: usize, words: &[OcrWord]) {
+        let imp = self.imp();
+        imp.pdf_view()
+            .set_recognized_text(page_index, ocr::words_to_chars(words));
+        if let Some(cache) = imp.text_cache.borrow_mut().as_mut() {
+            cache.invalidate(page_index);
+        }
+        self.update_annotation_highlights();
+    }
+
+    /// Bring back the text recognized on the open document's pages in earlier sessions
+    fn load_recognized_text(&self) {
+        let Some(pdf_path) = self.imp().current_pdf_path.borrow().clone() else {
+            return;
+        };
+        match ocr::load_pages(&pdf_path) {
+            Ok(pages) => {
+                for (page_index, words) in pages {
+                    self.apply_recognized_text(page_index, &words);
+                }
+            }
+            Err(e) => eprintln!("Failed to load recognized text: {}", e),
+        }
+    }
+
+    // ============ Ink Drawings ============
+
+    /// Draw on the pages with `tool`, in `color` when given, or go back to
+    /// selecting text with drags when `tool` is None
+    fn set_ink_tool(&self, tool: Option<InkTool>, color: Option<AnnotationColor>) {
+        let imp = self.imp();
+        if let Some(color) = color {
+            imp.ink_color.set(color);
+        }
+        let Some(tool) = tool else {
+            imp.pdf_view().set_drawing(false);
+            self.show_toast("Stopped drawing");
+            return;
+        };
+        if imp.current_pdf_path.borrow().is_none() {
+            self.show_toast("No document open");
+            return;
+        }
+
+        imp.ink_tool.set(tool);
+        imp.pdf_view().set_drawing(true);
+        self.show_toast(&format!(
+            "Drawing: {} in {}, :draw off to stop",
+            tool.label(),
+            imp.ink_color.get().label().to_lowercase()
+        ));
+    }
+
+    fn reload_ink_drawings(&self) {
+        let imp = self.imp();
+        let drawings = imp
+            .current_pdf_path
+            .borrow()
+            .as_deref()
+            .map(|path| {
+                ink::load_drawings_for_pdf(path).unwrap_or_else(|e| {
+                    eprintln!("Failed to load drawings: {}", e);
+                    Vec::new()
+                })
+            })
+            .unwrap_or_default();
+        imp.ink_drawings.replace(drawings);
+    }
+
+    fn start_ink_stroke(&self, x: f64, y: f64, page_index: usize) {
+        self.imp().ink_stroke.replace(Some(InkStroke {
+            page_index,
+            start: (x, y),
+            points: vec![(x, y)],
+        }));
+        self.update_ink_preview();
+    }
+
+    /// Follow the pointer, `offset_x` and `offset_y` away from where the drag started
+    fn extend_ink_stroke(&self, offset_x: f64, offset_y: f64) {
+        {
+            let mut stroke = self.imp().ink_stroke.borrow_mut();
+            let Some(stroke) = stroke.as_mut() else {
+                return;
+            };
+            let (x, y) = stroke.start;
+            stroke.points.push((x + offset_x, y + offset_y));
+        }
+        self.update_ink_preview();
+    }
+
+    fn update_ink_preview(&self) {
+        let imp = self.imp();
+        let stroke = imp.ink_stroke.borrow();
+        let Some(stroke) = stroke.as_ref() else {
+            return;
+        };
+        if let Some(overlay) = imp.pdf_view().highlight_overlay(stroke.page_index) {
+            overlay.set_ink_preview(Some(InkMark {
+                tool: imp.ink_tool.get(),
+                points: stroke.points.clone(),
+                color: imp.ink_color.get(),
+            }));
+        }
+    }
+
+    /// Save the drawing made with the pointer, converted to PDF points
+    fn finish_ink_stroke(&self) {
+        let imp = self.imp();
+        let Some(stroke) = imp.ink_stroke.take() else {
+            return;
+        };
+        if let Some(overlay) = imp.pdf_view().highlight_overlay(stroke.page_index) {
+            overlay.set_ink_preview(None);
+        }
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            return;
+        };
+
+        let points: Vec<(f64, f64)> = {
+            let pdf_view = imp.pdf_view();
+            let doc = pdf_view.document();
+            let Some(page_size) = doc
+                .as_ref()
+                .and_then(|doc| doc.page_size(stroke.page_index))
+            else {
+                return;
+            };
+            let Some(picture) = pdf_view.get_page_picture(stroke.page_index) else {
+                return;
+            };
+            let offset = calculate_picture_offset(&picture);
+            let render_width = pdf_view.page_render_width(stroke.page_index);
+            let rotation = pdf_view.page_rotation(stroke.page_index);
+            let crop = pdf_view.page_crop(stroke.page_index);
+
+            stroke
+                .points
+                .iter()
+                .map(|&(x, y)| {
+                    let click = calculate_click_coordinates_with_offset(
+                        x,
+                        y,
+                        page_size,
+                        offset,
+                        render_width,
+                        rotation,
+                        crop,
+                    );
+                    (click.pdf_x, click.pdf_y)
+                })
+                .collect()
+        };
+
+        let tool = imp.ink_tool.get();
+        let points = ink::finish_points(tool, &points);
+        let color = imp.ink_color.get();
+        self.write_annotations(
+            move || ink::save_drawing(&pdf_path, stroke.page_index, tool, &points, color),
+            |window, result| match result {
+                Ok(_) => {
+                    window.reload_ink_drawings();
+                    window.update_ink_highlights();
+                }
+                Err(e) => window.show_toast(&format!("Failed to save drawing: {}", e)),
+            },
+        );
+    }
+
+    /// Remove the last drawing made on the page at the reading position
+    fn erase_last_drawing(&self) {
+        let imp = self.imp();
+        let page_index = self
+            .current_reading_position()
+            .map(|cursor| cursor.page_index)
+            .unwrap_or_else(|| imp.pdf_view().current_page() as usize);
+        let id = ink::last_drawing_on_page(&imp.ink_drawings.borrow(), page_index).map(|d| d.id);
+        let Some(id) = id else {
+            self.show_toast("No drawings on this page");
+            return;
+        };
+
+        self.write_annotations(
+            move || ink::delete_drawing(id),
+            |window, result| match result {
+                Ok(()) => {
+                    window.reload_ink_drawings();
+                    window.update_ink_highlights();
+                }
+                Err(e) => window.show_toast(&format!("Failed to erase drawing: {}", e)),
+            },
+        );
+    }
+
+    /// Place the drawings on their pages at the current zoom and rotation
+    fn update_ink_highlights(&self) {
+        let imp = self.imp();
+        let drawings = imp.ink_drawings.borrow();
+        let pdf_view = imp.pdf_view();
+        let doc = pdf_view.document();
+        let page_pictures = pdf_view.page_pictures();
+
+        let mut page_marks: HashMap<usize, Vec<InkMark>> = HashMap::new();
+        if let Some(doc) = doc.as_ref() {
+            for drawing in drawings.iter() {
+                let Some(page_size) = doc.page_size(drawing.page_index) else {
+                    continue;
+                };
+                let x_offset = page_pictures
+                    .get(drawing.page_index)
+                    .map(|pic| calculate_picture_offset(pic))
+                    .unwrap_or(0.0);
+                let render_width = pdf_view.page_render_width(drawing.page_index);
+                let rotation = pdf_view.page_rotation(drawing.page_index);
+                let crop = pdf_view.page_crop(drawing.page_index);
+
+                let points = drawing
+                    .points
+                    .iter()
+                    .map(|&point| {
+                        let (x, y) =
+                            pdf_point_to_screen(point, page_size, render_width, rotation, crop);
+                        (x + x_offset, y)
+                    })
+                    .collect();
+                page_marks
+                    .entry(drawing.page_index)
+                    .or_default()
+                    .push(InkMark {
+                        tool: drawing.tool,
+                        points,
+                        color: drawing.color,
+                    });
+            }
+        }
+
+        for (page_index, overlay) in pdf_view.highlight_overlays().iter().enumerate() {
+            overlay.set_ink(page_marks.remove(&page_index).unwrap_or_default());
+        }
+    }
+
+    // ============ Crash Journal ============
+
+    /// Keep a journal of the open document, position and annotation draft while the
+    /// window is open, and pick up the one an abnormal exit left behind
+    fn setup_journal(&self) {
+        self.imp()
+            .recovered_journal
+            .replace(journal::read_journal());
+
+        let window_weak = self.downgrade();
+        glib::timeout_add_seconds_local(JOURNAL_INTERVAL_SECS, move || {
+            let Some(window) = window_weak.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            window.write_journal();
+            window.record_read_page();
+            glib::ControlFlow::Continue
+        });
+
+        self.connect_close_request(|_| {
+            if let Err(e) = journal::clear_journal() {
+                eprintln!("Failed to clear journal: {}", e);
+            }
+            glib::Propagation::Proceed
+        });
+    }
+
+    fn journal_entry(&self) -> Option<journal::JournalEntry> {
+        let imp = self.imp();
+        let pdf_path = imp.current_pdf_path.borrow().clone()?;
+        let cursor = self
+            .current_reading_position()
+            .unwrap_or_else(|| WordCursor::new(imp.pdf_view().current_page() as usize, 0));
+
+        let pending = *imp.pending_annotation.borrow();
+        let draft = pending
+            .filter(|_| imp.annotation_panel.is_visible())
+            .map(|(start, end)| journal::AnnotationDraft {
+                annotation_id: imp.annotation_panel.annotation_id(),
+                start_page: start.page_index,
+                start_word: start.word_index,
+                end_page: end.page_index,
+                end_word: end.word_index,
+                note: imp.annotation_panel.note(),
+            });
+
+        Some(journal::JournalEntry {
+            pdf_path,
+            page_index: cursor.page_index,
+            word_index: cursor.word_index,
+            draft,
+        })
+    }
+
+    fn write_journal(&self) {
+        let Some(entry) = self.journal_entry() else {
+            return;
+        };
+        if self.imp().last_journal.borrow().as_ref() == Some(&entry) {
+            return;
+        }
+
+        match journal::write_journal(&entry) {
+            Ok(()) => {
+                self.imp().last_journal.replace(Some(entry));
+            }
+            Err(e) => eprintln!("Failed to write journal: {}", e),
+        }
+    }
+
+    // ============ Reading Progress ============
+
+    /// Mark the page at the reading position as read, checked on every journal tick
+    fn record_read_page(&self) {
+        let imp = self.imp();
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            return;
+        };
+        let page_index = self
+            .current_reading_position()
+            .map(|cursor| cursor.page_index)
+            .unwrap_or_else(|| imp.pdf_view().current_page() as usize);
+
+        let (range, finished) = reading_log::track_page(imp.reading_range.get(), page_index);
+        imp.reading_range.set(Some(range));
+        if let Some((first_page, last_page)) = finished {
+            self.log_reading_event(ReadingEvent::PagesRead {
+                first_page,
+                last_page,
+            });
+        }
+
+        if !imp.read_pages.borrow_mut().insert(page_index) {
+            return;
+        }
+        if let Err(e) = positions::record_page_read(&pdf_path, page_index) {
+            eprintln!("Failed to record read page: {}", e);
+        }
+        self.update_chapter_progress();
+    }
+
+    /// Show how much of the document was read in the status bar, and the time
+    /// left at the recent reading speed
+    fn update_reading_progress(&self) {
+        let imp = self.imp();
+        let page_count = imp.pdf_view().total_pages() as usize;
+        let Some(position) = imp.pdf_view().reading_position().filter(|_| page_count > 0) else {
+            imp.status_bar.set_reading_progress(None);
+            self.update_window_title(None);
+            return;
+        };
+
+        let percent = reading_progress::progress_percent(position, page_count);
+        let mut speed = imp.reading_speed.borrow_mut();
+        speed.record(glib::monotonic_time() as f64 / 1_000_000.0, position);
+        let time_left = speed
+            .time_left(position, page_count)
+            .map(reading_progress::format_time_left);
+        imp.status_bar
+            .set_reading_progress(Some((percent, time_left.as_deref())));
+        self.update_window_title(Some(percent));
+    }
+
+    /// Show the document, page and progress in the window title and on the
+    /// launcher icon, so the reading position is visible from the task switcher
+    fn update_window_title(&self, percent: Option<f64>) {
+        let imp = self.imp();
+        let file_name = imp.current_pdf_path.borrow().as_deref().and_then(|path| {
+            Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        });
+
+        let title = match (file_name, percent) {
+            (Some(file_name), Some(percent)) => launcher::window_title(
+                &file_name,
+                imp.pdf_view().current_page() as usize + 1,
+                imp.pdf_view().total_pages() as usize,
+                percent,
+            ),
+            (Some(file_name), None) => file_name,
+            (None, _) => "Eyers".to_string(),
+        };
+        self.set_title(Some(&title));
+        launcher::set_progress(percent);
+    }
+
+    /// Log the pages read since the last jump, before leaving the document
+    fn finish_reading_range(&self) {
+        if let Some((first_page, last_page)) = self.imp().reading_range.take() {
+            self.log_reading_event(ReadingEvent::PagesRead {
+                first_page,
+                last_page,
+            });
+        }
+    }
+
+    fn load_read_pages(&self) {
+        let imp = self.imp();
+        let read_pages = imp
+            .current_pdf_path
+            .borrow()
+            .as_deref()
+            .map(|path| positions::load_read_pages(path).unwrap_or_default())
+            .unwrap_or_default();
+        imp.read_pages.replace(read_pages);
+        self.update_chapter_progress();
+    }
+
+    fn update_chapter_progress(&self) {
+        let imp = self.imp();
+        imp.toc_panel
+            .set_chapter_progress(&imp.read_pages.borrow(), imp.pdf_view().page_count());
+    }
+
+    /// Ask whether to restore the state of a previous run that exited abnormally
+    pub fn offer_journal_restore(&self) {
+        let Some(entry) = self.imp().recovered_journal.take() else {
+            return;
+        };
+        if !Path::new(&entry.pdf_path).exists() {
+            self.discard_journal();
+            return;
+        }
+
+        let file_name = Path::new(&entry.pdf_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| entry.pdf_path.clone());
+        let mut detail = format!(
+            "Eyers did not exit cleanly while reading {} (page {}).",
+            file_name,
+            entry.page_index + 1
+        );
+        if entry.draft.is_some() {
+            detail.push_str(" An unsaved annotation note can be recovered.");
+        }
+
+        let dialog = gtk::AlertDialog::builder()
+            .message("Restore Previous Session?")
+            .detail(detail)
+            .buttons(vec!["Discard".to_string(), "Restore".to_string()])
+            .cancel_button(0)
+            .default_button(1)
+            .build();
+
+        let window_weak = self.downgrade();
+        dialog.choose(Some(self), None::<&gio::Cancellable>, move |response| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            if matches!(response, Ok(1)) {
+                window.restore_journal(entry);
+            } else {
+                window.discard_journal();
+            }
+        });
+    }
+
+    fn discard_journal(&self) {
+        if let Err(e) = journal::clear_journal() {
+            eprintln!("Failed to clear journal: {}", e);
+        }
+        // Make sure the current state gets journaled again on the next tick
+        self.imp().last_journal.replace(None);
+    }
+
+    fn restore_journal(&self, entry: journal::JournalEntry) {
+        let is_open =
+            self.imp().current_pdf_path.borrow().as_deref() == Some(entry.pdf_path.as_str());
+        if !is_open {
+            self.open_file(Path::new(&entry.pdf_path));
+        }
+        if self.imp().current_pdf_path.borrow().as_deref() != Some(entry.pdf_path.as_str()) {
+            return;
+        }
+
+        // Wait for the freshly loaded pages to be laid out before scrolling
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            window.jump_to_position(entry.cursor());
+            if let Some(draft) = &entry.draft {
+                window.restore_annotation_draft(draft);
+            }
+        });
+    }
+
+    fn restore_annotation_draft(&self, draft: &journal::AnnotationDraft) {
+        let imp = self.imp();
+        let (start, end) = draft.range();
+
+        let selected_text = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let mut cache = imp.text_cache.borrow_mut();
+            let (Some(doc), Some(cache)) = (doc_borrow.as_ref(), cache.as_mut()) else {
+                return;
+            };
+            for page_index in start.page_index..=end.page_index {
+                cache.get_or_build(page_index, doc.as_ref());
+            }
+            cache.extract_text(start, end)
+        };
+
+        imp.pending_annotation.replace(Some((start, end)));
+        imp.annotation_panel.set_selected_text(&selected_text);
+        imp.annotation_panel.set_annotation_id(draft.annotation_id);
+        imp.annotation_panel.set_note(&draft.note);
+        // Drafts only keep the note, the tags are those of the annotation edited
+        let tags = draft
+            .annotation_id
+            .and_then(|id| annotations::get_annotation(id).ok())
+            .map(|ann| ann.tags)
+            .unwrap_or_default();
+        imp.annotation_panel.set_tags(&tags);
+        self.update_annotation_panel_links();
+        imp.annotation_panel.set_visible(true);
+        imp.annotation_panel.focus_input();
+    }
+
+    /// Scroll to a word position, moving the cursor there in Visual mode
+    fn jump_to_position(&self, cursor: WordCursor) {
+        let imp = self.imp();
+
+        imp.pdf_view().scroll_to_page(cursor.page_index as u16);
+
+        // The target page may not have been visited yet
+        {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let mut cache = imp.text_cache.borrow_mut();
+            if let (Some(doc), Some(cache)) = (doc_borrow.as_ref(), cache.as_mut()) {
+                cache.get_or_build(cursor.page_index, doc.as_ref());
+            }
+        }
+
+        if imp.app_mode.borrow().is_visual() {
+            self.move_cursor(cursor);
+        } else {
+            self.ensure_cursor_visible(cursor);
+        }
+    }
+
+    // ============ Jump List ============
+
+    /// Mouse back/forward buttons walk the jump list like browser history, as do
+    /// Ctrl+O / Ctrl+I
+    fn setup_jump_navigation(&self) {
+        let gesture = gtk::GestureClick::new();
+        gesture.set_button(0);
+
+        let window_weak = self.downgrade();
+        gesture.connect_pressed(move |gesture, _, _, _| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            match gesture.current_button() {
+                MOUSE_BUTTON_BACK => window.jump_back(),
+                MOUSE_BUTTON_FORWARD => window.jump_forward(),
+                _ => {
+                    gesture.set_state(gtk::EventSequenceState::Denied);
+                }
+            }
+        });
+
+        self.add_controller(gesture);
+    }
+
+    /// Remember the reading position before a jump
+    fn record_jump(&self) {
+        if let Some(position) = self.current_reading_position() {
+            self.imp().jump_list.borrow_mut().record(position);
+            self.record_visit(position);
+        }
+    }
+
+    /// Add the spot being left to the reading history
+    fn record_visit(&self, position: WordCursor) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        self.imp().visit_history.borrow_mut().record(position, now);
+    }
+
+    fn jump_back(&self) {
+        let Some(current) = self.current_reading_position() else {
+            return;
+        };
+        let target = self.imp().jump_list.borrow_mut().back(current);
+        if let Some(target) = target {
+            self.record_visit(current);
+            self.jump_to_position(target);
+        }
+    }
+
+    fn jump_forward(&self) {
+        let target = self.imp().jump_list.borrow_mut().forward();
+        if let Some(target) = target {
+            if let Some(current) = self.current_reading_position() {
+                self.record_visit(current);
+            }
+            self.jump_to_position(target);
+        }
+    }
+
+    /// Store the current position under `letter` for the open document
+    fn set_mark(&self, letter: char) {
+        let Some(pdf_path) = self.imp().current_pdf_path.borrow().clone() else {
+            self.show_toast("No document open");
+            return;
+        };
+        let Some(cursor) = self.current_reading_position() else {
+            return;
+        };
+
+        match marks::set_mark(&pdf_path, letter, cursor) {
+            Ok(()) => {
+                self.load_marks();
+                self.show_toast(&format!("Mark '{}' set", letter));
+            }
+            Err(e) => {
+                eprintln!("Failed to set mark: {}", e);
+                self.show_toast("Could not set the mark");
+            }
+        }
+    }
+
+    fn jump_to_mark(&self, letter: char) {
+        let target = marks::find_mark(&self.imp().marks.borrow(), letter).map(Mark::cursor);
+        match target {
+            Some(cursor) => {
+                self.record_jump();
+                self.jump_to_position(cursor);
+            }
+            None => self.show_toast(&format!("Mark '{}' not set", letter)),
+        }
+    }
+
+    /// Read the marks of the open document and list them in the TOC panel
+    fn load_marks(&self) {
+        let imp = self.imp();
+        let loaded = match imp.current_pdf_path.borrow().as_deref() {
+            Some(path) => marks::load_marks(path).unwrap_or_else(|e| {
+                eprintln!("Failed to load marks: {}", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        imp.toc_panel.populate_marks(&loaded);
+        imp.marks.replace(loaded);
+    }
+
+    /// Pick a document to open in the current tab, or in a new one with `new_tab`
+    fn show_open_dialog(&self, new_tab: bool) {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("PDF and EPUB documents"));
+        filter.add_suffix("pdf");
+        filter.add_suffix("epub");
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.append(&filter);
+
+        let dialog = gtk::FileDialog::builder()
+            .title("Select a Document")
+            .filters(&filters)
+            .default_filter(&filter)
+            .build();
+        let window_weak = self.downgrade();
+
+        dialog.open(Some(self), None::<&gio::Cancellable>, move |result| {
+            if let Some(window) = window_weak.upgrade() {
+                window.handle_file_dialog_result(result, new_tab);
+            }
+        });
+    }
+
+    fn handle_file_dialog_result(&self, result: Result<gio::File, glib::Error>, new_tab: bool) {
+        let file = match result {
+            Ok(f) => f,
+            Err(_) => return,
+        };
+
+        let path = match file.path() {
+            Some(p) => p,
+            None => return,
+        };
+
+        if new_tab {
+            self.open_file_in_new_tab(&path);
+        } else {
+            self.open_file(&path);
+        }
+    }
+
+    /// Show export annotations confirmation dialog
+    fn show_export_annotations_dialog(&self) {
+        let pdf_path = match self.imp().current_pdf_path.borrow().as_ref() {
+            Some(p) => p.clone(),
+            None => {
+                eprintln!("No PDF loaded, cannot export annotations");
+                return;
+            }
+        };
+
+        // Check if there are any annotations to export
+        let annotations = match annotations::load_annotations_for_pdf(&pdf_path) {
+            Ok(anns) => anns,
+            Err(e) => {
+                eprintln!("Failed to load annotations: {}", e);
+                return;
+            }
+        };
+
+        if annotations.is_empty() {
+            // Show a dialog saying there are no annotations
+            let dialog = gtk::AlertDialog::builder()
+                .message("No Annotations")
+                .detail("There are no annotations to export for this PDF.")
+                .buttons(["OK"])
+                .build();
+            dialog.show(Some(self));
+            return;
+        }
+
+        // The annotations can be limited to the recent ones, so periodic exports
+        // don't repeat the notes already exported
+        let now = glib::DateTime::now_local()
+            .map(|now| now.to_unix())
+            .unwrap_or_default();
+        let last_export = annotations::last_export(&pdf_path).unwrap_or_else(|e| {
+            eprintln!("Failed to read the last export time: {}", e);
+            None
+        });
+        let changed_since = |since: i64| {
+            annotations
+                .iter()
+                .filter(|a| a.changed_since(since))
+                .count()
+        };
+
+        let mut ranges = vec![("All", None), ("Last Week", Some(now - EXPORT_WEEK_SECS))];
+        let mut detail = format!(
+            "Export {} annotation(s) to a Markdown file?\n\n{} added or edited in the last week.",
+            annotations.len(),
+            changed_since(now - EXPORT_WEEK_SECS)
+        );
+        if let Some(last_export) = last_export {
+            ranges.push(("Since Last Export", Some(last_export)));
+            let date = glib::DateTime::from_unix_local(last_export)
+                .ok()
+                .and_then(|date| date.format("%Y-%m-%d %H:%M").ok())
+                .map(|date| date.to_string())
+                .unwrap_or_default();
+            detail.push_str(&format!(
+                "\n{} since the last export, on {}.",
+                changed_since(last_export),
+                date
+            ));
+        }
+
+        // Show confirmation dialog
+        let mut buttons = vec!["Cancel"];
+        buttons.extend(ranges.iter().map(|(label, _)| *label));
+        let dialog = gtk::AlertDialog::builder()
+            .message("Export Annotations")
+            .detail(&detail)
+            .buttons(buttons.as_slice())
+            .default_button(ranges.len() as i32)
+            .cancel_button(0)
+            .build();
+
+        let window_weak = self.downgrade();
+        dialog.choose(Some(self), None::<&gio::Cancellable>, move |result| {
+            if let Some(window) = window_weak.upgrade() {
+                // Button 0 is "Cancel", the others the ranges in order
+                if let Some(&(_, since)) = result
+                    .ok()
+                    .and_then(|choice| ranges.get((choice as usize).checked_sub(1)?))
+                {
+                    window.show_export_file_chooser(since);
+                }
+            }
+        });
+    }
+
+    /// Show file chooser for saving exported annotations
+    fn show_export_file_chooser(&self, since: Option<i64>) {
+        let pdf_path = match self.imp().current_pdf_path.borrow().as_ref() {
+            Some(p) => p.clone(),
+            None => return,
+        };
+
+        // Generate default filename from PDF name
+        let default_filename = annotations::export_file_name(&pdf_path);
+
+        let dialog = gtk::FileDialog::builder()
+            .title("Save Annotations")
+            .initial_name(&default_filename)
+            .build();
+
+        let window_weak = self.downgrade();
+        dialog.save(Some(self), None::<&gio::Cancellable>, move |result| {
+            if let Some(window) = window_weak.upgrade() {
+                window.handle_export_save_result(result, since);
+            }
+        });
+    }
+
+    /// Handle the result of the export file save dialog, exporting the annotations
+    /// changed since `since` or all of them
+    fn handle_export_save_result(
+        &self,
+        result: Result<gio::File, glib::Error>,
+        since: Option<i64>,
+    ) {
+        let file = match result {
+            Ok(f) => f,
+            Err(_) => return, // User cancelled
+        };
+
+        let save_path = match file.path() {
+            Some(p) => p,
+            None => return,
+        };
+
+        let pdf_path = match self.imp().current_pdf_path.borrow().as_ref() {
+            Some(p) => p.clone(),
+            None => return,
+        };
+
+        // Get PDF name for the markdown header
+        let pdf_name = Path::new(&pdf_path)
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Unknown PDF");
+
+        // Generate markdown content
+        let chapters = self.imp().pdf_view().bookmarks();
+        let options = ExportOptions {
+            since,
+            ..self.imp().export_options.get()
+        };
+        let exported_at = glib::DateTime::now_local()
+            .map(|now| now.to_unix())
+            .unwrap_or_default();
+        let citation = self.imp().citations.borrow().get(&pdf_path).cloned();
+        let markdown = match annotations::export_to_markdown(
+            &pdf_path,
+            pdf_name,
+            citation.as_deref(),
+            &chapters,
+            &options,
+        ) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to generate markdown: {}", e);
+                self.show_export_error(&format!("Failed to generate markdown: {}", e));
+                return;
+            }
+        };
+
+        // Write to file
+        if let Err(e) = fs::write(&save_path, &markdown) {
+            eprintln!("Failed to write file: {}", e);
+            self.show_export_error(&format!("Failed to write file: {}", e));
+            return;
+        }
+        let exported_pdf = pdf_path.clone();
+        self.write_annotations(
+            move || annotations::record_export(&exported_pdf, exported_at),
+            |_, result| {
+                if let Err(e) = result {
+                    eprintln!("Failed to record the export time: {}", e);
+                }
+            },
+        );
+        self.run_post_export_commands(vec![ExportedFile::new(&save_path, &pdf_path)]);
+
+        // Show success message
+        let dialog = gtk::AlertDialog::builder()
+            .message("Export Successful")
+            .detail(&format!("Annotations saved to:\n{}", save_path.display()))
+            .buttons(["OK"])
+            .build();
+        dialog.show(Some(self));
+    }
+
+    /// Export the recorded lookups and annotation notes as an Anki import file
+    fn show_flashcards_export_dialog(&self) {
+        let cards = flashcards::load_lookups().and_then(|mut cards| {
+            cards.extend(flashcards::annotation_cards()?);
+            Ok(cards)
+        });
+        let cards = match cards {
+            Ok(cards) => cards,
+            Err(e) => {
+                self.show_export_error(&format!("Failed to load flashcards: {}", e));
+                return;
+            }
+        };
+
+        if cards.is_empty() {
+            let dialog = gtk::AlertDialog::builder()
+                .message("No Flashcards")
+                .detail("Words looked up in the dictionary and annotation notes become flashcards.")
+                .buttons(["OK"])
+                .build();
+            dialog.show(Some(self));
+            return;
+        }
+
+        let dialog = gtk::FileDialog::builder()
+            .title("Export Flashcards")
+            .initial_name(flashcards::EXPORT_FILE_NAME)
+            .build();
+
+        let window_weak = self.downgrade();
+        dialog.save(Some(self), None::<&gio::Cancellable>, move |result| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(save_path) = result.ok().and_then(|file| file.path()) else {
+                return; // User cancelled
+            };
+
+            if let Err(e) = fs::write(&save_path, flashcards::to_anki_tsv(&cards)) {
+                window.show_export_error(&format!("Failed to write file: {}", e));
+                return;
+            }
+            let dialog = gtk::AlertDialog::builder()
+                .message("Export Successful")
+                .detail(&format!(
+                    "{} flashcard(s) saved to:\n{}\n\nImport the file in Anki with File > Import.",
+                    cards.len(),
+                    save_path.display()
+                ))
+                .buttons(["OK"])
+                .build();
+            dialog.show(Some(&window));
+        });
+    }
+
+    /// Show an error dialog for export failures
+    fn show_export_error(&self, message: &str) {
+        let dialog = gtk::AlertDialog::builder()
+            .message("Export Failed")
+            .detail(message)
+            .buttons(["OK"])
+            .build();
+        dialog.show(Some(self));
+    }
+
+    /// Export the annotations of every annotated document into `dir`, asking for
+    /// the directory when none was given
+    fn export_all_annotations(&self, dir: Option<PathBuf>, combined: bool) {
+        if let Some(dir) = dir {
+            self.write_all_annotations(&dir, combined);
+            return;
+        }
+
+        let dialog = gtk::FileDialog::builder()
+            .title("Export All Annotations To")
+            .build();
+
+        let window_weak = self.downgrade();
+        dialog.select_folder(Some(self), None::<&gio::Cancellable>, move |result| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            // Err when the user cancelled
+            if let Some(dir) = result.ok().and_then(|folder| folder.path()) {
+                window.write_all_annotations(&dir, combined);
+            }
+        });
+    }
+
+    /// Write one markdown file per annotated document into `dir`, or all of them
+    /// into a single `annotations.md` when `combined`
+    fn write_all_annotations(&self, dir: &Path, combined: bool) {
+        let imp = self.imp();
+
+        let pdf_paths = match annotations::annotated_documents() {
+            Ok(paths) => paths,
+            Err(e) => {
+                self.show_export_error(&format!("Failed to load annotations: {}", e));
+                return;
+            }
+        };
+        if pdf_paths.is_empty() {
+            self.show_toast("No annotations to export");
+            return;
+        }
+
+        if let Err(e) = fs::create_dir_all(dir) {
+            self.show_export_error(&format!("Failed to create {}: {}", dir.display(), e));
+            return;
+        }
+
+        let options = imp.export_options.get();
+        let current_pdf = imp.current_pdf_path.borrow().clone();
+        let file_names = annotations::unique_export_file_names(&pdf_paths);
+        let mut documents = Vec::with_capacity(pdf_paths.len());
+
+        for (pdf_path, file_name) in pdf_paths.iter().zip(file_names) {
+            let pdf_name = Path::new(pdf_path)
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("Unknown PDF");
+            let chapters = if current_pdf.as_deref() == Some(pdf_path.as_str()) {
+                imp.pdf_view().bookmarks()
+            } else if options.group_by_chapter {
+                self.load_document_chapters(pdf_path)
+            } else {
+                Vec::new()
+            };
+            let citation = imp.citations.borrow().get(pdf_path).cloned();
+
+            match annotations::export_to_markdown(
+                pdf_path,
+                pdf_name,
+                citation.as_deref(),
+                &chapters,
+                &options,
+            ) {
+                Ok(markdown) => documents.push((file_name, markdown)),
+                Err(e) => {
+                    self.show_export_error(&format!("Failed to export {}: {}", pdf_name, e));
+                    return;
+                }
+            }
+        }
+
+        let exported: Vec<ExportedFile> = if combined {
+            vec![ExportedFile {
+                output: dir.join("annotations.md"),
+                document: "annotations".to_string(),
+            }]
+        } else {
+            pdf_paths
+                .iter()
+                .zip(&documents)
+                .map(|(pdf_path, (file_name, _))| ExportedFile::new(&dir.join(file_name), pdf_path))
+                .collect()
+        };
+        let written = if combined {
+            let markdown = documents
+                .into_iter()
+                .map(|(_, markdown)| markdown)
+                .collect::<Vec<_>>()
+                .join("\n");
+            fs::write(dir.join("annotations.md"), markdown).map(|_| 1)
+        } else {
+            documents
+                .iter()
+                .try_for_each(|(file_name, markdown)| fs::write(dir.join(file_name), markdown))
+                .map(|_| documents.len())
+        };
+
+        match written {
+            Ok(files) => {
+                self.show_toast(&format!(
+                    "Exported annotations of {} document(s) to {} file(s) in {}",
+                    pdf_paths.len(),
+                    files,
+                    dir.display()
+                ));
+                self.run_post_export_commands(exported);
+            }
+            Err(e) => self.show_export_error(&format!("Failed to write file: {}", e)),
+        }
+    }
+
+    /// Run the post-export commands of the settings on the exported files in the
+    /// background, telling how it went in a toast
+    fn run_post_export_commands(&self, exported: Vec<ExportedFile>) {
+        let commands = self.imp().settings.borrow().post_export_commands.clone();
+        if post_export::parse_commands(&commands).is_empty() {
+            return;
+        }
+
+        // Resumed on the main loop once every command has returned
+        let window_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || {
+                exported
+                    .iter()
+                    .try_for_each(|file| post_export::run_commands(&commands, file).map(|_| ()))
+                    .map_err(|e| e.to_string())
+            })
+            .await
+            .unwrap_or_else(|_| Err("Post-export commands stopped".to_string()));
+
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            match result {
+                Ok(()) => window.show_toast("Post-export commands finished"),
+                Err(e) => {
+                    eprintln!("Post-export command failed: {}", e);
+                    window.show_toast(&format!("Post-export command failed: {}", e));
+                }
+            }
+        });
+    }
+
+    /// Rewrite the live-synced notes file of the open document, when live sync is on
+    fn sync_notes_file(&self) {
+        let imp = self.imp();
+
+        let dir = imp.notes_sync_dir.borrow().trim().to_string();
+        if dir.is_empty() {
+            return;
+        }
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            return;
+        };
+        let dir = command::expand_home(&dir);
+
+        let pdf_name = Path::new(&pdf_path)
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Unknown PDF");
+        let chapters = imp.pdf_view().bookmarks();
+        let options = imp.export_options.get();
+        let citation = imp.citations.borrow().get(&pdf_path).cloned();
+
+        let result = annotations::library_export_file_name(&pdf_path).and_then(|file_name| {
+            let markdown = annotations::export_to_markdown(
+                &pdf_path,
+                pdf_name,
+                citation.as_deref(),
+                &chapters,
+                &options,
+            )?;
+            Ok((file_name, markdown))
+        });
+
+        let written = match result {
+            Ok((file_name, markdown)) => fs::create_dir_all(&dir)
+                .and_then(|_| fs::write(dir.join(&file_name), markdown))
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = written {
+            eprintln!("Failed to sync notes to {}: {}", dir.display(), e);
+            self.show_toast(&format!("Live notes sync failed: {}", e));
+        }
+    }
+
+    /// Serve the HTTP API on `port`, or stop it with 0
+    fn set_api_port(&self, port: u16) {
+        let imp = self.imp();
+
+        let current = imp.api_server.borrow().as_ref().map(ApiServer::port);
+        if current.unwrap_or(0) == port {
+            return;
+        }
+        // Stop the previous server first, which also ends the wait for its events below
+        imp.api_server.replace(None);
+        if port == 0 {
+            self.show_toast("HTTP API stopped");
+            return;
+        }
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        match ApiServer::start(port, sender) {
+            Ok(server) => {
+                imp.api_server.replace(Some(server));
+                self.show_toast(&format!("HTTP API listening on 127.0.0.1:{}", port));
+            }
+            Err(e) => {
+                eprintln!("Failed to start the HTTP API: {}", e);
+                self.show_toast(&format!("Could not start the HTTP API: {}", e));
+                return;
+            }
+        }
+
+        // Each event is waited for on a worker thread and handled back on the main
+        // loop, until the server stops and drops its sender
+        let window_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let mut receiver = receiver;
+            loop {
+                let Ok((event, returned)) =
+                    gio::spawn_blocking(move || (receiver.recv(), receiver)).await
+                else {
+                    return;
+                };
+                receiver = returned;
+                let (Ok(event), Some(window)) = (event, window_weak.upgrade()) else {
+                    return;
+                };
+                window.handle_api_event(event);
+            }
+        });
+    }
+
+    fn handle_api_event(&self, event: ApiEvent) {
+        let imp = self.imp();
+        match event {
+            ApiEvent::AnnotationCreated { pdf_path } => {
+                let is_open = imp.current_pdf_path.borrow().as_deref() == Some(pdf_path.as_str());
+                if !is_open {
+                    return;
+                }
+                self.reload_annotations();
+                self.update_annotation_highlights();
+                imp.toc_panel
+                    .populate_annotations(&imp.annotations.borrow());
+                self.sync_notes_file();
+                self.show_toast("Annotation added through the HTTP API");
+            }
+        }
+    }
+
+    /// Save a copy of the open PDF with the annotations written into it, at `path`
+    /// or where the user picks
+    fn export_annotated_pdf(&self, path: Option<PathBuf>) {
+        let Some(pdf_path) = self.imp().current_pdf_path.borrow().clone() else {
+            self.show_toast("No document open");
+            return;
+        };
+        if EpubBackend::handles(Path::new(&pdf_path)) {
+            self.show_toast("Annotations can only be written into PDFs");
+            return;
+        }
+        if let Some(path) = path {
+            self.write_annotated_pdf(&pdf_path, &path);
+            return;
+        }
+
+        let dialog = gtk::FileDialog::builder()
+            .title("Save PDF with Annotations")
+            .initial_name(annotations::annotated_pdf_file_name(&pdf_path))
+            .build();
+
+        let window_weak = self.downgrade();
+        dialog.save(Some(self), None::<&gio::Cancellable>, move |result| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            // Err when the user cancelled
+            if let Some(path) = result.ok().and_then(|file| file.path()) {
+                window.write_annotated_pdf(&pdf_path, &path);
+            }
+        });
+    }
+
+    fn write_annotated_pdf(&self, pdf_path: &str, output: &Path) {
+        let Some(pdfium) = *self.imp().pdfium.borrow() else {
+            return;
+        };
+        if self.imp().annotations.borrow().is_empty() {
+            self.show_toast("No annotations to write");
+            return;
+        }
 
-            imp.pdf_view.set_current_popover(Some(popover));
+        let highlights = self.pdf_highlights();
+        let source = Path::new(pdf_path);
+        match annotations::write_highlights_to_pdf(pdfium, source, output, &highlights) {
+            Ok(written) => self.show_toast(&format!(
+                "Wrote {} highlight(s) to {}",
+                written,
+                output.display()
+            )),
+            Err(e) => self.show_export_error(&format!("Failed to write PDF: {}", e)),
         }
     }
 
-    /// Translate the text between start and end cursors
-    fn translate_range(&self, start: WordCursor, end: WordCursor) {
+    /// The annotations of the open document as written into the PDF: one rect per
+    /// line they cover, on each of their pages
+    fn pdf_highlights(&self) -> Vec<PdfHighlight> {
         let imp = self.imp();
-
-        let cache = imp.text_cache.borrow();
-        let cache = match cache.as_ref() {
-            Some(c) => c,
-            None => return,
+        let annotations = imp.annotations.borrow();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
+        let Some(doc) = doc_borrow.as_ref() else {
+            return Vec::new();
+        };
+        let mut cache = imp.text_cache.borrow_mut();
+        let Some(cache) = cache.as_mut() else {
+            return Vec::new();
         };
 
-        let mut text_parts: Vec<String> = Vec::new();
-
-        // Collect text from start to end (possibly across pages)
-        if start.page_index == end.page_index {
-            // Same page
-            if let Some(text_map) = cache.get(start.page_index) {
-                let word_start = start.word_index.min(end.word_index);
-                let word_end = start.word_index.max(end.word_index);
-
-                for idx in word_start..=word_end {
-                    if let Some(word) = text_map.get_word(idx) {
-                        text_parts.push(word.text.clone());
-                    }
-                }
-            }
-        } else {
-            // Cross-page selection
-            let (first, last) = if start.page_index < end.page_index {
-                (start, end)
-            } else {
-                (end, start)
-            };
-
-            // First page: from start.word_index to end of page
-            if let Some(text_map) = cache.get(first.page_index) {
-                for idx in first.word_index..text_map.word_count() {
-                    if let Some(word) = text_map.get_word(idx) {
-                        text_parts.push(word.text.clone());
-                    }
-                }
-            }
-
-            // Middle pages: all words
-            for page_idx in (first.page_index + 1)..last.page_index {
-                if let Some(text_map) = cache.get(page_idx) {
-                    for idx in 0..text_map.word_count() {
-                        if let Some(word) = text_map.get_word(idx) {
-                            text_parts.push(word.text.clone());
+        let mut highlights = Vec::new();
+        for ann in annotations.iter() {
+            for page_index in ann.start_page..=ann.end_page {
+                let Some(text_map) = cache.get_or_build(page_index, doc.as_ref()) else {
+                    continue;
+                };
+                let word_start = if page_index == ann.start_page {
+                    ann.start_word
+                } else {
+                    0
+                };
+                let word_end = if page_index == ann.end_page {
+                    (ann.end_word + 1).min(text_map.word_count())
+                } else {
+                    text_map.word_count()
+                };
+
+                // Words of a line merged into one rect
+                let mut lines: Vec<(usize, PdfRect)> = Vec::new();
+                for word in text_map.words.get(word_start..word_end).unwrap_or_default() {
+                    match lines.last_mut() {
+                        Some((line_index, rect)) if *line_index == word.line_index => {
+                            *rect = PdfRect::new_from_values(
+                                rect.bottom().value.min(word.bounds.bottom().value),
+                                rect.left().value.min(word.bounds.left().value),
+                                rect.top().value.max(word.bounds.top().value),
+                                rect.right().value.max(word.bounds.right().value),
+                            );
                         }
+                        _ => lines.push((word.line_index, word.bounds)),
                     }
                 }
-            }
 
-            // Last page: from start to end.word_index
-            if let Some(text_map) = cache.get(last.page_index) {
-                for idx in 0..=last.word_index {
-                    if let Some(word) = text_map.get_word(idx) {
-                        text_parts.push(word.text.clone());
-                    }
-                }
+                highlights.push(PdfHighlight {
+                    page_index,
+                    rects: lines.into_iter().map(|(_, rect)| rect).collect(),
+                    note: if page_index == ann.start_page {
+                        ann.note.clone()
+                    } else {
+                        String::new()
+                    },
+                    color: ann.color,
+                });
             }
         }
+        highlights
+    }
 
-        let text = text_parts.join(" ");
-        if !text.is_empty() {
-            imp.translation_panel.set_visible(true);
-            imp.translation_panel.translate(text);
+    /// Chapters of a document that is not open, to group its exported annotations
+    fn load_document_chapters(&self, pdf_path: &str) -> Vec<BookmarkEntry> {
+        let Some(pdfium) = *self.imp().pdfium.borrow() else {
+            return Vec::new();
+        };
+        match backend::open_document(pdfium, Path::new(pdf_path)) {
+            Ok(document) => document.outline(),
+            Err(e) => {
+                eprintln!("Failed to open {} for its chapters: {}", pdf_path, e);
+                Vec::new()
+            }
         }
     }
 
-    /// Execute a find operation (f/F + char)
-    fn execute_find(&self, target_char: char, forward: bool) -> bool {
+    /// Open a PDF file from a path in the current tab (public API for CLI usage),
+    /// or go to the tab already showing it
+    pub fn open_file(&self, path: &Path) {
         let imp = self.imp();
+        let active = imp.tabs.borrow().active_index();
+        let existing = imp.tabs.borrow().position(path);
+        if let Some(index) = existing.filter(|&index| index != active) {
+            self.switch_to_tab(index);
+            return;
+        }
 
-        // Only works in Visual mode
-        let cursor = match imp.app_mode.borrow().cursor() {
-            Some(c) => c,
-            None => return false,
+        self.leave_document();
+        if !self.load_document(path) {
+            return;
+        }
+        let Some(scrolled_window) = imp.scrolled_window.borrow().clone() else {
+            return;
         };
+        let state = TabState::new(imp.pdf_view(), scrolled_window);
+        imp.tabs.borrow_mut().replace_active(path, state);
+        self.update_tab_bar();
+    }
 
-        // Find the target word - scope the borrows
-        let new_cursor = {
-            let doc_borrow = imp.pdf_view.document();
-            let doc = match doc_borrow.as_ref() {
-                Some(d) => d,
-                None => return false,
-            };
+    /// Open `path` in a new tab after the current one, or go to the tab already
+    /// showing it
+    pub fn open_file_in_new_tab(&self, path: &Path) {
+        let imp = self.imp();
+        let existing = imp.tabs.borrow().position(path);
+        if let Some(index) = existing {
+            self.switch_to_tab(index);
+            return;
+        }
+        if imp.tabs.borrow().is_empty() {
+            self.open_file(path);
+            return;
+        }
 
-            let mut cache = imp.text_cache.borrow_mut();
-            let cache = match cache.as_mut() {
-                Some(c) => c,
-                None => return false,
-            };
+        let previous = imp.tabs.borrow().active_index();
+        self.leave_document();
+        self.stash_tab_state();
+        let state = self.new_tab_state();
+        let index = imp.tabs.borrow_mut().open(path, state);
+        self.restore_tab_state();
+        if self.load_document(path) {
+            self.update_tab_bar();
+            return;
+        }
 
-            // Find word on same line starting with target_char
-            find_word_on_line_starting_with(
-                cache,
-                doc,
-                cursor.page_index,
-                cursor.word_index,
-                target_char,
-                forward,
-            )
-            .map(|result| WordCursor::new(result.page_index, result.word_index))
-        };
+        // Back to the tab that was shown
+        let closed = imp.tabs.borrow_mut().close(index);
+        if let Some(tab) = closed {
+            imp.tab_stack.remove(&tab.state.scrolled_window);
+        }
+        imp.tabs.borrow_mut().set_active(previous);
+        self.restore_tab_state();
+        self.show_active_document();
+    }
 
-        // Update cursor if found
-        if let Some(new_cursor) = new_cursor {
-            self.update_cursor(new_cursor);
-            true
-        } else {
-            // No match found, do nothing
-            false
+    /// Add a tab for `path` without showing it; its document is loaded when
+    /// switched to
+    pub fn add_tab(&self, path: &Path) {
+        let imp = self.imp();
+        if imp.tabs.borrow().is_empty() {
+            self.open_file(path);
+            return;
         }
+        if imp.tabs.borrow().position(path).is_some() {
+            return;
+        }
+        let state = self.new_tab_state();
+        imp.tabs.borrow_mut().push(path, state);
+        self.update_tab_bar();
     }
 
-    // returns true if it finds one
-    fn search_annotation_forward(&self) -> bool {
-        // Only works in Visual mode
+    /// A view of its own for a new tab, set up like the active one
+    fn new_tab_state(&self) -> TabState {
+        let view = PdfView::new();
+        view.copy_settings(&self.imp().pdf_view());
+        let scrolled_window = self.add_tab_page(&view);
+        TabState::new(view, scrolled_window)
+    }
+
+    fn setup_tab_bar(&self) {
         let imp = self.imp();
-        let cursor = match imp.app_mode.borrow().cursor() {
-            Some(c) => c,
-            None => return false,
-        };
 
-        let pdf_ref = imp.current_pdf_path.borrow();
-        let pdf_path = pdf_ref
-            .as_ref()
-            .expect("Pdf Path, you can't search annotations if you don't have an open pdf");
+        let window_weak = self.downgrade();
+        imp.tab_bar.connect_closure(
+            "tab-selected",
+            false,
+            glib::closure_local!(move |_bar: &TabBar, index: u32| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.switch_to_tab(index as usize);
+                }
+            }),
+        );
 
-        if let Ok(Some(annotation)) =
-            find_next_annotation_at_position(&pdf_path, cursor.page_index, cursor.word_index)
-        {
-            let new_cursor = WordCursor::new(annotation.start_page, annotation.start_word);
-            self.update_cursor(new_cursor);
-            true
-        } else {
-            false
-        }
+        let window_weak = self.downgrade();
+        imp.tab_bar.connect_closure(
+            "tab-close-requested",
+            false,
+            glib::closure_local!(move |_bar: &TabBar, index: u32| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.close_tab(index as usize);
+                }
+            }),
+        );
     }
 
-    // returns true if it finds one
-    fn search_annotation_backward(&self) -> bool {
-        // Only works in Visual mode
+    fn update_tab_bar(&self) {
         let imp = self.imp();
-        let cursor = match imp.app_mode.borrow().cursor() {
-            Some(c) => c,
-            None => return false,
+        let tabs = imp.tabs.borrow();
+        let titles: Vec<String> = tabs.tabs().iter().map(DocumentTab::title).collect();
+        imp.tab_bar.set_tabs(&titles, tabs.active_index());
+    }
+
+    /// Keep the state of the active tab's document with it while another tab is
+    /// shown
+    fn stash_tab_state(&self) {
+        let imp = self.imp();
+        let mut tabs = imp.tabs.borrow_mut();
+        let Some(tab) = tabs.active_mut() else {
+            return;
         };
+        let state = &mut tab.state;
+        state.app_mode = imp.app_mode.replace(AppMode::exit_to_normal());
+        state.text_cache = imp.text_cache.take();
+        state.search_matches = imp.search_matches.take();
+        state.search_index = imp.search_index.take();
+        state.jump_list = imp.jump_list.take();
+        state.visit_history = imp.visit_history.take();
+        state.synctex = imp.synctex.take();
+        state.page_labels = imp.page_labels.take();
+        state.document_language = imp.document_language.take();
+    }
 
-        let pdf_ref = imp.current_pdf_path.borrow();
-        let pdf_path = pdf_ref
-            .as_ref()
-            .expect("Pdf Path, you can't search annotations if you don't have an open pdf");
+    /// Show the view of the active tab and bring back the state of its document
+    fn restore_tab_state(&self) {
+        let imp = self.imp();
+        let (view, scrolled_window) = {
+            let mut tabs = imp.tabs.borrow_mut();
+            let Some(tab) = tabs.active_mut() else {
+                return;
+            };
+            let state = &mut tab.state;
+            imp.app_mode.replace(std::mem::replace(
+                &mut state.app_mode,
+                AppMode::exit_to_normal(),
+            ));
+            imp.text_cache.replace(state.text_cache.take());
+            imp.search_matches
+                .replace(std::mem::take(&mut state.search_matches));
+            imp.search_index.set(state.search_index.take());
+            imp.jump_list.replace(std::mem::take(&mut state.jump_list));
+            imp.visit_history
+                .replace(std::mem::take(&mut state.visit_history));
+            imp.synctex.replace(state.synctex.take());
+            imp.page_labels.replace(state.page_labels.take());
+            imp.document_language.set(state.document_language.take());
+            (state.view.clone(), state.scrolled_window.clone())
+        };
 
-        if let Ok(Some(annotation)) =
-            find_prev_annotation_at_position(&pdf_path, cursor.page_index, cursor.word_index)
-        {
-            let new_cursor = WordCursor::new(annotation.start_page, annotation.start_word);
-            self.update_cursor(new_cursor);
-            true
-        } else {
-            false
-        }
+        imp.pdf_view.replace(view);
+        imp.tab_stack.set_visible_child(&scrolled_window);
+        imp.scrolled_window.replace(Some(scrolled_window));
+        self.bind_split_view();
     }
 
-    // TODO
-    // fn update_cursor_from_annotation()
+    /// Show the tab at `index`, its document where it was left. A tab added
+    /// without being shown loads its document the first time.
+    fn switch_to_tab(&self, index: usize) {
+        let imp = self.imp();
+        let previous = imp.tabs.borrow().active_index();
+        let Some(path) = imp.tabs.borrow().get(index).map(|tab| tab.path.clone()) else {
+            return;
+        };
+        if index == previous {
+            return;
+        }
 
-    fn update_cursor(&self, new_cursor: WordCursor) {
-        {
-            let mut mode = self.imp().app_mode.borrow_mut();
-            mode.set_cursor(new_cursor);
+        self.leave_document();
+        self.stash_tab_state();
+        imp.tabs.borrow_mut().set_active(index);
+        self.restore_tab_state();
+        if imp.pdf_view().has_document() {
+            self.show_active_document();
+        } else if !self.load_document(&path) {
+            imp.tabs.borrow_mut().set_active(previous);
+            self.restore_tab_state();
+            self.show_active_document();
+            self.show_toast(&format!("Could not open {}", path.display()));
+            return;
         }
-        self.imp().pdf_view.set_cursor(Some(new_cursor));
-        self.update_selection_display();
-        self.ensure_cursor_visible(new_cursor);
-        self.print_cursor_word(new_cursor);
+        self.update_tab_bar();
     }
 
-    /// Copy text range to clipboard and show feedback popup
-    fn copy_range_to_clipboard(&self, start: WordCursor, end: WordCursor) {
+    /// Close the tab at `index`, or the window with the last tab, like `:q`
+    fn close_tab(&self, index: usize) {
         let imp = self.imp();
+        if imp.tabs.borrow().len() <= 1 {
+            self.close();
+            return;
+        }
 
-        // Extract text with scoped borrow
-        let text = {
-            let cache = imp.text_cache.borrow();
-            match cache.as_ref() {
-                Some(c) => self.extract_text_range(c, start, end),
-                None => return,
+        let active = imp.tabs.borrow().active_index();
+        if index == active {
+            self.leave_document();
+        }
+        let closed = imp.tabs.borrow_mut().close(index);
+        if let Some(tab) = closed {
+            imp.tab_stack.remove(&tab.state.scrolled_window);
+        }
+        if index == active {
+            self.restore_tab_state();
+            let path = imp.tabs.borrow().active().map(|tab| tab.path.clone());
+            if imp.pdf_view().has_document() {
+                self.show_active_document();
+            } else if let Some(path) = path {
+                if !self.load_document(&path) {
+                    self.show_toast(&format!("Could not open {}", path.display()));
+                }
             }
+        }
+        self.update_tab_bar();
+    }
+
+    /// Bring the window up to date with the document of the tab just shown,
+    /// which its view kept loaded
+    fn show_active_document(&self) {
+        let imp = self.imp();
+        let Some(path) = imp.tabs.borrow().active().map(|tab| tab.path.clone()) else {
+            return;
         };
+        imp.current_pdf_path
+            .replace(Some(path.to_string_lossy().to_string()));
+        self.apply_dictionary_language();
 
-        if !text.is_empty() {
-            let clipboard = self.clipboard();
-            clipboard.set_text(&text);
-            self.show_copy_feedback(&text);
+        imp.ocr_queue.borrow_mut().clear();
+        imp.prefetch_line.set(None);
+        imp.primary_selection.set(None);
+        imp.ink_stroke.replace(None);
+        imp.annotation_rects.borrow_mut().clear();
+        self.reload_annotations();
+        self.reload_ink_drawings();
+
+        if self.reflow_active() && !self.build_reflow_text() {
+            imp.reflow_view.clear();
+            imp.view_stack.set_visible_child_name("pages");
         }
+
+        self.extract_and_populate_toc_entries();
+        self.update_mode_display();
+        self.update_highlights();
+        self.refresh_text_overlays();
+        // Shows the page of the view in the status bar and the window title
+        imp.pdf_view().schedule_page_update();
+        self.refresh_split_view();
     }
 
-    /// Extract text from a cursor range (reusable helper)
-    fn extract_text_range(
-        &self,
-        cache: &TextMapCache,
-        start: WordCursor,
-        end: WordCursor,
-    ) -> String {
-        let mut text_parts: Vec<String> = Vec::new();
-        let mut is_first_word = true;
-
-        if start.page_index == end.page_index {
-            // Same page
-            if let Some(text_map) = cache.get(start.page_index) {
-                let word_start = start.word_index.min(end.word_index);
-                let word_end = start.word_index.max(end.word_index);
-
-                for idx in word_start..=word_end {
-                    if let Some(word) = text_map.get_word(idx) {
-                        if let Some(surr_left) = &word.surround_left {
-                            if idx != word_start {
-                                text_parts.push(surr_left.clone());
-                            }
-                        }
-                        text_parts.push(word.text.clone());
-                    }
-                }
-            }
-        } else {
-            // Cross-page selection
-            let (first, last) = if start.page_index < end.page_index {
-                (start, end)
-            } else {
-                (end, start)
-            };
+    /// Remember where the open document was left, before another one is shown
+    fn leave_document(&self) {
+        self.save_session();
+        self.finish_reading_range();
+        self.imp().reading_speed.borrow_mut().clear();
+    }
 
-            // First page
-            if let Some(text_map) = cache.get(first.page_index) {
-                for idx in first.word_index..text_map.word_count() {
-                    if let Some(word) = text_map.get_word(idx) {
-                        if !is_first_word {
-                            if let Some(surr_left) = &word.surround_left {
-                                text_parts.push(surr_left.clone());
-                            }
-                        }
-                        text_parts.push(word.text.clone());
-                        is_first_word = false;
-                    }
-                }
-            }
+    /// Show the document at `path` in the view of the active tab, in place of
+    /// the one it showed, which is left first. Returns false when it could not
+    /// be loaded.
+    fn load_document(&self, path: &Path) -> bool {
+        let session = session::load_session(&path.to_string_lossy()).unwrap_or_else(|e| {
+            eprintln!("Failed to load session: {}", e);
+            None
+        });
+        if let Some(session) = &session {
+            self.imp().pdf_view().set_zoom_mode(session.zoom_mode);
+        }
 
-            // Middle pages
-            for page_idx in (first.page_index + 1)..last.page_index {
-                if let Some(text_map) = cache.get(page_idx) {
-                    for idx in 0..text_map.word_count() {
-                        if let Some(word) = text_map.get_word(idx) {
-                            if let Some(surr_left) = &word.surround_left {
-                                text_parts.push(surr_left.clone());
-                            }
-                            text_parts.push(word.text.clone());
-                        }
-                    }
-                }
-            }
+        if let Err(e) = self.imp().pdf_view().load_document(path.to_path_buf()) {
+            eprintln!("{}", e);
+            return false;
+        }
 
-            // Last page
-            if let Some(text_map) = cache.get(last.page_index) {
-                for idx in 0..=last.word_index {
-                    if let Some(word) = text_map.get_word(idx) {
-                        if let Some(surr_left) = &word.surround_left {
-                            text_parts.push(surr_left.clone());
-                        }
-                        text_parts.push(word.text.clone());
-                    }
-                }
-            }
+        // Store the PDF path for annotations
+        self.imp()
+            .current_pdf_path
+            .replace(Some(path.to_string_lossy().to_string()));
+
+        self.imp().header_bar.reload_button().set_sensitive(true);
+        if let Err(e) = recent_files::record_opened(&path.to_string_lossy()) {
+            eprintln!("Failed to record recent file: {}", e);
         }
+        self.log_reading_event(ReadingEvent::Opened);
+        self.load_synctex(path);
 
-        text_parts.join("")
-    }
+        let document_language = session::load_document_language(&path.to_string_lossy())
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load document language: {}", e);
+                None
+            });
+        self.imp().document_language.set(document_language);
+        self.apply_dictionary_language();
 
-    /// Show a brief toast notification when text is copied
-    fn show_copy_feedback(&self, text: &str) {
-        let imp = self.imp();
+        self.init_text_cache();
+        self.imp().ocr_queue.borrow_mut().clear();
+        self.imp().prefetch_line.set(None);
+        self.load_recognized_text();
+        // Load annotations for this PDF
+        self.reload_annotations();
+        self.imp().ink_stroke.replace(None);
+        self.reload_ink_drawings();
 
-        // Format the message with a preview of copied text
-        let preview = if text.len() > 40 {
-            format!("Copied: \"{}...\"", &text[..37])
-        } else {
-            format!("Copied: \"{}\"", text)
-        };
+        if self.reflow_active() && !self.build_reflow_text() {
+            self.imp().reflow_view.clear();
+            self.imp().view_stack.set_visible_child_name("pages");
+        }
+
+        self.extract_and_populate_toc_entries();
 
-        imp.toast_label.set_text(&preview);
+        self.imp().page_labels.replace(None);
+        self.imp().search_matches.borrow_mut().clear();
+        self.imp().search_index.set(None);
+        self.imp().jump_list.borrow_mut().clear();
+        self.imp().visit_history.borrow_mut().clear();
+        self.imp().primary_selection.set(None);
 
-        // Show the toast
-        imp.toast_revealer.set_reveal_child(true);
+        // Reset to Normal mode when loading new PDF
+        {
+            let mut mode = self.imp().app_mode.borrow_mut();
+            *mode = AppMode::exit_to_normal();
+        }
+        self.update_mode_display();
+        self.pdf_view().set_cursor(None);
+        self.pdf_view().clear_selection();
+        self.pdf_view().clear_all_highlights();
 
-        // Auto-hide after 1.5 seconds
-        let revealer = imp.toast_revealer.clone();
-        glib::timeout_add_local_once(std::time::Duration::from_millis(1500), move || {
-            revealer.set_reveal_child(false);
+        // Update annotation highlights after a brief delay to ensure pages are rendered
+        let window_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(window) = window_weak.upgrade() {
+                window.update_annotation_highlights();
+                window.update_ink_highlights();
+            }
         });
+
+        if let Some(session) = session {
+            self.restore_session(session);
+        }
+        self.refresh_split_view();
+        true
     }
 
-    fn toggle_toc_panel(&self) {
+    /// Show the open document at a 1-based `page` and `zoom` given on the command
+    /// line, instead of where it was left
+    pub fn go_to_start_location(&self, page: Option<i32>, zoom: Option<f64>) {
         let imp = self.imp();
-        let is_visible = imp.toc_panel.is_visible();
-        let toc_panel = self.toc_panel();
+        if imp.pdf_view().document().is_none() {
+            return;
+        }
 
-        if is_visible {
-            match toc_panel.toc_mode() {
-                TocMode::Chapters => {
-                    toc_panel.set_toc_mode(TocMode::Annotations);
-                }
-                TocMode::Annotations => {
-                    toc_panel.set_toc_mode(TocMode::Chapters);
-                    toc_panel.set_visible(false);
-                }
-            }
+        if let Some(zoom) = zoom {
+            imp.pdf_view().set_zoom_mode(ZoomMode::Percentage(zoom));
         }
 
-        if !is_visible {
-            imp.toc_panel.set_visible(true);
-            imp.toc_panel.grab_focus();
-            let current_page = imp.pdf_view.current_page();
-            imp.toc_panel.select_current_chapter(current_page);
+        let Some(page) = page else {
+            return;
+        };
+        let page_count = imp.pdf_view().page_count();
+        if page < 1 || page as usize > page_count {
+            self.show_toast(&format!("Page {} is out of range (1-{})", page, page_count));
+            return;
         }
 
+        // Runs after the restored session has scrolled the new pages
         let window_weak = self.downgrade();
-        glib::timeout_add_local_once(std::time::Duration::from_millis(100), move || {
+        glib::idle_add_local_once(move || {
             if let Some(window) = window_weak.upgrade() {
-                window.update_highlights();
+                window.scroll_to_page((page - 1) as u16);
             }
         });
     }
 
-    fn toggle_header_bar(&self) {
+    /// Dictionary language of the open document, the global one unless overridden
+    fn dictionary_language(&self) -> Language {
         let imp = self.imp();
-        let header = imp.header_bar.widget();
-        let is_visible = header.is_visible();
-        header.set_visible(!is_visible);
+        imp.document_language
+            .get()
+            .unwrap_or_else(|| imp.dictionary_language.get())
     }
 
-    fn toggle_status_bar(&self) {
+    fn apply_dictionary_language(&self) {
         let imp = self.imp();
-        let status_bar = imp.status_bar.widget();
-        let is_visible = status_bar.is_visible();
-        status_bar.set_visible(!is_visible);
+        imp.pdf_view()
+            .set_dictionary_language(self.dictionary_language());
+        imp.split_view
+            .set_dictionary_language(self.dictionary_language());
     }
 
-    fn setup_open_button(&self) {
-        let window_weak = self.downgrade();
+    /// Override the dictionary language of the open document, None to use the global one
+    fn set_document_language(&self, language: Option<Language>) {
+        let imp = self.imp();
+        if let Some(pdf_path) = imp.current_pdf_path.borrow().as_deref() {
+            if let Err(e) = session::save_document_language(pdf_path, language) {
+                eprintln!("Failed to save document language: {}", e);
+            }
+        }
+        imp.document_language.set(language);
+        self.apply_dictionary_language();
+    }
 
-        self.imp()
-            .header_bar
-            .open_button()
-            .connect_clicked(move |_| {
-                if let Some(window) = window_weak.upgrade() {
-                    window.show_open_dialog();
-                }
-            });
+    // ============ Session ============
+
+    /// Save the view state of the open document when the window closes
+    fn setup_session(&self) {
+        self.connect_close_request(|window| {
+            window.save_session();
+            window.finish_reading_range();
+            launcher::set_progress(None);
+            glib::Propagation::Proceed
+        });
     }
 
-    fn setup_settings_button(&self) {
-        let window_weak = self.downgrade();
+    fn save_session(&self) {
+        let imp = self.imp();
+        let Some(pdf_path) = imp.current_pdf_path.borrow().clone() else {
+            return;
+        };
+        let Some((scroll_x, scroll_y)) = imp
+            .scrolled_window
+            .borrow()
+            .as_ref()
+            .map(|s| (s.hadjustment().value(), s.vadjustment().value()))
+        else {
+            return;
+        };
 
-        self.imp()
-            .header_bar
-            .settings_button()
-            .connect_clicked(move |_| {
-                if let Some(window) = window_weak.upgrade() {
-                    window.show_settings_window();
-                }
-            });
+        let session = SessionState {
+            pdf_path,
+            scroll_x,
+            scroll_y,
+            zoom: imp.pdf_view().zoom_level(),
+            zoom_mode: imp.pdf_view().zoom_mode(),
+            paged_page: imp.pdf_view().paged().then(|| imp.pdf_view().paged_page()),
+            cursor: imp.app_mode.borrow().cursor(),
+        };
+        if let Err(e) = session::save_session(&session) {
+            eprintln!("Failed to save session: {}", e);
+        }
     }
 
-    fn show_settings_window(&self) {
-        let settings = SettingsWindow::new(self);
-        settings.set_language(self.imp().dictionary_language.get());
+    /// Put the freshly opened document back where it was left: the zoom is applied
+    /// before loading, the paged mode, cursor and scroll offsets here
+    fn restore_session(&self, session: SessionState) {
+        let imp = self.imp();
 
-        let window_weak = self.downgrade();
-        settings
-            .language_dropdown()
-            .connect_selected_notify(move |dropdown| {
-                if let Some(window) = window_weak.upgrade() {
-                    let lang = match dropdown.selected() {
-                        1 => Language::Spanish,
-                        _ => Language::English,
-                    };
-                    window.imp().dictionary_language.set(lang);
-                    window.imp().pdf_view.set_dictionary_language(lang);
-                }
-            });
+        let paged_page = session
+            .paged_page
+            .filter(|&page| page < imp.pdf_view().page_count());
+        imp.pdf_view().set_paged_page(paged_page);
 
-        settings.present();
-    }
+        if let Some(cursor) = session.cursor.filter(|cursor| self.word_exists(*cursor)) {
+            imp.app_mode.replace(AppMode::enter_visual(cursor));
+            imp.pdf_view().set_cursor(Some(cursor));
+            self.update_mode_display();
+        }
 
-    fn show_open_dialog(&self) {
-        let dialog = gtk::FileDialog::builder().title("Select a PDF").build();
+        // Wait for the new pages to be laid out before scrolling
         let window_weak = self.downgrade();
-
-        dialog.open(Some(self), None::<&gio::Cancellable>, move |result| {
-            if let Some(window) = window_weak.upgrade() {
-                window.handle_file_dialog_result(result);
+        glib::idle_add_local_once(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            // Fit modes may have fitted the pages to a view of another size
+            let scale = window.imp().pdf_view().zoom_level() / session.zoom;
+            if let Some(scrolled) = window.imp().scrolled_window.borrow().as_ref() {
+                scrolled.hadjustment().set_value(session.scroll_x * scale);
+                scrolled.vadjustment().set_value(session.scroll_y * scale);
             }
+            window.update_highlights();
         });
     }
 
-    fn handle_file_dialog_result(&self, result: Result<gio::File, glib::Error>) {
-        let file = match result {
-            Ok(f) => f,
-            Err(_) => return,
-        };
+    // ============ Startup Defaults ============
 
-        let path = match file.path() {
-            Some(p) => p,
-            None => return,
-        };
+    /// Switch on the lookup mode, bars and dictionary source saved for startup
+    fn apply_startup_defaults(&self) {
+        let imp = self.imp();
+        let defaults = startup::load_startup_defaults();
 
-        self.open_file(&path);
+        // The header bar keeps definitions and translation mutually exclusive
+        match defaults.mode {
+            StartupMode::Normal => {}
+            StartupMode::Definitions => imp.header_bar.set_definitions_enabled(true),
+            StartupMode::Translate => imp.header_bar.set_translate_enabled(true),
+        }
+        imp.header_bar
+            .widget()
+            .set_visible(defaults.show_header_bar);
+        imp.status_bar
+            .widget()
+            .set_visible(defaults.show_status_bar);
+        imp.pdf_view()
+            .set_dictionary_source(defaults.dictionary_source.clone());
+
+        imp.startup_defaults.replace(defaults);
     }
 
-    /// Show export annotations confirmation dialog
-    fn show_export_annotations_dialog(&self) {
-        let pdf_path = match self.imp().current_pdf_path.borrow().as_ref() {
-            Some(p) => p.clone(),
-            None => {
-                eprintln!("No PDF loaded, cannot export annotations");
-                return;
-            }
-        };
+    /// Change the startup defaults and save them for the next start
+    fn update_startup_defaults(&self, update: impl FnOnce(&mut StartupDefaults)) {
+        let mut defaults = self.imp().startup_defaults.borrow_mut();
+        let before = defaults.clone();
+        update(&mut defaults);
+        if *defaults == before {
+            return;
+        }
 
-        // Check if there are any annotations to export
-        let annotations = match annotations::load_annotations_for_pdf(&pdf_path) {
-            Ok(anns) => anns,
-            Err(e) => {
-                eprintln!("Failed to load annotations: {}", e);
-                return;
-            }
-        };
+        if let Err(e) = startup::save_startup_defaults(&defaults) {
+            eprintln!("Failed to save startup defaults: {}", e);
+        }
+    }
 
-        if annotations.is_empty() {
-            // Show a dialog saying there are no annotations
-            let dialog = gtk::AlertDialog::builder()
-                .message("No Annotations")
-                .detail("There are no annotations to export for this PDF.")
-                .buttons(["OK"])
-                .build();
-            dialog.show(Some(self));
+    // ============ Settings ============
+
+    /// Apply the margins cropping, annotation panel position and theme of the
+    /// saved settings
+    fn apply_settings(&self) {
+        let imp = self.imp();
+        let saved = settings::load_settings();
+
+        imp.pdf_view().set_auto_crop(saved.auto_crop);
+        self.set_annotation_panel_position(saved.annotation_panel);
+        self.set_theme(saved.theme);
+
+        imp.settings.replace(saved);
+    }
+
+    /// Change the settings and save them for the next start
+    fn update_settings(&self, update: impl FnOnce(&mut Settings)) {
+        let mut saved = self.imp().settings.borrow_mut();
+        let before = saved.clone();
+        update(&mut saved);
+        if *saved == before {
             return;
         }
 
-        // Show confirmation dialog
-        let dialog = gtk::AlertDialog::builder()
-            .message("Export Annotations")
-            .detail(&format!(
-                "Export {} annotation(s) to a Markdown file?",
-                annotations.len()
-            ))
-            .buttons(["Cancel", "Export"])
-            .default_button(1)
-            .cancel_button(0)
-            .build();
+        if let Err(e) = settings::save_settings(&saved) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+    }
 
-        let window_weak = self.downgrade();
-        dialog.choose(Some(self), None::<&gio::Cancellable>, move |result| {
-            if let Some(window) = window_weak.upgrade() {
-                if let Ok(choice) = result {
-                    if choice == 1 {
-                        // User chose "Export"
-                        window.show_export_file_chooser();
-                    }
-                }
+    fn setup_text_overlays(&self, view: &PdfView) {
+        let weak_self = self.downgrade();
+        view.connect_local("page-rendered", false, move |values| {
+            let window = weak_self.upgrade()?;
+            let view = values.first()?.get::<PdfView>().ok()?;
+            if !window.is_active_view(&view) {
+                return None;
             }
+            let page_index = values.get(1)?.get::<u32>().ok()? as usize;
+            window.update_bionic_page(page_index);
+            window.update_word_boxes_page(page_index);
+            None
         });
     }
 
-    /// Show file chooser for saving exported annotations
-    fn show_export_file_chooser(&self) {
-        let pdf_path = match self.imp().current_pdf_path.borrow().as_ref() {
-            Some(p) => p.clone(),
-            None => return,
-        };
-
-        // Generate default filename from PDF name
-        let pdf_name = Path::new(&pdf_path)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("annotations");
-        let default_filename = format!("{}_annotations.md", pdf_name);
-
-        let dialog = gtk::FileDialog::builder()
-            .title("Save Annotations")
-            .initial_name(&default_filename)
-            .build();
-
+    /// Move the highlights and drawings with the page content once the margins
+    /// cut off the pages changed
+    fn setup_crop_tracking(&self, view: &PdfView) {
         let window_weak = self.downgrade();
-        dialog.save(Some(self), None::<&gio::Cancellable>, move |result| {
-            if let Some(window) = window_weak.upgrade() {
-                window.handle_export_save_result(result);
+        view.connect_local("crop-changed", false, move |values| {
+            let window = window_weak.upgrade()?;
+            let view = values.first()?.get::<PdfView>().ok()?;
+            if !window.is_active_view(&view) {
+                return None;
             }
+            window.imp().annotation_rects.borrow_mut().clear();
+            // Once the pages are laid out at their new size
+            let window_weak = window.downgrade();
+            glib::idle_add_local_once(move || {
+                if let Some(window) = window_weak.upgrade() {
+                    window.update_highlights();
+                    window.refresh_text_overlays();
+                }
+            });
+            None
         });
     }
 
-    /// Handle the result of the export file save dialog
-    fn handle_export_save_result(&self, result: Result<gio::File, glib::Error>) {
-        let file = match result {
-            Ok(f) => f,
-            Err(_) => return, // User cancelled
-        };
+    /// Recompute the bionic reading and word boxes overlays of every rendered page
+    fn refresh_text_overlays(&self) {
+        for page_index in self.imp().pdf_view().rendered_pages() {
+            self.update_bionic_page(page_index);
+            self.update_word_boxes_page(page_index);
+        }
+    }
 
-        let save_path = match file.path() {
-            Some(p) => p,
-            None => return,
-        };
+    /// Recompute the word boxes debug overlay of a single page
+    fn update_word_boxes_page(&self, page_index: usize) {
+        let imp = self.imp();
 
-        let pdf_path = match self.imp().current_pdf_path.borrow().as_ref() {
-            Some(p) => p.clone(),
-            None => return,
+        let Some(overlay) = imp.pdf_view().highlight_overlay(page_index) else {
+            return;
         };
 
-        // Get PDF name for the markdown header
-        let pdf_name = Path::new(&pdf_path)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("Unknown PDF");
+        if !imp.show_word_boxes.get() {
+            overlay.set_word_boxes(WordBoxes::default());
+            return;
+        }
 
-        // Generate markdown content
-        let markdown = match annotations::export_to_markdown(&pdf_path, pdf_name) {
-            Ok(content) => content,
-            Err(e) => {
-                eprintln!("Failed to generate markdown: {}", e);
-                self.show_export_error(&format!("Failed to generate markdown: {}", e));
+        let boxes = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let Some(doc) = doc_borrow.as_ref() else {
                 return;
-            }
-        };
+            };
 
-        // Write to file
-        if let Err(e) = fs::write(&save_path, &markdown) {
-            eprintln!("Failed to write file: {}", e);
-            self.show_export_error(&format!("Failed to write file: {}", e));
-            return;
-        }
+            let mut cache = imp.text_cache.borrow_mut();
+            let Some(text_map) = cache
+                .as_mut()
+                .and_then(|c| c.get_or_build(page_index, doc.as_ref()))
+            else {
+                return;
+            };
 
-        // Show success message
-        let dialog = gtk::AlertDialog::builder()
-            .message("Export Successful")
-            .detail(&format!("Annotations saved to:\n{}", save_path.display()))
-            .buttons(["OK"])
-            .build();
-        dialog.show(Some(self));
-    }
+            let x_offset = imp
+                .pdf_view()
+                .page_picture(page_index as u16)
+                .map(|pic| calculate_picture_offset(&pic))
+                .unwrap_or(0.0);
+            let render_width = pdf_view.page_render_width(page_index);
+            let rotation = pdf_view.page_rotation(page_index);
+            let crop = pdf_view.page_crop(page_index);
+
+            let words: Vec<(HighlightRect, usize)> = text_map
+                .words
+                .iter()
+                .map(|word| {
+                    let rect = HighlightRect::from_pdf_bounds(
+                        &word.bounds,
+                        text_map.page_width,
+                        text_map.page_height,
+                        x_offset,
+                        render_width,
+                        rotation,
+                        crop,
+                    );
+                    (rect, word.line_index)
+                })
+                .collect();
+            let lines = text_map
+                .lines
+                .iter()
+                .filter_map(|line| {
+                    let line_words = words.get(line.word_start..line.word_end)?;
+                    let (first, rest) = line_words.split_first()?;
+                    Some(
+                        rest.iter()
+                            .fold(first.0, |rect, (word, _)| rect.union(word)),
+                    )
+                })
+                .collect();
 
-    /// Show an error dialog for export failures
-    fn show_export_error(&self, message: &str) {
-        let dialog = gtk::AlertDialog::builder()
-            .message("Export Failed")
-            .detail(message)
-            .buttons(["OK"])
-            .build();
-        dialog.show(Some(self));
+            WordBoxes { words, lines }
+        };
+
+        overlay.set_word_boxes(boxes);
     }
 
-    /// Open a PDF file from a path (public API for CLI usage)
-    pub fn open_file(&self, path: &Path) {
-        if let Err(e) = self.imp().pdf_view.load_pdf(path.to_path_buf()) {
-            eprintln!("{}", e);
+    /// Recompute the bionic reading overlay of a single page
+    fn update_bionic_page(&self, page_index: usize) {
+        let imp = self.imp();
+
+        let overlay = match imp.pdf_view().highlight_overlay(page_index) {
+            Some(o) => o,
+            None => return,
+        };
+
+        // Upright bold prefixes can't be drawn over the words of a turned page
+        if !imp.bionic_reading.get() || imp.pdf_view().page_rotation(page_index) != Rotation::NONE {
+            overlay.set_bionic_words(Vec::new());
             return;
         }
 
-        // Store the PDF path for annotations
-        self.imp()
-            .current_pdf_path
-            .replace(Some(path.to_string_lossy().to_string()));
-
-        self.init_text_cache();
-        // Load annotations for this PDF
-        self.reload_annotations();
+        let words = {
+            let pdf_view = imp.pdf_view();
+            let doc_borrow = pdf_view.document();
+            let doc = match doc_borrow.as_ref() {
+                Some(d) => d,
+                None => return,
+            };
 
-        self.extract_and_populate_toc_entries();
+            let mut cache = imp.text_cache.borrow_mut();
+            let text_map = match cache
+                .as_mut()
+                .and_then(|c| c.get_or_build(page_index, doc.as_ref()))
+            {
+                Some(t) => t,
+                None => return,
+            };
 
-        // Reset to Normal mode when loading new PDF
-        {
-            let mut mode = self.imp().app_mode.borrow_mut();
-            *mode = AppMode::exit_to_normal();
-        }
-        self.update_mode_display();
-        self.pdf_view().set_cursor(None);
-        self.pdf_view().clear_selection();
-        self.pdf_view().clear_all_highlights();
+            let x_offset = imp
+                .pdf_view()
+                .page_picture(page_index as u16)
+                .map(|pic| calculate_picture_offset(&pic))
+                .unwrap_or(0.0);
+            let render_width = pdf_view.page_render_width(page_index);
+            let crop = pdf_view.page_crop(page_index);
+
+            text_map
+                .words
+                .iter()
+                .map(|word| BionicWord {
+                    rect: HighlightRect::from_pdf_bounds(
+                        &word.bounds,
+                        text_map.page_width,
+                        text_map.page_height,
+                        x_offset,
+                        render_width,
+                        Rotation::NONE,
+                        crop,
+                    ),
+                    text: word.raw.clone(),
+                })
+                .collect()
+        };
 
-        // Update annotation highlights after a brief delay to ensure pages are rendered
-        let window_weak = self.downgrade();
-        glib::idle_add_local_once(move || {
-            if let Some(window) = window_weak.upgrade() {
-                window.update_annotation_highlights();
-            }
-        });
+        overlay.set_bionic_words(words);
     }
 
-    fn setup_page_indicator_label(&self) {
-        let status_bar = self.imp().status_bar.clone();
-        self.pdf_view().connect_closure(
+    fn setup_page_indicator_label(&self, view: &PdfView) {
+        let window_weak = self.downgrade();
+        view.connect_closure(
             "current-page-updated",
             false,
-            closure_local!(|_pdf_view: &PdfView, current_page: u32, total_pages: u32| {
-                let page_indicator_text = format!("[{current_page}/{total_pages}]");
-                status_bar.set_pages_indicator_text(&page_indicator_text);
-            }),
+            closure_local!(
+                move |pdf_view: &PdfView, current_page: u32, total_pages: u32| {
+                    let Some(window) = window_weak
+                        .upgrade()
+                        .filter(|window| window.is_active_view(pdf_view))
+                    else {
+                        return;
+                    };
+                    let page_indicator_text = format!("[{current_page}/{total_pages}]");
+                    window
+                        .imp()
+                        .status_bar
+                        .set_pages_indicator_text(&page_indicator_text);
+                    window.update_reading_progress();
+                }
+            ),
         );
     }
 
@@ -2122,26 +7803,28 @@ impl EyersWindow {
     fn init_text_cache(&self) {
         let imp = self.imp();
 
-        if let Some(ref doc) = *imp.pdf_view.document() {
-            let page_count = doc.pages().len() as usize;
+        if let Some(ref doc) = *imp.pdf_view().document() {
+            let page_count = doc.page_count();
             let cache = TextMapCache::new(page_count);
             imp.text_cache.replace(Some(cache));
         }
     }
 
     fn extract_and_populate_toc_entries(&self) {
-        let bookmarks = self.imp().pdf_view.bookmarks();
+        let bookmarks = self.imp().pdf_view().bookmarks();
         self.imp().toc_panel.populate_chapters(&bookmarks);
+        self.load_read_pages();
         let annotations = self.imp().annotations.borrow();
         self.imp().toc_panel.populate_annotations(&annotations);
+        self.load_marks();
     }
 
     pub fn header_bar(&self) -> &EyersHeaderBar {
         &self.imp().header_bar
     }
 
-    pub fn pdf_view(&self) -> &PdfView {
-        &self.imp().pdf_view
+    pub fn pdf_view(&self) -> PdfView {
+        self.imp().pdf_view()
     }
 
     pub fn toc_panel(&self) -> &TocPanel {
@@ -2161,6 +7844,13 @@ impl EyersWindow {
     fn setup_annotation_panel(&self) {
         let imp = self.imp();
 
+        let window_weak = self.downgrade();
+        imp.annotation_panel.connect_visible_notify(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.sync_annotation_dialog();
+            }
+        });
+
         // Handle save
         let window_weak = self.downgrade();
         imp.annotation_panel.connect_closure(
@@ -2196,6 +7886,101 @@ impl EyersWindow {
                 }
             }),
         );
+
+        // Handle "see also" links
+        let window_weak = self.downgrade();
+        imp.annotation_panel.connect_closure(
+            "link-requested",
+            false,
+            glib::closure_local!(move |panel: &AnnotationPanel, target: i64| {
+                if let (Some(window), Some(id)) = (window_weak.upgrade(), panel.annotation_id()) {
+                    window.set_annotation_link(id, target, true);
+                }
+            }),
+        );
+
+        let window_weak = self.downgrade();
+        imp.annotation_panel.connect_closure(
+            "unlink-requested",
+            false,
+            glib::closure_local!(move |panel: &AnnotationPanel, target: i64| {
+                if let (Some(window), Some(id)) = (window_weak.upgrade(), panel.annotation_id()) {
+                    window.set_annotation_link(id, target, false);
+                }
+            }),
+        );
+
+        let window_weak = self.downgrade();
+        imp.annotation_panel.connect_closure(
+            "link-activated",
+            false,
+            glib::closure_local!(move |_panel: &AnnotationPanel, target: i64| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                let annotation = window
+                    .imp()
+                    .annotations
+                    .borrow()
+                    .iter()
+                    .find(|ann| ann.id == target)
+                    .cloned();
+                if let Some(annotation) = annotation {
+                    window.jump_to_annotation(&annotation);
+                }
+            }),
+        );
+    }
+
+    /// Link or unlink two annotations, updating the panel and the TOC chips
+    fn set_annotation_link(&self, id: AnnotationId, target: AnnotationId, linked: bool) {
+        self.write_annotations(
+            move || {
+                if linked {
+                    annotations::link_annotations(id, target)
+                } else {
+                    annotations::unlink_annotations(id, target)
+                }
+            },
+            |window, result| {
+                if let Err(e) = result {
+                    window.show_toast(&format!("Failed to update annotation link: {}", e));
+                    return;
+                }
+                window.reload_annotation_links();
+                window.update_annotation_panel_links();
+            },
+        );
+    }
+
+    /// Show the links of the annotation edited in the annotation panel, offering
+    /// the other annotations of the document to link
+    fn update_annotation_panel_links(&self) {
+        let imp = self.imp();
+        let Some(id) = imp.annotation_panel.annotation_id() else {
+            imp.annotation_panel.set_links(&[], &[]);
+            return;
+        };
+
+        let linked_ids = annotations::linked_ids(&imp.annotation_links.borrow(), id);
+        let (linked, candidates): (Vec<Annotation>, Vec<Annotation>) = imp
+            .annotations
+            .borrow()
+            .iter()
+            .filter(|ann| ann.id != id)
+            .cloned()
+            .partition(|ann| linked_ids.contains(&ann.id));
+        imp.annotation_panel.set_links(&linked, &candidates);
+    }
+
+    /// Go to an annotation, putting the cursor on it in Visual mode
+    fn jump_to_annotation(&self, annotation: &Annotation) {
+        self.record_jump();
+        self.imp()
+            .pdf_view()
+            .scroll_to_page(annotation.start_page as u16);
+        self.move_cursor(annotation.get_start_word_cursor());
+        self.flash_annotation(annotation);
     }
 
     fn setup_annotate_button(&self) {
@@ -2261,7 +8046,7 @@ impl EyersWindow {
         let selected_text = {
             let cache = imp.text_cache.borrow();
             match cache.as_ref() {
-                Some(c) => self.extract_text_range(c, start, end),
+                Some(c) => c.extract_text(start, end),
                 None => return,
             }
         };
@@ -2276,11 +8061,16 @@ impl EyersWindow {
             // Editing existing annotation
             imp.annotation_panel.set_annotation_id(Some(ann.id));
             imp.annotation_panel.set_note(&ann.note);
+            imp.annotation_panel.set_color(ann.color);
+            imp.annotation_panel.set_tags(&ann.tags);
         } else {
             // New annotation
             imp.annotation_panel.set_annotation_id(None);
             imp.annotation_panel.set_note("");
+            imp.annotation_panel.set_color(AnnotationColor::default());
+            imp.annotation_panel.set_tags(&[]);
         }
+        self.update_annotation_panel_links();
 
         // Show panel and focus input
         imp.annotation_panel.set_visible(true);
@@ -2304,67 +8094,126 @@ impl EyersWindow {
         let selected_text = {
             let cache = imp.text_cache.borrow();
             match cache.as_ref() {
-                Some(c) => self.extract_text_range(c, start, end),
+                Some(c) => c.extract_text(start, end),
                 None => return,
             }
         };
 
         let annotation_id = imp.annotation_panel.annotation_id();
+        let color = imp.annotation_panel.color();
+        let tags = imp.annotation_panel.tags();
 
-        // Save or update
-        let result = if let Some(id) = annotation_id {
-            // Update existing
-            annotations::update_annotation(
-                id,
-                start.page_index,
-                start.word_index,
-                end.page_index,
-                end.word_index,
-                &selected_text,
-                note,
-            )
-            .map(|_| id)
-        } else {
-            // Create new
-            annotations::save_annotation(
-                &pdf_path,
-                start.page_index,
-                start.word_index,
-                end.page_index,
-                end.word_index,
-                &selected_text,
-                note,
-            )
-        };
+        // Saving twice while the first write waits would store the annotation twice
+        if imp.saving_annotation.replace(true) {
+            return;
+        }
 
-        match result {
-            Ok(id) => {
-                println!("Annotation saved successfully");
-                self.close_annotation_panel();
-                self.reload_annotations();
-                self.update_annotation_highlights();
-                if let Ok(annotation) = annotations::get_annotation(id) {
-                    self.imp().toc_panel.update_list_annotations(annotation);
+        // Save or update
+        let is_new = annotation_id.is_none();
+        let note = note.to_string();
+        let text = selected_text.clone();
+        self.write_annotations(
+            move || match annotation_id {
+                // Update existing
+                Some(id) => annotations::update_annotation(
+                    id,
+                    start.page_index,
+                    start.word_index,
+                    end.page_index,
+                    end.word_index,
+                    &text,
+                    &note,
+                    color,
+                    &tags,
+                )
+                .map(|_| id),
+                // Create new
+                None => annotations::save_annotation(
+                    &pdf_path,
+                    start.page_index,
+                    start.word_index,
+                    end.page_index,
+                    end.word_index,
+                    &text,
+                    &note,
+                    color,
+                    &tags,
+                ),
+            },
+            move |window, result| {
+                window.imp().saving_annotation.set(false);
+                match result {
+                    Ok(id) => {
+                        println!("Annotation saved successfully");
+                        if is_new {
+                            window.log_reading_event(ReadingEvent::Annotated {
+                                page_index: start.page_index,
+                                text: selected_text,
+                            });
+                        }
+                        window.close_saved_annotation_panel(start, end);
+                        window.reload_annotations();
+                        window.update_annotation_highlights();
+                        window.sync_notes_file();
+                        if let Ok(annotation) = annotations::get_annotation(id) {
+                            window.imp().toc_panel.update_list_annotations(annotation);
+                        }
+                    }
+                    Err(e) => {
+                        window.show_toast(&format!("Failed to save annotation: {}", e));
+                    }
                 }
-            }
-            Err(e) => {
-                eprintln!("Failed to save annotation: {}", e);
-            }
-        }
+            },
+        );
     }
 
     fn delete_annotation(&self, id: i64) {
-        match annotations::delete_annotation(id) {
-            Ok(_) => {
-                println!("Annotation deleted successfully");
-                self.close_annotation_panel();
-                self.reload_annotations();
-                self.update_annotation_highlights();
-                self.imp().toc_panel.remove_listbox_annotation(id);
-            }
-            Err(e) => {
-                eprintln!("Failed to delete annotation: {}", e);
+        self.write_annotations(
+            move || annotations::delete_annotation(id),
+            move |window, result| match result {
+                Ok(_) => {
+                    println!("Annotation deleted successfully");
+                    if window.imp().annotation_panel.annotation_id() == Some(id) {
+                        window.close_annotation_panel();
+                    }
+                    window.reload_annotations();
+                    window.update_annotation_highlights();
+                    window.sync_notes_file();
+                    window.imp().toc_panel.remove_listbox_annotation(id);
+                }
+                Err(e) => {
+                    window.show_toast(&format!("Failed to delete annotation: {}", e));
+                }
+            },
+        );
+    }
+
+    /// Run `write` on a worker thread, since it may wait seconds for another
+    /// process holding the database, then hand its result to `done`
+    fn write_annotations<T: Send + 'static>(
+        &self,
+        write: impl FnOnce() -> Result<T, AnnotationError> + Send + 'static,
+        done: impl FnOnce(&Self, Result<T, AnnotationError>) + 'static,
+    ) {
+        // Resumed on the main loop as soon as the writing thread returns
+        let window_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(write).await.unwrap_or_else(|_| {
+                Err(AnnotationError::DatabaseError(
+                    "the write stopped".to_string(),
+                ))
+            });
+            if let Some(window) = window_weak.upgrade() {
+                done(&window, result);
             }
+        });
+    }
+
+    /// Close the annotation panel after saving, unless it moved on to another
+    /// selection while the annotation was written
+    fn close_saved_annotation_panel(&self, start: WordCursor, end: WordCursor) {
+        if *self.imp().pending_annotation.borrow() == Some((start, end)) {
+            self.close_annotation_panel();
         }
     }
 
@@ -2392,6 +8241,9 @@ impl EyersWindow {
             .set_selected_text(&annotation.selected_text);
         imp.annotation_panel.set_annotation_id(Some(annotation.id));
         imp.annotation_panel.set_note(&annotation.note);
+        imp.annotation_panel.set_color(annotation.color);
+        imp.annotation_panel.set_tags(&annotation.tags);
+        self.update_annotation_panel_links();
 
         // Close TOC
         imp.toc_panel.set_visible(false);
@@ -2425,6 +8277,83 @@ impl EyersWindow {
         });
     }
 
+    /// Dock the annotation panel under or right of the pages, or float it in its
+    /// own window, keeping a note being written
+    fn set_annotation_panel_position(&self, position: PanelPosition) {
+        self.imp().annotation_panel_position.set(position);
+        self.place_annotation_panel();
+    }
+
+    /// Dock the annotation panel where set in the settings, or in a window of
+    /// its own in the compact layout
+    fn place_annotation_panel(&self) {
+        let imp = self.imp();
+        let panel = &imp.annotation_panel;
+        let visible = panel.is_visible();
+        let position = if imp.compact.get() {
+            PanelPosition::Floating
+        } else {
+            imp.annotation_panel_position.get()
+        };
+
+        if let Some(dialog) = imp.annotation_dialog.take() {
+            dialog.set_child(None::<&gtk::Widget>);
+            dialog.destroy();
+        } else if let Some(parent) = panel.parent().and_downcast::<Box>() {
+            parent.remove(panel);
+        }
+        panel.set_dock_position(position);
+
+        match position {
+            PanelPosition::Bottom => {
+                if let Some(main_box) = imp.main_box.borrow().as_ref() {
+                    main_box.append(panel);
+                }
+            }
+            PanelPosition::Right => {
+                if let Some(content_row) = imp.content_row.borrow().as_ref() {
+                    content_row.append(panel);
+                }
+            }
+            PanelPosition::Floating => {
+                let dialog = gtk::Window::builder()
+                    .title("Annotation")
+                    .transient_for(self)
+                    .default_width(480)
+                    .default_height(320)
+                    .hide_on_close(true)
+                    .child(panel)
+                    .build();
+
+                let window_weak = self.downgrade();
+                dialog.connect_close_request(move |_| {
+                    if let Some(window) = window_weak.upgrade() {
+                        window.close_annotation_panel();
+                    }
+                    glib::Propagation::Proceed
+                });
+                imp.annotation_dialog.replace(Some(dialog));
+            }
+        }
+
+        panel.set_visible(visible);
+        self.sync_annotation_dialog();
+    }
+
+    /// Show the floating annotation window whenever the panel is shown
+    fn sync_annotation_dialog(&self) {
+        let imp = self.imp();
+        let Some(dialog) = imp.annotation_dialog.borrow().clone() else {
+            return;
+        };
+        if imp.annotation_panel.is_visible() {
+            dialog.present();
+            imp.annotation_panel.focus_input();
+        } else {
+            dialog.set_visible(false);
+        }
+    }
+
     fn close_annotation_panel(&self) {
         let imp = self.imp();
         imp.annotation_panel.set_visible(false);
@@ -2440,6 +8369,7 @@ impl EyersWindow {
             Some(p) => p.clone(),
             None => {
                 imp.annotations.replace(Vec::new());
+                self.reload_annotation_links();
                 return;
             }
         };
@@ -2454,23 +8384,44 @@ impl EyersWindow {
                 imp.annotations.replace(Vec::new());
             }
         }
+        self.reload_annotation_links();
+    }
+
+    /// Reload the links between the annotations of the current PDF
+    fn reload_annotation_links(&self) {
+        let imp = self.imp();
+        let links = match imp.current_pdf_path.borrow().as_deref() {
+            Some(pdf_path) => annotations::load_links_for_pdf(pdf_path).unwrap_or_else(|e| {
+                eprintln!("Failed to load annotation links: {}", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        imp.annotation_links.replace(links.clone());
+        imp.toc_panel.set_annotation_links(links);
     }
 
     /// Update annotation highlights on all pages
     fn update_annotation_highlights(&self) {
         let imp = self.imp();
+        self.update_split_highlights();
 
         let annotations = imp.annotations.borrow();
+        if self.reflow_active() {
+            imp.reflow_view.set_annotations(&annotations);
+        }
         if annotations.is_empty() {
+            imp.annotation_rects.borrow_mut().clear();
             // Clear all annotation highlights
-            for overlay in imp.pdf_view.highlight_overlays().iter() {
+            for overlay in imp.pdf_view().highlight_overlays().iter() {
                 overlay.set_annotations(Vec::new());
             }
             return;
         }
 
         // We need mutable access to cache and document access
-        let doc_borrow = imp.pdf_view.document();
+        let pdf_view = imp.pdf_view();
+        let doc_borrow = pdf_view.document();
         let doc = match doc_borrow.as_ref() {
             Some(d) => d,
             None => return,
@@ -2482,132 +8433,152 @@ impl EyersWindow {
             None => return,
         };
 
-        let page_pictures = imp.pdf_view.page_pictures();
-        let render_width =
-            crate::services::pdf_text::get_render_width_for_zoom(imp.pdf_view.zoom_level());
+        let zoom_level = pdf_view.zoom_level();
+
+        let mut rect_cache = imp.annotation_rects.borrow_mut();
+        rect_cache.retain(zoom_level, &annotations);
 
         // Build annotation highlights per page
-        let mut page_ann_rects: std::collections::HashMap<usize, Vec<HighlightRect>> =
-            std::collections::HashMap::new();
+        let mut page_ann_rects: std::collections::HashMap<
+            usize,
+            Vec<(HighlightRect, AnnotationColor)>,
+        > = std::collections::HashMap::new();
 
         for ann in annotations.iter() {
-            // Handle same-page and cross-page annotations
-            if ann.start_page == ann.end_page {
-                // Same page - use get_or_build to ensure the text map exists
-                if let Some(text_map) = cache.get_or_build(ann.start_page, doc) {
-                    let x_offset = page_pictures
-                        .get(ann.start_page)
-                        .map(|pic| calculate_picture_offset(pic))
-                        .unwrap_or(0.0);
-
-                    for idx in ann.start_word..=ann.end_word {
-                        if let Some(word) = text_map.get_word(idx) {
-                            let rect = HighlightRect::from_pdf_bounds(
-                                &word.bounds,
-                                text_map.page_width,
-                                text_map.page_height,
-                                x_offset,
-                                render_width,
-                            );
-                            page_ann_rects
-                                .entry(ann.start_page)
-                                .or_insert_with(Vec::new)
-                                .push(rect);
-                        }
-                    }
-                }
-            } else {
-                // Cross-page annotation
-                // First page
-                if let Some(text_map) = cache.get_or_build(ann.start_page, doc) {
-                    let x_offset = page_pictures
-                        .get(ann.start_page)
-                        .map(|pic| calculate_picture_offset(pic))
-                        .unwrap_or(0.0);
-
-                    for idx in ann.start_word..text_map.word_count() {
-                        if let Some(word) = text_map.get_word(idx) {
-                            let rect = HighlightRect::from_pdf_bounds(
-                                &word.bounds,
-                                text_map.page_width,
-                                text_map.page_height,
-                                x_offset,
-                                render_width,
-                            );
-                            page_ann_rects
-                                .entry(ann.start_page)
-                                .or_insert_with(Vec::new)
-                                .push(rect);
-                        }
-                    }
-                }
-
-                // Middle pages
-                for page_idx in (ann.start_page + 1)..ann.end_page {
-                    if let Some(text_map) = cache.get_or_build(page_idx, doc) {
-                        let x_offset = page_pictures
-                            .get(page_idx)
-                            .map(|pic| calculate_picture_offset(pic))
-                            .unwrap_or(0.0);
+            let rects = rect_cache.get_or_compute(ann, || {
+                Self::compute_annotation_rects(cache, doc.as_ref(), ann, &pdf_view)
+            });
 
-                        for idx in 0..text_map.word_count() {
-                            if let Some(word) = text_map.get_word(idx) {
-                                let rect = HighlightRect::from_pdf_bounds(
-                                    &word.bounds,
-                                    text_map.page_width,
-                                    text_map.page_height,
-                                    x_offset,
-                                    render_width,
-                                );
-                                page_ann_rects
-                                    .entry(page_idx)
-                                    .or_insert_with(Vec::new)
-                                    .push(rect);
-                            }
-                        }
-                    }
-                }
+            for (page_index, rect) in rects {
+                page_ann_rects
+                    .entry(*page_index)
+                    .or_default()
+                    .push((*rect, ann.color));
+            }
+        }
 
-                // Last page
-                if let Some(text_map) = cache.get_or_build(ann.end_page, doc) {
-                    let x_offset = page_pictures
-                        .get(ann.end_page)
-                        .map(|pic| calculate_picture_offset(pic))
-                        .unwrap_or(0.0);
-
-                    for idx in 0..=ann.end_word {
-                        if let Some(word) = text_map.get_word(idx) {
-                            let rect = HighlightRect::from_pdf_bounds(
-                                &word.bounds,
-                                text_map.page_width,
-                                text_map.page_height,
-                                x_offset,
-                                render_width,
-                            );
-                            page_ann_rects
-                                .entry(ann.end_page)
-                                .or_insert_with(Vec::new)
-                                .push(rect);
-                        }
-                    }
-                }
+        // Cached rects are stored without the centering offset, which depends on the layout
+        let page_pictures = pdf_view.page_pictures();
+        for (page_index, rects) in page_ann_rects.iter_mut() {
+            let x_offset = page_pictures
+                .get(*page_index)
+                .map(|pic| calculate_picture_offset(pic))
+                .unwrap_or(0.0);
+            for (rect, _) in rects.iter_mut() {
+                rect.x += x_offset;
             }
         }
 
         // Apply annotation highlights to overlays
-        let overlays = imp.pdf_view.highlight_overlays();
+        let overlays = pdf_view.highlight_overlays();
         for (page_index, overlay) in overlays.iter().enumerate() {
             let rects = page_ann_rects.remove(&page_index).unwrap_or_default();
             overlay.set_annotations(rects);
         }
     }
 
+    /// Draw the annotations on the pages of the second view, their words taken
+    /// from the text maps shared with the pages
+    fn update_split_highlights(&self) {
+        let imp = self.imp();
+        if !self.split_active() {
+            return;
+        }
+
+        let annotations = imp.annotations.borrow();
+        let doc_borrow = imp.split_view.document();
+        let Some(doc) = doc_borrow.as_ref() else {
+            return;
+        };
+        let mut cache = imp.text_cache.borrow_mut();
+        let Some(cache) = cache.as_mut() else {
+            return;
+        };
+
+        let mut page_ann_rects: HashMap<usize, Vec<(HighlightRect, AnnotationColor)>> =
+            HashMap::new();
+        for ann in annotations.iter() {
+            for (page_index, rect) in
+                Self::compute_annotation_rects(cache, doc.as_ref(), ann, &imp.split_view)
+            {
+                page_ann_rects
+                    .entry(page_index)
+                    .or_default()
+                    .push((rect, ann.color));
+            }
+        }
+
+        let page_pictures = imp.split_view.page_pictures();
+        for (page_index, overlay) in imp.split_view.highlight_overlays().iter().enumerate() {
+            let mut rects = page_ann_rects.remove(&page_index).unwrap_or_default();
+            let x_offset = page_pictures
+                .get(page_index)
+                .map(|pic| calculate_picture_offset(pic))
+                .unwrap_or(0.0);
+            for (rect, _) in rects.iter_mut() {
+                rect.x += x_offset;
+            }
+            overlay.set_annotations(rects);
+        }
+    }
+
+    /// Compute the highlight rects of an annotation (without horizontal centering offset),
+    /// only visiting the words inside its range
+    fn compute_annotation_rects(
+        cache: &mut TextMapCache,
+        doc: &dyn DocumentBackend,
+        ann: &Annotation,
+        pdf_view: &PdfView,
+    ) -> Vec<(usize, HighlightRect)> {
+        let mut rects = Vec::new();
+
+        for page_index in ann.start_page..=ann.end_page {
+            let Some(text_map) = cache.get_or_build(page_index, doc) else {
+                continue;
+            };
+
+            let word_start = if page_index == ann.start_page {
+                ann.start_word
+            } else {
+                0
+            };
+            let word_end = if page_index == ann.end_page {
+                (ann.end_word + 1).min(text_map.word_count())
+            } else {
+                text_map.word_count()
+            };
+
+            let render_width = pdf_view.page_render_width(page_index);
+            let rotation = pdf_view.page_rotation(page_index);
+            let crop = pdf_view.page_crop(page_index);
+            for word in text_map.words.get(word_start..word_end).unwrap_or_default() {
+                let rect = HighlightRect::from_pdf_bounds(
+                    &word.bounds,
+                    text_map.page_width,
+                    text_map.page_height,
+                    0.0,
+                    render_width,
+                    rotation,
+                    crop,
+                );
+                rects.push((page_index, rect));
+            }
+        }
+
+        rects
+    }
+
     pub fn annotation_panel(&self) -> &AnnotationPanel {
         &self.imp().annotation_panel
     }
 
     /// Handle drag started event from PdfView
     fn handle_drag_started(&self, x: f64, y: f64, page_index: usize) {
+        if self.pdf_view().drawing() {
+            self.start_ink_stroke(x, y, page_index);
+            return;
+        }
+
         // 1. Check if definitions_enabled - return early if true
         if self.pdf_view().definitions_enabled() {
             return;
@@ -2622,8 +8593,8 @@ impl EyersWindow {
                 *mode = AppMode::Normal;
                 drop(mode);
 
-                self.imp().pdf_view.set_cursor(None);
-                self.imp().pdf_view.clear_selection();
+                self.imp().pdf_view().set_cursor(None);
+                self.imp().pdf_view().clear_selection();
                 self.update_mode_display();
                 self.update_highlights();
                 return;
@@ -2646,7 +8617,7 @@ impl EyersWindow {
         drop(mode);
 
         // 5. Sync cursor to PdfView and update displays
-        self.imp().pdf_view.set_cursor(Some(start_cursor));
+        self.imp().pdf_view().set_cursor(Some(start_cursor));
         self.update_mode_display();
         self.update_selection_display();
     }
@@ -2654,7 +8625,7 @@ impl EyersWindow {
     /// Handle drag motion event from PdfView
     fn handle_drag_motion(&self, x: f64, y: f64) {
         // 1. Check if definitions_enabled - return early if true
-        if self.pdf_view().definitions_enabled() {
+        if self.pdf_view().definitions_enabled() || self.pdf_view().drawing() {
             return;
         }
 
@@ -2704,12 +8675,17 @@ impl EyersWindow {
         drop(mode);
 
         // 7. Sync to PdfView and redraw highlights
-        self.imp().pdf_view.set_cursor(Some(cursor));
+        self.imp().pdf_view().set_cursor(Some(cursor));
         self.update_selection_display();
     }
 
     /// Handle drag ended event from PdfView
     fn handle_drag_ended(&self) {
+        if self.imp().ink_stroke.borrow().is_some() {
+            self.finish_ink_stroke();
+            return;
+        }
+
         // 1. Check if definitions_enabled - return early if true
         if self.pdf_view().definitions_enabled() {
             return;
@@ -2745,8 +8721,8 @@ impl EyersWindow {
             *mode = AppMode::Normal;
             drop(mode);
 
-            self.imp().pdf_view.set_cursor(None);
-            self.imp().pdf_view.clear_selection();
+            self.imp().pdf_view().set_cursor(None);
+            self.imp().pdf_view().clear_selection();
             self.update_mode_display();
             self.update_highlights();
         }
@@ -2782,43 +8758,39 @@ impl EyersWindow {
         let doc_borrow = pdf_view.document();
         let doc = doc_borrow.as_ref()?;
 
-        // Get the page
-        let page = doc.pages().get(page_index as u16).ok()?;
+        // Get the page size
+        let page_size = doc.page_size(page_index)?;
 
         // Get the picture for offset calculation
         let picture = pdf_view.get_page_picture(page_index)?;
 
-        // Calculate offset and zoom
+        // Calculate offset and the width the page is rendered at
         let offset = calculate_picture_offset(&picture);
-        let zoom = pdf_view.zoom_level();
+        let render_width = pdf_view.page_render_width(page_index);
 
         // Convert screen coordinates to PDF coordinates
-        let click = crate::services::pdf_text::calculate_click_coordinates_with_offset(
-            x, y, &page, offset, zoom,
+        let click = calculate_click_coordinates_with_offset(
+            x,
+            y,
+            page_size,
+            offset,
+            render_width,
+            pdf_view.page_rotation(page_index),
+            pdf_view.page_crop(page_index),
         );
 
-        // Get the text page
-        let text_page = page.text().ok()?;
-
-        // Find the character index at the click position
-        let char_idx = crate::services::pdf_text::find_char_index_at_click(&text_page, &click)?;
-
         // Get or build the text map for this page
         let mut cache = self.imp().text_cache.borrow_mut();
         let cache = cache.as_mut()?;
-        let text_map = cache.get_or_build(page_index, doc)?;
-
-        // Find the word that contains this character index
-        for (word_index, word) in text_map.words.iter().enumerate() {
-            if char_idx >= word.char_start && char_idx < word.char_end {
-                return Some(WordCursor {
-                    page_index,
-                    word_index,
-                });
-            }
-        }
-
-        None
+        let text_map = cache.get_or_build(page_index, doc.as_ref())?;
+
+        // Look the word up in the page's grid instead of going through its chars,
+        // as this runs on every pointer motion while drag-selecting
+        let word_index = text_map.word_at(click.pdf_x, click.pdf_y, CLICK_TOLERANCE)?;
+        Some(WordCursor {
+            page_index,
+            word_index,
+        })
     }
 
     /// Find which page contains the given global coordinates
@@ -2854,3 +8826,62 @@ impl EyersWindow {
         None
     }
 }
+
+/// Scroll `adjustment` so `position` lies at least `margin` (a fraction of the
+/// viewport) away from its edges. Positions more than a viewport away from the
+/// visible area are centered instead, as after a long jump.
+fn scroll_into_comfort_zone(adjustment: &gtk::Adjustment, position: f64, margin: f64) {
+    let scroll = adjustment.value();
+    let viewport = adjustment.page_size();
+    let margin = viewport * margin;
+
+    let new_scroll = if position < scroll - viewport || position > scroll + 2.0 * viewport {
+        position - viewport / 2.0
+    } else if position < scroll + margin {
+        position - margin
+    } else if position > scroll + viewport - margin {
+        position - viewport + margin
+    } else {
+        return;
+    };
+
+    let max_scroll = (adjustment.upper() - viewport).max(adjustment.lower());
+    adjustment.set_value(new_scroll.clamp(adjustment.lower(), max_scroll));
+}
+
+/// Page indices as 1-based page numbers with consecutive runs collapsed, e.g. "3–5, 9"
+fn format_page_ranges(pages: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &page in pages {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == page => *end = page,
+            _ => ranges.push((page, page)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                (start + 1).to_string()
+            } else {
+                format!("{}–{}", start + 1, end + 1)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The sentence around a word of the page, for the context of a flashcard
+fn sentence_around_word(text_map: &PageTextMap, word_index: usize) -> Option<String> {
+    let mut text = String::new();
+    let mut word_start = None;
+    for index in 0..text_map.word_count() {
+        if index == word_index {
+            word_start = Some(text.chars().count());
+        }
+        text.push_str(&text_map.get_word(index)?.text);
+        text.push(' ');
+    }
+    extract_sentence_at(&text, word_start?)
+}
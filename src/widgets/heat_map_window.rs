@@ -0,0 +1,173 @@
+use gtk::glib;
+use gtk::glib::subclass::Signal;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Button, FlowBox, Label, Orientation, ScrolledWindow, Window};
+use std::sync::OnceLock;
+
+use crate::services::heat_map::{self, PageActivity};
+
+mod imp {
+    use super::*;
+
+    pub struct HeatMapWindow {
+        pub flow_box: FlowBox,
+        pub summary_label: Label,
+    }
+
+    impl Default for HeatMapWindow {
+        fn default() -> Self {
+            Self {
+                flow_box: FlowBox::builder()
+                    .selection_mode(gtk::SelectionMode::None)
+                    .homogeneous(true)
+                    .min_children_per_line(5)
+                    .max_children_per_line(20)
+                    .row_spacing(4)
+                    .column_spacing(4)
+                    .valign(gtk::Align::Start)
+                    .build(),
+                summary_label: Label::builder()
+                    .halign(gtk::Align::Start)
+                    .wrap(true)
+                    .css_classes(["dim-label"])
+                    .build(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HeatMapWindow {
+        const NAME: &'static str = "HeatMapWindow";
+        type Type = super::HeatMapWindow;
+        type ParentType = Window;
+    }
+
+    impl ObjectImpl for HeatMapWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    Signal::builder("page-selected")
+                        .param_types([u32::static_type()])
+                        .build(),
+                ]
+            })
+        }
+    }
+
+    impl WidgetImpl for HeatMapWindow {}
+    impl WindowImpl for HeatMapWindow {}
+}
+
+glib::wrapper! {
+    /// Overview of the pages of a document, shaded by how many annotations and
+    /// lookups were made on each
+    pub struct HeatMapWindow(ObjectSubclass<imp::HeatMapWindow>)
+        @extends Window, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl HeatMapWindow {
+    pub fn new(parent: &impl IsA<Window>) -> Self {
+        glib::Object::builder()
+            .property("transient-for", parent)
+            .property("modal", true)
+            .property("title", "Annotation Heat Map")
+            .property("default-width", 520)
+            .property("default-height", 420)
+            .build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+
+        self.add_css_class("heat-map-window");
+
+        let main_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(12)
+            .margin_start(24)
+            .margin_end(24)
+            .margin_top(24)
+            .margin_bottom(24)
+            .build();
+
+        main_box.append(&imp.summary_label);
+
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .child(&imp.flow_box)
+            .build();
+        main_box.append(&scrolled);
+
+        let close_button = Button::builder()
+            .label("Close")
+            .halign(gtk::Align::End)
+            .build();
+
+        let window_weak = self.downgrade();
+        close_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.close();
+            }
+        });
+        main_box.append(&close_button);
+
+        self.set_child(Some(&main_box));
+    }
+
+    /// Show one cell per page, shaded relative to the busiest page
+    pub fn set_pages(&self, pages: &[PageActivity]) {
+        let imp = self.imp();
+        imp.flow_box.remove_all();
+
+        let max_total = pages.iter().map(PageActivity::total).max().unwrap_or(0);
+        for (page_index, activity) in pages.iter().enumerate() {
+            imp.flow_box
+                .append(&self.build_cell(page_index, activity, max_total));
+        }
+
+        let annotations: usize = pages.iter().map(|page| page.annotations).sum();
+        let lookups: usize = pages.iter().map(|page| page.lookups).sum();
+        imp.summary_label.set_label(&format!(
+            "{} annotations and {} lookups over {} pages. The stronger the color, the more of both on the page. Click a page to go to it.",
+            annotations,
+            lookups,
+            pages.len()
+        ));
+    }
+
+    fn build_cell(&self, page_index: usize, activity: &PageActivity, max_total: usize) -> Button {
+        let level_class = format!(
+            "heat-level-{}",
+            heat_map::heat_level(activity.total(), max_total)
+        );
+        let cell = Button::builder()
+            .label((page_index + 1).to_string())
+            .tooltip_text(format!(
+                "Page {}: {} annotations, {} lookups",
+                page_index + 1,
+                activity.annotations,
+                activity.lookups
+            ))
+            .css_classes(["heat-map-cell", level_class.as_str()])
+            .build();
+
+        let window_weak = self.downgrade();
+        cell.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.emit_by_name::<()>("page-selected", &[&(page_index as u32)]);
+                window.close();
+            }
+        });
+
+        cell
+    }
+}
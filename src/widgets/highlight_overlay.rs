@@ -2,10 +2,17 @@ use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use pdfium_render::prelude::PdfRect;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use crate::services::annotations::AnnotationColor;
+use crate::services::crop::PageCrop;
+use crate::services::ink::InkTool;
+use crate::services::rotation::Rotation;
+use crate::services::theme::HighlightPalette;
 
 /// A rectangle in screen coordinates for highlighting
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct HighlightRect {
     pub x: f64,
     pub y: f64,
@@ -22,30 +29,93 @@ impl HighlightRect {
     /// `render_width` is the effective render width (RENDER_WIDTH * zoom_level)
     /// `x_offset` accounts for horizontal centering when the Picture is narrower
     /// than its container (e.g., in fullscreen mode)
+    /// `rotation` and `crop` are the rotation and the margins cut off the page
+    /// it is shown with
     pub fn from_pdf_bounds(
         bounds: &PdfRect,
         page_width: f64,
         page_height: f64,
         x_offset: f64,
         render_width: i32,
+        rotation: Rotation,
+        crop: PageCrop,
     ) -> Self {
         let scale = render_width as f64 / page_width;
 
-        // PDF coords -> screen coords
-        // screen_x = pdf_x * scale + x_offset (account for centering)
+        // PDF coords -> screen coords of the unrotated page
+        // screen_x = pdf_x * scale
         // screen_y = (page_height - pdf_top) * scale (flip y-axis)
-        let x = bounds.left().value as f64 * scale + x_offset;
+        let x = bounds.left().value as f64 * scale;
         let y = (page_height - bounds.top().value as f64) * scale;
         let width = (bounds.right().value - bounds.left().value) as f64 * scale;
         let height = (bounds.top().value - bounds.bottom().value) as f64 * scale;
 
+        // Then cropped and turned with the page, and shifted by the centering offset
+        let (x, y) = crop.to_cropped((x, y), scale);
+        let (shown_width, shown_height) = crop.size((page_width, page_height));
+        let (x, y, width, height) = rotation.rect_to_screen(
+            (x, y, width, height),
+            (shown_width * scale, shown_height * scale),
+        );
+
         Self {
-            x,
+            x: x + x_offset,
             y,
             width,
             height,
         }
     }
+
+    /// Smallest rect containing both rects
+    pub fn union(&self, other: &HighlightRect) -> HighlightRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        HighlightRect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+/// A word redrawn by the bionic reading mode
+#[derive(Debug, Clone)]
+pub struct BionicWord {
+    pub rect: HighlightRect,
+    pub text: String,
+}
+
+impl BionicWord {
+    /// Split the word into its bolded first half and the regular remainder
+    pub fn split(&self) -> (&str, &str) {
+        let char_count = self.text.chars().count();
+        let bold_chars = char_count.div_ceil(2);
+        let split_at = self
+            .text
+            .char_indices()
+            .nth(bold_chars)
+            .map(|(idx, _)| idx)
+            .unwrap_or(self.text.len());
+        self.text.split_at(split_at)
+    }
+}
+
+/// A drawing placed on the page, with its points in screen coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct InkMark {
+    pub tool: InkTool,
+    pub points: Vec<(f64, f64)>,
+    pub color: AnnotationColor,
+}
+
+/// The words and lines of a page's text map, outlined by the word boxes debug view
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WordBoxes {
+    /// Word rects in reading order, with the index of their line
+    pub words: Vec<(HighlightRect, usize)>,
+    /// One rect around the words of each line
+    pub lines: Vec<HighlightRect>,
 }
 
 /// Highlight data for a page
@@ -53,10 +123,22 @@ impl HighlightRect {
 pub struct PageHighlights {
     /// Cursor highlight (single word)
     pub cursor: Option<HighlightRect>,
-    /// Selection highlights (multiple words, one rect per word)
-    pub selection: Vec<HighlightRect>,
-    /// Annotation highlights (light yellow, persistent)
-    pub annotations: Vec<HighlightRect>,
+    /// Selection highlights, one rect per word keyed by word index
+    pub selection: BTreeMap<usize, HighlightRect>,
+    /// Annotation highlights in the annotation's color (persistent)
+    pub annotations: Vec<(HighlightRect, AnnotationColor)>,
+    /// Free-hand and shape drawings (persistent)
+    pub ink: Vec<InkMark>,
+    /// Drawing being made, until the pointer is released
+    pub ink_preview: Option<InkMark>,
+    /// Search matches (orange, until the search is replaced)
+    pub search: Vec<HighlightRect>,
+    /// Annotation briefly emphasized after jumping to it
+    pub flash: Vec<HighlightRect>,
+    /// Words redrawn for bionic reading (persistent, drawn below everything else)
+    pub bionic: Vec<BionicWord>,
+    /// Text map outlines of the word boxes debug view (drawn above everything else)
+    pub word_boxes: WordBoxes,
 }
 
 mod imp {
@@ -65,6 +147,10 @@ mod imp {
     #[derive(Default)]
     pub struct HighlightOverlay {
         pub highlights: RefCell<PageHighlights>,
+        /// Annotations drawn fainter, in distraction-free mode
+        pub annotations_dimmed: Cell<bool>,
+        /// Colors of the theme the highlights are drawn with
+        pub palette: Cell<HighlightPalette>,
     }
 
     #[glib::object_subclass]
@@ -112,13 +198,30 @@ impl HighlightOverlay {
     fn draw(&self, cr: &gtk::cairo::Context) {
         let highlights = self.imp().highlights.borrow();
 
+        // Bionic text replaces the rendered text, so it goes under every highlight
+        for word in &highlights.bionic {
+            self.draw_bionic_word(cr, word);
+        }
+
         // Draw annotation highlights first (behind everything)
-        for rect in &highlights.annotations {
-            self.draw_annotation_rect(cr, rect);
+        for (rect, color) in &highlights.annotations {
+            self.draw_annotation_rect(cr, rect, *color);
+        }
+
+        for mark in highlights.ink.iter().chain(&highlights.ink_preview) {
+            self.draw_ink_mark(cr, mark);
+        }
+
+        for rect in &highlights.search {
+            self.draw_search_rect(cr, rect);
+        }
+
+        for rect in &highlights.flash {
+            self.draw_flash_rect(cr, rect);
         }
 
         // Draw selection highlights (behind cursor)
-        for rect in &highlights.selection {
+        for rect in highlights.selection.values() {
             self.draw_selection_rect(cr, rect);
         }
 
@@ -130,16 +233,55 @@ impl HighlightOverlay {
             );
             self.draw_cursor_rect(cr, cursor_rect);
         }
+
+        self.draw_word_boxes(cr, &highlights.word_boxes);
+    }
+
+    /// Outline each line and word, alternating colors between lines so merged or
+    /// split lines stand out, and number the words in reading order
+    fn draw_word_boxes(&self, cr: &gtk::cairo::Context, boxes: &WordBoxes) {
+        cr.set_line_width(1.0);
+        cr.set_dash(&[4.0, 2.0], 0.0);
+        for (line_index, rect) in boxes.lines.iter().enumerate() {
+            cr.set_source_rgba(0.5, 0.2, 0.8, 0.8);
+            cr.rectangle(
+                rect.x - 2.0,
+                rect.y - 2.0,
+                rect.width + 4.0,
+                rect.height + 4.0,
+            );
+            let _ = cr.stroke();
+
+            cr.set_font_size(9.0);
+            cr.move_to(rect.x - 2.0, rect.y - 4.0);
+            let _ = cr.show_text(&format!("L{}", line_index));
+        }
+        cr.set_dash(&[], 0.0);
+
+        cr.set_font_size(7.0);
+        for (word_index, (rect, line_index)) in boxes.words.iter().enumerate() {
+            if line_index % 2 == 0 {
+                cr.set_source_rgba(0.9, 0.1, 0.1, 0.9);
+            } else {
+                cr.set_source_rgba(0.0, 0.6, 0.2, 0.9);
+            }
+            cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+            let _ = cr.stroke();
+
+            cr.move_to(rect.x + 1.0, rect.y + rect.height + 7.0);
+            let _ = cr.show_text(&word_index.to_string());
+        }
     }
 
     fn draw_cursor_rect(&self, cr: &gtk::cairo::Context, rect: &HighlightRect) {
         // Blue with ~40% opacity for cursor
-        cr.set_source_rgba(0.2, 0.4, 0.8, 0.4);
+        let (r, g, b) = self.imp().palette.get().cursor.fractions();
+        cr.set_source_rgba(r, g, b, 0.4);
         cr.rectangle(rect.x, rect.y, rect.width, rect.height);
         let _ = cr.fill();
 
         // Add a subtle border
-        cr.set_source_rgba(0.2, 0.4, 0.8, 0.7);
+        cr.set_source_rgba(r, g, b, 0.7);
         cr.set_line_width(1.5);
         cr.rectangle(rect.x, rect.y, rect.width, rect.height);
         let _ = cr.stroke();
@@ -147,27 +289,174 @@ impl HighlightOverlay {
 
     fn draw_selection_rect(&self, cr: &gtk::cairo::Context, rect: &HighlightRect) {
         // Lighter blue with ~25% opacity for selection
-        cr.set_source_rgba(0.3, 0.5, 0.9, 0.25);
+        let (r, g, b) = self.imp().palette.get().selection.fractions();
+        cr.set_source_rgba(r, g, b, 0.25);
+        cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+        let _ = cr.fill();
+    }
+
+    fn draw_annotation_rect(
+        &self,
+        cr: &gtk::cairo::Context,
+        rect: &HighlightRect,
+        color: AnnotationColor,
+    ) {
+        // Light tints with ~30% opacity for annotations, ~10% when dimmed
+        let alpha = if self.imp().annotations_dimmed.get() {
+            0.1
+        } else {
+            0.3
+        };
+        let (r, g, b) = self.imp().palette.get().annotation(color).fractions();
+        cr.set_source_rgba(r, g, b, alpha);
+        cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+        let _ = cr.fill();
+    }
+
+    fn draw_ink_mark(&self, cr: &gtk::cairo::Context, mark: &InkMark) {
+        // Darker than the highlight tints so lines stay visible over text
+        let (r, g, b) = match mark.color {
+            AnnotationColor::Yellow => (0.85, 0.65, 0.0),
+            AnnotationColor::Green => (0.1, 0.6, 0.2),
+            AnnotationColor::Red => (0.85, 0.15, 0.15),
+            AnnotationColor::Blue => (0.15, 0.4, 0.85),
+        };
+        let alpha = if self.imp().annotations_dimmed.get() {
+            0.3
+        } else {
+            0.9
+        };
+        cr.set_source_rgba(r, g, b, alpha);
+        cr.set_line_width(2.0);
+        cr.set_line_cap(gtk::cairo::LineCap::Round);
+        cr.set_line_join(gtk::cairo::LineJoin::Round);
+
+        match (mark.tool, mark.points.as_slice()) {
+            (InkTool::Rectangle, [(x1, y1), .., (x2, y2)]) => {
+                cr.rectangle(x1.min(*x2), y1.min(*y2), (x2 - x1).abs(), (y2 - y1).abs());
+            }
+            (_, [(x, y)]) => {
+                // A single tap leaves a dot
+                cr.arc(*x, *y, 1.0, 0.0, std::f64::consts::TAU);
+            }
+            (_, [(x, y), rest @ ..]) => {
+                cr.move_to(*x, *y);
+                for (x, y) in rest {
+                    cr.line_to(*x, *y);
+                }
+            }
+            (_, []) => return,
+        }
+        let _ = cr.stroke();
+    }
+
+    fn draw_search_rect(&self, cr: &gtk::cairo::Context, rect: &HighlightRect) {
+        // Orange with ~35% opacity for search matches
+        let (r, g, b) = self.imp().palette.get().search.fractions();
+        cr.set_source_rgba(r, g, b, 0.35);
+        cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+        let _ = cr.fill();
+    }
+
+    fn draw_flash_rect(&self, cr: &gtk::cairo::Context, rect: &HighlightRect) {
+        // Saturated yellow with an outline, stronger than the annotation highlight
+        cr.set_source_rgba(1.0, 0.85, 0.0, 0.45);
         cr.rectangle(rect.x, rect.y, rect.width, rect.height);
         let _ = cr.fill();
+
+        cr.set_source_rgba(0.9, 0.6, 0.0, 0.9);
+        cr.set_line_width(2.0);
+        cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+        let _ = cr.stroke();
     }
 
-    fn draw_annotation_rect(&self, cr: &gtk::cairo::Context, rect: &HighlightRect) {
-        // Light yellow with ~30% opacity for annotations
-        cr.set_source_rgba(1.0, 0.95, 0.4, 0.3);
+    fn draw_bionic_word(&self, cr: &gtk::cairo::Context, word: &BionicWord) {
+        let rect = &word.rect;
+
+        // Dim the rendered text underneath
+        cr.set_source_rgba(1.0, 1.0, 1.0, 0.85);
         cr.rectangle(rect.x, rect.y, rect.width, rect.height);
         let _ = cr.fill();
+
+        let (bold, regular) = word.split();
+        cr.set_font_size(rect.height * 0.85);
+
+        cr.select_font_face(
+            "Sans",
+            gtk::cairo::FontSlant::Normal,
+            gtk::cairo::FontWeight::Bold,
+        );
+        let bold_width = cr.text_extents(bold).map(|e| e.x_advance()).unwrap_or(0.0);
+        cr.select_font_face(
+            "Sans",
+            gtk::cairo::FontSlant::Normal,
+            gtk::cairo::FontWeight::Normal,
+        );
+        let regular_width = cr
+            .text_extents(regular)
+            .map(|e| e.x_advance())
+            .unwrap_or(0.0);
+
+        let _ = cr.save();
+        cr.translate(rect.x, rect.y + rect.height * 0.8);
+
+        // Squeeze the text horizontally so it stays inside the original word box
+        let total_width = bold_width + regular_width;
+        if total_width > rect.width && total_width > 0.0 {
+            cr.scale(rect.width / total_width, 1.0);
+        }
+
+        cr.set_source_rgba(0.0, 0.0, 0.0, 0.9);
+        cr.move_to(0.0, 0.0);
+        cr.select_font_face(
+            "Sans",
+            gtk::cairo::FontSlant::Normal,
+            gtk::cairo::FontWeight::Bold,
+        );
+        let _ = cr.show_text(bold);
+        cr.select_font_face(
+            "Sans",
+            gtk::cairo::FontSlant::Normal,
+            gtk::cairo::FontWeight::Normal,
+        );
+        let _ = cr.show_text(regular);
+        let _ = cr.restore();
     }
 
-    /// Set the cursor highlight
+    /// Set the cursor highlight, redrawing only if it changed
     pub fn set_cursor(&self, rect: Option<HighlightRect>) {
-        self.imp().highlights.borrow_mut().cursor = rect;
+        let mut highlights = self.imp().highlights.borrow_mut();
+        if highlights.cursor == rect {
+            return;
+        }
+        highlights.cursor = rect;
+        drop(highlights);
         self.queue_draw();
     }
 
-    /// Set the selection highlights
-    pub fn set_selection(&self, rects: Vec<HighlightRect>) {
-        self.imp().highlights.borrow_mut().selection = rects;
+    /// Word indices currently highlighted as selected, in ascending order
+    pub fn selection_words(&self) -> Vec<usize> {
+        self.imp()
+            .highlights
+            .borrow()
+            .selection
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    /// Remove and add individual selection rects, redrawing only if something changed
+    pub fn update_selection(&self, remove: &[usize], add: Vec<(usize, HighlightRect)>) {
+        if remove.is_empty() && add.is_empty() {
+            return;
+        }
+
+        let mut highlights = self.imp().highlights.borrow_mut();
+        for word_index in remove {
+            highlights.selection.remove(word_index);
+        }
+        highlights.selection.extend(add);
+        drop(highlights);
         self.queue_draw();
     }
 
@@ -186,38 +475,95 @@ impl HighlightOverlay {
         highlights.cursor = None;
         highlights.selection.clear();
         highlights.annotations.clear();
+        highlights.ink.clear();
+        highlights.ink_preview = None;
+        highlights.search.clear();
+        highlights.flash.clear();
+        highlights.bionic.clear();
+        highlights.word_boxes = WordBoxes::default();
+        self.queue_draw();
+    }
+
+    /// Draw annotation highlights fainter, or normally again
+    pub fn set_annotations_dimmed(&self, dimmed: bool) {
+        if self.imp().annotations_dimmed.replace(dimmed) != dimmed {
+            self.queue_draw();
+        }
+    }
+
+    /// Draw the highlights with the colors of another theme
+    pub fn set_palette(&self, palette: HighlightPalette) {
+        if self.imp().palette.replace(palette) != palette {
+            self.queue_draw();
+        }
+    }
+
+    /// Set the annotation highlights, redrawing only if they changed
+    pub fn set_annotations(&self, rects: Vec<(HighlightRect, AnnotationColor)>) {
+        let mut highlights = self.imp().highlights.borrow_mut();
+        if highlights.annotations == rects {
+            return;
+        }
+        highlights.annotations = rects;
+        drop(highlights);
         self.queue_draw();
     }
 
-    /// Update both cursor and selection at once
-    pub fn set_highlights(&self, cursor: Option<HighlightRect>, selection: Vec<HighlightRect>) {
+    /// Set the drawings of the page, redrawing only if they changed
+    pub fn set_ink(&self, marks: Vec<InkMark>) {
         let mut highlights = self.imp().highlights.borrow_mut();
-        highlights.cursor = cursor;
-        highlights.selection = selection;
+        if highlights.ink == marks {
+            return;
+        }
+        highlights.ink = marks;
         drop(highlights);
         self.queue_draw();
     }
 
-    /// Set the annotation highlights
-    pub fn set_annotations(&self, rects: Vec<HighlightRect>) {
-        self.imp().highlights.borrow_mut().annotations = rects;
+    /// Show the drawing being made, or remove it once finished
+    pub fn set_ink_preview(&self, mark: Option<InkMark>) {
+        self.imp().highlights.borrow_mut().ink_preview = mark;
         self.queue_draw();
     }
 
-    /// Update all highlights at once (cursor, selection, and annotations)
-    pub fn set_all_highlights(
-        &self,
-        cursor: Option<HighlightRect>,
-        selection: Vec<HighlightRect>,
-        annotations: Vec<HighlightRect>,
-    ) {
+    /// Set the search match highlights, redrawing only if they changed
+    pub fn set_search_matches(&self, rects: Vec<HighlightRect>) {
         let mut highlights = self.imp().highlights.borrow_mut();
-        highlights.cursor = cursor;
-        highlights.selection = selection;
-        highlights.annotations = annotations;
+        if highlights.search == rects {
+            return;
+        }
+        highlights.search = rects;
         drop(highlights);
         self.queue_draw();
     }
+
+    /// Set the rects of the annotation being flashed, redrawing only if they changed
+    pub fn set_flash(&self, rects: Vec<HighlightRect>) {
+        let mut highlights = self.imp().highlights.borrow_mut();
+        if highlights.flash == rects {
+            return;
+        }
+        highlights.flash = rects;
+        drop(highlights);
+        self.queue_draw();
+    }
+
+    /// Set the outlines of the word boxes debug view, redrawing only if they changed
+    pub fn set_word_boxes(&self, boxes: WordBoxes) {
+        let mut highlights = self.imp().highlights.borrow_mut();
+        if highlights.word_boxes == boxes {
+            return;
+        }
+        highlights.word_boxes = boxes;
+        drop(highlights);
+        self.queue_draw();
+    }
+
+    /// Set the words redrawn by the bionic reading mode
+    pub fn set_bionic_words(&self, words: Vec<BionicWord>) {
+        self.imp().highlights.borrow_mut().bionic = words;
+        self.queue_draw();
+    }
 }
 
 impl Default for HighlightOverlay {
@@ -0,0 +1,238 @@
+use gtk::glib;
+use gtk::glib::subclass::Signal;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Button, Label, LevelBar, ListBox, Orientation, ScrolledWindow, Window};
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+use crate::modes::{Visit, WordCursor};
+use crate::services::bookmarks::{self, BookmarkEntry};
+
+mod imp {
+    use super::*;
+
+    pub struct HistoryWindow {
+        pub list_box: ListBox,
+        pub empty_label: Label,
+        /// Position of each row, in list order
+        pub cursors: RefCell<Vec<WordCursor>>,
+    }
+
+    impl Default for HistoryWindow {
+        fn default() -> Self {
+            Self {
+                list_box: ListBox::builder()
+                    .selection_mode(gtk::SelectionMode::Single)
+                    .build(),
+                empty_label: Label::builder()
+                    .label("Nothing visited yet. Jumps (links, chapters, search, marks…) add the spot they leave.")
+                    .wrap(true)
+                    .css_classes(["dim-label"])
+                    .margin_top(12)
+                    .margin_bottom(12)
+                    .build(),
+                cursors: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HistoryWindow {
+        const NAME: &'static str = "HistoryWindow";
+        type Type = super::HistoryWindow;
+        type ParentType = Window;
+    }
+
+    impl ObjectImpl for HistoryWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    Signal::builder("visit-selected")
+                        .param_types([WordCursor::static_type()])
+                        .build(),
+                ]
+            })
+        }
+    }
+
+    impl WidgetImpl for HistoryWindow {}
+    impl WindowImpl for HistoryWindow {}
+}
+
+glib::wrapper! {
+    /// Timeline of the spots visited in the document, newest first, each with
+    /// where it sits in the document
+    pub struct HistoryWindow(ObjectSubclass<imp::HistoryWindow>)
+        @extends Window, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl HistoryWindow {
+    pub fn new(parent: &impl IsA<Window>) -> Self {
+        glib::Object::builder()
+            .property("transient-for", parent)
+            .property("modal", true)
+            .property("title", "Reading History")
+            .property("default-width", 460)
+            .property("default-height", 420)
+            .build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+
+        self.add_css_class("history-window");
+
+        let main_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(12)
+            .margin_start(24)
+            .margin_end(24)
+            .margin_top(24)
+            .margin_bottom(24)
+            .build();
+
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .child(&imp.list_box)
+            .build();
+        main_box.append(&scrolled);
+        main_box.append(&imp.empty_label);
+
+        let close_button = Button::builder()
+            .label("Close")
+            .halign(gtk::Align::End)
+            .build();
+
+        let window_weak = self.downgrade();
+        close_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.close();
+            }
+        });
+        main_box.append(&close_button);
+
+        self.set_child(Some(&main_box));
+
+        let window_weak = self.downgrade();
+        imp.list_box.connect_row_activated(move |_, row| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let cursor = window
+                .imp()
+                .cursors
+                .borrow()
+                .get(row.index() as usize)
+                .copied();
+            if let Some(cursor) = cursor {
+                window.emit_by_name::<()>("visit-selected", &[&cursor]);
+                window.close();
+            }
+        });
+    }
+
+    /// List `visits` newest first, under the `current` position
+    pub fn set_visits(
+        &self,
+        visits: &[Visit],
+        current: Option<WordCursor>,
+        chapters: &[BookmarkEntry],
+        page_count: usize,
+    ) {
+        let imp = self.imp();
+        imp.list_box.remove_all();
+        imp.cursors.borrow_mut().clear();
+
+        if let Some(cursor) = current {
+            self.append_row(cursor, "Now", None, chapters, page_count);
+        }
+
+        for (i, visit) in visits.iter().enumerate().rev() {
+            let time = glib::DateTime::from_unix_local(visit.left_at)
+                .and_then(|time| time.format("%H:%M"))
+                .map(|time| time.to_string())
+                .unwrap_or_default();
+            // Time spent there: since the spot visited before was left
+            let minutes = i
+                .checked_sub(1)
+                .map(|prev| (visit.left_at - visits[prev].left_at) / 60)
+                .filter(|minutes| *minutes > 0);
+            self.append_row(visit.cursor, &time, minutes, chapters, page_count);
+        }
+
+        imp.empty_label.set_visible(visits.is_empty());
+    }
+
+    fn append_row(
+        &self,
+        cursor: WordCursor,
+        time: &str,
+        minutes: Option<i64>,
+        chapters: &[BookmarkEntry],
+        page_count: usize,
+    ) {
+        let row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(12)
+            .margin_top(6)
+            .margin_bottom(6)
+            .margin_start(6)
+            .margin_end(6)
+            .build();
+
+        let time_label = Label::builder()
+            .label(time)
+            .width_chars(5)
+            .xalign(0.0)
+            .css_classes(["monospace"])
+            .build();
+        row.append(&time_label);
+
+        let text_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(2)
+            .hexpand(true)
+            .build();
+
+        let mut title = format!("Page {}", cursor.page_index + 1);
+        if let Some(chapter) = bookmarks::chapter_at(chapters, cursor.page_index) {
+            title.push_str(&format!(" · {}", chapter.title));
+        }
+        let title_label = Label::builder()
+            .label(title)
+            .xalign(0.0)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        text_box.append(&title_label);
+
+        // Where the spot sits in the document, so jumps back and forth stand out
+        let position_bar = LevelBar::builder()
+            .min_value(0.0)
+            .max_value(page_count.max(1) as f64)
+            .value((cursor.page_index + 1) as f64)
+            .build();
+        position_bar.add_css_class("history-position");
+        text_box.append(&position_bar);
+        row.append(&text_box);
+
+        if let Some(minutes) = minutes {
+            let duration_label = Label::builder()
+                .label(format!("{} min", minutes))
+                .css_classes(["dim-label"])
+                .build();
+            row.append(&duration_label);
+        }
+
+        self.imp().list_box.append(&row);
+        self.imp().cursors.borrow_mut().push(cursor);
+    }
+}
@@ -0,0 +1,418 @@
+use gtk::glib;
+use gtk::pango;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Button, DrawingArea, Grid, Label, Orientation, ScrolledWindow, Window};
+use std::cell::RefCell;
+use std::path::Path;
+
+use crate::services::library_stats::{self, LibraryStats};
+
+/// Height of a row of the horizontal bar charts
+const BAR_ROW_HEIGHT: f64 = 28.0;
+/// Share of the width of a horizontal bar chart taken by the names
+const BAR_LABEL_SHARE: f64 = 0.4;
+/// Height of the reading time chart
+const DAILY_CHART_HEIGHT: i32 = 160;
+/// Space between a bar and its text
+const TEXT_GAP: f64 = 6.0;
+/// Opacity of the bars, drawn in the text color so they follow the theme
+const BAR_ALPHA: f64 = 0.35;
+
+/// A bar of a horizontal bar chart
+struct Bar {
+    label: String,
+    value: f64,
+    value_label: String,
+}
+
+mod imp {
+    use super::*;
+
+    pub struct LibraryStatsWindow {
+        pub figures: Grid,
+        pub languages_chart: DrawingArea,
+        pub languages_empty: Label,
+        pub most_read_chart: DrawingArea,
+        pub most_read_empty: Label,
+        pub daily_chart: DrawingArea,
+        pub stats: RefCell<LibraryStats>,
+    }
+
+    impl Default for LibraryStatsWindow {
+        fn default() -> Self {
+            let empty_label = |text: &str| {
+                Label::builder()
+                    .label(text)
+                    .halign(gtk::Align::Start)
+                    .css_classes(["dim-label"])
+                    .build()
+            };
+            Self {
+                figures: Grid::builder()
+                    .column_spacing(24)
+                    .row_spacing(2)
+                    .column_homogeneous(true)
+                    .build(),
+                languages_chart: DrawingArea::new(),
+                languages_empty: empty_label("No words looked up yet."),
+                most_read_chart: DrawingArea::new(),
+                most_read_empty: empty_label("Nothing read yet."),
+                daily_chart: DrawingArea::builder()
+                    .content_height(DAILY_CHART_HEIGHT)
+                    .build(),
+                stats: RefCell::new(LibraryStats::default()),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LibraryStatsWindow {
+        const NAME: &'static str = "LibraryStatsWindow";
+        type Type = super::LibraryStatsWindow;
+        type ParentType = Window;
+    }
+
+    impl ObjectImpl for LibraryStatsWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+    }
+
+    impl WidgetImpl for LibraryStatsWindow {}
+    impl WindowImpl for LibraryStatsWindow {}
+}
+
+glib::wrapper! {
+    /// Read-only summary of the whole library: documents, annotations, lookups per
+    /// language, the most read documents and the time read on the last days
+    pub struct LibraryStatsWindow(ObjectSubclass<imp::LibraryStatsWindow>)
+        @extends Window, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl LibraryStatsWindow {
+    pub fn new(parent: &impl IsA<Window>) -> Self {
+        glib::Object::builder()
+            .property("transient-for", parent)
+            .property("modal", true)
+            .property("title", "Library Statistics")
+            .property("default-width", 560)
+            .property("default-height", 640)
+            .build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+
+        self.add_css_class("library-stats-window");
+
+        let content = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(12)
+            .build();
+
+        content.append(&imp.figures);
+
+        content.append(&Self::section_heading("Words Looked Up per Language"));
+        content.append(&imp.languages_chart);
+        content.append(&imp.languages_empty);
+
+        content.append(&Self::section_heading("Most Read"));
+        content.append(&imp.most_read_chart);
+        content.append(&imp.most_read_empty);
+
+        content.append(&Self::section_heading(&format!(
+            "Reading Time, Last {} Days",
+            library_stats::CHART_DAYS
+        )));
+        content.append(&imp.daily_chart);
+        content.append(
+            &Label::builder()
+                .label(format!(
+                    "Minutes spent in documents, from the reading journal. Pauses over {} minutes don't count.",
+                    library_stats::MAX_READING_GAP_MINUTES
+                ))
+                .halign(gtk::Align::Start)
+                .wrap(true)
+                .css_classes(["dim-label"])
+                .build(),
+        );
+
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .child(&content)
+            .build();
+
+        let main_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(12)
+            .margin_start(24)
+            .margin_end(24)
+            .margin_top(24)
+            .margin_bottom(24)
+            .build();
+        main_box.append(&scrolled);
+
+        let close_button = Button::builder()
+            .label("Close")
+            .halign(gtk::Align::End)
+            .build();
+
+        let window_weak = self.downgrade();
+        close_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.close();
+            }
+        });
+        main_box.append(&close_button);
+
+        self.set_child(Some(&main_box));
+
+        let window_weak = self.downgrade();
+        imp.languages_chart
+            .set_draw_func(move |area, cr, width, _height| {
+                if let Some(window) = window_weak.upgrade() {
+                    let bars: Vec<Bar> = window
+                        .imp()
+                        .stats
+                        .borrow()
+                        .lookups_by_language
+                        .iter()
+                        .map(|(language, count)| Bar {
+                            label: language.clone(),
+                            value: *count as f64,
+                            value_label: count.to_string(),
+                        })
+                        .collect();
+                    draw_bars(area, cr, width as f64, &bars);
+                }
+            });
+
+        let window_weak = self.downgrade();
+        imp.most_read_chart
+            .set_draw_func(move |area, cr, width, _height| {
+                if let Some(window) = window_weak.upgrade() {
+                    let bars: Vec<Bar> = window
+                        .imp()
+                        .stats
+                        .borrow()
+                        .most_read
+                        .iter()
+                        .map(|document| Bar {
+                            label: document_name(&document.pdf_path),
+                            value: document.minutes as f64,
+                            value_label: format!(
+                                "{}, {} pages",
+                                library_stats::format_minutes(document.minutes),
+                                document.pages
+                            ),
+                        })
+                        .collect();
+                    draw_bars(area, cr, width as f64, &bars);
+                }
+            });
+
+        let window_weak = self.downgrade();
+        imp.daily_chart
+            .set_draw_func(move |area, cr, width, height| {
+                if let Some(window) = window_weak.upgrade() {
+                    draw_daily_chart(
+                        area,
+                        cr,
+                        width as f64,
+                        height as f64,
+                        &window.imp().stats.borrow().daily_minutes,
+                    );
+                }
+            });
+    }
+
+    fn section_heading(text: &str) -> Label {
+        Label::builder()
+            .label(text)
+            .halign(gtk::Align::Start)
+            .margin_top(12)
+            .css_classes(["heading"])
+            .build()
+    }
+
+    /// Show `stats`, redrawing the charts
+    pub fn set_stats(&self, stats: LibraryStats) {
+        let imp = self.imp();
+
+        while let Some(child) = imp.figures.first_child() {
+            imp.figures.remove(&child);
+        }
+        let total_minutes: u32 = stats.daily_minutes.iter().map(|(_, minutes)| minutes).sum();
+        let figures = [
+            (stats.documents.to_string(), "Documents".to_string()),
+            (stats.annotations.to_string(), "Annotations".to_string()),
+            (stats.lookups().to_string(), "Words looked up".to_string()),
+            (
+                library_stats::format_minutes(total_minutes),
+                format!("Read in the last {} days", library_stats::CHART_DAYS),
+            ),
+        ];
+        for (column, (value, caption)) in figures.into_iter().enumerate() {
+            let value_label = Label::builder()
+                .label(value)
+                .css_classes(["title-2"])
+                .build();
+            let caption_label = Label::builder()
+                .label(caption)
+                .wrap(true)
+                .justify(gtk::Justification::Center)
+                .css_classes(["dim-label"])
+                .build();
+            imp.figures.attach(&value_label, column as i32, 0, 1, 1);
+            imp.figures.attach(&caption_label, column as i32, 1, 1, 1);
+        }
+
+        let rows_height = |rows: usize| (rows as f64 * BAR_ROW_HEIGHT).ceil() as i32;
+        imp.languages_chart
+            .set_content_height(rows_height(stats.lookups_by_language.len()));
+        imp.languages_chart
+            .set_visible(!stats.lookups_by_language.is_empty());
+        imp.languages_empty
+            .set_visible(stats.lookups_by_language.is_empty());
+        imp.most_read_chart
+            .set_content_height(rows_height(stats.most_read.len()));
+        imp.most_read_chart.set_visible(!stats.most_read.is_empty());
+        imp.most_read_empty.set_visible(stats.most_read.is_empty());
+
+        imp.stats.replace(stats);
+        imp.languages_chart.queue_draw();
+        imp.most_read_chart.queue_draw();
+        imp.daily_chart.queue_draw();
+    }
+}
+
+/// File name of a document, the full path when it has none
+fn document_name(pdf_path: &str) -> String {
+    Path::new(pdf_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| pdf_path.to_string())
+}
+
+/// Set the source of `cr` to the text color of `area`, at `alpha` of its opacity
+fn set_text_color(area: &DrawingArea, cr: &gtk::cairo::Context, alpha: f64) {
+    let color = area.color();
+    cr.set_source_rgba(
+        color.red() as f64,
+        color.green() as f64,
+        color.blue() as f64,
+        color.alpha() as f64 * alpha,
+    );
+}
+
+/// A bar per row: its name on the left, then the bar, as long relative to the
+/// largest value, followed by its value
+fn draw_bars(area: &DrawingArea, cr: &gtk::cairo::Context, width: f64, bars: &[Bar]) {
+    let max_value = bars
+        .iter()
+        .map(|bar| bar.value)
+        .fold(0.0, f64::max)
+        .max(f64::EPSILON);
+    let label_width = width * BAR_LABEL_SHARE;
+
+    for (row, bar) in bars.iter().enumerate() {
+        let top = row as f64 * BAR_ROW_HEIGHT;
+
+        let label = area.create_pango_layout(Some(&bar.label));
+        label.set_width(((label_width - TEXT_GAP).max(0.0) as i32) * pango::SCALE);
+        label.set_ellipsize(pango::EllipsizeMode::Middle);
+        let (_, label_height) = label.pixel_size();
+        set_text_color(area, cr, 1.0);
+        cr.move_to(0.0, top + (BAR_ROW_HEIGHT - label_height as f64) / 2.0);
+        pangocairo::functions::show_layout(cr, &label);
+
+        let value = area.create_pango_layout(Some(&bar.value_label));
+        let (value_width, value_height) = value.pixel_size();
+        let room = (width - label_width - TEXT_GAP - value_width as f64).max(0.0);
+        let bar_width = (room * bar.value / max_value).max(1.0);
+        set_text_color(area, cr, BAR_ALPHA);
+        cr.rectangle(
+            label_width,
+            top + BAR_ROW_HEIGHT / 4.0,
+            bar_width,
+            BAR_ROW_HEIGHT / 2.0,
+        );
+        let _ = cr.fill();
+
+        set_text_color(area, cr, 1.0);
+        cr.move_to(
+            label_width + bar_width + TEXT_GAP,
+            top + (BAR_ROW_HEIGHT - value_height as f64) / 2.0,
+        );
+        pangocairo::functions::show_layout(cr, &value);
+    }
+}
+
+/// A column per day, as tall relative to the day read the most, with the day of
+/// the month under it and the minutes over it
+fn draw_daily_chart(
+    area: &DrawingArea,
+    cr: &gtk::cairo::Context,
+    width: f64,
+    height: f64,
+    days: &[(String, u32)],
+) {
+    if days.is_empty() {
+        return;
+    }
+    let max_minutes = days
+        .iter()
+        .map(|(_, minutes)| *minutes)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let column_width = width / days.len() as f64;
+    let text_height = area.create_pango_layout(Some("0")).pixel_size().1 as f64;
+    // Room for the day under the columns and the minutes over the tallest one
+    let chart_height = (height - 2.0 * (text_height + TEXT_GAP)).max(0.0);
+    let baseline = height - text_height - TEXT_GAP;
+
+    // Baseline
+    set_text_color(area, cr, BAR_ALPHA);
+    cr.set_line_width(1.0);
+    cr.move_to(0.0, baseline + 0.5);
+    cr.line_to(width, baseline + 0.5);
+    let _ = cr.stroke();
+
+    for (column, (day, minutes)) in days.iter().enumerate() {
+        let left = column as f64 * column_width;
+        let center = left + column_width / 2.0;
+        let bar_height = chart_height * *minutes as f64 / max_minutes as f64;
+
+        if *minutes > 0 {
+            set_text_color(area, cr, BAR_ALPHA);
+            cr.rectangle(
+                left + column_width * 0.2,
+                baseline - bar_height,
+                column_width * 0.6,
+                bar_height,
+            );
+            let _ = cr.fill();
+
+            let value = area.create_pango_layout(Some(&minutes.to_string()));
+            let value_width = value.pixel_size().0 as f64;
+            set_text_color(area, cr, 1.0);
+            cr.move_to(
+                center - value_width / 2.0,
+                baseline - bar_height - text_height - TEXT_GAP / 2.0,
+            );
+            pangocairo::functions::show_layout(cr, &value);
+        }
+
+        // Day of the month of `YYYY-MM-DD`
+        let label = area.create_pango_layout(Some(day.get(8..).unwrap_or(day)));
+        let label_width = label.pixel_size().0 as f64;
+        set_text_color(area, cr, 0.7);
+        cr.move_to(center - label_width / 2.0, baseline + TEXT_GAP);
+        pangocairo::functions::show_layout(cr, &label);
+    }
+}
@@ -1,23 +1,45 @@
+mod about_window;
 mod annotation_panel;
-mod definition_popover;
+pub mod definition_popover;
 mod eyers_header_bar;
 mod eyers_window;
+mod heat_map_window;
 mod highlight_overlay;
+mod history_window;
+mod library_stats_window;
 mod pdf_view;
 mod pendingkey_box;
+mod positions_window;
+mod properties_window;
+mod recent_files_popover;
+mod reflow_view;
 mod settings_window;
+mod snippet;
 mod status_bar;
+mod tab_bar;
 mod toc_panel;
 mod translation_panel;
+mod ui_font;
 
+pub use about_window::AboutWindow;
 pub use annotation_panel::AnnotationPanel;
 pub use definition_popover::DefinitionPopover;
 pub use eyers_header_bar::EyersHeaderBar;
 pub use eyers_window::EyersWindow;
-pub use highlight_overlay::{HighlightOverlay, HighlightRect};
-pub use pdf_view::PdfView;
+pub use heat_map_window::HeatMapWindow;
+pub use highlight_overlay::{BionicWord, HighlightOverlay, HighlightRect, InkMark, WordBoxes};
+pub use history_window::HistoryWindow;
+pub use library_stats_window::LibraryStatsWindow;
+pub use pdf_view::{LookupModifier, PdfView};
 pub use pendingkey_box::PendingKeyBox;
+pub use positions_window::PositionsWindow;
+pub use properties_window::PropertiesWindow;
+pub use recent_files_popover::RecentFilesPopover;
+pub use reflow_view::ReflowView;
 pub use settings_window::SettingsWindow;
+pub use snippet::render_snippet;
 pub use status_bar::StatusBar;
+pub use tab_bar::TabBar;
 pub use toc_panel::{TocMode, TocPanel};
 pub use translation_panel::TranslationPanel;
+pub use ui_font::UiFont;
@@ -1,25 +1,77 @@
 use glib::subclass::Signal;
 use glib::Properties;
+use gtk::gio;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::{Box, EventControllerMotion, GestureClick, GestureDrag, Orientation, Overlay, Picture};
-use pdfium_render::prelude::*;
+use pdfium_render::prelude::Pdfium;
 use std::cell::{Cell, RefCell};
-use std::collections::HashSet;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::OnceLock;
 
+use crate::backend::{
+    self, DocumentBackend, LinkTarget, OcrLayer, PageChar, PageLink, RecognizedPages,
+    RenderResult, RenderWorker, RenderedPage,
+};
 use crate::modes::WordCursor;
 use crate::services::bookmarks;
-use crate::services::dictionary::Language;
+use crate::services::crop::PageCrop;
+use crate::services::dictionary::{DictionarySource, Language};
 use crate::services::pdf_text::{
-    self, calculate_click_coordinates_with_offset, calculate_page_dimensions,
-    calculate_picture_offset, create_render_config_with_zoom, extract_word_at_index,
-    find_char_index_at_click,
+    self, MAX_ZOOM, MIN_ZOOM, RENDER_WIDTH, ZoomMode, calculate_click_coordinates_with_offset,
+    calculate_picture_offset, extract_word_at_index, find_char_index_at_click,
+    get_render_width_for_page, page_text, reference_page_width,
 };
+use crate::services::rotation::Rotation;
+use crate::services::theme::HighlightPalette;
 use crate::widgets::DefinitionPopover;
 use crate::widgets::HighlightOverlay;
+use crate::widgets::definition_popover;
+
+/// Gap between pages, vertically and side by side
+const PAGE_SPACING: i32 = 10;
+/// Room left around pages fitted to the view
+const FIT_MARGIN: i32 = 2 * PAGE_SPACING;
+
+/// Modifier that turns a click into a definition lookup, even while definitions
+/// are switched off in the header bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LookupModifier {
+    /// Only the header bar toggle triggers lookups
+    Off,
+    #[default]
+    Ctrl,
+    Alt,
+}
+
+impl LookupModifier {
+    /// All modifiers, in the order they are shown in the settings
+    pub const ALL: [LookupModifier; 3] = [
+        LookupModifier::Off,
+        LookupModifier::Ctrl,
+        LookupModifier::Alt,
+    ];
+
+    /// Human readable name for the settings dropdown
+    pub fn label(&self) -> &'static str {
+        match self {
+            LookupModifier::Off => "Off",
+            LookupModifier::Ctrl => "Ctrl+click",
+            LookupModifier::Alt => "Alt+click",
+        }
+    }
+
+    fn mask(&self) -> Option<gtk::gdk::ModifierType> {
+        match self {
+            LookupModifier::Off => None,
+            LookupModifier::Ctrl => Some(gtk::gdk::ModifierType::CONTROL_MASK),
+            LookupModifier::Alt => Some(gtk::gdk::ModifierType::ALT_MASK),
+        }
+    }
+}
 
 /// Represents a selection point in the PDF
 #[derive(Clone, Debug)]
@@ -37,7 +89,10 @@ mod imp {
     #[derive(Properties)]
     #[properties(wrapper_type = super::PdfView)]
     pub struct PdfView {
-        pub document: RefCell<Option<PdfDocument<'static>>>,
+        /// Shared with the split view showing the same document
+        pub document: RefCell<Option<Rc<dyn DocumentBackend>>>,
+        /// Text recognized on the document's scanned pages
+        pub recognized_pages: RefCell<RecognizedPages>,
         pub pdfium: RefCell<Option<&'static Pdfium>>,
         pub current_popover: RefCell<Option<DefinitionPopover>>,
         pub bookmarks: RefCell<Option<Vec<bookmarks::BookmarkEntry>>>,
@@ -46,7 +101,18 @@ mod imp {
         pub(super) highlight_overlays: RefCell<Vec<HighlightOverlay>>,
         /// Tracks which pages have been rendered at current zoom level
         pub(super) rendered_pages: RefCell<HashSet<usize>>,
+        /// Renders pages off the main thread; None falls back to rendering in place
+        pub(super) render_worker: RefCell<Option<RenderWorker>>,
+        /// Pages requested from the render worker and not shown yet
+        pub(super) pending_renders: RefCell<HashSet<usize>>,
+        /// Counts the render workers spawned, so pages of a replaced one are dropped
+        pub(super) render_worker_id: Cell<u64>,
+        /// Links of the pages looked at so far, loaded on first hover or click
+        pub(super) page_links: RefCell<HashMap<usize, Vec<PageLink>>>,
         pub selection_start: RefCell<Option<SelectionPoint>>,
+        /// Page and character of the last word looked up, where a Shift+click
+        /// phrase lookup starts
+        pub phrase_start: Cell<Option<(usize, usize)>>,
         pub current_page: Cell<u16>,
         pub total_pages: Cell<u16>,
         pub pending_update: Cell<bool>,
@@ -54,18 +120,70 @@ mod imp {
         pub visual_selection: RefCell<Option<(WordCursor, WordCursor)>>,
         /// Current zoom level (1.0 = 100%)
         pub zoom_level: Cell<f64>,
+        /// How the zoom level is chosen
+        pub zoom_mode: Cell<ZoomMode>,
+        /// Width in points of the pages shown at the zoom's render width, the
+        /// width most pages of the document have
+        pub reference_page_width: Cell<f64>,
+        /// Rotation of every page
+        pub document_rotation: Cell<Rotation>,
+        /// Pages turned on their own, on top of the document rotation
+        pub page_rotations: RefCell<HashMap<usize, Rotation>>,
+        /// Cut the blank margins found in the pixels of each page
+        #[property(get, set, default = false)]
+        pub auto_crop: Cell<bool>,
+        /// Margins found on the pages rendered so far
+        pub detected_crops: RefCell<HashMap<usize, PageCrop>>,
+        /// Margins of the first page found, used for the pages not rendered yet
+        pub estimated_crop: Cell<Option<PageCrop>>,
+        /// Margins set by hand for every page, over auto-crop
+        pub manual_crop: Cell<Option<PageCrop>>,
+        /// Colors of the theme the highlights are drawn with
+        pub highlight_palette: Cell<HighlightPalette>,
         #[property(get, set, default = false)]
         pub definitions_enabled: Cell<bool>,
         #[property(get, set, default = false)]
         pub translate_enabled: Cell<bool>,
+        /// Whether clicking outside a definition popover closes it
+        #[property(get, set, default = false)]
+        pub popover_autohide: Cell<bool>,
+        /// Whether Ctrl+click does a SyncTeX reverse search instead of a lookup
+        #[property(get, set, default = false)]
+        pub reverse_search_enabled: Cell<bool>,
+        /// Whether drags draw on the page, with clicks no longer following links or
+        /// looking up words
+        #[property(get, set, default = false)]
+        pub drawing: Cell<bool>,
+        /// Show pages in pairs side by side (facing pages)
+        #[property(get, set, default = false)]
+        pub two_page: Cell<bool>,
+        /// In two-page mode, keep the first page alone so later pairs start on an even page
+        #[property(get, set, default = false)]
+        pub cover_page: Cell<bool>,
+        /// Show one page (or pair of pages) at a time instead of scrolling through all
+        #[property(get, set, default = false)]
+        pub paged: Cell<bool>,
+        /// Page whose row is shown in paged mode
+        pub paged_page: Cell<usize>,
+        /// Modifier that makes a click look up the word under the pointer
+        pub lookup_modifier: Cell<LookupModifier>,
         /// Dictionary language (0=English, 1=Spanish)
         pub dictionary_language: Cell<Language>,
+        /// Where definitions are looked up
+        pub dictionary_source: RefCell<DictionarySource>,
+        /// Path of the open document, for its glossary
+        pub document_path: RefCell<Option<String>>,
+        /// Largest size definition popovers grow to when fitting their content
+        pub popover_max_size: Cell<(i32, i32)>,
+        /// Size the user last resized a definition popover to
+        pub popover_size: Cell<Option<(i32, i32)>>,
     }
 
     impl Default for PdfView {
         fn default() -> Self {
             Self {
                 document: RefCell::new(None),
+                recognized_pages: RefCell::new(RecognizedPages::default()),
                 pdfium: RefCell::new(None),
                 current_popover: RefCell::new(None),
                 bookmarks: RefCell::new(None),
@@ -73,16 +191,45 @@ mod imp {
                 page_overlays: RefCell::new(Vec::new()),
                 highlight_overlays: RefCell::new(Vec::new()),
                 rendered_pages: RefCell::new(HashSet::new()),
+                render_worker: RefCell::new(None),
+                pending_renders: RefCell::new(HashSet::new()),
+                render_worker_id: Cell::new(0),
+                page_links: RefCell::new(HashMap::new()),
                 selection_start: RefCell::new(None),
+                phrase_start: Cell::new(None),
                 current_page: Cell::new(0),
                 total_pages: Cell::new(0),
                 pending_update: Cell::new(false),
                 visual_cursor: RefCell::new(None),
                 visual_selection: RefCell::new(None),
                 zoom_level: Cell::new(1.0),
+                zoom_mode: Cell::new(ZoomMode::default()),
+                reference_page_width: Cell::new(1.0),
+                document_rotation: Cell::new(Rotation::NONE),
+                page_rotations: RefCell::new(HashMap::new()),
+                auto_crop: Cell::new(false),
+                detected_crops: RefCell::new(HashMap::new()),
+                estimated_crop: Cell::new(None),
+                manual_crop: Cell::new(None),
+                highlight_palette: Cell::new(HighlightPalette::default()),
                 definitions_enabled: Cell::new(false),
                 translate_enabled: Cell::new(false),
+                popover_autohide: Cell::new(false),
+                reverse_search_enabled: Cell::new(false),
+                drawing: Cell::new(false),
+                two_page: Cell::new(false),
+                cover_page: Cell::new(false),
+                paged: Cell::new(false),
+                paged_page: Cell::new(0),
+                lookup_modifier: Cell::new(LookupModifier::default()),
                 dictionary_language: Cell::new(Language::default()),
+                dictionary_source: RefCell::new(DictionarySource::default()),
+                document_path: RefCell::new(None),
+                popover_max_size: Cell::new((
+                    definition_popover::DEFAULT_MAX_WIDTH,
+                    definition_popover::DEFAULT_MAX_HEIGHT,
+                )),
+                popover_size: Cell::new(None),
             }
         }
     }
@@ -118,6 +265,40 @@ mod imp {
                         .param_types([f64::static_type(), f64::static_type()])
                         .build(),
                     Signal::builder("drag-ended").build(),
+                    // Pointer moved during a drag on a page, with the offset from where
+                    // the drag started
+                    Signal::builder("drag-updated")
+                        .param_types([f64::static_type(), f64::static_type()])
+                        .build(),
+                    Signal::builder("page-rendered")
+                        .param_types([u32::static_type()])
+                        .build(),
+                    // The margins cut off the pages changed, moving what is on them
+                    Signal::builder("crop-changed").build(),
+                    // Ctrl+click on a page with reverse search enabled, with the position in
+                    // PDF points
+                    Signal::builder("page-ctrl-clicked")
+                        .param_types([f64::static_type(), f64::static_type(), u32::static_type()])
+                        .build(),
+                    // Click on a link to a page of the document, with the target page index
+                    Signal::builder("link-activated")
+                        .param_types([u32::static_type()])
+                        .build(),
+                    // The pronunciation of a looked up word was asked for, with (word,
+                    // language code)
+                    Signal::builder("pronounce-requested")
+                        .param_types([String::static_type(), String::static_type()])
+                        .build(),
+                    // A definition was found for a word of the document, with (word,
+                    // definition markup, sentence around the word, page index)
+                    Signal::builder("word-looked-up")
+                        .param_types([
+                            String::static_type(),
+                            String::static_type(),
+                            String::static_type(),
+                            u32::static_type(),
+                        ])
+                        .build(),
                 ]
             })
         }
@@ -140,20 +321,36 @@ impl PdfView {
 
     fn setup_widgets(&self) {
         self.set_orientation(Orientation::Vertical);
-        self.set_spacing(10);
+        self.set_spacing(PAGE_SPACING);
         self.add_css_class("pdf-view");
         self.setup_scroll_tracking();
         self.setup_motion_tracking();
+
+        self.connect_two_page_notify(|view| view.relayout());
+        self.connect_cover_page_notify(|view| {
+            if view.two_page() {
+                view.relayout();
+            }
+        });
+        self.connect_paged_notify(|view| {
+            view.imp().paged_page.set(view.current_page() as usize);
+            view.relayout();
+        });
+        self.connect_auto_crop_notify(|view| view.crop_changed());
     }
 
     pub fn set_pdfium(&self, pdfium: &'static Pdfium) {
         self.imp().pdfium.replace(Some(pdfium));
     }
 
-    pub fn load_pdf(&self, path: PathBuf) -> Result<(), String> {
+    pub fn load_document(&self, path: PathBuf) -> Result<(), String> {
         self.clear();
         self.close_current_popover();
         self.imp().selection_start.replace(None);
+        self.imp().phrase_start.set(None);
+        self.imp()
+            .document_path
+            .replace(Some(path.to_string_lossy().to_string()));
 
         let pdfium = self
             .imp()
@@ -161,21 +358,113 @@ impl PdfView {
             .borrow()
             .ok_or_else(|| "Pdfium not initialized".to_string())?;
 
-        let document = pdfium
-            .load_pdf_from_file(&path, None)
-            .map_err(|e| format!("Failed to open PDF: {}", e))?;
+        let recognized_pages = RecognizedPages::default();
+        self.imp()
+            .recognized_pages
+            .replace(recognized_pages.clone());
+        let document = OcrLayer::new(
+            backend::open_document(pdfium, &path)?,
+            recognized_pages,
+        );
+        self.set_total_pages(document.page_count() as u16);
+        let widths = (0..document.page_count()).filter_map(|i| document.page_size(i).map(|s| s.0));
+        self.imp()
+            .reference_page_width
+            .set(reference_page_width(widths).unwrap_or(1.0));
+        self.imp().bookmarks.replace(Some(document.outline()));
 
-        self.set_total_pages(document.pages().len());
+        self.spawn_render_worker(pdfium, &path);
 
-        let entries = bookmarks::extract_bookmarks(&document);
-        self.imp().bookmarks.replace(Some(entries));
+        self.imp().document.replace(Some(Rc::new(document)));
+        // Fit modes fit the new pages, before anything is laid out at the old size
+        if let Some(zoom) = self.zoom_for_mode() {
+            self.imp().zoom_level.set(zoom);
+        }
+        self.render_pages();
 
-        self.imp().document.replace(Some(document));
+        Ok(())
+    }
+
+    /// Show the document `source` shows, at its zoom and rotation but scrolled
+    /// on its own. The document, its recognized text and outline are shared
+    /// rather than opened again; only rendering has its own worker.
+    pub fn share_document(&self, source: &PdfView) -> Result<(), String> {
+        let source_imp = source.imp();
+        let document = source_imp
+            .document
+            .borrow()
+            .clone()
+            .ok_or_else(|| "No document open".to_string())?;
+
+        self.clear();
+        self.close_current_popover();
+        let imp = self.imp();
+        imp.selection_start.replace(None);
+        imp.phrase_start.set(None);
+        imp.pdfium.replace(*source_imp.pdfium.borrow());
+        imp.document_path
+            .replace(source_imp.document_path.borrow().clone());
+        imp.recognized_pages
+            .replace(source_imp.recognized_pages.borrow().clone());
+        imp.bookmarks.replace(source_imp.bookmarks.borrow().clone());
+        imp.reference_page_width
+            .set(source_imp.reference_page_width.get());
+        imp.document_rotation.set(source.document_rotation());
+        imp.page_rotations
+            .replace(source_imp.page_rotations.borrow().clone());
+        imp.detected_crops
+            .replace(source_imp.detected_crops.borrow().clone());
+        imp.estimated_crop.set(source_imp.estimated_crop.get());
+        imp.manual_crop.set(source_imp.manual_crop.get());
+        imp.zoom_level.set(source.zoom_level());
+        imp.zoom_mode.set(source.zoom_mode());
+        imp.current_page.set(source.current_page());
+        self.set_total_pages(source.total_pages());
+        self.set_lookup_modifier(source.lookup_modifier());
+        self.set_dictionary_language(source.dictionary_language());
+        self.set_dictionary_source(source.dictionary_source());
+        let (max_width, max_height) = source.popover_max_size();
+        self.set_popover_max_size(max_width, max_height);
+
+        let path = imp.document_path.borrow().clone();
+        if let (Some(pdfium), Some(path)) = (*imp.pdfium.borrow(), path) {
+            self.spawn_render_worker(pdfium, Path::new(&path));
+        }
+
+        imp.document.replace(Some(document));
         self.render_pages();
 
         Ok(())
     }
 
+    /// Look up words, crop and draw highlights like `source` does, for a view
+    /// of another document opened next to it
+    pub fn copy_settings(&self, source: &PdfView) {
+        let source_imp = source.imp();
+        self.imp().pdfium.replace(*source_imp.pdfium.borrow());
+        self.set_highlight_palette(source_imp.highlight_palette.get());
+        self.set_lookup_modifier(source.lookup_modifier());
+        self.set_dictionary_language(source.dictionary_language());
+        self.set_dictionary_source(source.dictionary_source());
+        let (max_width, max_height) = source.popover_max_size();
+        self.set_popover_max_size(max_width, max_height);
+        self.set_popover_autohide(source.popover_autohide());
+        self.set_cover_page(source.cover_page());
+        self.set_auto_crop(source.auto_crop());
+    }
+
+    /// Render pages on a thread of their own, which opens the document again
+    /// since pdfium documents can't be shared between threads
+    fn spawn_render_worker(&self, pdfium: &'static Pdfium, path: &Path) {
+        let render_path = path.to_path_buf();
+        let worker = RenderWorker::spawn(std::boxed::Box::new(move || {
+            backend::open_document(pdfium, &render_path).ok()
+        }));
+        let imp = self.imp();
+        imp.render_worker_id.set(imp.render_worker_id.get() + 1);
+        imp.render_worker.replace(Some(worker));
+    }
+
     fn clear(&self) {
         while let Some(child) = self.first_child() {
             self.remove(&child);
@@ -184,17 +473,26 @@ impl PdfView {
         self.imp().page_overlays.borrow_mut().clear();
         self.imp().highlight_overlays.borrow_mut().clear();
         self.imp().rendered_pages.borrow_mut().clear();
+        self.imp().pending_renders.borrow_mut().clear();
+        self.imp().page_links.borrow_mut().clear();
+        self.imp().document_rotation.set(Rotation::NONE);
+        self.imp().page_rotations.borrow_mut().clear();
+        self.imp().detected_crops.borrow_mut().clear();
+        self.imp().estimated_crop.set(None);
+        self.imp().manual_crop.set(None);
+        self.imp().paged_page.set(0);
     }
 
     /// Calculate page dimensions at current zoom level without rendering
-    fn calculate_page_size(&self, page: &PdfPage) -> (i32, i32) {
-        let zoom = self.imp().zoom_level.get();
-        let render_width = crate::services::pdf_text::get_render_width_for_zoom(zoom);
-        let page_width_pts = page.width().value as f64;
-        let page_height_pts = page.height().value as f64;
+    fn calculate_page_size(&self, doc: &dyn DocumentBackend, page_index: usize) -> (i32, i32) {
+        let (page_width_pts, page_height_pts) = doc.page_size(page_index).unwrap_or((1.0, 1.0));
+        let render_width = self.render_width_for(page_width_pts);
         let scale = render_width as f64 / page_width_pts;
         let height = (page_height_pts * scale) as i32;
-        (render_width, height)
+        let (_, _, width, height) = self
+            .page_crop(page_index)
+            .pixel_rect((render_width, height), scale);
+        self.page_rotation(page_index).size((width, height))
     }
 
     /// Create a placeholder Picture with the correct size (no pixel allocation)
@@ -223,8 +521,8 @@ impl PdfView {
         let mut page_overlays = Vec::new();
         let mut highlight_overlays = Vec::new();
 
-        for (index, page) in doc.pages().iter().enumerate() {
-            let (width, height) = self.calculate_page_size(&page);
+        for index in 0..doc.page_count() {
+            let (width, height) = self.calculate_page_size(doc.as_ref(), index);
 
             // Create placeholder picture
             let picture = self.create_placeholder(width, height);
@@ -233,15 +531,16 @@ impl PdfView {
             let highlight = HighlightOverlay::new();
             highlight.set_content_width(width);
             highlight.set_content_height(height);
+            highlight.set_palette(self.imp().highlight_palette.get());
 
-            // Wrap in overlay
-            let overlay = Overlay::new();
+            // Wrap in overlay, kept at the top of rows taller than the page
+            let overlay = Overlay::builder().valign(gtk::Align::Start).build();
             overlay.set_child(Some(&picture));
             overlay.add_overlay(&highlight);
 
             self.setup_page_gesture(&picture, index);
             self.setup_page_drag_gesture(&picture, index);
-            self.append(&overlay);
+            self.setup_page_link_hover(&picture, index);
 
             page_pictures.push(picture);
             page_overlays.push(overlay);
@@ -255,10 +554,188 @@ impl PdfView {
 
         drop(doc_borrow);
 
+        self.arrange_pages();
+
         // Render visible pages immediately
         self.render_visible_pages();
     }
 
+    /// Put the pages in the view, one per row or in pairs in two-page mode
+    fn arrange_pages(&self) {
+        let page_overlays = self.imp().page_overlays.borrow();
+
+        // Detach the pages from their rows before dropping the rows
+        for overlay in page_overlays.iter() {
+            overlay.unparent();
+        }
+        while let Some(child) = self.first_child() {
+            self.remove(&child);
+        }
+
+        if !self.two_page() {
+            for overlay in page_overlays.iter() {
+                self.append(overlay);
+            }
+            drop(page_overlays);
+            self.update_shown_pages();
+            return;
+        }
+
+        let mut row: Option<Box> = None;
+        for (page_index, overlay) in page_overlays.iter().enumerate() {
+            let starts_row =
+                page_index == 0 || self.page_row(page_index - 1) != self.page_row(page_index);
+            if starts_row {
+                let new_row = Box::builder()
+                    .orientation(Orientation::Horizontal)
+                    .spacing(PAGE_SPACING)
+                    .halign(gtk::Align::Center)
+                    .build();
+                new_row.add_css_class("pdf-page-row");
+                self.append(&new_row);
+                row = Some(new_row);
+            }
+            if let Some(row) = &row {
+                row.append(overlay);
+            }
+        }
+
+        drop(page_overlays);
+        self.update_shown_pages();
+    }
+
+    /// In paged mode hide every page but those in the row of the shown page
+    fn update_shown_pages(&self) {
+        let page_overlays = self.imp().page_overlays.borrow();
+        for (page_index, overlay) in page_overlays.iter().enumerate() {
+            let shown = self.is_page_shown(page_index);
+            overlay.set_visible(shown);
+            // Rows of the two-page layout go with their pages
+            if let Some(row) = overlay
+                .parent()
+                .filter(|parent| parent != self.upcast_ref::<gtk::Widget>())
+            {
+                row.set_visible(shown);
+            }
+        }
+    }
+
+    /// Whether a page is laid out, false for the pages paged mode hides
+    pub fn is_page_shown(&self, page_index: usize) -> bool {
+        !self.paged() || self.page_row(page_index) == self.page_row(self.imp().paged_page.get())
+    }
+
+    /// Page whose row is shown in paged mode
+    pub fn paged_page(&self) -> usize {
+        self.imp().paged_page.get()
+    }
+
+    /// Show pages one row at a time starting at `page_index`, or all of them again
+    /// with None
+    pub fn set_paged_page(&self, page_index: Option<usize>) {
+        let imp = self.imp();
+        if let Some(page_index) = page_index {
+            imp.current_page.set(page_index as u16);
+            imp.paged_page.set(page_index);
+        }
+
+        if self.paged() != page_index.is_some() {
+            // Lays the pages out again at the current page
+            self.set_paged(page_index.is_some());
+        } else if self.paged() {
+            self.update_shown_pages();
+        }
+    }
+
+    /// Go to the start of the next or previous row of pages. In paged mode only
+    /// that row is shown.
+    pub fn flip_page(&self, forward: bool) {
+        let current_page = if self.paged() {
+            self.paged_page()
+        } else {
+            self.current_page() as usize
+        };
+        let row = self.page_row(current_page);
+        let target_row = if forward {
+            row + 1
+        } else if row > 0 {
+            row - 1
+        } else {
+            return;
+        };
+
+        if let Some(page_index) = (0..self.page_count()).find(|&i| self.page_row(i) == target_row) {
+            self.scroll_to_page(page_index as u16);
+        }
+    }
+
+    /// Rebuild the rows after a layout change, staying on the current page
+    fn relayout(&self) {
+        if self.imp().page_overlays.borrow().is_empty() {
+            return;
+        }
+
+        let current_page = self.current_page();
+        self.arrange_pages();
+
+        let view_weak = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(view) = view_weak.upgrade() {
+                view.scroll_to_page(current_page);
+                view.render_visible_pages();
+            }
+        });
+    }
+
+    /// Row of the layout a page is shown in
+    pub fn page_row(&self, page_index: usize) -> usize {
+        if !self.two_page() {
+            return page_index;
+        }
+        if self.cover_page() {
+            page_index.div_ceil(2)
+        } else {
+            page_index / 2
+        }
+    }
+
+    /// Top of each page in the view, None for the pages paged mode hides. Pages of
+    /// a row share its top, the row being as tall as its tallest page.
+    pub fn page_tops(&self) -> Vec<Option<f64>> {
+        let page_pictures = self.imp().page_pictures.borrow();
+        let heights: Vec<Option<f64>> = page_pictures
+            .iter()
+            .enumerate()
+            .map(|(i, picture)| {
+                self.is_page_shown(i)
+                    .then(|| picture.preferred_size().1.height() as f64)
+            })
+            .collect();
+        let rows: Vec<usize> = (0..heights.len()).map(|i| self.page_row(i)).collect();
+        pdf_text::page_tops(&heights, &rows, PAGE_SPACING as f64)
+    }
+
+    /// Width a page is rendered at before rotation, following its own size
+    pub fn page_render_width(&self, page_index: usize) -> i32 {
+        let page_width_pts = self
+            .imp()
+            .document
+            .borrow()
+            .as_ref()
+            .and_then(|doc| doc.page_size(page_index))
+            .map_or(self.imp().reference_page_width.get(), |size| size.0);
+        self.render_width_for(page_width_pts)
+    }
+
+    /// Render width at the current zoom of a page `page_width_pts` wide
+    fn render_width_for(&self, page_width_pts: f64) -> i32 {
+        get_render_width_for_page(
+            self.zoom_level(),
+            page_width_pts,
+            self.imp().reference_page_width.get(),
+        )
+    }
+
     /// Render only the pages that are currently visible (plus a small buffer)
     pub fn render_visible_pages(&self) {
         let visible_range = match self.get_visible_page_range() {
@@ -266,6 +743,11 @@ impl PdfView {
             None => return,
         };
 
+        if self.imp().render_worker.borrow().is_some() {
+            self.request_pages(visible_range);
+            return;
+        }
+
         let doc_borrow = self.imp().document.borrow();
         let doc = match doc_borrow.as_ref() {
             Some(d) => d,
@@ -277,26 +759,171 @@ impl PdfView {
         let page_overlays = self.imp().page_overlays.borrow();
         let highlight_overlays = self.imp().highlight_overlays.borrow();
 
+        let mut newly_rendered = Vec::new();
+
         // Render pages in visible range that haven't been rendered yet
         for page_index in visible_range {
             if rendered.contains(&page_index) {
                 continue; // Already rendered
             }
 
-            if let Ok(page) = doc.pages().get(page_index as u16) {
-                if let Some(picture) = page_pictures.get(page_index) {
-                    if let Some(overlay) = page_overlays.get(page_index) {
-                        if let Some(highlight) = highlight_overlays.get(page_index) {
-                            // Render the page
-                            self.render_page_content(
-                                &page, page_index, picture, overlay, highlight,
-                            );
-                            rendered.insert(page_index);
-                        }
+            if let Some(picture) = page_pictures.get(page_index) {
+                if let Some(overlay) = page_overlays.get(page_index) {
+                    if let Some(highlight) = highlight_overlays.get(page_index) {
+                        // Render the page
+                        self.render_page_content(
+                            doc.as_ref(),
+                            page_index,
+                            picture,
+                            overlay,
+                            highlight,
+                        );
+                        rendered.insert(page_index);
+                        newly_rendered.push(page_index);
                     }
                 }
             }
         }
+
+        drop(rendered);
+        drop(page_pictures);
+        drop(page_overlays);
+        drop(highlight_overlays);
+        drop(doc_borrow);
+
+        for page_index in newly_rendered {
+            self.emit_by_name::<()>("page-rendered", &[&(page_index as u32)]);
+        }
+    }
+
+    /// Queue the pages on the render worker. They replace their placeholders as
+    /// they finish, so scrolling never waits for pdfium.
+    fn request_pages(&self, visible_range: std::ops::RangeInclusive<usize>) {
+        let imp = self.imp();
+        let worker_borrow = imp.render_worker.borrow();
+        let worker = match worker_borrow.as_ref() {
+            Some(w) => w,
+            None => return,
+        };
+        let rendered = imp.rendered_pages.borrow();
+        let mut pending = imp.pending_renders.borrow_mut();
+
+        // Drop pages scrolled past so the visible ones don't wait behind them
+        let queued = pending.len();
+        pending.retain(|page_index| visible_range.contains(page_index));
+        if pending.len() != queued {
+            worker.cancel_pending();
+            let mut requeued: Vec<usize> = pending.iter().copied().collect();
+            requeued.sort_unstable();
+            for page_index in requeued {
+                worker.request(page_index, self.page_render_width(page_index));
+            }
+        }
+
+        for page_index in visible_range {
+            if rendered.contains(&page_index) || !pending.insert(page_index) {
+                continue;
+            }
+            worker.request(page_index, self.page_render_width(page_index));
+        }
+
+        let any_pending = !pending.is_empty();
+        drop(pending);
+        drop(rendered);
+        drop(worker_borrow);
+
+        if any_pending {
+            self.receive_rendered_pages();
+        }
+    }
+
+    /// Show finished pages as the render worker sends them, until nothing is
+    /// pending anymore
+    fn receive_rendered_pages(&self) {
+        let imp = self.imp();
+        let worker_id = imp.render_worker_id.get();
+        // Taken already while pages are being received
+        let results = imp
+            .render_worker
+            .borrow_mut()
+            .as_mut()
+            .and_then(RenderWorker::take_results);
+        let Some(results) = results else {
+            return;
+        };
+
+        // Each page is waited for on a worker thread and shown back on the main loop
+        let view_weak = self.downgrade();
+        glib::spawn_future_local(async move {
+            let mut results = results;
+            loop {
+                let Ok((result, returned)) =
+                    gio::spawn_blocking(move || (results.recv(), results)).await
+                else {
+                    return;
+                };
+                results = returned;
+                let Some(view) = view_weak.upgrade() else {
+                    return;
+                };
+                let imp = view.imp();
+                // Replaced by the worker of another document meanwhile
+                if imp.render_worker_id.get() != worker_id {
+                    return;
+                }
+
+                let Ok(result) = result else {
+                    // The worker could not open the document, render in place instead
+                    imp.render_worker.replace(None);
+                    imp.pending_renders.borrow_mut().clear();
+                    view.render_visible_pages();
+                    return;
+                };
+                view.show_rendered_page(result);
+
+                if imp.pending_renders.borrow().is_empty() {
+                    if let Some(worker) = imp.render_worker.borrow_mut().as_mut() {
+                        worker.return_results(results);
+                    }
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Put a page finished by the render worker in place of its placeholder
+    fn show_rendered_page(&self, result: RenderResult) {
+        let imp = self.imp();
+
+        // Rendered before a zoom change, a newer request is on its way
+        if result.width != self.page_render_width(result.page_index) {
+            return;
+        }
+        if !imp.pending_renders.borrow_mut().remove(&result.page_index) {
+            return;
+        }
+        let Some(rendered) = result.page else {
+            return;
+        };
+
+        let picture = imp.page_pictures.borrow().get(result.page_index).cloned();
+        let highlight = imp
+            .highlight_overlays
+            .borrow()
+            .get(result.page_index)
+            .cloned();
+        let (Some(picture), Some(highlight)) = (picture, highlight) else {
+            return;
+        };
+
+        self.show_texture(result.page_index, rendered, &picture, &highlight);
+        imp.rendered_pages.borrow_mut().insert(result.page_index);
+        self.emit_by_name::<()>("page-rendered", &[&(result.page_index as u32)]);
+    }
+
+    /// Indices of the pages rendered at the current zoom level
+    pub fn rendered_pages(&self) -> Vec<usize> {
+        self.imp().rendered_pages.borrow().iter().copied().collect()
     }
 
     /// Get the range of pages currently visible (with buffer)
@@ -311,7 +938,7 @@ impl PdfView {
             return None;
         }
 
-        let spacing = 10.0;
+        let page_tops = self.page_tops();
         let mut first_visible: Option<usize> = None;
         let mut last_visible: Option<usize> = None;
 
@@ -319,7 +946,9 @@ impl PdfView {
             let nat_size = picture.preferred_size().1;
             let picture_height = nat_size.height() as f64;
 
-            let page_top = index as f64 * (picture_height + spacing);
+            let Some(page_top) = page_tops[index] else {
+                continue;
+            };
             let page_bottom = page_top + picture_height;
 
             // Check if page intersects with viewport
@@ -345,48 +974,67 @@ impl PdfView {
     /// Render actual content for a specific page
     fn render_page_content(
         &self,
-        page: &PdfPage,
+        doc: &dyn DocumentBackend,
         page_index: usize,
         picture: &Picture,
         _overlay: &Overlay,
         highlight: &HighlightOverlay,
     ) {
-        let zoom = self.imp().zoom_level.get();
-        let config = create_render_config_with_zoom(zoom);
-
-        let bitmap = match page.render_with_config(&config) {
-            Ok(b) => b,
-            Err(_) => return,
+        let rendered = match doc.render_page(page_index, self.page_render_width(page_index)) {
+            Some(r) => r,
+            None => return,
         };
 
-        let dimensions = calculate_page_dimensions(&bitmap);
-        let texture = self.create_texture_from_bitmap(&bitmap, &dimensions);
+        self.show_texture(page_index, rendered, picture, highlight);
+    }
+
+    fn show_texture(
+        &self,
+        page_index: usize,
+        rendered: RenderedPage,
+        picture: &Picture,
+        highlight: &HighlightOverlay,
+    ) {
+        let imp = self.imp();
+        let page_width_pts = imp
+            .document
+            .borrow()
+            .as_ref()
+            .and_then(|doc| doc.page_size(page_index))
+            .map_or(imp.reference_page_width.get(), |size| size.0);
+        let scale = rendered.width as f64 / page_width_pts;
+        if self.auto_crop() && !imp.detected_crops.borrow().contains_key(&page_index) {
+            self.detect_crop(page_index, &rendered, scale);
+        }
+
+        // Pages are always rendered whole and upright, and cropped and turned here
+        let rendered = self.page_crop(page_index).crop_page(rendered, scale);
+        let rendered = self.page_rotation(page_index).rotate_page(rendered);
+        let texture = self.create_texture_from_rendered(&rendered);
 
         // Update the picture's paintable and remove placeholder styling
         picture.set_paintable(Some(&texture));
         picture.remove_css_class("pdf-placeholder");
+        // The crop found on the page may differ from the one it was laid out with
+        picture.set_width_request(rendered.width);
+        picture.set_height_request(rendered.height);
 
         // Update highlight overlay size (in case it changed)
-        highlight.set_content_width(dimensions.width);
-        highlight.set_content_height(dimensions.height);
+        highlight.set_content_width(rendered.width);
+        highlight.set_content_height(rendered.height);
 
         println!("Rendered page {}", page_index);
     }
 
-    fn create_texture_from_bitmap(
-        &self,
-        bitmap: &PdfBitmap,
-        config: &pdf_text::PageRenderConfig,
-    ) -> gtk::gdk::MemoryTexture {
-        let bytes = bitmap.as_raw_bytes();
-        let bytes_glib = glib::Bytes::from(&bytes);
+    fn create_texture_from_rendered(&self, rendered: &RenderedPage) -> gtk::gdk::MemoryTexture {
+        let bytes_glib = glib::Bytes::from(&rendered.pixels);
 
         gtk::gdk::MemoryTexture::new(
-            config.width,
-            config.height,
+            rendered.width,
+            rendered.height,
             gtk::gdk::MemoryFormat::B8g8r8a8,
             &bytes_glib,
-            config.stride,
+            rendered.stride,
         )
     }
 
@@ -394,9 +1042,9 @@ impl PdfView {
         let gesture = GestureClick::new();
         let view_weak = self.downgrade();
 
-        gesture.connect_pressed(move |_, _, x, y| {
+        gesture.connect_pressed(move |gesture, _, x, y| {
             if let Some(view) = view_weak.upgrade() {
-                view.handle_page_click(x, y, page_index);
+                view.handle_page_click(x, y, page_index, gesture.current_event_state());
             }
         });
 
@@ -410,6 +1058,10 @@ impl PdfView {
         let view_weak_begin = view_weak.clone();
         gesture.connect_drag_begin(move |_, start_x, start_y| {
             if let Some(view) = view_weak_begin.upgrade() {
+                // A press on a link follows it instead of starting a selection
+                if !view.drawing() && view.link_at(start_x, start_y, page_index).is_some() {
+                    return;
+                }
                 view.emit_by_name::<()>(
                     "drag-started",
                     &[&start_x, &start_y, &(page_index as u32)],
@@ -417,6 +1069,13 @@ impl PdfView {
             }
         });
 
+        let view_weak_update = view_weak.clone();
+        gesture.connect_drag_update(move |_, offset_x, offset_y| {
+            if let Some(view) = view_weak_update.upgrade() {
+                view.emit_by_name::<()>("drag-updated", &[&offset_x, &offset_y]);
+            }
+        });
+
         gesture.connect_drag_end(move |_, _offset_x, _offset_y| {
             if let Some(view) = view_weak.upgrade() {
                 view.emit_by_name::<()>("drag-ended", &[]);
@@ -426,27 +1085,172 @@ impl PdfView {
         picture.add_controller(gesture);
     }
 
-    fn handle_page_click(&self, x: f64, y: f64, page_index: usize) {
+    fn handle_page_click(
+        &self,
+        x: f64,
+        y: f64,
+        page_index: usize,
+        modifiers: gtk::gdk::ModifierType,
+    ) {
+        // Presses are the start of drawings
+        if self.drawing() {
+            return;
+        }
+
+        // SyncTeX reverse search keeps Ctrl+click on documents that have SyncTeX data
+        if self.reverse_search_enabled() && modifiers.contains(gtk::gdk::ModifierType::CONTROL_MASK)
+        {
+            self.handle_page_ctrl_click(x, y, page_index);
+            return;
+        }
+
         // Close any existing popover first
         self.close_current_popover();
 
-        if self.definitions_enabled() {
-            self.handle_definition_click(x, y, page_index);
+        let lookup_click = self
+            .lookup_modifier()
+            .mask()
+            .is_some_and(|mask| modifiers.contains(mask));
+
+        // Links are followed unless the click is a lookup
+        if let Some(target) = self.link_at(x, y, page_index).filter(|_| !lookup_click) {
+            self.follow_link(target);
+            return;
+        }
+
+        if lookup_click || self.definitions_enabled() {
+            let phrase = modifiers.contains(gtk::gdk::ModifierType::SHIFT_MASK);
+            self.handle_definition_click(x, y, page_index, phrase);
         } else if self.translate_enabled() {
             self.handle_translate_click(x, y, page_index);
         }
     }
 
-    fn handle_definition_click(&self, x: f64, y: f64, page_index: usize) {
+    /// Show a pointer cursor while hovering a link
+    fn setup_page_link_hover(&self, picture: &Picture, page_index: usize) {
+        let motion_controller = EventControllerMotion::new();
+        let view_weak = self.downgrade();
+
+        motion_controller.connect_motion(move |controller, x, y| {
+            let Some(view) = view_weak.upgrade() else {
+                return;
+            };
+            let cursor = view.link_at(x, y, page_index).map(|_| "pointer");
+            if let Some(widget) = controller.widget() {
+                widget.set_cursor_from_name(cursor);
+            }
+        });
+
+        motion_controller.connect_leave(|controller| {
+            if let Some(widget) = controller.widget() {
+                widget.set_cursor_from_name(None);
+            }
+        });
+
+        picture.add_controller(motion_controller);
+    }
+
+    /// Target of the link under a point of a page picture
+    fn link_at(&self, x: f64, y: f64, page_index: usize) -> Option<LinkTarget> {
+        let imp = self.imp();
+        let click = {
+            let doc_borrow = imp.document.borrow();
+            let doc = doc_borrow.as_ref()?;
+            let page_size = doc.page_size(page_index)?;
+            let picture = self.page_picture(page_index as u16)?;
+            let offset = calculate_picture_offset(&picture);
+
+            imp.page_links
+                .borrow_mut()
+                .entry(page_index)
+                .or_insert_with(|| doc.links(page_index));
+
+            calculate_click_coordinates_with_offset(
+                x,
+                y,
+                page_size,
+                offset,
+                self.render_width_for(page_size.0),
+                self.page_rotation(page_index),
+                self.page_crop(page_index),
+            )
+        };
+
+        let (pdf_x, pdf_y) = (click.pdf_x as f32, click.pdf_y as f32);
+        imp.page_links
+            .borrow()
+            .get(&page_index)?
+            .iter()
+            .find(|link| {
+                link.bounds.left().value <= pdf_x
+                    && pdf_x <= link.bounds.right().value
+                    && link.bounds.bottom().value <= pdf_y
+                    && pdf_y <= link.bounds.top().value
+            })
+            .map(|link| link.target.clone())
+    }
+
+    /// Jump to the target page of an internal link, or open an external one in the browser
+    fn follow_link(&self, target: LinkTarget) {
+        match target {
+            LinkTarget::Page { page_index } => {
+                self.emit_by_name::<()>("link-activated", &[&(page_index as u32)]);
+            }
+            LinkTarget::Uri(uri) => {
+                let window = self.root().and_downcast::<gtk::Window>();
+                gtk::UriLauncher::new(&uri).launch(
+                    window.as_ref(),
+                    None::<&gtk::gio::Cancellable>,
+                    move |result| {
+                        if let Err(e) = result {
+                            eprintln!("Failed to open {}: {}", uri, e);
+                        }
+                    },
+                );
+            }
+        }
+    }
+
+    fn handle_page_ctrl_click(&self, x: f64, y: f64, page_index: usize) {
+        let click = {
+            let doc_borrow = self.imp().document.borrow();
+            let Some(page_size) = doc_borrow.as_ref().and_then(|doc| doc.page_size(page_index))
+            else {
+                return;
+            };
+            let Some(picture) = self.page_picture(page_index as u16) else {
+                return;
+            };
+            let offset = calculate_picture_offset(&picture);
+            calculate_click_coordinates_with_offset(
+                x,
+                y,
+                page_size,
+                offset,
+                self.render_width_for(page_size.0),
+                self.page_rotation(page_index),
+                self.page_crop(page_index),
+            )
+        };
+
+        self.emit_by_name::<()>(
+            "page-ctrl-clicked",
+            &[&click.pdf_x, &click.pdf_y, &(page_index as u32)],
+        );
+    }
+
+    /// Look up the word under the click, or with `phrase` the words from the last
+    /// looked up one to it
+    fn handle_definition_click(&self, x: f64, y: f64, page_index: usize, phrase: bool) {
         let doc_borrow = self.imp().document.borrow();
         let doc = match doc_borrow.as_ref() {
             Some(d) => d,
             None => return,
         };
 
-        let page = match doc.pages().get(page_index as u16) {
-            Ok(p) => p,
-            Err(_) => return,
+        let page_size = match doc.page_size(page_index) {
+            Some(size) => size,
+            None => return,
         };
 
         let page_pictures = self.imp().page_pictures.borrow();
@@ -456,24 +1260,35 @@ impl PdfView {
         };
 
         let offset = calculate_picture_offset(picture);
-        let zoom = self.zoom_level();
-        let click = calculate_click_coordinates_with_offset(x, y, &page, offset, zoom);
-
-        self.process_definition_click(&page, &click, picture);
+        let render_width = self.render_width_for(page_size.0);
+        let rotation = self.page_rotation(page_index);
+        let click = calculate_click_coordinates_with_offset(
+            x,
+            y,
+            page_size,
+            offset,
+            render_width,
+            rotation,
+            self.page_crop(page_index),
+        );
+
+        self.process_definition_click(doc.as_ref(), page_index, &click, picture, phrase);
     }
 
     fn process_definition_click(
         &self,
-        page: &PdfPage,
+        doc: &dyn DocumentBackend,
+        page_index: usize,
         click: &pdf_text::ClickData,
         picture: &Picture,
+        phrase: bool,
     ) {
-        let text_page = match page.text() {
-            Ok(tp) => tp,
-            Err(_) => return,
+        let chars = match doc.page_chars(page_index) {
+            Some(chars) => chars,
+            None => return,
         };
 
-        let char_idx = match find_char_index_at_click(&text_page, click) {
+        let char_idx = match find_char_index_at_click(&chars, click) {
             Some(idx) => idx,
             None => {
                 println!("No character found near click.");
@@ -481,11 +1296,46 @@ impl PdfView {
             }
         };
 
-        let full_text = text_page.all();
+        let full_text = page_text(&chars);
+
+        // Phrases don't span pages; the start stays for another end to be tried
+        let phrase_start = self
+            .imp()
+            .phrase_start
+            .get()
+            .filter(|(start_page, _)| phrase && *start_page == page_index);
+        let context = pdf_text::extract_sentence_at(&full_text, char_idx).unwrap_or_default();
+        if let Some((_, start_idx)) = phrase_start {
+            let popover = self.create_definition_popover();
+            self.track_lookup(&popover, page_index, context);
+            popover.show_at(picture, click.screen_x, click.screen_y);
+            match pdf_text::extract_phrase_between(&full_text, start_idx, char_idx) {
+                Some(phrase) => popover.fetch_and_display_phrase(
+                    phrase.original,
+                    phrase.lowercase,
+                    self.dictionary_language(),
+                    self.dictionary_source(),
+                ),
+                None => popover.show_message(&format!(
+                    "Phrases span up to {} words from the last word looked up.",
+                    pdf_text::PHRASE_MAX_WORDS
+                )),
+            }
+            self.imp().current_popover.replace(Some(popover));
+            return;
+        }
+
+        self.imp().phrase_start.set(Some((page_index, char_idx)));
         if let Some(word) = extract_word_at_index(&full_text, char_idx) {
-            let popover = DefinitionPopover::new();
+            let popover = self.create_definition_popover();
+            self.track_lookup(&popover, page_index, context);
             popover.show_at(picture, click.screen_x, click.screen_y);
-            popover.fetch_and_display(word.original, word.lowercase, self.dictionary_language());
+            popover.fetch_and_display(
+                word.original,
+                word.lowercase,
+                self.dictionary_language(),
+                self.dictionary_source(),
+            );
 
             self.imp().current_popover.replace(Some(popover));
         }
@@ -498,9 +1348,9 @@ impl PdfView {
             None => return,
         };
 
-        let page = match doc.pages().get(page_index as u16) {
-            Ok(p) => p,
-            Err(_) => return,
+        let page_size = match doc.page_size(page_index) {
+            Some(size) => size,
+            None => return,
         };
 
         let page_pictures = self.imp().page_pictures.borrow();
@@ -510,15 +1360,24 @@ impl PdfView {
         };
 
         let offset = calculate_picture_offset(picture);
-        let zoom = self.zoom_level();
-        let click = calculate_click_coordinates_with_offset(x, y, &page, offset, zoom);
-
-        let text_page = match page.text() {
-            Ok(tp) => tp,
-            Err(_) => return,
+        let render_width = self.render_width_for(page_size.0);
+        let rotation = self.page_rotation(page_index);
+        let click = calculate_click_coordinates_with_offset(
+            x,
+            y,
+            page_size,
+            offset,
+            render_width,
+            rotation,
+            self.page_crop(page_index),
+        );
+
+        let page_chars = match doc.page_chars(page_index) {
+            Some(chars) => chars,
+            None => return,
         };
 
-        let char_idx = match find_char_index_at_click(&text_page, &click) {
+        let char_idx = match find_char_index_at_click(&page_chars, &click) {
             Some(idx) => idx,
             None => {
                 println!("No character found near click.");
@@ -526,7 +1385,7 @@ impl PdfView {
             }
         };
 
-        let full_text = text_page.all();
+        let full_text = page_text(&page_chars);
         let word_info = match extract_word_at_index(&full_text, char_idx) {
             Some(w) => w,
             None => return,
@@ -592,16 +1451,15 @@ impl PdfView {
             //TODO: find if you can stop the scroll of mouse so it can set value of adjustment
             //right
             let adjustment = scrolled.vadjustment();
-            let page_pictures = self.page_pictures();
 
-            if let Some(picture) = page_pictures.get(page_index as usize) {
-                let widget = picture.upcast_ref::<gtk::Widget>();
-                let natural_size = widget.preferred_size().1;
-                let page_height = natural_size.height() as f64;
-                let spacing = 10.0;
-                let page_size = adjustment.page_size();
+            // Paged mode shows the row of the page instead of scrolling to it
+            if self.paged() {
+                self.set_paged_page(Some(page_index as usize));
+                self.schedule_page_update();
+            }
 
-                let target_y = page_height * page_index as f64 + spacing * page_index as f64;
+            if let Some(&Some(target_y)) = self.page_tops().get(page_index as usize) {
+                let page_size = adjustment.page_size();
                 let max_value = adjustment.upper() - page_size;
 
                 let new_value = if target_y < 0.0 {
@@ -704,6 +1562,37 @@ impl PdfView {
         }
     }
 
+    /// Position reached in the document, in pages: the page at the top of the
+    /// view plus how far into it the view is scrolled, the page count once
+    /// scrolled to the end. In paged mode, the shown page counts as reached.
+    pub fn reading_position(&self) -> Option<f64> {
+        let total_pages = self.total_pages() as f64;
+        let page_index = self.calculate_current_page_from_scroll()? as usize;
+        if self.paged() {
+            return Some((page_index as f64 + 1.0).min(total_pages));
+        }
+
+        let adjustment = self.find_scrolled_window()?.vadjustment();
+        if adjustment.value() + adjustment.page_size() >= adjustment.upper() - 1.0 {
+            return Some(total_pages);
+        }
+
+        let page_top = self.page_tops().get(page_index).copied().flatten();
+        let page_height = self
+            .imp()
+            .page_pictures
+            .borrow()
+            .get(page_index)
+            .map(|picture| picture.preferred_size().1.height() as f64);
+        let into_page = match (page_top, page_height) {
+            (Some(top), Some(height)) if height > 0.0 => {
+                ((adjustment.value() - top) / height).clamp(0.0, 1.0)
+            }
+            _ => 0.0,
+        };
+        Some(page_index as f64 + into_page)
+    }
+
     fn find_scrolled_window(&self) -> Option<gtk::ScrolledWindow> {
         self.parent()?.parent()?.downcast().ok()
     }
@@ -717,14 +1606,16 @@ impl PdfView {
         let visible_start = scroll_y;
         let visible_end = scroll_y + viewport_height;
 
+        let page_tops = self.page_tops();
         let page_pictures = self.imp().page_pictures.borrow();
-        let spacing = 10.0;
 
         for (index, picture) in page_pictures.iter().enumerate() {
             let nat_size = picture.preferred_size().1;
             let picture_height = nat_size.height() as f64;
 
-            let page_top = index as f64 * (picture_height + spacing);
+            let Some(page_top) = page_tops[index] else {
+                continue;
+            };
             let page_bottom = page_top + picture_height;
 
             if page_bottom > visible_start && page_top < visible_end {
@@ -743,10 +1634,19 @@ impl PdfView {
     }
 
     /// Get a reference to the document
-    pub fn document(&self) -> std::cell::Ref<'_, Option<PdfDocument<'static>>> {
+    pub fn document(&self) -> std::cell::Ref<'_, Option<Rc<dyn DocumentBackend>>> {
         self.imp().document.borrow()
     }
 
+    /// Use the characters recognized by OCR as the text of a scanned page
+    pub fn set_recognized_text(&self, page_index: usize, chars: Vec<PageChar>) {
+        self.imp()
+            .recognized_pages
+            .borrow()
+            .borrow_mut()
+            .insert(page_index, chars);
+    }
+
     /// Get the highlight overlay for a specific page
     pub fn highlight_overlay(&self, page_index: usize) -> Option<HighlightOverlay> {
         self.imp()
@@ -761,6 +1661,14 @@ impl PdfView {
         self.imp().highlight_overlays.borrow()
     }
 
+    /// Draw the highlights of every page with the colors of another theme
+    pub fn set_highlight_palette(&self, palette: HighlightPalette) {
+        self.imp().highlight_palette.set(palette);
+        for overlay in self.imp().highlight_overlays.borrow().iter() {
+            overlay.set_palette(palette);
+        }
+    }
+
     /// Set the visual cursor position
     pub fn set_cursor(&self, cursor: Option<WordCursor>) {
         self.imp().visual_cursor.replace(cursor);
@@ -796,6 +1704,91 @@ impl PdfView {
         }
     }
 
+    /// Create a definition popover using the size the user last resized one to,
+    /// or fitting its content up to the configured maximum
+    pub fn create_definition_popover(&self) -> DefinitionPopover {
+        let popover = DefinitionPopover::new();
+        popover.set_autohide(self.popover_autohide());
+        popover.set_glossary_document(self.imp().document_path.borrow().clone());
+        let (max_width, max_height) = self.imp().popover_max_size.get();
+        popover.set_max_size(max_width, max_height);
+        if let Some((width, height)) = self.imp().popover_size.get() {
+            popover.set_fixed_size(width, height);
+        }
+
+        let view_weak = self.downgrade();
+        popover.connect_closure(
+            "resized",
+            false,
+            glib::closure_local!(
+                move |_popover: &DefinitionPopover, width: i32, height: i32| {
+                    if let Some(view) = view_weak.upgrade() {
+                        view.imp().popover_size.set(Some((width, height)));
+                    }
+                }
+            ),
+        );
+
+        let view_weak = self.downgrade();
+        popover.connect_closure(
+            "pronounce-requested",
+            false,
+            glib::closure_local!(
+                move |_popover: &DefinitionPopover, word: &str, language: &str| {
+                    if let Some(view) = view_weak.upgrade() {
+                        view.emit_by_name::<()>("pronounce-requested", &[&word, &language]);
+                    }
+                }
+            ),
+        );
+
+        // Forget a popover that closed by itself (outside click or its Close button)
+        let view_weak = self.downgrade();
+        popover.connect_closed(move |popover| {
+            let Some(view) = view_weak.upgrade() else {
+                return;
+            };
+            let is_current = view.imp().current_popover.borrow().as_ref() == Some(popover);
+            if is_current {
+                view.imp().current_popover.replace(None);
+                let popover = popover.clone();
+                glib::idle_add_local_once(move || popover.unparent());
+            }
+        });
+
+        popover
+    }
+
+    /// Emit "word-looked-up" once `popover` finds its definition
+    pub fn track_lookup(&self, popover: &DefinitionPopover, page_index: usize, context: String) {
+        let view_weak = self.downgrade();
+        popover.connect_closure(
+            "definition-found",
+            false,
+            glib::closure_local!(move |_popover: &DefinitionPopover,
+                                       word: &str,
+                                       definition: &str| {
+                if let Some(view) = view_weak.upgrade() {
+                    view.emit_by_name::<()>(
+                        "word-looked-up",
+                        &[&word, &definition, &context, &(page_index as u32)],
+                    );
+                }
+            }),
+        );
+    }
+
+    /// Get the maximum size of definition popovers
+    pub fn popover_max_size(&self) -> (i32, i32) {
+        self.imp().popover_max_size.get()
+    }
+
+    /// Set the maximum size of definition popovers, forgetting any resized size
+    pub fn set_popover_max_size(&self, width: i32, height: i32) {
+        self.imp().popover_max_size.set((width, height));
+        self.imp().popover_size.set(None);
+    }
+
     /// Set the current popover (for external use)
     pub fn set_current_popover(&self, popover: Option<DefinitionPopover>) {
         // Close existing popover first
@@ -814,10 +1807,74 @@ impl PdfView {
     }
 
     /// Set the zoom level and update page sizes
-    pub fn set_zoom_level(&self, zoom: f64) {
-        let clamped_zoom = zoom.clamp(0.5, 3.0);
+    fn set_zoom_level(&self, zoom: f64) {
+        let clamped_zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
         self.imp().zoom_level.set(clamped_zoom);
-        self.update_page_sizes_for_zoom();
+        self.update_page_sizes();
+    }
+
+    /// Get how the zoom level is chosen
+    pub fn zoom_mode(&self) -> ZoomMode {
+        self.imp().zoom_mode.get()
+    }
+
+    /// Switch to a zoom mode, or apply the current one again after the view or the
+    /// pages changed size. Returns whether the zoom level changed.
+    pub fn set_zoom_mode(&self, mode: ZoomMode) -> bool {
+        self.imp().zoom_mode.set(mode);
+
+        let Some(zoom) = self.zoom_for_mode() else {
+            return false;
+        };
+        if (zoom - self.zoom_level()).abs() < 0.001 {
+            return false;
+        }
+        self.set_zoom_level(zoom);
+        true
+    }
+
+    /// Zoom level the zoom mode asks for, None while there is nothing to fit
+    fn zoom_for_mode(&self) -> Option<f64> {
+        self.zoom_mode()
+            .zoom_level(self.unzoomed_page_size(), self.view_size())
+    }
+
+    /// Size of the current page at 100% zoom as shown, two pages side by side in
+    /// the two-page layout
+    fn unzoomed_page_size(&self) -> Option<(f64, f64)> {
+        let doc_borrow = self.imp().document.borrow();
+        let doc = doc_borrow.as_ref()?;
+        let page_index = (self.current_page() as usize).min(doc.page_count().saturating_sub(1));
+        let (page_width_pts, page_height_pts) =
+            self.page_crop(page_index).size(doc.page_size(page_index)?);
+
+        let width = RENDER_WIDTH as f64 * page_width_pts / self.imp().reference_page_width.get();
+        let (width, height) = self
+            .page_rotation(page_index)
+            .size((width, width * page_height_pts / page_width_pts));
+        if self.two_page() {
+            Some((width * 2.0 + PAGE_SPACING as f64, height))
+        } else {
+            Some((width, height))
+        }
+    }
+
+    /// Size of the area pages are shown in, without a margin around them
+    fn view_size(&self) -> Option<(f64, f64)> {
+        let scrolled = self.find_scrolled_window()?;
+        let width = scrolled.width() - FIT_MARGIN;
+        let height = scrolled.height() - FIT_MARGIN;
+        (width > 0 && height > 0).then_some((width as f64, height as f64))
+    }
+
+    /// Get the modifier that makes a click look up a definition
+    pub fn lookup_modifier(&self) -> LookupModifier {
+        self.imp().lookup_modifier.get()
+    }
+
+    /// Set the modifier that makes a click look up a definition
+    pub fn set_lookup_modifier(&self, modifier: LookupModifier) {
+        self.imp().lookup_modifier.set(modifier);
     }
 
     /// Get the current dictionary language
@@ -830,9 +1887,118 @@ impl PdfView {
         self.imp().dictionary_language.set(lang);
     }
 
-    /// Update all page sizes for the new zoom level (fast - no rendering)
+    /// Get where definitions are looked up
+    pub fn dictionary_source(&self) -> DictionarySource {
+        self.imp().dictionary_source.borrow().clone()
+    }
+
+    /// Set where definitions are looked up
+    pub fn set_dictionary_source(&self, source: DictionarySource) {
+        self.imp().dictionary_source.replace(source);
+    }
+
+    /// Rotation a page is shown with, its own on top of the document's
+    pub fn page_rotation(&self, page_index: usize) -> Rotation {
+        let imp = self.imp();
+        let page_rotation = imp
+            .page_rotations
+            .borrow()
+            .get(&page_index)
+            .copied()
+            .unwrap_or_default();
+        imp.document_rotation.get().combined(page_rotation)
+    }
+
+    /// Margins cut off a page: the ones set by hand, or with auto-crop those
+    /// found on it, estimated from another page until it is rendered
+    pub fn page_crop(&self, page_index: usize) -> PageCrop {
+        let imp = self.imp();
+        if let Some(crop) = imp.manual_crop.get() {
+            return crop;
+        }
+        if !self.auto_crop() {
+            return PageCrop::NONE;
+        }
+        imp.detected_crops
+            .borrow()
+            .get(&page_index)
+            .copied()
+            .or(imp.estimated_crop.get())
+            .unwrap_or(PageCrop::NONE)
+    }
+
+    /// Margins set by hand for every page
+    pub fn manual_crop(&self) -> Option<PageCrop> {
+        self.imp().manual_crop.get()
+    }
+
+    /// Cut the same margins off every page, or go back to auto-crop (or no crop)
+    /// with None
+    pub fn set_manual_crop(&self, crop: Option<PageCrop>) {
+        self.imp().manual_crop.set(crop);
+        self.crop_changed();
+    }
+
+    /// Find the margins of a page in its first render. The first page found
+    /// gives the estimate the pages not rendered yet are laid out with.
+    fn detect_crop(&self, page_index: usize, rendered: &RenderedPage, scale: f64) {
+        let imp = self.imp();
+        let crop = PageCrop::detect(rendered, scale);
+        let previous = self.page_crop(page_index);
+        imp.detected_crops.borrow_mut().insert(page_index, crop);
+
+        if imp.estimated_crop.get().is_none() {
+            imp.estimated_crop.set(Some(crop));
+            // Lay the other pages out with it once this one is shown
+            let view_weak = self.downgrade();
+            glib::idle_add_local_once(move || {
+                if let Some(view) = view_weak.upgrade() {
+                    view.crop_changed();
+                }
+            });
+        } else if imp.manual_crop.get().is_none() && crop != previous {
+            self.emit_by_name::<()>("crop-changed", &[]);
+        }
+    }
+
+    /// Lay the pages out again after their margins changed, fitting the zoom to
+    /// their new size
+    fn crop_changed(&self) {
+        if !self.set_zoom_mode(self.zoom_mode()) {
+            self.update_page_sizes();
+        }
+        self.emit_by_name::<()>("crop-changed", &[]);
+    }
+
+    /// Rotation of every page, without the pages turned on their own
+    pub fn document_rotation(&self) -> Rotation {
+        self.imp().document_rotation.get()
+    }
+
+    /// Turn every page by `quarter_turns`, clockwise when positive
+    pub fn rotate_document(&self, quarter_turns: i32) {
+        let imp = self.imp();
+        imp.document_rotation
+            .set(imp.document_rotation.get().rotated(quarter_turns));
+        self.update_page_sizes();
+    }
+
+    /// Turn a single page by `quarter_turns`, clockwise when positive
+    pub fn rotate_page(&self, page_index: usize, quarter_turns: i32) {
+        {
+            let mut page_rotations = self.imp().page_rotations.borrow_mut();
+            let rotation = page_rotations.entry(page_index).or_default();
+            *rotation = rotation.rotated(quarter_turns);
+            if *rotation == Rotation::NONE {
+                page_rotations.remove(&page_index);
+            }
+        }
+        self.update_page_sizes();
+    }
+
+    /// Update all page sizes for the zoom level and rotations (fast - no rendering)
     /// Then render only visible pages
-    fn update_page_sizes_for_zoom(&self) {
+    fn update_page_sizes(&self) {
         let doc_borrow = self.imp().document.borrow();
         let doc = match doc_borrow.as_ref() {
             Some(d) => d,
@@ -843,8 +2009,8 @@ impl PdfView {
         let highlight_overlays = self.imp().highlight_overlays.borrow();
 
         // Update sizes for all pages (fast - just size request changes)
-        for (index, page) in doc.pages().iter().enumerate() {
-            let (width, height) = self.calculate_page_size(&page);
+        for index in 0..doc.page_count() {
+            let (width, height) = self.calculate_page_size(doc.as_ref(), index);
 
             if let Some(picture) = page_pictures.get(index) {
                 // Just update size request - no pixel allocation
@@ -863,6 +2029,10 @@ impl PdfView {
 
         // Mark all pages as needing re-render
         self.imp().rendered_pages.borrow_mut().clear();
+        self.imp().pending_renders.borrow_mut().clear();
+        if let Some(worker) = self.imp().render_worker.borrow().as_ref() {
+            worker.cancel_pending();
+        }
 
         drop(doc_borrow);
         drop(page_pictures);
@@ -0,0 +1,232 @@
+use gtk::glib;
+use gtk::glib::subclass::Signal;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Button, Entry, Label, ListBox, Orientation, ScrolledWindow, Window};
+use std::sync::OnceLock;
+
+use crate::services::positions::SavedPosition;
+
+mod imp {
+    use super::*;
+
+    pub struct PositionsWindow {
+        pub name_entry: Entry,
+        pub save_button: Button,
+        pub list_box: ListBox,
+        pub empty_label: Label,
+    }
+
+    impl Default for PositionsWindow {
+        fn default() -> Self {
+            Self {
+                name_entry: Entry::builder()
+                    .placeholder_text("Name for the current position")
+                    .hexpand(true)
+                    .build(),
+                save_button: Button::with_label("Save"),
+                list_box: ListBox::builder()
+                    .selection_mode(gtk::SelectionMode::None)
+                    .build(),
+                empty_label: Label::builder()
+                    .label("No saved positions for this document.")
+                    .css_classes(["dim-label"])
+                    .margin_top(12)
+                    .margin_bottom(12)
+                    .build(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PositionsWindow {
+        const NAME: &'static str = "PositionsWindow";
+        type Type = super::PositionsWindow;
+        type ParentType = Window;
+    }
+
+    impl ObjectImpl for PositionsWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    Signal::builder("position-save-requested")
+                        .param_types([String::static_type()])
+                        .build(),
+                    Signal::builder("position-selected")
+                        .param_types([i64::static_type()])
+                        .build(),
+                    Signal::builder("position-delete-requested")
+                        .param_types([i64::static_type()])
+                        .build(),
+                ]
+            })
+        }
+    }
+
+    impl WidgetImpl for PositionsWindow {}
+    impl WindowImpl for PositionsWindow {}
+}
+
+glib::wrapper! {
+    pub struct PositionsWindow(ObjectSubclass<imp::PositionsWindow>)
+        @extends Window, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl PositionsWindow {
+    pub fn new(parent: &impl IsA<Window>) -> Self {
+        glib::Object::builder()
+            .property("transient-for", parent)
+            .property("modal", true)
+            .property("title", "Saved Positions")
+            .property("default-width", 420)
+            .property("default-height", 360)
+            .build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+
+        self.add_css_class("positions-window");
+
+        let main_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(12)
+            .margin_start(24)
+            .margin_end(24)
+            .margin_top(24)
+            .margin_bottom(24)
+            .build();
+
+        // Save row: name entry + save button
+        let save_box = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .build();
+        save_box.append(&imp.name_entry);
+        save_box.append(&imp.save_button);
+        main_box.append(&save_box);
+
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .child(&imp.list_box)
+            .build();
+        main_box.append(&scrolled);
+        main_box.append(&imp.empty_label);
+
+        let close_button = Button::builder()
+            .label("Close")
+            .halign(gtk::Align::End)
+            .build();
+
+        let window_weak = self.downgrade();
+        close_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.close();
+            }
+        });
+        main_box.append(&close_button);
+
+        self.set_child(Some(&main_box));
+
+        // Both the button and Enter in the entry save the position
+        let window_weak = self.downgrade();
+        imp.save_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.request_save();
+            }
+        });
+
+        let window_weak = self.downgrade();
+        imp.name_entry.connect_activate(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.request_save();
+            }
+        });
+    }
+
+    fn request_save(&self) {
+        let imp = self.imp();
+        let name = imp.name_entry.text().trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+
+        imp.name_entry.set_text("");
+        self.emit_by_name::<()>("position-save-requested", &[&name]);
+    }
+
+    /// Replace the listed positions
+    pub fn set_positions(&self, positions: &[SavedPosition]) {
+        let imp = self.imp();
+
+        while let Some(row) = imp.list_box.first_child() {
+            imp.list_box.remove(&row);
+        }
+
+        for position in positions {
+            imp.list_box.append(&self.build_row(position));
+        }
+
+        imp.empty_label.set_visible(positions.is_empty());
+    }
+
+    fn build_row(&self, position: &SavedPosition) -> Box {
+        let row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .margin_top(4)
+            .margin_bottom(4)
+            .build();
+
+        let name_label = Label::builder()
+            .label(&position.name)
+            .halign(gtk::Align::Start)
+            .hexpand(true)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+
+        let page_label = Label::builder()
+            .label(format!("Page {}", position.page_index + 1))
+            .css_classes(["dim-label"])
+            .build();
+
+        let go_button = Button::builder()
+            .icon_name("go-jump-symbolic")
+            .tooltip_text("Go to position")
+            .build();
+        let delete_button = Button::builder()
+            .icon_name("user-trash-symbolic")
+            .tooltip_text("Delete position")
+            .build();
+
+        let id = position.id;
+        let window_weak = self.downgrade();
+        go_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.emit_by_name::<()>("position-selected", &[&id]);
+                window.close();
+            }
+        });
+
+        let window_weak = self.downgrade();
+        delete_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.emit_by_name::<()>("position-delete-requested", &[&id]);
+            }
+        });
+
+        row.append(&name_label);
+        row.append(&page_label);
+        row.append(&go_button);
+        row.append(&delete_button);
+        row
+    }
+}
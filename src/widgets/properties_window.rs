@@ -0,0 +1,111 @@
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Button, Grid, Label, Orientation, Window};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct PropertiesWindow {
+        pub grid: Grid,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PropertiesWindow {
+        const NAME: &'static str = "PropertiesWindow";
+        type Type = super::PropertiesWindow;
+        type ParentType = Window;
+    }
+
+    impl ObjectImpl for PropertiesWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+    }
+
+    impl WidgetImpl for PropertiesWindow {}
+    impl WindowImpl for PropertiesWindow {}
+}
+
+glib::wrapper! {
+    pub struct PropertiesWindow(ObjectSubclass<imp::PropertiesWindow>)
+        @extends Window, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl PropertiesWindow {
+    pub fn new(parent: &impl IsA<Window>) -> Self {
+        glib::Object::builder()
+            .property("transient-for", parent)
+            .property("modal", true)
+            .property("title", "Document Properties")
+            .property("default-width", 460)
+            .property("default-height", 240)
+            .build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+
+        self.add_css_class("properties-window");
+
+        let main_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(12)
+            .margin_start(24)
+            .margin_end(24)
+            .margin_top(24)
+            .margin_bottom(24)
+            .build();
+
+        imp.grid.set_row_spacing(6);
+        imp.grid.set_column_spacing(12);
+        main_box.append(&imp.grid);
+
+        let close_button = Button::builder()
+            .label("Close")
+            .halign(gtk::Align::End)
+            .valign(gtk::Align::End)
+            .vexpand(true)
+            .build();
+        main_box.append(&close_button);
+
+        self.set_child(Some(&main_box));
+
+        let window_weak = self.downgrade();
+        close_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.close();
+            }
+        });
+    }
+
+    /// Show one row per (name, value) pair
+    pub fn set_rows(&self, rows: &[(&str, String)]) {
+        let grid = &self.imp().grid;
+        while let Some(child) = grid.first_child() {
+            grid.remove(&child);
+        }
+
+        for (row, (name, value)) in rows.iter().enumerate() {
+            let name_label = Label::builder()
+                .label(*name)
+                .halign(gtk::Align::Start)
+                .valign(gtk::Align::Start)
+                .css_classes(["dim-label"])
+                .build();
+            let value_label = Label::builder()
+                .label(value.as_str())
+                .halign(gtk::Align::Start)
+                .hexpand(true)
+                .selectable(true)
+                .wrap(true)
+                .wrap_mode(gtk::pango::WrapMode::WordChar)
+                .build();
+            grid.attach(&name_label, 0, row as i32, 1, 1);
+            grid.attach(&value_label, 1, row as i32, 1, 1);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use gtk::glib;
+use gtk::glib::subclass::Signal;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Label, ListBox, Orientation, PolicyType, Popover, ScrolledWindow, SearchEntry};
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+use crate::services::recent_files::{self, RecentFile};
+
+const LIST_WIDTH: i32 = 360;
+const LIST_MAX_HEIGHT: i32 = 400;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct RecentFilesPopover {
+        pub search_entry: SearchEntry,
+        pub list_box: ListBox,
+        /// Every recent file, most recent first, as loaded when the popover opened
+        pub files: RefCell<Vec<RecentFile>>,
+        /// Paths of the rows currently shown, in row order
+        pub shown_paths: RefCell<Vec<String>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RecentFilesPopover {
+        const NAME: &'static str = "RecentFilesPopover";
+        type Type = super::RecentFilesPopover;
+        type ParentType = Popover;
+    }
+
+    impl ObjectImpl for RecentFilesPopover {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // Emitted with the path of the file picked from the list
+                    Signal::builder("file-activated")
+                        .param_types([String::static_type()])
+                        .build(),
+                ]
+            })
+        }
+    }
+
+    impl WidgetImpl for RecentFilesPopover {}
+    impl PopoverImpl for RecentFilesPopover {}
+}
+
+glib::wrapper! {
+    pub struct RecentFilesPopover(ObjectSubclass<imp::RecentFilesPopover>)
+        @extends Popover, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::ShortcutManager;
+}
+
+impl RecentFilesPopover {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+        self.add_css_class("recent-files-popover");
+
+        imp.search_entry
+            .set_placeholder_text(Some("Filter recent files..."));
+
+        imp.list_box.set_selection_mode(gtk::SelectionMode::Browse);
+        imp.list_box.add_css_class("recent-files-list");
+
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .propagate_natural_height(true)
+            .min_content_width(LIST_WIDTH)
+            .max_content_height(LIST_MAX_HEIGHT)
+            .child(&imp.list_box)
+            .build();
+
+        let container = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(6)
+            .margin_start(6)
+            .margin_end(6)
+            .margin_top(6)
+            .margin_bottom(6)
+            .build();
+        container.append(&imp.search_entry);
+        container.append(&scroller);
+        self.set_child(Some(&container));
+
+        // Reload on every open so files opened meanwhile show up first
+        self.connect_show(|popover| {
+            popover.reload();
+        });
+
+        let popover = self.downgrade();
+        imp.search_entry.connect_search_changed(move |_| {
+            if let Some(popover) = popover.upgrade() {
+                popover.populate();
+            }
+        });
+
+        // Enter in the filter opens the best match
+        let popover = self.downgrade();
+        imp.search_entry.connect_activate(move |_| {
+            if let Some(popover) = popover.upgrade() {
+                popover.activate_index(0);
+            }
+        });
+
+        // Down arrow moves from the filter into the list
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let list_box = imp.list_box.clone();
+        key_controller.connect_key_pressed(move |_, keyval, _, _| {
+            if keyval == gtk::gdk::Key::Down {
+                if let Some(row) = list_box.row_at_index(0) {
+                    row.grab_focus();
+                }
+                return glib::Propagation::Stop;
+            }
+            glib::Propagation::Proceed
+        });
+        imp.search_entry.add_controller(key_controller);
+
+        let popover = self.downgrade();
+        imp.list_box.connect_row_activated(move |_, row| {
+            if let Some(popover) = popover.upgrade() {
+                popover.activate_index(row.index());
+            }
+        });
+    }
+
+    /// Load the recent files again and clear the filter
+    pub fn reload(&self) {
+        let imp = self.imp();
+        let files = recent_files::load_recent_files().unwrap_or_else(|e| {
+            eprintln!("Failed to load recent files: {}", e);
+            Vec::new()
+        });
+        imp.files.replace(files);
+        imp.search_entry.set_text("");
+        self.populate();
+        imp.search_entry.grab_focus();
+    }
+
+    /// Fill the list with the files matching the filter
+    fn populate(&self) {
+        let imp = self.imp();
+
+        while let Some(child) = imp.list_box.first_child() {
+            imp.list_box.remove(&child);
+        }
+
+        let query = imp.search_entry.text();
+        let files = imp.files.borrow();
+        let matches = recent_files::filter_recent_files(&files, &query);
+
+        if matches.is_empty() {
+            let message = if files.is_empty() {
+                "No recent files"
+            } else {
+                "No matching files"
+            };
+            let label = Label::new(Some(message));
+            label.add_css_class("dim-label");
+            label.set_margin_top(12);
+            label.set_margin_bottom(12);
+            imp.list_box.set_placeholder(Some(&label));
+        }
+
+        let mut shown_paths = Vec::with_capacity(matches.len());
+        for file in matches {
+            imp.list_box.append(&Self::create_row(file));
+            shown_paths.push(file.pdf_path.clone());
+        }
+        imp.shown_paths.replace(shown_paths);
+
+        if let Some(row) = imp.list_box.row_at_index(0) {
+            imp.list_box.select_row(Some(&row));
+        }
+    }
+
+    fn create_row(file: &RecentFile) -> Box {
+        let row = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(2)
+            .margin_start(6)
+            .margin_end(6)
+            .margin_top(4)
+            .margin_bottom(4)
+            .build();
+        row.set_tooltip_text(Some(&file.pdf_path));
+
+        let name = Label::new(Some(&file.file_name()));
+        name.set_xalign(0.0);
+        name.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+        name.add_css_class("recent-file-name");
+
+        let path = Label::new(Some(&file.pdf_path));
+        path.set_xalign(0.0);
+        path.set_ellipsize(gtk::pango::EllipsizeMode::Start);
+        path.add_css_class("dim-label");
+        path.add_css_class("caption");
+
+        row.append(&name);
+        row.append(&path);
+        row
+    }
+
+    fn activate_index(&self, index: i32) {
+        let path = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.imp().shown_paths.borrow().get(index).cloned());
+        if let Some(path) = path {
+            self.popdown();
+            self.emit_by_name::<()>("file-activated", &[&path]);
+        }
+    }
+}
+
+impl Default for RecentFilesPopover {
+    fn default() -> Self {
+        Self::new()
+    }
+}
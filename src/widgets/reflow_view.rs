@@ -0,0 +1,194 @@
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Orientation, PolicyType, ScrolledWindow, TextView};
+use std::cell::RefCell;
+
+use crate::modes::WordCursor;
+use crate::services::annotations::{Annotation, AnnotationColor};
+use crate::text_map::ReflowText;
+
+/// Margin around the text, wide enough to read comfortably on narrow windows too
+const TEXT_MARGIN: i32 = 24;
+
+mod imp {
+    use super::*;
+
+    pub struct ReflowView {
+        pub scrolled_window: ScrolledWindow,
+        pub text_view: TextView,
+        /// Text shown, mapping buffer offsets back to document words
+        pub text: RefCell<ReflowText>,
+    }
+
+    impl Default for ReflowView {
+        fn default() -> Self {
+            Self {
+                scrolled_window: ScrolledWindow::builder()
+                    .hscrollbar_policy(PolicyType::Never)
+                    .vscrollbar_policy(PolicyType::Automatic)
+                    .vexpand(true)
+                    .hexpand(true)
+                    .build(),
+                text_view: TextView::builder()
+                    .editable(false)
+                    .cursor_visible(false)
+                    .wrap_mode(gtk::WrapMode::WordChar)
+                    .pixels_below_lines(4)
+                    .left_margin(TEXT_MARGIN)
+                    .right_margin(TEXT_MARGIN)
+                    .top_margin(TEXT_MARGIN)
+                    .bottom_margin(TEXT_MARGIN)
+                    .build(),
+                text: RefCell::new(ReflowText::new()),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ReflowView {
+        const NAME: &'static str = "ReflowView";
+        type Type = super::ReflowView;
+        type ParentType = Box;
+    }
+
+    impl ObjectImpl for ReflowView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+    }
+
+    impl WidgetImpl for ReflowView {}
+    impl BoxImpl for ReflowView {}
+}
+
+glib::wrapper! {
+    /// The text of the document wrapped to the width of the view, for windows too
+    /// narrow for the pages
+    pub struct ReflowView(ObjectSubclass<imp::ReflowView>)
+        @extends Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl ReflowView {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+
+        self.set_orientation(Orientation::Vertical);
+        self.add_css_class("reflow-view");
+
+        imp.text_view.add_css_class("reflow-text");
+        imp.scrolled_window.set_child(Some(&imp.text_view));
+        self.append(&imp.scrolled_window);
+
+        let buffer = imp.text_view.buffer();
+        for color in AnnotationColor::ALL {
+            let rgba = match color {
+                AnnotationColor::Yellow => "rgba(255, 242, 102, 0.45)",
+                AnnotationColor::Green => "rgba(115, 230, 115, 0.45)",
+                AnnotationColor::Red => "rgba(255, 115, 115, 0.45)",
+                AnnotationColor::Blue => "rgba(115, 179, 255, 0.45)",
+            };
+            buffer.create_tag(Some(&Self::annotation_tag(color)), &[("background", &rgba)]);
+        }
+    }
+
+    fn annotation_tag(color: AnnotationColor) -> String {
+        format!("annotation-{}", color.as_str())
+    }
+
+    /// Show `text`, scrolled to its start
+    pub fn set_text(&self, text: ReflowText) {
+        let imp = self.imp();
+        imp.text_view.buffer().set_text(&text.text);
+        imp.text.replace(text);
+        imp.scrolled_window.vadjustment().set_value(0.0);
+    }
+
+    /// Drop the text, e.g. when the document is closed
+    pub fn clear(&self) {
+        self.set_text(ReflowText::new());
+    }
+
+    /// Highlight the annotations over the words they were made on
+    pub fn set_annotations(&self, annotations: &[Annotation]) {
+        let imp = self.imp();
+        let buffer = imp.text_view.buffer();
+        let (start, end) = buffer.bounds();
+        for color in AnnotationColor::ALL {
+            buffer.remove_tag_by_name(&Self::annotation_tag(color), &start, &end);
+        }
+
+        let text = imp.text.borrow();
+        for ann in annotations {
+            let Some((start, end)) = text.offsets(
+                WordCursor::new(ann.start_page, ann.start_word),
+                WordCursor::new(ann.end_page, ann.end_word),
+            ) else {
+                continue;
+            };
+            buffer.apply_tag_by_name(
+                &Self::annotation_tag(ann.color),
+                &buffer.iter_at_offset(start as i32),
+                &buffer.iter_at_offset(end as i32),
+            );
+        }
+    }
+
+    /// Scroll so the word at `cursor`, or the first one after it, is at the top
+    pub fn scroll_to_word(&self, cursor: WordCursor) {
+        let imp = self.imp();
+        let text = imp.text.borrow();
+        let Some((start, _)) = text.offsets(cursor, WordCursor::new(usize::MAX, 0)) else {
+            return;
+        };
+        drop(text);
+
+        let buffer = imp.text_view.buffer();
+        let mark = buffer.create_mark(None, &buffer.iter_at_offset(start as i32), true);
+        imp.text_view.scroll_to_mark(&mark, 0.0, true, 0.0, 0.0);
+        buffer.delete_mark(&mark);
+    }
+
+    /// The word at the top of the view
+    pub fn top_word(&self) -> Option<WordCursor> {
+        let imp = self.imp();
+        let visible = imp.text_view.visible_rect();
+        let iter = imp.text_view.iter_at_location(visible.x(), visible.y())?;
+        imp.text.borrow().word_at(iter.offset() as usize)
+    }
+
+    /// Words of the text selected with the pointer, as an inclusive range
+    pub fn selection(&self) -> Option<(WordCursor, WordCursor)> {
+        let imp = self.imp();
+        let (start, end) = imp.text_view.buffer().selection_bounds()?;
+        let text = imp.text.borrow();
+        // The end offset is exclusive, so it can sit right after the last word
+        let last = end.offset().saturating_sub(1).max(start.offset());
+        Some((
+            text.word_at(start.offset() as usize)?,
+            text.word_at(last as usize)?,
+        ))
+    }
+
+    /// Scroll by a fraction of the view height, negative to scroll up
+    pub fn scroll_by_percent(&self, percent: f64) {
+        let vadj = self.imp().scrolled_window.vadjustment();
+        let page_size = vadj.page_size();
+        let new_value = (vadj.value() + page_size * (percent / 100.0))
+            .max(vadj.lower())
+            .min(vadj.upper() - page_size);
+        vadj.set_value(new_value);
+    }
+}
+
+impl Default for ReflowView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,10 +2,23 @@ use glib::Properties;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
-use gtk::{Box, Button, DropDown, Label, Orientation, StringList, Window};
-use std::cell::Cell;
+use gtk::{
+    Box, Button, DropDown, Entry, FontDialog, FontDialogButton, Label, Orientation, PasswordEntry,
+    ScrolledWindow, SpinButton, StringList, Switch, TextView, Window,
+};
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 
-use crate::services::dictionary::Language;
+use crate::services::annotations::{ExportOptions, ExportSort};
+use crate::services::dictionary::{self, DictionarySource, Language};
+use crate::services::settings::PanelPosition;
+use crate::services::startup::StartupMode;
+use crate::services::theme::Theme;
+use crate::services::translation::{self, TranslationBackend, TranslatorConfig};
+use crate::services::web_search::SearchEngine;
+use crate::text_map::CopyJoinStyle;
+use crate::widgets::definition_popover;
+use crate::widgets::{LookupModifier, UiFont};
 
 mod imp {
     use super::*;
@@ -14,19 +27,349 @@ mod imp {
     #[properties(wrapper_type = super::SettingsWindow)]
     pub struct SettingsWindow {
         pub language_dropdown: DropDown,
+        /// Languages in the dropdown, those of the selected dictionary source
+        pub languages: RefCell<Vec<Language>>,
+        /// Set while the language list is replaced, so the transient selections are ignored
+        pub updating_languages: Cell<bool>,
+        pub language_per_document_switch: Switch,
+        pub dictionary_source_dropdown: DropDown,
+        pub dictionary_dir_entry: Entry,
+        pub dictionary_dir_box: Box,
+        pub prefetch_switch: Switch,
+        pub prefetch_online_switch: Switch,
+        pub definition_cache_label: Label,
+        pub clear_cache_button: Button,
+        pub translation_backend_dropdown: DropDown,
+        pub libretranslate_url_entry: Entry,
+        pub libretranslate_key_entry: PasswordEntry,
+        pub deepl_key_entry: PasswordEntry,
+        pub translation_command_entry: Entry,
+        pub join_style_dropdown: DropDown,
+        pub lookup_modifier_dropdown: DropDown,
+        pub bionic_switch: Switch,
+        pub word_boxes_switch: Switch,
+        pub page_labels_switch: Switch,
+        pub export_group_switch: Switch,
+        pub export_group_tag_switch: Switch,
+        pub export_sort_dropdown: DropDown,
+        pub theme_dropdown: DropDown,
+        pub font_button: FontDialogButton,
+        pub font_size_spin: SpinButton,
+        pub popover_width_spin: SpinButton,
+        pub popover_height_spin: SpinButton,
+        pub popover_autohide_switch: Switch,
+        pub clipboard_lookup_switch: Switch,
+        pub auto_crop_switch: Switch,
+        pub cover_page_switch: Switch,
+        pub viewport_anchor_spin: SpinButton,
+        pub annotation_wrap_switch: Switch,
+        pub presentation_fade_switch: Switch,
+        pub synctex_editor_entry: Entry,
+        pub web_search_dropdown: DropDown,
+        pub web_search_entry: Entry,
+        pub notes_sync_dir_entry: Entry,
+        pub post_export_view: TextView,
+        pub api_port_spin: SpinButton,
+        pub startup_mode_dropdown: DropDown,
+        pub startup_header_switch: Switch,
+        pub startup_status_switch: Switch,
+        pub annotation_panel_dropdown: DropDown,
 
         #[property(get, set, default = 0)]
         pub selected_language: Cell<u32>,
+        /// Whether the language applies to the open document only
+        #[property(get, set, default = false)]
+        pub language_per_document: Cell<bool>,
+        /// 0 = built-in database, 1 = online, 2 = local directory
+        #[property(get, set, default = 0)]
+        pub selected_dictionary_source: Cell<u32>,
+        /// Directory of the StarDict/dictd files
+        #[property(get, set)]
+        pub local_dictionary_dir: RefCell<String>,
+        /// Index into TranslationBackend::ALL
+        #[property(get, set, default = 0)]
+        pub selected_translation_backend: Cell<u32>,
+        /// Empty for the local server
+        #[property(get, set)]
+        pub libretranslate_url: RefCell<String>,
+        #[property(get, set)]
+        pub libretranslate_api_key: RefCell<String>,
+        #[property(get, set)]
+        pub deepl_api_key: RefCell<String>,
+        #[property(get, set)]
+        pub translation_command: RefCell<String>,
+        #[property(get, set, default = 0)]
+        pub selected_join_style: Cell<u32>,
+        #[property(get, set, default = 0)]
+        pub selected_lookup_modifier: Cell<u32>,
+        #[property(get, set, default = false)]
+        pub bionic_reading: Cell<bool>,
+        #[property(get, set, default = false)]
+        pub show_word_boxes: Cell<bool>,
+        #[property(get, set, default = false)]
+        pub use_page_labels: Cell<bool>,
+        #[property(get, set, default = false)]
+        pub export_group_by_chapter: Cell<bool>,
+        #[property(get, set, default = false)]
+        pub export_group_by_tag: Cell<bool>,
+        #[property(get, set, default = 0)]
+        pub selected_export_sort: Cell<u32>,
+        /// UI font family, empty for the theme font
+        #[property(get, set)]
+        pub ui_font_family: RefCell<String>,
+        /// UI font size in points, 0 for the default size
+        #[property(get, set, default = 0.0)]
+        pub ui_font_size: Cell<f64>,
+        #[property(get, set, default = definition_popover::DEFAULT_MAX_WIDTH)]
+        pub popover_max_width: Cell<i32>,
+        #[property(get, set, default = definition_popover::DEFAULT_MAX_HEIGHT)]
+        pub popover_max_height: Cell<i32>,
+        #[property(get, set, default = false)]
+        pub popover_autohide: Cell<bool>,
+        /// Whether words copied in other applications are looked up
+        #[property(get, set, default = false)]
+        pub clipboard_lookup: Cell<bool>,
+        /// Whether the blank margins of the pages are cut off
+        #[property(get, set, default = false)]
+        pub auto_crop: Cell<bool>,
+        #[property(get, set, default = false)]
+        pub cover_page: Cell<bool>,
+        /// Height the cursor lands at after a jump, 0.0 for the top of the viewport
+        /// and 1.0 for the bottom
+        #[property(get, set, minimum = 0.0, maximum = 1.0, default = 0.2)]
+        pub viewport_anchor: Cell<f64>,
+        /// Whether ]a/[a wrap around the ends of the document
+        #[property(get, set, default = false)]
+        pub annotation_wrap: Cell<bool>,
+        /// Whether pages fade in when turned in presentation mode
+        #[property(get, set, default = true)]
+        pub presentation_fade: Cell<bool>,
+        /// Whether the rare words of the cursor's line are looked up ahead of time
+        #[property(get, set, default = false)]
+        pub prefetch_definitions: Cell<bool>,
+        /// Whether prefetching may query the online dictionary
+        #[property(get, set, default = false)]
+        pub prefetch_online: Cell<bool>,
+        /// Reverse search command, empty to only print the source line
+        #[property(get, set)]
+        pub synctex_editor: RefCell<String>,
+        /// URL template the selection is searched with on gx
+        #[property(get, set)]
+        pub web_search: RefCell<String>,
+        /// Directory the annotations are kept exported to, empty to disable
+        #[property(get, set)]
+        pub notes_sync_dir: RefCell<String>,
+        /// Commands run after an export, one per line
+        #[property(get, set)]
+        pub post_export_commands: RefCell<String>,
+        /// Port of the local HTTP API, 0 when it is off
+        #[property(get, set, maximum = 65535, default = 0)]
+        pub api_port: Cell<u32>,
+        /// Index into StartupMode::ALL
+        #[property(get, set, default = 0)]
+        pub selected_startup_mode: Cell<u32>,
+        #[property(get, set, default = true)]
+        pub startup_header_bar: Cell<bool>,
+        #[property(get, set, default = true)]
+        pub startup_status_bar: Cell<bool>,
+        /// Index into PanelPosition::ALL
+        #[property(get, set, default = 0)]
+        pub selected_annotation_panel: Cell<u32>,
+        /// Index into Theme::ALL
+        #[property(get, set, default = 0)]
+        pub selected_theme: Cell<u32>,
     }
 
     impl Default for SettingsWindow {
         fn default() -> Self {
-            let languages = StringList::new(&["English", "Spanish"]);
-            let dropdown = DropDown::new(Some(languages), None::<gtk::Expression>);
+            // Filled by update_language_list; searchable since the list can be long
+            let dropdown = DropDown::new(
+                None::<StringList>,
+                Some(gtk::PropertyExpression::new(
+                    gtk::StringObject::static_type(),
+                    None::<gtk::Expression>,
+                    "string",
+                )),
+            );
+            dropdown.set_enable_search(true);
+            dropdown.set_search_match_mode(gtk::StringFilterMatchMode::Substring);
+
+            let join_styles: Vec<&str> = CopyJoinStyle::ALL.iter().map(|s| s.label()).collect();
+            let join_style_dropdown =
+                DropDown::new(Some(StringList::new(&join_styles)), None::<gtk::Expression>);
+
+            let translation_backends: Vec<&str> =
+                TranslationBackend::ALL.iter().map(|b| b.label()).collect();
+
+            let lookup_modifiers: Vec<&str> =
+                LookupModifier::ALL.iter().map(|m| m.label()).collect();
+            let lookup_modifier_dropdown = DropDown::new(
+                Some(StringList::new(&lookup_modifiers)),
+                None::<gtk::Expression>,
+            );
+
+            let startup_modes: Vec<&str> = StartupMode::ALL.iter().map(|m| m.label()).collect();
+            let panel_positions: Vec<&str> = PanelPosition::ALL.iter().map(|p| p.label()).collect();
+            let themes: Vec<&str> = Theme::ALL.iter().map(|t| t.label()).collect();
+            // The last entry stands for a template typed in by hand
+            let mut search_engines: Vec<&str> =
+                SearchEngine::ALL.iter().map(|e| e.label()).collect();
+            search_engines.push("Custom");
 
             Self {
                 language_dropdown: dropdown,
+                languages: RefCell::new(Vec::new()),
+                updating_languages: Cell::new(false),
+                language_per_document_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                dictionary_source_dropdown: DropDown::new(
+                    Some(StringList::new(&[
+                        "Built-in database",
+                        "Online",
+                        "Local directory",
+                    ])),
+                    None::<gtk::Expression>,
+                ),
+                dictionary_dir_entry: Entry::builder()
+                    .placeholder_text("~/.stardict/dic")
+                    .valign(gtk::Align::Center)
+                    .build(),
+                dictionary_dir_box: Box::builder()
+                    .orientation(Orientation::Horizontal)
+                    .spacing(4)
+                    .build(),
+                join_style_dropdown,
+                lookup_modifier_dropdown,
+                bionic_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                word_boxes_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                page_labels_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                export_group_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                export_group_tag_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                export_sort_dropdown: DropDown::new(
+                    Some(StringList::new(&["Position", "Creation date"])),
+                    None::<gtk::Expression>,
+                ),
                 selected_language: Cell::new(0),
+                language_per_document: Cell::new(false),
+                selected_dictionary_source: Cell::new(0),
+                local_dictionary_dir: RefCell::new(String::new()),
+                translation_backend_dropdown: DropDown::new(
+                    Some(StringList::new(&translation_backends)),
+                    None::<gtk::Expression>,
+                ),
+                libretranslate_url_entry: Entry::builder()
+                    .placeholder_text(translation::LIBRETRANSLATE_URL)
+                    .valign(gtk::Align::Center)
+                    .build(),
+                libretranslate_key_entry: PasswordEntry::builder()
+                    .placeholder_text("Optional")
+                    .show_peek_icon(true)
+                    .valign(gtk::Align::Center)
+                    .build(),
+                deepl_key_entry: PasswordEntry::builder()
+                    .show_peek_icon(true)
+                    .valign(gtk::Align::Center)
+                    .build(),
+                translation_command_entry: Entry::builder()
+                    .placeholder_text("argos-translate --from-lang %{source} --to-lang %{target}")
+                    .valign(gtk::Align::Center)
+                    .build(),
+                selected_translation_backend: Cell::new(0),
+                libretranslate_url: RefCell::new(String::new()),
+                libretranslate_api_key: RefCell::new(String::new()),
+                deepl_api_key: RefCell::new(String::new()),
+                translation_command: RefCell::new(String::new()),
+                selected_join_style: Cell::new(0),
+                selected_lookup_modifier: Cell::new(0),
+                bionic_reading: Cell::new(false),
+                show_word_boxes: Cell::new(false),
+                use_page_labels: Cell::new(false),
+                export_group_by_chapter: Cell::new(false),
+                export_group_by_tag: Cell::new(false),
+                theme_dropdown: DropDown::new(
+                    Some(StringList::new(&themes)),
+                    None::<gtk::Expression>,
+                ),
+                font_button: FontDialogButton::builder()
+                    .dialog(&FontDialog::builder().title("UI Font").build())
+                    .level(gtk::FontLevel::Family)
+                    .valign(gtk::Align::Center)
+                    .build(),
+                font_size_spin: SpinButton::with_range(0.0, 32.0, 1.0),
+                popover_width_spin: SpinButton::with_range(200.0, 1600.0, 50.0),
+                popover_height_spin: SpinButton::with_range(60.0, 1200.0, 50.0),
+                popover_autohide_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                clipboard_lookup_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                auto_crop_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                cover_page_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                viewport_anchor_spin: SpinButton::with_range(0.0, 90.0, 5.0),
+                annotation_wrap_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                presentation_fade_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                prefetch_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                prefetch_online_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                definition_cache_label: Label::builder().css_classes(["dim-label"]).build(),
+                clear_cache_button: Button::builder()
+                    .label("Clear Cache")
+                    .valign(gtk::Align::Center)
+                    .build(),
+                synctex_editor_entry: Entry::builder()
+                    .placeholder_text("code --goto %{input}:%{line}")
+                    .valign(gtk::Align::Center)
+                    .build(),
+                web_search_dropdown: DropDown::new(
+                    Some(StringList::new(&search_engines)),
+                    None::<gtk::Expression>,
+                ),
+                web_search_entry: Entry::builder()
+                    .placeholder_text("https://example.org/search?q=%s")
+                    .valign(gtk::Align::Center)
+                    .build(),
+                notes_sync_dir_entry: Entry::builder()
+                    .placeholder_text("Off, e.g. ~/notes/eyers")
+                    .valign(gtk::Align::Center)
+                    .build(),
+                post_export_view: TextView::builder()
+                    .monospace(true)
+                    .wrap_mode(gtk::WrapMode::WordChar)
+                    .top_margin(4)
+                    .bottom_margin(4)
+                    .left_margin(4)
+                    .right_margin(4)
+                    .build(),
+                api_port_spin: SpinButton::with_range(0.0, 65535.0, 1.0),
+                startup_mode_dropdown: DropDown::new(
+                    Some(StringList::new(&startup_modes)),
+                    None::<gtk::Expression>,
+                ),
+                startup_header_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                startup_status_switch: Switch::builder().valign(gtk::Align::Center).build(),
+                annotation_panel_dropdown: DropDown::new(
+                    Some(StringList::new(&panel_positions)),
+                    None::<gtk::Expression>,
+                ),
+                selected_export_sort: Cell::new(0),
+                ui_font_family: RefCell::new(String::new()),
+                ui_font_size: Cell::new(0.0),
+                popover_max_width: Cell::new(definition_popover::DEFAULT_MAX_WIDTH),
+                popover_max_height: Cell::new(definition_popover::DEFAULT_MAX_HEIGHT),
+                popover_autohide: Cell::new(false),
+                clipboard_lookup: Cell::new(false),
+                auto_crop: Cell::new(false),
+                cover_page: Cell::new(false),
+                viewport_anchor: Cell::new(0.2),
+                annotation_wrap: Cell::new(false),
+                presentation_fade: Cell::new(true),
+                prefetch_definitions: Cell::new(false),
+                prefetch_online: Cell::new(false),
+                synctex_editor: RefCell::new(String::new()),
+                web_search: RefCell::new(String::new()),
+                notes_sync_dir: RefCell::new(String::new()),
+                post_export_commands: RefCell::new(String::new()),
+                api_port: Cell::new(0),
+                selected_startup_mode: Cell::new(0),
+                startup_header_bar: Cell::new(true),
+                startup_status_bar: Cell::new(true),
+                selected_annotation_panel: Cell::new(0),
+                selected_theme: Cell::new(0),
             }
         }
     }
@@ -63,7 +406,7 @@ impl SettingsWindow {
             .property("modal", true)
             .property("title", "Settings")
             .property("default-width", 400)
-            .property("default-height", 200)
+            .property("default-height", 620)
             .property("resizable", false)
             .build()
     }
@@ -106,7 +449,7 @@ impl SettingsWindow {
 
         // Description label
         let desc_label = Label::builder()
-            .label("Select the language for dictionary definitions.\nEnglish: Look up English words, get Spanish translations.\nSpanish: Look up Spanish words, get English translations.")
+            .label("Language of the words looked up. The list depends on the dictionary source: the built-in database has English and Spanish, each translated into the other, the online dictionary English, and local dictionaries are searched whatever the language. Type to search the list.")
             .halign(gtk::Align::Start)
             .wrap(true)
             .css_classes(["dim-label"])
@@ -116,6 +459,369 @@ impl SettingsWindow {
         main_box.append(&lang_box);
         main_box.append(&desc_label);
 
+        Self::append_setting_row(
+            &main_box,
+            "Language for This Document Only:",
+            &imp.language_per_document_switch,
+            "Keep the language above for the open document, while other documents use the \
+             global one. Useful when reading English and Spanish books in turn.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Dictionary Source:",
+            &imp.dictionary_source_dropdown,
+            "Built-in: the Wiktionary database in ~/.local/share/eyers. Online: the Free \
+             Dictionary API, which needs network access. Local directory: StarDict (.ifo) and \
+             dictd (.index) dictionaries, read without network access.",
+        );
+
+        let choose_dir_button = Button::builder()
+            .icon_name("folder-open-symbolic")
+            .tooltip_text("Choose Directory")
+            .valign(gtk::Align::Center)
+            .build();
+        imp.dictionary_dir_box.append(&imp.dictionary_dir_entry);
+        imp.dictionary_dir_box.append(&choose_dir_button);
+
+        Self::append_setting_row(
+            &main_box,
+            "Local Dictionary Directory:",
+            &imp.dictionary_dir_box,
+            "Directory searched, with its direct subdirectories, for dictionary files. \
+             Compressed .dict.dz and .idx.gz files are read as well.",
+        );
+
+        let window_weak = self.downgrade();
+        choose_dir_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.choose_dictionary_dir();
+            }
+        });
+
+        Self::append_setting_row(
+            &main_box,
+            "Prefetch Definitions:",
+            &imp.prefetch_switch,
+            "In Visual mode, look up the long words of the cursor's line in the background \
+             once it stays there a moment, so d shows them at once. Only the built-in database \
+             and local dictionaries are queried.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Prefetch Online:",
+            &imp.prefetch_online_switch,
+            "Also prefetch from the online dictionary, sending the words of the line to it \
+             while you read.",
+        );
+
+        let definition_cache_box = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(12)
+            .build();
+        definition_cache_box.append(&imp.definition_cache_label);
+        definition_cache_box.append(&imp.clear_cache_button);
+        self.update_definition_cache_label();
+
+        Self::append_setting_row(
+            &main_box,
+            "Definition Cache:",
+            &definition_cache_box,
+            &format!(
+                "Definitions fetched from the online dictionary are kept, so looking a word up \
+                 again is instant and works offline. The least recently used are dropped past \
+                 {} words.",
+                dictionary::DEFINITION_CACHE_SIZE
+            ),
+        );
+
+        let window_weak = self.downgrade();
+        imp.clear_cache_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                if !dictionary::clear_definition_cache() {
+                    eprintln!("Failed to clear the definition cache");
+                }
+                window.update_definition_cache_label();
+            }
+        });
+
+        Self::append_setting_row(
+            &main_box,
+            "Translation Service:",
+            &imp.translation_backend_dropdown,
+            "Service translating selections: a LibreTranslate server, DeepL (needs an API key) \
+             or a local command.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "LibreTranslate URL:",
+            &imp.libretranslate_url_entry,
+            "Translate endpoint of the LibreTranslate server, a self-hosted one by default.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "LibreTranslate API Key:",
+            &imp.libretranslate_key_entry,
+            "Only needed by servers requiring one, like libretranslate.com.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "DeepL API Key:",
+            &imp.deepl_key_entry,
+            "Key of a DeepL API Free (ending in :fx) or Pro account.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Translation Command:",
+            &imp.translation_command_entry,
+            "Command reading the text on stdin and printing the translation. %{source} and \
+             %{target} are replaced by the language codes.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Definition Click:",
+            &imp.lookup_modifier_dropdown,
+            "Click with this modifier to look up a word while definitions are switched off, \
+             so lookups and drag-selection work together. On documents with SyncTeX data, \
+             Ctrl+click does a reverse search instead.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Copied Line Breaks:",
+            &imp.join_style_dropdown,
+            "How multi-line selections are joined when yanked to the clipboard.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Bionic Reading (experimental):",
+            &imp.bionic_switch,
+            "Redraw the text with the first half of each word in bold.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Show Word Boxes (debug):",
+            &imp.word_boxes_switch,
+            "Outline the words and lines Eyers found on each page, numbered in reading \
+             order. A screenshot of them helps reporting wrong reading order or word splits.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Use Page Labels:",
+            &imp.page_labels_switch,
+            "Make [count]gg go to the printed page number, like the 12 of a page numbered \
+             after roman-numbered front matter.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Group Export by Chapter:",
+            &imp.export_group_switch,
+            "Put exported annotations under the heading of their chapter.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Group Export by Tag:",
+            &imp.export_group_tag_switch,
+            "Put exported annotations under a heading per tag instead, listing those with several tags under each.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Export Order:",
+            &imp.export_sort_dropdown,
+            "Order of the annotations in the export (inside each chapter when grouped).",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Live Notes Sync:",
+            &imp.notes_sync_dir_entry,
+            "Directory where each document's annotations are re-exported to markdown whenever one is saved or deleted, to keep a notes vault current. The file is rewritten, so edit notes in Eyers.",
+        );
+
+        let post_export_scrolled = ScrolledWindow::builder()
+            .child(&imp.post_export_view)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .min_content_width(280)
+            .min_content_height(72)
+            .has_frame(true)
+            .build();
+
+        Self::append_setting_row(
+            &main_box,
+            "Post-Export Commands:",
+            &post_export_scrolled,
+            "Run after annotations are exported to markdown, one command per line, in the directory of the exported file. %{output} is replaced by the file, %{dir} by its directory and %{document} by the name of the document, e.g. pandoc %{output} -o %{document}.pdf. Lines starting with # are skipped.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "HTTP API Port:",
+            &imp.api_port_spin,
+            "Serve annotations and reading positions as JSON on 127.0.0.1 at this port (e.g. 8765) for browser extensions and note-taking plugins. Only reachable from this computer.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Theme:",
+            &imp.theme_dropdown,
+            "Colors of the panels, bars and highlights. ~/.config/eyers/style.css is loaded over \
+             any theme and reloaded when saved; its @define-color eyers_* colors recolor the \
+             highlights on the pages too.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Panel Font:",
+            &imp.font_button,
+            "Font of definitions, translations and annotation notes.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Panel Font Size:",
+            &imp.font_size_spin,
+            "Text size in points for the same popovers and panels, independent of the zoom.",
+        );
+
+        let popover_size_box = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(4)
+            .build();
+        popover_size_box.append(&imp.popover_width_spin);
+        popover_size_box.append(&Label::new(Some("×")));
+        popover_size_box.append(&imp.popover_height_spin);
+
+        Self::append_setting_row(
+            &main_box,
+            "Definition Popover Size:",
+            &popover_size_box,
+            "Largest width × height the definition popover grows to. Resizing a popover with its corner grip keeps that size until this changes.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Close Popover on Outside Click:",
+            &imp.popover_autohide_switch,
+            "Clicking anywhere else closes the definition popover instead of needing its Close button. The click only closes it, so the selection stays as it was.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Look Up Copied Words:",
+            &imp.clipboard_lookup_switch,
+            "Copying a word or a short phrase in any other application, like a browser, shows its definition in Eyers. Keep the window next to what you read.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Two-Page Cover:",
+            &imp.cover_page_switch,
+            "In the two-page layout (D), show the first page on its own so books pair their left and right pages.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Crop Margins:",
+            &imp.auto_crop_switch,
+            "Cut the blank margins off the pages so the text fills more of the window. :crop edit moves the edges by hand.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Presentation Fade:",
+            &imp.presentation_fade_switch,
+            "In presentation mode (f or F11), fade each page in when turning to it.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Wrap Annotation Jumps:",
+            &imp.annotation_wrap_switch,
+            "]a after the last annotation goes back to the first one, and [a before the first one to the last.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Cursor Anchor (%):",
+            &imp.viewport_anchor_spin,
+            "How far down the viewport the Visual mode cursor lands after Ctrl+d/Ctrl+u, page jumps and table of contents entries. 50 puts it mid-screen.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "SyncTeX Editor:",
+            &imp.synctex_editor_entry,
+            "Command run on Ctrl+click for documents with a SyncTeX file. %{input}, %{line} and %{column} are replaced by the source position.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Web Search:",
+            &imp.web_search_dropdown,
+            "Where gx searches the selection, or the word under the cursor. gX always searches Wikipedia.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Search URL:",
+            &imp.web_search_entry,
+            "%s is replaced by the searched text and %l by the dictionary language code, e.g. en.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Start In:",
+            &imp.startup_mode_dropdown,
+            "Whether clicks look up definitions, translate, or do neither when Eyers starts. \
+             The dictionary source above is remembered as well.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Show Header Bar at Start:",
+            &imp.startup_header_switch,
+            "Start with the header bar shown. b still toggles it while reading.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Show Status Bar at Start:",
+            &imp.startup_status_switch,
+            "Start with the status bar shown at the bottom of the window.",
+        );
+
+        Self::append_setting_row(
+            &main_box,
+            "Annotation Panel:",
+            &imp.annotation_panel_dropdown,
+            "Where notes are written: under the pages, right of them, which leaves their whole \
+             height for reading on wide screens, or in a window of its own.",
+        );
+
+        let reset_font_button = Button::builder()
+            .label("Reset Font")
+            .halign(gtk::Align::Start)
+            .build();
+        let window_weak = self.downgrade();
+        reset_font_button.connect_clicked(move |_| {
+            if let Some(window) = window_weak.upgrade() {
+                window.set_ui_font(&UiFont::default());
+            }
+        });
+        main_box.append(&reset_font_button);
+
         // Close button
         let close_button = Button::builder()
             .label("Close")
@@ -140,31 +846,658 @@ impl SettingsWindow {
         imp.language_dropdown
             .connect_selected_notify(move |dropdown| {
                 if let Some(window) = window_weak.upgrade() {
-                    window.set_selected_language(dropdown.selected());
+                    if !window.imp().updating_languages.get() {
+                        window.set_selected_language(dropdown.selected());
+                    }
+                }
+            });
+
+        self.update_language_list();
+        self.connect_selected_dictionary_source_notify(|window| window.update_language_list());
+
+        self.bind_property(
+            "language-per-document",
+            &imp.language_per_document_switch,
+            "active",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        self.bind_property(
+            "selected-dictionary-source",
+            &imp.dictionary_source_dropdown,
+            "selected",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        self.bind_property("local-dictionary-dir", &imp.dictionary_dir_entry, "text")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        // The directory only matters for local dictionaries
+        self.bind_property(
+            "selected-dictionary-source",
+            &imp.dictionary_dir_box,
+            "sensitive",
+        )
+        .transform_to(|_, selected: u32| Some(selected == 2))
+        .sync_create()
+        .build();
+
+        self.bind_property(
+            "selected-translation-backend",
+            &imp.translation_backend_dropdown,
+            "selected",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        for (property, entry) in [
+            (
+                "libretranslate-url",
+                imp.libretranslate_url_entry.upcast_ref::<gtk::Widget>(),
+            ),
+            (
+                "libretranslate-api-key",
+                imp.libretranslate_key_entry.upcast_ref(),
+            ),
+            ("deepl-api-key", imp.deepl_key_entry.upcast_ref()),
+            (
+                "translation-command",
+                imp.translation_command_entry.upcast_ref(),
+            ),
+        ] {
+            self.bind_property(property, entry, "text")
+                .bidirectional()
+                .sync_create()
+                .build();
+        }
+
+        // Each field only matters for its own backend
+        for (backend, entry) in [
+            (
+                TranslationBackend::LibreTranslate,
+                imp.libretranslate_url_entry.upcast_ref::<gtk::Widget>(),
+            ),
+            (
+                TranslationBackend::LibreTranslate,
+                imp.libretranslate_key_entry.upcast_ref(),
+            ),
+            (TranslationBackend::DeepL, imp.deepl_key_entry.upcast_ref()),
+            (
+                TranslationBackend::Command,
+                imp.translation_command_entry.upcast_ref(),
+            ),
+        ] {
+            self.bind_property("selected-translation-backend", entry, "sensitive")
+                .transform_to(move |_, selected: u32| {
+                    Some(TranslationBackend::ALL.get(selected as usize) == Some(&backend))
+                })
+                .sync_create()
+                .build();
+        }
+
+        self.bind_property("bionic-reading", &imp.bionic_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("show-word-boxes", &imp.word_boxes_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("use-page-labels", &imp.page_labels_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("clipboard-lookup", &imp.clipboard_lookup_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("auto-crop", &imp.auto_crop_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("popover-autohide", &imp.popover_autohide_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("cover-page", &imp.cover_page_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property(
+            "export-group-by-chapter",
+            &imp.export_group_switch,
+            "active",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        self.bind_property(
+            "export-group-by-tag",
+            &imp.export_group_tag_switch,
+            "active",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        self.bind_property(
+            "selected-export-sort",
+            &imp.export_sort_dropdown,
+            "selected",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        imp.popover_width_spin
+            .set_value(self.popover_max_width() as f64);
+        imp.popover_height_spin
+            .set_value(self.popover_max_height() as f64);
+
+        let window_weak = self.downgrade();
+        imp.popover_width_spin.connect_value_changed(move |spin| {
+            if let Some(window) = window_weak.upgrade() {
+                window.set_popover_max_width(spin.value_as_int());
+            }
+        });
+
+        let window_weak = self.downgrade();
+        imp.popover_height_spin.connect_value_changed(move |spin| {
+            if let Some(window) = window_weak.upgrade() {
+                window.set_popover_max_height(spin.value_as_int());
+            }
+        });
+
+        // A size of 0 keeps the stylesheet default
+        imp.font_size_spin.connect_output(|spin| {
+            if spin.value() == 0.0 {
+                spin.set_text("Default");
+                glib::Propagation::Stop
+            } else {
+                glib::Propagation::Proceed
+            }
+        });
+
+        self.bind_property("synctex-editor", &imp.synctex_editor_entry, "text")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("web-search", &imp.web_search_entry, "text")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        // Picking an engine fills in its template; editing the template by
+        // hand selects Custom
+        self.bind_property("web-search", &imp.web_search_dropdown, "selected")
+            .transform_to(|_, template: String| {
+                let index = SearchEngine::from_template(&template)
+                    .and_then(|engine| SearchEngine::ALL.iter().position(|e| *e == engine))
+                    .unwrap_or(SearchEngine::ALL.len());
+                Some(index as u32)
+            })
+            .transform_from(|_, selected: u32| {
+                SearchEngine::ALL
+                    .get(selected as usize)
+                    .map(|engine| engine.template().to_string())
+            })
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("notes-sync-dir", &imp.notes_sync_dir_entry, "text")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property(
+            "post-export-commands",
+            &imp.post_export_view.buffer(),
+            "text",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        self.bind_property("api-port", &imp.api_port_spin, "value")
+            .transform_to(|_, port: u32| Some(port as f64))
+            .transform_from(|_, value: f64| Some(value as u32))
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        imp.api_port_spin.connect_output(|spin| {
+            if spin.value() == 0.0 {
+                spin.set_text("Off");
+                glib::Propagation::Stop
+            } else {
+                glib::Propagation::Proceed
+            }
+        });
+
+        self.bind_property(
+            "selected-startup-mode",
+            &imp.startup_mode_dropdown,
+            "selected",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        self.bind_property("startup-header-bar", &imp.startup_header_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("startup-status-bar", &imp.startup_status_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property(
+            "selected-annotation-panel",
+            &imp.annotation_panel_dropdown,
+            "selected",
+        )
+        .bidirectional()
+        .sync_create()
+        .build();
+
+        self.bind_property("selected-theme", &imp.theme_dropdown, "selected")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("annotation-wrap", &imp.annotation_wrap_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("presentation-fade", &imp.presentation_fade_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("prefetch-definitions", &imp.prefetch_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("prefetch-online", &imp.prefetch_online_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property(
+            "prefetch-definitions",
+            &imp.prefetch_online_switch,
+            "sensitive",
+        )
+        .sync_create()
+        .build();
+
+        self.bind_property("viewport-anchor", &imp.viewport_anchor_spin, "value")
+            .transform_to(|_, anchor: f64| Some(anchor * 100.0))
+            .transform_from(|_, percent: f64| Some(percent / 100.0))
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.bind_property("ui-font-size", &imp.font_size_spin, "value")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        let window_weak = self.downgrade();
+        imp.font_button.connect_font_desc_notify(move |button| {
+            if let Some(window) = window_weak.upgrade() {
+                let family = button
+                    .font_desc()
+                    .and_then(|desc| desc.family())
+                    .map(|family| family.to_string())
+                    .unwrap_or_default();
+                window.set_ui_font_family(family);
+            }
+        });
+
+        let window_weak = self.downgrade();
+        imp.join_style_dropdown
+            .connect_selected_notify(move |dropdown| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.set_selected_join_style(dropdown.selected());
+                }
+            });
+
+        let window_weak = self.downgrade();
+        imp.lookup_modifier_dropdown
+            .connect_selected_notify(move |dropdown| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.set_selected_lookup_modifier(dropdown.selected());
                 }
             });
     }
 
+    /// Append a "label: control" row followed by a dimmed description
+    fn append_setting_row(
+        container: &Box,
+        label: &str,
+        control: &impl IsA<gtk::Widget>,
+        description: &str,
+    ) {
+        let row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(12)
+            .build();
+        row.add_css_class("settings-lang-row");
+
+        let row_label = Label::builder()
+            .label(label)
+            .halign(gtk::Align::Start)
+            .hexpand(true)
+            .build();
+        row_label.add_css_class("settings-lang-label");
+
+        control.add_css_class("settings-lang-dropdown");
+        row.append(&row_label);
+        row.append(control);
+
+        let desc_label = Label::builder()
+            .label(description)
+            .halign(gtk::Align::Start)
+            .wrap(true)
+            .css_classes(["dim-label"])
+            .build();
+        desc_label.add_css_class("settings-description");
+
+        container.append(&row);
+        container.append(&desc_label);
+    }
+
     /// Returns the currently selected language
     pub fn language(&self) -> Language {
-        match self.selected_language() {
-            1 => Language::Spanish,
-            _ => Language::English,
-        }
+        self.imp()
+            .languages
+            .borrow()
+            .get(self.selected_language() as usize)
+            .copied()
+            .unwrap_or_default()
     }
 
-    /// Sets the language in the dropdown
+    /// Sets the language in the dropdown, if the dictionary source supports it
     pub fn set_language(&self, lang: Language) {
-        let idx = match lang {
-            Language::English => 0,
-            Language::Spanish => 1,
+        let idx = self
+            .imp()
+            .languages
+            .borrow()
+            .iter()
+            .position(|l| *l == lang);
+        if let Some(idx) = idx {
+            self.imp().language_dropdown.set_selected(idx as u32);
+        }
+    }
+
+    /// Offer the languages of the selected dictionary source. The current language is
+    /// kept when the source supports it, otherwise the source's default is selected.
+    fn update_language_list(&self) {
+        let imp = self.imp();
+        let backend = self.dictionary_source().backend();
+        let languages = backend.languages();
+        let current = self.language();
+        let selected = if languages.contains(&current) {
+            current
+        } else {
+            backend.default_language()
         };
-        self.imp().language_dropdown.set_selected(idx);
+
+        let names: Vec<&str> = languages.iter().map(|l| l.name()).collect();
+        let idx = languages.iter().position(|l| *l == selected).unwrap_or(0);
+        imp.languages.replace(languages);
+
+        imp.updating_languages.set(true);
+        imp.language_dropdown
+            .set_model(Some(&StringList::new(&names)));
+        imp.language_dropdown.set_selected(idx as u32);
+        imp.updating_languages.set(false);
+        self.set_selected_language(idx as u32);
     }
 
-    /// Returns a reference to the language dropdown for signal connections
-    pub fn language_dropdown(&self) -> &DropDown {
-        &self.imp().language_dropdown
+    /// Enable the per-document language switch, only meaningful with a document open
+    pub fn set_has_document(&self, has_document: bool) {
+        self.imp()
+            .language_per_document_switch
+            .set_sensitive(has_document);
+    }
+
+    /// Returns the selected dictionary source
+    pub fn dictionary_source(&self) -> DictionarySource {
+        match self.selected_dictionary_source() {
+            1 => DictionarySource::Online,
+            2 => DictionarySource::Local(expand_home(self.local_dictionary_dir().trim())),
+            _ => DictionarySource::Builtin,
+        }
+    }
+
+    /// Sets the dictionary source in the controls
+    pub fn set_dictionary_source(&self, source: &DictionarySource) {
+        match source {
+            DictionarySource::Builtin => self.set_selected_dictionary_source(0),
+            DictionarySource::Online => self.set_selected_dictionary_source(1),
+            DictionarySource::Local(dir) => {
+                self.set_local_dictionary_dir(dir.display().to_string());
+                self.set_selected_dictionary_source(2);
+            }
+        }
+    }
+
+    /// Show how many definitions are cached
+    fn update_definition_cache_label(&self) {
+        let count = dictionary::definition_cache_len();
+        let imp = self.imp();
+        imp.definition_cache_label.set_label(&match count {
+            1 => "1 word".to_string(),
+            _ => format!("{} words", count),
+        });
+        imp.clear_cache_button.set_sensitive(count > 0);
+    }
+
+    fn choose_dictionary_dir(&self) {
+        let dialog = gtk::FileDialog::builder()
+            .title("Select a Dictionary Directory")
+            .build();
+
+        let window_weak = self.downgrade();
+        dialog.select_folder(Some(self), None::<&gtk::gio::Cancellable>, move |result| {
+            let (Some(window), Ok(folder)) = (window_weak.upgrade(), result) else {
+                return;
+            };
+            if let Some(path) = folder.path() {
+                window.set_local_dictionary_dir(path.display().to_string());
+            }
+        });
+    }
+
+    /// Returns the currently selected copy join style
+    pub fn join_style(&self) -> CopyJoinStyle {
+        CopyJoinStyle::ALL
+            .get(self.selected_join_style() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets the copy join style in the dropdown
+    pub fn set_join_style(&self, style: CopyJoinStyle) {
+        let idx = CopyJoinStyle::ALL
+            .iter()
+            .position(|s| *s == style)
+            .unwrap_or(0);
+        self.imp().join_style_dropdown.set_selected(idx as u32);
+    }
+
+    /// Returns the currently selected definition lookup modifier
+    pub fn lookup_modifier(&self) -> LookupModifier {
+        LookupModifier::ALL
+            .get(self.selected_lookup_modifier() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets the definition lookup modifier in the dropdown
+    pub fn set_lookup_modifier(&self, modifier: LookupModifier) {
+        let idx = LookupModifier::ALL
+            .iter()
+            .position(|m| *m == modifier)
+            .unwrap_or(0);
+        self.imp().lookup_modifier_dropdown.set_selected(idx as u32);
+    }
+
+    /// Returns the translation backend and credentials currently entered
+    pub fn translator_config(&self) -> TranslatorConfig {
+        TranslatorConfig {
+            backend: TranslationBackend::ALL
+                .get(self.selected_translation_backend() as usize)
+                .copied()
+                .unwrap_or_default(),
+            libretranslate_url: self.libretranslate_url(),
+            libretranslate_api_key: self.libretranslate_api_key(),
+            deepl_api_key: self.deepl_api_key(),
+            command: self.translation_command(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the translation backend and credentials in the controls
+    pub fn set_translator_config(&self, config: &TranslatorConfig) {
+        let idx = TranslationBackend::ALL
+            .iter()
+            .position(|b| *b == config.backend)
+            .unwrap_or(0);
+        self.set_selected_translation_backend(idx as u32);
+        self.set_libretranslate_url(config.libretranslate_url.clone());
+        self.set_libretranslate_api_key(config.libretranslate_api_key.clone());
+        self.set_deepl_api_key(config.deepl_api_key.clone());
+        self.set_translation_command(config.command.clone());
+    }
+
+    /// Returns the export options currently selected
+    pub fn export_options(&self) -> ExportOptions {
+        ExportOptions {
+            group_by_chapter: self.export_group_by_chapter(),
+            group_by_tag: self.export_group_by_tag(),
+            sort: match self.selected_export_sort() {
+                1 => ExportSort::CreatedAt,
+                _ => ExportSort::Position,
+            },
+            since: None,
+        }
+    }
+
+    /// Sets the export options in the controls
+    pub fn set_export_options(&self, options: ExportOptions) {
+        self.set_export_group_by_chapter(options.group_by_chapter);
+        self.set_export_group_by_tag(options.group_by_tag);
+        self.set_selected_export_sort(match options.sort {
+            ExportSort::Position => 0,
+            ExportSort::CreatedAt => 1,
+        });
+    }
+
+    /// Returns the lookup mode selected for startup
+    pub fn startup_mode(&self) -> StartupMode {
+        StartupMode::ALL
+            .get(self.selected_startup_mode() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets the startup lookup mode in the dropdown
+    pub fn set_startup_mode(&self, mode: StartupMode) {
+        let idx = StartupMode::ALL
+            .iter()
+            .position(|m| *m == mode)
+            .unwrap_or(0);
+        self.set_selected_startup_mode(idx as u32);
+    }
+
+    /// Returns the selected annotation panel position
+    pub fn annotation_panel_position(&self) -> PanelPosition {
+        PanelPosition::ALL
+            .get(self.selected_annotation_panel() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets the annotation panel position in the dropdown
+    pub fn set_annotation_panel_position(&self, position: PanelPosition) {
+        let idx = PanelPosition::ALL
+            .iter()
+            .position(|p| *p == position)
+            .unwrap_or(0);
+        self.set_selected_annotation_panel(idx as u32);
+    }
+
+    /// Returns the selected theme
+    pub fn theme(&self) -> Theme {
+        Theme::ALL
+            .get(self.selected_theme() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets the theme in the dropdown
+    pub fn set_theme(&self, theme: Theme) {
+        let idx = Theme::ALL.iter().position(|t| *t == theme).unwrap_or(0);
+        self.set_selected_theme(idx as u32);
+    }
+
+    /// Returns the selected panel font
+    pub fn ui_font(&self) -> UiFont {
+        let family = self.ui_font_family();
+        let size = self.ui_font_size();
+        UiFont {
+            family: (!family.is_empty()).then_some(family),
+            size: (size > 0.0).then_some(size),
+        }
+    }
+
+    /// Sets the panel font in the controls
+    pub fn set_ui_font(&self, font: &UiFont) {
+        let desc = font
+            .family
+            .as_deref()
+            .map(gtk::pango::FontDescription::from_string);
+        self.imp()
+            .font_button
+            .set_property("font-desc", desc.as_ref());
+        self.set_ui_font_family(font.family.clone().unwrap_or_default());
+        self.set_ui_font_size(font.size.unwrap_or(0.0));
+    }
+
+    /// Sets the definition popover maximum size in the controls
+    pub fn set_popover_max_size(&self, width: i32, height: i32) {
+        let imp = self.imp();
+        imp.popover_width_spin.set_value(width as f64);
+        imp.popover_height_spin.set_value(height as f64);
+    }
+
+    /// Returns a reference to the join style dropdown for signal connections
+    pub fn join_style_dropdown(&self) -> &DropDown {
+        &self.imp().join_style_dropdown
     }
 }
 
@@ -173,3 +1506,13 @@ impl Default for SettingsWindow {
         glib::Object::builder().build()
     }
 }
+
+/// Expand a leading `~` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest.trim_start_matches('/')))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
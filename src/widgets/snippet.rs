@@ -0,0 +1,65 @@
+use gtk::prelude::*;
+use gtk::{gdk, graphene, gsk, pango};
+
+const FOOTER_PADDING: f32 = 12.0;
+const FOOTER_FONT_SIZE: i32 = 11;
+
+/// Render `area` of `widget` (in the widget's own coordinates) to a texture, with
+/// an attribution `footer` line below it. Everything drawn on the widget, including
+/// the highlight overlays, ends up in the image.
+pub fn render_snippet(
+    widget: &impl IsA<gtk::Widget>,
+    area: &graphene::Rect,
+    footer: &str,
+) -> Option<gdk::Texture> {
+    let widget = widget.as_ref();
+    let renderer = widget.native()?.renderer()?;
+
+    let width = area.width();
+    if width < 1.0 || area.height() < 1.0 {
+        return None;
+    }
+
+    let layout = widget.create_pango_layout(Some(footer));
+    let mut font = pango::FontDescription::new();
+    font.set_size(FOOTER_FONT_SIZE * pango::SCALE);
+    layout.set_font_description(Some(&font));
+    layout.set_width(((width - 2.0 * FOOTER_PADDING).max(1.0) as i32) * pango::SCALE);
+    layout.set_wrap(pango::WrapMode::WordChar);
+    let footer_height = layout.pixel_size().1 as f32 + 2.0 * FOOTER_PADDING;
+    let height = area.height() + footer_height;
+
+    let snapshot = gtk::Snapshot::new();
+    snapshot.append_color(
+        &gdk::RGBA::WHITE,
+        &graphene::Rect::new(0.0, 0.0, width, height),
+    );
+
+    // The page content, shifted so the area starts at the origin
+    snapshot.save();
+    snapshot.translate(&graphene::Point::new(-area.x(), -area.y()));
+    snapshot.push_clip(area);
+    gtk::WidgetPaintable::new(Some(widget)).snapshot(
+        &snapshot,
+        widget.width() as f64,
+        widget.height() as f64,
+    );
+    snapshot.pop();
+    snapshot.restore();
+
+    // Separator and attribution
+    snapshot.append_color(
+        &gdk::RGBA::new(0.0, 0.0, 0.0, 0.15),
+        &graphene::Rect::new(0.0, area.height(), width, 1.0),
+    );
+    snapshot.save();
+    snapshot.translate(&graphene::Point::new(
+        FOOTER_PADDING,
+        area.height() + FOOTER_PADDING,
+    ));
+    snapshot.append_layout(&layout, &gdk::RGBA::new(0.2, 0.2, 0.2, 1.0));
+    snapshot.restore();
+
+    let node: gsk::RenderNode = snapshot.to_node()?;
+    Some(renderer.render_texture(&node, Some(&graphene::Rect::new(0.0, 0.0, width, height))))
+}
@@ -1,6 +1,10 @@
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
+use std::cell::RefCell;
+
+use crate::modes::command;
+use crate::services::tts::TtsState;
 
 mod imp {
     use super::*;
@@ -9,8 +13,20 @@ mod imp {
     pub struct StatusBar {
         pub center_box: gtk::CenterBox,
         pub mode_label: gtk::Label,
+        /// Share of the document read and the estimated time left
+        pub progress_label: gtk::Label,
+        /// Words and characters of the Visual mode selection
+        pub selection_label: gtk::Label,
         pub pages_indicator_label: gtk::Label,
         pub pdf_name: gtk::Label,
+        pub start_box: gtk::Box,
+        pub command_entry: gtk::Entry,
+        /// Completions cycled through by repeated Tab presses, with the shown one
+        pub completions: RefCell<Option<(Vec<String>, usize)>>,
+        /// Text-to-speech controls, shown while reading aloud
+        pub tts_box: gtk::Box,
+        pub tts_pause_button: gtk::Button,
+        pub tts_stop_button: gtk::Button,
     }
 
     #[glib::object_subclass]
@@ -60,7 +76,59 @@ impl StatusBar {
         // Mode label (left side, before open button)
         imp.mode_label.set_label("NORMAL");
         imp.mode_label.add_css_class("mode-label");
-        center_box.set_start_widget(Some(&imp.mode_label));
+        imp.start_box.set_spacing(8);
+        imp.start_box.append(&imp.mode_label);
+
+        imp.progress_label.add_css_class("progress-label");
+        imp.progress_label.set_visible(false);
+        imp.start_box.append(&imp.progress_label);
+
+        imp.selection_label.add_css_class("selection-count-label");
+        imp.selection_label.set_visible(false);
+        imp.start_box.append(&imp.selection_label);
+
+        // Command line, shown while typing a `:` command
+        imp.command_entry.add_css_class("command-entry");
+        imp.command_entry.set_has_frame(false);
+        imp.command_entry.set_width_chars(30);
+        imp.command_entry.set_visible(false);
+        imp.start_box.append(&imp.command_entry);
+        center_box.set_start_widget(Some(&imp.start_box));
+
+        imp.tts_pause_button
+            .set_icon_name("media-playback-pause-symbolic");
+        imp.tts_pause_button
+            .set_tooltip_text(Some("Pause reading (r)"));
+        imp.tts_pause_button.add_css_class("flat");
+        imp.tts_stop_button
+            .set_icon_name("media-playback-stop-symbolic");
+        imp.tts_stop_button
+            .set_tooltip_text(Some("Stop reading (R)"));
+        imp.tts_stop_button.add_css_class("flat");
+        imp.tts_box.add_css_class("tts-controls");
+        imp.tts_box.append(&imp.tts_pause_button);
+        imp.tts_box.append(&imp.tts_stop_button);
+        imp.tts_box.set_visible(false);
+        imp.start_box.append(&imp.tts_box);
+
+        let key_controller = gtk::EventControllerKey::new();
+        let status_bar_weak = self.downgrade();
+        key_controller.connect_key_pressed(move |_, key, _, _| {
+            if key == gtk::gdk::Key::Escape {
+                if let Some(status_bar) = status_bar_weak.upgrade() {
+                    status_bar.hide_command_line();
+                }
+                return glib::Propagation::Stop;
+            }
+            if key == gtk::gdk::Key::Tab {
+                if let Some(status_bar) = status_bar_weak.upgrade() {
+                    status_bar.complete_command_line();
+                }
+                return glib::Propagation::Stop;
+            }
+            glib::Propagation::Proceed
+        });
+        imp.command_entry.add_controller(key_controller);
 
         imp.pages_indicator_label
             .add_css_class("pages-indicator-label");
@@ -75,10 +143,133 @@ impl StatusBar {
         &self.imp().mode_label
     }
 
+    pub fn command_entry(&self) -> &gtk::Entry {
+        &self.imp().command_entry
+    }
+
+    /// Show the command line with a prompt (`:` for commands, `/` for search) and
+    /// focus it
+    pub fn show_command_line(&self, prompt: &str) {
+        let imp = self.imp();
+        imp.center_box.set_can_focus(true);
+        imp.command_entry.set_text(prompt);
+        imp.command_entry.set_visible(true);
+        imp.command_entry.grab_focus();
+        imp.command_entry.set_position(-1);
+    }
+
+    /// Complete the path typed after `:e`. Fills in the part shared by every
+    /// match, then cycles through the matches on further presses.
+    fn complete_command_line(&self) {
+        let imp = self.imp();
+        let text = imp.command_entry.text().to_string();
+
+        // Still showing a completion of the previous press: move to the next one
+        let next = imp
+            .completions
+            .borrow()
+            .as_ref()
+            .filter(|(completions, index)| completions[*index] == text)
+            .map(|(completions, index)| (index + 1) % completions.len());
+        if let Some(next) = next {
+            if let Some((completions, index)) = imp.completions.borrow_mut().as_mut() {
+                *index = next;
+                self.set_command_text(&completions[next]);
+            }
+            return;
+        }
+
+        let completions = command::path_completions(&text);
+        let prefix = command::common_prefix(&completions);
+        imp.completions.replace(None);
+
+        if completions.len() == 1 || prefix.len() > text.len() {
+            self.set_command_text(&prefix);
+        } else if completions.len() > 1 {
+            self.set_command_text(&completions[0]);
+            imp.completions.replace(Some((completions, 0)));
+        }
+    }
+
+    fn set_command_text(&self, text: &str) {
+        let entry = &self.imp().command_entry;
+        entry.set_text(text);
+        entry.set_position(-1);
+    }
+
+    pub fn hide_command_line(&self) {
+        let imp = self.imp();
+        imp.completions.replace(None);
+        imp.command_entry.set_text("");
+        imp.command_entry.set_visible(false);
+        imp.center_box.set_can_focus(false);
+    }
+
+    pub fn tts_pause_button(&self) -> &gtk::Button {
+        &self.imp().tts_pause_button
+    }
+
+    pub fn tts_stop_button(&self) -> &gtk::Button {
+        &self.imp().tts_stop_button
+    }
+
+    /// Show the text-to-speech controls while reading, with the pause button
+    /// turned into a play button while paused
+    pub fn set_tts_state(&self, state: TtsState) {
+        let imp = self.imp();
+        let active = state != TtsState::Stopped;
+        imp.tts_box.set_visible(active);
+        // The bar ignores the pointer unless it has controls to click
+        imp.center_box.set_can_target(active);
+
+        if state == TtsState::Paused {
+            imp.tts_pause_button
+                .set_icon_name("media-playback-start-symbolic");
+            imp.tts_pause_button
+                .set_tooltip_text(Some("Resume reading (r)"));
+        } else {
+            imp.tts_pause_button
+                .set_icon_name("media-playback-pause-symbolic");
+            imp.tts_pause_button
+                .set_tooltip_text(Some("Pause reading (r)"));
+        }
+    }
+
     pub fn set_mode_text(&self, mode: &str) {
         self.imp().mode_label.set_label(mode);
     }
 
+    /// Show the word and character count of the selection, hidden for None
+    pub fn set_selection_count(&self, count: Option<(usize, usize)>) {
+        let label = &self.imp().selection_label;
+        match count {
+            Some((words, chars)) => {
+                let words_unit = if words == 1 { "word" } else { "words" };
+                let chars_unit = if chars == 1 { "char" } else { "chars" };
+                label.set_label(&format!("{} {} / {} {}", words, words_unit, chars, chars_unit));
+                label.set_visible(true);
+            }
+            None => label.set_visible(false),
+        }
+    }
+
+    /// Show how much of the document was read, with the time left when the
+    /// reading speed is known, hidden for None
+    pub fn set_reading_progress(&self, progress: Option<(f64, Option<&str>)>) {
+        let label = &self.imp().progress_label;
+        match progress {
+            Some((percent, Some(time_left))) => {
+                label.set_label(&format!("{:.0}% · {}", percent, time_left));
+                label.set_visible(true);
+            }
+            Some((percent, None)) => {
+                label.set_label(&format!("{:.0}%", percent));
+                label.set_visible(true);
+            }
+            None => label.set_visible(false),
+        }
+    }
+
     pub fn set_pdf_name(&self, name: &str) {
         self.imp().pdf_name.set_label(name);
     }
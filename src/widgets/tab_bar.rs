@@ -0,0 +1,142 @@
+use gtk::glib;
+use gtk::glib::subclass::Signal;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{Box, Button, Label, Orientation, ScrolledWindow};
+use std::sync::OnceLock;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct TabBar {
+        pub tabs_box: Box,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TabBar {
+        const NAME: &'static str = "TabBar";
+        type Type = super::TabBar;
+        type ParentType = Box;
+    }
+
+    impl ObjectImpl for TabBar {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_widgets();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    Signal::builder("tab-selected")
+                        .param_types([u32::static_type()])
+                        .build(),
+                    Signal::builder("tab-close-requested")
+                        .param_types([u32::static_type()])
+                        .build(),
+                ]
+            })
+        }
+    }
+
+    impl WidgetImpl for TabBar {}
+    impl BoxImpl for TabBar {}
+}
+
+glib::wrapper! {
+    /// One tab per open document, shown once there are two or more
+    pub struct TabBar(ObjectSubclass<imp::TabBar>)
+        @extends Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl TabBar {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    fn setup_widgets(&self) {
+        let imp = self.imp();
+
+        self.set_orientation(Orientation::Horizontal);
+        self.add_css_class("tab-bar");
+        self.set_visible(false);
+
+        imp.tabs_box.set_orientation(Orientation::Horizontal);
+        imp.tabs_box.set_spacing(2);
+
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Automatic)
+            .vscrollbar_policy(gtk::PolicyType::Never)
+            .hexpand(true)
+            .child(&imp.tabs_box)
+            .build();
+        self.append(&scrolled);
+    }
+
+    /// Show a tab per title, `active` highlighted
+    pub fn set_tabs(&self, titles: &[String], active: usize) {
+        let tabs_box = &self.imp().tabs_box;
+        while let Some(child) = tabs_box.first_child() {
+            tabs_box.remove(&child);
+        }
+
+        for (index, title) in titles.iter().enumerate() {
+            tabs_box.append(&self.build_tab(index, title, index == active));
+        }
+
+        self.set_visible(titles.len() > 1);
+    }
+
+    fn build_tab(&self, index: usize, title: &str, active: bool) -> Box {
+        let tab = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .css_classes(["tab"])
+            .build();
+        if active {
+            tab.add_css_class("tab-active");
+        }
+
+        let label = Label::builder()
+            .label(format!("{} {}", index + 1, title))
+            .ellipsize(gtk::pango::EllipsizeMode::Middle)
+            .max_width_chars(24)
+            .build();
+        let select_button = Button::builder()
+            .child(&label)
+            .tooltip_text(title)
+            .css_classes(["flat"])
+            .build();
+        let close_button = Button::builder()
+            .icon_name("window-close-symbolic")
+            .tooltip_text("Close tab (:q)")
+            .css_classes(["flat", "tab-close"])
+            .build();
+
+        let bar_weak = self.downgrade();
+        select_button.connect_clicked(move |_| {
+            if let Some(bar) = bar_weak.upgrade() {
+                bar.emit_by_name::<()>("tab-selected", &[&(index as u32)]);
+            }
+        });
+
+        let bar_weak = self.downgrade();
+        close_button.connect_clicked(move |_| {
+            if let Some(bar) = bar_weak.upgrade() {
+                bar.emit_by_name::<()>("tab-close-requested", &[&(index as u32)]);
+            }
+        });
+
+        tab.append(&select_button);
+        tab.append(&close_button);
+        tab
+    }
+}
+
+impl Default for TabBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,6 @@
 use crate::modes::WordCursor;
 use crate::objects::annotation_object::AnnotationObject;
-use crate::services::annotations::Annotation;
+use crate::services::annotations::{self, Annotation, AnnotationColor, AnnotationId};
 use glib::signal::SignalHandlerId;
 use glib::subclass::Signal;
 use gtk::CustomSorter;
@@ -13,13 +13,18 @@ use gtk::{Box, Button, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow,
 use std::cell::{Cell, OnceCell, RefCell};
 use std::sync::OnceLock;
 
-use crate::services::bookmarks::BookmarkEntry;
+use crate::services::bookmarks::{self, BookmarkEntry, ChapterProgress};
+use crate::services::marks::Mark;
+use crate::services::recent_files;
+use std::collections::HashSet;
 
 #[derive(Default, Copy, Clone)]
 pub enum TocMode {
     Annotations,
     #[default]
     Chapters,
+    /// The positions stored with `m{a-z}`
+    Marks,
 }
 
 mod imp {
@@ -32,6 +37,8 @@ mod imp {
     pub struct TocChapterRow {
         pub page_index: Cell<u16>,
         pub depth: Cell<usize>,
+        pub title: RefCell<String>,
+        pub progress: Label,
     }
 
     #[glib::object_subclass]
@@ -45,10 +52,30 @@ mod imp {
     impl WidgetImpl for TocChapterRow {}
     impl ListBoxRowImpl for TocChapterRow {}
 
+    #[derive(Default)]
+    pub struct TocMarkRow {
+        pub letter: Cell<char>,
+        pub cursor: Cell<(usize, usize)>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TocMarkRow {
+        const NAME: &'static str = "TocMarkRow";
+        type Type = super::TocMarkRow;
+        type ParentType = ListBoxRow;
+    }
+
+    impl ObjectImpl for TocMarkRow {}
+    impl WidgetImpl for TocMarkRow {}
+    impl ListBoxRowImpl for TocMarkRow {}
+
     #[derive(Default)]
     pub struct TocAnnotationRow {
+        pub color: Label,
         pub title: Label,
         pub subtitle: Label,
+        /// Chips of the annotations linked as "see also"
+        pub links_box: Box,
         pub page_index: Label,
         pub edit_button: Button,
         pub delete_button: Button,
@@ -75,8 +102,22 @@ mod imp {
         pub mode: Cell<TocMode>,
         pub stack: Stack,
         pub annotations_store: OnceCell<gio::ListStore>,
+        /// Hides annotations of other colors than `color_filter`
+        pub annotations_filter: OnceCell<gtk::CustomFilter>,
+        pub color_filter: Cell<Option<AnnotationColor>>,
+        pub color_filter_dropdown: gtk::DropDown,
+        /// Hides annotations without `tag_filter`, picked among `tags`
+        pub tag_filter: RefCell<Option<String>>,
+        pub tags: RefCell<Vec<String>>,
+        pub tag_filter_dropdown: gtk::DropDown,
+        /// Linked annotation pairs, shown as chips on the rows
+        pub annotation_links: RefCell<Vec<(AnnotationId, AnnotationId)>>,
+        /// Filters chapters by title and annotations by text, note or tags
+        pub search_entry: gtk::SearchEntry,
+        pub search_query: RefCell<String>,
         pub list_view_annotations: ListView,
         pub list_box_chapters: ListBox,
+        pub list_box_marks: ListBox,
         pub close_button: Button,
     }
 
@@ -126,6 +167,7 @@ impl TocChapterRow {
         let row: TocChapterRow = glib::Object::builder().build();
         row.imp().page_index.set(page_index);
         row.imp().depth.set(depth);
+        row.imp().title.replace(title.to_string());
 
         let container = Box::builder()
             .orientation(Orientation::Horizontal)
@@ -146,6 +188,11 @@ impl TocChapterRow {
         label.add_css_class("toc-chapter-title");
         container.append(&label);
 
+        let progress = &row.imp().progress;
+        progress.add_css_class("toc-progress");
+        container.append(progress);
+        row.set_progress(ChapterProgress::Unread);
+
         let label = Label::new(Some(&page_index.to_string()));
         label.set_xalign(0.0);
         label.set_hexpand(false);
@@ -164,6 +211,80 @@ impl TocChapterRow {
     pub fn depth(&self) -> usize {
         self.imp().depth.get()
     }
+
+    pub fn title(&self) -> String {
+        self.imp().title.borrow().clone()
+    }
+
+    pub fn set_progress(&self, progress: ChapterProgress) {
+        let label = &self.imp().progress;
+        for class in [
+            "toc-progress-unread",
+            "toc-progress-partial",
+            "toc-progress-done",
+        ] {
+            label.remove_css_class(class);
+        }
+
+        let (symbol, class, tooltip) = match progress {
+            ChapterProgress::Unread => ("○", "toc-progress-unread", "Unread"),
+            ChapterProgress::Partial => ("◐", "toc-progress-partial", "Partially read"),
+            ChapterProgress::Done => ("●", "toc-progress-done", "Read"),
+        };
+        label.set_text(symbol);
+        label.add_css_class(class);
+        label.set_tooltip_text(Some(tooltip));
+    }
+}
+
+glib::wrapper! {
+    pub struct TocMarkRow(ObjectSubclass<imp::TocMarkRow>)
+        @extends ListBoxRow, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Actionable;
+}
+
+impl TocMarkRow {
+    pub fn new(mark: &Mark) -> Self {
+        let row: TocMarkRow = glib::Object::builder().build();
+        row.imp().letter.set(mark.letter);
+        row.imp().cursor.set((mark.page_index, mark.word_index));
+
+        let container = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .margin_start(12)
+            .margin_end(12)
+            .margin_top(4)
+            .margin_bottom(4)
+            .hexpand(true)
+            .build();
+        container.add_css_class("toc-mark-row");
+
+        let label = Label::new(Some(&mark.letter.to_string()));
+        label.set_xalign(0.0);
+        label.add_css_class("toc-mark-letter");
+        label.add_css_class("monospace");
+        container.append(&label);
+
+        let label = Label::new(Some(&format!("Page {}", mark.page_index + 1)));
+        label.set_xalign(0.0);
+        label.set_hexpand(true);
+        label.add_css_class("toc-page-index");
+        container.append(&label);
+
+        row.set_child(Some(&container));
+
+        row
+    }
+
+    pub fn letter(&self) -> char {
+        self.imp().letter.get()
+    }
+
+    pub fn cursor(&self) -> WordCursor {
+        let (page_index, word_index) = self.imp().cursor.get();
+        WordCursor::new(page_index, word_index)
+    }
 }
 
 glib::wrapper! {
@@ -192,6 +313,11 @@ impl TocAnnotationRow {
         self.set_margin_top(4);
         self.set_margin_bottom(4);
 
+        imp.color.set_text("●");
+        imp.color.set_valign(gtk::Align::Start);
+        imp.color.add_css_class("toc-annotation-color");
+        self.append(&imp.color);
+
         let sub_container = gtk::Box::builder()
             .orientation(gtk::Orientation::Vertical)
             .spacing(2)
@@ -210,6 +336,11 @@ impl TocAnnotationRow {
         imp.subtitle.add_css_class("toc-subtitle");
         sub_container.append(&imp.subtitle);
 
+        imp.links_box.set_orientation(gtk::Orientation::Horizontal);
+        imp.links_box.set_spacing(4);
+        imp.links_box.add_css_class("toc-annotation-links");
+        sub_container.append(&imp.links_box);
+
         self.append(&sub_container);
 
         imp.page_index.set_xalign(0.0);
@@ -244,6 +375,14 @@ impl TocAnnotationRow {
         let imp = self.imp();
         let data = obj.annotation();
 
+        for color in AnnotationColor::ALL {
+            imp.color
+                .remove_css_class(&format!("annotation-color-{}", color.as_str()));
+        }
+        imp.color
+            .add_css_class(&format!("annotation-color-{}", data.color.as_str()));
+        imp.color.set_tooltip_text(Some(data.color.label()));
+
         imp.title.set_text(&data.selected_text);
         imp.subtitle.set_text(&data.note);
         imp.page_index.set_text(&data.start_page.to_string());
@@ -254,6 +393,10 @@ impl TocAnnotationRow {
         self.imp().annotation_id.get()
     }
 
+    pub fn links_box(&self) -> &Box {
+        &self.imp().links_box
+    }
+
     pub fn edit_button(&self) -> &Button {
         &self.imp().edit_button
     }
@@ -299,6 +442,23 @@ impl TocPanel {
         title_label.add_css_class("heading");
         header_box.append(title_label);
 
+        // Color filter, only shown with the annotations list
+        let filter_names: Vec<&str> = std::iter::once("All colors")
+            .chain(AnnotationColor::ALL.iter().map(|color| color.label()))
+            .collect();
+        imp.color_filter_dropdown
+            .set_model(Some(&gtk::StringList::new(&filter_names)));
+        imp.color_filter_dropdown
+            .set_tooltip_text(Some("Show annotations of one color"));
+        imp.color_filter_dropdown.set_visible(false);
+        header_box.append(&imp.color_filter_dropdown);
+
+        // Tag filter, only shown with the annotations list once some are tagged
+        imp.tag_filter_dropdown
+            .set_tooltip_text(Some("Show annotations with one tag"));
+        imp.tag_filter_dropdown.set_visible(false);
+        header_box.append(&imp.tag_filter_dropdown);
+
         imp.close_button.set_icon_name("window-close-symbolic");
         imp.close_button.add_css_class("flat");
         imp.close_button.add_css_class("toc-close-btn");
@@ -306,6 +466,13 @@ impl TocPanel {
 
         self.append(&header_box);
 
+        imp.search_entry.set_placeholder_text(Some("Filter (/)"));
+        imp.search_entry.set_margin_start(12);
+        imp.search_entry.set_margin_end(12);
+        imp.search_entry.set_margin_bottom(8);
+        imp.search_entry.add_css_class("toc-search");
+        self.append(&imp.search_entry);
+
         let scrolled_window = ScrolledWindow::builder()
             .vexpand(true)
             .hscrollbar_policy(gtk::PolicyType::Never)
@@ -314,11 +481,37 @@ impl TocPanel {
         imp.list_box_chapters
             .set_selection_mode(gtk::SelectionMode::Single);
         imp.list_box_chapters.add_css_class("toc-list");
+        let panel_weak = self.downgrade();
+        imp.list_box_chapters.set_filter_func(move |row| {
+            let Some(panel) = panel_weak.upgrade() else {
+                return true;
+            };
+            // The "No chapters found" placeholder has no title to match
+            let query = panel.imp().search_query.borrow();
+            row.downcast_ref::<TocChapterRow>()
+                .is_none_or(|row| Self::matches_query(&query, &[row.title().as_str()]))
+        });
+
+        imp.list_box_marks
+            .set_selection_mode(gtk::SelectionMode::Single);
+        imp.list_box_marks.add_css_class("toc-list");
+        let panel_weak = self.downgrade();
+        imp.list_box_marks.set_filter_func(move |row| {
+            let Some(panel) = panel_weak.upgrade() else {
+                return true;
+            };
+            let query = panel.imp().search_query.borrow();
+            row.downcast_ref::<TocMarkRow>()
+                .is_none_or(|row| Self::matches_query(&query, &[row.letter().to_string().as_str()]))
+        });
 
         let store = gio::ListStore::new::<AnnotationObject>();
         let _ = self.imp().annotations_store.set(store.clone());
+        let filter = self.create_annotation_filter();
+        let _ = self.imp().annotations_filter.set(filter.clone());
+        let filter_model = gtk::FilterListModel::new(Some(store), Some(filter));
         let sorter = self.create_annotation_sorter();
-        let sort_model = gtk::SortListModel::new(Some(store), Some(sorter));
+        let sort_model = gtk::SortListModel::new(Some(filter_model), Some(sorter));
         let selection_model = gtk::SingleSelection::new(Some(sort_model));
         imp.list_view_annotations.set_model(Some(&selection_model));
 
@@ -331,6 +524,7 @@ impl TocPanel {
         let stack = &self.imp().stack;
         stack.add_named(&imp.list_box_chapters, Some("chapters"));
         stack.add_named(&imp.list_view_annotations, Some("annotations"));
+        stack.add_named(&imp.list_box_marks, Some("marks"));
         // self.imp().list_view_annotations.set_can_focus(false);
 
         scrolled_window.set_child(Some(stack));
@@ -338,6 +532,53 @@ impl TocPanel {
         self.append(&scrolled_window);
         self.add_css_class("toc-panel");
 
+        let panel_weak = self.downgrade();
+        imp.color_filter_dropdown
+            .connect_selected_notify(move |dropdown| {
+                if let Some(panel) = panel_weak.upgrade() {
+                    // Entry 0 is "All colors"
+                    let color = (dropdown.selected() as usize)
+                        .checked_sub(1)
+                        .and_then(|index| AnnotationColor::ALL.get(index).copied());
+                    panel.set_color_filter(color);
+                }
+            });
+
+        let panel_weak = self.downgrade();
+        imp.tag_filter_dropdown
+            .connect_selected_notify(move |dropdown| {
+                if let Some(panel) = panel_weak.upgrade() {
+                    // Entry 0 is "All tags"
+                    let tag = (dropdown.selected() as usize)
+                        .checked_sub(1)
+                        .and_then(|index| panel.imp().tags.borrow().get(index).cloned());
+                    panel.set_tag_filter(tag);
+                }
+            });
+
+        let panel_weak = self.downgrade();
+        imp.search_entry.connect_search_changed(move |entry| {
+            if let Some(panel) = panel_weak.upgrade() {
+                panel.set_search_query(&entry.text());
+            }
+        });
+
+        // Enter moves to the first match, Escape clears the filter
+        let panel_weak = self.downgrade();
+        imp.search_entry.connect_activate(move |_| {
+            if let Some(panel) = panel_weak.upgrade() {
+                panel.select_first();
+            }
+        });
+
+        let panel_weak = self.downgrade();
+        imp.search_entry.connect_stop_search(move |_| {
+            if let Some(panel) = panel_weak.upgrade() {
+                panel.clear_search();
+                panel.select_first();
+            }
+        });
+
         let panel_weak = self.downgrade();
         imp.list_box_chapters.connect_row_activated(move |_, row| {
             if let Some(panel) = panel_weak.upgrade() {
@@ -351,6 +592,15 @@ impl TocPanel {
             }
         });
 
+        let panel_weak = self.downgrade();
+        imp.list_box_marks.connect_row_activated(move |_, row| {
+            if let Some(panel) = panel_weak.upgrade() {
+                if let Some(mark_row) = row.downcast_ref::<TocMarkRow>() {
+                    panel.emit_mark_selected(mark_row);
+                }
+            }
+        });
+
         let panel_weak = self.downgrade();
         imp.list_view_annotations
             .connect_activate(move |list_view, position| {
@@ -399,6 +649,9 @@ impl TocPanel {
                 .unwrap();
 
             row_widget.bind_data(&data_obj);
+            if let Some(panel) = panel_weak.upgrade() {
+                panel.fill_link_chips(&row_widget, data_obj.annotation().id);
+            }
 
             let imp = row_widget.imp();
 
@@ -454,6 +707,188 @@ impl TocPanel {
         factory
     }
 
+    fn create_annotation_filter(&self) -> gtk::CustomFilter {
+        let panel_weak = self.downgrade();
+        gtk::CustomFilter::new(move |obj| {
+            let Some(panel) = panel_weak.upgrade() else {
+                return true;
+            };
+            let Some(item) = obj.downcast_ref::<AnnotationObject>() else {
+                return false;
+            };
+            let annotation = item.annotation();
+            let imp = panel.imp();
+            imp.color_filter
+                .get()
+                .is_none_or(|color| annotation.color == color)
+                && imp
+                    .tag_filter
+                    .borrow()
+                    .as_ref()
+                    .is_none_or(|tag| annotation.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+                && Self::matches_query(
+                    &imp.search_query.borrow(),
+                    &[
+                        annotation.selected_text.as_str(),
+                        annotation.note.as_str(),
+                        annotations::join_tags(&annotation.tags).as_str(),
+                    ],
+                )
+        })
+    }
+
+    /// Whether any of `texts` fuzzy matches `query`; everything matches an empty one
+    fn matches_query(query: &str, texts: &[&str]) -> bool {
+        let query = query.trim();
+        query.is_empty()
+            || texts
+                .iter()
+                .any(|text| recent_files::fuzzy_score(query, text).is_some())
+    }
+
+    /// Only list chapters and annotations matching `query`
+    pub fn set_search_query(&self, query: &str) {
+        let imp = self.imp();
+        if *imp.search_query.borrow() == query {
+            return;
+        }
+        imp.search_query.replace(query.to_string());
+        imp.list_box_chapters.invalidate_filter();
+        imp.list_box_marks.invalidate_filter();
+        if let Some(filter) = imp.annotations_filter.get() {
+            filter.changed(gtk::FilterChange::Different);
+        }
+    }
+
+    /// Put the cursor in the filter entry
+    pub fn focus_search(&self) {
+        self.imp().search_entry.grab_focus();
+    }
+
+    /// Drop the filter, listing every chapter and annotation again
+    pub fn clear_search(&self) {
+        // Clearing the entry notifies `search-changed`, but only after a delay
+        self.imp().search_entry.set_text("");
+        self.set_search_query("");
+    }
+
+    pub fn has_search_query(&self) -> bool {
+        !self.imp().search_query.borrow().is_empty()
+    }
+
+    /// Only list annotations of `color`, or all of them with `None`
+    pub fn set_color_filter(&self, color: Option<AnnotationColor>) {
+        let imp = self.imp();
+        if imp.color_filter.get() == color {
+            return;
+        }
+        imp.color_filter.set(color);
+        if let Some(filter) = imp.annotations_filter.get() {
+            filter.changed(gtk::FilterChange::Different);
+        }
+    }
+
+    /// Show the "see also" links of the annotations. Rows are rebound to update
+    /// their chips.
+    pub fn set_annotation_links(&self, links: Vec<(AnnotationId, AnnotationId)>) {
+        self.imp().annotation_links.replace(links);
+        let store = self.get_store();
+        store.items_changed(0, store.n_items(), store.n_items());
+    }
+
+    /// Listed annotations linked to `id`
+    fn linked_annotations(&self, id: AnnotationId) -> Vec<Annotation> {
+        let linked = annotations::linked_ids(&self.imp().annotation_links.borrow(), id);
+        let mut found: Vec<Annotation> = self
+            .get_store()
+            .iter::<AnnotationObject>()
+            .filter_map(Result::ok)
+            .map(|item| item.annotation())
+            .filter(|ann| linked.contains(&ann.id))
+            .collect();
+        found.sort_by_key(|ann| (ann.start_page, ann.start_word));
+        found
+    }
+
+    /// One flat button per linked annotation, going to it when clicked
+    fn fill_link_chips(&self, row: &TocAnnotationRow, id: AnnotationId) {
+        let links_box = row.links_box();
+        while let Some(child) = links_box.first_child() {
+            links_box.remove(&child);
+        }
+
+        let linked = self.linked_annotations(id);
+        links_box.set_visible(!linked.is_empty());
+        for ann in linked {
+            let chip = Button::builder()
+                .label(format!("↪ {}", ann.short_label()))
+                .tooltip_text(ann.selected_text.as_str())
+                .css_classes(["flat", "toc-annotation-link-chip"])
+                .build();
+            let panel_weak = self.downgrade();
+            chip.connect_clicked(move |_| {
+                if let Some(panel) = panel_weak.upgrade() {
+                    panel.emit_by_name::<()>(
+                        "toc-entry-selected",
+                        &[&(ann.start_page as u32), &Some(ann.get_start_word_cursor())],
+                    );
+                }
+            });
+            links_box.append(&chip);
+        }
+    }
+
+    /// Only list annotations tagged `tag`, or all of them with `None`
+    pub fn set_tag_filter(&self, tag: Option<String>) {
+        let imp = self.imp();
+        if *imp.tag_filter.borrow() == tag {
+            return;
+        }
+        imp.tag_filter.replace(tag);
+        if let Some(filter) = imp.annotations_filter.get() {
+            filter.changed(gtk::FilterChange::Different);
+        }
+    }
+
+    /// Offer the tags of the listed annotations in the tag filter, keeping the
+    /// one picked if it is still used
+    fn refresh_tags(&self) {
+        let imp = self.imp();
+        let listed: Vec<Annotation> = self
+            .get_store()
+            .iter::<AnnotationObject>()
+            .filter_map(Result::ok)
+            .map(|item| item.annotation())
+            .collect();
+        let tags = annotations::all_tags(&listed);
+        if *imp.tags.borrow() == tags {
+            return;
+        }
+
+        let picked = imp.tag_filter.borrow().clone();
+        let names: Vec<&str> = std::iter::once("All tags")
+            .chain(tags.iter().map(String::as_str))
+            .collect();
+        let selected = picked
+            .and_then(|picked| tags.iter().position(|tag| *tag == picked))
+            .map_or(0, |index| index + 1);
+        let model = gtk::StringList::new(&names);
+        // Set before the dropdown announces the new selection
+        imp.tags.replace(tags);
+        imp.tag_filter_dropdown.set_model(Some(&model));
+        imp.tag_filter_dropdown.set_selected(selected as u32);
+        self.update_filter_visibility();
+    }
+
+    /// Show the filters that apply to the list shown
+    fn update_filter_visibility(&self) {
+        let imp = self.imp();
+        let annotations = matches!(self.toc_mode(), TocMode::Annotations);
+        imp.color_filter_dropdown.set_visible(annotations);
+        imp.tag_filter_dropdown
+            .set_visible(annotations && !imp.tags.borrow().is_empty());
+    }
+
     fn create_annotation_sorter(&self) -> CustomSorter {
         CustomSorter::new(move |obj1, obj2| {
             let ann1 = obj1
@@ -492,7 +927,12 @@ impl TocPanel {
                 stack.set_visible_child_name("annotations");
                 title_label.set_text("Annotations");
             }
+            TocMode::Marks => {
+                stack.set_visible_child_name("marks");
+                title_label.set_text("Marks");
+            }
         }
+        self.update_filter_visibility();
     }
 
     pub fn update_list_annotations(&self, new_annotation: Annotation) {
@@ -508,6 +948,7 @@ impl TocPanel {
         }
 
         store.append(&AnnotationObject::new(new_annotation));
+        self.refresh_tags();
     }
 
     pub fn remove_listbox_annotation(&self, id: i64) {
@@ -525,6 +966,7 @@ impl TocPanel {
                 break;
             }
         }
+        self.refresh_tags();
     }
 
     pub fn get_store(&self) -> &gio::ListStore {
@@ -557,6 +999,7 @@ impl TocPanel {
                 store.append(&obj);
             }
         }
+        self.refresh_tags();
 
         // TODO
         // self.actualizar_estado_vacio();
@@ -583,6 +1026,37 @@ impl TocPanel {
         }
     }
 
+    pub fn populate_marks(&self, marks: &[Mark]) {
+        let imp = self.imp();
+
+        while let Some(row) = imp.list_box_marks.first_child() {
+            imp.list_box_marks.remove(&row);
+        }
+
+        if marks.is_empty() {
+            let label = Label::new(Some("No marks, set one with m{a-z}"));
+            label.set_margin_start(12);
+            label.set_margin_end(12);
+            label.set_margin_top(12);
+            label.set_margin_bottom(12);
+            label.set_xalign(0.0);
+            label.set_opacity(0.6);
+            imp.list_box_marks.append(&label);
+        } else {
+            for mark in marks {
+                imp.list_box_marks.append(&TocMarkRow::new(mark));
+            }
+        }
+    }
+
+    fn emit_mark_selected(&self, row: &TocMarkRow) {
+        let cursor = Some(row.cursor());
+        self.emit_by_name::<()>(
+            "toc-entry-selected",
+            &[&(row.cursor().page_index as u32), &cursor],
+        );
+    }
+
     fn flatten_chapters_entries(&self, entries: &[BookmarkEntry], initial_depth: usize) {
         for entry in entries {
             self.add_chapter_row(entry, initial_depth);
@@ -599,6 +1073,27 @@ impl TocPanel {
         imp.list_box_chapters.append(&entry_row);
     }
 
+    /// Mark each chapter as unread, partially read or read from the pages read so far
+    pub fn set_chapter_progress(&self, read_pages: &HashSet<usize>, page_count: usize) {
+        let rows: Vec<TocChapterRow> = self
+            .imp()
+            .list_box_chapters
+            .observe_children()
+            .iter::<glib::Object>()
+            .filter_map(|child| child.ok().and_downcast::<TocChapterRow>())
+            .collect();
+
+        let chapters: Vec<(usize, usize)> = rows
+            .iter()
+            .map(|row| (row.page_index() as usize, row.depth()))
+            .collect();
+        let ranges = bookmarks::chapter_ranges(&chapters, page_count);
+
+        for (row, pages) in rows.iter().zip(ranges) {
+            row.set_progress(ChapterProgress::for_pages(pages, read_pages));
+        }
+    }
+
     pub fn select_current_chapter(&self, page: u16) {
         let imp = self.imp();
         let children = imp.list_box_chapters.observe_children();
@@ -610,6 +1105,9 @@ impl TocPanel {
             match item {
                 Ok(child) => {
                     if let Some(entry_row) = child.downcast_ref::<TocChapterRow>() {
+                        if !entry_row.is_child_visible() {
+                            continue;
+                        }
                         let entry_page = entry_row.page_index();
                         if entry_page <= page && entry_page >= best_page_index {
                             best_match = Some(child.clone());
@@ -646,13 +1144,14 @@ impl TocPanel {
                     imp.list_view_annotations.grab_focus();
                 }
             }
-            TocMode::Chapters => {
-                assert!(imp.list_box_chapters.is_visible());
-                if let Some(first_child) = imp.list_box_chapters.first_child() {
-                    if let Some(list_row) = first_child.downcast_ref::<ListBoxRow>() {
-                        imp.list_box_chapters.select_row(Some(list_row));
-                        imp.list_box_chapters.grab_focus();
-                    }
+            TocMode::Chapters | TocMode::Marks => {
+                let list_box = self.list_box();
+                assert!(list_box.is_visible());
+                if let Some(list_row) =
+                    Self::shown_row(list_box.first_child(), |row| row.next_sibling())
+                {
+                    list_box.select_row(Some(&list_row));
+                    list_box.grab_focus();
                 }
             }
         };
@@ -684,13 +1183,14 @@ impl TocPanel {
                     }
                 }
             }
-            TocMode::Chapters => {
-                assert!(imp.list_box_chapters.is_visible());
-                if let Some(last_child) = imp.list_box_chapters.last_child() {
-                    if let Some(list_row) = last_child.downcast_ref::<ListBoxRow>() {
-                        imp.list_box_chapters.select_row(Some(list_row));
-                        imp.list_box_chapters.grab_focus();
-                    }
+            TocMode::Chapters | TocMode::Marks => {
+                let list_box = self.list_box();
+                assert!(list_box.is_visible());
+                if let Some(list_row) =
+                    Self::shown_row(list_box.last_child(), |row| row.prev_sibling())
+                {
+                    list_box.select_row(Some(&list_row));
+                    list_box.grab_focus();
                 }
             }
         }
@@ -704,9 +1204,9 @@ impl TocPanel {
                 assert!(imp.list_view_annotations.is_visible());
                 self.select_next_annotation()
             }
-            TocMode::Chapters => {
-                assert!(imp.list_box_chapters.is_visible());
-                self.select_next_chapter()
+            TocMode::Chapters | TocMode::Marks => {
+                assert!(self.list_box().is_visible());
+                self.select_next_row()
             }
         };
     }
@@ -762,11 +1262,22 @@ impl TocPanel {
         false
     }
 
-    fn select_next_chapter(&self) -> bool {
+    /// The list box of the chapters or the marks, whichever is shown
+    fn list_box(&self) -> &ListBox {
         let imp = self.imp();
-        if let Some(current) = imp.list_box_chapters.selected_row() {
-            if let Some(prev) = current.next_sibling().and_downcast_ref::<gtk::ListBoxRow>() {
-                imp.list_box_chapters.select_row(Some(prev));
+        match self.toc_mode() {
+            TocMode::Marks => &imp.list_box_marks,
+            _ => &imp.list_box_chapters,
+        }
+    }
+
+    fn select_next_row(&self) -> bool {
+        let list_box = self.list_box();
+        if let Some(current) = list_box.selected_row() {
+            if let Some(prev) =
+                Self::shown_row(current.next_sibling(), |row| row.next_sibling()).as_ref()
+            {
+                list_box.select_row(Some(prev));
                 prev.grab_focus();
                 return true;
             }
@@ -774,11 +1285,28 @@ impl TocPanel {
         false
     }
 
-    fn select_prev_chapter(&self) -> bool {
-        let imp = self.imp();
-        if let Some(current) = imp.list_box_chapters.selected_row() {
-            if let Some(prev) = current.prev_sibling().and_downcast_ref::<gtk::ListBoxRow>() {
-                imp.list_box_chapters.select_row(Some(prev));
+    /// The first row from `start` on, stepping with `next`, not hidden by the filter
+    fn shown_row(
+        start: Option<gtk::Widget>,
+        next: impl Fn(&gtk::Widget) -> Option<gtk::Widget>,
+    ) -> Option<ListBoxRow> {
+        let mut widget = start;
+        while let Some(current) = widget {
+            if current.is_child_visible() {
+                return current.downcast::<ListBoxRow>().ok();
+            }
+            widget = next(&current);
+        }
+        None
+    }
+
+    fn select_prev_row(&self) -> bool {
+        let list_box = self.list_box();
+        if let Some(current) = list_box.selected_row() {
+            if let Some(prev) =
+                Self::shown_row(current.prev_sibling(), |row| row.prev_sibling()).as_ref()
+            {
+                list_box.select_row(Some(prev));
                 prev.grab_focus();
                 return true;
             }
@@ -794,9 +1322,9 @@ impl TocPanel {
                 assert!(imp.list_view_annotations.is_visible());
                 self.select_prev_annotation()
             }
-            TocMode::Chapters => {
-                assert!(imp.list_box_chapters.is_visible());
-                self.select_prev_chapter()
+            TocMode::Chapters | TocMode::Marks => {
+                assert!(self.list_box().is_visible());
+                self.select_prev_row()
             }
         };
     }
@@ -849,6 +1377,14 @@ impl TocPanel {
                     }
                 }
             }
+            TocMode::Marks => {
+                if let Some(row) = imp.list_box_marks.selected_row() {
+                    if let Some(mark_row) = row.downcast_ref::<TocMarkRow>() {
+                        self.emit_mark_selected(mark_row);
+                        self.set_visible(false);
+                    }
+                }
+            }
         };
     }
 
@@ -881,7 +1417,11 @@ impl TocPanel {
         while let Some(row) = imp.list_box_chapters.first_child() {
             imp.list_box_chapters.remove(&row);
         }
+        while let Some(row) = imp.list_box_marks.first_child() {
+            imp.list_box_marks.remove(&row);
+        }
         self.get_store().remove_all();
+        self.refresh_tags();
     }
 }
 
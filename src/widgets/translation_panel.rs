@@ -1,13 +1,18 @@
+use gtk::gio;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::{Box, Button, Label, Orientation, Separator, Spinner};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
 
-use crate::services::translation;
+use crate::services::translation::{self, TranslatorConfig};
 
 const MIN_PANEL_HEIGHT: i32 = 80;
 const DEFAULT_PANEL_HEIGHT: i32 = 100;
+/// Quiet time after the last request before translating, so a selection being
+/// dragged isn't sent to the backend at every change
+const TRANSLATE_DELAY: Duration = Duration::from_millis(300);
 
 mod imp {
     use super::*;
@@ -18,6 +23,11 @@ mod imp {
         pub close_button: Button,
         pub resize_handle: Separator,
         pub panel_height: RefCell<i32>,
+        /// Counts translations so only the result of the latest one is shown
+        pub request: Cell<u64>,
+        /// Translation waiting for the requests to settle
+        pub pending: RefCell<Option<glib::SourceId>>,
+        pub translator_config: RefCell<TranslatorConfig>,
     }
 
     impl Default for TranslationPanel {
@@ -28,6 +38,9 @@ mod imp {
                 close_button: Button::new(),
                 resize_handle: Separator::new(Orientation::Horizontal),
                 panel_height: RefCell::new(DEFAULT_PANEL_HEIGHT),
+                request: Cell::new(0),
+                pending: RefCell::new(None),
+                translator_config: RefCell::new(TranslatorConfig::default()),
             }
         }
     }
@@ -141,33 +154,77 @@ impl TranslationPanel {
         self.set_loading(false);
     }
 
+    /// Translate `text` once no other request came for a moment. Texts
+    /// translated recently are shown at once.
     pub fn translate(&self, text: String) {
-        self.set_loading(true);
-
-        let (sender, receiver) = std::sync::mpsc::channel::<Result<String, String>>();
+        let imp = self.imp();
+        let request = imp.request.get() + 1;
+        imp.request.set(request);
+        self.cancel_pending();
+        let config = self.translator_config();
+
+        if let Some(translated) = translation::cached_translation(&text, &config) {
+            self.set_translation(&translated);
+            return;
+        }
 
-        std::thread::spawn(move || {
-            let result = translation::translate(&text).map_err(|e| e.to_string());
-            let _ = sender.send(result);
+        self.set_loading(true);
+        let panel_weak = self.downgrade();
+        let source = glib::timeout_add_local_once(TRANSLATE_DELAY, move || {
+            if let Some(panel) = panel_weak.upgrade() {
+                panel.imp().pending.take();
+                panel.start_translation(text, config, request);
+            }
         });
+        imp.pending.replace(Some(source));
+    }
 
+    fn start_translation(&self, text: String, config: TranslatorConfig, request: u64) {
+        // Resumed on the main loop as soon as the translation thread returns
         let panel_weak = self.downgrade();
-        glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
-            if let Ok(result) = receiver.try_recv() {
-                if let Some(panel) = panel_weak.upgrade() {
-                    match result {
-                        Ok(translated) => panel.set_translation(&translated),
-                        Err(error) => panel.set_error(&error),
-                    }
-                }
-                return glib::ControlFlow::Break;
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || {
+                translation::translate(&text, &config).map_err(|e| e.to_string())
+            })
+            .await
+            .unwrap_or_else(|_| Err("Translation failed".to_string()));
+
+            // Dropped when a newer translation was started meanwhile
+            let Some(panel) = panel_weak
+                .upgrade()
+                .filter(|panel| panel.imp().request.get() == request)
+            else {
+                return;
+            };
+            match result {
+                Ok(translated) => panel.set_translation(&translated),
+                Err(error) => panel.set_error(&error),
             }
-            glib::ControlFlow::Continue
         });
     }
 
+    /// Drop the translation waiting to start, if any
+    fn cancel_pending(&self) {
+        if let Some(source) = self.imp().pending.take() {
+            source.remove();
+        }
+    }
+
+    /// Backend and credentials used by the next translations
+    pub fn set_translator_config(&self, config: TranslatorConfig) {
+        self.imp().translator_config.replace(config);
+    }
+
+    pub fn translator_config(&self) -> TranslatorConfig {
+        self.imp().translator_config.borrow().clone()
+    }
+
     pub fn clear(&self) {
-        self.imp().label.set_text("");
+        // Results of a translation still running are not shown anymore
+        let imp = self.imp();
+        imp.request.set(imp.request.get() + 1);
+        self.cancel_pending();
+        imp.label.set_text("");
         self.set_loading(false);
     }
 
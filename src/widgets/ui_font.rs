@@ -0,0 +1,32 @@
+/// Font used for reading text in popovers and panels (definitions, translations,
+/// annotation notes), independent of the PDF zoom
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UiFont {
+    /// Font family, or None for the theme font
+    pub family: Option<String>,
+    /// Size in points, or None for the stylesheet default
+    pub size: Option<f64>,
+}
+
+/// Text elements the UI font applies to
+const UI_FONT_SELECTORS: &str = ".definition-text, .translation-text, .annotation-input, \
+                                 .annotation-selected-text, .annotation-for-label";
+
+impl UiFont {
+    /// CSS overriding the stylesheet for the popover and panel text
+    pub fn css(&self) -> String {
+        let mut rules = String::new();
+        if let Some(family) = &self.family {
+            let escaped = family.replace('\\', "\\\\").replace('"', "\\\"");
+            rules.push_str(&format!("font-family: \"{}\";", escaped));
+        }
+        if let Some(size) = self.size {
+            rules.push_str(&format!("font-size: {}pt;", size));
+        }
+
+        if rules.is_empty() {
+            return String::new();
+        }
+        format!("{} {{ {} }}", UI_FONT_SELECTORS, rules)
+    }
+}